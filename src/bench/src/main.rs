@@ -0,0 +1,177 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! pgbench-like workload against the embedded sql engine or, when server address
+//! is given, against a running node over the wire
+//!
+//! usage: `cargo run --release -p bench -- [scale] [scans] [<server address>]`
+//!
+//! `bench` schema of the server is dropped before the workload is run.
+
+mod wire;
+
+use sql_engine::Handler;
+use std::{
+    env,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use storage::{backend::SledBackendStorage, frontend::FrontendStorage};
+use wire::Connection;
+
+const BRANCHES: usize = 1;
+const TELLERS: usize = 10;
+const ACCOUNTS: usize = 100_000;
+const ROWS_PER_INSERT: usize = 100;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let scale = args.next().map(|arg| arg.parse().expect("scale factor")).unwrap_or(1);
+    let scans = args
+        .next()
+        .map(|arg| arg.parse().expect("number of scans"))
+        .unwrap_or(10);
+
+    let mut workload = match args.next() {
+        Some(server) => Workload::Wire(Connection::open(&server).expect("connected to server")),
+        None => {
+            let storage = Arc::new(RwLock::new(FrontendStorage::default().expect("no system errors")));
+            Workload::Embedded(Box::new(Handler::new(storage)))
+        }
+    };
+
+    workload.execute_all(
+        "create tables",
+        vec![
+            "drop schema if exists bench;".to_owned(),
+            "create schema bench;".to_owned(),
+            "create table bench.pgbench_branches (bid integer, bbalance integer, filler char(88));".to_owned(),
+            "create table bench.pgbench_tellers (tid integer, bid integer, tbalance integer, filler char(84));"
+                .to_owned(),
+            "create table bench.pgbench_accounts (aid integer, bid integer, abalance integer, filler char(84));"
+                .to_owned(),
+        ],
+    );
+    workload.execute_all(
+        "insert branches",
+        inserts("bench.pgbench_branches", BRANCHES * scale, |id| {
+            format!("({}, 0, '')", id + 1)
+        }),
+    );
+    workload.execute_all(
+        "insert tellers",
+        inserts("bench.pgbench_tellers", TELLERS * scale, |id| {
+            format!("({}, {}, 0, '')", id + 1, id / TELLERS + 1)
+        }),
+    );
+    workload.execute_all(
+        "insert accounts",
+        inserts("bench.pgbench_accounts", ACCOUNTS * scale, |id| {
+            format!("({}, {}, 0, '')", id + 1, id / ACCOUNTS + 1)
+        }),
+    );
+    workload.execute_all(
+        "scan tellers",
+        vec!["select * from bench.pgbench_tellers;".to_owned(); scans],
+    );
+    workload.execute_all(
+        "scan accounts",
+        vec!["select * from bench.pgbench_accounts;".to_owned(); scans],
+    );
+}
+
+fn inserts<F: Fn(usize) -> String>(table_name: &str, rows: usize, row: F) -> Vec<String> {
+    (0..rows)
+        .collect::<Vec<usize>>()
+        .chunks(ROWS_PER_INSERT)
+        .map(|ids| {
+            format!(
+                "insert into {} values {};",
+                table_name,
+                ids.iter().map(|id| row(*id)).collect::<Vec<String>>().join(", ")
+            )
+        })
+        .collect()
+}
+
+enum Workload {
+    Embedded(Box<Handler<SledBackendStorage>>),
+    Wire(Connection),
+}
+
+impl Workload {
+    fn execute(&mut self, query: &str) -> Result<(), String> {
+        match self {
+            Workload::Embedded(handler) => handler
+                .execute(query)
+                .expect("no system errors")
+                .map(|_event| ())
+                .map_err(|error| error.to_string()),
+            // records are read along with the rest of the response
+            Workload::Wire(connection) => connection.query(query).expect("no io errors"),
+        }
+    }
+
+    fn execute_all(&mut self, phase: &str, queries: Vec<String>) {
+        let mut latencies = Vec::with_capacity(queries.len());
+        let started = Instant::now();
+        for query in queries {
+            let start = Instant::now();
+            if let Err(error) = self.execute(query.as_str()) {
+                panic!("{} failed with {}", query, error);
+            }
+            latencies.push(start.elapsed());
+        }
+        Report::new(phase, started.elapsed(), latencies).print();
+    }
+}
+
+struct Report<'p> {
+    phase: &'p str,
+    total: Duration,
+    latencies: Vec<Duration>,
+}
+
+impl<'p> Report<'p> {
+    fn new(phase: &'p str, total: Duration, mut latencies: Vec<Duration>) -> Self {
+        latencies.sort();
+        Self {
+            phase,
+            total,
+            latencies,
+        }
+    }
+
+    fn percentile(&self, percent: usize) -> Duration {
+        if self.latencies.is_empty() {
+            Duration::default()
+        } else {
+            self.latencies[(self.latencies.len() - 1) * percent / 100]
+        }
+    }
+
+    fn print(&self) {
+        let throughput = self.latencies.len() as f64 / self.total.as_secs_f64();
+        println!(
+            "{:<16} statements: {:>6} total: {:>10.3?} tps: {:>10.1} p50: {:>10.3?} p95: {:>10.3?} p99: {:>10.3?}",
+            self.phase,
+            self.latencies.len(),
+            self.total,
+            throughput,
+            self.percentile(50),
+            self.percentile(95),
+            self.percentile(99)
+        );
+    }
+}
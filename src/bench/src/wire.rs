@@ -0,0 +1,136 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! blocking client of postgres wire protocol that runs simple queries
+
+use std::{
+    env,
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+const PROTOCOL_VERSION: i32 = 196_608;
+const AUTHENTICATION: u8 = b'R';
+const ERROR_RESPONSE: u8 = b'E';
+const READY_FOR_QUERY: u8 = b'Z';
+const AUTHENTICATION_OK: i32 = 0;
+const CLEARTEXT_PASSWORD: i32 = 3;
+const MESSAGE_FIELD: u8 = b'M';
+
+pub struct Connection {
+    stream: TcpStream,
+}
+
+impl Connection {
+    /// user is taken from `PGUSER` and cleartext password from `PGPASSWORD`
+    /// environment variables
+    pub fn open(server: &str) -> io::Result<Connection> {
+        let user = env::var("PGUSER").unwrap_or_else(|_| "postgres".to_owned());
+        let mut connection = Connection {
+            stream: TcpStream::connect(server)?,
+        };
+        connection.stream.set_nodelay(true)?;
+        let mut body = PROTOCOL_VERSION.to_be_bytes().to_vec();
+        cstring(&mut body, "user");
+        cstring(&mut body, &user);
+        cstring(&mut body, "database");
+        cstring(&mut body, &user);
+        body.push(0);
+        connection.stream.write_all(&(body.len() as i32 + 4).to_be_bytes())?;
+        connection.stream.write_all(&body)?;
+
+        loop {
+            let (tag, body) = connection.receive()?;
+            match tag {
+                AUTHENTICATION => match i32::from_be_bytes([body[0], body[1], body[2], body[3]]) {
+                    AUTHENTICATION_OK => {}
+                    CLEARTEXT_PASSWORD => {
+                        let mut password = vec![];
+                        cstring(&mut password, &env::var("PGPASSWORD").unwrap_or_default());
+                        connection.send(b'p', &password)?;
+                    }
+                    method => {
+                        return Err(io::Error::other(format!(
+                            "authentication method {} is not supported",
+                            method
+                        )))
+                    }
+                },
+                ERROR_RESPONSE => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionRefused,
+                        format!("server rejected connection: {}", message(&body)),
+                    ))
+                }
+                READY_FOR_QUERY => return Ok(connection),
+                _ => {}
+            }
+        }
+    }
+
+    /// returns message of the error that server responded with
+    pub fn query(&mut self, sql: &str) -> io::Result<Result<(), String>> {
+        let mut body = vec![];
+        cstring(&mut body, sql);
+        self.send(b'Q', &body)?;
+        let mut result = Ok(());
+        loop {
+            match self.receive()? {
+                (ERROR_RESPONSE, body) => result = Err(message(&body)),
+                (READY_FOR_QUERY, _) => return Ok(result),
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&mut self, tag: u8, body: &[u8]) -> io::Result<()> {
+        let mut message = Vec::with_capacity(body.len() + 5);
+        message.push(tag);
+        message.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+        message.extend_from_slice(body);
+        self.stream.write_all(&message)
+    }
+
+    fn receive(&mut self) -> io::Result<(u8, Vec<u8>)> {
+        let mut header = [0; 5];
+        self.stream.read_exact(&mut header)?;
+        let len = i32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        let mut body = vec![0; (len as usize).saturating_sub(4)];
+        self.stream.read_exact(&mut body)?;
+        Ok((header[0], body))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.send(b'X', &[]);
+    }
+}
+
+fn cstring(body: &mut Vec<u8>, value: &str) {
+    body.extend_from_slice(value.as_bytes());
+    body.push(0);
+}
+
+/// fields of error response are a type byte followed by a null terminated value
+fn message(body: &[u8]) -> String {
+    for field in body.split(|byte| *byte == 0) {
+        match field.split_first() {
+            Some((&MESSAGE_FIELD, value)) => return String::from_utf8_lossy(value).into_owned(),
+            Some(_) => {}
+            None => break,
+        }
+    }
+    String::from_utf8_lossy(body).into_owned()
+}
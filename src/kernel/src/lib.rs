@@ -12,6 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+pub mod clock;
+
 pub type SystemResult<T> = std::result::Result<T, SystemError>;
 
 #[derive(Debug)]
@@ -49,6 +56,31 @@ impl SystemError {
             kind: SystemErrorKind::Io(io_error),
         }
     }
+
+    /// where the error was created
+    pub fn backtrace(&self) -> &backtrace::Backtrace {
+        &self.backtrace
+    }
+
+    /// where the failure that caused the error happened, if it is known
+    pub fn cause(&self) -> Option<&backtrace::Backtrace> {
+        self.cause.as_ref()
+    }
+}
+
+impl Display for SystemError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for SystemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            SystemErrorKind::Io(io_error) => Some(io_error),
+            SystemErrorKind::Unrecoverable => None,
+        }
+    }
 }
 
 impl PartialEq for SystemError {
@@ -65,10 +97,10 @@ pub enum SystemErrorKind {
 
 impl PartialEq for SystemErrorKind {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (SystemErrorKind::Io(_), SystemErrorKind::Io(_)) => true,
-            (SystemErrorKind::Unrecoverable, SystemErrorKind::Unrecoverable) => true,
-            _ => false,
-        }
+        matches!(
+            (self, other),
+            (SystemErrorKind::Io(_), SystemErrorKind::Io(_))
+                | (SystemErrorKind::Unrecoverable, SystemErrorKind::Unrecoverable)
+        )
     }
 }
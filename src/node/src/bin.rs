@@ -17,5 +17,57 @@ extern crate simple_logger;
 
 fn main() {
     simple_logger::init_by_env();
-    node::node::Node::default().start();
+    let node = node::node::Node::default();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let node = match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["migrate", migrations] => return migrate(node, migrations),
+        ["migrate", migrations, data_directory] => {
+            return migrate(node.with_data_directory(*data_directory), migrations)
+        }
+        ["restore", dump] => return restore(node, dump),
+        ["restore", dump, data_directory] => return restore(node.with_data_directory(*data_directory), dump),
+        [] => node,
+        ["--capture", capture_file] => node.with_workload_capture(*capture_file),
+        [data_directory, "--capture", capture_file] => node
+            .with_data_directory(*data_directory)
+            .with_workload_capture(*capture_file),
+        [data_directory] => node.with_data_directory(*data_directory),
+        _ => {
+            eprintln!("usage: database [<data directory>] [--capture <capture file>]");
+            eprintln!("       database migrate <migrations directory> [<data directory>]");
+            eprintln!("       database restore <dump file> [<data directory>]");
+            std::process::exit(2)
+        }
+    };
+    node.start()
+}
+
+fn migrate(node: node::node::Node, migrations: &str) {
+    match node.migrate(migrations) {
+        Ok(applied) if applied.is_empty() => println!("all migrations are already applied"),
+        Ok(applied) => {
+            for migration in applied {
+                println!("{} is applied", migration);
+            }
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1)
+        }
+    }
+}
+
+fn restore(node: node::node::Node, dump: &str) {
+    match node.restore(dump) {
+        Ok(restored) => {
+            for notice in restored.notices {
+                eprintln!("NOTICE: {}", notice);
+            }
+            println!("{} statements are restored", restored.executed);
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1)
+        }
+    }
 }
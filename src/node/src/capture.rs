@@ -0,0 +1,453 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! capture file is a text file with an event per line:
+//! `<session id> <microseconds since capture start> <microseconds it took> <event> [<field> ...]`
+//! where parts are separated by tabs. Tabs, line breaks and backslashes of fields
+//! are escaped with a backslash. Parameters of binds are written as hex of their bytes
+//! as they could be in binary format, null values of parameters are written as `\N`.
+//! Empty lines and lines that start with `#` are ignored.
+
+use protocol::Command;
+use std::{
+    fs::File,
+    io::{self, BufRead, LineWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const NULL: &str = "\\N";
+
+/// What a client did in a session, only commands that change what is executed
+/// on the server are captured
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Connect {
+        user: String,
+        database: String,
+    },
+    Query(String),
+    Parse {
+        statement_name: String,
+        sql: String,
+        param_types: Vec<i32>,
+    },
+    Bind {
+        portal_name: String,
+        statement_name: String,
+        param_formats: Vec<i16>,
+        params: Vec<Option<Vec<u8>>>,
+        result_formats: Vec<i16>,
+    },
+    Execute {
+        portal_name: String,
+        max_rows: i32,
+    },
+    CloseStatement(String),
+    ClosePortal(String),
+    Sync,
+    Disconnect,
+}
+
+impl Event {
+    /// descriptions and flushes are not captured as they give nothing to replay,
+    /// sessions are ended by `Disconnect` however a connection is closed
+    pub fn from_command(command: &Command) -> Option<Event> {
+        match command {
+            Command::Query(sql) => Some(Event::Query(sql.clone())),
+            Command::Parse {
+                statement_name,
+                sql,
+                param_types,
+            } => Some(Event::Parse {
+                statement_name: statement_name.clone(),
+                sql: sql.clone(),
+                param_types: param_types.clone(),
+            }),
+            Command::Bind {
+                portal_name,
+                statement_name,
+                param_formats,
+                params,
+                result_formats,
+            } => Some(Event::Bind {
+                portal_name: portal_name.clone(),
+                statement_name: statement_name.clone(),
+                param_formats: param_formats.clone(),
+                params: params.clone(),
+                result_formats: result_formats.clone(),
+            }),
+            Command::Execute { portal_name, max_rows } => Some(Event::Execute {
+                portal_name: portal_name.clone(),
+                max_rows: *max_rows,
+            }),
+            Command::CloseStatement(statement_name) => Some(Event::CloseStatement(statement_name.clone())),
+            Command::ClosePortal(portal_name) => Some(Event::ClosePortal(portal_name.clone())),
+            Command::Sync => Some(Event::Sync),
+            Command::DescribeStatement(_) | Command::DescribePortal(_) | Command::Flush | Command::Terminate => None,
+        }
+    }
+
+    fn fields(&self) -> Vec<String> {
+        match self {
+            Event::Connect { user, database } => vec!["connect".to_owned(), escape(user), escape(database)],
+            Event::Query(sql) => vec!["query".to_owned(), escape(sql)],
+            Event::Parse {
+                statement_name,
+                sql,
+                param_types,
+            } => vec![
+                "parse".to_owned(),
+                escape(statement_name),
+                escape(sql),
+                join(param_types),
+            ],
+            Event::Bind {
+                portal_name,
+                statement_name,
+                param_formats,
+                params,
+                result_formats,
+            } => {
+                let mut fields = vec![
+                    "bind".to_owned(),
+                    escape(portal_name),
+                    escape(statement_name),
+                    join(param_formats),
+                    join(result_formats),
+                ];
+                fields.extend(params.iter().map(|param| match param {
+                    Some(value) => to_hex(value),
+                    None => NULL.to_owned(),
+                }));
+                fields
+            }
+            Event::Execute { portal_name, max_rows } => {
+                vec!["execute".to_owned(), escape(portal_name), max_rows.to_string()]
+            }
+            Event::CloseStatement(statement_name) => vec!["close_statement".to_owned(), escape(statement_name)],
+            Event::ClosePortal(portal_name) => vec!["close_portal".to_owned(), escape(portal_name)],
+            Event::Sync => vec!["sync".to_owned()],
+            Event::Disconnect => vec!["disconnect".to_owned()],
+        }
+    }
+
+    fn parse(fields: &[&str]) -> Option<Event> {
+        let event = match fields {
+            ["connect", user, database] => Event::Connect {
+                user: unescape(user)?,
+                database: unescape(database)?,
+            },
+            ["query", sql] => Event::Query(unescape(sql)?),
+            ["parse", statement_name, sql, param_types] => Event::Parse {
+                statement_name: unescape(statement_name)?,
+                sql: unescape(sql)?,
+                param_types: split(param_types)?,
+            },
+            ["bind", portal_name, statement_name, param_formats, result_formats, params @ ..] => Event::Bind {
+                portal_name: unescape(portal_name)?,
+                statement_name: unescape(statement_name)?,
+                param_formats: split(param_formats)?,
+                params: params
+                    .iter()
+                    .map(|param| {
+                        if *param == NULL {
+                            Some(None)
+                        } else {
+                            from_hex(param).map(Some)
+                        }
+                    })
+                    .collect::<Option<_>>()?,
+                result_formats: split(result_formats)?,
+            },
+            ["execute", portal_name, max_rows] => Event::Execute {
+                portal_name: unescape(portal_name)?,
+                max_rows: max_rows.parse().ok()?,
+            },
+            ["close_statement", statement_name] => Event::CloseStatement(unescape(statement_name)?),
+            ["close_portal", portal_name] => Event::ClosePortal(unescape(portal_name)?),
+            ["sync"] => Event::Sync,
+            ["disconnect"] => Event::Disconnect,
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+/// Event of a session along with the time it happened at and took
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedEvent {
+    pub session_id: u64,
+    pub elapsed: Duration,
+    pub duration: Duration,
+    pub event: Event,
+}
+
+/// Writes events of all sessions of a node into a single file as they happen
+#[derive(Clone)]
+pub struct Capture {
+    started: Instant,
+    output: Arc<Mutex<LineWriter<File>>>,
+}
+
+impl Capture {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Capture> {
+        let file = File::create(path)?;
+        Ok(Capture {
+            started: Instant::now(),
+            output: Arc::new(Mutex::new(LineWriter::new(file))),
+        })
+    }
+
+    /// event that started at the moment and ends now, failures to write are
+    /// logged as workload should not stop because of them
+    pub fn record(&self, session_id: u64, started: Instant, event: Event) {
+        let captured = CapturedEvent {
+            session_id,
+            elapsed: started.saturating_duration_since(self.started),
+            duration: started.elapsed(),
+            event,
+        };
+        let mut output = self.output.lock().unwrap();
+        if let Err(error) = write_event(&mut *output, &captured) {
+            log::warn!("event is not captured due to {:?}", error);
+        }
+    }
+}
+
+pub fn write_event<W: Write>(output: &mut W, captured: &CapturedEvent) -> io::Result<()> {
+    let mut fields = vec![
+        captured.session_id.to_string(),
+        captured.elapsed.as_micros().to_string(),
+        captured.duration.as_micros().to_string(),
+    ];
+    fields.extend(captured.event.fields());
+    writeln!(output, "{}", fields.join("\t"))
+}
+
+pub fn read_events<R: BufRead>(input: R) -> io::Result<Vec<CapturedEvent>> {
+    let mut events = vec![];
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_event(&line) {
+            Some(event) => events.push(event),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed event at line {}: {}", index + 1, line),
+                ))
+            }
+        }
+    }
+    Ok(events)
+}
+
+fn parse_event(line: &str) -> Option<CapturedEvent> {
+    let fields = line.split('\t').collect::<Vec<&str>>();
+    match fields.as_slice() {
+        [session_id, elapsed, duration, event @ ..] => Some(CapturedEvent {
+            session_id: session_id.parse().ok()?,
+            elapsed: Duration::from_micros(elapsed.parse().ok()?),
+            duration: Duration::from_micros(duration.parse().ok()?),
+            event: Event::parse(event)?,
+        }),
+        _ => None,
+    }
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values.iter().map(T::to_string).collect::<Vec<String>>().join(",")
+}
+
+fn split<T: std::str::FromStr>(values: &str) -> Option<Vec<T>> {
+    values
+        .split(',')
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().ok())
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) || !value.is_ascii() {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&value[index..index + 2], 16).ok())
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(value: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            't' => unescaped.push('\t'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captured(session_id: u64, elapsed: u64, event: Event) -> CapturedEvent {
+        CapturedEvent {
+            session_id,
+            elapsed: Duration::from_micros(elapsed),
+            duration: Duration::from_micros(5),
+            event,
+        }
+    }
+
+    #[test]
+    fn write_and_read_events() {
+        let events = vec![
+            captured(
+                0,
+                0,
+                Event::Connect {
+                    user: "postgres".to_owned(),
+                    database: "postgres".to_owned(),
+                },
+            ),
+            captured(0, 10, Event::Query("select 'a\tb\\c';\nselect 1;".to_owned())),
+            captured(
+                1,
+                20,
+                Event::Parse {
+                    statement_name: "".to_owned(),
+                    sql: "select $1, $2;".to_owned(),
+                    param_types: vec![23, 0],
+                },
+            ),
+            captured(
+                1,
+                30,
+                Event::Bind {
+                    portal_name: "".to_owned(),
+                    statement_name: "".to_owned(),
+                    param_formats: vec![],
+                    params: vec![Some(b"1".to_vec()), None, Some(vec![])],
+                    result_formats: vec![],
+                },
+            ),
+            captured(
+                1,
+                40,
+                Event::Execute {
+                    portal_name: "".to_owned(),
+                    max_rows: 0,
+                },
+            ),
+            captured(1, 50, Event::Sync),
+            captured(0, 60, Event::Disconnect),
+        ];
+
+        let mut output = vec![];
+        for event in events.iter() {
+            write_event(&mut output, event).expect("written");
+        }
+
+        assert_eq!(
+            String::from_utf8(output.clone()).expect("utf8").lines().nth(1),
+            Some("0\t10\t5\tquery\tselect 'a\\tb\\\\c';\\nselect 1;")
+        );
+        assert_eq!(read_events(output.as_slice()).expect("read"), events);
+    }
+
+    #[test]
+    fn binary_params_round_trip() {
+        let bind = Command::Bind {
+            portal_name: "portal".to_owned(),
+            statement_name: "statement".to_owned(),
+            param_formats: vec![1, 0],
+            params: vec![Some(vec![0, 0, 0, 1]), Some(vec![b'\t', b'\n', 0xff]), None],
+            result_formats: vec![1],
+        };
+        let event = Event::from_command(&bind).expect("captured");
+
+        let mut output = vec![];
+        write_event(&mut output, &captured(0, 10, event.clone())).expect("written");
+
+        assert_eq!(
+            String::from_utf8(output.clone()).expect("utf8"),
+            "0\t10\t5\tbind\tportal\tstatement\t1,0\t1\t00000001\t090aff\t\\N\n"
+        );
+        assert_eq!(
+            read_events(output.as_slice()).expect("read"),
+            vec![captured(
+                0,
+                10,
+                Event::Bind {
+                    portal_name: "portal".to_owned(),
+                    statement_name: "statement".to_owned(),
+                    param_formats: vec![1, 0],
+                    params: vec![Some(vec![0, 0, 0, 1]), Some(vec![b'\t', b'\n', 0xff]), None],
+                    result_formats: vec![1],
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn commands_that_are_captured() {
+        assert_eq!(
+            Event::from_command(&Command::Query("select 1;".to_owned())),
+            Some(Event::Query("select 1;".to_owned()))
+        );
+        assert_eq!(Event::from_command(&Command::DescribePortal("".to_owned())), None);
+        assert_eq!(Event::from_command(&Command::Flush), None);
+        assert_eq!(Event::from_command(&Command::Terminate), None);
+    }
+
+    #[test]
+    fn malformed_event() {
+        let error = read_events("0\t10\t5\tquery\t\\x\n".as_bytes()).expect_err("malformed");
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "malformed event at line 1: 0\t10\t5\tquery\t\\x");
+    }
+}
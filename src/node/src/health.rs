@@ -0,0 +1,148 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::node::RUNNING;
+use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+use smol::{Async, Task};
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, RwLock,
+    },
+};
+use storage::{backend::BackendStorage, frontend::FrontendStorage};
+
+const OK: &str = "200 OK";
+const NOT_FOUND: &str = "404 Not Found";
+const SERVICE_UNAVAILABLE: &str = "503 Service Unavailable";
+
+/// Serves `/healthz` and `/readyz` probes over plain HTTP
+pub async fn serve<P: BackendStorage + Send + Sync + 'static>(
+    local_address: String,
+    state: Arc<AtomicU8>,
+    storage: Arc<RwLock<FrontendStorage<P>>>,
+) -> io::Result<()> {
+    let listener = Async::<TcpListener>::bind(local_address)?;
+    loop {
+        let (stream, _address) = listener.accept().await?;
+        let state = state.clone();
+        let storage = storage.clone();
+        Task::spawn(async move {
+            if let Err(error) = respond(stream, &state, &storage).await {
+                log::error!("failed to respond to probe due to {:?}", error);
+            }
+        })
+        .detach();
+    }
+}
+
+async fn respond<P: BackendStorage>(
+    mut stream: Async<TcpStream>,
+    state: &AtomicU8,
+    storage: &RwLock<FrontendStorage<P>>,
+) -> io::Result<()> {
+    let mut buffer = [0u8; 1024];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = probe(path, state.load(Ordering::SeqCst), storage);
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await
+}
+
+fn probe<P: BackendStorage>(path: &str, state: u8, storage: &RwLock<FrontendStorage<P>>) -> (&'static str, String) {
+    let storage_accessible = match storage.read() {
+        Ok(storage) => storage.database_size().is_ok(),
+        Err(_) => false,
+    };
+    let running = state == RUNNING;
+    let (healthy, checks) = match path {
+        "/healthz" => (storage_accessible, vec![("storage", storage_accessible)]),
+        "/readyz" => (
+            storage_accessible && running,
+            vec![("storage", storage_accessible), ("node", running)],
+        ),
+        _ => return (NOT_FOUND, "{\"status\":\"not found\"}".to_owned()),
+    };
+    let checks = checks
+        .into_iter()
+        .map(|(name, passed)| format!("\"{}\":\"{}\"", name, if passed { "ok" } else { "failed" }))
+        .collect::<Vec<String>>()
+        .join(",");
+    if healthy {
+        (OK, format!("{{\"status\":\"ok\",\"checks\":{{{}}}}}", checks))
+    } else {
+        (
+            SERVICE_UNAVAILABLE,
+            format!("{{\"status\":\"failed\",\"checks\":{{{}}}}}", checks),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::CREATED;
+    use test_helpers::in_memory_backend_storage::InMemoryStorage;
+
+    fn storage() -> RwLock<FrontendStorage<InMemoryStorage>> {
+        RwLock::new(FrontendStorage::new(InMemoryStorage::default()).expect("no system errors"))
+    }
+
+    #[test]
+    fn healthy_node() {
+        assert_eq!(
+            probe("/healthz", CREATED, &storage()),
+            (OK, "{\"status\":\"ok\",\"checks\":{\"storage\":\"ok\"}}".to_owned())
+        );
+    }
+
+    #[test]
+    fn ready_node() {
+        assert_eq!(
+            probe("/readyz", RUNNING, &storage()),
+            (
+                OK,
+                "{\"status\":\"ok\",\"checks\":{\"storage\":\"ok\",\"node\":\"ok\"}}".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn node_that_is_not_running_is_not_ready() {
+        assert_eq!(
+            probe("/readyz", CREATED, &storage()),
+            (
+                SERVICE_UNAVAILABLE,
+                "{\"status\":\"failed\",\"checks\":{\"storage\":\"ok\",\"node\":\"failed\"}}".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_probe() {
+        assert_eq!(
+            probe("/unknown", RUNNING, &storage()),
+            (NOT_FOUND, "{\"status\":\"not found\"}".to_owned())
+        );
+    }
+}
@@ -17,5 +17,11 @@ extern crate log;
 extern crate protocol;
 extern crate storage;
 
+pub mod capture;
+mod health;
+pub mod migrate;
 pub mod node;
 mod query_listener;
+pub mod restore;
+
+pub use query_listener::ConnectionSettings;
@@ -0,0 +1,509 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! applies `.sql` files of a directory in the order of versions that their names
+//! start with, like `0001_create_accounts.sql`. Versions of applied files are kept
+//! in `system.migrations` table, so every file is applied once.
+//!
+//! every file is applied in a transaction along with recording its version.
+//! Changes of definitions can't be reverted yet, files with them are applied
+//! statement by statement and their version is recorded after the last one.
+
+use kernel::SystemError;
+use sql_engine::{Handler, QueryError, QueryEvent};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::{Path, PathBuf},
+};
+use storage::backend::BackendStorage;
+
+// sql state of statements that can't be executed in a transaction
+const ACTIVE_SQL_TRANSACTION: &str = "25001";
+const SCHEMA_ALREADY_EXISTS: &str = "42P06";
+
+/// `.sql` file of a migrations directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub version: u64,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Migration {
+    /// returns `None` if the file is not `.sql` one
+    pub fn from_path(path: PathBuf) -> Option<Result<Migration, MigrationError>> {
+        if path.extension().and_then(|extension| extension.to_str()) != Some("sql") {
+            return None;
+        }
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        let digits = stem.find(|c: char| !c.is_ascii_digit()).unwrap_or(stem.len());
+        let version = match stem[..digits].parse() {
+            Ok(version) => version,
+            Err(_) => return Some(Err(MigrationError::NoVersion(path))),
+        };
+        let name = stem[digits..].trim_start_matches(['_', '-']).to_owned();
+        Some(Ok(Migration { version, name, path }))
+    }
+}
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())
+    }
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Io(io::Error),
+    System(SystemError),
+    NoVersion(PathBuf),
+    DuplicateVersion(u64),
+    Failed {
+        migration: Migration,
+        statement: String,
+        error: Box<QueryError>,
+        // statements before the failed one are not reverted
+        partially_applied: bool,
+    },
+}
+
+impl From<io::Error> for MigrationError {
+    fn from(error: io::Error) -> MigrationError {
+        MigrationError::Io(error)
+    }
+}
+
+impl From<SystemError> for MigrationError {
+    fn from(error: SystemError) -> MigrationError {
+        MigrationError::System(error)
+    }
+}
+
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Io(error) => write!(f, "migrations can't be read due to {}", error),
+            MigrationError::System(error) => write!(f, "migrations can't be applied due to {}", error),
+            MigrationError::NoVersion(path) => write!(f, "name of {} does not start with version", path.display()),
+            MigrationError::DuplicateVersion(version) => {
+                write!(f, "there are more than one migration with {} version", version)
+            }
+            MigrationError::Failed {
+                migration,
+                statement,
+                error,
+                partially_applied,
+            } => {
+                write!(f, "{} is not applied, \"{}\" failed: {}", migration, statement, error)?;
+                if *partially_applied {
+                    write!(f, ", statements before it are left applied")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// migrations of the directory ordered by their versions
+pub fn migrations(directory: &Path) -> Result<Vec<Migration>, MigrationError> {
+    let mut migrations = vec![];
+    for entry in fs::read_dir(directory)? {
+        if let Some(migration) = Migration::from_path(entry?.path()) {
+            migrations.push(migration?);
+        }
+    }
+    migrations.sort_by_key(|migration| migration.version);
+    for pair in migrations.windows(2) {
+        if pair[0].version == pair[1].version {
+            return Err(MigrationError::DuplicateVersion(pair[0].version));
+        }
+    }
+    Ok(migrations)
+}
+
+/// applies migrations of the directory that are not applied yet and returns them
+pub fn apply<P: BackendStorage>(handler: &mut Handler<P>, directory: &Path) -> Result<Vec<Migration>, MigrationError> {
+    let migrations = migrations(directory)?;
+    let applied = applied_versions(handler)?;
+    let mut newly_applied = vec![];
+    for migration in migrations {
+        if applied.contains(&migration.version) {
+            continue;
+        }
+        let sql = fs::read_to_string(&migration.path)?;
+        let statements = statements(&sql);
+        let recorded = format!(
+            "insert into system.migrations values ({}, '{}');",
+            migration.version,
+            migration.name.replace('\'', "''")
+        );
+        execute(handler, &migration, "begin;", false)?;
+        let mut in_transaction = true;
+        for statement in statements.iter() {
+            match handler.execute(statement)? {
+                Err(error) if error.code().as_deref() == Some(ACTIVE_SQL_TRANSACTION) => {
+                    execute(handler, &migration, "rollback;", false)?;
+                    in_transaction = false;
+                    break;
+                }
+                Err(error) => {
+                    execute(handler, &migration, "rollback;", false)?;
+                    return Err(failed(migration, statement, error, false));
+                }
+                Ok(_event) => {}
+            }
+        }
+        if in_transaction {
+            execute(handler, &migration, &recorded, false)?;
+            execute(handler, &migration, "commit;", false)?;
+        } else {
+            for (index, statement) in statements.iter().enumerate() {
+                execute(handler, &migration, statement, index > 0)?;
+            }
+            execute(handler, &migration, &recorded, !statements.is_empty())?;
+        }
+        newly_applied.push(migration);
+    }
+    Ok(newly_applied)
+}
+
+fn applied_versions<P: BackendStorage>(handler: &mut Handler<P>) -> Result<HashSet<u64>, MigrationError> {
+    match handler.execute("create schema system;")? {
+        Ok(_) => {}
+        Err(error) if error.code().as_deref() == Some(SCHEMA_ALREADY_EXISTS) => {}
+        Err(error) => return Err(MigrationError::System(unrecoverable(error))),
+    }
+    let created = handler
+        .execute("create table if not exists system.migrations (version bigint primary key, name varchar(255));")?;
+    if let Err(error) = created {
+        return Err(MigrationError::System(unrecoverable(error)));
+    }
+    let versions = match handler.execute("select version from system.migrations;")? {
+        Ok(QueryEvent::RecordsSelected((_columns, records))) => records,
+        Ok(QueryEvent::RecordsStreamed(_columns, records)) => records.collect::<Result<_, SystemError>>()?,
+        Ok(event) => return Err(SystemError::unrecoverable(format!("{:?} is not versions", event)).into()),
+        Err(error) => return Err(MigrationError::System(unrecoverable(error))),
+    };
+    Ok(versions
+        .into_iter()
+        .filter_map(|record| record.first().and_then(|version| version.parse().ok()))
+        .collect())
+}
+
+fn execute<P: BackendStorage>(
+    handler: &mut Handler<P>,
+    migration: &Migration,
+    statement: &str,
+    partially_applied: bool,
+) -> Result<(), MigrationError> {
+    match handler.execute(statement)? {
+        Ok(_event) => Ok(()),
+        Err(error) => Err(failed(migration.clone(), statement, error, partially_applied)),
+    }
+}
+
+fn failed(migration: Migration, statement: &str, error: QueryError, partially_applied: bool) -> MigrationError {
+    MigrationError::Failed {
+        migration,
+        statement: statement.to_owned(),
+        error: Box::new(error),
+        partially_applied,
+    }
+}
+
+fn unrecoverable(error: QueryError) -> SystemError {
+    SystemError::unrecoverable(format!("system.migrations table can't be used due to {}", error))
+}
+
+/// statements of a file are separated by semicolons that are not in quotes,
+/// dollar quotes or comments, statements that have only comments are skipped
+pub fn statements(sql: &str) -> Vec<&str> {
+    let mut statements = vec![];
+    let mut start = 0;
+    let mut has_code = false;
+    let mut chars = sql.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                has_code = true;
+                // doubled quotes inside are two quoted strings in a row
+                for (_index, next) in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            '$' => {
+                has_code = true;
+                if let Some(delimiter) = dollar_quote(&sql[index..]) {
+                    let body = index + delimiter.len();
+                    let end = sql[body..]
+                        .find(delimiter)
+                        .map(|at| body + at + delimiter.len())
+                        .unwrap_or_else(|| sql.len());
+                    while chars.peek().map(|(next, _c)| *next < end).unwrap_or(false) {
+                        chars.next();
+                    }
+                }
+            }
+            '-' if chars.peek().map(|(_index, next)| *next == '-').unwrap_or(false) => {
+                for (_index, next) in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek().map(|(_index, next)| *next == '*').unwrap_or(false) => {
+                chars.next();
+                let mut previous = ' ';
+                for (_index, next) in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            ';' => {
+                if has_code {
+                    statements.push(sql[start..=index].trim());
+                }
+                start = index + 1;
+                has_code = false;
+            }
+            c if !c.is_whitespace() => has_code = true,
+            _ => {}
+        }
+    }
+    if has_code {
+        statements.push(sql[start..].trim());
+    }
+    statements
+}
+
+/// `$tag$` or `$$` that the text starts with, `$1` is a parameter and not a quote
+fn dollar_quote(sql: &str) -> Option<&str> {
+    let tag = &sql[1..];
+    if tag.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let tag_len = tag.find(|c: char| !c.is_alphanumeric() && c != '_')?;
+    if tag[tag_len..].starts_with('$') {
+        Some(&sql[..tag_len + 2])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, RwLock};
+    use storage::frontend::FrontendStorage;
+    use test_helpers::in_memory_backend_storage::InMemoryStorage;
+
+    fn handler() -> Handler<InMemoryStorage> {
+        Handler::new(Arc::new(RwLock::new(
+            FrontendStorage::new(InMemoryStorage::default()).expect("storage"),
+        )))
+    }
+
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("database_migrations_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("directory is created");
+        for (file, sql) in files {
+            fs::write(directory.join(file), sql).expect("file is written");
+        }
+        directory
+    }
+
+    fn select(handler: &mut Handler<InMemoryStorage>, sql: &str) -> Vec<Vec<String>> {
+        match handler.execute(sql).expect("no system errors") {
+            Ok(QueryEvent::RecordsSelected((_columns, records))) => records,
+            Ok(QueryEvent::RecordsStreamed(_columns, records)) => {
+                records.collect::<Result<_, SystemError>>().expect("records")
+            }
+            other => panic!("{:?} is not records", other),
+        }
+    }
+
+    fn versions(migrations: &[Migration]) -> Vec<u64> {
+        migrations.iter().map(|migration| migration.version).collect()
+    }
+
+    #[test]
+    fn migrations_are_applied_in_order_of_versions() {
+        let directory = directory(
+            "order",
+            &[
+                (
+                    "10_insert_accounts.sql",
+                    "insert into accounts.balances values (1, 100);",
+                ),
+                (
+                    "2_create_accounts.sql",
+                    "create schema accounts;\ncreate table accounts.balances (id integer, amount integer);",
+                ),
+            ],
+        );
+        let mut handler = handler();
+
+        let applied = apply(&mut handler, &directory).expect("applied");
+
+        assert_eq!(versions(&applied), vec![2, 10]);
+        assert_eq!(
+            select(&mut handler, "select id, amount from accounts.balances;"),
+            vec![vec!["1".to_owned(), "100".to_owned()]]
+        );
+        assert_eq!(
+            select(&mut handler, "select version, name from system.migrations;"),
+            vec![
+                vec!["2".to_owned(), "create_accounts".to_owned()],
+                vec!["10".to_owned(), "insert_accounts".to_owned()]
+            ]
+        );
+    }
+
+    #[test]
+    fn applied_migrations_are_skipped() {
+        let directory = directory(
+            "skip",
+            &[
+                (
+                    "1_create_accounts.sql",
+                    "create schema accounts;\ncreate table accounts.balances (id integer);",
+                ),
+                ("2_insert_accounts.sql", "insert into accounts.balances values (1);"),
+            ],
+        );
+        let mut handler = handler();
+        apply(&mut handler, &directory).expect("applied");
+        fs::write(
+            directory.join("3_insert_accounts.sql"),
+            "insert into accounts.balances values (3);",
+        )
+        .expect("file is written");
+
+        let applied = apply(&mut handler, &directory).expect("applied");
+
+        assert_eq!(versions(&applied), vec![3]);
+        assert_eq!(
+            select(&mut handler, "select id from accounts.balances;"),
+            vec![vec!["1".to_owned()], vec!["3".to_owned()]]
+        );
+    }
+
+    #[test]
+    fn failed_migration_is_rolled_back() {
+        let directory = directory(
+            "rollback",
+            &[
+                (
+                    "1_create_accounts.sql",
+                    "create schema accounts;\ncreate table accounts.balances (id integer);",
+                ),
+                (
+                    "2_insert_accounts.sql",
+                    "insert into accounts.balances values (1);\ninsert into accounts.missing values (2);",
+                ),
+            ],
+        );
+        let mut handler = handler();
+
+        match apply(&mut handler, &directory) {
+            Err(MigrationError::Failed {
+                migration,
+                statement,
+                partially_applied,
+                ..
+            }) => {
+                assert_eq!(migration.version, 2);
+                assert_eq!(statement, "insert into accounts.missing values (2);");
+                assert!(!partially_applied);
+            }
+            other => panic!("{:?} is not failed migration", other),
+        }
+        assert_eq!(
+            select(&mut handler, "select id from accounts.balances;"),
+            Vec::<Vec<String>>::new()
+        );
+        assert_eq!(
+            select(&mut handler, "select version from system.migrations;"),
+            vec![vec!["1".to_owned()]]
+        );
+    }
+
+    #[test]
+    fn semicolons_in_dollar_quotes() {
+        assert_eq!(
+            statements("select $$a;b$$; select $tag$ $$; $tag$, $1;\nselect 1"),
+            vec!["select $$a;b$$;", "select $tag$ $$; $tag$, $1;", "select 1"]
+        );
+    }
+
+    #[test]
+    fn statements_are_split_by_semicolons() {
+        assert_eq!(
+            statements("create schema accounts;\n\ncreate table accounts.balances (id integer);\nselect 1"),
+            vec![
+                "create schema accounts;",
+                "create table accounts.balances (id integer);",
+                "select 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn semicolons_in_quotes_and_comments() {
+        assert_eq!(
+            statements(
+                "-- creates table; with comment\ninsert into t values ('a;b', 'it''s');\n\
+                 /* ; */ select \"a;\" from t; -- trailing;"
+            ),
+            vec![
+                "-- creates table; with comment\ninsert into t values ('a;b', 'it''s');",
+                "/* ; */ select \"a;\" from t;"
+            ]
+        );
+    }
+
+    #[test]
+    fn version_and_name_of_migration() {
+        assert_eq!(
+            Migration::from_path(PathBuf::from("migrations/0012_add_balance.sql"))
+                .expect("sql file")
+                .expect("migration"),
+            Migration {
+                version: 12,
+                name: "add_balance".to_owned(),
+                path: PathBuf::from("migrations/0012_add_balance.sql"),
+            }
+        );
+    }
+
+    #[test]
+    fn files_other_than_sql_are_skipped() {
+        assert!(Migration::from_path(PathBuf::from("migrations/README.md")).is_none());
+    }
+
+    #[test]
+    fn migration_without_version() {
+        assert!(matches!(
+            Migration::from_path(PathBuf::from("migrations/add_balance.sql")),
+            Some(Err(MigrationError::NoVersion(_)))
+        ));
+    }
+}
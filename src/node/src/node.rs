@@ -12,17 +12,44 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::query_listener::SmolQueryListener;
-use protocol::{listener::Secure, messages::Message, ColumnMetadata, Command, QueryListener};
-use smol::Task;
-use sql_engine::{Handler, QueryEvent, QueryResult};
+use crate::{
+    capture::{Capture, Event},
+    health,
+    migrate::{self, Migration, MigrationError},
+    query_listener::{ConnectionSettings, SmolQueryListener},
+    restore::{self, RestoreError, Restored},
+};
+use futures_util::future::{self, Either};
+use kernel::SystemResult;
+use protocol::{
+    hba::{HostBasedAuthentication, Rule},
+    listener::Secure,
+    messages::Message,
+    ColumnMetadata, Command, QueryListener, TransactionStatus,
+};
+use smol::{Task, Timer};
+use sql_engine::{
+    Handler, PortalEvent, QueryError, QueryEvent, QueryResult, RecordStream, Session, SessionActivity,
+    StatementStatistics, TransactionState, WaitEvent,
+};
 use sql_types::SqlType;
-use std::sync::{
-    atomic::{AtomicU8, Ordering},
-    Arc, Mutex,
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Instant,
+};
+use storage::{
+    backend::BackendStorage,
+    engine::{StorageConfig, StorageEngine},
+    frontend::FrontendStorage,
+    temp::TempStorage,
 };
 
 const PORT: usize = 5432;
+const HEALTH_PORT: usize = 8080;
 const HOST: &str = "0.0.0.0";
 
 pub const CREATED: u8 = 0;
@@ -31,17 +58,75 @@ pub const STOPPED: u8 = 2;
 
 pub struct Node {
     state: Arc<AtomicU8>,
+    authentication: HostBasedAuthentication,
+    connection_settings: ConnectionSettings,
+    data_directory: Option<PathBuf>,
+    storage_engine: String,
+    capture_file: Option<PathBuf>,
 }
 
 impl Default for Node {
     fn default() -> Self {
         Self {
             state: Arc::new(AtomicU8::new(CREATED)),
+            authentication: HostBasedAuthentication::trust_all(),
+            connection_settings: ConnectionSettings::default(),
+            data_directory: None,
+            storage_engine: "sled".to_owned(),
+            capture_file: None,
         }
     }
 }
 
 impl Node {
+    pub fn with_connection_settings(mut self, connection_settings: ConnectionSettings) -> Self {
+        self.connection_settings = connection_settings;
+        self
+    }
+
+    /// data is lost when node stops if it is not kept in a directory
+    pub fn with_data_directory<D: Into<PathBuf>>(mut self, data_directory: D) -> Self {
+        self.data_directory = Some(data_directory.into());
+        self
+    }
+
+    /// name of an engine that is registered in `StorageEngine`
+    pub fn with_storage_engine(mut self, storage_engine: &str) -> Self {
+        self.storage_engine = storage_engine.to_owned();
+        self
+    }
+
+    /// statements that clients execute are written to the file along with their
+    /// parameters and timing, so that the workload can be replayed later
+    pub fn with_workload_capture<F: Into<PathBuf>>(mut self, capture_file: F) -> Self {
+        self.capture_file = Some(capture_file.into());
+        self
+    }
+
+    /// applies migrations of the directory without starting the server and
+    /// returns the ones that were not applied before
+    pub fn migrate<D: AsRef<Path>>(&self, directory: D) -> Result<Vec<Migration>, MigrationError> {
+        let backend =
+            StorageEngine::open(&self.storage_engine, &self.storage_config())?.expect("storage engine is registered");
+        let storage = Arc::new(RwLock::new(FrontendStorage::new(backend)?));
+        migrate::apply(&mut Handler::new(storage), directory.as_ref())
+    }
+
+    /// restores plain sql dump that is made with `pg_dump` without starting the server
+    pub fn restore<D: AsRef<Path>>(&self, dump: D) -> Result<Restored, RestoreError> {
+        let backend =
+            StorageEngine::open(&self.storage_engine, &self.storage_config())?.expect("storage engine is registered");
+        let storage = Arc::new(RwLock::new(FrontendStorage::new(backend)?));
+        restore::restore(&mut Handler::new(storage), dump.as_ref())
+    }
+
+    fn storage_config(&self) -> StorageConfig {
+        match &self.data_directory {
+            Some(data_directory) => StorageConfig::default().with_data_directory(data_directory),
+            None => StorageConfig::default(),
+        }
+    }
+
     pub fn state(&self) -> u8 {
         self.state.load(Ordering::SeqCst)
     }
@@ -50,56 +135,249 @@ impl Node {
         self.state.store(STOPPED, Ordering::SeqCst);
     }
 
+    pub fn reload_authentication(&self, rules: Vec<Rule>) {
+        self.authentication.reload(rules);
+    }
+
     pub fn start(&self) {
         let local_address = format!("{}:{}", HOST, PORT);
         log::debug!("Starting server on {}", local_address);
 
         smol::run(async {
-            let listener = SmolQueryListener::bind(local_address, Secure::none())
-                .await
-                .expect("open server connection");
+            let listener = SmolQueryListener::bind(
+                local_address,
+                Secure::none(),
+                self.authentication.clone(),
+                self.connection_settings,
+            )
+            .await
+            .expect("open server connection");
             self.state.store(RUNNING, Ordering::SeqCst);
 
-            let storage = Arc::new(Mutex::new(storage::frontend::FrontendStorage::default().unwrap()));
+            let backend = StorageEngine::open(&self.storage_engine, &self.storage_config())
+                .expect("no system errors")
+                .expect("storage engine is registered");
+            let storage = Arc::new(RwLock::new(FrontendStorage::new(backend).unwrap()));
+            // sorts spill rows that do not fit into work_mem next to the data
+            let temp_directory = match &self.data_directory {
+                Some(data_directory) => data_directory.join("temp"),
+                None => std::env::temp_dir().join(format!("database_temp_{}", std::process::id())),
+            };
+            let temp = Arc::new(TempStorage::open(temp_directory).expect("temp directory is created"));
+            let query_ids = Arc::new(AtomicU64::new(0));
+            let statistics = Arc::new(Mutex::new(StatementStatistics::default()));
+            let activity = Arc::new(Mutex::new(SessionActivity::default()));
+            let session_ids = Arc::new(AtomicU64::new(0));
+            let capture = self
+                .capture_file
+                .as_ref()
+                .map(|capture_file| Capture::create(capture_file).expect("capture file is created"));
+
+            let health_address = format!("{}:{}", HOST, HEALTH_PORT);
+            let health_state = self.state.clone();
+            let health_storage = storage.clone();
+            Task::spawn(async move {
+                if let Err(error) = health::serve(health_address, health_state, health_storage).await {
+                    log::error!("health probes are not served due to {:?}", error);
+                }
+            })
+            .detach();
 
             log::debug!("waiting for connections");
-            while let Ok(mut connection) = listener.accept().await.expect("no io errors") {
+            loop {
+                let accepted = listener.accept().await;
                 if self.state() == STOPPED {
                     return;
                 }
-                let state = self.state.clone();
+                let mut connection = match accepted {
+                    Ok(Ok(connection)) => connection,
+                    Ok(Err(error)) => {
+                        log::warn!("client connection is rejected due to {:?}", error);
+                        continue;
+                    }
+                    Err(error) => {
+                        log::warn!("client connection is not established due to {:?}", error);
+                        continue;
+                    }
+                };
+                let idle_timeout = self.connection_settings.idle_timeout;
                 let storage = storage.clone();
+                let temp = temp.clone();
+                let query_ids = query_ids.clone();
+                let statistics = statistics.clone();
+                let activity = activity.clone();
+                let capture = capture.clone();
+                let session_id = session_ids.fetch_add(1, Ordering::SeqCst);
+                let user_name = connection.parameter("user").unwrap_or_default();
+                let application_name = connection.parameter("application_name").unwrap_or_default();
+                activity
+                    .lock()
+                    .unwrap()
+                    .connect(session_id, user_name, application_name);
+                let database = connection.parameter("database").unwrap_or(user_name);
+                if let Some(capture) = capture.as_ref() {
+                    let event = Event::Connect {
+                        user: user_name.to_owned(),
+                        database: database.to_owned(),
+                    };
+                    capture.record(session_id, Instant::now(), event);
+                }
+                let mut session = Session::new(session_id, user_name, database);
+                session
+                    .set("application_name", application_name)
+                    .expect("application name is valid setting");
+                // date and time parameters are set by clients during handshake
+                for (name, value) in connection.properties().1.iter() {
+                    if name.eq_ignore_ascii_case("datestyle") || name.eq_ignore_ascii_case("timezone") {
+                        if let Err(error) = session.set(name, value) {
+                            log::warn!("startup parameter is ignored due to {:?}", error);
+                        }
+                    }
+                }
                 Task::spawn(async move {
-                    let mut sql_handler = Handler::new(storage);
+                    let mut sql_handler = Handler::with_activity(storage, statistics, activity.clone())
+                        .with_session(session)
+                        .with_temp_storage(temp)
+                        .with_result_streaming();
+                    let parameters = QueryResultMapper::parameters(sql_handler.take_parameter_changes());
+                    if let Err(error) = connection.send(parameters).await {
+                        log::warn!("connection with client is lost due to {:?}", error);
+                        return;
+                    }
 
                     log::debug!("ready to handle query");
+                    // after an error of extended query protocol commands are skipped until Sync
+                    let mut skip_until_sync = false;
                     loop {
-                        match connection.receive().await {
-                            Err(e) => {
-                                log::debug!("SHOULD STOP");
-                                log::error!("UNEXPECTED ERROR: {:?}", e);
-                                state.store(STOPPED, Ordering::SeqCst);
-                                return;
+                        connection.set_transaction_status(match sql_handler.transaction_state() {
+                            TransactionState::Idle => TransactionStatus::Idle,
+                            TransactionState::InProgress => TransactionStatus::InTransaction,
+                            TransactionState::Failed => TransactionStatus::Failed,
+                        });
+                        activity.lock().unwrap().wait_started(session_id, WaitEvent::ClientRead);
+                        let received = match idle_timeout {
+                            None => Some(connection.receive().await),
+                            Some(timeout) => {
+                                match future::select(Box::pin(connection.receive()), Timer::after(timeout)).await {
+                                    Either::Left((received, _timer)) => Some(received),
+                                    Either::Right(_) => None,
+                                }
+                            }
+                        };
+                        activity.lock().unwrap().wait_finished(session_id);
+                        let received = match received {
+                            Some(received) => received,
+                            None => {
+                                log::info!("closing connection with idle client");
+                                let _ = connection
+                                    .send(vec![Message::ErrorResponse(
+                                        Some("FATAL".to_owned()),
+                                        Some("57P05".to_owned()),
+                                        Some("terminating connection due to idle-session timeout".to_owned()),
+                                    )])
+                                    .await;
+                                break;
                             }
-                            Ok(Err(e)) => {
-                                log::debug!("SHOULD STOP");
-                                log::error!("UNEXPECTED ERROR: {:?}", e);
-                                state.store(STOPPED, Ordering::SeqCst);
-                                return;
+                        };
+                        wait_for_transaction(&mut sql_handler, &activity, session_id).await;
+                        let started = Instant::now();
+                        let captured = match (capture.as_ref(), &received) {
+                            (Some(_), Ok(Ok(command))) => Event::from_command(command),
+                            _ => None,
+                        };
+                        match received {
+                            // keepalive probes that are not answered end up here for half-open connections
+                            Err(error) => {
+                                log::warn!("connection with client is lost due to {:?}", error);
+                                break;
+                            }
+                            Ok(Err(error)) => {
+                                log::warn!("closing connection with client due to {:?}", error);
+                                break;
                             }
                             Ok(Ok(Command::Terminate)) => {
                                 log::debug!("Closing connection with client");
                                 break;
                             }
                             Ok(Ok(Command::Query(sql_query))) => {
-                                let response = sql_handler.execute(sql_query.as_str()).expect("no system error");
-                                match connection.send(QueryResultMapper::map(response)).await {
+                                let query_id = query_ids.fetch_add(1, Ordering::SeqCst);
+                                let span = tracing::info_span!("query", query_id, sql = sql_query.as_str());
+                                activity.lock().unwrap().query_started(session_id, sql_query.as_str());
+                                let response = span
+                                    .in_scope(|| sql_handler.execute(sql_query.as_str()))
+                                    .expect("no system error");
+                                let mut notices = sql_handler
+                                    .take_notices()
+                                    .into_iter()
+                                    .map(QueryResultMapper::notice)
+                                    .collect::<Vec<Message>>();
+                                notices.extend(QueryResultMapper::parameters(sql_handler.take_parameter_changes()));
+                                activity
+                                    .lock()
+                                    .unwrap()
+                                    .wait_started(session_id, WaitEvent::ClientWrite);
+                                let sent = match response {
+                                    Ok(QueryEvent::RecordsStreamed(columns, records)) => {
+                                        connection
+                                            .send_stream(
+                                                notices.into_iter().chain(QueryResultMapper::stream(columns, records)),
+                                            )
+                                            .await
+                                    }
+                                    response => {
+                                        let mut messages = notices;
+                                        messages.extend(QueryResultMapper::map(response));
+                                        connection.send(messages).await
+                                    }
+                                };
+                                activity.lock().unwrap().wait_finished(session_id);
+                                activity.lock().unwrap().query_finished(session_id);
+                                match sent {
                                     Ok(()) => {}
                                     Err(error) => eprintln!("{:?}", error), // break Err(SystemError::io(error)),
                                 }
                             }
+                            Ok(Ok(Command::Sync)) => {
+                                skip_until_sync = false;
+                                sql_handler.sync();
+                            }
+                            Ok(Ok(Command::Flush)) => {}
+                            Ok(Ok(_command)) if skip_until_sync => {}
+                            Ok(Ok(command)) => {
+                                let response = extended_query(&mut sql_handler, command).expect("no system error");
+                                let mut notices = sql_handler
+                                    .take_notices()
+                                    .into_iter()
+                                    .map(QueryResultMapper::notice)
+                                    .collect::<Vec<Message>>();
+                                notices.extend(QueryResultMapper::parameters(sql_handler.take_parameter_changes()));
+                                let sent = match response {
+                                    Ok(messages) => connection.send_stream(notices.into_iter().chain(messages)).await,
+                                    Err(error) => {
+                                        skip_until_sync = true;
+                                        let mut messages = notices;
+                                        messages.extend(QueryResultMapper::map(Err(error)));
+                                        connection.send(messages).await
+                                    }
+                                };
+                                if let Err(error) = sent {
+                                    log::warn!("connection with client is lost due to {:?}", error);
+                                    break;
+                                }
+                            }
+                        }
+                        if let (Some(capture), Some(event)) = (capture.as_ref(), captured) {
+                            capture.record(session_id, started, event);
                         }
                     }
+                    if let Some(capture) = capture.as_ref() {
+                        capture.record(session_id, Instant::now(), Event::Disconnect);
+                    }
+                    wait_for_transaction(&mut sql_handler, &activity, session_id).await;
+                    if let Err(error) = sql_handler.close() {
+                        log::error!("temporary objects of session are not dropped due to {:?}", error);
+                    }
+                    activity.lock().unwrap().disconnect(session_id);
                 })
                 .detach();
             }
@@ -107,13 +385,162 @@ impl Node {
     }
 }
 
+type Messages = Box<dyn Iterator<Item = Message> + Send>;
+
+// sessions share the thread, so a session that would block on a transaction
+// block of another session waits for it on a blocking thread to let the block
+// end. Handler is borrowed mutably as it is not shared between threads
+async fn wait_for_transaction<P: BackendStorage + Send + Sync + 'static>(
+    sql_handler: &mut Handler<P>,
+    activity: &Mutex<SessionActivity>,
+    session_id: u64,
+) {
+    let mut waited = false;
+    // another block could start before the session is resumed
+    while let Some(lock_wait) = sql_handler.lock_wait() {
+        if !waited {
+            activity
+                .lock()
+                .unwrap()
+                .wait_started(session_id, WaitEvent::TransactionLock);
+            waited = true;
+        }
+        if !Task::blocking(async move { lock_wait.wait() }).await {
+            sql_handler.lock_timed_out();
+            break;
+        }
+    }
+    if waited {
+        activity.lock().unwrap().wait_finished(session_id);
+    }
+}
+
+// commands of extended query protocol are answered without waiting for Sync
+fn extended_query<P: BackendStorage>(
+    sql_handler: &mut Handler<P>,
+    command: Command,
+) -> SystemResult<Result<Messages, QueryError>> {
+    let messages = match command {
+        Command::Parse {
+            statement_name,
+            sql,
+            param_types,
+        } => {
+            let param_types = param_types.into_iter().map(TypeConverter::sql_type).collect();
+            match sql_handler.prepare(&statement_name, &sql, param_types) {
+                Ok(()) => vec![Message::ParseComplete],
+                Err(error) => return Ok(Err(error)),
+            }
+        }
+        Command::Bind {
+            portal_name,
+            statement_name,
+            param_formats,
+            params,
+            result_formats,
+        } => {
+            if result_formats.iter().any(|format| *format != 0) {
+                return Ok(Err(QueryError::feature_not_supported(
+                    "binary format of results".to_owned(),
+                )));
+            }
+            let params = match text_params(&param_formats, params) {
+                Ok(params) => params,
+                Err(error) => return Ok(Err(error)),
+            };
+            match sql_handler.bind(&portal_name, &statement_name, params) {
+                Ok(()) => vec![Message::BindComplete],
+                Err(error) => return Ok(Err(error)),
+            }
+        }
+        Command::DescribeStatement(statement_name) => match sql_handler.describe_statement(&statement_name)? {
+            Ok((param_types, columns)) => vec![
+                Message::ParameterDescription(
+                    param_types
+                        .iter()
+                        .map(|param_type| param_type.as_ref().map(TypeConverter::pg_oid).unwrap_or(TEXT_OID))
+                        .collect(),
+                ),
+                QueryResultMapper::row_description(columns),
+            ],
+            Err(error) => return Ok(Err(error)),
+        },
+        Command::DescribePortal(portal_name) => match sql_handler.describe_portal(&portal_name)? {
+            Ok(columns) => vec![QueryResultMapper::row_description(columns)],
+            Err(error) => return Ok(Err(error)),
+        },
+        Command::Execute { portal_name, max_rows } => {
+            return Ok(sql_handler
+                .execute_portal(&portal_name, max_rows.max(0) as usize)?
+                .map(QueryResultMapper::portal))
+        }
+        Command::CloseStatement(statement_name) => {
+            sql_handler.close_statement(&statement_name);
+            vec![Message::CloseComplete]
+        }
+        Command::ClosePortal(portal_name) => {
+            sql_handler.close_portal(&portal_name);
+            vec![Message::CloseComplete]
+        }
+        Command::Query(_) | Command::Sync | Command::Flush | Command::Terminate => vec![],
+    };
+    Ok(Ok(Box::new(messages.into_iter())))
+}
+
+// values of parameters are accepted in text format only, a single format
+// code applies to all of them
+fn text_params(param_formats: &[i16], params: Vec<Option<Vec<u8>>>) -> Result<Vec<Option<String>>, QueryError> {
+    if param_formats.iter().any(|format| *format != 0) {
+        return Err(QueryError::feature_not_supported(
+            "binary format of parameters".to_owned(),
+        ));
+    }
+    params
+        .into_iter()
+        .map(|param| match param {
+            Some(value) => String::from_utf8(value).map(Some).map_err(|error| {
+                QueryError::invalid_text_representation(
+                    "text".to_owned(),
+                    String::from_utf8_lossy(error.as_bytes()).into_owned(),
+                )
+            }),
+            None => Ok(None),
+        })
+        .collect()
+}
+
+// parameters which types are not specified are described as text
+const TEXT_OID: i32 = 25;
+
 struct TypeConverter;
 
 impl TypeConverter {
+    fn sql_type(pg_oid: i32) -> Option<SqlType> {
+        match pg_oid {
+            16 => Some(SqlType::Bool),
+            20 => Some(SqlType::BigInt),
+            21 => Some(SqlType::SmallInt),
+            23 => Some(SqlType::Integer),
+            700 => Some(SqlType::Real),
+            701 => Some(SqlType::DoublePrecision),
+            // length of character types is checked when value is stored
+            25 | 1043 => Some(SqlType::VarChar(255)),
+            1042 => Some(SqlType::Char(255)),
+            1082 => Some(SqlType::Date),
+            1083 => Some(SqlType::Time),
+            1114 => Some(SqlType::Timestamp),
+            1184 => Some(SqlType::TimestampWithTimeZone),
+            1186 => Some(SqlType::Interval),
+            1266 => Some(SqlType::TimeWithTimeZone),
+            1700 => Some(SqlType::Decimal),
+            _ => None,
+        }
+    }
+
     fn pg_oid(sql_type: &SqlType) -> i32 {
         match sql_type {
             SqlType::Bool => 16,
-            SqlType::Char(_) => 18,
+            SqlType::Char(_) => 1042,        // PG bpchar
             SqlType::BigInt => 20,           // PG int8
             SqlType::SmallInt => 21,         // PG int2
             SqlType::Integer => 23,          // PG int4
@@ -133,7 +560,7 @@ impl TypeConverter {
     fn pg_len(sql_type: &SqlType) -> i16 {
         match sql_type {
             SqlType::Bool => 1,
-            SqlType::Char(_) => 1,
+            SqlType::Char(_) => -1,
             SqlType::BigInt => 8,
             SqlType::SmallInt => 2,
             SqlType::Integer => 4,
@@ -149,6 +576,14 @@ impl TypeConverter {
             SqlType::Decimal => -1,
         }
     }
+
+    // PG keeps length of character types together with the 4 bytes of their header
+    fn pg_type_modifier(sql_type: &SqlType) -> i32 {
+        match sql_type {
+            SqlType::Char(len) | SqlType::VarChar(len) => *len as i32 + 4,
+            _ => -1,
+        }
+    }
 }
 
 struct QueryResultMapper;
@@ -160,17 +595,22 @@ impl QueryResultMapper {
             Ok(QueryEvent::SchemaDropped) => vec![Message::CommandComplete("DROP SCHEMA".to_owned())],
             Ok(QueryEvent::TableCreated) => vec![Message::CommandComplete("CREATE TABLE".to_owned())],
             Ok(QueryEvent::TableDropped) => vec![Message::CommandComplete("DROP TABLE".to_owned())],
+            Ok(QueryEvent::TableAltered) => vec![Message::CommandComplete("ALTER TABLE".to_owned())],
+            Ok(QueryEvent::TableTruncated) => vec![Message::CommandComplete("TRUNCATE TABLE".to_owned())],
+            Ok(QueryEvent::SequenceCreated) => vec![Message::CommandComplete("CREATE SEQUENCE".to_owned())],
+            Ok(QueryEvent::SequenceDropped) => vec![Message::CommandComplete("DROP SEQUENCE".to_owned())],
+            Ok(QueryEvent::SequenceAltered) => vec![Message::CommandComplete("ALTER SEQUENCE".to_owned())],
+            Ok(QueryEvent::IndexCreated) => vec![Message::CommandComplete("CREATE INDEX".to_owned())],
+            Ok(QueryEvent::IndexDropped) => vec![Message::CommandComplete("DROP INDEX".to_owned())],
             Ok(QueryEvent::VariableSet) => vec![Message::CommandComplete("SET".to_owned())],
             Ok(QueryEvent::TransactionStarted) => vec![Message::CommandComplete("BEGIN".to_owned())],
+            Ok(QueryEvent::TransactionCommitted) => vec![Message::CommandComplete("COMMIT".to_owned())],
+            Ok(QueryEvent::TransactionRolledBack) => vec![Message::CommandComplete("ROLLBACK".to_owned())],
+            Ok(QueryEvent::Checkpoint) => vec![Message::CommandComplete("CHECKPOINT".to_owned())],
+            Ok(QueryEvent::QueryEmpty) => vec![Message::EmptyQueryResponse],
             Ok(QueryEvent::RecordsInserted(records)) => vec![Message::CommandComplete(format!("INSERT 0 {}", records))],
             Ok(QueryEvent::RecordsSelected(projection)) => {
-                let definition = projection.0;
-                let description: Vec<ColumnMetadata> = definition
-                    .into_iter()
-                    .map(|(name, sql_type)| {
-                        ColumnMetadata::new(name, TypeConverter::pg_oid(&sql_type), TypeConverter::pg_len(&sql_type))
-                    })
-                    .collect();
+                let description = Self::description(projection.0);
                 let records = projection.1;
                 let len = records.len();
                 let mut messages = vec![Message::RowDescription(description)];
@@ -180,8 +620,12 @@ impl QueryResultMapper {
                 messages.push(Message::CommandComplete(format!("SELECT {}", len)));
                 messages
             }
+            Ok(QueryEvent::RecordsStreamed(columns, records)) => Self::stream(columns, records).collect(),
             Ok(QueryEvent::RecordsUpdated(records)) => vec![Message::CommandComplete(format!("UPDATE {}", records))],
             Ok(QueryEvent::RecordsDeleted(records)) => vec![Message::CommandComplete(format!("DELETE {}", records))],
+            Ok(QueryEvent::StatementPrepared) => vec![Message::CommandComplete("PREPARE".to_owned())],
+            Ok(QueryEvent::StatementDeallocated) => vec![Message::CommandComplete("DEALLOCATE".to_owned())],
+            Ok(QueryEvent::AllStatementsDeallocated) => vec![Message::CommandComplete("DEALLOCATE ALL".to_owned())],
             Err(query_error) => vec![Message::ErrorResponse(
                 query_error.severity(),
                 query_error.code(),
@@ -189,11 +633,113 @@ impl QueryResultMapper {
             )],
         }
     }
+
+    // rows of executed portal are described by Describe command before it
+    fn portal(event: PortalEvent) -> Messages {
+        match event {
+            PortalEvent::Completed(QueryEvent::RecordsStreamed(_columns, records)) => Box::new(StreamedRecords {
+                description: None,
+                records,
+                sent: 0,
+                completed: false,
+            }),
+            PortalEvent::Completed(QueryEvent::RecordsSelected((_columns, records))) => {
+                let len = records.len();
+                let mut messages = records.into_iter().map(Message::DataRow).collect::<Vec<Message>>();
+                messages.push(Message::CommandComplete(format!("SELECT {}", len)));
+                Box::new(messages.into_iter())
+            }
+            PortalEvent::Completed(event) => Box::new(Self::map(Ok(event)).into_iter()),
+            PortalEvent::Suspended(records) => {
+                let mut messages = records.into_iter().map(Message::DataRow).collect::<Vec<Message>>();
+                messages.push(Message::PortalSuspended);
+                Box::new(messages.into_iter())
+            }
+        }
+    }
+
+    fn row_description(columns: Option<Vec<(String, SqlType)>>) -> Message {
+        match columns {
+            Some(columns) => Message::RowDescription(Self::description(columns)),
+            None => Message::NoData,
+        }
+    }
+
+    fn notice(notice: QueryError) -> Message {
+        Message::Notice(notice.severity(), notice.code(), Some(format!("{}", notice)))
+    }
+
+    fn parameters(changes: Vec<(String, String)>) -> Vec<Message> {
+        changes
+            .into_iter()
+            .map(|(name, value)| Message::ParameterStatus(name, value))
+            .collect()
+    }
+
+    fn stream(columns: Vec<(String, SqlType)>, records: RecordStream) -> StreamedRecords {
+        StreamedRecords {
+            description: Some(Self::description(columns)),
+            records,
+            sent: 0,
+            completed: false,
+        }
+    }
+
+    fn description(columns: Vec<(String, SqlType)>) -> Vec<ColumnMetadata> {
+        columns
+            .into_iter()
+            .map(|(name, sql_type)| {
+                ColumnMetadata::new(name, TypeConverter::pg_oid(&sql_type), TypeConverter::pg_len(&sql_type))
+                    .with_type_modifier(TypeConverter::pg_type_modifier(&sql_type))
+            })
+            .collect()
+    }
+}
+
+// produces messages of selected records as they are read from storage
+struct StreamedRecords {
+    description: Option<Vec<ColumnMetadata>>,
+    records: RecordStream,
+    sent: usize,
+    completed: bool,
+}
+
+impl Iterator for StreamedRecords {
+    type Item = Message;
+
+    fn next(&mut self) -> Option<Message> {
+        if let Some(description) = self.description.take() {
+            return Some(Message::RowDescription(description));
+        }
+        if self.completed {
+            return None;
+        }
+        match self.records.next() {
+            Some(Ok(record)) => {
+                self.sent += 1;
+                Some(Message::DataRow(record))
+            }
+            Some(Err(error)) => {
+                log::error!("records can't be read due to {}", error);
+                self.completed = true;
+                Some(Message::ErrorResponse(
+                    Some("ERROR".to_owned()),
+                    Some("XX000".to_owned()),
+                    Some(format!("{}", error)),
+                ))
+            }
+            None => {
+                self.completed = true;
+                Some(Message::CommandComplete(format!("SELECT {}", self.sent)))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod mapper {
     use super::*;
+    use kernel::{SystemError, SystemResult};
     use sql_engine::QueryError;
     use sql_types::SqlType;
 
@@ -229,6 +775,70 @@ mod mapper {
         );
     }
 
+    #[test]
+    fn empty_query() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::QueryEmpty)),
+            vec![Message::EmptyQueryResponse]
+        );
+    }
+
+    #[test]
+    fn alter_table() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::TableAltered)),
+            vec![Message::CommandComplete("ALTER TABLE".to_owned())]
+        );
+    }
+
+    #[test]
+    fn truncate_table() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::TableTruncated)),
+            vec![Message::CommandComplete("TRUNCATE TABLE".to_owned())]
+        );
+    }
+
+    #[test]
+    fn create_sequence() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::SequenceCreated)),
+            vec![Message::CommandComplete("CREATE SEQUENCE".to_owned())]
+        );
+    }
+
+    #[test]
+    fn drop_sequence() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::SequenceDropped)),
+            vec![Message::CommandComplete("DROP SEQUENCE".to_owned())]
+        );
+    }
+
+    #[test]
+    fn alter_sequence() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::SequenceAltered)),
+            vec![Message::CommandComplete("ALTER SEQUENCE".to_owned())]
+        );
+    }
+
+    #[test]
+    fn create_index() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::IndexCreated)),
+            vec![Message::CommandComplete("CREATE INDEX".to_owned())]
+        );
+    }
+
+    #[test]
+    fn drop_index() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::IndexDropped)),
+            vec![Message::CommandComplete("DROP INDEX".to_owned())]
+        );
+    }
+
     #[test]
     fn insert_record() {
         let records_number = 3;
@@ -264,6 +874,85 @@ mod mapper {
         );
     }
 
+    #[test]
+    fn select_records_of_character_types() {
+        let projection = (
+            vec![
+                ("column_name_1".to_owned(), SqlType::Char(10)),
+                ("column_name_2".to_owned(), SqlType::VarChar(20)),
+            ],
+            vec![],
+        );
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::RecordsSelected(projection))),
+            vec![
+                Message::RowDescription(vec![
+                    ColumnMetadata::new("column_name_1".to_owned(), 1042, -1).with_type_modifier(14),
+                    ColumnMetadata::new("column_name_2".to_owned(), 1043, -1).with_type_modifier(24)
+                ]),
+                Message::CommandComplete("SELECT 0".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn prepare_and_deallocate() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::StatementPrepared)),
+            vec![Message::CommandComplete("PREPARE".to_owned())]
+        );
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::StatementDeallocated)),
+            vec![Message::CommandComplete("DEALLOCATE".to_owned())]
+        );
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::AllStatementsDeallocated)),
+            vec![Message::CommandComplete("DEALLOCATE ALL".to_owned())]
+        );
+    }
+
+    #[test]
+    fn executed_portal() {
+        let projection = (
+            vec![("column_name".to_owned(), SqlType::SmallInt)],
+            vec![vec!["1".to_owned()]],
+        );
+        assert_eq!(
+            QueryResultMapper::portal(PortalEvent::Completed(QueryEvent::RecordsSelected(projection)))
+                .collect::<Vec<Message>>(),
+            vec![
+                Message::DataRow(vec!["1".to_owned()]),
+                Message::CommandComplete("SELECT 1".to_owned())
+            ]
+        );
+        assert_eq!(
+            QueryResultMapper::portal(PortalEvent::Completed(QueryEvent::RecordsInserted(1))).collect::<Vec<Message>>(),
+            vec![Message::CommandComplete("INSERT 0 1".to_owned())]
+        );
+    }
+
+    #[test]
+    fn suspended_portal() {
+        assert_eq!(
+            QueryResultMapper::portal(PortalEvent::Suspended(vec![vec!["1".to_owned()]])).collect::<Vec<Message>>(),
+            vec![Message::DataRow(vec!["1".to_owned()]), Message::PortalSuspended]
+        );
+    }
+
+    #[test]
+    fn binary_parameters_are_not_supported() {
+        assert_eq!(
+            text_params(&[1], vec![Some(vec![0, 0, 0, 1])]),
+            Err(QueryError::feature_not_supported(
+                "binary format of parameters".to_owned()
+            ))
+        );
+        assert_eq!(
+            text_params(&[], vec![Some(b"1".to_vec()), None]),
+            Ok(vec![Some("1".to_owned()), None])
+        );
+    }
+
     #[test]
     fn update_records() {
         let records_number = 3;
@@ -282,6 +971,87 @@ mod mapper {
         )
     }
 
+    #[test]
+    fn stream_records() {
+        let records: Vec<SystemResult<Vec<String>>> = vec![Ok(vec!["1".to_owned()]), Ok(vec!["2".to_owned()])];
+        let records = RecordStream::new(Box::new(records.into_iter()));
+        assert_eq!(
+            QueryResultMapper::stream(vec![("column_name".to_owned(), SqlType::SmallInt)], records)
+                .collect::<Vec<Message>>(),
+            vec![
+                Message::RowDescription(vec![ColumnMetadata::new("column_name".to_owned(), 21, 2)]),
+                Message::DataRow(vec!["1".to_owned()]),
+                Message::DataRow(vec!["2".to_owned()]),
+                Message::CommandComplete("SELECT 2".to_owned())
+            ]
+        )
+    }
+
+    #[test]
+    fn stream_records_that_can_not_be_read() {
+        let records: Vec<SystemResult<Vec<String>>> = vec![
+            Ok(vec!["1".to_owned()]),
+            Err(SystemError::unrecoverable("storage is broken".to_owned())),
+            Ok(vec!["2".to_owned()]),
+        ];
+        let records = RecordStream::new(Box::new(records.into_iter()));
+        let messages = QueryResultMapper::stream(vec![("column_name".to_owned(), SqlType::SmallInt)], records)
+            .collect::<Vec<Message>>();
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1], Message::DataRow(vec!["1".to_owned()]));
+        assert!(matches!(messages[2], Message::ErrorResponse(_, Some(ref code), _) if code == "XX000"));
+    }
+
+    #[test]
+    fn commit_transaction() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::TransactionCommitted)),
+            vec![Message::CommandComplete("COMMIT".to_owned())]
+        )
+    }
+
+    #[test]
+    fn rollback_transaction() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::TransactionRolledBack)),
+            vec![Message::CommandComplete("ROLLBACK".to_owned())]
+        )
+    }
+
+    #[test]
+    fn notice() {
+        assert_eq!(
+            QueryResultMapper::notice(QueryError::table_does_not_exist_skipping(
+                "schema_name.table_name".to_owned()
+            )),
+            Message::Notice(
+                Some("NOTICE".to_owned()),
+                Some("00000".to_owned()),
+                Some("table \"schema_name.table_name\" does not exist, skipping".to_owned())
+            )
+        )
+    }
+
+    #[test]
+    fn parameter_changes() {
+        assert_eq!(
+            QueryResultMapper::parameters(vec![("TimeZone".to_owned(), "Europe/Kiev".to_owned())]),
+            vec![Message::ParameterStatus(
+                "TimeZone".to_owned(),
+                "Europe/Kiev".to_owned()
+            )]
+        )
+    }
+
+    #[test]
+    fn checkpoint() {
+        assert_eq!(
+            QueryResultMapper::map(Ok(QueryEvent::Checkpoint)),
+            vec![Message::CommandComplete("CHECKPOINT".to_owned())]
+        )
+    }
+
     #[test]
     fn schema_already_exists() {
         let schema_name = "some_table_name".to_owned();
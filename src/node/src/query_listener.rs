@@ -13,20 +13,44 @@
 // limitations under the License.
 
 use async_trait::async_trait;
-use protocol::{listener::Secure, QueryListener, ServerListener};
+use protocol::{hba::HostBasedAuthentication, listener::Secure, QueryListener, ServerListener};
 use smol::Async;
+use socket2::Socket;
 use std::{
     io,
     net::{SocketAddr, TcpListener, TcpStream},
+    time::Duration,
 };
 
+/// Options of accepted client connections
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConnectionSettings {
+    /// disables Nagle's algorithm, so small responses are not delayed
+    pub nodelay: bool,
+    /// idle time after which TCP keepalive probes detect half-open connections
+    pub keepalive: Option<Duration>,
+    /// time a client can stay idle between queries before it is disconnected
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> ConnectionSettings {
+        ConnectionSettings {
+            nodelay: true,
+            keepalive: Some(Duration::from_secs(60)),
+            idle_timeout: None,
+        }
+    }
+}
+
 pub struct SmolServerListener {
     inner: Async<TcpListener>,
+    settings: ConnectionSettings,
 }
 
 impl SmolServerListener {
-    fn new(inner: Async<TcpListener>) -> SmolServerListener {
-        SmolServerListener { inner }
+    fn new(inner: Async<TcpListener>, settings: ConnectionSettings) -> SmolServerListener {
+        SmolServerListener { inner, settings }
     }
 }
 
@@ -35,23 +59,45 @@ impl ServerListener for SmolServerListener {
     type Channel = Async<TcpStream>;
 
     async fn channel(&self) -> io::Result<(Self::Channel, SocketAddr)> {
-        self.inner.accept().await
+        let (channel, address) = self.inner.accept().await?;
+        configure(channel.get_ref(), &self.settings)?;
+        Ok((channel, address))
     }
 }
 
+fn configure(stream: &TcpStream, settings: &ConnectionSettings) -> io::Result<()> {
+    stream.set_nodelay(settings.nodelay)?;
+    // the clone shares the socket with the stream, so options are set for both
+    Socket::from(stream.try_clone()?).set_keepalive(settings.keepalive)
+}
+
 pub struct SmolQueryListener {
     listener: SmolServerListener,
     secure: Secure,
+    authentication: HostBasedAuthentication,
 }
 
 impl SmolQueryListener {
-    pub async fn bind<A: ToString>(addr: A, secure: Secure) -> io::Result<SmolQueryListener> {
+    pub async fn bind<A: ToString>(
+        addr: A,
+        secure: Secure,
+        authentication: HostBasedAuthentication,
+        settings: ConnectionSettings,
+    ) -> io::Result<SmolQueryListener> {
         let listener = Async::<TcpListener>::bind(addr)?;
-        Ok(SmolQueryListener::new(SmolServerListener::new(listener), secure))
+        Ok(SmolQueryListener::new(
+            SmolServerListener::new(listener, settings),
+            secure,
+            authentication,
+        ))
     }
 
-    fn new(listener: SmolServerListener, secure: Secure) -> SmolQueryListener {
-        SmolQueryListener { listener, secure }
+    fn new(listener: SmolServerListener, secure: Secure, authentication: HostBasedAuthentication) -> SmolQueryListener {
+        SmolQueryListener {
+            listener,
+            secure,
+            authentication,
+        }
     }
 }
 
@@ -67,4 +113,33 @@ impl QueryListener for SmolQueryListener {
     fn secure(&self) -> &Secure {
         &self.secure
     }
+
+    fn authentication(&self) -> &HostBasedAuthentication {
+        &self.authentication
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepted_connection_is_configured() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("listener is bound");
+        let _client = TcpStream::connect(listener.local_addr().expect("local address")).expect("client connected");
+        let (stream, _address) = listener.accept().expect("connection accepted");
+        let settings = ConnectionSettings {
+            nodelay: true,
+            keepalive: Some(Duration::from_secs(30)),
+            idle_timeout: None,
+        };
+
+        configure(&stream, &settings).expect("connection configured");
+
+        assert!(stream.nodelay().expect("nodelay"));
+        assert_eq!(
+            Socket::from(stream).keepalive().expect("keepalive"),
+            Some(Duration::from_secs(30))
+        );
+    }
 }
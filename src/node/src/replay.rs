@@ -0,0 +1,314 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! re-executes a workload captured with `database --capture <capture file>` against a server
+//!
+//! usage: `cargo run -p node --bin replay -- <server address> <capture file> [<speed>]`
+//!
+//! every captured session is replayed over its own connection. Events are sent at
+//! their captured moments divided by `speed` (`1` by default), `max` sends them
+//! without waiting. Cleartext password is taken from `PGPASSWORD` environment variable.
+
+extern crate node;
+
+use node::capture::{read_events, CapturedEvent, Event};
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    net::TcpStream,
+    process, thread,
+    time::{Duration, Instant},
+};
+
+const PROTOCOL_VERSION: i32 = 196_608;
+const AUTHENTICATION: u8 = b'R';
+const ERROR_RESPONSE: u8 = b'E';
+const READY_FOR_QUERY: u8 = b'Z';
+const AUTHENTICATION_OK: i32 = 0;
+const CLEARTEXT_PASSWORD: i32 = 3;
+
+fn main() -> io::Result<()> {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let (server, file, speed) = match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        [server, file] => (server.to_string(), file.to_string(), Some(1.0)),
+        [server, file, "max"] => (server.to_string(), file.to_string(), None),
+        [server, file, speed] => match speed.parse::<f64>() {
+            Ok(speed) if speed > 0.0 => (server.to_string(), file.to_string(), Some(speed)),
+            _ => usage(),
+        },
+        _ => usage(),
+    };
+
+    let mut sessions = BTreeMap::<u64, Vec<CapturedEvent>>::new();
+    for captured in read_events(BufReader::new(File::open(file)?))? {
+        sessions.entry(captured.session_id).or_default().push(captured);
+    }
+
+    let started = Instant::now();
+    let replays = sessions
+        .into_iter()
+        .map(|(session_id, events)| {
+            let server = server.clone();
+            thread::spawn(move || (session_id, replay(&server, events, started, speed)))
+        })
+        .collect::<Vec<_>>();
+
+    let mut total = Summary::default();
+    let mut failed = false;
+    for replay in replays {
+        match replay.join().expect("replay thread does not panic") {
+            (_, Ok(summary)) => total.add(&summary),
+            (session_id, Err(error)) => {
+                eprintln!("session {} failed: {}", session_id, error);
+                failed = true;
+            }
+        }
+    }
+    println!(
+        "replayed {} statements in {:?}, {} of them failed",
+        total.statements,
+        started.elapsed(),
+        total.errors
+    );
+    println!(
+        "statements took {:?} when captured and {:?} when replayed",
+        total.captured, total.replayed
+    );
+    if failed {
+        process::exit(1)
+    }
+    Ok(())
+}
+
+fn usage() -> ! {
+    eprintln!("usage: replay <server address> <capture file> [<speed>|max]");
+    process::exit(2)
+}
+
+#[derive(Default)]
+struct Summary {
+    statements: usize,
+    errors: usize,
+    captured: Duration,
+    replayed: Duration,
+}
+
+impl Summary {
+    fn add(&mut self, other: &Summary) {
+        self.statements += other.statements;
+        self.errors += other.errors;
+        self.captured += other.captured;
+        self.replayed += other.replayed;
+    }
+}
+
+fn replay(server: &str, events: Vec<CapturedEvent>, started: Instant, speed: Option<f64>) -> io::Result<Summary> {
+    let mut summary = Summary::default();
+    let mut connection = None;
+    for captured in events {
+        if let Some(speed) = speed {
+            let at = started + captured.elapsed.div_f64(speed);
+            let now = Instant::now();
+            if at > now {
+                thread::sleep(at - now);
+            }
+        }
+        summary.captured += captured.duration;
+        let sent = Instant::now();
+        match captured.event {
+            Event::Connect { user, database } => {
+                connection = Some(Connection::open(server, &user, &database)?);
+                continue;
+            }
+            Event::Disconnect => {
+                if let Some(mut connection) = connection.take() {
+                    connection.send(b'X', &[])?;
+                }
+                continue;
+            }
+            event => {
+                let connection = match connection.as_mut() {
+                    Some(connection) => connection,
+                    None => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "event is captured before connection",
+                        ))
+                    }
+                };
+                match event {
+                    Event::Query(sql) => {
+                        let mut body = vec![];
+                        cstring(&mut body, &sql);
+                        connection.send(b'Q', &body)?;
+                        summary.statements += 1;
+                        summary.errors += connection.wait_ready()?;
+                    }
+                    Event::Parse {
+                        statement_name,
+                        sql,
+                        param_types,
+                    } => {
+                        let mut body = vec![];
+                        cstring(&mut body, &statement_name);
+                        cstring(&mut body, &sql);
+                        body.extend_from_slice(&(param_types.len() as i16).to_be_bytes());
+                        for param_type in param_types {
+                            body.extend_from_slice(&param_type.to_be_bytes());
+                        }
+                        connection.send(b'P', &body)?;
+                    }
+                    Event::Bind {
+                        portal_name,
+                        statement_name,
+                        param_formats,
+                        params,
+                        result_formats,
+                    } => {
+                        let mut body = vec![];
+                        cstring(&mut body, &portal_name);
+                        cstring(&mut body, &statement_name);
+                        body.extend_from_slice(&(param_formats.len() as i16).to_be_bytes());
+                        for param_format in param_formats {
+                            body.extend_from_slice(&param_format.to_be_bytes());
+                        }
+                        body.extend_from_slice(&(params.len() as i16).to_be_bytes());
+                        for param in params {
+                            match param {
+                                Some(value) => {
+                                    body.extend_from_slice(&(value.len() as i32).to_be_bytes());
+                                    body.extend_from_slice(&value);
+                                }
+                                None => body.extend_from_slice(&(-1i32).to_be_bytes()),
+                            }
+                        }
+                        body.extend_from_slice(&(result_formats.len() as i16).to_be_bytes());
+                        for result_format in result_formats {
+                            body.extend_from_slice(&result_format.to_be_bytes());
+                        }
+                        connection.send(b'B', &body)?;
+                    }
+                    Event::Execute { portal_name, max_rows } => {
+                        let mut body = vec![];
+                        cstring(&mut body, &portal_name);
+                        body.extend_from_slice(&max_rows.to_be_bytes());
+                        connection.send(b'E', &body)?;
+                        summary.statements += 1;
+                    }
+                    Event::CloseStatement(name) => {
+                        let mut body = vec![b'S'];
+                        cstring(&mut body, &name);
+                        connection.send(b'C', &body)?;
+                    }
+                    Event::ClosePortal(name) => {
+                        let mut body = vec![b'P'];
+                        cstring(&mut body, &name);
+                        connection.send(b'C', &body)?;
+                    }
+                    Event::Sync => {
+                        connection.send(b'S', &[])?;
+                        summary.errors += connection.wait_ready()?;
+                    }
+                    Event::Connect { .. } | Event::Disconnect => unreachable!(),
+                }
+            }
+        }
+        summary.replayed += sent.elapsed();
+    }
+    Ok(summary)
+}
+
+fn cstring(body: &mut Vec<u8>, value: &str) {
+    body.extend_from_slice(value.as_bytes());
+    body.push(0);
+}
+
+struct Connection {
+    stream: TcpStream,
+}
+
+impl Connection {
+    fn open(server: &str, user: &str, database: &str) -> io::Result<Connection> {
+        let mut connection = Connection {
+            stream: TcpStream::connect(server)?,
+        };
+        let mut body = PROTOCOL_VERSION.to_be_bytes().to_vec();
+        cstring(&mut body, "user");
+        cstring(&mut body, user);
+        cstring(&mut body, "database");
+        cstring(&mut body, database);
+        body.push(0);
+        connection.stream.write_all(&(body.len() as i32 + 4).to_be_bytes())?;
+        connection.stream.write_all(&body)?;
+
+        loop {
+            let (tag, body) = connection.receive()?;
+            match tag {
+                AUTHENTICATION => match i32::from_be_bytes([body[0], body[1], body[2], body[3]]) {
+                    AUTHENTICATION_OK => {}
+                    CLEARTEXT_PASSWORD => {
+                        let mut password = vec![];
+                        cstring(&mut password, &env::var("PGPASSWORD").unwrap_or_default());
+                        connection.send(b'p', &password)?;
+                    }
+                    method => {
+                        return Err(io::Error::other(format!(
+                            "authentication method {} is not supported",
+                            method
+                        )))
+                    }
+                },
+                ERROR_RESPONSE => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionRefused,
+                        "server rejected connection",
+                    ))
+                }
+                READY_FOR_QUERY => return Ok(connection),
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&mut self, tag: u8, body: &[u8]) -> io::Result<()> {
+        let mut message = Vec::with_capacity(body.len() + 5);
+        message.push(tag);
+        message.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+        message.extend_from_slice(body);
+        self.stream.write_all(&message)
+    }
+
+    fn receive(&mut self) -> io::Result<(u8, Vec<u8>)> {
+        let mut header = [0; 5];
+        self.stream.read_exact(&mut header)?;
+        let len = i32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        let mut body = vec![0; (len as usize).saturating_sub(4)];
+        self.stream.read_exact(&mut body)?;
+        Ok((header[0], body))
+    }
+
+    /// returns number of errors server responded with
+    fn wait_ready(&mut self) -> io::Result<usize> {
+        let mut errors = 0;
+        loop {
+            match self.receive()? {
+                (ERROR_RESPONSE, _) => errors += 1,
+                (READY_FOR_QUERY, _) => return Ok(errors),
+                _ => {}
+            }
+        }
+    }
+}
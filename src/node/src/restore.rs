@@ -0,0 +1,296 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! executes plain sql file that is made with `pg_dump` statement by statement.
+//!
+//! settings of a session, comments of objects and their ownership have nothing
+//! to apply to, so `SET`, `COMMENT ON` and `ALTER ... OWNER TO` statements are
+//! skipped with notices. Data of `COPY ... FROM stdin` is skipped as well, data
+//! has to be dumped with `--inserts` to be restored. Restore stops at the first
+//! statement that fails, statements before it are left applied.
+
+use crate::migrate::statements;
+use kernel::SystemError;
+use sql_engine::{Handler, QueryError};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+};
+use storage::backend::BackendStorage;
+
+#[derive(Debug)]
+pub enum RestoreError {
+    Io(io::Error),
+    System(SystemError),
+    Failed {
+        statement: String,
+        error: Box<QueryError>,
+        // number of statements that were executed before the failed one
+        executed: usize,
+    },
+}
+
+impl From<io::Error> for RestoreError {
+    fn from(error: io::Error) -> RestoreError {
+        RestoreError::Io(error)
+    }
+}
+
+impl From<SystemError> for RestoreError {
+    fn from(error: SystemError) -> RestoreError {
+        RestoreError::System(error)
+    }
+}
+
+impl Display for RestoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RestoreError::Io(error) => write!(f, "dump can't be read due to {}", error),
+            RestoreError::System(error) => write!(f, "dump can't be restored due to {}", error),
+            RestoreError::Failed {
+                statement,
+                error,
+                executed,
+            } => write!(
+                f,
+                "\"{}\" failed: {}, {} statements before it are left applied",
+                statement, error, executed
+            ),
+        }
+    }
+}
+
+/// Statements of a dump that are executed and notices about what is skipped
+#[derive(Debug, Default, PartialEq)]
+pub struct Restored {
+    pub executed: usize,
+    pub notices: Vec<String>,
+}
+
+/// restores the dump file and returns what is done
+pub fn restore<P: BackendStorage>(handler: &mut Handler<P>, dump: &Path) -> Result<Restored, RestoreError> {
+    let sql = fs::read_to_string(dump)?;
+    let mut restored = Restored::default();
+    let sql = without_copy_data(&sql, &mut restored.notices);
+    for statement in statements(&sql) {
+        let statement = code(statement);
+        if is_ignorable(statement) {
+            restored.notices.push(format!("\"{}\" is skipped", statement));
+            continue;
+        }
+        let executed = handler.execute(statement)?;
+        restored
+            .notices
+            .extend(handler.take_notices().into_iter().map(|notice| notice.to_string()));
+        if let Err(error) = executed {
+            return Err(RestoreError::Failed {
+                statement: statement.to_owned(),
+                error: Box::new(error),
+                executed: restored.executed,
+            });
+        }
+        restored.executed += 1;
+    }
+    Ok(restored)
+}
+
+/// drops lines of `COPY ... FROM stdin` statements along with their data that
+/// ends with `\.` line
+fn without_copy_data(sql: &str, notices: &mut Vec<String>) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut copy = false;
+    for line in sql.lines() {
+        if copy {
+            copy = line != "\\.";
+            continue;
+        }
+        let lowercase = line.trim().to_lowercase();
+        if lowercase.starts_with("copy ") && lowercase.ends_with("from stdin;") {
+            notices.push(format!(
+                "data of \"{}\" is skipped, dump it with --inserts",
+                line.trim()
+            ));
+            copy = true;
+            continue;
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+/// statement without comments that precede it
+fn code(statement: &str) -> &str {
+    let mut code = statement.trim_start();
+    loop {
+        if code.starts_with("--") {
+            code = code.find('\n').map(|end| &code[end..]).unwrap_or_default();
+        } else if code.starts_with("/*") {
+            code = code.find("*/").map(|end| &code[end + 2..]).unwrap_or_default();
+        } else {
+            return code;
+        }
+        code = code.trim_start();
+    }
+}
+
+fn is_ignorable(statement: &str) -> bool {
+    let words = statement
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    words.starts_with("set ")
+        || words.starts_with("select pg_catalog.set_config(")
+        || words.starts_with("comment on ")
+        || (words.starts_with("alter ") && words.contains(" owner to "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sql_engine::QueryEvent;
+    use std::{
+        path::PathBuf,
+        sync::{Arc, RwLock},
+    };
+    use storage::frontend::FrontendStorage;
+    use test_helpers::in_memory_backend_storage::InMemoryStorage;
+
+    const DUMP: &str = "--\n\
+        -- PostgreSQL database dump\n\
+        --\n\
+        \n\
+        SET statement_timeout = 0;\n\
+        SET client_encoding = 'UTF8';\n\
+        SELECT pg_catalog.set_config('search_path', '', false);\n\
+        \n\
+        --\n\
+        -- Name: accounts; Type: SCHEMA; Schema: -; Owner: postgres\n\
+        --\n\
+        \n\
+        CREATE SCHEMA accounts;\n\
+        \n\
+        \n\
+        ALTER SCHEMA accounts OWNER TO postgres;\n\
+        \n\
+        --\n\
+        -- Name: balances; Type: TABLE; Schema: accounts; Owner: postgres\n\
+        --\n\
+        \n\
+        CREATE TABLE accounts.balances (\n    \
+            id integer NOT NULL,\n    \
+            name character varying(50),\n    \
+            amount bigint\n\
+        );\n\
+        \n\
+        \n\
+        ALTER TABLE accounts.balances OWNER TO postgres;\n\
+        \n\
+        --\n\
+        -- Name: TABLE balances; Type: COMMENT; Schema: accounts; Owner: postgres\n\
+        --\n\
+        \n\
+        COMMENT ON TABLE accounts.balances IS 'balances; of accounts';\n\
+        \n\
+        --\n\
+        -- Data for Name: balances; Type: TABLE DATA; Schema: accounts; Owner: postgres\n\
+        --\n\
+        \n\
+        COPY accounts.balances (id, name, amount) FROM stdin;\n\
+        1\tfirst\t100\n\
+        2\tsecond; with semicolon\t200\n\
+        \\.\n\
+        \n\
+        \n\
+        INSERT INTO accounts.balances VALUES (3, 'third', 300);\n\
+        \n\
+        \n\
+        --\n\
+        -- PostgreSQL database dump complete\n\
+        --\n\
+        \n";
+
+    fn handler() -> Handler<InMemoryStorage> {
+        Handler::new(Arc::new(RwLock::new(
+            FrontendStorage::new(InMemoryStorage::default()).expect("storage"),
+        )))
+    }
+
+    fn dump(name: &str, sql: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("database_dump_{}_{}.sql", name, std::process::id()));
+        fs::write(&path, sql).expect("dump is written");
+        path
+    }
+
+    #[test]
+    fn dump_is_restored() {
+        let mut handler = handler();
+
+        let restored = restore(&mut handler, &dump("restored", DUMP)).expect("restored");
+
+        assert_eq!(
+            restored,
+            Restored {
+                executed: 3,
+                notices: vec![
+                    "data of \"COPY accounts.balances (id, name, amount) FROM stdin;\" is skipped, dump it with --inserts"
+                        .to_owned(),
+                    "\"SET statement_timeout = 0;\" is skipped".to_owned(),
+                    "\"SET client_encoding = 'UTF8';\" is skipped".to_owned(),
+                    "\"SELECT pg_catalog.set_config('search_path', '', false);\" is skipped".to_owned(),
+                    "\"ALTER SCHEMA accounts OWNER TO postgres;\" is skipped".to_owned(),
+                    "\"ALTER TABLE accounts.balances OWNER TO postgres;\" is skipped".to_owned(),
+                    "\"COMMENT ON TABLE accounts.balances IS 'balances; of accounts';\" is skipped".to_owned(),
+                ]
+            }
+        );
+        match handler
+            .execute("select id, name, amount from accounts.balances;")
+            .expect("no system errors")
+        {
+            Ok(QueryEvent::RecordsSelected((_columns, records))) => assert_eq!(
+                records,
+                vec![vec!["3".to_owned(), "third".to_owned(), "300".to_owned()]]
+            ),
+            other => panic!("{:?} is not records", other),
+        }
+    }
+
+    #[test]
+    fn restore_stops_at_failed_statement() {
+        let mut handler = handler();
+
+        match restore(
+            &mut handler,
+            &dump(
+                "failed",
+                "CREATE SCHEMA accounts;\n\
+                 -- Name: balances; Type: TABLE\n\
+                 CREATE TABLE accounts.balances (id integer);\n\
+                 CREATE TABLE accounts.balances (id integer);\n\
+                 CREATE TABLE accounts.history (id integer);\n",
+            ),
+        ) {
+            Err(RestoreError::Failed {
+                statement, executed, ..
+            }) => {
+                assert_eq!(statement, "CREATE TABLE accounts.balances (id integer);");
+                assert_eq!(executed, 2);
+            }
+            other => panic!("{:?} is not failed restore", other),
+        }
+    }
+}
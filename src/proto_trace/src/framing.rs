@@ -0,0 +1,151 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use protocol::{Version, VERSION_GSSENC, VERSION_SSL};
+use std::convert::TryInto;
+
+/// splits a byte stream of one connection direction into protocol messages
+pub struct Framer {
+    buffer: Vec<u8>,
+    typed: bool,
+}
+
+impl Framer {
+    /// messages that client sends start with untyped ssl, gss or startup request
+    pub fn client() -> Self {
+        Self {
+            buffer: vec![],
+            typed: false,
+        }
+    }
+
+    /// messages that server sends always start with a tag byte
+    pub fn server() -> Self {
+        Self {
+            buffer: vec![],
+            typed: true,
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// returns the next complete message if it was fully received
+    pub fn next_message(&mut self) -> Option<Vec<u8>> {
+        let header = if self.typed { 5 } else { 4 };
+        if self.buffer.len() < header {
+            return None;
+        }
+        let len = i32::from_be_bytes(self.buffer[header - 4..header].try_into().unwrap());
+        let size = header - 4 + len.max(4) as usize;
+        if self.buffer.len() < size {
+            return None;
+        }
+        let message: Vec<u8> = self.buffer.drain(..size).collect();
+        if !self.typed && !is_encryption_request(&message) {
+            self.typed = true;
+        }
+        Some(message)
+    }
+
+    /// bytes that were received but do not form a complete message yet
+    pub fn remaining(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+/// whether a message is an untyped `ssl` or `gss` encryption request
+pub fn is_encryption_request(message: &[u8]) -> bool {
+    message.len() == 8 && {
+        let code = Version::from_be_bytes(message[4..8].try_into().unwrap());
+        code == VERSION_SSL || code == VERSION_GSSENC
+    }
+}
+
+/// human readable name of a message for reports
+pub fn describe(message: &[u8]) -> String {
+    match message.first() {
+        Some(tag) if tag.is_ascii_alphabetic() => format!("'{}' ({} bytes)", *tag as char, message.len()),
+        _ => format!("untyped ({} bytes)", message.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::VERSION_3;
+
+    fn untyped(code: Version, payload: &[u8]) -> Vec<u8> {
+        let mut message = ((payload.len() + 8) as i32).to_be_bytes().to_vec();
+        message.extend_from_slice(&code.to_be_bytes());
+        message.extend_from_slice(payload);
+        message
+    }
+
+    fn typed(tag: u8, payload: &[u8]) -> Vec<u8> {
+        let mut message = vec![tag];
+        message.extend_from_slice(&((payload.len() + 4) as i32).to_be_bytes());
+        message.extend_from_slice(payload);
+        message
+    }
+
+    #[test]
+    fn incomplete_message() {
+        let mut framer = Framer::server();
+        let message = typed(b'Z', b"I");
+
+        framer.push(&message[..3]);
+        assert_eq!(framer.next_message(), None);
+
+        framer.push(&message[3..]);
+        assert_eq!(framer.next_message(), Some(message));
+        assert_eq!(framer.remaining(), &[] as &[u8]);
+    }
+
+    #[test]
+    fn several_messages_in_one_chunk() {
+        let mut framer = Framer::server();
+        let first = typed(b'C', b"SELECT 1\0");
+        let second = typed(b'Z', b"I");
+
+        framer.push(&[first.clone(), second.clone()].concat());
+
+        assert_eq!(framer.next_message(), Some(first));
+        assert_eq!(framer.next_message(), Some(second));
+        assert_eq!(framer.next_message(), None);
+    }
+
+    #[test]
+    fn client_handshake() {
+        let mut framer = Framer::client();
+        let ssl = untyped(VERSION_SSL, &[]);
+        let startup = untyped(VERSION_3, b"user\0username\0\0");
+        let query = typed(b'Q', b"select 1;\0");
+
+        framer.push(&[ssl.clone(), startup.clone(), query.clone()].concat());
+
+        let message = framer.next_message().unwrap();
+        assert!(is_encryption_request(&message));
+        assert_eq!(message, ssl);
+        assert_eq!(framer.next_message(), Some(startup));
+        assert_eq!(framer.next_message(), Some(query));
+    }
+
+    #[test]
+    fn describe_messages() {
+        assert_eq!(describe(&typed(b'Z', b"I")), "'Z' (6 bytes)");
+        assert_eq!(describe(&untyped(VERSION_SSL, &[])), "untyped (8 bytes)");
+    }
+}
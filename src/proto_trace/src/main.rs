@@ -0,0 +1,208 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! records message exchanges between a client and the server and replays them
+//! to reproduce driver specific protocol issues
+//!
+//! usage: `cargo run -p proto_trace -- record <listen address> <server address> <trace directory>`
+//!        `cargo run -p proto_trace -- replay <server address> <trace file>`
+//!
+//! `record` accepts client connections, forwards them to the server and writes messages of
+//! every connection into `<trace directory>/<connection number>.trace`.
+//! `ssl` and `gss` encryption requests are declined so that clients fall back to plain text.
+//! `replay` sends client messages of a trace to the server and compares server responses
+//! with recorded ones.
+
+mod framing;
+mod trace;
+
+use crate::{
+    framing::{describe, is_encryption_request, Framer},
+    trace::{read_records, write_record, Direction, Record},
+};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+const BACKEND_KEY_DATA: u8 = b'K';
+
+fn main() -> io::Result<()> {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["record", listen, server, directory] => record(listen, server, PathBuf::from(directory)),
+        ["replay", server, file] => {
+            if replay(server, Path::new(file))? {
+                Ok(())
+            } else {
+                process::exit(1)
+            }
+        }
+        _ => {
+            eprintln!("usage: proto-trace record <listen address> <server address> <trace directory>");
+            eprintln!("       proto-trace replay <server address> <trace file>");
+            process::exit(2)
+        }
+    }
+}
+
+fn record(listen: &str, server: &str, directory: PathBuf) -> io::Result<()> {
+    fs::create_dir_all(&directory)?;
+    let listener = TcpListener::bind(listen)?;
+    println!("recording connections to {} from {}", server, listen);
+    for (id, client) in listener.incoming().enumerate() {
+        let client = client?;
+        let server = server.to_owned();
+        let path = directory.join(format!("{}.trace", id));
+        thread::spawn(move || match proxy(client, &server, &path) {
+            Ok(()) => println!("connection {} is recorded to {}", id, path.display()),
+            Err(error) => eprintln!("connection {} failed: {}", id, error),
+        });
+    }
+    Ok(())
+}
+
+fn proxy(client: TcpStream, server: &str, path: &Path) -> io::Result<()> {
+    let server = TcpStream::connect(server)?;
+    let trace = Arc::new(Mutex::new(File::create(path)?));
+    let started = Instant::now();
+
+    let responses = {
+        let (server, client, trace) = (server.try_clone()?, client.try_clone()?, trace.clone());
+        thread::spawn(move || forward(server, client, Framer::server(), Direction::ToClient, &trace, started))
+    };
+    let requests = forward(client, server, Framer::client(), Direction::ToServer, &trace, started);
+    let responses = responses.join().expect("forwarding thread does not panic");
+    requests.and(responses)
+}
+
+fn forward(
+    mut source: TcpStream,
+    mut destination: TcpStream,
+    mut framer: Framer,
+    direction: Direction,
+    trace: &Mutex<File>,
+    started: Instant,
+) -> io::Result<()> {
+    let mut buffer = [0; 8192];
+    loop {
+        let read = source.read(&mut buffer)?;
+        if read == 0 {
+            destination.write_all(framer.remaining())?;
+            // the other side could be already gone
+            let _ = destination.shutdown(Shutdown::Write);
+            return Ok(());
+        }
+        framer.push(&buffer[..read]);
+        while let Some(message) = framer.next_message() {
+            if direction == Direction::ToServer && is_encryption_request(&message) {
+                source.write_all(b"N")?;
+                continue;
+            }
+            let record = Record {
+                direction,
+                elapsed: started.elapsed(),
+                message,
+            };
+            write_record(&mut *trace.lock().expect("trace file lock"), &record)?;
+            destination.write_all(&record.message)?;
+        }
+    }
+}
+
+/// returns `false` if server responses differ from recorded ones
+fn replay(server: &str, path: &Path) -> io::Result<bool> {
+    let records = read_records(BufReader::new(File::open(path)?))?;
+    let mut stream = TcpStream::connect(server)?;
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+    let mut framer = Framer::server();
+    let mut mismatches = 0;
+    let started = Instant::now();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    for (index, expected) in records.iter().enumerate() {
+        match expected.direction {
+            Direction::ToServer => stream.write_all(&expected.message)?,
+            Direction::ToClient => match receive(&mut stream, &mut framer) {
+                Ok(Some(message)) => {
+                    if !same_response(&expected.message, &message) {
+                        mismatches += 1;
+                        writeln!(stdout, "# message {} differs, expected:", index + 1)?;
+                        write_record(&mut stdout, expected)?;
+                        writeln!(stdout, "# actual:")?;
+                        let actual = Record {
+                            direction: Direction::ToClient,
+                            elapsed: started.elapsed(),
+                            message,
+                        };
+                        write_record(&mut stdout, &actual)?;
+                    }
+                }
+                Ok(None) => {
+                    writeln!(
+                        stdout,
+                        "# server closed connection, expected message {}: {}",
+                        index + 1,
+                        describe(&expected.message)
+                    )?;
+                    return Ok(false);
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut => {
+                    writeln!(
+                        stdout,
+                        "# server did not respond, expected message {}: {}",
+                        index + 1,
+                        describe(&expected.message)
+                    )?;
+                    return Ok(false);
+                }
+                Err(error) => return Err(error),
+            },
+        }
+    }
+
+    writeln!(stdout, "# replayed {} messages, {} differ", records.len(), mismatches)?;
+    Ok(mismatches == 0)
+}
+
+fn receive(stream: &mut TcpStream, framer: &mut Framer) -> io::Result<Option<Vec<u8>>> {
+    let mut buffer = [0; 8192];
+    loop {
+        if let Some(message) = framer.next_message() {
+            return Ok(Some(message));
+        }
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        framer.push(&buffer[..read]);
+    }
+}
+
+/// backend key data is generated for every connection so only its size is compared
+fn same_response(expected: &[u8], actual: &[u8]) -> bool {
+    match expected.first() {
+        Some(&BACKEND_KEY_DATA) => expected.len() == actual.len() && actual.first() == Some(&BACKEND_KEY_DATA),
+        _ => expected == actual,
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! trace file is a text file with a message per line:
+//! `<direction> <microseconds since connection start> <hex encoded message>`
+//! where direction is `>` for client to server and `<` for server to client messages.
+//! Empty lines and lines that start with `#` are ignored.
+
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    ToServer,
+    ToClient,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::ToServer => ">",
+            Direction::ToClient => "<",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub direction: Direction,
+    pub elapsed: Duration,
+    pub message: Vec<u8>,
+}
+
+pub fn write_record<W: Write>(output: &mut W, record: &Record) -> io::Result<()> {
+    let mut hex = String::with_capacity(record.message.len() * 2);
+    for byte in record.message.iter() {
+        write!(hex, "{:02x}", byte).expect("writing to string does not fail");
+    }
+    writeln!(
+        output,
+        "{} {} {}",
+        record.direction.as_str(),
+        record.elapsed.as_micros(),
+        hex
+    )?;
+    output.flush()
+}
+
+pub fn read_records<R: BufRead>(input: R) -> io::Result<Vec<Record>> {
+    let mut records = vec![];
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_record(line) {
+            Some(record) => records.push(record),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed record at line {}: {}", index + 1, line),
+                ))
+            }
+        }
+    }
+    Ok(records)
+}
+
+fn parse_record(line: &str) -> Option<Record> {
+    let mut parts = line.split_whitespace();
+    let direction = match parts.next()? {
+        ">" => Direction::ToServer,
+        "<" => Direction::ToClient,
+        _ => return None,
+    };
+    let elapsed = Duration::from_micros(parts.next()?.parse().ok()?);
+    let hex = parts.next()?;
+    if parts.next().is_some() || hex.len() % 2 != 0 {
+        return None;
+    }
+    let mut message = Vec::with_capacity(hex.len() / 2);
+    for index in (0..hex.len()).step_by(2) {
+        message.push(u8::from_str_radix(hex.get(index..index + 2)?, 16).ok()?);
+    }
+    Some(Record {
+        direction,
+        elapsed,
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_records() {
+        let records = vec![
+            Record {
+                direction: Direction::ToServer,
+                elapsed: Duration::from_micros(0),
+                message: b"Q\x00\x00\x00\x0eselect 1;\x00".to_vec(),
+            },
+            Record {
+                direction: Direction::ToClient,
+                elapsed: Duration::from_micros(1234),
+                message: b"Z\x00\x00\x00\x05I".to_vec(),
+            },
+        ];
+
+        let mut output = vec![];
+        for record in records.iter() {
+            write_record(&mut output, record).expect("written");
+        }
+
+        assert_eq!(
+            String::from_utf8(output.clone()).expect("utf8"),
+            "> 0 510000000e73656c65637420313b00\n< 1234 5a0000000549\n"
+        );
+        assert_eq!(read_records(output.as_slice()).expect("read"), records);
+    }
+
+    #[test]
+    fn comments_and_empty_lines_are_skipped() {
+        let input = "# recorded session\n\n< 10 5a0000000549\n";
+
+        assert_eq!(
+            read_records(input.as_bytes()).expect("read"),
+            vec![Record {
+                direction: Direction::ToClient,
+                elapsed: Duration::from_micros(10),
+                message: b"Z\x00\x00\x00\x05I".to_vec(),
+            }]
+        );
+    }
+
+    #[test]
+    fn malformed_record() {
+        let error = read_records("> 0 5a0\n".as_bytes()).expect_err("malformed");
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "malformed record at line 1: > 0 5a0");
+    }
+}
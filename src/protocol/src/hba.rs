@@ -0,0 +1,370 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    net::IpAddr,
+    sync::{Arc, RwLock},
+};
+
+/// Method that client has to use to authenticate itself
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthMethod {
+    /// Client is allowed to connect without a password
+    Trust,
+    /// Client has to send a password in clear-text form
+    Password,
+    /// Client has to go through `SCRAM-SHA-256` exchange
+    Scram,
+    /// Client is not allowed to connect
+    Reject,
+}
+
+/// Range of client addresses in `CIDR` notation
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl AddressRange {
+    /// Creates range of addresses that share first `prefix_len` bits with `network`
+    pub fn new(network: IpAddr, prefix_len: u8) -> AddressRange {
+        let max_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        AddressRange {
+            network,
+            prefix_len: prefix_len.min(max_len),
+        }
+    }
+
+    /// returns `true` if `address` belongs to the range
+    pub fn contains(&self, address: IpAddr) -> bool {
+        match (self.network, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Rule that assigns authentication method to clients that connect from an
+/// address range to a database as a user. `None` database or user matches any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    addresses: Option<AddressRange>,
+    database: Option<String>,
+    user: Option<String>,
+    method: AuthMethod,
+}
+
+impl Rule {
+    /// Creates rule, `None` matches any address, database or user
+    pub fn new(
+        addresses: Option<AddressRange>,
+        database: Option<&str>,
+        user: Option<&str>,
+        method: AuthMethod,
+    ) -> Rule {
+        Rule {
+            addresses,
+            database: database.map(ToOwned::to_owned),
+            user: user.map(ToOwned::to_owned),
+            method,
+        }
+    }
+
+    fn matches(&self, address: IpAddr, database: &str, user: &str) -> bool {
+        self.addresses
+            .as_ref()
+            .map(|range| range.contains(address))
+            .unwrap_or(true)
+            && self.database.as_ref().map(|name| name == database).unwrap_or(true)
+            && self.user.as_ref().map(|name| name == user).unwrap_or(true)
+    }
+}
+
+/// Error of parsing host based authentication rules, contains number of a line
+/// where it occurred
+#[derive(Debug, PartialEq)]
+pub enum RuleError {
+    /// Line has less fields than `host database user address method`
+    MissingField(usize),
+    /// Only `host` connections are supported
+    UnsupportedConnectionType(usize, String),
+    /// Address is not in `CIDR` notation
+    InvalidAddress(usize, String),
+    /// Method is neither of `trust`, `password`, `scram-sha-256` or `reject`
+    UnknownMethod(usize, String),
+}
+
+/// Parses rules in `pg_hba.conf` format, one rule per line
+/// `host <database> <user> <address> <method>`, where `all` matches any
+/// database, user or address and `#` starts a comment
+pub fn parse(rules: &str) -> Result<Vec<Rule>, RuleError> {
+    rules
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.split('#').next().unwrap_or_default()))
+        .filter(|(_line, rule)| !rule.trim().is_empty())
+        .map(|(line, rule)| parse_rule(line, rule))
+        .collect()
+}
+
+fn parse_rule(line: usize, rule: &str) -> Result<Rule, RuleError> {
+    let fields = rule.split_whitespace().collect::<Vec<&str>>();
+    if fields.len() < 5 {
+        return Err(RuleError::MissingField(line));
+    }
+    if fields[0] != "host" {
+        return Err(RuleError::UnsupportedConnectionType(line, fields[0].to_owned()));
+    }
+    let any = |name: &str| if name == "all" { None } else { Some(name.to_owned()) };
+    let addresses = if fields[3] == "all" {
+        None
+    } else {
+        Some(parse_address_range(fields[3]).ok_or_else(|| RuleError::InvalidAddress(line, fields[3].to_owned()))?)
+    };
+    let method = match fields[4] {
+        "trust" => AuthMethod::Trust,
+        "password" => AuthMethod::Password,
+        "scram-sha-256" => AuthMethod::Scram,
+        "reject" => AuthMethod::Reject,
+        method => return Err(RuleError::UnknownMethod(line, method.to_owned())),
+    };
+    Ok(Rule {
+        addresses,
+        database: any(fields[1]),
+        user: any(fields[2]),
+        method,
+    })
+}
+
+fn parse_address_range(range: &str) -> Option<AddressRange> {
+    let mut parts = range.splitn(2, '/');
+    let network = parts.next()?.parse::<IpAddr>().ok()?;
+    let max_len = match network {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    };
+    let prefix_len = match parts.next() {
+        Some(prefix_len) => prefix_len.parse::<u8>().ok().filter(|len| *len <= max_len)?,
+        None => max_len,
+    };
+    Some(AddressRange::new(network, prefix_len))
+}
+
+/// Ordered host based authentication rules that could be replaced while
+/// server is accepting connections
+#[derive(Debug, Clone)]
+pub struct HostBasedAuthentication {
+    rules: Arc<RwLock<Vec<Rule>>>,
+}
+
+impl HostBasedAuthentication {
+    /// Creates authentication with rules that are checked in the given order
+    pub fn new(rules: Vec<Rule>) -> HostBasedAuthentication {
+        HostBasedAuthentication {
+            rules: Arc::new(RwLock::new(rules)),
+        }
+    }
+
+    /// Creates authentication that lets any client connect without a password
+    pub fn trust_all() -> HostBasedAuthentication {
+        HostBasedAuthentication::new(vec![Rule::new(None, None, None, AuthMethod::Trust)])
+    }
+
+    /// Replaces rules, connections that are already established are not affected
+    pub fn reload(&self, rules: Vec<Rule>) {
+        *self.rules.write().expect("to acquire write lock") = rules;
+    }
+
+    /// returns method of the first rule that matches client, client is rejected
+    /// if no rules match
+    pub fn method(&self, address: IpAddr, database: &str, user: &str) -> AuthMethod {
+        self.rules
+            .read()
+            .expect("to acquire read lock")
+            .iter()
+            .find(|rule| rule.matches(address, database, user))
+            .map(|rule| rule.method)
+            .unwrap_or(AuthMethod::Reject)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    const REMOTE: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+    #[cfg(test)]
+    mod address_range {
+        use super::*;
+
+        #[test]
+        fn ipv4_range() {
+            let range = AddressRange::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8);
+
+            assert!(range.contains(REMOTE));
+            assert!(!range.contains(LOCALHOST));
+        }
+
+        #[test]
+        fn ipv6_range() {
+            let range = AddressRange::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 128);
+
+            assert!(range.contains(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+            assert!(!range.contains(LOCALHOST));
+        }
+
+        #[test]
+        fn zero_prefix_matches_any_address_of_the_same_family() {
+            let range = AddressRange::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+            assert!(range.contains(REMOTE));
+            assert!(range.contains(LOCALHOST));
+        }
+    }
+
+    #[cfg(test)]
+    mod parsing {
+        use super::*;
+
+        #[test]
+        fn rules_with_comments() {
+            assert_eq!(
+                parse(
+                    "# local connections\n\
+                     host all all 127.0.0.1/32 trust\n\
+                     \n\
+                     host db_name user_name 10.0.0.0/8 password # remote\n\
+                     host all all all reject"
+                ),
+                Ok(vec![
+                    Rule::new(Some(AddressRange::new(LOCALHOST, 32)), None, None, AuthMethod::Trust),
+                    Rule::new(
+                        Some(AddressRange::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8)),
+                        Some("db_name"),
+                        Some("user_name"),
+                        AuthMethod::Password
+                    ),
+                    Rule::new(None, None, None, AuthMethod::Reject),
+                ])
+            );
+        }
+
+        #[test]
+        fn address_without_prefix_length_is_single_host() {
+            assert_eq!(
+                parse("host all all ::1 scram-sha-256"),
+                Ok(vec![Rule::new(
+                    Some(AddressRange::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 128)),
+                    None,
+                    None,
+                    AuthMethod::Scram
+                )])
+            );
+        }
+
+        #[test]
+        fn missing_field() {
+            assert_eq!(parse("host all all trust"), Err(RuleError::MissingField(1)));
+        }
+
+        #[test]
+        fn unsupported_connection_type() {
+            assert_eq!(
+                parse("local all all all trust"),
+                Err(RuleError::UnsupportedConnectionType(1, "local".to_owned()))
+            );
+        }
+
+        #[test]
+        fn invalid_address() {
+            assert_eq!(
+                parse("host all all all trust\nhost all all 127.0.0.1/33 trust"),
+                Err(RuleError::InvalidAddress(2, "127.0.0.1/33".to_owned()))
+            );
+        }
+
+        #[test]
+        fn unknown_method() {
+            assert_eq!(
+                parse("host all all all md5"),
+                Err(RuleError::UnknownMethod(1, "md5".to_owned()))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod matching {
+        use super::*;
+
+        #[test]
+        fn first_matching_rule_wins() {
+            let authentication = HostBasedAuthentication::new(vec![
+                Rule::new(None, None, Some("admin"), AuthMethod::Password),
+                Rule::new(None, None, None, AuthMethod::Trust),
+            ]);
+
+            assert_eq!(
+                authentication.method(LOCALHOST, "postgres", "admin"),
+                AuthMethod::Password
+            );
+            assert_eq!(
+                authentication.method(LOCALHOST, "postgres", "postgres"),
+                AuthMethod::Trust
+            );
+        }
+
+        #[test]
+        fn client_is_rejected_when_no_rule_matches() {
+            let authentication = HostBasedAuthentication::new(vec![Rule::new(
+                Some(AddressRange::new(LOCALHOST, 32)),
+                Some("postgres"),
+                None,
+                AuthMethod::Trust,
+            )]);
+
+            assert_eq!(
+                authentication.method(REMOTE, "postgres", "postgres"),
+                AuthMethod::Reject
+            );
+            assert_eq!(
+                authentication.method(LOCALHOST, "other", "postgres"),
+                AuthMethod::Reject
+            );
+        }
+
+        #[test]
+        fn reloaded_rules_are_used_by_clones() {
+            let authentication = HostBasedAuthentication::trust_all();
+            let shared = authentication.clone();
+
+            authentication.reload(vec![Rule::new(None, None, None, AuthMethod::Reject)]);
+
+            assert_eq!(shared.method(LOCALHOST, "postgres", "postgres"), AuthMethod::Reject);
+        }
+    }
+}
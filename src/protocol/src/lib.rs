@@ -16,14 +16,17 @@
 //! API for backend implementation of PostgreSQL Wire Protocol
 extern crate log;
 
-use crate::messages::Message;
-use byteorder::{ByteOrder, NetworkEndian};
-use bytes::BytesMut;
+use crate::{
+    listener::{read_len, read_message},
+    messages::Message,
+};
 use futures_util::io::{AsyncReadExt, AsyncWriteExt};
 use std::io;
 
 pub use listener::{QueryListener, ServerListener};
 
+/// Module contains host based rules of client authentication
+pub mod hba;
 /// Module contains functionality to listen to incoming client connections and
 /// queries
 pub mod listener;
@@ -51,6 +54,12 @@ pub const VERSION_SSL: Version = (1234 << 16) + 5679;
 /// Client initiate `gss` encrypted connection
 pub const VERSION_GSSENC: Version = (1234 << 16) + 5680;
 
+/// Maximum size in bytes of a message that client is allowed to send after handshake
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// Size in bytes of encoded messages that are buffered before they are written
+/// to a client when a response is streamed
+pub const DEFAULT_STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
 /// `Error` type in protocol `Result`. Indicates that something went not well
 #[derive(Debug, PartialEq)]
 pub enum Error {
@@ -62,6 +71,18 @@ pub enum Error {
     UnsupportedRequest,
     /// Indicates that during handshake client sent unrecognized protocol version
     UnrecognizedVersion,
+    /// Indicates that client requested an encoding other than UTF-8
+    UnsupportedClientEncoding(String),
+    /// Indicates that client sent a message that exceeds maximum allowed size
+    MessageTooLarge(usize),
+    /// Indicates that client sent a message with invalid length or layout
+    MalformedMessage,
+    /// Indicates that host based authentication rules do not let the user connect
+    ConnectionRejected(String),
+    /// Indicates that the user sent wrong password
+    AuthenticationFailed(String),
+    /// Indicates that authentication method required by rules is not supported yet
+    UnsupportedAuthenticationMethod(String),
 }
 
 /// Result of handling incoming bytes from a client
@@ -69,20 +90,110 @@ pub enum Error {
 pub enum Command {
     /// Client commands to execute a `Query`
     Query(String),
+    /// Client commands to prepare a statement that has `$n` parameters
+    Parse {
+        /// name of the statement, empty name is for the unnamed statement
+        statement_name: String,
+        /// text of the statement
+        sql: String,
+        /// PostgreSQL data type ids of parameters, `0` leaves a type unspecified
+        param_types: Vec<i32>,
+    },
+    /// Client commands to create a portal from a prepared statement and
+    /// values of its parameters
+    Bind {
+        /// name of the portal, empty name is for the unnamed portal
+        portal_name: String,
+        /// name of the prepared statement
+        statement_name: String,
+        /// format codes of parameters, `0` is text and `1` is binary
+        param_formats: Vec<i16>,
+        /// values of parameters, `None` stands for `NULL`
+        params: Vec<Option<Vec<u8>>>,
+        /// format codes of result columns
+        result_formats: Vec<i16>,
+    },
+    /// Client commands to describe parameters and rows of a prepared statement
+    DescribeStatement(String),
+    /// Client commands to describe rows of a portal
+    DescribePortal(String),
+    /// Client commands to execute a portal
+    Execute {
+        /// name of the portal
+        portal_name: String,
+        /// maximum number of rows to return, `0` means that there is no limit
+        max_rows: i32,
+    },
+    /// Client commands to close a prepared statement
+    CloseStatement(String),
+    /// Client commands to close a portal
+    ClosePortal(String),
+    /// Client commands to end a sequence of extended query commands
+    Sync,
+    /// Client commands to send responses that are not sent yet
+    Flush,
     /// Client commands to terminate current connection
     Terminate,
 }
 
+/// Transaction status of a backend reported to a client with every
+/// ReadyForQuery message
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TransactionStatus {
+    /// Not in a transaction block
+    Idle,
+    /// In a transaction block
+    InTransaction,
+    /// In a failed transaction block, queries will be rejected until block is ended
+    Failed,
+}
+
+impl TransactionStatus {
+    /// returns status indicator that is sent to a client
+    pub fn as_u8(self) -> u8 {
+        match self {
+            TransactionStatus::Idle => b'I',
+            TransactionStatus::InTransaction => b'T',
+            TransactionStatus::Failed => b'E',
+        }
+    }
+}
+
 /// Structure to handle client-server PostgreSQL Wire Protocol connection
 pub struct Connection<RW: AsyncReadExt + AsyncWriteExt + Unpin> {
     properties: (Version, Params, SslMode),
     socket: RW,
+    max_message_size: usize,
+    stream_buffer_size: usize,
+    transaction_status: TransactionStatus,
+    // extended query commands are not followed by ReadyForQuery until Sync
+    ready_for_query: bool,
 }
 
 impl<RW: AsyncReadExt + AsyncWriteExt + Unpin> Connection<RW> {
     /// Creates new Connection with properties and read-write socket
     pub fn new(properties: (Version, Params, SslMode), socket: RW) -> Connection<RW> {
-        Connection { properties, socket }
+        Connection {
+            properties,
+            socket,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stream_buffer_size: DEFAULT_STREAM_BUFFER_SIZE,
+            transaction_status: TransactionStatus::Idle,
+            ready_for_query: true,
+        }
+    }
+
+    /// Sets maximum size in bytes of a message that client is allowed to send
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Connection<RW> {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Sets size in bytes of encoded messages that are buffered before they
+    /// are written to client when a response is streamed
+    pub fn with_stream_buffer_size(mut self, stream_buffer_size: usize) -> Connection<RW> {
+        self.stream_buffer_size = stream_buffer_size;
+        self
     }
 
     /// connection properties tuple
@@ -90,38 +201,144 @@ impl<RW: AsyncReadExt + AsyncWriteExt + Unpin> Connection<RW> {
         &(self.properties)
     }
 
+    /// value of a parameter that client sent during handshake
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.properties
+            .1
+            .iter()
+            .find(|(key, _value)| key == name)
+            .map(|(_key, value)| value.as_str())
+    }
+
+    /// Sets transaction status that is reported to client when connection is
+    /// ready to receive next command
+    pub fn set_transaction_status(&mut self, transaction_status: TransactionStatus) {
+        self.transaction_status = transaction_status;
+    }
+
     async fn send_ready_for_query(&mut self) -> io::Result<Result<()>> {
         log::debug!("send ready for query message");
         self.socket
-            .write_all(Message::ReadyForQuery.as_vec().as_slice())
+            .write_all(Message::ReadyForQuery(self.transaction_status).as_vec().as_slice())
             .await?;
         Ok(Ok(()))
     }
 
     /// receives and decodes a command from remote client
     pub async fn receive(&mut self) -> io::Result<Result<Command>> {
-        self.send_ready_for_query().await?.expect("to send ready for query");
+        if self.ready_for_query {
+            self.send_ready_for_query().await?.expect("to send ready for query");
+            self.ready_for_query = false;
+        }
         let mut buffer = [0u8; 1];
         let tag = self.socket.read_exact(&mut buffer).await.map(|_| buffer[0])?;
         if b'X' == tag {
-            Ok(Ok(Command::Terminate))
-        } else {
-            let mut buffer = [0u8; 4];
-            let len = self
-                .socket
-                .read_exact(&mut buffer)
-                .await
-                .map(|_| NetworkEndian::read_u32(&buffer))?;
-            let mut buffer = BytesMut::with_capacity(len as usize - 4);
-            buffer.resize(len as usize - 4, b'0');
-            let sql_buff = self.socket.read_exact(&mut buffer).await.map(|_| buffer)?;
-            log::debug!("FOR TEST sql = {:?}", sql_buff);
-            let sql = match String::from_utf8(sql_buff[..sql_buff.len() - 1].to_vec()) {
-                Ok(sql) => sql,
-                Err(_e) => return Ok(Err(Error::QueryIsNotValidUtfString)),
-            };
-            log::debug!("SQL = {}", sql);
-            Ok(Ok(Command::Query(sql)))
+            return Ok(Ok(Command::Terminate));
+        }
+        let len = match read_len(&mut self.socket, self.max_message_size).await? {
+            Ok(len) => len,
+            Err(error) => return Ok(Err(error)),
+        };
+        let buff = read_message(len, &mut self.socket).await?;
+        log::debug!("FOR TEST message = {:?}", buff);
+        let command = match tag {
+            b'P' => Self::parse(&buff),
+            b'B' => Self::bind(&buff),
+            b'D' => Self::describe(&buff),
+            b'E' => Self::execute(&buff),
+            b'C' => Self::close(&buff),
+            b'S' => Ok(Command::Sync),
+            b'H' => Ok(Command::Flush),
+            _ => Self::query(&buff),
+        };
+        if let Ok(Command::Query(_)) | Ok(Command::Sync) = &command {
+            self.ready_for_query = true;
+        }
+        Ok(command)
+    }
+
+    fn query(buff: &[u8]) -> Result<Command> {
+        let mut body = Body::new(buff);
+        let sql = body.string()?;
+        body.end()?;
+        log::debug!("SQL = {}", sql);
+        Ok(Command::Query(sql))
+    }
+
+    fn parse(buff: &[u8]) -> Result<Command> {
+        let mut body = Body::new(buff);
+        let statement_name = body.string()?;
+        let sql = body.string()?;
+        let mut param_types = vec![];
+        for _ in 0..body.i16()? {
+            param_types.push(body.i32()?);
+        }
+        body.end()?;
+        Ok(Command::Parse {
+            statement_name,
+            sql,
+            param_types,
+        })
+    }
+
+    fn bind(buff: &[u8]) -> Result<Command> {
+        let mut body = Body::new(buff);
+        let portal_name = body.string()?;
+        let statement_name = body.string()?;
+        let mut param_formats = vec![];
+        for _ in 0..body.i16()? {
+            param_formats.push(body.i16()?);
+        }
+        let mut params = vec![];
+        for _ in 0..body.i16()? {
+            params.push(match body.i32()? {
+                -1 => None,
+                len => Some(body.bytes(len)?.to_vec()),
+            });
+        }
+        let mut result_formats = vec![];
+        for _ in 0..body.i16()? {
+            result_formats.push(body.i16()?);
+        }
+        body.end()?;
+        Ok(Command::Bind {
+            portal_name,
+            statement_name,
+            param_formats,
+            params,
+            result_formats,
+        })
+    }
+
+    fn describe(buff: &[u8]) -> Result<Command> {
+        let mut body = Body::new(buff);
+        let kind = body.u8()?;
+        let name = body.string()?;
+        body.end()?;
+        match kind {
+            b'S' => Ok(Command::DescribeStatement(name)),
+            b'P' => Ok(Command::DescribePortal(name)),
+            _ => Err(Error::MalformedMessage),
+        }
+    }
+
+    fn execute(buff: &[u8]) -> Result<Command> {
+        let mut body = Body::new(buff);
+        let portal_name = body.string()?;
+        let max_rows = body.i32()?;
+        body.end()?;
+        Ok(Command::Execute { portal_name, max_rows })
+    }
+
+    fn close(buff: &[u8]) -> Result<Command> {
+        let mut body = Body::new(buff);
+        let kind = body.u8()?;
+        let name = body.string()?;
+        body.end()?;
+        match kind {
+            b'S' => Ok(Command::CloseStatement(name)),
+            b'P' => Ok(Command::ClosePortal(name)),
+            _ => Err(Error::MalformedMessage),
         }
     }
 
@@ -135,6 +352,77 @@ impl<RW: AsyncReadExt + AsyncWriteExt + Unpin> Connection<RW> {
         log::debug!("end of the command is sent");
         Ok(())
     }
+
+    /// Sends response messages to client as they are produced. Messages are
+    /// encoded into a bounded buffer that is written out whenever it is full,
+    /// so a slow client holds back production of the next messages
+    pub async fn send_stream<M: Iterator<Item = Message>>(&mut self, messages: M) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(self.stream_buffer_size);
+        for message in messages {
+            buffer.extend_from_slice(message.as_vec().as_slice());
+            if buffer.len() >= self.stream_buffer_size {
+                self.socket.write_all(buffer.as_slice()).await?;
+                buffer.clear();
+            }
+        }
+        if !buffer.is_empty() {
+            self.socket.write_all(buffer.as_slice()).await?;
+        }
+        log::debug!("end of the streamed command is sent");
+        Ok(())
+    }
+}
+
+// fields of a message that client sent, any field that does not fit into
+// the message makes it malformed
+struct Body<'b> {
+    buff: &'b [u8],
+}
+
+impl<'b> Body<'b> {
+    fn new(buff: &'b [u8]) -> Body<'b> {
+        Body { buff }
+    }
+
+    fn bytes(&mut self, len: i32) -> Result<&'b [u8]> {
+        if len < 0 || len as usize > self.buff.len() {
+            return Err(Error::MalformedMessage);
+        }
+        let (bytes, rest) = self.buff.split_at(len as usize);
+        self.buff = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        self.bytes(2).map(|bytes| i16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        self.bytes(4)
+            .map(|bytes| i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let end = match self.buff.iter().position(|byte| *byte == 0) {
+            Some(end) => end,
+            None => return Err(Error::MalformedMessage),
+        };
+        let string = self.bytes(end as i32)?;
+        self.buff = &self.buff[1..];
+        String::from_utf8(string.to_vec()).map_err(|_e| Error::QueryIsNotValidUtfString)
+    }
+
+    fn end(&self) -> Result<()> {
+        if self.buff.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::MalformedMessage)
+        }
+    }
 }
 
 impl<RW: AsyncReadExt + AsyncWriteExt + Unpin> PartialEq for Connection<RW> {
@@ -153,6 +441,9 @@ pub struct ColumnMetadata {
     pub type_id: i32,
     /// PostgreSQL data type size
     pub type_size: i16,
+    /// type specific modifier, e.g. maximum length of `varchar`, `-1` if type
+    /// does not have any
+    pub type_modifier: i32,
 }
 
 impl ColumnMetadata {
@@ -162,8 +453,15 @@ impl ColumnMetadata {
             name,
             type_id,
             type_size,
+            type_modifier: -1,
         }
     }
+
+    /// Sets type modifier of the column
+    pub fn with_type_modifier(mut self, type_modifier: i32) -> Self {
+        self.type_modifier = type_modifier;
+        self
+    }
 }
 
 /// Enum that describes possible `ssl` mode
@@ -187,6 +485,7 @@ mod tests {
         #[cfg(test)]
         mod read_query {
             use super::*;
+            use bytes::BytesMut;
             use test_helpers::async_io;
 
             #[async_std::test]
@@ -212,7 +511,29 @@ mod tests {
 
                 let actual_content = test_case.read_result().await;
                 let mut expected_content = BytesMut::new();
-                expected_content.extend_from_slice(Message::ReadyForQuery.as_vec().as_slice());
+                expected_content.extend_from_slice(Message::ReadyForQuery(TransactionStatus::Idle).as_vec().as_slice());
+                assert_eq!(actual_content, expected_content);
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn report_transaction_status_before_reading_query() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[81], &[0, 0, 0, 14], b"select 1;\0"]).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case.clone());
+                connection.set_transaction_status(TransactionStatus::InTransaction);
+
+                let query = connection.receive().await?;
+
+                assert_eq!(query, Ok(Command::Query("select 1;".to_owned())));
+
+                let actual_content = test_case.read_result().await;
+                let mut expected_content = BytesMut::new();
+                expected_content.extend_from_slice(
+                    Message::ReadyForQuery(TransactionStatus::InTransaction)
+                        .as_vec()
+                        .as_slice(),
+                );
                 assert_eq!(actual_content, expected_content);
 
                 Ok(())
@@ -248,5 +569,268 @@ mod tests {
                 assert!(query.is_err());
             }
         }
+
+        #[cfg(test)]
+        mod extended_query {
+            use super::*;
+            use bytes::BytesMut;
+            use test_helpers::{async_io, pg_frontend};
+
+            async fn receive(messages: Vec<pg_frontend::Message>) -> io::Result<Vec<Result<Command>>> {
+                let content = messages
+                    .iter()
+                    .map(pg_frontend::Message::as_vec)
+                    .collect::<Vec<Vec<u8>>>();
+                let test_case = async_io::TestCase::with_content(content.iter().map(Vec::as_slice).collect()).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case);
+                let mut commands = vec![];
+                for _ in 0..messages.len() {
+                    commands.push(connection.receive().await?);
+                }
+                Ok(commands)
+            }
+
+            #[async_std::test]
+            async fn parse() -> io::Result<()> {
+                assert_eq!(
+                    receive(vec![pg_frontend::Message::Parse("statement", "select $1;", vec![23])]).await?,
+                    vec![Ok(Command::Parse {
+                        statement_name: "statement".to_owned(),
+                        sql: "select $1;".to_owned(),
+                        param_types: vec![23],
+                    })]
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn bind() -> io::Result<()> {
+                assert_eq!(
+                    receive(vec![pg_frontend::Message::Bind("", "statement", vec![Some("1"), None])]).await?,
+                    vec![Ok(Command::Bind {
+                        portal_name: "".to_owned(),
+                        statement_name: "statement".to_owned(),
+                        param_formats: vec![],
+                        params: vec![Some(b"1".to_vec()), None],
+                        result_formats: vec![],
+                    })]
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn describe_execute_and_close() -> io::Result<()> {
+                assert_eq!(
+                    receive(vec![
+                        pg_frontend::Message::Describe(b'S', "statement"),
+                        pg_frontend::Message::Describe(b'P', "portal"),
+                        pg_frontend::Message::Execute("portal", 10),
+                        pg_frontend::Message::Close(b'P', "portal"),
+                        pg_frontend::Message::Close(b'S', "statement"),
+                    ])
+                    .await?,
+                    vec![
+                        Ok(Command::DescribeStatement("statement".to_owned())),
+                        Ok(Command::DescribePortal("portal".to_owned())),
+                        Ok(Command::Execute {
+                            portal_name: "portal".to_owned(),
+                            max_rows: 10
+                        }),
+                        Ok(Command::ClosePortal("portal".to_owned())),
+                        Ok(Command::CloseStatement("statement".to_owned())),
+                    ]
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn unknown_kind_of_described_object() -> io::Result<()> {
+                assert_eq!(
+                    receive(vec![pg_frontend::Message::Describe(b'X', "statement")]).await?,
+                    vec![Err(Error::MalformedMessage)]
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn parameter_value_longer_than_message() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![
+                    b"B",
+                    &[0, 0, 0, 14],
+                    &[0, 0, 0, 0, 0, 1, 0, 0, 0, 9, 49, 0],
+                ])
+                .await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case);
+
+                assert_eq!(connection.receive().await?, Err(Error::MalformedMessage));
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn ready_for_query_is_sent_after_sync() -> io::Result<()> {
+                let content = [
+                    pg_frontend::Message::Parse("", "select 1;", vec![]).as_vec(),
+                    pg_frontend::Message::Sync.as_vec(),
+                    pg_frontend::Message::Terminate.as_vec(),
+                ];
+                let test_case = async_io::TestCase::with_content(content.iter().map(Vec::as_slice).collect()).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case.clone());
+
+                connection.receive().await?.expect("parse command");
+                connection.receive().await?.expect("sync command");
+                connection.receive().await?.expect("terminate command");
+
+                let mut expected_content = BytesMut::new();
+                expected_content.extend_from_slice(Message::ReadyForQuery(TransactionStatus::Idle).as_vec().as_slice());
+                expected_content.extend_from_slice(Message::ReadyForQuery(TransactionStatus::Idle).as_vec().as_slice());
+                assert_eq!(test_case.read_result().await, expected_content);
+
+                Ok(())
+            }
+        }
+
+        #[cfg(test)]
+        mod malformed_query {
+            use super::*;
+            use test_helpers::{async_io, fuzz::ByteSource, pg_frontend};
+
+            #[async_std::test]
+            async fn length_less_than_length_itself() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[81], &[0, 0, 0, 3]]).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case);
+
+                let query = connection.receive().await?;
+
+                assert_eq!(query, Err(Error::MalformedMessage));
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn query_without_content() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[81], &[0, 0, 0, 4]]).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case);
+
+                let query = connection.receive().await?;
+
+                assert_eq!(query, Err(Error::MalformedMessage));
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn query_is_not_null_terminated() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[81], &[0, 0, 0, 13], b"select 1;"]).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case);
+
+                let query = connection.receive().await?;
+
+                assert_eq!(query, Err(Error::MalformedMessage));
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn query_exceeds_max_message_size() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[81], &[0, 0, 0, 14], b"select 1;\0"]).await;
+                let mut connection =
+                    Connection::new((VERSION_3, vec![], SslMode::Disable), test_case).with_max_message_size(13);
+
+                let query = connection.receive().await?;
+
+                assert_eq!(query, Err(Error::MessageTooLarge(14)));
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn huge_length_is_rejected_before_reading_content() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[81], &[255, 255, 255, 255]]).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case);
+
+                let query = connection.receive().await?;
+
+                assert_eq!(query, Err(Error::MessageTooLarge(u32::MAX as usize)));
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn arbitrary_bytes() {
+                let mut source = ByteSource::new(5432);
+                for _ in 0..256 {
+                    let bytes = source.bytes(64);
+                    let test_case = async_io::TestCase::with_content(vec![bytes.as_slice()]).await;
+                    let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case);
+
+                    let _query = connection.receive().await;
+                }
+            }
+
+            #[async_std::test]
+            async fn mutated_query() {
+                let mut source = ByteSource::new(5432);
+                let query = pg_frontend::Message::Query("select * from schema_name.table_name;").as_vec();
+                for _ in 0..256 {
+                    let bytes = source.mutate(query.as_slice());
+                    let test_case = async_io::TestCase::with_content(vec![bytes.as_slice()]).await;
+                    let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case);
+
+                    let _query = connection.receive().await;
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod send_stream {
+            use super::*;
+            use bytes::BytesMut;
+            use test_helpers::async_io;
+
+            #[async_std::test]
+            async fn messages_larger_than_buffer() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![]).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case.clone())
+                    .with_stream_buffer_size(8);
+                let messages = vec![
+                    Message::DataRow(vec!["1".to_owned()]),
+                    Message::DataRow(vec!["2".to_owned()]),
+                    Message::CommandComplete("SELECT 2".to_owned()),
+                ];
+
+                let mut expected_content = BytesMut::new();
+                for message in messages.iter() {
+                    expected_content.extend_from_slice(message.as_vec().as_slice());
+                }
+
+                connection.send_stream(messages.into_iter()).await?;
+
+                assert_eq!(test_case.read_result().await, expected_content);
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn messages_smaller_than_buffer() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![]).await;
+                let mut connection = Connection::new((VERSION_3, vec![], SslMode::Disable), test_case.clone());
+
+                connection
+                    .send_stream(vec![Message::CommandComplete("SELECT 0".to_owned())].into_iter())
+                    .await?;
+
+                assert_eq!(
+                    test_case.read_result().await,
+                    BytesMut::from(Message::CommandComplete("SELECT 0".to_owned()).as_vec().as_slice())
+                );
+
+                Ok(())
+            }
+        }
     }
 }
@@ -13,16 +13,22 @@
 // limitations under the License.
 
 use crate::{
-    messages::Message, Connection, Error, Params, Result, SslMode, VERSION_1, VERSION_2, VERSION_3, VERSION_CANCEL,
-    VERSION_GSSENC, VERSION_SSL,
+    hba::{AuthMethod, HostBasedAuthentication},
+    messages::Message,
+    Connection, Error, Params, Result, SslMode, DEFAULT_MAX_MESSAGE_SIZE, VERSION_1, VERSION_2, VERSION_3,
+    VERSION_CANCEL, VERSION_GSSENC, VERSION_SSL,
 };
 use async_trait::async_trait;
 use byteorder::{ByteOrder, NetworkEndian};
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BytesMut};
 use futures_util::io::{self, AsyncReadExt, AsyncWriteExt};
-use itertools::Itertools;
 use std::net::SocketAddr;
 
+const SERVER_ENCODING: &str = "UTF8";
+// the same limit PostgreSQL puts on startup and authentication messages
+const MAX_STARTUP_MESSAGE_SIZE: usize = 10_000;
+const PASSWORD_MESSAGE: u8 = b'p';
+
 /// Listener trait that use underline network to `accept` queries from clients
 #[async_trait]
 pub trait QueryListener {
@@ -37,90 +43,62 @@ pub trait QueryListener {
         let (mut socket, address) = self.server_channel().channel().await?;
         log::debug!("ADDRESS {:?}", address);
 
-        let len = read_len(&mut socket).await?;
-        let mut message = read_message(len, &mut socket).await?;
+        let mut message = match read_startup_message(&mut socket).await? {
+            Ok(message) => message,
+            Err(error) => {
+                reject(&mut socket, &error).await?;
+                return Ok(Err(error));
+            }
+        };
         log::debug!("MESSAGE FOR TEST = {:#?}", message);
         let version = NetworkEndian::read_i32(message.bytes());
         log::debug!("VERSION FOR TEST = {:#?}", version);
         message.advance(4);
 
         if version == VERSION_3 {
-            let parsed = message
-                .bytes()
-                .split(|b| *b == 0)
-                .filter(|b| !b.is_empty())
-                .map(|b| std::str::from_utf8(b).unwrap().to_owned())
-                .tuples()
-                .collect::<Params>();
+            let parsed = match parse_params(message.bytes()) {
+                Ok(parsed) => parsed,
+                Err(error) => {
+                    reject(&mut socket, &error).await?;
+                    return Ok(Err(error));
+                }
+            };
             message.advance(message.remaining());
             log::debug!("Version {}\nparams = {:?}", version, parsed);
-            socket.write_all(Message::AuthenticationOk.as_vec().as_slice()).await?;
-            Ok(Ok(Connection::new((version, parsed, SslMode::Disable), socket)))
+            match handshake(self, &mut socket, address, &parsed).await? {
+                Ok(()) => Ok(Ok(Connection::new((version, parsed, SslMode::Disable), socket)
+                    .with_max_message_size(self.max_message_size()))),
+                Err(error) => Ok(Err(error)),
+            }
         } else if version == VERSION_SSL {
             if self.secure().ssl_support() {
                 unimplemented!()
             } else {
                 socket.write_all(Message::NoticeResponse.as_vec().as_slice()).await?;
-                let len = read_len(&mut socket).await?;
-                log::debug!("LEN = {:?}", len);
-                let mut message = read_message(len, &mut socket).await?;
+                let mut message = match read_startup_message(&mut socket).await? {
+                    Ok(message) => message,
+                    Err(error) => {
+                        reject(&mut socket, &error).await?;
+                        return Ok(Err(error));
+                    }
+                };
                 log::debug!("MESSAGE FOR TEST = {:#?}", message);
                 let version = NetworkEndian::read_i32(message.bytes());
                 message.advance(4);
-                let parsed = {
-                    message
-                        .bytes()
-                        .split(|b| *b == 0)
-                        .filter(|b| !b.is_empty())
-                        .map(|b| std::str::from_utf8(b).unwrap().to_owned())
-                        .tuples()
-                        .collect::<Params>()
+                let parsed = match parse_params(message.bytes()) {
+                    Ok(parsed) => parsed,
+                    Err(error) => {
+                        reject(&mut socket, &error).await?;
+                        return Ok(Err(error));
+                    }
                 };
                 message.advance(message.remaining());
                 log::debug!("MESSAGE FOR TEST = {:#?}", parsed);
-                socket
-                    .write_all(Message::AuthenticationCleartextPassword.as_vec().as_slice())
-                    .await?;
-                let mut buffer = [0u8; 1];
-                let tag = socket.read_exact(&mut buffer).await.map(|_| buffer[0]);
-                log::debug!("client message response tag {:?}", tag);
-                log::debug!("waiting for authentication response");
-                let len = read_len(&mut socket).await?;
-                let _message = read_message(len, &mut socket).await?;
-                socket.write_all(Message::AuthenticationOk.as_vec().as_slice()).await?;
-
-                let mut parameter_status = BytesMut::with_capacity(256);
-                parameter_status.put_u8(b'S');
-                let mut parameters = BytesMut::with_capacity(256);
-                parameters.extend_from_slice(b"client_encoding");
-                parameters.put_u8(0);
-                parameters.extend_from_slice(b"UTF8");
-                parameters.put_u8(0);
-                eprintln!("LEN = {:?}", parameters.len());
-                eprintln!("PARAMS = {:?}", parameters);
-                parameter_status.put_u32(4 + parameters.bytes().len() as u32);
-                parameter_status.extend_from_slice(parameters.as_ref());
-                eprintln!("LEN = {:?}", parameter_status.len());
-                eprintln!("PARAMS = {:?}", parameter_status);
-                socket.write_all(parameter_status.to_vec().as_slice()).await?;
-
-                // TODO it does not required by psycopg2 but good example of ParameterStatus message from backend
-                let mut parameter_status = BytesMut::with_capacity(256);
-                parameter_status.put_u8(b'S');
-                let mut parameters = BytesMut::with_capacity(256);
-                parameters.extend_from_slice(b"DateStyle");
-                parameters.put_u8(0);
-                parameters.extend_from_slice(b"DMY");
-                parameters.put_u8(0);
-                eprintln!("LEN = {:?}", parameters.len());
-                eprintln!("PARAMS = {:?}", parameters);
-                parameter_status.put_u32(4 + parameters.bytes().len() as u32);
-                parameter_status.extend_from_slice(parameters.as_ref());
-                eprintln!("LEN = {:?}", parameter_status.len());
-                eprintln!("PARAMS = {:?}", parameter_status);
-                socket.write_all(parameter_status.to_vec().as_slice()).await?;
-
-                Ok(Ok(Connection::new((version, parsed, SslMode::Require), socket)))
+                match handshake(self, &mut socket, address, &parsed).await? {
+                    Ok(()) => Ok(Ok(Connection::new((version, parsed, SslMode::Require), socket)
+                        .with_max_message_size(self.max_message_size()))),
+                    Err(error) => Ok(Err(error)),
+                }
             }
         } else if version == VERSION_GSSENC {
             if self.secure().gssenc_support() {
@@ -145,6 +123,21 @@ pub trait QueryListener {
     /// returns configuration of accepting or rejecting secure connections from
     /// clients
     fn secure(&self) -> &Secure;
+
+    /// returns maximum size in bytes of a message that clients are allowed to
+    /// send after handshake
+    fn max_message_size(&self) -> usize {
+        DEFAULT_MAX_MESSAGE_SIZE
+    }
+
+    /// returns rules that decide how clients authenticate themselves
+    fn authentication(&self) -> &HostBasedAuthentication;
+
+    /// returns password of the user to check when rules require password
+    /// authentication
+    fn password(&self, _user: &str) -> Option<String> {
+        None
+    }
 }
 
 /// Trait that uses underline network protocol to establish bidirectional
@@ -209,7 +202,220 @@ impl Secure {
     }
 }
 
-async fn read_len<RW>(socket: &mut RW) -> io::Result<usize>
+/// checks parameters that client sent during handshake and returns statuses
+/// of run-time parameters that client should know about
+fn startup_parameters(params: &Params) -> Result<Vec<Message>> {
+    let parameter = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _value)| key == name)
+            .map(|(_key, value)| value.as_str())
+    };
+    let client_encoding = parameter("client_encoding").unwrap_or(SERVER_ENCODING);
+    if !is_utf8(client_encoding) {
+        return Err(Error::UnsupportedClientEncoding(client_encoding.to_owned()));
+    }
+    Ok(vec![
+        Message::ParameterStatus(
+            "application_name".to_owned(),
+            parameter("application_name").unwrap_or_default().to_owned(),
+        ),
+        Message::ParameterStatus("client_encoding".to_owned(), SERVER_ENCODING.to_owned()),
+        Message::ParameterStatus("DateStyle".to_owned(), "ISO, MDY".to_owned()),
+        Message::ParameterStatus("integer_datetimes".to_owned(), "on".to_owned()),
+        Message::ParameterStatus("server_encoding".to_owned(), SERVER_ENCODING.to_owned()),
+        Message::ParameterStatus("TimeZone".to_owned(), "UTC".to_owned()),
+    ])
+}
+
+// encoding names are case insensitive and non alphanumeric characters in them are ignored
+fn is_utf8(encoding: &str) -> bool {
+    let normalized = encoding
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_lowercase();
+    normalized == "utf8" || normalized == "unicode"
+}
+
+// validates startup parameters and authenticates client, client is notified
+// if either fails
+async fn handshake<L>(
+    listener: &L,
+    socket: &mut L::Channel,
+    address: SocketAddr,
+    params: &Params,
+) -> io::Result<Result<()>>
+where
+    L: QueryListener + Sync + ?Sized,
+{
+    let parameter = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _value)| key == name)
+            .map(|(_key, value)| value.as_str())
+    };
+    let user = parameter("user").unwrap_or_default();
+    let database = parameter("database").unwrap_or(user);
+    let parameter_statuses = match startup_parameters(params) {
+        Ok(parameter_statuses) => parameter_statuses,
+        Err(error) => {
+            reject(socket, &error).await?;
+            return Ok(Err(error));
+        }
+    };
+    let method = listener.authentication().method(address.ip(), database, user);
+    log::debug!(
+        "{:?} authentication of {:?} to {:?} from {:?}",
+        method,
+        user,
+        database,
+        address
+    );
+    if let Err(error) = authenticate(socket, user, method, listener.password(user)).await? {
+        reject(socket, &error).await?;
+        return Ok(Err(error));
+    }
+    socket.write_all(Message::AuthenticationOk.as_vec().as_slice()).await?;
+    send_parameter_statuses(socket, parameter_statuses).await?;
+    Ok(Ok(()))
+}
+
+async fn authenticate<RW>(
+    socket: &mut RW,
+    user: &str,
+    method: AuthMethod,
+    password: Option<String>,
+) -> io::Result<Result<()>>
+where
+    RW: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    match method {
+        AuthMethod::Trust => Ok(Ok(())),
+        AuthMethod::Reject => Ok(Err(Error::ConnectionRejected(user.to_owned()))),
+        AuthMethod::Scram => Ok(Err(Error::UnsupportedAuthenticationMethod("scram-sha-256".to_owned()))),
+        AuthMethod::Password => {
+            socket
+                .write_all(Message::AuthenticationCleartextPassword.as_vec().as_slice())
+                .await?;
+            log::debug!("waiting for authentication response");
+            match read_password(socket).await? {
+                Ok(sent) if password.as_deref() == Some(sent.as_str()) => Ok(Ok(())),
+                Ok(_) => Ok(Err(Error::AuthenticationFailed(user.to_owned()))),
+                Err(error) => Ok(Err(error)),
+            }
+        }
+    }
+}
+
+async fn read_password<RW>(socket: &mut RW) -> io::Result<Result<String>>
+where
+    RW: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    let mut buffer = [0u8; 1];
+    let tag = socket.read_exact(&mut buffer).await.map(|_| buffer[0])?;
+    log::debug!("client message response tag {:?}", tag);
+    if tag != PASSWORD_MESSAGE {
+        return Ok(Err(Error::MalformedMessage));
+    }
+    let len = match read_len(socket, MAX_STARTUP_MESSAGE_SIZE).await? {
+        Ok(len) => len,
+        Err(error) => return Ok(Err(error)),
+    };
+    let message = read_message(len, socket).await?;
+    match message.split_last() {
+        Some((0, password)) => Ok(String::from_utf8(password.to_vec()).map_err(|_| Error::MalformedMessage)),
+        _ => Ok(Err(Error::MalformedMessage)),
+    }
+}
+
+async fn reject<RW>(socket: &mut RW, error: &Error) -> io::Result<()>
+where
+    RW: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    let (code, message) = match error {
+        Error::UnsupportedClientEncoding(encoding) => (
+            "22023",
+            format!("invalid value for parameter \"client_encoding\": \"{}\"", encoding),
+        ),
+        Error::MessageTooLarge(len) => ("08P01", format!("invalid message length {}", len)),
+        Error::MalformedMessage => ("08P01", "invalid message layout".to_owned()),
+        Error::ConnectionRejected(user) => (
+            "28000",
+            format!(
+                "connection of user \"{}\" is rejected by host based authentication rules",
+                user
+            ),
+        ),
+        Error::AuthenticationFailed(user) => ("28P01", format!("password authentication failed for user \"{}\"", user)),
+        Error::UnsupportedAuthenticationMethod(method) => (
+            "28000",
+            format!("authentication method \"{}\" is not supported", method),
+        ),
+        error => ("22023", format!("{:?}", error)),
+    };
+    socket
+        .write_all(
+            Message::ErrorResponse(Some("FATAL".to_owned()), Some(code.to_owned()), Some(message))
+                .as_vec()
+                .as_slice(),
+        )
+        .await
+}
+
+async fn send_parameter_statuses<RW>(socket: &mut RW, parameter_statuses: Vec<Message>) -> io::Result<()>
+where
+    RW: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    for parameter_status in parameter_statuses {
+        socket.write_all(parameter_status.as_vec().as_slice()).await?;
+    }
+    Ok(())
+}
+
+/// startup message starts with protocol version followed by parameters
+async fn read_startup_message<RW>(socket: &mut RW) -> io::Result<Result<BytesMut>>
+where
+    RW: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    let len = match read_len(socket, MAX_STARTUP_MESSAGE_SIZE).await? {
+        Ok(len) if len >= 4 => len,
+        Ok(_) => return Ok(Err(Error::MalformedMessage)),
+        Err(error) => return Ok(Err(error)),
+    };
+    read_message(len, socket).await.map(Ok)
+}
+
+/// parameters are pairs of null terminated names and values followed by an
+/// empty name
+fn parse_params(bytes: &[u8]) -> Result<Params> {
+    let utf8 = |bytes: &[u8]| {
+        std::str::from_utf8(bytes)
+            .map(ToOwned::to_owned)
+            .map_err(|_| Error::MalformedMessage)
+    };
+    let mut strings = bytes.split(|b| *b == 0);
+    let mut params = vec![];
+    loop {
+        match strings.next() {
+            Some(name) if !name.is_empty() => match strings.next() {
+                Some(value) => params.push((utf8(name)?, utf8(value)?)),
+                None => return Err(Error::MalformedMessage),
+            },
+            // nothing should follow the terminating null byte
+            Some(_) => {
+                return match (strings.next(), strings.next()) {
+                    (Some([]), None) => Ok(params),
+                    _ => Err(Error::MalformedMessage),
+                }
+            }
+            None => return Err(Error::MalformedMessage),
+        }
+    }
+}
+
+/// reads length of a message and returns how many bytes are left to read
+pub(crate) async fn read_len<RW>(socket: &mut RW, max_message_size: usize) -> io::Result<Result<usize>>
 where
     RW: AsyncReadExt + AsyncWriteExt + Unpin,
 {
@@ -218,10 +424,16 @@ where
         .read_exact(&mut buffer)
         .await
         .map(|_| NetworkEndian::read_u32(&buffer) as usize)?;
-    Ok(len - 4)
+    if len < 4 {
+        Ok(Err(Error::MalformedMessage))
+    } else if len > max_message_size {
+        Ok(Err(Error::MessageTooLarge(len)))
+    } else {
+        Ok(Ok(len - 4))
+    }
 }
 
-async fn read_message<RW>(len: usize, socket: &mut RW) -> io::Result<BytesMut>
+pub(crate) async fn read_message<RW>(len: usize, socket: &mut RW) -> io::Result<BytesMut>
 where
     RW: AsyncReadExt + AsyncWriteExt + Unpin,
 {
@@ -233,12 +445,16 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hba::Rule;
     use std::net::{IpAddr, Ipv4Addr};
     use test_helpers::{async_io, pg_frontend};
 
+    const PASSWORD: &str = "123";
+
     struct MockQueryListener {
         server_listener: MockServerListener,
         secure: Secure,
+        authentication: HostBasedAuthentication,
     }
 
     impl MockQueryListener {
@@ -246,8 +462,14 @@ mod tests {
             MockQueryListener {
                 server_listener: MockServerListener::new(test_case),
                 secure,
+                authentication: HostBasedAuthentication::trust_all(),
             }
         }
+
+        fn authenticated_with(self, method: AuthMethod) -> MockQueryListener {
+            self.authentication.reload(vec![Rule::new(None, None, None, method)]);
+            self
+        }
     }
 
     #[async_trait]
@@ -262,6 +484,14 @@ mod tests {
         fn secure(&self) -> &Secure {
             &self.secure
         }
+
+        fn authentication(&self) -> &HostBasedAuthentication {
+            &self.authentication
+        }
+
+        fn password(&self, _user: &str) -> Option<String> {
+            Some(PASSWORD.to_owned())
+        }
     }
 
     struct MockServerListener {
@@ -286,6 +516,38 @@ mod tests {
         }
     }
 
+    fn parameter_statuses(application_name: &str) -> Vec<u8> {
+        vec![
+            ("application_name", application_name),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            ("server_encoding", "UTF8"),
+            ("TimeZone", "UTC"),
+        ]
+        .into_iter()
+        .flat_map(|(name, value)| Message::ParameterStatus(name.to_owned(), value.to_owned()).as_vec())
+        .collect()
+    }
+
+    #[cfg(test)]
+    mod encoding {
+        use super::*;
+
+        #[test]
+        fn utf8_encoding_names() {
+            assert!(is_utf8("UTF8"));
+            assert!(is_utf8("utf-8"));
+            assert!(is_utf8("Unicode"));
+        }
+
+        #[test]
+        fn other_encoding_names() {
+            assert!(!is_utf8("LATIN1"));
+            assert!(!is_utf8("SQL_ASCII"));
+        }
+    }
+
     #[cfg(test)]
     mod hand_shake {
         use super::*;
@@ -348,11 +610,328 @@ mod tests {
                 let actual_content = test_case.read_result().await;
                 let mut expected_content = BytesMut::new();
                 expected_content.extend_from_slice(Message::AuthenticationOk.as_vec().as_slice());
+                expected_content.extend_from_slice(parameter_statuses("").as_slice());
+
+                assert_eq!(actual_content, expected_content);
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn application_name_is_reported_back() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![
+                    pg_frontend::Message::SslDisabled.as_vec().as_slice(),
+                    pg_frontend::Message::Setup(vec![("user", "postgres"), ("application_name", "app")])
+                        .as_vec()
+                        .as_slice(),
+                ])
+                .await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .accept()
+                    .await?
+                    .expect("connection is open");
+
+                assert_eq!(connection.parameter("application_name"), Some("app"));
+
+                let actual_content = test_case.read_result().await;
+                let mut expected_content = BytesMut::new();
+                expected_content.extend_from_slice(Message::AuthenticationOk.as_vec().as_slice());
+                expected_content.extend_from_slice(parameter_statuses("app").as_slice());
+
+                assert_eq!(actual_content, expected_content);
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn not_utf8_client_encoding_is_rejected() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![
+                    pg_frontend::Message::SslDisabled.as_vec().as_slice(),
+                    pg_frontend::Message::Setup(vec![("user", "postgres"), ("client_encoding", "LATIN1")])
+                        .as_vec()
+                        .as_slice(),
+                ])
+                .await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::UnsupportedClientEncoding("LATIN1".to_owned()))
+                );
+
+                let actual_content = test_case.read_result().await;
+                let mut expected_content = BytesMut::new();
+                expected_content.extend_from_slice(
+                    Message::ErrorResponse(
+                        Some("FATAL".to_owned()),
+                        Some("22023".to_owned()),
+                        Some("invalid value for parameter \"client_encoding\": \"LATIN1\"".to_owned()),
+                    )
+                    .as_vec()
+                    .as_slice(),
+                );
+
+                assert_eq!(actual_content, expected_content);
+
+                Ok(())
+            }
+        }
+
+        #[cfg(test)]
+        mod authentication {
+            use super::*;
+
+            fn fatal(code: &str, message: &str) -> BytesMut {
+                let mut expected_content = BytesMut::new();
+                expected_content.extend_from_slice(
+                    Message::ErrorResponse(
+                        Some("FATAL".to_owned()),
+                        Some(code.to_owned()),
+                        Some(message.to_owned()),
+                    )
+                    .as_vec()
+                    .as_slice(),
+                );
+                expected_content
+            }
+
+            async fn startup(password: Option<&'static str>) -> async_io::TestCase {
+                let setup = pg_frontend::Message::Setup(vec![("user", "postgres")]).as_vec();
+                let password = password
+                    .map(|password| pg_frontend::Message::Password(password).as_vec())
+                    .unwrap_or_default();
+                async_io::TestCase::with_content(vec![setup.as_slice(), password.as_slice()]).await
+            }
+
+            #[async_std::test]
+            async fn rejected_user() -> io::Result<()> {
+                let test_case = startup(None).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .authenticated_with(AuthMethod::Reject)
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::ConnectionRejected("postgres".to_owned()))
+                );
+                assert_eq!(
+                    test_case.read_result().await,
+                    fatal(
+                        "28000",
+                        "connection of user \"postgres\" is rejected by host based authentication rules"
+                    )
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn user_with_correct_password() -> io::Result<()> {
+                let test_case = startup(Some(PASSWORD)).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .authenticated_with(AuthMethod::Password)
+                    .accept()
+                    .await?;
+
+                assert_eq!(connection.map(|connection| connection.properties().0), Ok(VERSION_3));
+
+                let actual_content = test_case.read_result().await;
+                let mut expected_content = BytesMut::new();
+                expected_content.extend_from_slice(Message::AuthenticationCleartextPassword.as_vec().as_slice());
+                expected_content.extend_from_slice(Message::AuthenticationOk.as_vec().as_slice());
+                expected_content.extend_from_slice(parameter_statuses("").as_slice());
 
                 assert_eq!(actual_content, expected_content);
 
                 Ok(())
             }
+
+            #[async_std::test]
+            async fn user_with_wrong_password() -> io::Result<()> {
+                let test_case = startup(Some("wrong")).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .authenticated_with(AuthMethod::Password)
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::AuthenticationFailed("postgres".to_owned()))
+                );
+
+                let actual_content = test_case.read_result().await;
+                let mut expected_content = BytesMut::new();
+                expected_content.extend_from_slice(Message::AuthenticationCleartextPassword.as_vec().as_slice());
+                expected_content
+                    .extend_from_slice(&fatal("28P01", "password authentication failed for user \"postgres\""));
+
+                assert_eq!(actual_content, expected_content);
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn scram_is_not_supported() -> io::Result<()> {
+                let test_case = startup(None).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .authenticated_with(AuthMethod::Scram)
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::UnsupportedAuthenticationMethod("scram-sha-256".to_owned()))
+                );
+
+                Ok(())
+            }
+        }
+
+        #[cfg(test)]
+        mod malformed_startup {
+            use super::*;
+            use test_helpers::fuzz::ByteSource;
+
+            fn protocol_violation(message: &str) -> BytesMut {
+                let mut expected_content = BytesMut::new();
+                expected_content.extend_from_slice(
+                    Message::ErrorResponse(
+                        Some("FATAL".to_owned()),
+                        Some("08P01".to_owned()),
+                        Some(message.to_owned()),
+                    )
+                    .as_vec()
+                    .as_slice(),
+                );
+                expected_content
+            }
+
+            #[async_std::test]
+            async fn message_exceeds_max_startup_message_size() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[0, 0, 39, 17]]).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::MessageTooLarge(10_001))
+                );
+                assert_eq!(
+                    test_case.read_result().await,
+                    protocol_violation("invalid message length 10001")
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn message_is_shorter_than_protocol_version() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[0, 0, 0, 6, 0, 3]]).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::MalformedMessage)
+                );
+                assert_eq!(
+                    test_case.read_result().await,
+                    protocol_violation("invalid message layout")
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn parameter_without_value() -> io::Result<()> {
+                let test_case = async_io::TestCase::with_content(vec![&[0, 0, 0, 13, 0, 3, 0, 0], b"user\0"]).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::MalformedMessage)
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn parameters_are_not_null_terminated() -> io::Result<()> {
+                let test_case =
+                    async_io::TestCase::with_content(vec![&[0, 0, 0, 21, 0, 3, 0, 0], b"user\0postgres"]).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::MalformedMessage)
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn parameter_is_not_utf8_string() -> io::Result<()> {
+                let test_case =
+                    async_io::TestCase::with_content(vec![&[0, 0, 0, 17, 0, 3, 0, 0], b"user\0\xff\xfe\0\0"]).await;
+
+                let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .accept()
+                    .await?;
+
+                assert_eq!(
+                    connection.map(|connection| connection.properties().0),
+                    Err(Error::MalformedMessage)
+                );
+
+                Ok(())
+            }
+
+            #[async_std::test]
+            async fn arbitrary_bytes() {
+                let mut source = ByteSource::new(5432);
+                for _ in 0..256 {
+                    let bytes = source.bytes(64);
+                    let test_case = async_io::TestCase::with_content(vec![bytes.as_slice()]).await;
+
+                    let _connection = MockQueryListener::new(test_case, Secure::none()).accept().await;
+                }
+            }
+
+            #[async_std::test]
+            async fn mutated_startup_message() {
+                let mut source = ByteSource::new(5432);
+                let message = pg_frontend::Message::Setup(vec![
+                    ("client_encoding", "UTF8"),
+                    ("user", "postgres"),
+                    ("application_name", "psql"),
+                ])
+                .as_vec();
+                for _ in 0..256 {
+                    let bytes = source.mutate(message.as_slice());
+                    let test_case = async_io::TestCase::with_content(vec![bytes.as_slice()]).await;
+
+                    let _connection = MockQueryListener::new(test_case, Secure::none()).accept().await;
+                }
+            }
         }
 
         #[cfg(test)]
@@ -402,6 +981,7 @@ mod tests {
                 .await;
 
                 let connection = MockQueryListener::new(test_case.clone(), Secure::none())
+                    .authenticated_with(AuthMethod::Password)
                     .accept()
                     .await?;
 
@@ -412,6 +992,7 @@ mod tests {
                 expected_content.extend_from_slice(Message::NoticeResponse.as_vec().as_slice());
                 expected_content.extend_from_slice(Message::AuthenticationCleartextPassword.as_vec().as_slice());
                 expected_content.extend_from_slice(Message::AuthenticationOk.as_vec().as_slice());
+                expected_content.extend_from_slice(parameter_statuses("psql").as_slice());
 
                 assert_eq!(actual_content, expected_content);
 
@@ -12,12 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::ColumnMetadata;
+use crate::{ColumnMetadata, TransactionStatus};
 use bytes::{Buf, BufMut, BytesMut};
 
-// const PARSE_COMPLETE: u8 = b'1';
-// const BIND_COMPLETE: u8 = b'2';
-// const CLOSE_COMPLETE: u8 = b'3';
+const PARSE_COMPLETE: u8 = b'1';
+const BIND_COMPLETE: u8 = b'2';
+const CLOSE_COMPLETE: u8 = b'3';
 // const NOTIFICATION_RESPONSE: u8 = b'A';
 // const COPY_DONE: u8 = b'c';
 const COMMAND_COMPLETE: u8 = b'C';
@@ -31,12 +31,12 @@ const MESSAGE: u8 = b'M';
 // const COPY_OUT_RESPONSE: u8 = b'H';
 const EMPTY_QUERY_RESPONSE: u8 = b'I';
 // const BACKEND_KEY_DATA: u8 = b'K';
-// const NO_DATA: u8 = b'n';
+const NO_DATA: u8 = b'n';
 const NOTICE_RESPONSE: u8 = b'N';
 const AUTHENTICATION: u8 = b'R';
-// const PORTAL_SUSPENDED: u8 = b's';
-// const PARAMETER_STATUS: u8 = b'S';
-// const PARAMETER_DESCRIPTION: u8 = b't';
+const PORTAL_SUSPENDED: u8 = b's';
+const PARAMETER_STATUS: u8 = b'S';
+const PARAMETER_DESCRIPTION: u8 = b't';
 const ROW_DESCRIPTION: u8 = b'T';
 const READY_FOR_QUERY: u8 = b'Z';
 
@@ -62,8 +62,9 @@ pub enum Message {
     AuthenticationMD5Password,
     /// The authentication exchange is successfully completed.
     AuthenticationOk,
-    /// Start-up is completed. The frontend can now issue commands.
-    ReadyForQuery,
+    /// Start-up is completed. The frontend can now issue commands. Contains
+    /// the current transaction status of the backend.
+    ReadyForQuery(TransactionStatus),
     /// One of the set of rows returned by a SELECT, FETCH, etc query.
     DataRow(Vec<String>),
     /// Indicates that rows are about to be returned in response to a SELECT, FETCH,
@@ -78,6 +79,26 @@ pub enum Message {
     /// An error has occurred. Contains (`Severity`, `Error Code`, `Error Message`)
     /// all of them are optional
     ErrorResponse(Option<String>, Option<String>, Option<String>),
+    /// A notice has been issued while the command is executed. Contains
+    /// (`Severity`, `Notice Code`, `Notice Message`) all of them are optional
+    Notice(Option<String>, Option<String>, Option<String>),
+    /// Informs the frontend about the current setting of a run-time parameter.
+    /// Contains (`Parameter Name`, `Parameter Value`)
+    ParameterStatus(String, String),
+    /// A Parse command of extended query protocol is completed.
+    ParseComplete,
+    /// A Bind command of extended query protocol is completed.
+    BindComplete,
+    /// A Close command of extended query protocol is completed.
+    CloseComplete,
+    /// Statement or portal that the client describes does not return rows.
+    NoData,
+    /// An Execute command has reached its row count limit, the rest of the
+    /// rows are returned by the following Execute commands.
+    PortalSuspended,
+    /// Types of parameters that a prepared statement needs. Contains
+    /// PostgreSQL data type ids
+    ParameterDescription(Vec<i32>),
 }
 
 impl Message {
@@ -88,7 +109,7 @@ impl Message {
             Message::AuthenticationCleartextPassword => vec![AUTHENTICATION, 0, 0, 0, 8, 0, 0, 0, 3],
             Message::AuthenticationMD5Password => vec![AUTHENTICATION, 0, 0, 0, 12, 0, 0, 0, 5, 1, 1, 1, 1],
             Message::AuthenticationOk => vec![AUTHENTICATION, 0, 0, 0, 8, 0, 0, 0, 0],
-            Message::ReadyForQuery => vec![READY_FOR_QUERY, 0, 0, 0, 5, EMPTY_QUERY_RESPONSE],
+            Message::ReadyForQuery(status) => vec![READY_FOR_QUERY, 0, 0, 0, 5, status.as_u8()],
             Message::DataRow(row) => {
                 let mut row_buff = BytesMut::with_capacity(256);
                 for field in row.iter() {
@@ -106,13 +127,13 @@ impl Message {
             Message::RowDescription(description) => {
                 let mut buff = BytesMut::with_capacity(256);
                 for field in description.iter() {
-                    buff.put_slice(field.name.as_str().as_bytes());
+                    buff.put_slice(field.name.as_bytes());
                     buff.put_u8(0); // end of c string
                     buff.put_i32(0); // table id
                     buff.put_i16(0); // column id
                     buff.put_i32(field.type_id);
                     buff.put_i16(field.type_size);
-                    buff.put_i32(-1); // type modifier
+                    buff.put_i32(field.type_modifier);
                     buff.put_i16(0);
                 }
                 let mut len_buff = BytesMut::new();
@@ -131,34 +152,63 @@ impl Message {
                 command_buff.to_vec()
             }
             Message::EmptyQueryResponse => vec![EMPTY_QUERY_RESPONSE, 0, 0, 0, 4],
-            Message::ErrorResponse(severity, code, message) => {
-                let mut error_response_buff = BytesMut::with_capacity(256);
-                error_response_buff.put_u8(ERROR_RESPONSE);
-                let mut message_buff = BytesMut::with_capacity(256);
-                if let Some(severity) = severity.as_ref() {
-                    message_buff.put_u8(SEVERITY);
-                    message_buff.extend_from_slice(severity.as_bytes());
-                    message_buff.put_u8(0);
-                }
-                if let Some(code) = code.as_ref() {
-                    message_buff.put_u8(CODE);
-                    message_buff.extend_from_slice(code.as_bytes());
-                    message_buff.put_u8(0);
-                }
-                if let Some(message) = message.as_ref() {
-                    message_buff.put_u8(MESSAGE);
-                    message_buff.extend_from_slice(message.as_bytes());
-                    message_buff.put_u8(0);
+            Message::ErrorResponse(severity, code, message) => fields(ERROR_RESPONSE, severity, code, message),
+            Message::Notice(severity, code, message) => fields(NOTICE_RESPONSE, severity, code, message),
+            Message::ParameterStatus(name, value) => {
+                let mut parameter_status_buff = BytesMut::with_capacity(256);
+                parameter_status_buff.put_u8(PARAMETER_STATUS);
+                parameter_status_buff.put_i32(4 + name.len() as i32 + 1 + value.len() as i32 + 1);
+                parameter_status_buff.extend_from_slice(name.as_bytes());
+                parameter_status_buff.put_u8(0);
+                parameter_status_buff.extend_from_slice(value.as_bytes());
+                parameter_status_buff.put_u8(0);
+                parameter_status_buff.to_vec()
+            }
+            Message::ParseComplete => vec![PARSE_COMPLETE, 0, 0, 0, 4],
+            Message::BindComplete => vec![BIND_COMPLETE, 0, 0, 0, 4],
+            Message::CloseComplete => vec![CLOSE_COMPLETE, 0, 0, 0, 4],
+            Message::NoData => vec![NO_DATA, 0, 0, 0, 4],
+            Message::PortalSuspended => vec![PORTAL_SUSPENDED, 0, 0, 0, 4],
+            Message::ParameterDescription(type_ids) => {
+                let mut buff = BytesMut::with_capacity(256);
+                buff.put_u8(PARAMETER_DESCRIPTION);
+                buff.put_i32(6 + 4 * type_ids.len() as i32);
+                buff.put_i16(type_ids.len() as i16);
+                for type_id in type_ids {
+                    buff.put_i32(*type_id);
                 }
-                error_response_buff.put_i32(message_buff.len() as i32 + 4 + 1);
-                error_response_buff.extend_from_slice(message_buff.as_ref());
-                error_response_buff.put_u8(0);
-                error_response_buff.to_vec()
+                buff.to_vec()
             }
         }
     }
 }
 
+// error and notice messages have the same fields
+fn fields(tag: u8, severity: &Option<String>, code: &Option<String>, message: &Option<String>) -> Vec<u8> {
+    let mut response_buff = BytesMut::with_capacity(256);
+    response_buff.put_u8(tag);
+    let mut message_buff = BytesMut::with_capacity(256);
+    if let Some(severity) = severity.as_ref() {
+        message_buff.put_u8(SEVERITY);
+        message_buff.extend_from_slice(severity.as_bytes());
+        message_buff.put_u8(0);
+    }
+    if let Some(code) = code.as_ref() {
+        message_buff.put_u8(CODE);
+        message_buff.extend_from_slice(code.as_bytes());
+        message_buff.put_u8(0);
+    }
+    if let Some(message) = message.as_ref() {
+        message_buff.put_u8(MESSAGE);
+        message_buff.extend_from_slice(message.as_bytes());
+        message_buff.put_u8(0);
+    }
+    response_buff.put_i32(message_buff.len() as i32 + 4 + 1);
+    response_buff.extend_from_slice(message_buff.as_ref());
+    response_buff.put_u8(0);
+    response_buff.to_vec()
+}
+
 #[cfg(test)]
 mod serialized_messages {
     use super::*;
@@ -187,8 +237,24 @@ mod serialized_messages {
     #[test]
     fn ready_for_query() {
         assert_eq!(
-            Message::ReadyForQuery.as_vec(),
-            vec![READY_FOR_QUERY, 0, 0, 0, 5, EMPTY_QUERY_RESPONSE]
+            Message::ReadyForQuery(TransactionStatus::Idle).as_vec(),
+            vec![READY_FOR_QUERY, 0, 0, 0, 5, b'I']
+        )
+    }
+
+    #[test]
+    fn ready_for_query_in_transaction_block() {
+        assert_eq!(
+            Message::ReadyForQuery(TransactionStatus::InTransaction).as_vec(),
+            vec![READY_FOR_QUERY, 0, 0, 0, 5, b'T']
+        )
+    }
+
+    #[test]
+    fn ready_for_query_in_failed_transaction_block() {
+        assert_eq!(
+            Message::ReadyForQuery(TransactionStatus::Failed).as_vec(),
+            vec![READY_FOR_QUERY, 0, 0, 0, 5, b'E']
         )
     }
 
@@ -237,6 +303,46 @@ mod serialized_messages {
         );
     }
 
+    #[test]
+    fn row_description_with_type_modifier() {
+        assert_eq!(
+            Message::RowDescription(vec![
+                ColumnMetadata::new("c1".to_owned(), 1043, -1).with_type_modifier(14)
+            ])
+            .as_vec(),
+            vec![
+                ROW_DESCRIPTION,
+                0,
+                0,
+                0,
+                27,
+                0,
+                1,
+                99,
+                49,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                4,
+                19,
+                255,
+                255,
+                0,
+                0,
+                0,
+                14,
+                0,
+                0
+            ]
+        );
+    }
+
     #[test]
     fn command_complete() {
         assert_eq!(
@@ -260,4 +366,37 @@ mod serialized_messages {
             vec![ERROR_RESPONSE, 0, 0, 0, 5, 0]
         )
     }
+
+    #[test]
+    fn notice_with_fields() {
+        assert_eq!(
+            Message::Notice(Some("NOTICE".to_owned()), None, None).as_vec(),
+            vec![NOTICE_RESPONSE, 0, 0, 0, 13, SEVERITY, 78, 79, 84, 73, 67, 69, 0, 0]
+        )
+    }
+
+    #[test]
+    fn parameter_status() {
+        assert_eq!(
+            Message::ParameterStatus("a".to_owned(), "b".to_owned()).as_vec(),
+            vec![PARAMETER_STATUS, 0, 0, 0, 8, 97, 0, 98, 0]
+        )
+    }
+
+    #[test]
+    fn extended_query_completions() {
+        assert_eq!(Message::ParseComplete.as_vec(), vec![PARSE_COMPLETE, 0, 0, 0, 4]);
+        assert_eq!(Message::BindComplete.as_vec(), vec![BIND_COMPLETE, 0, 0, 0, 4]);
+        assert_eq!(Message::CloseComplete.as_vec(), vec![CLOSE_COMPLETE, 0, 0, 0, 4]);
+        assert_eq!(Message::NoData.as_vec(), vec![NO_DATA, 0, 0, 0, 4]);
+        assert_eq!(Message::PortalSuspended.as_vec(), vec![PORTAL_SUSPENDED, 0, 0, 0, 4]);
+    }
+
+    #[test]
+    fn parameter_description() {
+        assert_eq!(
+            Message::ParameterDescription(vec![23, 25]).as_vec(),
+            vec![PARAMETER_DESCRIPTION, 0, 0, 0, 14, 0, 2, 0, 0, 0, 23, 0, 0, 0, 25]
+        )
+    }
 }
@@ -0,0 +1,246 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use sql_types::SqlType;
+use std::collections::BTreeMap;
+use storage::Projection;
+
+/// name of the virtual table that exposes sessions of connected clients
+pub const PG_STAT_ACTIVITY: &str = "pg_stat_activity";
+
+/// Resource that a session is waiting on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaitEvent {
+    // waiting for a command from the client
+    ClientRead,
+    // waiting for the client to receive results
+    ClientWrite,
+    // waiting for another session to release storage
+    StorageLock,
+    // waiting for a transaction block of another session to end
+    TransactionLock,
+}
+
+impl WaitEvent {
+    fn event_type(self) -> &'static str {
+        match self {
+            WaitEvent::ClientRead | WaitEvent::ClientWrite => "Client",
+            WaitEvent::StorageLock | WaitEvent::TransactionLock => "Lock",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            WaitEvent::ClientRead => "ClientRead",
+            WaitEvent::ClientWrite => "ClientWrite",
+            WaitEvent::StorageLock => "storage",
+            WaitEvent::TransactionLock => "transactionid",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    user_name: String,
+    application_name: String,
+    query: Option<String>,
+    last_query: String,
+    wait_event: Option<WaitEvent>,
+}
+
+/// Keeps track of connected clients and queries they are running
+#[derive(Debug, Default)]
+pub struct SessionActivity {
+    sessions: BTreeMap<u64, Session>,
+}
+
+impl SessionActivity {
+    pub fn connect(&mut self, session_id: u64, user_name: &str, application_name: &str) {
+        self.sessions.insert(
+            session_id,
+            Session {
+                user_name: user_name.to_owned(),
+                application_name: application_name.to_owned(),
+                ..Session::default()
+            },
+        );
+    }
+
+    pub fn disconnect(&mut self, session_id: u64) {
+        self.sessions.remove(&session_id);
+    }
+
+    pub fn query_started(&mut self, session_id: u64, query: &str) {
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            session.query = Some(query.to_owned());
+        }
+    }
+
+    pub fn query_finished(&mut self, session_id: u64) {
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            if let Some(query) = session.query.take() {
+                session.last_query = query;
+            }
+        }
+    }
+
+    pub fn wait_started(&mut self, session_id: u64, wait_event: WaitEvent) {
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            session.wait_event = Some(wait_event);
+        }
+    }
+
+    pub fn wait_finished(&mut self, session_id: u64) {
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            session.wait_event = None;
+        }
+    }
+
+    pub fn projection(&self) -> Projection {
+        let columns = vec![
+            ("pid".to_owned(), SqlType::BigInt),
+            ("usename".to_owned(), SqlType::VarChar(63)),
+            ("application_name".to_owned(), SqlType::VarChar(63)),
+            ("wait_event_type".to_owned(), SqlType::VarChar(63)),
+            ("wait_event".to_owned(), SqlType::VarChar(63)),
+            ("state".to_owned(), SqlType::VarChar(6)),
+            ("query".to_owned(), SqlType::VarChar(255)),
+        ];
+        let records = self
+            .sessions
+            .iter()
+            .map(|(session_id, session)| {
+                let (state, query) = match &session.query {
+                    Some(query) => ("active", query.clone()),
+                    None => ("idle", session.last_query.clone()),
+                };
+                let (wait_event_type, wait_event) = match session.wait_event {
+                    Some(wait_event) => (wait_event.event_type(), wait_event.name()),
+                    None => ("", ""),
+                };
+                vec![
+                    session_id.to_string(),
+                    session.user_name.clone(),
+                    session.application_name.clone(),
+                    wait_event_type.to_owned(),
+                    wait_event.to_owned(),
+                    state.to_owned(),
+                    query,
+                ]
+            })
+            .collect();
+        (columns, records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connected_session_is_idle() {
+        let mut activity = SessionActivity::default();
+        activity.connect(1, "postgres", "psql");
+
+        assert_eq!(
+            activity.projection().1,
+            vec![vec![
+                "1".to_owned(),
+                "postgres".to_owned(),
+                "psql".to_owned(),
+                "".to_owned(),
+                "".to_owned(),
+                "idle".to_owned(),
+                "".to_owned()
+            ]]
+        );
+    }
+
+    #[test]
+    fn session_with_running_query_is_active() {
+        let mut activity = SessionActivity::default();
+        activity.connect(1, "postgres", "psql");
+        activity.query_started(1, "select * from pg_stat_activity;");
+
+        assert_eq!(
+            activity.projection().1,
+            vec![vec![
+                "1".to_owned(),
+                "postgres".to_owned(),
+                "psql".to_owned(),
+                "".to_owned(),
+                "".to_owned(),
+                "active".to_owned(),
+                "select * from pg_stat_activity;".to_owned()
+            ]]
+        );
+    }
+
+    #[test]
+    fn session_waiting_for_client() {
+        let mut activity = SessionActivity::default();
+        activity.connect(1, "postgres", "psql");
+        activity.wait_started(1, WaitEvent::ClientRead);
+
+        assert_eq!(
+            activity.projection().1,
+            vec![vec![
+                "1".to_owned(),
+                "postgres".to_owned(),
+                "psql".to_owned(),
+                "Client".to_owned(),
+                "ClientRead".to_owned(),
+                "idle".to_owned(),
+                "".to_owned()
+            ]]
+        );
+    }
+
+    #[test]
+    fn session_waiting_for_storage_lock() {
+        let mut activity = SessionActivity::default();
+        activity.connect(1, "postgres", "psql");
+        activity.query_started(1, "delete from schema_name.table_name;");
+        activity.wait_started(1, WaitEvent::StorageLock);
+
+        assert_eq!(
+            activity.projection().1,
+            vec![vec![
+                "1".to_owned(),
+                "postgres".to_owned(),
+                "psql".to_owned(),
+                "Lock".to_owned(),
+                "storage".to_owned(),
+                "active".to_owned(),
+                "delete from schema_name.table_name;".to_owned()
+            ]]
+        );
+
+        activity.wait_finished(1);
+
+        assert_eq!(activity.projection().1[0][3], "");
+        assert_eq!(activity.projection().1[0][4], "");
+    }
+
+    #[test]
+    fn disconnected_session_is_removed() {
+        let mut activity = SessionActivity::default();
+        activity.connect(1, "postgres", "psql");
+        activity.query_started(1, "select 1;");
+        activity.query_finished(1);
+        activity.disconnect(1);
+
+        assert_eq!(activity.projection().1, Vec::<Vec<String>>::new());
+    }
+}
@@ -0,0 +1,606 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    expression::{ColumnValue, Datum, Evaluator},
+    sort::encode_key,
+    QueryError,
+};
+use sql_types::SqlType;
+use sqlparser::ast::{Expr, Function, Ident};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Functions that fold values of rows of a group into a single value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    pub(crate) fn find(name: &str) -> Option<AggregateFunction> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(AggregateFunction::Count),
+            "sum" => Some(AggregateFunction::Sum),
+            "avg" => Some(AggregateFunction::Avg),
+            "min" => Some(AggregateFunction::Min),
+            "max" => Some(AggregateFunction::Max),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "count",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Avg => "avg",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+        }
+    }
+}
+
+/// Call of an aggregate function in a query, `count(*)` has no argument
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct AggregateCall {
+    function: AggregateFunction,
+    arg: Option<Expr>,
+    distinct: bool,
+}
+
+impl AggregateCall {
+    fn parse(function: &Function, raw_sql_query: &str) -> Result<Option<AggregateCall>, QueryError> {
+        let aggregate = match AggregateFunction::find(&function.name.to_string()) {
+            Some(aggregate) => aggregate,
+            None => return Ok(None),
+        };
+        if function.over.is_some() {
+            return Err(QueryError::not_supported_operation(raw_sql_query.to_owned()));
+        }
+        let arg = match (aggregate, function.args.as_slice()) {
+            (AggregateFunction::Count, [Expr::Wildcard]) if !function.distinct => None,
+            (_, [arg]) if *arg != Expr::Wildcard => Some(arg.clone()),
+            (_, args) => {
+                return Err(QueryError::undefined_function(format!(
+                    "{}({})",
+                    aggregate.name(),
+                    vec!["unknown"; args.len()].join(", ")
+                )))
+            }
+        };
+        if arg.as_ref().map(contains_aggregate) == Some(true) {
+            return Err(QueryError::nested_aggregate());
+        }
+        Ok(Some(AggregateCall {
+            function: aggregate,
+            arg,
+            distinct: function.distinct,
+        }))
+    }
+
+    /// expression that the function is applied to
+    pub(crate) fn arg(&self) -> Option<&Expr> {
+        self.arg.as_ref()
+    }
+
+    /// type of values that the function gives, `None` when it is the type of
+    /// values that are aggregated, which is known only when they are given
+    pub(crate) fn sql_type(&self, arg_type: Option<SqlType>) -> Option<SqlType> {
+        match (self.function, arg_type) {
+            (AggregateFunction::Count, _) => Some(SqlType::BigInt),
+            (AggregateFunction::Sum, Some(SqlType::SmallInt))
+            | (AggregateFunction::Sum, Some(SqlType::Integer))
+            | (AggregateFunction::Sum, Some(SqlType::BigInt)) => Some(SqlType::BigInt),
+            (AggregateFunction::Sum, Some(SqlType::Real)) | (AggregateFunction::Avg, Some(SqlType::Real)) => {
+                Some(SqlType::DoublePrecision)
+            }
+            (AggregateFunction::Avg, Some(SqlType::DoublePrecision)) => Some(SqlType::DoublePrecision),
+            (AggregateFunction::Avg, Some(_)) => Some(SqlType::Decimal),
+            (_, arg_type) => arg_type,
+        }
+    }
+}
+
+/// whether an expression calls any of aggregate functions
+pub(crate) fn contains_aggregate(expr: &Expr) -> bool {
+    let mut found = false;
+    visit(expr, &mut |expr| {
+        if let Expr::Function(function) = expr {
+            found = found || AggregateFunction::find(&function.name.to_string()).is_some();
+        }
+    });
+    found
+}
+
+/// calls of aggregate functions that are not already among the given ones
+pub(crate) fn collect_calls(
+    expr: &Expr,
+    calls: &mut Vec<AggregateCall>,
+    raw_sql_query: &str,
+) -> Result<(), QueryError> {
+    match expr {
+        Expr::Function(function) => match AggregateCall::parse(function, raw_sql_query)? {
+            Some(call) => {
+                if !calls.contains(&call) {
+                    calls.push(call);
+                }
+                Ok(())
+            }
+            None => function
+                .args
+                .iter()
+                .try_for_each(|arg| collect_calls(arg, calls, raw_sql_query)),
+        },
+        expr => children(expr)
+            .into_iter()
+            .try_for_each(|child| collect_calls(child, calls, raw_sql_query)),
+    }
+}
+
+/// name of the column that values of GROUP BY expression are given in after rows are grouped
+pub(crate) fn group_column(index: usize) -> String {
+    format!("#group{}", index)
+}
+
+/// name of the column that values of aggregate function are given in after rows are grouped
+pub(crate) fn aggregate_column(index: usize) -> String {
+    format!("#aggregate{}", index)
+}
+
+/// Expression that is evaluated with rows of groups, GROUP BY expressions
+/// and calls of aggregate functions refer to columns of these rows. Other
+/// columns have no single value in a group
+pub(crate) fn grouped(expr: &Expr, group_by: &[Expr], calls: &[AggregateCall]) -> Result<Expr, QueryError> {
+    if let Some(index) = group_by.iter().position(|group| same_expr(group, expr)) {
+        return Ok(Expr::Identifier(Ident::new(group_column(index))));
+    }
+    let boxed = |expr: &Expr| grouped(expr, group_by, calls).map(Box::new);
+    let rewritten = match expr {
+        Expr::Identifier(Ident { value, .. }) => return Err(QueryError::ungrouped_column(value.clone())),
+        Expr::CompoundIdentifier(idents) => {
+            let name = idents
+                .iter()
+                .map(|ident| ident.value.as_str())
+                .collect::<Vec<_>>()
+                .join(".");
+            return Err(QueryError::ungrouped_column(name));
+        }
+        Expr::Function(function) => {
+            let call = AggregateCall::parse(function, "").ok().flatten();
+            match calls.iter().position(|known| call.as_ref() == Some(known)) {
+                Some(index) => Expr::Identifier(Ident::new(aggregate_column(index))),
+                None => Expr::Function(Function {
+                    args: function
+                        .args
+                        .iter()
+                        .map(|arg| grouped(arg, group_by, calls))
+                        .collect::<Result<_, _>>()?,
+                    ..function.clone()
+                }),
+            }
+        }
+        Expr::IsNull(expr) => Expr::IsNull(boxed(expr)?),
+        Expr::IsNotNull(expr) => Expr::IsNotNull(boxed(expr)?),
+        Expr::Nested(expr) => Expr::Nested(boxed(expr)?),
+        Expr::Cast { expr, data_type } => Expr::Cast {
+            expr: boxed(expr)?,
+            data_type: data_type.clone(),
+        },
+        Expr::UnaryOp { op, expr } => Expr::UnaryOp {
+            op: op.clone(),
+            expr: boxed(expr)?,
+        },
+        Expr::BinaryOp { left, op, right } => Expr::BinaryOp {
+            left: boxed(left)?,
+            op: op.clone(),
+            right: boxed(right)?,
+        },
+        Expr::Between {
+            expr,
+            negated,
+            low,
+            high,
+        } => Expr::Between {
+            expr: boxed(expr)?,
+            negated: *negated,
+            low: boxed(low)?,
+            high: boxed(high)?,
+        },
+        Expr::InList { expr, list, negated } => Expr::InList {
+            expr: boxed(expr)?,
+            list: list
+                .iter()
+                .map(|item| grouped(item, group_by, calls))
+                .collect::<Result<_, _>>()?,
+            negated: *negated,
+        },
+        expr => expr.clone(),
+    };
+    Ok(rewritten)
+}
+
+// a column can be referred to with or without a table name
+fn same_expr(left: &Expr, right: &Expr) -> bool {
+    let column = |expr: &Expr| match expr {
+        Expr::Identifier(Ident { value, .. }) => Some(value.clone()),
+        Expr::CompoundIdentifier(idents) => idents.last().map(|ident| ident.value.clone()),
+        _ => None,
+    };
+    match (column(left), column(right)) {
+        (Some(left), Some(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+fn visit<F: FnMut(&Expr)>(expr: &Expr, f: &mut F) {
+    f(expr);
+    for child in children(expr) {
+        visit(child, f);
+    }
+}
+
+fn children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::IsNull(expr)
+        | Expr::IsNotNull(expr)
+        | Expr::Nested(expr)
+        | Expr::Cast { expr, .. }
+        | Expr::UnaryOp { expr, .. } => vec![expr],
+        Expr::BinaryOp { left, right, .. } => vec![left, right],
+        Expr::Between { expr, low, high, .. } => vec![expr, low, high],
+        Expr::InList { expr, list, .. } => {
+            let mut children = vec![&**expr];
+            children.extend(list.iter());
+            children
+        }
+        Expr::Function(function) => function.args.iter().collect(),
+        _ => vec![],
+    }
+}
+
+/// State of an aggregate function for rows of a group that are seen so far,
+/// nulls are skipped by all of them
+enum Accumulator {
+    Count(i64),
+    Sum(Option<Total>),
+    Avg(Option<Total>, i64),
+    Min(Option<(Vec<u8>, Datum)>),
+    Max(Option<(Vec<u8>, Datum)>),
+}
+
+// integers are summed up in a wider type, so only the total can overflow
+#[derive(Clone, Copy)]
+enum Total {
+    Integer(i128),
+    Float(f64),
+    Numeric(f64),
+}
+
+impl Accumulator {
+    fn new(function: AggregateFunction) -> Accumulator {
+        match function {
+            AggregateFunction::Count => Accumulator::Count(0),
+            AggregateFunction::Sum => Accumulator::Sum(None),
+            AggregateFunction::Avg => Accumulator::Avg(None, 0),
+            AggregateFunction::Min => Accumulator::Min(None),
+            AggregateFunction::Max => Accumulator::Max(None),
+        }
+    }
+
+    fn add(&mut self, datum: Datum) -> Result<(), QueryError> {
+        if datum == Datum::Null {
+            return Ok(());
+        }
+        match self {
+            Accumulator::Count(count) => *count += 1,
+            Accumulator::Sum(total) => *total = Some(add(*total, datum, AggregateFunction::Sum)?),
+            Accumulator::Avg(total, count) => {
+                *total = Some(add(*total, datum, AggregateFunction::Avg)?);
+                *count += 1;
+            }
+            Accumulator::Min(min) => {
+                let key = order_key(&datum);
+                if min.as_ref().map(|(least, _datum)| key < *least) != Some(false) {
+                    *min = Some((key, datum));
+                }
+            }
+            Accumulator::Max(max) => {
+                let key = order_key(&datum);
+                if max.as_ref().map(|(greatest, _datum)| key > *greatest) != Some(false) {
+                    *max = Some((key, datum));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // functions give null for a group without values, except `count`
+    fn finish(self) -> Result<Datum, QueryError> {
+        match self {
+            Accumulator::Count(count) => Ok(Datum::Integer(count)),
+            Accumulator::Sum(None) | Accumulator::Avg(None, _) => Ok(Datum::Null),
+            Accumulator::Sum(Some(Total::Integer(total))) => {
+                if total >= i64::MIN as i128 && total <= i64::MAX as i128 {
+                    Ok(Datum::Integer(total as i64))
+                } else {
+                    Err(QueryError::numeric_value_out_of_range("bigint".to_owned()))
+                }
+            }
+            Accumulator::Sum(Some(Total::Float(total))) => Ok(Datum::Float(total)),
+            Accumulator::Sum(Some(Total::Numeric(total))) => Ok(Datum::Numeric(total)),
+            Accumulator::Avg(Some(Total::Integer(total)), count) => Ok(Datum::Numeric(total as f64 / count as f64)),
+            Accumulator::Avg(Some(Total::Float(total)), count) => Ok(Datum::Float(total / count as f64)),
+            Accumulator::Avg(Some(Total::Numeric(total)), count) => Ok(Datum::Numeric(total / count as f64)),
+            Accumulator::Min(value) | Accumulator::Max(value) => {
+                Ok(value.map(|(_key, datum)| datum).unwrap_or(Datum::Null))
+            }
+        }
+    }
+}
+
+fn add(total: Option<Total>, datum: Datum, function: AggregateFunction) -> Result<Total, QueryError> {
+    let total = match (total, datum) {
+        (None, Datum::Integer(value)) => Total::Integer(value as i128),
+        (None, Datum::Float(value)) => Total::Float(value),
+        (None, Datum::Numeric(value)) => Total::Numeric(value),
+        (Some(Total::Integer(total)), Datum::Integer(value)) => Total::Integer(total + value as i128),
+        (Some(Total::Float(total)), Datum::Float(value)) => Total::Float(total + value),
+        (Some(Total::Numeric(total)), Datum::Numeric(value)) => Total::Numeric(total + value),
+        (_, datum) => {
+            return Err(QueryError::undefined_function(format!(
+                "{}({})",
+                function.name(),
+                datum.type_name()
+            )))
+        }
+    };
+    match total {
+        Total::Float(value) | Total::Numeric(value) if value.is_infinite() => Err(QueryError::value_out_of_range(
+            "value out of range: overflow".to_owned(),
+        )),
+        total => Ok(total),
+    }
+}
+
+// values are compared in the order they are sorted in
+fn order_key(datum: &Datum) -> Vec<u8> {
+    let mut key = vec![];
+    encode_key(datum, false, false, &mut key);
+    key
+}
+
+// rows of a group that are seen so far
+struct Group {
+    values: Vec<Datum>,
+    accumulators: Vec<Accumulator>,
+    // values of DISTINCT calls that are already aggregated
+    seen: Vec<BTreeSet<Vec<u8>>>,
+}
+
+/// Aggregate operator, rows are put into groups by values of GROUP BY
+/// expressions and values of aggregate functions are computed for each group
+pub(crate) struct Aggregate<'q> {
+    group_by: &'q [Expr],
+    calls: &'q [AggregateCall],
+    groups: BTreeMap<Vec<u8>, Group>,
+}
+
+impl<'q> Aggregate<'q> {
+    pub(crate) fn new(group_by: &'q [Expr], calls: &'q [AggregateCall]) -> Aggregate<'q> {
+        Aggregate {
+            group_by,
+            calls,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn add<V: ColumnValue>(&mut self, evaluator: &Evaluator, row: &[V]) -> Result<(), QueryError> {
+        let mut key = vec![];
+        let mut values = vec![];
+        for expr in self.group_by {
+            let datum = evaluator.row_value(expr, row)?;
+            encode_key(&datum, false, false, &mut key);
+            values.push(datum);
+        }
+        let calls = self.calls;
+        let group = self.groups.entry(key).or_insert_with(|| Group {
+            values,
+            accumulators: calls.iter().map(|call| Accumulator::new(call.function)).collect(),
+            seen: vec![BTreeSet::new(); calls.len()],
+        });
+        for (index, call) in calls.iter().enumerate() {
+            let datum = match &call.arg {
+                Some(arg) => evaluator.row_value(arg, row)?,
+                // `count(*)` counts rows whatever values they have
+                None => Datum::Boolean(true),
+            };
+            if call.distinct && !group.seen[index].insert(order_key(&datum)) {
+                continue;
+            }
+            group.accumulators[index].add(datum)?;
+        }
+        Ok(())
+    }
+
+    /// rows of groups in order of GROUP BY values, values of aggregate
+    /// functions follow values of GROUP BY expressions. Without GROUP BY all
+    /// rows are a single group even when there are none
+    pub(crate) fn finish(mut self) -> Result<Vec<Vec<Datum>>, QueryError> {
+        if self.group_by.is_empty() && self.groups.is_empty() {
+            self.groups.insert(
+                vec![],
+                Group {
+                    values: vec![],
+                    accumulators: self.calls.iter().map(|call| Accumulator::new(call.function)).collect(),
+                    seen: vec![],
+                },
+            );
+        }
+        let mut rows = vec![];
+        for (_key, group) in self.groups {
+            let mut row = group.values;
+            for accumulator in group.accumulators {
+                row.push(accumulator.finish()?);
+            }
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datetime::{DateStyle, TimeZone};
+    use sqlparser::ast::ObjectName;
+
+    fn call(name: &str, args: Vec<Expr>) -> Expr {
+        Expr::Function(Function {
+            name: ObjectName(vec![Ident::new(name)]),
+            args,
+            over: None,
+            distinct: false,
+        })
+    }
+
+    fn column(name: &str) -> Expr {
+        Expr::Identifier(Ident::new(name))
+    }
+
+    fn aggregated(group_by: Vec<Expr>, exprs: Vec<Expr>, rows: Vec<Vec<&str>>) -> Result<Vec<Vec<Datum>>, QueryError> {
+        let columns = vec![
+            ("name".to_owned(), SqlType::VarChar(10)),
+            ("value".to_owned(), SqlType::BigInt),
+        ];
+        let evaluator = Evaluator::new(DateStyle::default(), TimeZone::default(), "").with_columns(&columns);
+        let mut calls = vec![];
+        for expr in &exprs {
+            collect_calls(expr, &mut calls, "")?;
+        }
+        let mut aggregate = Aggregate::new(&group_by, &calls);
+        for row in rows {
+            let row: Vec<String> = row.into_iter().map(str::to_owned).collect();
+            aggregate.add(&evaluator, &row)?;
+        }
+        aggregate.finish()
+    }
+
+    #[test]
+    fn aggregates_of_groups() {
+        assert_eq!(
+            aggregated(
+                vec![column("name")],
+                vec![
+                    call("count", vec![Expr::Wildcard]),
+                    call("sum", vec![column("value")]),
+                    call("max", vec![column("value")]),
+                ],
+                vec![vec!["b", "2"], vec!["a", "5"], vec!["b", "-7"]]
+            ),
+            Ok(vec![
+                vec![
+                    Datum::Text("a".to_owned()),
+                    Datum::Integer(1),
+                    Datum::Integer(5),
+                    Datum::Integer(5)
+                ],
+                vec![
+                    Datum::Text("b".to_owned()),
+                    Datum::Integer(2),
+                    Datum::Integer(-5),
+                    Datum::Integer(2)
+                ],
+            ])
+        );
+    }
+
+    #[test]
+    fn no_rows_without_group_by() {
+        assert_eq!(
+            aggregated(
+                vec![],
+                vec![
+                    call("count", vec![column("value")]),
+                    call("avg", vec![column("value")]),
+                    call("min", vec![column("name")]),
+                ],
+                vec![]
+            ),
+            Ok(vec![vec![Datum::Integer(0), Datum::Null, Datum::Null]])
+        );
+        assert_eq!(
+            aggregated(vec![column("name")], vec![call("count", vec![Expr::Wildcard])], vec![]),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn average_of_integers() {
+        assert_eq!(
+            aggregated(
+                vec![],
+                vec![call("avg", vec![column("value")])],
+                vec![vec!["a", "1"], vec!["b", "2"]]
+            ),
+            Ok(vec![vec![Datum::Numeric(1.5)]])
+        );
+    }
+
+    #[test]
+    fn sum_out_of_range() {
+        let max = i64::MAX.to_string();
+        assert_eq!(
+            aggregated(
+                vec![],
+                vec![call("sum", vec![column("value")])],
+                vec![vec!["a", &max], vec!["b", "1"]]
+            ),
+            Err(QueryError::numeric_value_out_of_range("bigint".to_owned()))
+        );
+        assert_eq!(
+            aggregated(vec![], vec![call("sum", vec![column("name")])], vec![vec!["a", "1"]]),
+            Err(QueryError::undefined_function("sum(text)".to_owned()))
+        );
+    }
+
+    #[test]
+    fn nested_calls() {
+        assert_eq!(
+            aggregated(
+                vec![],
+                vec![call("sum", vec![call("count", vec![Expr::Wildcard])])],
+                vec![]
+            ),
+            Err(QueryError::nested_aggregate())
+        );
+    }
+
+    #[test]
+    fn ungrouped_columns() {
+        let group_by = vec![column("name")];
+        let calls = vec![];
+
+        assert_eq!(
+            grouped(&column("name"), &group_by, &calls),
+            Ok(Expr::Identifier(Ident::new(group_column(0))))
+        );
+        assert_eq!(
+            grouped(&column("value"), &group_by, &calls),
+            Err(QueryError::ungrouped_column("value".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::QueryError;
+
+/// `ALTER TABLE` that changes columns or name of a table, sql parser knows
+/// only changes of constraints
+#[derive(Debug, PartialEq)]
+pub(crate) struct AlterTable {
+    pub(crate) name: String,
+    pub(crate) action: AlterTableAction,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum AlterTableAction {
+    // definition of the column as it is written in `CREATE TABLE`
+    AddColumn(String),
+    DropColumn(String),
+    RenameColumn { from: String, to: String },
+    RenameTo(String),
+}
+
+/// returns `None` if the query does not change columns or name of a table
+pub(crate) fn parse(raw_sql_query: &str) -> Option<Result<AlterTable, QueryError>> {
+    let statement = raw_sql_query.trim().trim_end_matches(';').trim_end();
+    let rest = keyword(keyword(statement, "alter")?, "table")?;
+    let (name, rest) = word(rest);
+    let action = if let Some(rest) = keyword(rest, "add") {
+        if keyword(rest, "constraint").is_some() {
+            return None;
+        }
+        add_column(keyword(rest, "column").unwrap_or(rest))
+    } else if let Some(rest) = keyword(rest, "drop") {
+        if keyword(rest, "constraint").is_some() {
+            return None;
+        }
+        drop_column(keyword(rest, "column").unwrap_or(rest))
+    } else if let Some(rest) = keyword(rest, "rename") {
+        match keyword(rest, "to") {
+            Some(rest) => rename_to(rest),
+            None => rename_column(keyword(rest, "column").unwrap_or(rest)),
+        }
+    } else {
+        return None;
+    };
+    Some(action.map(|action| AlterTable {
+        name: identifier(name),
+        action,
+    }))
+}
+
+fn add_column(definition: &str) -> Result<AlterTableAction, QueryError> {
+    if definition.is_empty() {
+        Err(unexpected("column definition", ""))
+    } else {
+        Ok(AlterTableAction::AddColumn(definition.to_owned()))
+    }
+}
+
+fn drop_column(rest: &str) -> Result<AlterTableAction, QueryError> {
+    let (column, rest) = word(rest);
+    if column.is_empty() {
+        return Err(unexpected("column name", column));
+    }
+    end(rest)?;
+    Ok(AlterTableAction::DropColumn(identifier(column)))
+}
+
+fn rename_column(rest: &str) -> Result<AlterTableAction, QueryError> {
+    let (from, rest) = word(rest);
+    let rest = match keyword(rest, "to") {
+        Some(rest) => rest,
+        None => return Err(unexpected("TO", word(rest).0)),
+    };
+    let (to, rest) = word(rest);
+    if to.is_empty() {
+        return Err(unexpected("column name", to));
+    }
+    end(rest)?;
+    Ok(AlterTableAction::RenameColumn {
+        from: identifier(from),
+        to: identifier(to),
+    })
+}
+
+fn rename_to(rest: &str) -> Result<AlterTableAction, QueryError> {
+    let (name, rest) = word(rest);
+    if name.is_empty() {
+        return Err(unexpected("table name", name));
+    }
+    end(rest)?;
+    Ok(AlterTableAction::RenameTo(identifier(name)))
+}
+
+fn end(rest: &str) -> Result<(), QueryError> {
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(unexpected("end of statement", word(rest).0))
+    }
+}
+
+// keyword has to be followed by a whitespace
+fn keyword<'q>(statement: &'q str, keyword: &str) -> Option<&'q str> {
+    let prefix = statement.get(..keyword.len())?;
+    let rest = &statement[keyword.len()..];
+    if prefix.eq_ignore_ascii_case(keyword) && (rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+fn word(statement: &str) -> (&str, &str) {
+    let end = statement.find(char::is_whitespace).unwrap_or(statement.len());
+    let (word, rest) = statement.split_at(end);
+    (word, rest.trim_start())
+}
+
+// quoted identifiers keep their case
+fn identifier(name: &str) -> String {
+    name.split('.')
+        .map(|part| part.trim_matches('"'))
+        .collect::<Vec<&str>>()
+        .join(".")
+}
+
+fn unexpected(expected: &str, found: &str) -> QueryError {
+    let found = if found.is_empty() { "EOF" } else { found };
+    QueryError::syntax_error(format!("Expected {}, found: {}", expected, found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alter_table(name: &str, action: AlterTableAction) -> Option<Result<AlterTable, QueryError>> {
+        Some(Ok(AlterTable {
+            name: name.to_owned(),
+            action,
+        }))
+    }
+
+    #[test]
+    fn not_a_column_change() {
+        assert_eq!(parse("create table schema_name.table_name (id integer);"), None);
+        assert_eq!(
+            parse("alter table schema_name.table_name add constraint id_check check (id > 0);"),
+            None
+        );
+        assert_eq!(
+            parse("alter table schema_name.table_name drop constraint id_check;"),
+            None
+        );
+    }
+
+    #[test]
+    fn add_column() {
+        assert_eq!(
+            parse("alter table schema_name.table_name add column age smallint default 18;"),
+            alter_table(
+                "schema_name.table_name",
+                AlterTableAction::AddColumn("age smallint default 18".to_owned())
+            )
+        );
+        assert_eq!(
+            parse("ALTER TABLE table_name ADD name varchar(10) NOT NULL DEFAULT 'a  b'"),
+            alter_table(
+                "table_name",
+                AlterTableAction::AddColumn("name varchar(10) NOT NULL DEFAULT 'a  b'".to_owned())
+            )
+        );
+        assert_eq!(
+            parse("alter table table_name add column;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected column definition, found: EOF".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn drop_column() {
+        assert_eq!(
+            parse("alter table schema_name.table_name drop column age;"),
+            alter_table("schema_name.table_name", AlterTableAction::DropColumn("age".to_owned()))
+        );
+        assert_eq!(
+            parse("alter table table_name drop \"Age\""),
+            alter_table("table_name", AlterTableAction::DropColumn("Age".to_owned()))
+        );
+        assert_eq!(
+            parse("alter table table_name drop column age cascade;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected end of statement, found: cascade".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn rename_column() {
+        assert_eq!(
+            parse("alter table table_name rename column name to title;"),
+            alter_table(
+                "table_name",
+                AlterTableAction::RenameColumn {
+                    from: "name".to_owned(),
+                    to: "title".to_owned()
+                }
+            )
+        );
+        assert_eq!(
+            parse("alter table table_name rename name title;"),
+            Some(Err(QueryError::syntax_error("Expected TO, found: title".to_owned())))
+        );
+    }
+
+    #[test]
+    fn rename_table() {
+        assert_eq!(
+            parse("ALTER TABLE schema_name.table_name RENAME TO new_name;"),
+            alter_table(
+                "schema_name.table_name",
+                AlterTableAction::RenameTo("new_name".to_owned())
+            )
+        );
+        assert_eq!(
+            parse("alter table table_name rename to;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected table name, found: EOF".to_owned()
+            )))
+        );
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// `SET` of a custom parameter, sql parser does not know qualified parameter names
+#[derive(Debug, PartialEq)]
+pub(crate) struct CustomSetting {
+    pub(crate) name: String,
+    pub(crate) value: String,
+    pub(crate) local: bool,
+}
+
+/// returns `None` if the query does not set a parameter with qualified name
+pub(crate) fn parse(raw_sql_query: &str) -> Option<CustomSetting> {
+    let statement = raw_sql_query.trim().trim_end_matches(';').trim_end();
+    let rest = keyword(statement, "set")?;
+    let (local, rest) = match keyword(rest, "local") {
+        Some(rest) => (true, rest),
+        None => (false, keyword(rest, "session").unwrap_or(rest)),
+    };
+    let name_end = rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len());
+    let (name, rest) = rest.split_at(name_end);
+    if !is_qualified(name) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let rest = match rest.find('=') {
+        Some(0) => rest[1..].trim_start(),
+        _ => keyword(rest, "to")?,
+    };
+    Some(CustomSetting {
+        name: name.to_owned(),
+        value: value(rest)?,
+        local,
+    })
+}
+
+// keyword has to be followed by a whitespace or a quote
+fn keyword<'q>(statement: &'q str, keyword: &str) -> Option<&'q str> {
+    let prefix = statement.get(..keyword.len())?;
+    let rest = &statement[keyword.len()..];
+    if prefix.eq_ignore_ascii_case(keyword) && (rest.starts_with(char::is_whitespace) || rest.starts_with('\'')) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+fn is_qualified(name: &str) -> bool {
+    let parts = name.split('.').collect::<Vec<&str>>();
+    parts.len() > 1
+        && parts.iter().all(|part| {
+            part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        })
+}
+
+// quoted strings, numbers and words are accepted as values
+fn value(value: &str) -> Option<String> {
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        let quoted = &value[1..value.len() - 1];
+        if quoted.replace("''", "").contains('\'') {
+            None
+        } else {
+            Some(quoted.replace("''", "'"))
+        }
+    } else if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+    {
+        Some(value.to_owned())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setting(name: &str, value: &str, local: bool) -> Option<CustomSetting> {
+        Some(CustomSetting {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            local,
+        })
+    }
+
+    #[test]
+    fn set_custom_parameter() {
+        assert_eq!(
+            parse("set my.app_tenant = '42';"),
+            setting("my.app_tenant", "42", false)
+        );
+        assert_eq!(parse("SET my.app_tenant TO 42"), setting("my.app_tenant", "42", false));
+        assert_eq!(
+            parse("set my.app_tenant='it''s'"),
+            setting("my.app_tenant", "it's", false)
+        );
+    }
+
+    #[test]
+    fn set_custom_parameter_for_session_and_transaction() {
+        assert_eq!(
+            parse("set session my.app_tenant = tenant_name;"),
+            setting("my.app_tenant", "tenant_name", false)
+        );
+        assert_eq!(
+            parse("set local my.app_tenant = '42';"),
+            setting("my.app_tenant", "42", true)
+        );
+    }
+
+    #[test]
+    fn not_qualified_parameters_are_left_to_sql_parser() {
+        assert_eq!(parse("set search_path = schema_name;"), None);
+        assert_eq!(parse("set local search_path to public;"), None);
+        assert_eq!(parse("settle my.app_tenant = '42';"), None);
+        assert_eq!(parse("select current_setting('my.app_tenant');"), None);
+    }
+
+    #[test]
+    fn malformed_statements_are_left_to_sql_parser() {
+        assert_eq!(parse("set my.app_tenant '42';"), None);
+        assert_eq!(parse("set my.app_tenant = 'unterminated;"), None);
+        assert_eq!(parse("set my. = '42';"), None);
+        assert_eq!(parse("set my.app_tenant = ;"), None);
+    }
+}
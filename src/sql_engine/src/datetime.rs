@@ -0,0 +1,1014 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::QueryError;
+use chrono::{
+    Datelike, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone as _, Timelike,
+    Utc,
+};
+use chrono_tz::{OffsetName, Tz, TZ_VARIANTS};
+use sql_types::{format_timestamp, parse_timestamp, parse_timestamp_with_time_zone, Interval, SqlType};
+use std::fmt::{self, Display, Formatter};
+
+/// format of dates and times that are sent to client
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OutputStyle {
+    Iso,
+    Sql,
+    Postgres,
+    German,
+}
+
+/// order of fields in dates that are ambiguous, e.g. `01/02/2020`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FieldOrder {
+    Ymd,
+    Dmy,
+    Mdy,
+}
+
+/// Value of `DateStyle` run-time parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DateStyle {
+    output: OutputStyle,
+    order: FieldOrder,
+}
+
+impl Default for DateStyle {
+    fn default() -> DateStyle {
+        DateStyle {
+            output: OutputStyle::Iso,
+            order: FieldOrder::Mdy,
+        }
+    }
+}
+
+impl DateStyle {
+    /// value can change either output style or field order, e.g. `DMY`, the
+    /// other one is taken from the current style
+    pub(crate) fn parse(value: &str, current: DateStyle) -> Option<DateStyle> {
+        let mut output = None;
+        let mut order = None;
+        for part in value.split(',').map(str::trim) {
+            let is_set = match part.to_lowercase().as_str() {
+                "iso" => set(&mut output, OutputStyle::Iso),
+                "sql" => set(&mut output, OutputStyle::Sql),
+                "postgres" => set(&mut output, OutputStyle::Postgres),
+                "german" => set(&mut output, OutputStyle::German),
+                "ymd" => set(&mut order, FieldOrder::Ymd),
+                "dmy" | "euro" | "european" => set(&mut order, FieldOrder::Dmy),
+                "mdy" | "us" | "noneuro" | "noneuropean" => set(&mut order, FieldOrder::Mdy),
+                _ => false,
+            };
+            if !is_set {
+                return None;
+            }
+        }
+        // german style puts day before month unless order is given
+        let order = match (order, output) {
+            (Some(order), _) => order,
+            (None, Some(OutputStyle::German)) => FieldOrder::Dmy,
+            (None, _) => current.order,
+        };
+        Some(DateStyle {
+            output: output.unwrap_or(current.output),
+            order,
+        })
+    }
+}
+
+// conflicting values of the same component are not allowed
+fn set<T: PartialEq>(component: &mut Option<T>, value: T) -> bool {
+    match component {
+        Some(current) if *current != value => false,
+        _ => {
+            *component = Some(value);
+            true
+        }
+    }
+}
+
+impl Display for DateStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let output = match self.output {
+            OutputStyle::Iso => "ISO",
+            OutputStyle::Sql => "SQL",
+            OutputStyle::Postgres => "Postgres",
+            OutputStyle::German => "German",
+        };
+        let order = match self.order {
+            FieldOrder::Ymd => "YMD",
+            FieldOrder::Dmy => "DMY",
+            FieldOrder::Mdy => "MDY",
+        };
+        write!(f, "{}, {}", output, order)
+    }
+}
+
+/// Value of `TimeZone` run-time parameter, either a region from time zone
+/// database, e.g. `Europe/Kiev`, or a fixed offset from UTC in hours, that is
+/// positive east of Greenwich, e.g. `+03` or `-05:30`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TimeZone {
+    Region(Tz),
+    Offset(FixedOffset),
+}
+
+impl Default for TimeZone {
+    fn default() -> TimeZone {
+        TimeZone::Region(Tz::UTC)
+    }
+}
+
+impl TimeZone {
+    /// region names are case insensitive, `LOCAL` and `DEFAULT` stand for
+    /// time zone of the server that is always UTC
+    pub(crate) fn parse(value: &str) -> Option<TimeZone> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("local") || value.eq_ignore_ascii_case("default") {
+            return Some(TimeZone::default());
+        }
+        match TZ_VARIANTS.iter().find(|tz| tz.name().eq_ignore_ascii_case(value)) {
+            Some(tz) => Some(TimeZone::Region(*tz)),
+            None => fixed_offset(value).map(TimeZone::Offset),
+        }
+    }
+
+    pub(crate) fn name(&self) -> String {
+        match self {
+            TimeZone::Region(tz) => tz.name().to_owned(),
+            TimeZone::Offset(offset) => format_offset(offset),
+        }
+    }
+
+    // offset and its abbreviation at the moment
+    fn offset(&self, utc: &NaiveDateTime) -> (FixedOffset, String) {
+        match self {
+            TimeZone::Region(tz) => {
+                let offset = tz.offset_from_utc_datetime(utc);
+                (offset.fix(), offset.abbreviation().to_owned())
+            }
+            TimeZone::Offset(offset) => (*offset, format_offset(offset)),
+        }
+    }
+
+    pub(crate) fn local(&self, utc: &NaiveDateTime) -> NaiveDateTime {
+        let (offset, _abbreviation) = self.offset(utc);
+        *utc + Duration::seconds(offset.local_minus_utc() as i64)
+    }
+
+    // local time that does not exist due to daylight saving transition is
+    // taken with the offset that was before the transition, ambiguous local
+    // time is taken with the earlier offset
+    pub(crate) fn utc(&self, local: &NaiveDateTime) -> NaiveDateTime {
+        let offset = match self {
+            TimeZone::Region(tz) => match tz.offset_from_local_datetime(local) {
+                LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => offset.fix(),
+                LocalResult::None => tz.offset_from_utc_datetime(&(*local - Duration::days(1))).fix(),
+            },
+            TimeZone::Offset(offset) => *offset,
+        };
+        *local - Duration::seconds(offset.local_minus_utc() as i64)
+    }
+}
+
+/// translates a value that client sent into the format storage keeps, values
+/// of timestamp types are kept in ISO format and in UTC if they have time zone,
+/// intervals are kept in `postgres` style
+pub(crate) fn input(value: String, sql_type: SqlType, style: DateStyle, zone: TimeZone) -> Result<String, QueryError> {
+    match sql_type {
+        SqlType::Timestamp => Ok(format_timestamp(&timestamp(&value, sql_type, style, zone)?)),
+        SqlType::TimestampWithTimeZone => Ok(format!(
+            "{}+00",
+            format_timestamp(&timestamp(&value, sql_type, style, zone)?)
+        )),
+        SqlType::Interval => Ok(interval(&value)?.to_string()),
+        _ => Ok(value),
+    }
+}
+
+/// date and time that a value of a date or a timestamp type stands for,
+/// timestamps with time zone are in UTC and dates are at midnight
+pub(crate) fn timestamp(
+    value: &str,
+    sql_type: SqlType,
+    style: DateStyle,
+    zone: TimeZone,
+) -> Result<NaiveDateTime, QueryError> {
+    let parsed = match sql_type {
+        SqlType::TimestampWithTimeZone => {
+            parse(value, style.order).map(|(local, given)| given.unwrap_or(zone).utc(&local))
+        }
+        SqlType::Date => parse(value, style.order).map(|(local, _zone)| midnight(local.date())),
+        _ => parse(value, style.order).map(|(local, _zone)| local),
+    };
+    parsed.ok_or_else(|| QueryError::invalid_datetime_format(type_name(sql_type), value.to_owned()))
+}
+
+pub(crate) fn interval(value: &str) -> Result<Interval, QueryError> {
+    Interval::parse(value)
+        .ok_or_else(|| QueryError::invalid_datetime_format(type_name(SqlType::Interval), value.to_owned()))
+}
+
+/// translates a value that storage keeps into the format client expects
+pub(crate) fn output(value: String, sql_type: SqlType, style: DateStyle, zone: TimeZone) -> String {
+    match sql_type {
+        SqlType::Timestamp => match parse_timestamp(&value) {
+            Some(timestamp) => format(&timestamp, None, style),
+            None => value,
+        },
+        SqlType::TimestampWithTimeZone => match parse_timestamp_with_time_zone(&value) {
+            Some(utc) => format(&zone.local(&utc), Some(zone.offset(&utc)), style),
+            None => value,
+        },
+        _ => value,
+    }
+}
+
+pub(crate) fn has_datetimes(columns: &[(String, SqlType)]) -> bool {
+    columns.iter().any(|(_name, sql_type)| {
+        matches!(
+            sql_type,
+            SqlType::Timestamp | SqlType::TimestampWithTimeZone | SqlType::Interval
+        )
+    })
+}
+
+/// dates are sent in `DateStyle` format, e.g. `2020-01-02` or `02.01.2020`
+pub(crate) fn format_date(date: &NaiveDate, style: DateStyle) -> String {
+    let format = match (style.output, style.order) {
+        (OutputStyle::Iso, _) => "%Y-%m-%d",
+        (OutputStyle::Sql, FieldOrder::Dmy) => "%d/%m/%Y",
+        (OutputStyle::Sql, _) => "%m/%d/%Y",
+        (OutputStyle::Postgres, FieldOrder::Dmy) => "%d-%m-%Y",
+        (OutputStyle::Postgres, _) => "%m-%d-%Y",
+        (OutputStyle::German, _) => "%d.%m.%Y",
+    };
+    date.format(format).to_string()
+}
+
+/// months and days are added in local time of the zone, so that time of day
+/// is kept across daylight saving transitions, days that are not in the
+/// resulting month are cut off, e.g. `2020-01-31` plus `1 mon` is `2020-02-29`
+pub(crate) fn add_interval(
+    timestamp: &NaiveDateTime,
+    interval: Interval,
+    zone: Option<TimeZone>,
+) -> Option<NaiveDateTime> {
+    let mut local = match zone {
+        Some(zone) => zone.local(timestamp),
+        None => *timestamp,
+    };
+    if interval.months != 0 {
+        let months = local.year() as i64 * 12 + local.month0() as i64 + interval.months as i64;
+        let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+        if year.abs() > i32::MAX as i64 {
+            return None;
+        }
+        let day = local.day().min(days_in_month(year as i32, month)?);
+        local = NaiveDate::from_ymd_opt(year as i32, month, day)?.and_time(local.time());
+    }
+    local = local.checked_add_signed(Duration::days(interval.days as i64))?;
+    let timestamp = match zone {
+        Some(zone) => zone.utc(&local),
+        None => local,
+    };
+    timestamp.checked_add_signed(Duration::microseconds(interval.microseconds))
+}
+
+/// difference is given in days and time, days are 24 hours long
+pub(crate) fn difference(left: &NaiveDateTime, right: &NaiveDateTime) -> Option<Interval> {
+    let microseconds = left.signed_duration_since(*right).num_microseconds()?;
+    Some(Interval::new(0, 0, microseconds).justify_hours())
+}
+
+/// symbolic difference that is given in years, months and days, fields are
+/// subtracted one by one in local time of the zone, e.g. age of `2020-03-01`
+/// at `2020-04-15 12:00` is `1 mon 14 days 12:00:00`
+pub(crate) fn age(left: &NaiveDateTime, right: &NaiveDateTime, zone: Option<TimeZone>) -> Interval {
+    let (left, right) = match zone {
+        Some(zone) => (zone.local(left), zone.local(right)),
+        None => (*left, *right),
+    };
+    let (later, earlier) = if left < right { (right, left) } else { (left, right) };
+    let field = |part: fn(&NaiveDateTime) -> i64| part(&later) - part(&earlier);
+    let mut microseconds = field(|timestamp| (timestamp.nanosecond() / 1_000) as i64);
+    let mut seconds = field(|timestamp| timestamp.second() as i64);
+    let mut minutes = field(|timestamp| timestamp.minute() as i64);
+    let mut hours = field(|timestamp| timestamp.hour() as i64);
+    let mut days = field(|timestamp| timestamp.day() as i64);
+    let mut months = field(|timestamp| timestamp.month() as i64);
+    let mut years = field(|timestamp| timestamp.year() as i64);
+    // fields borrow from larger ones, a month is as long as the earlier month is
+    if microseconds < 0 {
+        microseconds += 1_000_000;
+        seconds -= 1;
+    }
+    if seconds < 0 {
+        seconds += 60;
+        minutes -= 1;
+    }
+    if minutes < 0 {
+        minutes += 60;
+        hours -= 1;
+    }
+    if hours < 0 {
+        hours += 24;
+        days -= 1;
+    }
+    if days < 0 {
+        days += days_in_month(earlier.year(), earlier.month()).unwrap_or(30) as i64;
+        months -= 1;
+    }
+    if months < 0 {
+        months += 12;
+        years -= 1;
+    }
+    let time = ((hours * 60 + minutes) * 60 + seconds) * 1_000_000 + microseconds;
+    let age = Interval::new((years * 12 + months) as i32, days as i32, time);
+    if left < right {
+        Interval::new(-age.months, -age.days, -age.microseconds)
+    } else {
+        age
+    }
+}
+
+/// current date in the time zone
+pub(crate) fn today(zone: TimeZone) -> NaiveDate {
+    zone.local(&Utc::now().naive_utc()).date()
+}
+
+pub(crate) fn midnight(date: NaiveDate) -> NaiveDateTime {
+    date.and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
+}
+
+fn days_in_month(year: i32, month: u32) -> Option<u32> {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)?
+        .pred_opt()
+        .map(|last_day| last_day.day())
+}
+
+pub(crate) fn type_name(sql_type: SqlType) -> String {
+    match sql_type {
+        SqlType::TimestampWithTimeZone => "timestamp with time zone".to_owned(),
+        SqlType::Date => "date".to_owned(),
+        SqlType::Interval => "interval".to_owned(),
+        _ => "timestamp".to_owned(),
+    }
+}
+
+fn format(timestamp: &NaiveDateTime, zone: Option<(FixedOffset, String)>, style: DateStyle) -> String {
+    let iso = format_timestamp(timestamp);
+    let time = iso.rsplit(' ').next().unwrap_or_default();
+    let formatted = match (style.output, style.order) {
+        (OutputStyle::Iso, _) => iso.clone(),
+        (OutputStyle::Sql, FieldOrder::Dmy) => format!("{} {}", timestamp.format("%d/%m/%Y"), time),
+        (OutputStyle::Sql, _) => format!("{} {}", timestamp.format("%m/%d/%Y"), time),
+        (OutputStyle::Postgres, FieldOrder::Dmy) => {
+            format!("{} {} {}", timestamp.format("%a %d %b"), time, timestamp.format("%Y"))
+        }
+        (OutputStyle::Postgres, _) => format!("{} {} {}", timestamp.format("%a %b %d"), time, timestamp.format("%Y")),
+        (OutputStyle::German, _) => format!("{} {}", timestamp.format("%d.%m.%Y"), time),
+    };
+    match (zone, style.output) {
+        (None, _) => formatted,
+        (Some((offset, _abbreviation)), OutputStyle::Iso) => formatted + format_offset(&offset).as_str(),
+        (Some((_offset, abbreviation)), _) => format!("{} {}", formatted, abbreviation),
+    }
+}
+
+// minutes and seconds of an offset are shown only if they are not zero, e.g. `+03` or `-05:30`
+fn format_offset(offset: &FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (hours, 0, 0) => format!("{}{:02}", sign, hours),
+        (hours, minutes, 0) => format!("{}{:02}:{:02}", sign, hours, minutes),
+        (hours, minutes, seconds) => format!("{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds),
+    }
+}
+
+// hours with optional minutes, e.g. `3`, `-08`, `+05:30` or `+0530`
+fn fixed_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, digits) = match value.chars().next()? {
+        '+' => (1, &value[1..]),
+        '-' => (-1, &value[1..]),
+        _ => (1, value),
+    };
+    let (hours, minutes) = match digits.find(':') {
+        Some(colon) => (&digits[..colon], &digits[colon + 1..]),
+        None if digits.len() == 4 => digits.split_at(2),
+        None => (digits, "0"),
+    };
+    if hours.is_empty() || hours.len() > 2 || !hours.chars().chain(minutes.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours = hours.parse::<i32>().ok()?;
+    let minutes = minutes.parse::<i32>().ok()?;
+    if hours > 15 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+// abbreviations that are used in output of regions, offsets are in hours
+const ABBREVIATIONS: [(&str, i32); 19] = [
+    ("utc", 0),
+    ("gmt", 0),
+    ("z", 0),
+    ("wet", 0),
+    ("west", 1),
+    ("cet", 1),
+    ("cest", 2),
+    ("eet", 2),
+    ("eest", 3),
+    ("msk", 3),
+    ("jst", 9),
+    ("est", -5),
+    ("edt", -4),
+    ("cst", -6),
+    ("cdt", -5),
+    ("mst", -7),
+    ("mdt", -6),
+    ("pst", -8),
+    ("pdt", -7),
+];
+
+// month names can be full or abbreviated, e.g. `January` or `Jan`
+fn month(word: &str) -> Option<u32> {
+    let word = word.to_lowercase();
+    if word.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|month| word.starts_with(month))
+        .map(|index| index as u32 + 1)
+}
+
+fn is_weekday(word: &str) -> bool {
+    let word = word.to_lowercase();
+    word.len() >= 3 && WEEKDAYS.iter().any(|weekday| word.starts_with(weekday))
+}
+
+// time zone that is given along with date and time, e.g. `+03`, `EEST` or `Europe/Kiev`
+fn zone(word: &str) -> Option<TimeZone> {
+    if word.starts_with('+') || word.starts_with('-') {
+        return fixed_offset(word).map(TimeZone::Offset);
+    }
+    let lowercase = word.to_lowercase();
+    match ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _hours)| *abbreviation == lowercase)
+    {
+        Some((_abbreviation, hours)) => FixedOffset::east_opt(hours * 3600).map(TimeZone::Offset),
+        None => TimeZone::parse(word),
+    }
+}
+
+// years that are given with two digits are the closest to 2020
+fn year(digits: &str) -> Option<i32> {
+    let year = digits.parse::<i32>().ok()?;
+    match digits.len() {
+        1 | 2 if year < 70 => Some(year + 2000),
+        1 | 2 => Some(year + 1900),
+        _ => Some(year),
+    }
+}
+
+// date with separated fields, e.g. `2020-01-02`, `01/02/2020`, `02.01.2020`
+// or `02-Jan-2020`, years with four digits always come first
+fn date(word: &str, order: FieldOrder) -> Option<NaiveDate> {
+    let separator = word.chars().find(|c| *c == '-' || *c == '/' || *c == '.')?;
+    let fields = word.split(separator).collect::<Vec<&str>>();
+    if fields.len() != 3 || fields.iter().any(|field| field.is_empty()) {
+        return None;
+    }
+    let (year_field, month_number, day_field) = match fields.iter().position(|field| month(field).is_some()) {
+        Some(index) => {
+            let rest = fields
+                .iter()
+                .enumerate()
+                .filter(|(position, _field)| *position != index)
+                .map(|(_position, field)| *field)
+                .collect::<Vec<&str>>();
+            let (year, day) = if rest[0].len() > 2 {
+                (rest[0], rest[1])
+            } else {
+                (rest[1], rest[0])
+            };
+            (year, month(fields[index])?, day)
+        }
+        None => {
+            let (year, month, day) = match (fields[0].len() > 2, order) {
+                (true, _) | (false, FieldOrder::Ymd) => (fields[0], fields[1], fields[2]),
+                (false, FieldOrder::Dmy) => (fields[2], fields[1], fields[0]),
+                (false, FieldOrder::Mdy) => (fields[2], fields[0], fields[1]),
+            };
+            (year, month.parse().ok()?, day)
+        }
+    };
+    if !year_field.chars().chain(day_field.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year(year_field)?, month_number, day_field.parse().ok()?)
+}
+
+fn is_date(word: &str) -> bool {
+    word.split(&['-', '/', '.'][..]).count() == 3
+        && word.chars().any(|c| c.is_ascii_digit())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '/' || c == '.')
+}
+
+// time of day with optional fractional seconds and time zone, e.g.
+// `03:04`, `03:04:05.678` or `03:04:05+03`
+fn time(word: &str) -> Option<(NaiveTime, Option<TimeZone>)> {
+    let zone_start = word
+        .find(|c: char| c == '+' || c == '-' || c.is_ascii_alphabetic())
+        .unwrap_or(word.len());
+    let (time, rest) = word.split_at(zone_start);
+    let fields = time.split(':').collect::<Vec<&str>>();
+    let (hours, minutes, seconds) = match fields.as_slice() {
+        [hours, minutes] => (*hours, *minutes, "0"),
+        [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+        _ => return None,
+    };
+    let (seconds, fraction) = match seconds.find('.') {
+        Some(dot) => (&seconds[..dot], &seconds[dot + 1..]),
+        None => (seconds, ""),
+    };
+    let fields = [hours, minutes, seconds, fraction];
+    if fields.iter().any(|field| !field.chars().all(|c| c.is_ascii_digit())) || hours.is_empty() {
+        return None;
+    }
+    // fractional seconds are kept with microseconds precision
+    let microseconds = format!("{:0<6}", fraction.get(..6).unwrap_or(fraction)).parse().ok()?;
+    let time = NaiveTime::from_hms_micro_opt(
+        hours.parse().ok()?,
+        minutes.parse().ok()?,
+        seconds.parse().ok()?,
+        microseconds,
+    )?;
+    let zone = if rest.is_empty() { None } else { Some(zone(rest)?) };
+    Some((time, zone))
+}
+
+// returns local date and time along with time zone if it is given
+fn parse(value: &str, order: FieldOrder) -> Option<(NaiveDateTime, Option<TimeZone>)> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("epoch") {
+        let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?.and_hms_opt(0, 0, 0)?;
+        return Some((epoch, Some(TimeZone::default())));
+    }
+    let mut date_of = None;
+    let mut time_of_day = None;
+    let mut given_zone = None;
+    let mut month_number = None;
+    let mut numbers = vec![];
+    let mut afternoon = None;
+    // date and time of ISO 8601 format can be joined with `T`, e.g. `2020-01-02T03:04:05`
+    let words = value
+        .split_whitespace()
+        .flat_map(|word| match word.find(&['T', 't'][..]) {
+            Some(index) if is_date(&word[..index]) && word[index + 1..].contains(':') => {
+                vec![&word[..index], &word[index + 1..]]
+            }
+            _ => vec![word],
+        })
+        .map(|word| word.trim_end_matches(','));
+    for word in words {
+        if word.starts_with('+') || word.starts_with('-') {
+            given_zone = Some(zone(word)?);
+        } else if word.contains(':') && time_of_day.is_none() {
+            let (time, zone) = time(word)?;
+            time_of_day = Some(time);
+            given_zone = zone.or(given_zone);
+        } else if is_date(word) && date_of.is_none() {
+            date_of = Some(date(word, order)?);
+        } else if word.chars().all(|c| c.is_ascii_digit()) {
+            numbers.push(word);
+        } else if word.eq_ignore_ascii_case("am") || word.eq_ignore_ascii_case("pm") {
+            afternoon = Some(word.eq_ignore_ascii_case("pm"));
+        } else if let Some(number) = month(word).filter(|_| month_number.is_none() && date_of.is_none()) {
+            month_number = Some(number);
+        } else if is_weekday(word) {
+            continue;
+        } else {
+            given_zone = Some(zone(word)?);
+        }
+    }
+    // fields of a date can be separate words, e.g. `Jan 2 2020` or `2 Jan 2020`
+    let date_of = match (date_of, month_number, numbers.as_slice()) {
+        (Some(date), None, []) => date,
+        (None, Some(month), [first, second]) => {
+            let (year_field, day) = if first.len() > 2 {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            NaiveDate::from_ymd_opt(year(year_field)?, month, day.parse().ok()?)?
+        }
+        _ => return None,
+    };
+    let mut time_of_day = time_of_day.unwrap_or_else(|| NaiveTime::from_hms_opt(0, 0, 0).unwrap());
+    time_of_day = match (afternoon, time_of_day.hour()) {
+        (Some(true), hour) if hour < 12 => time_of_day + Duration::hours(12),
+        (Some(false), 12) => time_of_day - Duration::hours(12),
+        (Some(_), hour) if hour > 12 => return None,
+        _ => time_of_day,
+    };
+    Some((date_of.and_time(time_of_day), given_zone))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(test)]
+    mod date_style {
+        use super::*;
+
+        #[test]
+        fn output_style_and_field_order() {
+            let style = DateStyle::parse("sql, dmy", DateStyle::default());
+
+            assert_eq!(style.map(|style| style.to_string()), Some("SQL, DMY".to_owned()));
+        }
+
+        #[test]
+        fn single_component_keeps_the_other_one() {
+            let style = DateStyle::parse("SQL, DMY", DateStyle::default()).expect("valid style");
+
+            assert_eq!(
+                DateStyle::parse("Postgres", style).map(|style| style.to_string()),
+                Some("Postgres, DMY".to_owned())
+            );
+            assert_eq!(
+                DateStyle::parse("European", DateStyle::default()).map(|style| style.to_string()),
+                Some("ISO, DMY".to_owned())
+            );
+        }
+
+        #[test]
+        fn german_puts_day_first() {
+            assert_eq!(
+                DateStyle::parse("German", DateStyle::default()).map(|style| style.to_string()),
+                Some("German, DMY".to_owned())
+            );
+        }
+
+        #[test]
+        fn invalid() {
+            assert_eq!(DateStyle::parse("ISO, SQL", DateStyle::default()), None);
+            assert_eq!(DateStyle::parse("YMD, DMY", DateStyle::default()), None);
+            assert_eq!(DateStyle::parse("unknown", DateStyle::default()), None);
+        }
+    }
+
+    #[cfg(test)]
+    mod time_zone {
+        use super::*;
+
+        #[test]
+        fn regions_are_case_insensitive() {
+            assert_eq!(
+                TimeZone::parse("europe/kiev").map(|zone| zone.name()),
+                Some("Europe/Kiev".to_owned())
+            );
+        }
+
+        #[test]
+        fn offsets_in_hours() {
+            assert_eq!(TimeZone::parse("-8").map(|zone| zone.name()), Some("-08".to_owned()));
+            assert_eq!(
+                TimeZone::parse("+05:30").map(|zone| zone.name()),
+                Some("+05:30".to_owned())
+            );
+        }
+
+        #[test]
+        fn invalid() {
+            assert_eq!(TimeZone::parse("Mars/Olympus_Mons"), None);
+            assert_eq!(TimeZone::parse("+16"), None);
+        }
+    }
+
+    #[cfg(test)]
+    mod input {
+        use super::*;
+
+        fn timestamp(value: &str, style: &str) -> Result<String, QueryError> {
+            input(
+                value.to_owned(),
+                SqlType::Timestamp,
+                DateStyle::parse(style, DateStyle::default()).expect("valid style"),
+                TimeZone::default(),
+            )
+        }
+
+        fn timestamp_with_time_zone(value: &str, zone: &str) -> Result<String, QueryError> {
+            input(
+                value.to_owned(),
+                SqlType::TimestampWithTimeZone,
+                DateStyle::default(),
+                TimeZone::parse(zone).expect("valid time zone"),
+            )
+        }
+
+        #[test]
+        fn iso_format() {
+            assert_eq!(
+                timestamp("2020-01-02 03:04:05.678", "ISO, MDY"),
+                Ok("2020-01-02 03:04:05.678".to_owned())
+            );
+            assert_eq!(
+                timestamp("2020-01-02T03:04:05", "ISO, DMY"),
+                Ok("2020-01-02 03:04:05".to_owned())
+            );
+            assert_eq!(
+                timestamp("2020-01-02", "ISO, MDY"),
+                Ok("2020-01-02 00:00:00".to_owned())
+            );
+        }
+
+        #[test]
+        fn ambiguous_fields_follow_date_style() {
+            assert_eq!(timestamp("01/02/2020", "MDY"), Ok("2020-01-02 00:00:00".to_owned()));
+            assert_eq!(timestamp("01/02/2020", "DMY"), Ok("2020-02-01 00:00:00".to_owned()));
+            assert_eq!(timestamp("20/01/02", "YMD"), Ok("2020-01-02 00:00:00".to_owned()));
+        }
+
+        #[test]
+        fn month_names() {
+            assert_eq!(
+                timestamp("Thu Jan 02 03:04:05 2020", "MDY"),
+                Ok("2020-01-02 03:04:05".to_owned())
+            );
+            assert_eq!(
+                timestamp("2 January 2020 3:04 pm", "MDY"),
+                Ok("2020-01-02 15:04:00".to_owned())
+            );
+            assert_eq!(timestamp("02-Jan-2020", "MDY"), Ok("2020-01-02 00:00:00".to_owned()));
+        }
+
+        #[test]
+        fn time_zone_is_ignored_without_time_zone() {
+            assert_eq!(
+                timestamp("2020-01-02 03:04:05+03", "ISO, MDY"),
+                Ok("2020-01-02 03:04:05".to_owned())
+            );
+        }
+
+        #[test]
+        fn session_time_zone() {
+            assert_eq!(
+                timestamp_with_time_zone("2020-07-01 03:00:00", "Europe/Kiev"),
+                Ok("2020-07-01 00:00:00+00".to_owned())
+            );
+            assert_eq!(
+                timestamp_with_time_zone("2020-01-01 03:00:00", "Europe/Kiev"),
+                Ok("2020-01-01 01:00:00+00".to_owned())
+            );
+        }
+
+        #[test]
+        fn given_time_zone() {
+            assert_eq!(
+                timestamp_with_time_zone("2020-07-01 03:00:00-05:30", "Europe/Kiev"),
+                Ok("2020-07-01 08:30:00+00".to_owned())
+            );
+            assert_eq!(
+                timestamp_with_time_zone("2020-07-01 03:00:00 America/New_York", "UTC"),
+                Ok("2020-07-01 07:00:00+00".to_owned())
+            );
+            assert_eq!(
+                timestamp_with_time_zone("2020-07-01 03:00:00 PST", "UTC"),
+                Ok("2020-07-01 11:00:00+00".to_owned())
+            );
+        }
+
+        #[test]
+        fn skipped_local_time() {
+            assert_eq!(
+                timestamp_with_time_zone("2020-03-29 03:30:00", "Europe/Kiev"),
+                Ok("2020-03-29 01:30:00+00".to_owned())
+            );
+        }
+
+        #[test]
+        fn invalid() {
+            assert_eq!(
+                timestamp("2020-13-01", "ISO, MDY"),
+                Err(QueryError::invalid_datetime_format(
+                    "timestamp".to_owned(),
+                    "2020-13-01".to_owned()
+                ))
+            );
+            assert_eq!(
+                timestamp_with_time_zone("yesterday noon", "UTC"),
+                Err(QueryError::invalid_datetime_format(
+                    "timestamp with time zone".to_owned(),
+                    "yesterday noon".to_owned()
+                ))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod output {
+        use super::*;
+
+        fn timestamp(value: &str, style: &str) -> String {
+            output(
+                value.to_owned(),
+                SqlType::Timestamp,
+                DateStyle::parse(style, DateStyle::default()).expect("valid style"),
+                TimeZone::default(),
+            )
+        }
+
+        fn timestamp_with_time_zone(value: &str, style: &str, zone: &str) -> String {
+            output(
+                value.to_owned(),
+                SqlType::TimestampWithTimeZone,
+                DateStyle::parse(style, DateStyle::default()).expect("valid style"),
+                TimeZone::parse(zone).expect("valid time zone"),
+            )
+        }
+
+        #[test]
+        fn styles() {
+            let value = "2020-01-02 03:04:05.5";
+
+            assert_eq!(timestamp(value, "ISO, MDY"), "2020-01-02 03:04:05.5");
+            assert_eq!(timestamp(value, "SQL, MDY"), "01/02/2020 03:04:05.5");
+            assert_eq!(timestamp(value, "SQL, DMY"), "02/01/2020 03:04:05.5");
+            assert_eq!(timestamp(value, "Postgres, MDY"), "Thu Jan 02 03:04:05.5 2020");
+            assert_eq!(timestamp(value, "Postgres, DMY"), "Thu 02 Jan 03:04:05.5 2020");
+            assert_eq!(timestamp(value, "German"), "02.01.2020 03:04:05.5");
+        }
+
+        #[test]
+        fn in_session_time_zone() {
+            let value = "2020-07-01 00:00:00+00";
+
+            assert_eq!(timestamp_with_time_zone(value, "ISO", "UTC"), "2020-07-01 00:00:00+00");
+            assert_eq!(
+                timestamp_with_time_zone(value, "ISO", "Europe/Kiev"),
+                "2020-07-01 03:00:00+03"
+            );
+            assert_eq!(
+                timestamp_with_time_zone(value, "ISO", "Asia/Kolkata"),
+                "2020-07-01 05:30:00+05:30"
+            );
+            assert_eq!(
+                timestamp_with_time_zone(value, "SQL", "Europe/Kiev"),
+                "07/01/2020 03:00:00 EEST"
+            );
+            assert_eq!(
+                timestamp_with_time_zone(value, "Postgres", "America/New_York"),
+                "Tue Jun 30 20:00:00 2020 EDT"
+            );
+            assert_eq!(
+                timestamp_with_time_zone(value, "German", "-8"),
+                "30.06.2020 16:00:00 -08"
+            );
+        }
+
+        #[test]
+        fn round_trip() {
+            let style = DateStyle::parse("Postgres", DateStyle::default()).expect("valid style");
+            let zone = TimeZone::parse("America/New_York").expect("valid time zone");
+
+            let shown = output(
+                "2020-07-01 00:00:00+00".to_owned(),
+                SqlType::TimestampWithTimeZone,
+                style,
+                zone,
+            );
+
+            assert_eq!(
+                input(shown, SqlType::TimestampWithTimeZone, style, TimeZone::default()),
+                Ok("2020-07-01 00:00:00+00".to_owned())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod arithmetic {
+        use super::*;
+
+        fn at(value: &str) -> NaiveDateTime {
+            parse_timestamp(value).expect("valid timestamp")
+        }
+
+        fn interval(value: &str) -> Interval {
+            Interval::parse(value).expect("valid interval")
+        }
+
+        #[test]
+        fn add_months_to_the_end_of_month() {
+            assert_eq!(
+                add_interval(&at("2020-01-31 10:00:00"), interval("1 mon"), None),
+                Some(at("2020-02-29 10:00:00"))
+            );
+            assert_eq!(
+                add_interval(&at("2020-03-31 10:00:00"), interval("-1 mons 1 day 02:00:00"), None),
+                Some(at("2020-03-01 12:00:00"))
+            );
+        }
+
+        #[test]
+        fn days_keep_time_of_day_across_daylight_saving_transition() {
+            let zone = TimeZone::parse("America/New_York").expect("valid time zone");
+            // 2020-03-07 12:00 EST
+            let before = at("2020-03-07 17:00:00");
+
+            assert_eq!(
+                add_interval(&before, interval("1 day"), Some(zone)),
+                Some(at("2020-03-08 16:00:00"))
+            );
+            assert_eq!(
+                add_interval(&before, interval("24 hours"), Some(zone)),
+                Some(at("2020-03-08 17:00:00"))
+            );
+        }
+
+        #[test]
+        fn out_of_range() {
+            assert_eq!(
+                add_interval(&at("2020-01-01 00:00:00"), Interval::new(i32::MAX, 0, 0), None),
+                None
+            );
+        }
+
+        #[test]
+        fn difference_in_days_and_time() {
+            assert_eq!(
+                difference(&at("2020-03-01 12:00:00"), &at("2020-01-01 00:00:00")),
+                Some(interval("60 days 12:00:00"))
+            );
+            assert_eq!(
+                difference(&at("2020-01-01 00:00:00"), &at("2020-01-02 06:00:00")),
+                Some(interval("-1 days -06:00:00"))
+            );
+        }
+
+        #[test]
+        fn age_in_years_months_and_days() {
+            assert_eq!(
+                age(&at("2020-04-15 12:00:00"), &at("2020-03-01 00:00:00"), None),
+                interval("1 mon 14 days 12:00:00")
+            );
+            assert_eq!(
+                age(&at("2020-03-01 00:00:00"), &at("2019-01-31 00:00:00"), None),
+                interval("1 year 1 mon 1 day")
+            );
+            assert_eq!(
+                age(&at("2019-01-31 00:00:00"), &at("2020-03-01 00:00:00"), None),
+                interval("-1 years -1 mons -1 days")
+            );
+        }
+
+        #[test]
+        fn age_in_local_time() {
+            let zone = TimeZone::parse("+03").expect("valid time zone");
+
+            assert_eq!(
+                age(&at("2020-02-01 21:00:00"), &at("2020-01-01 00:00:00"), Some(zone)),
+                interval("1 mon 21:00:00")
+            );
+        }
+
+        #[test]
+        fn dates_in_date_style() {
+            let date = NaiveDate::from_ymd_opt(2020, 1, 2).expect("valid date");
+            let style = |value: &str| DateStyle::parse(value, DateStyle::default()).expect("valid style");
+
+            assert_eq!(format_date(&date, style("ISO")), "2020-01-02");
+            assert_eq!(format_date(&date, style("SQL, DMY")), "02/01/2020");
+            assert_eq!(format_date(&date, style("German")), "02.01.2020");
+        }
+    }
+}
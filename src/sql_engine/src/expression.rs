@@ -0,0 +1,1458 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    datetime::{self, DateStyle, TimeZone},
+    math::{self, Random},
+    QueryError,
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use sql_types::{format_timestamp, parse_timestamp, parse_timestamp_with_time_zone, Interval, SqlType};
+use sqlparser::ast::{BinaryOperator, DataType, DateTimeField, Expr, Function, Ident, UnaryOperator, Value};
+use std::cmp::Ordering;
+use storage::Comparison;
+
+/// Value of an expression
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Datum {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    // kept in double precision, only the type is different
+    Numeric(f64),
+    // value of a character column
+    Text(String),
+    // string literal, its type is taken from the context it is used in
+    Unknown(String),
+    Date(NaiveDate),
+    Timestamp(NaiveDateTime),
+    // kept in UTC
+    TimestampWithTimeZone(NaiveDateTime),
+    Interval(Interval),
+}
+
+impl Datum {
+    pub(crate) fn sql_type(&self) -> SqlType {
+        match self {
+            Datum::Boolean(_) => SqlType::Bool,
+            Datum::Integer(value) if *value >= i32::MIN as i64 && *value <= i32::MAX as i64 => SqlType::Integer,
+            Datum::Integer(_) => SqlType::BigInt,
+            Datum::Float(_) => SqlType::DoublePrecision,
+            Datum::Numeric(_) => SqlType::Decimal,
+            Datum::Null | Datum::Text(_) | Datum::Unknown(_) => SqlType::VarChar(255),
+            Datum::Date(_) => SqlType::Date,
+            Datum::Timestamp(_) => SqlType::Timestamp,
+            Datum::TimestampWithTimeZone(_) => SqlType::TimestampWithTimeZone,
+            Datum::Interval(_) => SqlType::Interval,
+        }
+    }
+
+    pub(crate) fn type_name(&self) -> String {
+        match self {
+            Datum::Boolean(_) => "boolean".to_owned(),
+            Datum::Integer(_) => "integer".to_owned(),
+            Datum::Float(_) => "double precision".to_owned(),
+            Datum::Numeric(_) => "numeric".to_owned(),
+            Datum::Text(_) => "text".to_owned(),
+            Datum::Null | Datum::Unknown(_) => "unknown".to_owned(),
+            Datum::Timestamp(_) => "timestamp without time zone".to_owned(),
+            datum => datetime::type_name(datum.sql_type()),
+        }
+    }
+}
+
+/// Value of a column of a row that expressions are evaluated with
+pub(crate) trait ColumnValue {
+    fn datum(&self, sql_type: SqlType) -> Datum;
+}
+
+// rows of tables keep values in the format of storage
+impl ColumnValue for String {
+    fn datum(&self, sql_type: SqlType) -> Datum {
+        column_value(sql_type, self)
+    }
+}
+
+// rows that operators give, e.g. values of groups
+impl ColumnValue for Datum {
+    fn datum(&self, _sql_type: SqlType) -> Datum {
+        self.clone()
+    }
+}
+
+/// Evaluates expressions, e.g. `timestamp '2020-01-31' + interval '1 month'`
+/// or `id between 1 and 10`, columns they refer to take values of a row as
+/// storage gives them. Dates and timestamps of literals are read and shown in
+/// `DateStyle` and `TimeZone` of the session
+pub(crate) struct Evaluator<'q> {
+    style: DateStyle,
+    zone: TimeZone,
+    raw_sql_query: &'q str,
+    columns: &'q [(String, SqlType)],
+    random: Option<&'q Random>,
+}
+
+impl<'q> Evaluator<'q> {
+    pub(crate) fn new(style: DateStyle, zone: TimeZone, raw_sql_query: &'q str) -> Evaluator<'q> {
+        Evaluator {
+            style,
+            zone,
+            raw_sql_query,
+            columns: &[],
+            random: None,
+        }
+    }
+
+    /// generator that gives values of `random()`
+    pub(crate) fn with_random(mut self, random: &'q Random) -> Evaluator<'q> {
+        self.random = Some(random);
+        self
+    }
+
+    /// columns of rows that expressions are evaluated with
+    pub(crate) fn with_columns(mut self, columns: &'q [(String, SqlType)]) -> Evaluator<'q> {
+        self.columns = columns;
+        self
+    }
+
+    pub(crate) fn evaluate(&self, expr: &Expr) -> Result<Datum, QueryError> {
+        self.value::<String>(expr, &[])
+    }
+
+    /// rows are selected only when the predicate is true, not false or null
+    pub(crate) fn filter<V: ColumnValue>(&self, predicate: &Expr, row: &[V]) -> Result<bool, QueryError> {
+        let datum = self.value(predicate, row)?;
+        Ok(self.truth(datum, "WHERE")? == Some(true))
+    }
+
+    /// value of an expression for a row, e.g. of a key that rows are sorted by
+    pub(crate) fn row_value<V: ColumnValue>(&self, expr: &Expr, row: &[V]) -> Result<Datum, QueryError> {
+        self.value(expr, row)
+    }
+
+    /// number of rows that LIMIT or OFFSET is given, `None` stands for null
+    pub(crate) fn row_count(&self, expr: &Expr, clause: &str) -> Result<Option<i64>, QueryError> {
+        match self.evaluate(expr)? {
+            Datum::Null => Ok(None),
+            Datum::Integer(value) => Ok(Some(value)),
+            Datum::Float(value) | Datum::Numeric(value) => {
+                let value = value.round();
+                if value >= i64::MIN as f64 && value < i64::MAX as f64 {
+                    Ok(Some(value as i64))
+                } else {
+                    Err(QueryError::numeric_value_out_of_range("bigint".to_owned()))
+                }
+            }
+            Datum::Unknown(value) => match value.trim().parse::<i64>() {
+                Ok(count) => Ok(Some(count)),
+                Err(_) => Err(QueryError::invalid_text_representation("bigint".to_owned(), value)),
+            },
+            datum => Err(QueryError::datatype_mismatch(
+                clause.to_owned(),
+                "bigint".to_owned(),
+                datum.type_name(),
+            )),
+        }
+    }
+
+    /// columns that are referred to by the expression have to exist even when
+    /// there are no rows to evaluate it with
+    pub(crate) fn check(&self, expr: &Expr) -> Result<(), QueryError> {
+        let mut names = vec![];
+        referenced_columns(expr, &mut names);
+        let non_existing: Vec<String> = names
+            .into_iter()
+            .filter(|name| self.columns.iter().all(|(column, _sql_type)| column != name))
+            .collect();
+        if non_existing.is_empty() {
+            Ok(())
+        } else {
+            Err(QueryError::column_does_not_exist(non_existing))
+        }
+    }
+
+    fn value<V: ColumnValue>(&self, expr: &Expr, row: &[V]) -> Result<Datum, QueryError> {
+        match expr {
+            Expr::Value(Value::Number(number)) => match number.parse::<i64>() {
+                Ok(integer) => Ok(Datum::Integer(integer)),
+                Err(_) => number
+                    .parse::<f64>()
+                    .map(Datum::Float)
+                    .map_err(|_| self.not_supported()),
+            },
+            Expr::Value(Value::SingleQuotedString(value)) => Ok(Datum::Unknown(value.clone())),
+            Expr::Value(Value::Boolean(value)) => Ok(Datum::Boolean(*value)),
+            Expr::Value(Value::Null) => Ok(Datum::Null),
+            // literals of a type, e.g. `date '2020-01-31'`
+            Expr::TypedString { data_type, value } => match cast_type(data_type) {
+                Some(sql_type) => self.cast(Datum::Unknown(value.clone()), sql_type),
+                None => Err(self.not_supported()),
+            },
+            Expr::Value(Value::Interval {
+                value, leading_field, ..
+            }) => {
+                // unit can be given after a number, e.g. `interval '3' day`
+                let value = match leading_field {
+                    Some(field) if value.trim().parse::<f64>().is_ok() => format!("{} {}", value, unit(field)),
+                    _ => value.clone(),
+                };
+                datetime::interval(&value).map(Datum::Interval)
+            }
+            Expr::Identifier(Ident { value, .. }) => self.column(value, row),
+            // table name does not matter when only one table is read
+            Expr::CompoundIdentifier(idents) => match idents.last() {
+                Some(Ident { value, .. }) => self.column(value, row),
+                None => Err(self.not_supported()),
+            },
+            Expr::Nested(expr) => self.value(expr, row),
+            Expr::Cast { expr, data_type } => {
+                let sql_type = match cast_type(data_type) {
+                    Some(sql_type) => sql_type,
+                    None => return Err(self.not_supported()),
+                };
+                self.cast(self.value(expr, row)?, sql_type)
+            }
+            Expr::UnaryOp {
+                op: UnaryOperator::Not,
+                expr,
+            } => {
+                let datum = self.value(expr, row)?;
+                Ok(boolean(self.truth(datum, "NOT")?.map(|value| !value)))
+            }
+            Expr::UnaryOp { op, expr } => self.unary(op, self.value(expr, row)?),
+            // right operand is not evaluated when the left one decides the result
+            Expr::BinaryOp {
+                left,
+                op: BinaryOperator::And,
+                right,
+            } => match self.truth(self.value(left, row)?, "AND")? {
+                Some(false) => Ok(Datum::Boolean(false)),
+                left => Ok(boolean(and(left, self.truth(self.value(right, row)?, "AND")?))),
+            },
+            Expr::BinaryOp {
+                left,
+                op: BinaryOperator::Or,
+                right,
+            } => match self.truth(self.value(left, row)?, "OR")? {
+                Some(true) => Ok(Datum::Boolean(true)),
+                left => Ok(boolean(or(left, self.truth(self.value(right, row)?, "OR")?))),
+            },
+            Expr::BinaryOp { left, op, right } if comparison(op).is_some() => {
+                self.compare(self.value(left, row)?, op, self.value(right, row)?)
+            }
+            Expr::BinaryOp { left, op, right } => self.binary(self.value(left, row)?, op, self.value(right, row)?),
+            Expr::IsNull(expr) => Ok(Datum::Boolean(self.value(expr, row)? == Datum::Null)),
+            Expr::IsNotNull(expr) => Ok(Datum::Boolean(self.value(expr, row)? != Datum::Null)),
+            Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let value = self.value(expr, row)?;
+                let low = self.compare(value.clone(), &BinaryOperator::GtEq, self.value(low, row)?)?;
+                let high = self.compare(value, &BinaryOperator::LtEq, self.value(high, row)?)?;
+                let between = and(self.truth(low, "BETWEEN")?, self.truth(high, "BETWEEN")?);
+                Ok(boolean(if *negated { between.map(|value| !value) } else { between }))
+            }
+            // value that is not found in a list with nulls may be equal to one of them
+            Expr::InList { expr, list, negated } => {
+                let value = self.value(expr, row)?;
+                let mut found = Some(false);
+                for item in list {
+                    let equal = self.compare(value.clone(), &BinaryOperator::Eq, self.value(item, row)?)?;
+                    found = or(found, self.truth(equal, "IN")?);
+                }
+                Ok(boolean(if *negated { found.map(|value| !value) } else { found }))
+            }
+            Expr::Function(function) => self.function(function, row),
+            _ => Err(self.not_supported()),
+        }
+    }
+
+    fn column<V: ColumnValue>(&self, name: &str, row: &[V]) -> Result<Datum, QueryError> {
+        let index = self.columns.iter().position(|(column, _sql_type)| column == name);
+        match index.and_then(|index| row.get(index).map(|value| value.datum(self.columns[index].1))) {
+            Some(datum) => Ok(datum),
+            None => Err(QueryError::column_does_not_exist(vec![name.to_owned()])),
+        }
+    }
+
+    // `None` stands for null
+    fn truth(&self, datum: Datum, clause: &str) -> Result<Option<bool>, QueryError> {
+        match datum {
+            Datum::Null => Ok(None),
+            Datum::Boolean(value) => Ok(Some(value)),
+            Datum::Unknown(value) => match value.trim().to_lowercase().as_str() {
+                "t" | "true" | "y" | "yes" | "on" | "1" => Ok(Some(true)),
+                "f" | "false" | "n" | "no" | "off" | "0" => Ok(Some(false)),
+                _ => Err(QueryError::invalid_text_representation("boolean".to_owned(), value)),
+            },
+            datum => Err(QueryError::datatype_mismatch(
+                clause.to_owned(),
+                "boolean".to_owned(),
+                datum.type_name(),
+            )),
+        }
+    }
+
+    pub(crate) fn output(&self, datum: Datum) -> String {
+        match datum {
+            Datum::Null => "".to_owned(),
+            Datum::Boolean(true) => "t".to_owned(),
+            Datum::Boolean(false) => "f".to_owned(),
+            Datum::Integer(value) => value.to_string(),
+            Datum::Float(value) | Datum::Numeric(value) => value.to_string(),
+            Datum::Text(value) | Datum::Unknown(value) => value,
+            Datum::Date(date) => datetime::format_date(&date, self.style),
+            Datum::Timestamp(timestamp) => {
+                datetime::output(format_timestamp(&timestamp), SqlType::Timestamp, self.style, self.zone)
+            }
+            Datum::TimestampWithTimeZone(timestamp) => datetime::output(
+                format!("{}+00", format_timestamp(&timestamp)),
+                SqlType::TimestampWithTimeZone,
+                self.style,
+                self.zone,
+            ),
+            Datum::Interval(interval) => interval.to_string(),
+        }
+    }
+
+    fn cast(&self, datum: Datum, sql_type: SqlType) -> Result<Datum, QueryError> {
+        let zone = self.zone;
+        let cast = match (datum, sql_type) {
+            (Datum::Unknown(value), SqlType::Date) => {
+                Datum::Date(datetime::timestamp(&value, SqlType::Date, self.style, zone)?.date())
+            }
+            (Datum::Unknown(value), SqlType::Timestamp) => {
+                Datum::Timestamp(datetime::timestamp(&value, SqlType::Timestamp, self.style, zone)?)
+            }
+            (Datum::Unknown(value), SqlType::TimestampWithTimeZone) => Datum::TimestampWithTimeZone(
+                datetime::timestamp(&value, SqlType::TimestampWithTimeZone, self.style, zone)?,
+            ),
+            (Datum::Unknown(value), SqlType::Interval) => Datum::Interval(datetime::interval(&value)?),
+            (Datum::Date(date), SqlType::Timestamp) => Datum::Timestamp(datetime::midnight(date)),
+            (Datum::Date(date), SqlType::TimestampWithTimeZone) => {
+                Datum::TimestampWithTimeZone(zone.utc(&datetime::midnight(date)))
+            }
+            (Datum::Timestamp(timestamp), SqlType::Date) => Datum::Date(timestamp.date()),
+            (Datum::Timestamp(timestamp), SqlType::TimestampWithTimeZone) => {
+                Datum::TimestampWithTimeZone(zone.utc(&timestamp))
+            }
+            (Datum::TimestampWithTimeZone(timestamp), SqlType::Date) => Datum::Date(zone.local(&timestamp).date()),
+            (Datum::TimestampWithTimeZone(timestamp), SqlType::Timestamp) => Datum::Timestamp(zone.local(&timestamp)),
+            (datum, SqlType::Decimal) | (datum, SqlType::DoublePrecision) => {
+                let type_name = if sql_type == SqlType::Decimal {
+                    "numeric".to_owned()
+                } else {
+                    "double precision".to_owned()
+                };
+                match number(&datum) {
+                    Some(Ok(value)) if sql_type == SqlType::Decimal => Datum::Numeric(value),
+                    Some(Ok(value)) => Datum::Float(value),
+                    Some(Err(())) => {
+                        return Err(QueryError::invalid_text_representation(type_name, self.output(datum)))
+                    }
+                    None => return Err(QueryError::cannot_coerce(datum.type_name(), type_name)),
+                }
+            }
+            (datum, sql_type) if datum.sql_type() == sql_type => datum,
+            (datum, sql_type) => {
+                return Err(QueryError::cannot_coerce(
+                    datum.type_name(),
+                    datetime::type_name(sql_type),
+                ))
+            }
+        };
+        Ok(cast)
+    }
+
+    fn unary(&self, op: &UnaryOperator, datum: Datum) -> Result<Datum, QueryError> {
+        match (op, datum) {
+            (_, Datum::Null) => Ok(Datum::Null),
+            (UnaryOperator::Plus, datum @ Datum::Integer(_))
+            | (UnaryOperator::Plus, datum @ Datum::Float(_))
+            | (UnaryOperator::Plus, datum @ Datum::Numeric(_))
+            | (UnaryOperator::Plus, datum @ Datum::Interval(_)) => Ok(datum),
+            (UnaryOperator::Minus, Datum::Integer(value)) => value
+                .checked_neg()
+                .map(Datum::Integer)
+                .ok_or_else(|| QueryError::numeric_value_out_of_range("bigint".to_owned())),
+            (UnaryOperator::Minus, Datum::Float(value)) => Ok(Datum::Float(-value)),
+            (UnaryOperator::Minus, Datum::Numeric(value)) => Ok(Datum::Numeric(-value)),
+            (UnaryOperator::Minus, Datum::Interval(interval)) => interval
+                .checked_neg()
+                .map(Datum::Interval)
+                .ok_or_else(|| out_of_range(SqlType::Interval)),
+            (op, datum) => Err(QueryError::undefined_operator(format!("{} {}", op, datum.type_name()))),
+        }
+    }
+
+    fn binary(&self, left: Datum, op: &BinaryOperator, right: Datum) -> Result<Datum, QueryError> {
+        let (left, right) = self.common_type(left, op, right)?;
+        let zone = self.zone;
+        match (left, op, right) {
+            (Datum::Null, _, _) | (_, _, Datum::Null) => Ok(Datum::Null),
+            (Datum::Integer(left), op, Datum::Integer(right)) if is_arithmetic(op) => {
+                integer_arithmetic(left, op, right)
+            }
+            (Datum::Integer(left), op, Datum::Float(right)) if is_arithmetic(op) => {
+                float_arithmetic(left as f64, op, right)
+            }
+            (Datum::Float(left), op, Datum::Integer(right)) if is_arithmetic(op) => {
+                float_arithmetic(left, op, right as f64)
+            }
+            (Datum::Float(left), op, Datum::Float(right)) if is_arithmetic(op) => float_arithmetic(left, op, right),
+            // numeric values take over integers and give way to floats
+            (Datum::Numeric(left), op, Datum::Numeric(right)) if is_arithmetic(op) => {
+                numeric_arithmetic(left, op, right)
+            }
+            (Datum::Numeric(left), op, Datum::Integer(right)) if is_arithmetic(op) => {
+                numeric_arithmetic(left, op, right as f64)
+            }
+            (Datum::Integer(left), op, Datum::Numeric(right)) if is_arithmetic(op) => {
+                numeric_arithmetic(left as f64, op, right)
+            }
+            (Datum::Numeric(left), op, Datum::Float(right)) | (Datum::Float(left), op, Datum::Numeric(right))
+                if is_arithmetic(op) =>
+            {
+                float_arithmetic(left, op, right)
+            }
+            (Datum::Timestamp(left), BinaryOperator::Minus, Datum::Timestamp(right))
+            | (Datum::TimestampWithTimeZone(left), BinaryOperator::Minus, Datum::TimestampWithTimeZone(right)) => {
+                datetime::difference(&left, &right)
+                    .map(Datum::Interval)
+                    .ok_or_else(|| out_of_range(SqlType::Interval))
+            }
+            (Datum::Timestamp(timestamp), BinaryOperator::Plus, Datum::Interval(interval))
+            | (Datum::Interval(interval), BinaryOperator::Plus, Datum::Timestamp(timestamp)) => {
+                add_interval(&timestamp, Some(interval), None).map(Datum::Timestamp)
+            }
+            (Datum::Timestamp(timestamp), BinaryOperator::Minus, Datum::Interval(interval)) => {
+                add_interval(&timestamp, interval.checked_neg(), None).map(Datum::Timestamp)
+            }
+            (Datum::TimestampWithTimeZone(timestamp), BinaryOperator::Plus, Datum::Interval(interval))
+            | (Datum::Interval(interval), BinaryOperator::Plus, Datum::TimestampWithTimeZone(timestamp)) => {
+                add_interval(&timestamp, Some(interval), Some(zone)).map(Datum::TimestampWithTimeZone)
+            }
+            (Datum::TimestampWithTimeZone(timestamp), BinaryOperator::Minus, Datum::Interval(interval)) => {
+                add_interval(&timestamp, interval.checked_neg(), Some(zone)).map(Datum::TimestampWithTimeZone)
+            }
+            (Datum::Date(date), BinaryOperator::Plus, Datum::Interval(interval))
+            | (Datum::Interval(interval), BinaryOperator::Plus, Datum::Date(date)) => {
+                add_interval(&datetime::midnight(date), Some(interval), None).map(Datum::Timestamp)
+            }
+            (Datum::Date(date), BinaryOperator::Minus, Datum::Interval(interval)) => {
+                add_interval(&datetime::midnight(date), interval.checked_neg(), None).map(Datum::Timestamp)
+            }
+            (Datum::Date(date), BinaryOperator::Plus, Datum::Integer(days))
+            | (Datum::Integer(days), BinaryOperator::Plus, Datum::Date(date)) => add_days(date, Some(days)),
+            (Datum::Date(date), BinaryOperator::Minus, Datum::Integer(days)) => add_days(date, days.checked_neg()),
+            (Datum::Date(left), BinaryOperator::Minus, Datum::Date(right)) => {
+                Ok(Datum::Integer(left.signed_duration_since(right).num_days()))
+            }
+            (Datum::Interval(left), BinaryOperator::Plus, Datum::Interval(right)) => left
+                .checked_add(right)
+                .map(Datum::Interval)
+                .ok_or_else(|| out_of_range(SqlType::Interval)),
+            (Datum::Interval(left), BinaryOperator::Minus, Datum::Interval(right)) => left
+                .checked_sub(right)
+                .map(Datum::Interval)
+                .ok_or_else(|| out_of_range(SqlType::Interval)),
+            (Datum::Interval(interval), BinaryOperator::Multiply, Datum::Integer(factor))
+            | (Datum::Integer(factor), BinaryOperator::Multiply, Datum::Interval(interval)) => interval
+                .checked_mul(factor as f64)
+                .map(Datum::Interval)
+                .ok_or_else(|| out_of_range(SqlType::Interval)),
+            (Datum::Interval(interval), BinaryOperator::Multiply, Datum::Float(factor))
+            | (Datum::Float(factor), BinaryOperator::Multiply, Datum::Interval(interval)) => interval
+                .checked_mul(factor)
+                .map(Datum::Interval)
+                .ok_or_else(|| out_of_range(SqlType::Interval)),
+            (Datum::Interval(_), BinaryOperator::Divide, Datum::Integer(0)) => Err(QueryError::division_by_zero()),
+            (Datum::Interval(interval), BinaryOperator::Divide, Datum::Integer(divisor)) => interval
+                .checked_div(divisor as f64)
+                .map(Datum::Interval)
+                .ok_or_else(|| out_of_range(SqlType::Interval)),
+            (Datum::Interval(interval), BinaryOperator::Divide, Datum::Float(divisor)) => {
+                if divisor == 0.0 {
+                    return Err(QueryError::division_by_zero());
+                }
+                interval
+                    .checked_div(divisor)
+                    .map(Datum::Interval)
+                    .ok_or_else(|| out_of_range(SqlType::Interval))
+            }
+            (left, op, right) => Err(QueryError::undefined_operator(format!(
+                "{} {} {}",
+                left.type_name(),
+                op,
+                right.type_name()
+            ))),
+        }
+    }
+
+    fn compare(&self, left: Datum, op: &BinaryOperator, right: Datum) -> Result<Datum, QueryError> {
+        let (left, right) = self.common_type(left, op, right)?;
+        let ordering = match (&left, &right) {
+            (Datum::Null, _) | (_, Datum::Null) => return Ok(Datum::Null),
+            (Datum::Boolean(left), Datum::Boolean(right)) => Some(left.cmp(right)),
+            (Datum::Integer(left), Datum::Integer(right)) => Some(left.cmp(right)),
+            (Datum::Integer(left), Datum::Float(right)) => Some(float_order(*left as f64, *right)),
+            (Datum::Float(left), Datum::Integer(right)) => Some(float_order(*left, *right as f64)),
+            (Datum::Float(left), Datum::Float(right)) => Some(float_order(*left, *right)),
+            (Datum::Numeric(_), _) | (_, Datum::Numeric(_)) => match (number(&left), number(&right)) {
+                (Some(Ok(left)), Some(Ok(right))) => Some(float_order(left, right)),
+                _ => None,
+            },
+            (Datum::Text(left), Datum::Text(right))
+            | (Datum::Text(left), Datum::Unknown(right))
+            | (Datum::Unknown(left), Datum::Text(right))
+            | (Datum::Unknown(left), Datum::Unknown(right)) => Some(left.cmp(right)),
+            (Datum::Date(left), Datum::Date(right)) => Some(left.cmp(right)),
+            (Datum::Timestamp(left), Datum::Timestamp(right))
+            | (Datum::TimestampWithTimeZone(left), Datum::TimestampWithTimeZone(right)) => Some(left.cmp(right)),
+            (Datum::Interval(left), Datum::Interval(right)) => Some(span(left).cmp(&span(right))),
+            _ => None,
+        };
+        match (ordering, comparison(op)) {
+            (Some(ordering), Some(comparison)) => Ok(Datum::Boolean(match comparison {
+                Comparison::Eq => ordering == Ordering::Equal,
+                Comparison::NotEq => ordering != Ordering::Equal,
+                Comparison::Lt => ordering == Ordering::Less,
+                Comparison::LtEq => ordering != Ordering::Greater,
+                Comparison::Gt => ordering == Ordering::Greater,
+                Comparison::GtEq => ordering != Ordering::Less,
+            })),
+            _ => Err(QueryError::undefined_operator(format!(
+                "{} {} {}",
+                left.type_name(),
+                op,
+                right.type_name()
+            ))),
+        }
+    }
+
+    // string literals take type of the other operand, if the operation is not
+    // defined for two values of that type they are taken as intervals, dates
+    // and timestamps are taken as timestamps with time zone along with them
+    fn common_type(&self, left: Datum, op: &BinaryOperator, right: Datum) -> Result<(Datum, Datum), QueryError> {
+        let zone = self.zone;
+        let resolve = |unknown: String, other: &Datum| -> Result<Datum, QueryError> {
+            match (other, op) {
+                (Datum::Interval(_), BinaryOperator::Multiply) | (Datum::Interval(_), BinaryOperator::Divide) => {
+                    Ok(match unknown.trim().parse::<f64>() {
+                        Ok(number) => Datum::Float(number),
+                        Err(_) => Datum::Unknown(unknown),
+                    })
+                }
+                (Datum::Interval(_), _) | (Datum::Date(_), BinaryOperator::Plus) => {
+                    self.cast(Datum::Unknown(unknown), SqlType::Interval)
+                }
+                (Datum::Timestamp(_), BinaryOperator::Plus)
+                | (Datum::TimestampWithTimeZone(_), BinaryOperator::Plus) => {
+                    self.cast(Datum::Unknown(unknown), SqlType::Interval)
+                }
+                (Datum::Date(_), _) | (Datum::Timestamp(_), _) | (Datum::TimestampWithTimeZone(_), _) => {
+                    self.cast(Datum::Unknown(unknown), other.sql_type())
+                }
+                (Datum::Integer(_), _) => match unknown.trim().parse::<i64>() {
+                    Ok(value) => Ok(Datum::Integer(value)),
+                    Err(_) => Err(QueryError::invalid_text_representation("integer".to_owned(), unknown)),
+                },
+                (Datum::Float(_), _) | (Datum::Numeric(_), _) => self.cast(Datum::Unknown(unknown), other.sql_type()),
+                (Datum::Boolean(_), _) => self.truth(Datum::Unknown(unknown), "").map(boolean),
+                (Datum::Text(_), _) => Ok(Datum::Text(unknown)),
+                _ => Ok(Datum::Unknown(unknown)),
+            }
+        };
+        let with_time_zone = |datum: Datum| match datum {
+            Datum::Date(date) => Datum::TimestampWithTimeZone(zone.utc(&datetime::midnight(date))),
+            Datum::Timestamp(timestamp) => Datum::TimestampWithTimeZone(zone.utc(&timestamp)),
+            datum => datum,
+        };
+        Ok(match (left, right) {
+            (Datum::Unknown(left), right) if !matches!(right, Datum::Unknown(_)) => (resolve(left, &right)?, right),
+            (left, Datum::Unknown(right)) if !matches!(left, Datum::Unknown(_)) => {
+                let right = resolve(right, &left)?;
+                (left, right)
+            }
+            (Datum::Date(date), right @ Datum::Timestamp(_)) => (Datum::Timestamp(datetime::midnight(date)), right),
+            (left @ Datum::Timestamp(_), Datum::Date(date)) => (left, Datum::Timestamp(datetime::midnight(date))),
+            (left @ Datum::TimestampWithTimeZone(_), right) => (left, with_time_zone(right)),
+            (left, right @ Datum::TimestampWithTimeZone(_)) => (with_time_zone(left), right),
+            (left, right) => (left, right),
+        })
+    }
+
+    fn function<V: ColumnValue>(&self, function: &Function, row: &[V]) -> Result<Datum, QueryError> {
+        let name = function.name.to_string().to_lowercase();
+        let args = function
+            .args
+            .iter()
+            .map(|arg| self.value(arg, row))
+            .collect::<Result<Vec<_>, _>>()?;
+        if math::is_defined(&name, args.len()) {
+            return self.math(&name, &args);
+        }
+        let zone = self.zone;
+        let result = match (name.as_str(), args.as_slice()) {
+            ("random", []) => match self.random {
+                Some(random) => Some(Datum::Float(random.next())),
+                None => return Err(self.not_supported()),
+            },
+            ("setseed", [seed]) => match (self.random, seed, number(seed)) {
+                (_, Datum::Null, _) => Some(Datum::Null),
+                (None, _, _) => return Err(self.not_supported()),
+                (Some(random), _, Some(Ok(value))) if (-1.0..=1.0).contains(&value) => {
+                    random.set_seed(value);
+                    Some(Datum::Null)
+                }
+                (Some(_), _, Some(Ok(value))) => {
+                    return Err(QueryError::value_out_of_range(format!(
+                        "setseed parameter {} is out of allowed range [-1,1]",
+                        value
+                    )))
+                }
+                (Some(_), seed, Some(Err(()))) => {
+                    return Err(QueryError::invalid_text_representation(
+                        "double precision".to_owned(),
+                        self.output(seed.clone()),
+                    ))
+                }
+                (Some(_), _, None) => None,
+            },
+            ("age", [timestamp]) => match self.timestamp_argument(timestamp.clone(), None)? {
+                Datum::Timestamp(timestamp) => {
+                    let today = datetime::midnight(datetime::today(zone));
+                    Some(Datum::Interval(datetime::age(&today, &timestamp, None)))
+                }
+                Datum::TimestampWithTimeZone(timestamp) => {
+                    let today = zone.utc(&datetime::midnight(datetime::today(zone)));
+                    Some(Datum::Interval(datetime::age(&today, &timestamp, Some(zone))))
+                }
+                _ => None,
+            },
+            ("age", [left, right]) => {
+                let left = self.timestamp_argument(left.clone(), Some(right))?;
+                let right = self.timestamp_argument(right.clone(), Some(&left))?;
+                let age = match (left, right) {
+                    (Datum::Timestamp(left), Datum::Timestamp(right)) => Some(datetime::age(&left, &right, None)),
+                    (Datum::TimestampWithTimeZone(left), Datum::TimestampWithTimeZone(right)) => {
+                        Some(datetime::age(&left, &right, Some(zone)))
+                    }
+                    (Datum::Timestamp(left), Datum::TimestampWithTimeZone(right)) => {
+                        Some(datetime::age(&zone.utc(&left), &right, Some(zone)))
+                    }
+                    (Datum::TimestampWithTimeZone(left), Datum::Timestamp(right)) => {
+                        Some(datetime::age(&left, &zone.utc(&right), Some(zone)))
+                    }
+                    _ => None,
+                };
+                age.map(Datum::Interval)
+            }
+            ("justify_hours", [interval]) | ("justify_days", [interval]) | ("justify_interval", [interval]) => {
+                let justified = match self.cast(interval.clone(), SqlType::Interval) {
+                    Ok(Datum::Interval(interval)) if name == "justify_hours" => Some(interval.justify_hours()),
+                    Ok(Datum::Interval(interval)) if name == "justify_days" => Some(interval.justify_days()),
+                    Ok(Datum::Interval(interval)) => Some(interval.justify_interval()),
+                    _ => None,
+                };
+                justified.map(Datum::Interval)
+            }
+            _ => return Err(self.not_supported()),
+        };
+        result.ok_or_else(|| undefined_function(&name, &args))
+    }
+
+    // arguments are taken in double precision, result is numeric when a
+    // function that has a numeric variant is given a numeric argument
+    fn math(&self, name: &str, args: &[Datum]) -> Result<Datum, QueryError> {
+        if args.contains(&Datum::Null) {
+            return Ok(Datum::Null);
+        }
+        let mut numbers = vec![];
+        for arg in args {
+            match (arg, number(arg)) {
+                (Datum::Text(_), _) | (_, None) => return Err(undefined_function(name, args)),
+                (_, Some(Ok(value))) => numbers.push(value),
+                (arg, Some(Err(()))) => {
+                    return Err(QueryError::invalid_text_representation(
+                        "double precision".to_owned(),
+                        self.output(arg.clone()),
+                    ))
+                }
+            }
+        }
+        let value = math::apply(name, &numbers)?;
+        // logarithm to a base is defined only for numeric values
+        let numeric = (name == "log" && args.len() == 2)
+            || (math::has_numeric_variant(name) && args.iter().any(|arg| matches!(arg, Datum::Numeric(_))));
+        Ok(if numeric {
+            Datum::Numeric(value)
+        } else {
+            Datum::Float(value)
+        })
+    }
+
+    // string literals are taken as timestamps with time zone unless the other
+    // argument is a timestamp without it
+    fn timestamp_argument(&self, argument: Datum, other: Option<&Datum>) -> Result<Datum, QueryError> {
+        match (argument, other) {
+            (Datum::Unknown(value), Some(Datum::Timestamp(_))) | (Datum::Unknown(value), Some(Datum::Date(_))) => {
+                self.cast(Datum::Unknown(value), SqlType::Timestamp)
+            }
+            (Datum::Unknown(value), _) => self.cast(Datum::Unknown(value), SqlType::TimestampWithTimeZone),
+            (Datum::Date(date), _) => Ok(Datum::Timestamp(datetime::midnight(date))),
+            (argument, _) => Ok(argument),
+        }
+    }
+
+    fn not_supported(&self) -> QueryError {
+        QueryError::not_supported_operation(self.raw_sql_query.to_owned())
+    }
+}
+
+/// name of a column that expression gives value to, e.g. name of a function
+/// or of a type that value is cast to
+pub(crate) fn column_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Function(function) => function.name.to_string().to_lowercase(),
+        Expr::Cast { data_type, .. } | Expr::TypedString { data_type, .. } => match cast_type(data_type) {
+            Some(SqlType::TimestampWithTimeZone) => "timestamptz".to_owned(),
+            Some(sql_type) => datetime::type_name(sql_type),
+            None => "?column?".to_owned(),
+        },
+        Expr::Value(Value::Interval { .. }) => "interval".to_owned(),
+        Expr::Nested(expr) => column_name(expr),
+        _ => "?column?".to_owned(),
+    }
+}
+
+/// comparisons of the columns with constants along with the compared column,
+/// rows are looked up by them in indexes of the columns
+pub(crate) fn column_conditions(predicate: &Expr, columns: &[String]) -> Vec<(String, Comparison, String)> {
+    columns
+        .iter()
+        .flat_map(|column| {
+            key_conditions(predicate, column)
+                .into_iter()
+                .map(move |(comparison, value)| (column.clone(), comparison, value))
+        })
+        .collect()
+}
+
+/// comparisons of the column with constants that rows satisfying the predicate
+/// have to satisfy too, they are looked for only among operands of `AND`
+pub(crate) fn key_conditions(predicate: &Expr, column: &str) -> Vec<(Comparison, String)> {
+    let is_column = |expr: &Expr| match expr {
+        Expr::Identifier(Ident { value, .. }) => value == column,
+        Expr::CompoundIdentifier(idents) => idents.last().map(|ident| ident.value == column) == Some(true),
+        _ => false,
+    };
+    match predicate {
+        Expr::Nested(expr) => key_conditions(expr, column),
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let mut conditions = key_conditions(left, column);
+            conditions.extend(key_conditions(right, column));
+            conditions
+        }
+        Expr::BinaryOp { left, op, right } => match (comparison(op), constant(right), constant(left)) {
+            (Some(comparison), Some(value), _) if is_column(left) => vec![(comparison, value)],
+            (Some(comparison), _, Some(value)) if is_column(right) => vec![(reversed(comparison), value)],
+            _ => vec![],
+        },
+        Expr::Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        } if is_column(expr) => {
+            let mut conditions = vec![];
+            if let Some(low) = constant(low) {
+                conditions.push((Comparison::GtEq, low));
+            }
+            if let Some(high) = constant(high) {
+                conditions.push((Comparison::LtEq, high));
+            }
+            conditions
+        }
+        Expr::InList {
+            expr,
+            list,
+            negated: false,
+        } if is_column(expr) => match list.as_slice() {
+            [item] => constant(item)
+                .map(|value| vec![(Comparison::Eq, value)])
+                .unwrap_or_default(),
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+// text of a literal as it is given to storage
+fn constant(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Value(Value::Number(value)) | Expr::Value(Value::SingleQuotedString(value)) => Some(value.clone()),
+        Expr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => match &**expr {
+            Expr::Value(Value::Number(value)) => Some(format!("-{}", value)),
+            _ => None,
+        },
+        Expr::Nested(expr) => constant(expr),
+        _ => None,
+    }
+}
+
+fn comparison(op: &BinaryOperator) -> Option<Comparison> {
+    match op {
+        BinaryOperator::Eq => Some(Comparison::Eq),
+        BinaryOperator::NotEq => Some(Comparison::NotEq),
+        BinaryOperator::Lt => Some(Comparison::Lt),
+        BinaryOperator::LtEq => Some(Comparison::LtEq),
+        BinaryOperator::Gt => Some(Comparison::Gt),
+        BinaryOperator::GtEq => Some(Comparison::GtEq),
+        _ => None,
+    }
+}
+
+// `1 < id` is the same as `id > 1`
+fn reversed(comparison: Comparison) -> Comparison {
+    match comparison {
+        Comparison::Lt => Comparison::Gt,
+        Comparison::LtEq => Comparison::GtEq,
+        Comparison::Gt => Comparison::Lt,
+        Comparison::GtEq => Comparison::LtEq,
+        comparison => comparison,
+    }
+}
+
+fn referenced_columns(expr: &Expr, names: &mut Vec<String>) {
+    match expr {
+        Expr::Identifier(Ident { value, .. }) => names.push(value.clone()),
+        Expr::CompoundIdentifier(idents) => names.extend(idents.last().map(|ident| ident.value.clone())),
+        Expr::IsNull(expr)
+        | Expr::IsNotNull(expr)
+        | Expr::Nested(expr)
+        | Expr::Cast { expr, .. }
+        | Expr::UnaryOp { expr, .. } => referenced_columns(expr, names),
+        Expr::BinaryOp { left, right, .. } => {
+            referenced_columns(left, names);
+            referenced_columns(right, names);
+        }
+        Expr::Between { expr, low, high, .. } => {
+            for expr in &[expr, low, high] {
+                referenced_columns(expr, names);
+            }
+        }
+        Expr::InList { expr, list, .. } => {
+            referenced_columns(expr, names);
+            for item in list {
+                referenced_columns(item, names);
+            }
+        }
+        Expr::Function(function) => {
+            for arg in &function.args {
+                referenced_columns(arg, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+// values of columns are given in the format they are kept in storage
+fn column_value(sql_type: SqlType, value: &str) -> Datum {
+    let datum = match sql_type {
+        SqlType::SmallInt | SqlType::Integer | SqlType::BigInt => value.parse().ok().map(Datum::Integer),
+        SqlType::Real | SqlType::DoublePrecision => value.parse().ok().map(Datum::Float),
+        SqlType::Decimal => value.parse().ok().map(Datum::Numeric),
+        SqlType::Timestamp => parse_timestamp(value).map(Datum::Timestamp),
+        SqlType::TimestampWithTimeZone => parse_timestamp_with_time_zone(value).map(Datum::TimestampWithTimeZone),
+        SqlType::Interval => Interval::parse(value).map(Datum::Interval),
+        _ => None,
+    };
+    datum.unwrap_or_else(|| Datum::Text(value.to_owned()))
+}
+
+fn undefined_function(name: &str, args: &[Datum]) -> QueryError {
+    QueryError::undefined_function(format!(
+        "{}({})",
+        name,
+        args.iter().map(Datum::type_name).collect::<Vec<_>>().join(", ")
+    ))
+}
+
+// numbers that are given as text can't be read when they are malformed
+fn number(datum: &Datum) -> Option<Result<f64, ()>> {
+    match datum {
+        Datum::Integer(value) => Some(Ok(*value as f64)),
+        Datum::Float(value) | Datum::Numeric(value) => Some(Ok(*value)),
+        Datum::Text(value) | Datum::Unknown(value) => Some(value.trim().parse().map_err(|_| ())),
+        _ => None,
+    }
+}
+
+fn boolean(value: Option<bool>) -> Datum {
+    value.map(Datum::Boolean).unwrap_or(Datum::Null)
+}
+
+// null is unknown, so it decides the result only when the other value does not
+fn and(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+fn or(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+fn is_arithmetic(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Plus
+            | BinaryOperator::Minus
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulus
+    )
+}
+
+fn integer_arithmetic(left: i64, op: &BinaryOperator, right: i64) -> Result<Datum, QueryError> {
+    let result = match op {
+        BinaryOperator::Divide | BinaryOperator::Modulus if right == 0 => return Err(QueryError::division_by_zero()),
+        BinaryOperator::Plus => left.checked_add(right),
+        BinaryOperator::Minus => left.checked_sub(right),
+        BinaryOperator::Multiply => left.checked_mul(right),
+        BinaryOperator::Divide => left.checked_div(right),
+        _ => left.checked_rem(right),
+    };
+    result
+        .map(Datum::Integer)
+        .ok_or_else(|| QueryError::numeric_value_out_of_range("bigint".to_owned()))
+}
+
+fn float_arithmetic(left: f64, op: &BinaryOperator, right: f64) -> Result<Datum, QueryError> {
+    let result = match op {
+        BinaryOperator::Divide | BinaryOperator::Modulus if right == 0.0 => return Err(QueryError::division_by_zero()),
+        BinaryOperator::Plus => left + right,
+        BinaryOperator::Minus => left - right,
+        BinaryOperator::Multiply => left * right,
+        BinaryOperator::Divide => left / right,
+        _ => left % right,
+    };
+    if result.is_infinite() && left.is_finite() && right.is_finite() {
+        Err(QueryError::numeric_value_out_of_range("double precision".to_owned()))
+    } else {
+        Ok(Datum::Float(result))
+    }
+}
+
+// NaN is equal to itself and greater than any other number like in PostgreSQL
+fn numeric_arithmetic(left: f64, op: &BinaryOperator, right: f64) -> Result<Datum, QueryError> {
+    match float_arithmetic(left, op, right)? {
+        Datum::Float(value) => Ok(Datum::Numeric(value)),
+        datum => Ok(datum),
+    }
+}
+
+fn float_order(left: f64, right: f64) -> Ordering {
+    left.partial_cmp(&right)
+        .unwrap_or_else(|| left.is_nan().cmp(&right.is_nan()))
+}
+
+// months and days are taken as 30 days and 24 hours to compare intervals
+pub(crate) fn span(interval: &Interval) -> i128 {
+    const MICROSECONDS_PER_DAY: i128 = 86_400_000_000;
+    (interval.months as i128 * 30 + interval.days as i128) * MICROSECONDS_PER_DAY + interval.microseconds as i128
+}
+
+fn cast_type(data_type: &DataType) -> Option<SqlType> {
+    match data_type {
+        DataType::Date => Some(SqlType::Date),
+        DataType::Timestamp => Some(SqlType::Timestamp),
+        DataType::Interval => Some(SqlType::Interval),
+        DataType::Decimal(_, _) => Some(SqlType::Decimal),
+        DataType::Double | DataType::Float(_) => Some(SqlType::DoublePrecision),
+        // sql parser does not keep time zone of timestamp type, so it is rewritten before parsing
+        DataType::Custom(name) if name.to_string().eq_ignore_ascii_case("timestamptz") => {
+            Some(SqlType::TimestampWithTimeZone)
+        }
+        _ => None,
+    }
+}
+
+fn unit(field: &DateTimeField) -> &'static str {
+    match field {
+        DateTimeField::Year => "years",
+        DateTimeField::Month => "months",
+        DateTimeField::Day => "days",
+        DateTimeField::Hour => "hours",
+        DateTimeField::Minute => "minutes",
+        DateTimeField::Second => "seconds",
+    }
+}
+
+// interval is `None` when it can not be negated
+fn add_interval(
+    timestamp: &NaiveDateTime,
+    interval: Option<Interval>,
+    zone: Option<TimeZone>,
+) -> Result<NaiveDateTime, QueryError> {
+    interval
+        .and_then(|interval| datetime::add_interval(timestamp, interval, zone))
+        .ok_or_else(|| out_of_range(SqlType::Timestamp))
+}
+
+fn add_days(date: NaiveDate, days: Option<i64>) -> Result<Datum, QueryError> {
+    days.filter(|days| days.abs() <= i32::MAX as i64)
+        .and_then(|days| date.checked_add_signed(Duration::days(days)))
+        .map(Datum::Date)
+        .ok_or_else(|| out_of_range(SqlType::Date))
+}
+
+fn out_of_range(sql_type: SqlType) -> QueryError {
+    QueryError::datetime_field_overflow(datetime::type_name(sql_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::ast::ObjectName;
+
+    fn evaluate(expr: Expr) -> Result<String, QueryError> {
+        let evaluator = Evaluator::new(DateStyle::default(), TimeZone::default(), "");
+        evaluator.evaluate(&expr).map(|datum| evaluator.output(datum))
+    }
+
+    fn timestamp(value: &str) -> Expr {
+        Expr::TypedString {
+            data_type: DataType::Timestamp,
+            value: value.to_owned(),
+        }
+    }
+
+    fn date(value: &str) -> Expr {
+        Expr::TypedString {
+            data_type: DataType::Date,
+            value: value.to_owned(),
+        }
+    }
+
+    fn interval(value: &str) -> Expr {
+        Expr::Value(Value::Interval {
+            value: value.to_owned(),
+            leading_field: None,
+            leading_precision: None,
+            last_field: None,
+            fractional_seconds_precision: None,
+        })
+    }
+
+    fn string(value: &str) -> Expr {
+        Expr::Value(Value::SingleQuotedString(value.to_owned()))
+    }
+
+    fn number(value: &str) -> Expr {
+        Expr::Value(Value::Number(value.to_owned()))
+    }
+
+    fn binary(left: Expr, op: BinaryOperator, right: Expr) -> Expr {
+        Expr::BinaryOp {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    fn column(name: &str) -> Expr {
+        Expr::Identifier(Ident::new(name))
+    }
+
+    fn null() -> Expr {
+        Expr::Value(Value::Null)
+    }
+
+    #[test]
+    fn timestamp_plus_interval() {
+        assert_eq!(
+            evaluate(binary(
+                timestamp("2020-01-31 10:00"),
+                BinaryOperator::Plus,
+                interval("1 month")
+            )),
+            Ok("2020-02-29 10:00:00".to_owned())
+        );
+    }
+
+    #[test]
+    fn interval_is_taken_from_string_literal() {
+        assert_eq!(
+            evaluate(binary(timestamp("2020-01-01"), BinaryOperator::Minus, string("1 day"))),
+            Err(QueryError::invalid_datetime_format(
+                "timestamp".to_owned(),
+                "1 day".to_owned()
+            ))
+        );
+        assert_eq!(
+            evaluate(binary(timestamp("2020-01-01"), BinaryOperator::Plus, string("1 day"))),
+            Ok("2020-01-02 00:00:00".to_owned())
+        );
+    }
+
+    #[test]
+    fn difference_of_timestamps() {
+        assert_eq!(
+            evaluate(binary(
+                timestamp("2020-03-01"),
+                BinaryOperator::Minus,
+                timestamp("2020-01-01 12:00")
+            )),
+            Ok("59 days 12:00:00".to_owned())
+        );
+    }
+
+    #[test]
+    fn dates_and_days() {
+        let date = date("2020-02-28");
+
+        assert_eq!(
+            evaluate(binary(date.clone(), BinaryOperator::Plus, number("2"))),
+            Ok("2020-03-01".to_owned())
+        );
+        assert_eq!(
+            evaluate(binary(
+                date.clone(),
+                BinaryOperator::Minus,
+                self::date("2020-01-01")
+            )),
+            Ok("58".to_owned())
+        );
+        assert_eq!(
+            evaluate(binary(date, BinaryOperator::Plus, interval("1 hour"))),
+            Ok("2020-02-28 01:00:00".to_owned())
+        );
+    }
+
+    #[test]
+    fn interval_multiplied_and_divided() {
+        assert_eq!(
+            evaluate(binary(interval("1 mon"), BinaryOperator::Multiply, number("1.5"))),
+            Ok("1 mon 15 days".to_owned())
+        );
+        assert_eq!(
+            evaluate(binary(number("2"), BinaryOperator::Multiply, interval("1 day 01:00"))),
+            Ok("2 days 02:00:00".to_owned())
+        );
+        assert_eq!(
+            evaluate(binary(interval("1 day"), BinaryOperator::Divide, number("0"))),
+            Err(QueryError::division_by_zero())
+        );
+    }
+
+    #[test]
+    fn negated_interval() {
+        assert_eq!(
+            evaluate(Expr::UnaryOp {
+                op: UnaryOperator::Minus,
+                expr: Box::new(interval("1 day -02:00")),
+            }),
+            Ok("-1 days +02:00:00".to_owned())
+        );
+    }
+
+    #[test]
+    fn interval_with_leading_field() {
+        assert_eq!(
+            evaluate(Expr::Value(Value::Interval {
+                value: "3".to_owned(),
+                leading_field: Some(DateTimeField::Day),
+                leading_precision: None,
+                last_field: None,
+                fractional_seconds_precision: None,
+            })),
+            Ok("3 days".to_owned())
+        );
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(
+            evaluate(binary(
+                interval("178000000 years"),
+                BinaryOperator::Plus,
+                interval("178000000 years")
+            )),
+            Err(QueryError::datetime_field_overflow("interval".to_owned()))
+        );
+    }
+
+    #[test]
+    fn timestamp_cast_to_date() {
+        assert_eq!(
+            evaluate(Expr::Cast {
+                expr: Box::new(string("2020-01-02 03:04:05")),
+                data_type: DataType::Date,
+            }),
+            Ok("2020-01-02".to_owned())
+        );
+        assert_eq!(
+            column_name(&Expr::Cast {
+                expr: Box::new(string("2020-01-02 03:04:05")),
+                data_type: DataType::Date,
+            }),
+            "date".to_owned()
+        );
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(
+            evaluate(binary(number("1"), BinaryOperator::Lt, number("2.5"))),
+            Ok("t".to_owned())
+        );
+        assert_eq!(
+            evaluate(binary(string("b"), BinaryOperator::GtEq, string("a"))),
+            Ok("t".to_owned())
+        );
+        assert_eq!(
+            evaluate(binary(
+                timestamp("2020-01-01"),
+                BinaryOperator::Eq,
+                string("2020-01-01 00:00")
+            )),
+            Ok("t".to_owned())
+        );
+        assert_eq!(
+            evaluate(binary(number("1"), BinaryOperator::Eq, string("one"))),
+            Err(QueryError::invalid_text_representation(
+                "integer".to_owned(),
+                "one".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn logic_with_nulls() {
+        let unknown = binary(number("1"), BinaryOperator::Eq, null());
+
+        assert_eq!(evaluate(unknown.clone()), Ok("".to_owned()));
+        assert_eq!(
+            evaluate(binary(
+                unknown.clone(),
+                BinaryOperator::Or,
+                Expr::Value(Value::Boolean(true))
+            )),
+            Ok("t".to_owned())
+        );
+        assert_eq!(
+            evaluate(binary(unknown, BinaryOperator::And, Expr::Value(Value::Boolean(false)))),
+            Ok("f".to_owned())
+        );
+        assert_eq!(evaluate(Expr::IsNull(Box::new(null()))), Ok("t".to_owned()));
+    }
+
+    #[test]
+    fn between_and_in_list() {
+        assert_eq!(
+            evaluate(Expr::Between {
+                expr: Box::new(number("5")),
+                negated: false,
+                low: Box::new(number("1")),
+                high: Box::new(number("5")),
+            }),
+            Ok("t".to_owned())
+        );
+        assert_eq!(
+            evaluate(Expr::InList {
+                expr: Box::new(number("5")),
+                list: vec![number("1"), null()],
+                negated: true,
+            }),
+            Ok("".to_owned())
+        );
+    }
+
+    #[test]
+    fn integer_arithmetic_errors() {
+        assert_eq!(
+            evaluate(binary(number("9223372036854775807"), BinaryOperator::Plus, number("1"))),
+            Err(QueryError::numeric_value_out_of_range("bigint".to_owned()))
+        );
+        assert_eq!(
+            evaluate(binary(number("1"), BinaryOperator::Modulus, number("0"))),
+            Err(QueryError::division_by_zero())
+        );
+        assert_eq!(
+            evaluate(binary(number("7"), BinaryOperator::Divide, number("2"))),
+            Ok("3".to_owned())
+        );
+    }
+
+    #[test]
+    fn filter_rows() {
+        let columns = vec![
+            ("id".to_owned(), SqlType::Integer),
+            ("name".to_owned(), SqlType::VarChar(10)),
+        ];
+        let evaluator = Evaluator::new(DateStyle::default(), TimeZone::default(), "").with_columns(&columns);
+        let predicate = binary(
+            binary(column("id"), BinaryOperator::Gt, number("2")),
+            BinaryOperator::And,
+            binary(column("name"), BinaryOperator::NotEq, string("b")),
+        );
+
+        assert_eq!(
+            evaluator.filter(&predicate, &["10".to_owned(), "a".to_owned()]),
+            Ok(true)
+        );
+        assert_eq!(
+            evaluator.filter(&predicate, &["10".to_owned(), "b".to_owned()]),
+            Ok(false)
+        );
+        assert_eq!(
+            evaluator.filter(&predicate, &["2".to_owned(), "a".to_owned()]),
+            Ok(false)
+        );
+        assert_eq!(
+            evaluator.filter(&column("id"), &["1".to_owned(), "a".to_owned()]),
+            Err(QueryError::datatype_mismatch(
+                "WHERE".to_owned(),
+                "boolean".to_owned(),
+                "integer".to_owned()
+            ))
+        );
+        assert_eq!(
+            evaluator.check(&binary(column("age"), BinaryOperator::Gt, column("id"))),
+            Err(QueryError::column_does_not_exist(vec!["age".to_owned()]))
+        );
+    }
+
+    #[test]
+    fn conditions_on_key_column() {
+        let predicate = binary(
+            binary(number("1"), BinaryOperator::Lt, column("id")),
+            BinaryOperator::And,
+            Expr::Nested(Box::new(binary(
+                binary(column("id"), BinaryOperator::LtEq, number("10")),
+                BinaryOperator::And,
+                binary(column("name"), BinaryOperator::Eq, string("a")),
+            ))),
+        );
+
+        assert_eq!(
+            key_conditions(&predicate, "id"),
+            vec![(Comparison::Gt, "1".to_owned()), (Comparison::LtEq, "10".to_owned())]
+        );
+        assert_eq!(
+            key_conditions(
+                &binary(
+                    binary(column("id"), BinaryOperator::Eq, number("1")),
+                    BinaryOperator::Or,
+                    binary(column("id"), BinaryOperator::Eq, number("2"))
+                ),
+                "id"
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn conditions_on_columns() {
+        let predicate = binary(
+            binary(column("id"), BinaryOperator::Gt, number("1")),
+            BinaryOperator::And,
+            binary(string("a"), BinaryOperator::Eq, column("name")),
+        );
+
+        assert_eq!(
+            column_conditions(&predicate, &["id".to_owned(), "name".to_owned(), "age".to_owned()]),
+            vec![
+                ("id".to_owned(), Comparison::Gt, "1".to_owned()),
+                ("name".to_owned(), Comparison::Eq, "a".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn numeric_values() {
+        let numeric = |value: &str| Expr::Cast {
+            expr: Box::new(number(value)),
+            data_type: DataType::Decimal(None, None),
+        };
+        let evaluator = Evaluator::new(DateStyle::default(), TimeZone::default(), "");
+
+        assert_eq!(
+            evaluator.evaluate(&binary(numeric("1.5"), BinaryOperator::Multiply, number("2"))),
+            Ok(Datum::Numeric(3.0))
+        );
+        assert_eq!(
+            evaluator.evaluate(&binary(numeric("1.5"), BinaryOperator::Plus, number("0.5"))),
+            Ok(Datum::Float(2.0))
+        );
+        assert_eq!(
+            evaluate(binary(numeric("2"), BinaryOperator::Gt, string("1.5"))),
+            Ok("t".to_owned())
+        );
+        assert_eq!(
+            evaluate(Expr::Cast {
+                expr: Box::new(string("two")),
+                data_type: DataType::Decimal(None, None),
+            }),
+            Err(QueryError::invalid_text_representation(
+                "numeric".to_owned(),
+                "two".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn math_functions() {
+        let function = |name: &str, args: Vec<Expr>| {
+            Expr::Function(Function {
+                name: ObjectName(vec![Ident::new(name)]),
+                args,
+                over: None,
+                distinct: false,
+            })
+        };
+
+        assert_eq!(evaluate(function("pi", vec![])), Ok(std::f64::consts::PI.to_string()));
+        assert_eq!(evaluate(function("exp", vec![null()])), Ok("".to_owned()));
+        assert_eq!(
+            evaluate(function("sqrt", vec![timestamp("2020-01-01")])),
+            Err(QueryError::undefined_function(
+                "sqrt(timestamp without time zone)".to_owned()
+            ))
+        );
+        assert_eq!(
+            evaluate(function("random", vec![])),
+            Err(QueryError::not_supported_operation("".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,408 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{session::Session, QueryError};
+use kernel::SystemResult;
+use sql_types::SqlType;
+use sqlparser::ast::Ident;
+use storage::{backend::BackendStorage, frontend::FrontendStorage, DefaultValue, Projection, TableConstraint};
+
+const INFORMATION_SCHEMA: &str = "information_schema";
+const PG_CATALOG: &str = "pg_catalog";
+const TEMP_SCHEMA_PREFIX: &str = "pg_temp_";
+
+/// Read-only relations that describe schemas and tables of the catalog to
+/// client tools, they are built from the catalog whenever they are selected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SystemRelation {
+    Schemata,
+    Tables,
+    Columns,
+    PgNamespace,
+    PgTables,
+}
+
+impl SystemRelation {
+    /// relations of `pg_catalog` are found without schema name, as it is
+    /// always searched first
+    pub fn find(name: &[Ident]) -> Option<SystemRelation> {
+        let name = name.iter().map(|ident| ident.value.to_lowercase()).collect::<Vec<_>>();
+        match name.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+            [INFORMATION_SCHEMA, "schemata"] => Some(SystemRelation::Schemata),
+            [INFORMATION_SCHEMA, "tables"] => Some(SystemRelation::Tables),
+            [INFORMATION_SCHEMA, "columns"] => Some(SystemRelation::Columns),
+            [PG_CATALOG, "pg_namespace"] | ["pg_namespace"] => Some(SystemRelation::PgNamespace),
+            [PG_CATALOG, "pg_tables"] | ["pg_tables"] => Some(SystemRelation::PgTables),
+            _ => None,
+        }
+    }
+
+    pub fn columns(self) -> Vec<(String, SqlType)> {
+        let columns: &[(&str, SqlType)] = match self {
+            SystemRelation::Schemata => &[
+                ("catalog_name", SqlType::VarChar(63)),
+                ("schema_name", SqlType::VarChar(63)),
+                ("schema_owner", SqlType::VarChar(63)),
+            ],
+            SystemRelation::Tables => &[
+                ("table_catalog", SqlType::VarChar(63)),
+                ("table_schema", SqlType::VarChar(63)),
+                ("table_name", SqlType::VarChar(63)),
+                ("table_type", SqlType::VarChar(15)),
+            ],
+            SystemRelation::Columns => &[
+                ("table_catalog", SqlType::VarChar(63)),
+                ("table_schema", SqlType::VarChar(63)),
+                ("table_name", SqlType::VarChar(63)),
+                ("column_name", SqlType::VarChar(63)),
+                ("ordinal_position", SqlType::Integer),
+                ("column_default", SqlType::VarChar(255)),
+                ("is_nullable", SqlType::VarChar(3)),
+                ("data_type", SqlType::VarChar(63)),
+            ],
+            SystemRelation::PgNamespace => &[("nspname", SqlType::VarChar(63))],
+            SystemRelation::PgTables => &[
+                ("schemaname", SqlType::VarChar(63)),
+                ("tablename", SqlType::VarChar(63)),
+                ("tableowner", SqlType::VarChar(63)),
+            ],
+        };
+        columns
+            .iter()
+            .map(|(name, sql_type)| ((*name).to_owned(), *sql_type))
+            .collect()
+    }
+
+    pub fn projection<P: BackendStorage>(
+        self,
+        storage: &FrontendStorage<P>,
+        session: &Session,
+    ) -> SystemResult<Projection> {
+        let catalog = session.database_name();
+        let owner = session.user_name();
+        let records = match self {
+            SystemRelation::Schemata => schema_names(storage, session)?
+                .into_iter()
+                .map(|schema_name| vec![catalog.to_owned(), schema_name, owner.to_owned()])
+                .collect(),
+            SystemRelation::PgNamespace => schema_names(storage, session)?
+                .into_iter()
+                .map(|schema_name| vec![schema_name])
+                .collect(),
+            SystemRelation::Tables => table_names(storage, session)?
+                .into_iter()
+                .map(|(schema_name, table_name)| {
+                    let table_type = if schema_name == session.temp_schema() {
+                        "LOCAL TEMPORARY"
+                    } else {
+                        "BASE TABLE"
+                    };
+                    vec![catalog.to_owned(), schema_name, table_name, table_type.to_owned()]
+                })
+                .collect(),
+            SystemRelation::PgTables => table_names(storage, session)?
+                .into_iter()
+                .map(|(schema_name, table_name)| vec![schema_name, table_name, owner.to_owned()])
+                .collect(),
+            SystemRelation::Columns => {
+                let mut records = vec![];
+                for (schema_name, table_name) in table_names(storage, session)? {
+                    let columns = storage.table_columns(&schema_name, &table_name)?.unwrap_or_default();
+                    let constraints = storage
+                        .table_constraints(&schema_name, &table_name)?
+                        .unwrap_or_default();
+                    for (position, (column_name, sql_type)) in columns.into_iter().enumerate() {
+                        let column_default = constraints
+                            .iter()
+                            .find_map(|constraint| match constraint {
+                                TableConstraint::Default(column, value) if *column == column_name => {
+                                    Some(column_default(value, sql_type))
+                                }
+                                _ => None,
+                            })
+                            .unwrap_or_default();
+                        let is_nullable = if is_not_null(&constraints, &column_name) {
+                            "NO"
+                        } else {
+                            "YES"
+                        };
+                        records.push(vec![
+                            catalog.to_owned(),
+                            schema_name.clone(),
+                            table_name.clone(),
+                            column_name,
+                            (position + 1).to_string(),
+                            column_default,
+                            is_nullable.to_owned(),
+                            type_name(sql_type).to_owned(),
+                        ]);
+                    }
+                }
+                records
+            }
+        };
+        Ok((self.columns(), records))
+    }
+}
+
+/// keeps only the named columns of the projection in the given order
+pub fn select(projection: Projection, column_names: &[String]) -> Result<Projection, QueryError> {
+    let (columns, records) = projection;
+    let mut indexes = vec![];
+    let mut non_existing_columns = vec![];
+    for name in column_names {
+        match columns
+            .iter()
+            .position(|(column, _sql_type)| *column == name.to_lowercase())
+        {
+            Some(index) => indexes.push(index),
+            None => non_existing_columns.push(name.clone()),
+        }
+    }
+    if !non_existing_columns.is_empty() {
+        return Err(QueryError::column_does_not_exist(non_existing_columns));
+    }
+    Ok((
+        indexes.iter().map(|index| columns[*index].clone()).collect(),
+        records
+            .into_iter()
+            .map(|record| indexes.iter().map(|index| record[*index].clone()).collect())
+            .collect(),
+    ))
+}
+
+// schemas of the catalog along with the system ones, temporary schemas of
+// other sessions are not visible
+fn schema_names<P: BackendStorage>(storage: &FrontendStorage<P>, session: &Session) -> SystemResult<Vec<String>> {
+    let temp_schema = session.temp_schema();
+    let mut schema_names = storage
+        .schema_names()?
+        .into_iter()
+        .filter(|schema_name| !schema_name.starts_with(TEMP_SCHEMA_PREFIX) || *schema_name == temp_schema)
+        .chain(vec![INFORMATION_SCHEMA.to_owned(), PG_CATALOG.to_owned()])
+        .collect::<Vec<_>>();
+    schema_names.sort();
+    Ok(schema_names)
+}
+
+fn table_names<P: BackendStorage>(
+    storage: &FrontendStorage<P>,
+    session: &Session,
+) -> SystemResult<Vec<(String, String)>> {
+    let mut table_names = vec![];
+    for schema_name in schema_names(storage, session)? {
+        // system schemas are not recorded in the catalog
+        if let Ok(mut names) = storage.table_names(&schema_name)? {
+            names.sort();
+            table_names.extend(names.into_iter().map(|table_name| (schema_name.clone(), table_name)));
+        }
+    }
+    Ok(table_names)
+}
+
+fn is_not_null(constraints: &[TableConstraint], column_name: &str) -> bool {
+    constraints.iter().any(|constraint| match constraint {
+        TableConstraint::NotNull(column) => column == column_name,
+        TableConstraint::Unique(unique) => {
+            unique.is_primary && unique.columns.iter().any(|column| column == column_name)
+        }
+        _ => false,
+    })
+}
+
+// default expression as PostgreSQL shows it
+fn column_default(value: &DefaultValue, sql_type: SqlType) -> String {
+    match value {
+        DefaultValue::Constant(constant) if constant.parse::<f64>().is_ok() => constant.clone(),
+        DefaultValue::Constant(constant) => format!("'{}'::{}", constant.replace('\'', "''"), type_name(sql_type)),
+        DefaultValue::NextValue {
+            schema_name,
+            sequence_name,
+            ..
+        } => format!("nextval('{}.{}'::regclass)", schema_name, sequence_name),
+    }
+}
+
+fn type_name(sql_type: SqlType) -> &'static str {
+    match sql_type {
+        SqlType::Bool => "boolean",
+        SqlType::Char(_) => "character",
+        SqlType::VarChar(_) => "character varying",
+        SqlType::Decimal => "numeric",
+        SqlType::SmallInt => "smallint",
+        SqlType::Integer => "integer",
+        SqlType::BigInt => "bigint",
+        SqlType::Real => "real",
+        SqlType::DoublePrecision => "double precision",
+        SqlType::Time => "time without time zone",
+        SqlType::TimeWithTimeZone => "time with time zone",
+        SqlType::Timestamp => "timestamp without time zone",
+        SqlType::TimestampWithTimeZone => "timestamp with time zone",
+        SqlType::Date => "date",
+        SqlType::Interval => "interval",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::ast::Ident;
+    use storage::UniqueConstraint;
+    use test_helpers::in_memory_backend_storage::InMemoryStorage;
+
+    #[rstest::fixture]
+    fn catalog() -> FrontendStorage<InMemoryStorage> {
+        let mut storage = FrontendStorage::new(InMemoryStorage::default()).expect("no system errors");
+        storage
+            .create_schema("schema_name")
+            .expect("no system errors")
+            .expect("schema is created");
+        storage
+            .create_table_with_constraints(
+                "schema_name",
+                "table_name",
+                vec![
+                    ("id".to_owned(), SqlType::Integer),
+                    ("name".to_owned(), SqlType::VarChar(20)),
+                ],
+                vec![
+                    TableConstraint::Unique(UniqueConstraint {
+                        name: "table_name_pkey".to_owned(),
+                        columns: vec!["id".to_owned()],
+                        is_primary: true,
+                    }),
+                    TableConstraint::Default("name".to_owned(), DefaultValue::Constant("unknown".to_owned())),
+                ],
+            )
+            .expect("no system errors")
+            .expect("table is created");
+        storage
+    }
+
+    fn name(parts: &[&str]) -> Vec<Ident> {
+        parts.iter().map(|part| Ident::new(*part)).collect()
+    }
+
+    #[test]
+    fn find_relations() {
+        assert_eq!(
+            SystemRelation::find(&name(&["information_schema", "tables"])),
+            Some(SystemRelation::Tables)
+        );
+        assert_eq!(
+            SystemRelation::find(&name(&["INFORMATION_SCHEMA", "Columns"])),
+            Some(SystemRelation::Columns)
+        );
+        assert_eq!(
+            SystemRelation::find(&name(&["pg_namespace"])),
+            Some(SystemRelation::PgNamespace)
+        );
+        assert_eq!(SystemRelation::find(&name(&["tables"])), None);
+        assert_eq!(SystemRelation::find(&name(&["schema_name", "pg_tables"])), None);
+    }
+
+    #[rstest::rstest]
+    fn schemata(mut catalog: FrontendStorage<InMemoryStorage>) {
+        let session = Session::new(1, "postgres", "postgres");
+        for schema_name in &["pg_temp_1", "pg_temp_2"] {
+            catalog
+                .create_schema(schema_name)
+                .expect("no system errors")
+                .expect("schema is created");
+        }
+
+        assert_eq!(
+            SystemRelation::PgNamespace
+                .projection(&catalog, &session)
+                .expect("no system errors")
+                .1,
+            vec![
+                vec!["information_schema".to_owned()],
+                vec!["pg_catalog".to_owned()],
+                vec!["pg_temp_1".to_owned()],
+                vec!["schema_name".to_owned()],
+            ]
+        );
+    }
+
+    #[rstest::rstest]
+    fn tables(catalog: FrontendStorage<InMemoryStorage>) {
+        assert_eq!(
+            SystemRelation::Tables
+                .projection(&catalog, &Session::default())
+                .expect("no system errors")
+                .1,
+            vec![vec![
+                "postgres".to_owned(),
+                "schema_name".to_owned(),
+                "table_name".to_owned(),
+                "BASE TABLE".to_owned()
+            ]]
+        );
+    }
+
+    #[rstest::rstest]
+    fn columns(catalog: FrontendStorage<InMemoryStorage>) {
+        assert_eq!(
+            SystemRelation::Columns
+                .projection(&catalog, &Session::default())
+                .expect("no system errors")
+                .1,
+            vec![
+                vec![
+                    "postgres".to_owned(),
+                    "schema_name".to_owned(),
+                    "table_name".to_owned(),
+                    "id".to_owned(),
+                    "1".to_owned(),
+                    "".to_owned(),
+                    "NO".to_owned(),
+                    "integer".to_owned()
+                ],
+                vec![
+                    "postgres".to_owned(),
+                    "schema_name".to_owned(),
+                    "table_name".to_owned(),
+                    "name".to_owned(),
+                    "2".to_owned(),
+                    "'unknown'::character varying".to_owned(),
+                    "YES".to_owned(),
+                    "character varying".to_owned()
+                ],
+            ]
+        );
+    }
+
+    #[rstest::rstest]
+    fn select_named_columns(catalog: FrontendStorage<InMemoryStorage>) {
+        let projection = SystemRelation::PgTables
+            .projection(&catalog, &Session::default())
+            .expect("no system errors");
+
+        assert_eq!(
+            select(projection.clone(), &["TableName".to_owned(), "schemaname".to_owned()]),
+            Ok((
+                vec![
+                    ("tablename".to_owned(), SqlType::VarChar(63)),
+                    ("schemaname".to_owned(), SqlType::VarChar(63))
+                ],
+                vec![vec!["table_name".to_owned(), "schema_name".to_owned()]]
+            ))
+        );
+        assert_eq!(
+            select(projection, &["relname".to_owned()]),
+            Err(QueryError::column_does_not_exist(vec!["relname".to_owned()]))
+        );
+    }
+}
@@ -14,24 +14,82 @@
 
 extern crate log;
 
-use kernel::SystemResult;
+use crate::{
+    activity::PG_STAT_ACTIVITY,
+    aggregate::Aggregate,
+    alter_table::{AlterTable, AlterTableAction},
+    expression::{Datum, Evaluator},
+    information_schema::SystemRelation,
+    prepared::{Portal, PreparedCommand, PreparedStatement, Registry},
+    sequence::SequenceStatement,
+    series::{Series, GENERATE_SERIES},
+    session::SERVER_VERSION,
+    sort::{encode_key, Sorter},
+    statistics::PG_STAT_STATEMENTS,
+    transaction::Transaction,
+};
+pub use crate::{
+    activity::{SessionActivity, WaitEvent},
+    session::Session,
+    statistics::StatementStatistics,
+    transaction::TransactionState,
+};
+
+use kernel::{
+    clock::{Clock, SystemClock},
+    SystemResult,
+};
 use sql_types::SqlType;
-use sqlparser::{dialect::PostgreSqlDialect, parser::Parser};
+use sqlparser::{
+    dialect::PostgreSqlDialect,
+    parser::{Parser, ParserError},
+};
 use std::fmt::Formatter;
 use std::{
-    fmt::{Display, Result},
+    fmt::{Debug, Display, Result},
     ops::Deref,
-    sync::{Arc, Mutex},
+    sync::{Arc, LockResult, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, TryLockResult},
+    time::{Duration, Instant},
 };
+use storage::temp::{TempFileLimitExceeded, TempStorage};
 use storage::{
-    backend::BackendStorage, frontend::FrontendStorage, CreateTableError, DropTableError, OperationOnTableError,
-    Projection, SchemaAlreadyExists, SchemaDoesNotExist,
+    backend::BackendStorage, frontend::FrontendStorage, AlterTableError, CheckConstraint, Comparison, CreateIndexError,
+    CreateTableError, DefaultValue, DropIndexError, DropTableError, OperationOnTableError, Projection, RecordCursor,
+    SchemaAlreadyExists, SchemaDoesNotExist, SequenceDefinition, SequenceError, StreamedProjection, TableAlteration,
+    TableConstraint, UniqueConstraint,
 };
 
+mod activity;
+mod aggregate;
+mod alter_table;
+mod custom_setting;
+mod datetime;
+mod expression;
+mod information_schema;
+mod math;
+mod prepared;
+mod rewrite;
+mod sequence;
+mod series;
+mod session;
+mod sort;
+mod statistics;
+mod subquery;
+mod transaction;
+mod truncate;
+
 pub type QueryResult = std::result::Result<QueryEvent, QueryError>;
+/// names and types of columns of rows that a statement returns
+pub type Columns = Vec<(String, SqlType)>;
+/// types of parameters of a prepared statement and columns of its rows
+pub type StatementDescription = (Vec<Option<SqlType>>, Option<Columns>);
+
+const KILOBYTE: u64 = 1024;
 
 /// Message severities
 /// Reference: defined in https://www.postgresql.org/docs/12/protocol-error-fields.html
+// all severities of the protocol are listed, even those that are not reported yet
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub(crate) enum Severity {
     Error,
@@ -45,17 +103,17 @@ pub(crate) enum Severity {
 }
 
 // easy conversion into a string.
-impl Into<String> for Severity {
-    fn into(self) -> String {
-        match self {
-            Self::Error => "ERROR".to_string(),
-            Self::Fatal => "FATAL".to_string(),
-            Self::Panic => "PANIC".to_string(),
-            Self::Warning => "WARNING".to_string(),
-            Self::Notice => "NOTIC".to_string(),
-            Self::Debug => "DEBUG".to_string(),
-            Self::Info => "INFO".to_string(),
-            Self::Log => "LOG".to_string(),
+impl From<Severity> for String {
+    fn from(severity: Severity) -> String {
+        match severity {
+            Severity::Error => "ERROR".to_string(),
+            Severity::Fatal => "FATAL".to_string(),
+            Severity::Panic => "PANIC".to_string(),
+            Severity::Warning => "WARNING".to_string(),
+            Severity::Notice => "NOTICE".to_string(),
+            Severity::Debug => "DEBUG".to_string(),
+            Severity::Info => "INFO".to_string(),
+            Severity::Log => "LOG".to_string(),
         }
     }
 }
@@ -67,7 +125,76 @@ pub(crate) enum QueryErrorKind {
     SchemaDoesNotExist(String),
     TableDoesNotExist(String),
     ColumnDoesNotExist(Vec<String>),
+    // column name and table name
+    ColumnAlreadyExists(String, String),
     NotSupportedOperation(String),
+    SyntaxError(String),
+    InvalidParameterValue(String, String),
+    // type name and value
+    InvalidDatetimeFormat(String, String),
+    // name of the type which range the value is out of
+    DatetimeFieldOverflow(String),
+    // type name and value
+    InvalidTextRepresentation(String, String),
+    // name of the type which range the value is out of
+    NumericValueOutOfRange(String),
+    // clause, type that it requires and the type that is given to it
+    DatatypeMismatch(String, String, String),
+    DivisionByZero,
+    // reason why the value is out of range, e.g. overflow
+    ValueOutOfRange(String),
+    // reasons why arguments are not accepted
+    InvalidArgumentForLogarithm(String),
+    InvalidArgumentForPowerFunction(String),
+    // names of source and target types
+    CannotCoerce(String, String),
+    UndefinedFunction(String),
+    UndefinedOperator(String),
+    UnrecognizedParameter(String),
+    InvalidRowCountInLimitClause,
+    InvalidRowCountInResultOffsetClause,
+    // clause and the position that is given to it
+    InvalidColumnReference(String, String),
+    // column that is neither grouped nor aggregated
+    UngroupedColumn(String),
+    // clause where aggregate functions can't be used
+    AggregateNotAllowed(String),
+    NestedAggregate,
+    TempFileLimitExceeded(u64),
+    LockTimeout,
+    SequenceAlreadyExists(String),
+    SequenceDoesNotExist(String),
+    SequenceLimitReached(String),
+    IndexAlreadyExists(String),
+    IndexDoesNotExist(String),
+    // `None` stands for the last value of any sequence
+    SequenceValueNotDefined(Option<String>),
+    ActiveTransaction(String),
+    NoActiveTransaction(String),
+    InFailedTransaction,
+    ZeroStepSize,
+    UndefinedType(String),
+    PreparedStatementAlreadyExists(String),
+    PreparedStatementDoesNotExist(String),
+    // statement name
+    WrongNumberOfParameters(String),
+    // statement name, number of given and required parameters
+    BindParametersMismatch(String, usize, usize),
+    PortalAlreadyExists(String),
+    PortalDoesNotExist(String),
+    FeatureNotSupported(String),
+    MoreThanOneRowReturnedBySubquery,
+    SubqueryMustReturnOneColumn,
+    SubqueryHasTooManyColumns,
+    UniqueViolation(String),
+    NotNullViolation(String),
+    // table name and constraint name
+    CheckViolation(String, String),
+    MultiplePrimaryKeys(String),
+    // `IF EXISTS` and `IF NOT EXISTS` turn errors into notices
+    Skipping(Box<QueryErrorKind>),
+    // clause that is dropped from a query, because it does not change the result
+    ClauseIgnored(String),
 }
 
 #[derive(Debug, PartialEq)]
@@ -126,1225 +253,8128 @@ impl QueryError {
         }
     }
 
-    pub fn not_supported_operation(raw_sql_query: String) -> Self {
+    pub fn column_already_exists(column_name: String, table_name: String) -> Self {
         Self {
             severity: Severity::Error,
-            code: "42601".to_owned(),
-            kind: QueryErrorKind::NotSupportedOperation(raw_sql_query),
+            code: "42701".to_owned(),
+            kind: QueryErrorKind::ColumnAlreadyExists(column_name, table_name),
         }
     }
-}
 
-impl Display for QueryError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        match &self.kind {
-            QueryErrorKind::SchemaAlreadyExists(schema_name) => write!(f, "schema \"{}\" already exists", schema_name),
-            QueryErrorKind::TableAlreadyExists(table_name) => write!(f, "table \"{}\" already exists", table_name),
-            QueryErrorKind::SchemaDoesNotExist(schema_name) => write!(f, "schema \"{}\" does not exist", schema_name),
-            QueryErrorKind::TableDoesNotExist(table_name) => write!(f, "table \"{}\" does not exist", table_name),
-            QueryErrorKind::ColumnDoesNotExist(columns) => {
-                if columns.len() > 1 {
-                    write!(f, "columns {} do not exist", columns.join(", "))
-                } else {
-                    write!(f, "column {} does not exist", columns[0])
-                }
-            }
-            QueryErrorKind::NotSupportedOperation(raw_sql_query) => {
-                write!(f, "Currently, Query '{}' can't be executed", raw_sql_query)
-            }
+    pub fn unique_violation(constraint_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "23505".to_owned(),
+            kind: QueryErrorKind::UniqueViolation(constraint_name),
         }
     }
-}
 
-pub struct Handler<P: BackendStorage> {
-    storage: Arc<Mutex<FrontendStorage<P>>>,
-}
+    pub fn not_null_violation(column_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "23502".to_owned(),
+            kind: QueryErrorKind::NotNullViolation(column_name),
+        }
+    }
 
-impl<P: BackendStorage> Handler<P> {
-    pub fn new(storage: Arc<Mutex<FrontendStorage<P>>>) -> Self {
-        Self { storage }
+    pub fn check_violation(table_name: String, constraint_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "23514".to_owned(),
+            kind: QueryErrorKind::CheckViolation(table_name, constraint_name),
+        }
     }
 
-    #[allow(clippy::match_wild_err_arm)]
-    pub fn execute(&mut self, raw_sql_query: &str) -> SystemResult<QueryResult> {
-        let statement = match Parser::parse_sql(&PostgreSqlDialect {}, raw_sql_query) {
-            Ok(mut statements) => statements.pop().unwrap(),
-            Err(e) => {
-                log::error!("{:?} can't be parsed. Error: {:?}", raw_sql_query, e);
-                unimplemented!("PANIC!!! Ah-a-a-a")
-            }
-        };
-        log::debug!("STATEMENT = {:?}", statement);
-        match statement {
-            sqlparser::ast::Statement::StartTransaction { .. } => Ok(Ok(QueryEvent::TransactionStarted)),
-            sqlparser::ast::Statement::SetVariable { .. } => Ok(Ok(QueryEvent::VariableSet)),
-            sqlparser::ast::Statement::CreateTable { mut name, columns, .. } => {
-                let table_name = name.0.pop().unwrap().to_string();
-                let schema_name = name.0.pop().unwrap().to_string();
-                match (self.storage.lock().unwrap()).create_table(
-                    &schema_name,
-                    &table_name,
-                    columns
-                        .into_iter()
-                        .map(|c| {
-                            let name = c.name.to_string();
-                            let sql_type = match c.data_type {
-                                sqlparser::ast::DataType::SmallInt => SqlType::SmallInt,
-                                sqlparser::ast::DataType::Int => SqlType::Integer,
-                                sqlparser::ast::DataType::BigInt => SqlType::BigInt,
-                                sqlparser::ast::DataType::Char(len) => SqlType::Char(len.unwrap_or(255)),
-                                sqlparser::ast::DataType::Varchar(len) => SqlType::VarChar(len.unwrap_or(255)),
-                                _ => unimplemented!(),
-                            };
-                            (name, sql_type)
-                        })
-                        .collect(),
-                )? {
-                    Ok(()) => Ok(Ok(QueryEvent::TableCreated)),
-                    Err(CreateTableError::SchemaDoesNotExist) => {
-                        Ok(Err(QueryError::schema_does_not_exist(schema_name)))
-                    }
-                    Err(CreateTableError::TableAlreadyExists) => Ok(Err(QueryError::table_already_exists(table_name))),
-                }
-            }
-            sqlparser::ast::Statement::CreateSchema { schema_name, .. } => {
-                let schema_name = schema_name.to_string();
-                match (self.storage.lock().unwrap()).create_schema(&schema_name)? {
-                    Ok(()) => Ok(Ok(QueryEvent::SchemaCreated)),
-                    Err(SchemaAlreadyExists) => Ok(Err(QueryError::schema_already_exists(schema_name))),
-                }
-            }
-            sqlparser::ast::Statement::Drop { object_type, names, .. } => match object_type {
-                sqlparser::ast::ObjectType::Table => {
-                    let table_name = names[0].0[1].to_string();
-                    let schema_name = names[0].0[0].to_string();
-                    match (self.storage.lock().unwrap()).drop_table(&schema_name, &table_name)? {
-                        Ok(()) => Ok(Ok(QueryEvent::TableDropped)),
-                        Err(DropTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
-                            schema_name + "." + table_name.as_str(),
-                        ))),
-                        Err(DropTableError::SchemaDoesNotExist) => {
-                            Ok(Err(QueryError::schema_does_not_exist(schema_name)))
-                        }
-                    }
-                }
-                sqlparser::ast::ObjectType::Schema => {
-                    let schema_name = names[0].0[0].to_string();
-                    match (self.storage.lock().unwrap()).drop_schema(&schema_name)? {
-                        Ok(()) => Ok(Ok(QueryEvent::SchemaDropped)),
-                        Err(SchemaDoesNotExist) => Ok(Err(QueryError::schema_does_not_exist(schema_name))),
-                    }
-                }
-                _ => Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
-            },
-            sqlparser::ast::Statement::Insert {
-                mut table_name,
-                columns,
-                source,
-                ..
-            } => {
-                let name = table_name.0.pop().unwrap().to_string();
-                let schema_name = table_name.0.pop().unwrap().to_string();
-                let sqlparser::ast::Query { body, .. } = &*source;
-                if let sqlparser::ast::SetExpr::Values(values) = &body {
-                    let values = &values.0;
+    pub fn multiple_primary_keys(table_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42P16".to_owned(),
+            kind: QueryErrorKind::MultiplePrimaryKeys(table_name),
+        }
+    }
 
-                    let columns = if columns.is_empty() {
-                        vec![]
-                    } else {
-                        columns
-                            .into_iter()
-                            .map(|id| {
-                                let sqlparser::ast::Ident { value, .. } = id;
-                                value
-                            })
-                            .collect()
-                    };
+    pub fn not_supported_operation(raw_sql_query: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42601".to_owned(),
+            kind: QueryErrorKind::NotSupportedOperation(raw_sql_query),
+        }
+    }
 
-                    let rows: Vec<Vec<String>> = values
-                        .iter()
-                        .map(|v| {
-                            v.iter()
-                                .map(|v| match v {
-                                    sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(v)) => v.to_string(),
-                                    sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(v)) => {
-                                        v.to_string()
-                                    }
-                                    sqlparser::ast::Expr::UnaryOp { op, expr } => match (op, &**expr) {
-                                        (
-                                            sqlparser::ast::UnaryOperator::Minus,
-                                            sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(v)),
-                                        ) => "-".to_owned() + v.as_str(),
-                                        (op, expr) => unimplemented!("{:?} {:?} is not currently supported", op, expr),
-                                    },
-                                    expr => unimplemented!("{:?} is not currently supported", expr),
-                                })
-                                .collect()
-                        })
-                        .collect();
+    pub fn syntax_error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42601".to_owned(),
+            kind: QueryErrorKind::SyntaxError(message),
+        }
+    }
 
-                    let len = rows.len();
-                    match (self.storage.lock().unwrap()).insert_into(&schema_name, &name, columns, rows)? {
-                        Ok(_) => Ok(Ok(QueryEvent::RecordsInserted(len))),
-                        Err(OperationOnTableError::SchemaDoesNotExist) => {
-                            Ok(Err(QueryError::schema_does_not_exist(schema_name)))
-                        }
-                        Err(OperationOnTableError::TableDoesNotExist) => {
-                            Ok(Err(QueryError::table_does_not_exist(schema_name + "." + name.as_str())))
-                        }
-                        Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)) => {
-                            Ok(Err(QueryError::column_does_not_exist(non_existing_columns)))
-                        }
-                        Err(e) => {
-                            eprintln!("{:?}", e);
-                            unimplemented!()
-                        }
-                    }
-                } else {
-                    Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned())))
-                }
-            }
-            sqlparser::ast::Statement::Query(query) => {
-                let sqlparser::ast::Query { body, .. } = *query;
-                if let sqlparser::ast::SetExpr::Select(select) = body {
-                    let sqlparser::ast::Select { projection, from, .. } = select.deref();
-                    let sqlparser::ast::TableWithJoins { relation, .. } = &from[0];
-                    let (schema_name, table_name) = match relation {
-                        sqlparser::ast::TableFactor::Table { name, .. } => {
-                            let table_name = name.0[1].to_string();
-                            let schema_name = name.0[0].to_string();
-                            (schema_name, table_name)
-                        }
-                        _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
-                    };
-                    let table_columns = {
-                        let projection = projection.clone();
-                        let mut columns: Vec<String> = vec![];
-                        for item in projection {
-                            match item {
-                                sqlparser::ast::SelectItem::Wildcard => {
-                                    match (self.storage.lock().unwrap()).table_columns(&schema_name, &table_name)? {
-                                        Ok(all_columns) => columns.extend(
-                                            all_columns
-                                                .into_iter()
-                                                .map(|(name, _sql_type)| name)
-                                                .collect::<Vec<String>>(),
-                                        ),
-                                        Err(_e) => {
-                                            return Ok(Err(QueryError::not_supported_operation(
-                                                raw_sql_query.to_owned(),
-                                            )))
-                                        }
-                                    }
-                                }
-                                sqlparser::ast::SelectItem::UnnamedExpr(sqlparser::ast::Expr::Identifier(
-                                    sqlparser::ast::Ident { value, .. },
-                                )) => columns.push(value.clone()),
-                                _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
-                            }
-                        }
-                        columns
-                    };
-                    match (self.storage.lock().unwrap()).select_all_from(&schema_name, &table_name, table_columns)? {
-                        Ok(records) => Ok(Ok(QueryEvent::RecordsSelected(records))),
-                        Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)) => {
-                            Ok(Err(QueryError::column_does_not_exist(non_existing_columns)))
-                        }
-                        Err(OperationOnTableError::SchemaDoesNotExist) => {
-                            Ok(Err(QueryError::schema_does_not_exist(schema_name.to_owned())))
-                        }
-                        Err(OperationOnTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
-                            schema_name.to_owned() + "." + table_name.as_str(),
-                        ))),
-                        _ => Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
-                    }
-                } else {
-                    Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned())))
-                }
-            }
-            sqlparser::ast::Statement::Update {
-                table_name,
-                assignments,
-                ..
-            } => {
-                let schema_name = table_name.0[0].to_string();
-                let table_name = table_name.0[1].to_string();
+    pub fn invalid_parameter_value(parameter: String, value: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "22023".to_owned(),
+            kind: QueryErrorKind::InvalidParameterValue(parameter, value),
+        }
+    }
 
-                let to_update: Vec<(String, String)> = assignments
-                    .iter()
-                    .map(|item| {
-                        let sqlparser::ast::Assignment { id, value } = &item;
-                        let sqlparser::ast::Ident { value: column, .. } = id;
-
-                        let value = match value {
-                            sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(val)) => val.to_owned(),
-                            sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(v)) => v.to_string(),
-                            sqlparser::ast::Expr::UnaryOp { op, expr } => match (op, &**expr) {
-                                (
-                                    sqlparser::ast::UnaryOperator::Minus,
-                                    sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(v)),
-                                ) => "-".to_owned() + v.as_str(),
-                                (op, expr) => unimplemented!("{:?} {:?} is not currently supported", op, expr),
-                            },
-                            expr => unimplemented!("{:?} is not currently supported", expr),
-                        };
-
-                        (column.to_owned(), value)
-                    })
-                    .collect();
+    pub fn invalid_datetime_format(type_name: String, value: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "22007".to_owned(),
+            kind: QueryErrorKind::InvalidDatetimeFormat(type_name, value),
+        }
+    }
 
-                match (self.storage.lock().unwrap()).update_all(&schema_name, &table_name, to_update)? {
-                    Ok(records_number) => Ok(Ok(QueryEvent::RecordsUpdated(records_number))),
-                    Err(OperationOnTableError::SchemaDoesNotExist) => {
-                        Ok(Err(QueryError::schema_does_not_exist(schema_name)))
-                    }
-                    Err(OperationOnTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
-                        schema_name + "." + table_name.as_str(),
-                    ))),
-                    Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)) => {
-                        Ok(Err(QueryError::column_does_not_exist(non_existing_columns)))
-                    }
-                    _ => unimplemented!(),
-                }
-            }
-            sqlparser::ast::Statement::Delete { table_name, .. } => {
-                let schema_name = table_name.0[0].to_string();
-                let table_name = table_name.0[1].to_string();
-                match (self.storage.lock().unwrap()).delete_all_from(&schema_name, &table_name)? {
-                    Ok(records_number) => Ok(Ok(QueryEvent::RecordsDeleted(records_number))),
-                    Err(OperationOnTableError::SchemaDoesNotExist) => {
-                        Ok(Err(QueryError::schema_does_not_exist(schema_name)))
-                    }
-                    Err(OperationOnTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
-                        schema_name + "." + table_name.as_str(),
-                    ))),
-                    Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)) => {
-                        Ok(Err(QueryError::column_does_not_exist(non_existing_columns)))
-                    }
-                    _ => unimplemented!(),
-                }
-            }
-            _ => Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+    pub fn datetime_field_overflow(type_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "22008".to_owned(),
+            kind: QueryErrorKind::DatetimeFieldOverflow(type_name),
         }
     }
-}
 
-#[derive(Debug, PartialEq)]
-pub enum QueryEvent {
-    SchemaCreated,
-    SchemaDropped,
-    TableCreated,
-    TableDropped,
-    VariableSet,
-    TransactionStarted,
-    RecordsInserted(usize),
-    RecordsSelected(Projection),
-    RecordsUpdated(usize),
-    RecordsDeleted(usize),
-}
+    pub fn invalid_text_representation(type_name: String, value: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "22P02".to_owned(),
+            kind: QueryErrorKind::InvalidTextRepresentation(type_name, value),
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use sql_types::SqlType;
-    use storage::frontend::FrontendStorage;
-    use test_helpers::in_memory_backend_storage::InMemoryStorage;
+    pub fn numeric_value_out_of_range(type_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "22003".to_owned(),
+            kind: QueryErrorKind::NumericValueOutOfRange(type_name),
+        }
+    }
 
-    type InMemorySqlEngine = Handler<InMemoryStorage>;
+    pub fn datatype_mismatch(clause: String, expected_type: String, type_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42804".to_owned(),
+            kind: QueryErrorKind::DatatypeMismatch(clause, expected_type, type_name),
+        }
+    }
 
-    #[rstest::fixture]
-    fn sql_engine() -> InMemorySqlEngine {
-        Handler::new(in_memory_storage())
+    pub fn division_by_zero() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "22012".to_owned(),
+            kind: QueryErrorKind::DivisionByZero,
+        }
     }
 
-    #[cfg(test)]
-    mod schema {
-        use super::*;
-
-        #[rstest::rstest]
-        fn create_schema_query(mut sql_engine: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine
-                    .execute("create schema schema_name;")
-                    .expect("no system errors"),
-                Ok(QueryEvent::SchemaCreated)
-            );
+    pub fn cannot_coerce(source_type: String, target_type: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42846".to_owned(),
+            kind: QueryErrorKind::CannotCoerce(source_type, target_type),
         }
+    }
 
-        #[rstest::rstest]
-        fn create_schema_with_the_same_name(mut sql_engine: InMemorySqlEngine) {
-            sql_engine
-                .execute("create schema schema_name;")
-                .expect("no system errors")
-                .expect("schema created");
-
-            assert_eq!(
-                sql_engine
-                    .execute("create schema schema_name;")
-                    .expect("no system errors"),
-                Err(QueryError::schema_already_exists("schema_name".to_owned()))
-            );
+    pub fn value_out_of_range(reason: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "22003".to_owned(),
+            kind: QueryErrorKind::ValueOutOfRange(reason),
         }
+    }
 
-        #[rstest::rstest]
-        fn drop_schema(mut sql_engine: InMemorySqlEngine) {
-            sql_engine
-                .execute("create schema schema_name;")
-                .expect("no system errors")
-                .expect("schema created");
+    pub fn invalid_argument_for_logarithm(reason: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "2201E".to_owned(),
+            kind: QueryErrorKind::InvalidArgumentForLogarithm(reason),
+        }
+    }
 
-            assert_eq!(
-                sql_engine
-                    .execute("drop schema schema_name;")
-                    .expect("no system errors"),
-                Ok(QueryEvent::SchemaDropped)
-            );
-            assert_eq!(
-                sql_engine
-                    .execute("create schema schema_name;")
-                    .expect("no system errors"),
-                Ok(QueryEvent::SchemaCreated)
-            );
+    pub fn invalid_argument_for_power_function(reason: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "2201F".to_owned(),
+            kind: QueryErrorKind::InvalidArgumentForPowerFunction(reason),
         }
+    }
 
-        #[rstest::rstest]
-        fn drop_non_existent_schema(mut sql_engine: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine
-                    .execute("drop schema non_existent")
-                    .expect("no system errors"),
-                Err(QueryError::schema_does_not_exist("non_existent".to_owned()))
-            );
+    pub fn undefined_function(signature: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42883".to_owned(),
+            kind: QueryErrorKind::UndefinedFunction(signature),
         }
+    }
 
-        #[rstest::rstest]
-        fn select_from_nonexistent_schema(mut sql_engine: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine
-                    .execute("select * from non_existent.some_table;")
-                    .expect("no system errors"),
-                Err(QueryError::schema_does_not_exist("non_existent".to_owned()))
-            );
+    pub fn undefined_operator(signature: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42883".to_owned(),
+            kind: QueryErrorKind::UndefinedOperator(signature),
         }
+    }
 
-        #[rstest::rstest]
-        fn select_named_columns_from_nonexistent_schema(mut sql_engine: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine
-                    .execute("select column_1 from schema_name.table_name;")
-                    .expect("no system errors"),
-                Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
-            );
+    pub fn unrecognized_parameter(parameter: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42704".to_owned(),
+            kind: QueryErrorKind::UnrecognizedParameter(parameter),
         }
+    }
 
-        #[rstest::rstest]
-        fn insert_into_table_in_nonexistent_schema(mut sql_engine: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine
-                    .execute("insert into schema_name.table_name values (123);")
-                    .expect("no system errors"),
-                Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
-            );
+    pub fn invalid_row_count_in_limit_clause() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "2201W".to_owned(),
+            kind: QueryErrorKind::InvalidRowCountInLimitClause,
         }
+    }
 
-        #[rstest::rstest]
-        fn update_records_in_table_from_non_existent_schema(mut sql_engine: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine
-                    .execute("update schema_name.table_name set column_test=789;")
-                    .expect("no system errors"),
-                Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
-            );
+    pub fn invalid_row_count_in_result_offset_clause() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "2201X".to_owned(),
+            kind: QueryErrorKind::InvalidRowCountInResultOffsetClause,
         }
+    }
 
-        #[rstest::rstest]
-        fn delete_from_table_in_nonexistent_schema(mut sql_engine: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine
-                    .execute("delete from schema_name.table_name;")
-                    .expect("no system errors"),
-                Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
-            );
+    pub fn invalid_column_reference(clause: String, position: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42P10".to_owned(),
+            kind: QueryErrorKind::InvalidColumnReference(clause, position),
         }
     }
 
-    #[cfg(test)]
-    mod table {
-        use super::*;
+    pub fn ungrouped_column(column_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42803".to_owned(),
+            kind: QueryErrorKind::UngroupedColumn(column_name),
+        }
+    }
 
-        #[cfg(test)]
-        mod schemaless {
-            use super::*;
+    pub fn aggregate_not_allowed(clause: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42803".to_owned(),
+            kind: QueryErrorKind::AggregateNotAllowed(clause),
+        }
+    }
 
-            #[rstest::rstest]
-            fn create_table_in_non_existent_schema(mut sql_engine: InMemorySqlEngine) {
-                assert_eq!(
-                    sql_engine
-                        .execute("create table schema_name.table_name (column_name smallint);")
-                        .expect("no system errors"),
-                    Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
-                );
-            }
+    pub fn nested_aggregate() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42803".to_owned(),
+            kind: QueryErrorKind::NestedAggregate,
+        }
+    }
 
-            #[rstest::rstest]
-            fn drop_table_from_non_existent_schema(mut sql_engine: InMemorySqlEngine) {
-                assert_eq!(
-                    sql_engine
-                        .execute("drop table schema_name.table_name;")
-                        .expect("no system errors"),
-                    Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
-                );
-            }
+    pub fn temp_file_limit_exceeded(limit: u64) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "53400".to_owned(),
+            kind: QueryErrorKind::TempFileLimitExceeded(limit),
         }
+    }
 
-        #[rstest::fixture]
-        fn sql_engine_with_schema(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
-            sql_engine
-                .execute("create schema schema_name;")
-                .expect("no system errors")
-                .expect("schema created");
+    pub fn lock_timeout() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "55P03".to_owned(),
+            kind: QueryErrorKind::LockTimeout,
+        }
+    }
 
-            sql_engine
+    pub fn sequence_already_exists(sequence_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42P07".to_owned(),
+            kind: QueryErrorKind::SequenceAlreadyExists(sequence_name),
         }
+    }
 
-        #[rstest::rstest]
-        fn create_table(mut sql_engine_with_schema: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("create table schema_name.table_name (column_name smallint);")
-                    .expect("no system errors"),
-                Ok(QueryEvent::TableCreated)
-            );
+    pub fn sequence_does_not_exist(sequence_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42P01".to_owned(),
+            kind: QueryErrorKind::SequenceDoesNotExist(sequence_name),
         }
+    }
 
-        #[rstest::rstest]
-        fn drop_table(mut sql_engine_with_schema: InMemorySqlEngine) {
-            sql_engine_with_schema
-                .execute("create table schema_name.table_name (column_name smallint);")
-                .expect("no system errors")
-                .expect("table created");
+    pub fn index_already_exists(index_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42P07".to_owned(),
+            kind: QueryErrorKind::IndexAlreadyExists(index_name),
+        }
+    }
 
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("drop table schema_name.table_name;")
-                    .expect("no system errors"),
-                Ok(QueryEvent::TableDropped)
-            );
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("create table schema_name.table_name (column_name smallint);")
-                    .expect("no system errors"),
-                Ok(QueryEvent::TableCreated)
-            );
+    pub fn index_does_not_exist(index_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42704".to_owned(),
+            kind: QueryErrorKind::IndexDoesNotExist(index_name),
         }
+    }
 
-        #[rstest::rstest]
-        fn drop_non_existent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("drop table schema_name.table_name;")
-                    .expect("no system errors"),
-                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
-            );
+    pub fn sequence_limit_reached(sequence_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "2200H".to_owned(),
+            kind: QueryErrorKind::SequenceLimitReached(sequence_name),
         }
+    }
 
-        #[rstest::rstest]
-        fn select_from_not_existed_table(mut sql_engine_with_schema: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("select * from schema_name.non_existent;")
-                    .expect("no system errors"),
-                Err(QueryError::table_does_not_exist("schema_name.non_existent".to_owned()))
-            );
+    pub fn sequence_value_not_defined(sequence_name: Option<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "55000".to_owned(),
+            kind: QueryErrorKind::SequenceValueNotDefined(sequence_name),
         }
+    }
 
-        #[rstest::rstest]
-        fn select_named_columns_from_non_existent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("select column_1 from schema_name.non_existent;")
-                    .expect("no system errors"),
-                Err(QueryError::table_does_not_exist("schema_name.non_existent".to_owned()))
-            );
+    pub fn active_transaction(command: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "25001".to_owned(),
+            kind: QueryErrorKind::ActiveTransaction(command),
         }
+    }
 
-        #[rstest::rstest]
-        fn insert_into_nonexistent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("insert into schema_name.table_name values (123);")
-                    .expect("no system errors"),
-                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
-            );
+    pub fn no_active_transaction(command: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code: "25P01".to_owned(),
+            kind: QueryErrorKind::NoActiveTransaction(command),
         }
+    }
 
-        #[rstest::rstest]
-        fn update_records_in_non_existent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("update schema_name.table_name set column_test=789;")
-                    .expect("no system errors"),
-                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
-            );
+    pub fn in_failed_transaction() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "25P02".to_owned(),
+            kind: QueryErrorKind::InFailedTransaction,
         }
+    }
 
-        #[rstest::rstest]
-        fn delete_from_nonexistent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
-            assert_eq!(
-                sql_engine_with_schema
-                    .execute("delete from schema_name.table_name;")
-                    .expect("no system errors"),
-                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
-            );
+    pub fn zero_step_size() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "22023".to_owned(),
+            kind: QueryErrorKind::ZeroStepSize,
         }
     }
 
-    #[rstest::rstest]
-    fn insert_and_select_single_row(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_test smallint);")
-            .expect("no system errors")
-            .expect("table created");
+    pub fn undefined_type(type_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42704".to_owned(),
+            kind: QueryErrorKind::UndefinedType(type_name),
+        }
+    }
 
-        assert_eq!(
-            sql_engine
-                .execute("insert into schema_name.table_name values (123);")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsInserted(1))
-        );
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![("column_test".to_owned(), SqlType::SmallInt)],
-                vec![vec!["123".to_owned()]]
-            )))
-        );
+    pub fn prepared_statement_already_exists(statement_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42P05".to_owned(),
+            kind: QueryErrorKind::PreparedStatementAlreadyExists(statement_name),
+        }
     }
 
-    #[rstest::rstest]
-    fn insert_and_select_multiple_rows(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_test smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name values (123);")
-            .expect("no system errors")
-            .expect("row inserted");
+    pub fn prepared_statement_does_not_exist(statement_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "26000".to_owned(),
+            kind: QueryErrorKind::PreparedStatementDoesNotExist(statement_name),
+        }
+    }
 
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![("column_test".to_owned(), SqlType::SmallInt)],
-                vec![vec!["123".to_owned()]]
-            )))
-        );
+    pub fn wrong_number_of_parameters(statement_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42601".to_owned(),
+            kind: QueryErrorKind::WrongNumberOfParameters(statement_name),
+        }
+    }
 
-        sql_engine
-            .execute("insert into schema_name.table_name values (456);")
-            .expect("no system errors")
-            .expect("row inserted");
+    pub fn bind_parameters_mismatch(statement_name: String, given: usize, required: usize) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "08P01".to_owned(),
+            kind: QueryErrorKind::BindParametersMismatch(statement_name, given, required),
+        }
+    }
 
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![("column_test".to_owned(), SqlType::SmallInt)],
-                vec![vec!["123".to_owned()], vec!["456".to_owned()]]
-            )))
-        );
+    pub fn portal_already_exists(portal_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42P03".to_owned(),
+            kind: QueryErrorKind::PortalAlreadyExists(portal_name),
+        }
     }
 
-    #[rstest::rstest]
-    fn insert_and_select_named_columns(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (col1 smallint, col2 smallint, col3 smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name (col2, col3, col1) values (1, 2, 3), (4, 5, 6);")
-            .expect("no system errors")
-            .expect("row inserted");
+    pub fn portal_does_not_exist(portal_name: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "34000".to_owned(),
+            kind: QueryErrorKind::PortalDoesNotExist(portal_name),
+        }
+    }
 
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("col1".to_owned(), SqlType::SmallInt),
-                    ("col2".to_owned(), SqlType::SmallInt),
-                    ("col3".to_owned(), SqlType::SmallInt),
-                ],
-                vec![
-                    vec!["3".to_owned(), "1".to_owned(), "2".to_owned()],
-                    vec!["6".to_owned(), "4".to_owned(), "5".to_owned()],
-                ]
-            )))
-        );
+    pub fn feature_not_supported(feature: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "0A000".to_owned(),
+            kind: QueryErrorKind::FeatureNotSupported(feature),
+        }
     }
 
-    #[rstest::rstest]
-    fn update_all_records(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_test smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name values (123);")
-            .expect("no system errors")
-            .expect("row inserted");
-        sql_engine
-            .execute("insert into schema_name.table_name values (456);")
-            .expect("no system errors")
-            .expect("row inserted");
+    pub fn more_than_one_row_returned_by_subquery() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "21000".to_owned(),
+            kind: QueryErrorKind::MoreThanOneRowReturnedBySubquery,
+        }
+    }
 
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![("column_test".to_owned(), SqlType::SmallInt)],
-                vec![vec!["123".to_owned()], vec!["456".to_owned()]]
-            )))
-        );
-        assert_eq!(
-            sql_engine
-                .execute("update schema_name.table_name set column_test=789;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsUpdated(2))
-        );
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![("column_test".to_owned(), SqlType::SmallInt)],
-                vec![vec!["789".to_owned()], vec!["789".to_owned()]]
-            )))
-        );
+    pub fn subquery_must_return_one_column() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42601".to_owned(),
+            kind: QueryErrorKind::SubqueryMustReturnOneColumn,
+        }
     }
 
-    #[rstest::rstest]
-    fn update_single_column_of_all_records(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (col1 smallint, col2 smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name values (123, 789);")
-            .expect("no system errors")
-            .expect("row inserted");
-        sql_engine
-            .execute("insert into schema_name.table_name values (456, 789);")
-            .expect("no system errors")
-            .expect("row inserted");
+    pub fn subquery_has_too_many_columns() -> Self {
+        Self {
+            severity: Severity::Error,
+            code: "42601".to_owned(),
+            kind: QueryErrorKind::SubqueryHasTooManyColumns,
+        }
+    }
 
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("col1".to_owned(), SqlType::SmallInt),
-                    ("col2".to_owned(), SqlType::SmallInt),
-                ],
-                vec![
-                    vec!["123".to_owned(), "789".to_owned()],
-                    vec!["456".to_owned(), "789".to_owned()],
-                ]
-            )))
-        );
-        assert_eq!(
-            sql_engine
-                .execute("update schema_name.table_name set col2=357;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsUpdated(2))
-        );
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("col1".to_owned(), SqlType::SmallInt),
-                    ("col2".to_owned(), SqlType::SmallInt),
-                ],
-                vec![
-                    vec!["123".to_owned(), "357".to_owned()],
-                    vec!["456".to_owned(), "357".to_owned()],
-                ]
-            )))
-        );
+    pub fn table_already_exists_skipping(table_name: String) -> Self {
+        Self {
+            severity: Severity::Notice,
+            code: "42P07".to_owned(),
+            kind: QueryErrorKind::Skipping(Box::new(QueryErrorKind::TableAlreadyExists(table_name))),
+        }
     }
 
-    #[rstest::rstest]
-    fn update_multiple_columns_of_all_records(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
+    pub fn table_does_not_exist_skipping(table_name: String) -> Self {
+        Self {
+            severity: Severity::Notice,
+            code: "00000".to_owned(),
+            kind: QueryErrorKind::Skipping(Box::new(QueryErrorKind::TableDoesNotExist(table_name))),
+        }
+    }
+
+    pub fn sequence_already_exists_skipping(sequence_name: String) -> Self {
+        Self {
+            severity: Severity::Notice,
+            code: "42P07".to_owned(),
+            kind: QueryErrorKind::Skipping(Box::new(QueryErrorKind::SequenceAlreadyExists(sequence_name))),
+        }
+    }
+
+    pub fn sequence_does_not_exist_skipping(sequence_name: String) -> Self {
+        Self {
+            severity: Severity::Notice,
+            code: "00000".to_owned(),
+            kind: QueryErrorKind::Skipping(Box::new(QueryErrorKind::SequenceDoesNotExist(sequence_name))),
+        }
+    }
+
+    pub fn index_already_exists_skipping(index_name: String) -> Self {
+        Self {
+            severity: Severity::Notice,
+            code: "42P07".to_owned(),
+            kind: QueryErrorKind::Skipping(Box::new(QueryErrorKind::IndexAlreadyExists(index_name))),
+        }
+    }
+
+    pub fn index_does_not_exist_skipping(index_name: String) -> Self {
+        Self {
+            severity: Severity::Notice,
+            code: "00000".to_owned(),
+            kind: QueryErrorKind::Skipping(Box::new(QueryErrorKind::IndexDoesNotExist(index_name))),
+        }
+    }
+
+    pub fn schema_does_not_exist_skipping(schema_name: String) -> Self {
+        Self {
+            severity: Severity::Notice,
+            code: "00000".to_owned(),
+            kind: QueryErrorKind::Skipping(Box::new(QueryErrorKind::SchemaDoesNotExist(schema_name))),
+        }
+    }
+
+    pub fn clause_ignored(clause: String) -> Self {
+        Self {
+            severity: Severity::Notice,
+            code: "00000".to_owned(),
+            kind: QueryErrorKind::ClauseIgnored(clause),
+        }
+    }
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Display for QueryErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            QueryErrorKind::SchemaAlreadyExists(schema_name) => write!(f, "schema \"{}\" already exists", schema_name),
+            QueryErrorKind::TableAlreadyExists(table_name) => write!(f, "table \"{}\" already exists", table_name),
+            QueryErrorKind::SchemaDoesNotExist(schema_name) => write!(f, "schema \"{}\" does not exist", schema_name),
+            QueryErrorKind::TableDoesNotExist(table_name) => write!(f, "table \"{}\" does not exist", table_name),
+            QueryErrorKind::ColumnDoesNotExist(columns) => {
+                if columns.len() > 1 {
+                    write!(f, "columns {} do not exist", columns.join(", "))
+                } else {
+                    write!(f, "column {} does not exist", columns[0])
+                }
+            }
+            QueryErrorKind::ColumnAlreadyExists(column_name, table_name) => write!(
+                f,
+                "column \"{}\" of relation \"{}\" already exists",
+                column_name, table_name
+            ),
+            QueryErrorKind::NotSupportedOperation(raw_sql_query) => {
+                write!(f, "Currently, Query '{}' can't be executed", raw_sql_query)
+            }
+            QueryErrorKind::SyntaxError(message) => write!(f, "syntax error: {}", message),
+            QueryErrorKind::InvalidParameterValue(parameter, value) => {
+                write!(f, "invalid value for parameter \"{}\": \"{}\"", parameter, value)
+            }
+            QueryErrorKind::InvalidDatetimeFormat(type_name, value) => {
+                write!(f, "invalid input syntax for type {}: \"{}\"", type_name, value)
+            }
+            QueryErrorKind::DatetimeFieldOverflow(type_name) => write!(f, "{} out of range", type_name),
+            QueryErrorKind::InvalidTextRepresentation(type_name, value) => {
+                write!(f, "invalid input syntax for type {}: \"{}\"", type_name, value)
+            }
+            QueryErrorKind::NumericValueOutOfRange(type_name) => write!(f, "{} out of range", type_name),
+            QueryErrorKind::DatatypeMismatch(clause, expected_type, type_name) => write!(
+                f,
+                "argument of {} must be type {}, not type {}",
+                clause, expected_type, type_name
+            ),
+            QueryErrorKind::DivisionByZero => write!(f, "division by zero"),
+            QueryErrorKind::ValueOutOfRange(reason) => write!(f, "{}", reason),
+            QueryErrorKind::InvalidArgumentForLogarithm(reason) => write!(f, "{}", reason),
+            QueryErrorKind::InvalidArgumentForPowerFunction(reason) => write!(f, "{}", reason),
+            QueryErrorKind::CannotCoerce(source_type, target_type) => {
+                write!(f, "cannot cast type {} to {}", source_type, target_type)
+            }
+            QueryErrorKind::UndefinedFunction(signature) => write!(f, "function {} does not exist", signature),
+            QueryErrorKind::UndefinedOperator(signature) => write!(f, "operator does not exist: {}", signature),
+            QueryErrorKind::UnrecognizedParameter(parameter) => {
+                write!(f, "unrecognized configuration parameter \"{}\"", parameter)
+            }
+            QueryErrorKind::InvalidRowCountInLimitClause => write!(f, "LIMIT must not be negative"),
+            QueryErrorKind::InvalidRowCountInResultOffsetClause => write!(f, "OFFSET must not be negative"),
+            QueryErrorKind::InvalidColumnReference(clause, position) => {
+                write!(f, "{} position {} is not in select list", clause, position)
+            }
+            QueryErrorKind::UngroupedColumn(column_name) => write!(
+                f,
+                "column \"{}\" must appear in the GROUP BY clause or be used in an aggregate function",
+                column_name
+            ),
+            QueryErrorKind::AggregateNotAllowed(clause) => {
+                write!(f, "aggregate functions are not allowed in {}", clause)
+            }
+            QueryErrorKind::NestedAggregate => write!(f, "aggregate function calls cannot be nested"),
+            QueryErrorKind::TempFileLimitExceeded(limit) => write!(
+                f,
+                "temporary file size exceeds temp_file_limit ({}kB)",
+                limit / KILOBYTE
+            ),
+            QueryErrorKind::LockTimeout => write!(f, "canceling statement due to lock timeout"),
+            QueryErrorKind::SequenceAlreadyExists(sequence_name) => {
+                write!(f, "relation \"{}\" already exists", sequence_name)
+            }
+            QueryErrorKind::SequenceDoesNotExist(sequence_name) => {
+                write!(f, "relation \"{}\" does not exist", sequence_name)
+            }
+            QueryErrorKind::IndexAlreadyExists(index_name) => write!(f, "relation \"{}\" already exists", index_name),
+            QueryErrorKind::IndexDoesNotExist(index_name) => write!(f, "index \"{}\" does not exist", index_name),
+            QueryErrorKind::SequenceLimitReached(sequence_name) => {
+                write!(f, "nextval: reached limit of sequence \"{}\"", sequence_name)
+            }
+            QueryErrorKind::SequenceValueNotDefined(Some(sequence_name)) => write!(
+                f,
+                "currval of sequence \"{}\" is not yet defined in this session",
+                sequence_name
+            ),
+            QueryErrorKind::SequenceValueNotDefined(None) => {
+                write!(f, "lastval is not yet defined in this session")
+            }
+            QueryErrorKind::ActiveTransaction(command) => {
+                write!(f, "{} cannot run inside a transaction block", command)
+            }
+            QueryErrorKind::NoActiveTransaction(command) => {
+                write!(f, "{} can only be used in transaction blocks", command)
+            }
+            QueryErrorKind::InFailedTransaction => write!(
+                f,
+                "current transaction is aborted, commands ignored until end of transaction block"
+            ),
+            QueryErrorKind::ZeroStepSize => write!(f, "step size cannot equal zero"),
+            QueryErrorKind::UndefinedType(type_name) => write!(f, "type \"{}\" does not exist", type_name),
+            QueryErrorKind::PreparedStatementAlreadyExists(statement_name) => {
+                write!(f, "prepared statement \"{}\" already exists", statement_name)
+            }
+            QueryErrorKind::PreparedStatementDoesNotExist(statement_name) => {
+                write!(f, "prepared statement \"{}\" does not exist", statement_name)
+            }
+            QueryErrorKind::WrongNumberOfParameters(statement_name) => {
+                write!(
+                    f,
+                    "wrong number of parameters for prepared statement \"{}\"",
+                    statement_name
+                )
+            }
+            QueryErrorKind::BindParametersMismatch(statement_name, given, required) => write!(
+                f,
+                "bind message supplies {} parameters, but prepared statement \"{}\" requires {}",
+                given, statement_name, required
+            ),
+            QueryErrorKind::PortalAlreadyExists(portal_name) => write!(f, "cursor \"{}\" already exists", portal_name),
+            QueryErrorKind::PortalDoesNotExist(portal_name) => write!(f, "portal \"{}\" does not exist", portal_name),
+            QueryErrorKind::FeatureNotSupported(feature) => write!(f, "{} is not supported", feature),
+            QueryErrorKind::MoreThanOneRowReturnedBySubquery => {
+                write!(f, "more than one row returned by a subquery used as an expression")
+            }
+            QueryErrorKind::SubqueryMustReturnOneColumn => write!(f, "subquery must return only one column"),
+            QueryErrorKind::SubqueryHasTooManyColumns => write!(f, "subquery has too many columns"),
+            QueryErrorKind::UniqueViolation(constraint_name) => write!(
+                f,
+                "duplicate key value violates unique constraint \"{}\"",
+                constraint_name
+            ),
+            QueryErrorKind::NotNullViolation(column_name) => write!(
+                f,
+                "null value in column \"{}\" violates not-null constraint",
+                column_name
+            ),
+            QueryErrorKind::CheckViolation(table_name, constraint_name) => write!(
+                f,
+                "new row for relation \"{}\" violates check constraint \"{}\"",
+                table_name, constraint_name
+            ),
+            QueryErrorKind::MultiplePrimaryKeys(table_name) => {
+                write!(f, "multiple primary keys for table \"{}\" are not allowed", table_name)
+            }
+            QueryErrorKind::Skipping(kind) => write!(f, "{}, skipping", kind),
+            QueryErrorKind::ClauseIgnored(clause) => write!(f, "{} is not supported and is ignored", clause),
+        }
+    }
+}
+
+pub struct Handler<P: BackendStorage> {
+    storage: Arc<RwLock<FrontendStorage<P>>>,
+    statistics: Arc<Mutex<StatementStatistics>>,
+    activity: Arc<Mutex<SessionActivity>>,
+    session: Session,
+    transaction: Option<Transaction>,
+    stream_results: bool,
+    clock: Arc<dyn Clock>,
+    notices: Vec<QueryError>,
+    // values of parameters that client was notified about
+    reported_settings: Vec<(String, String)>,
+    // sorts spill rows that do not fit into work_mem here
+    temp: Option<Arc<TempStorage>>,
+    statements: Registry<PreparedStatement>,
+    portals: Registry<Portal>,
+    // client waited for a transaction block of another session longer than
+    // lock_timeout before the current command
+    lock_timed_out: bool,
+}
+
+impl<P: BackendStorage> Handler<P> {
+    pub fn new(storage: Arc<RwLock<FrontendStorage<P>>>) -> Self {
+        Self::with_statistics(storage, Arc::default())
+    }
+
+    pub fn with_statistics(
+        storage: Arc<RwLock<FrontendStorage<P>>>,
+        statistics: Arc<Mutex<StatementStatistics>>,
+    ) -> Self {
+        Self::with_activity(storage, statistics, Arc::default())
+    }
+
+    pub fn with_activity(
+        storage: Arc<RwLock<FrontendStorage<P>>>,
+        statistics: Arc<Mutex<StatementStatistics>>,
+        activity: Arc<Mutex<SessionActivity>>,
+    ) -> Self {
+        Self {
+            storage,
+            statistics,
+            activity,
+            session: Session::default(),
+            transaction: None,
+            stream_results: false,
+            clock: Arc::new(SystemClock),
+            notices: vec![],
+            reported_settings: Session::default().reported_settings(),
+            temp: None,
+            statements: Registry::default(),
+            portals: Registry::default(),
+            lock_timed_out: false,
+        }
+    }
+
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = session;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// without temp storage rows of a sort are kept in memory whatever
+    /// work_mem is
+    pub fn with_temp_storage(mut self, temp: Arc<TempStorage>) -> Self {
+        self.temp = Some(temp);
+        self
+    }
+
+    /// records of tables are read from storage as they are consumed instead
+    /// of being collected before query is completed
+    pub fn with_result_streaming(mut self) -> Self {
+        self.stream_results = true;
+        self
+    }
+
+    /// total size in bytes of temp files that a query is allowed to spill
+    pub fn temp_file_limit(&self) -> Option<u64> {
+        self.session.temp_file_limit()
+    }
+
+    pub fn transaction_state(&self) -> TransactionState {
+        match &self.transaction {
+            None => TransactionState::Idle,
+            Some(transaction) if transaction.is_failed() => TransactionState::Failed,
+            Some(_) => TransactionState::InProgress,
+        }
+    }
+
+    /// notices that were raised by the last executed query
+    pub fn take_notices(&mut self) -> Vec<QueryError> {
+        std::mem::take(&mut self.notices)
+    }
+
+    /// parameters which values have changed since client was notified last time
+    pub fn take_parameter_changes(&mut self) -> Vec<(String, String)> {
+        let current = self.session.reported_settings();
+        let changed = current
+            .iter()
+            .filter(|setting| !self.reported_settings.contains(setting))
+            .cloned()
+            .collect();
+        self.reported_settings = current;
+        changed
+    }
+
+    /// drops temporary objects of the session when client disconnects
+    pub fn close(&mut self) -> SystemResult<()> {
+        let temp_schema = self.session.temp_schema();
+        let _dropped = self.storage_mut().drop_schema(&temp_schema)?;
+        Ok(())
+    }
+
+    pub fn execute(&mut self, raw_sql_query: &str) -> SystemResult<QueryResult> {
+        self.notices.clear();
+        // empty query does not change state of a transaction even if it is failed
+        if raw_sql_query
+            .trim_matches(|c: char| c == ';' || c.is_whitespace())
+            .is_empty()
+        {
+            return Ok(Ok(QueryEvent::QueryEmpty));
+        }
+        let started = self.clock.now();
+        let mut result = self.execute_statement(raw_sql_query)?;
+        match &mut result {
+            Ok(event) => {
+                self.statistics
+                    .lock()
+                    .unwrap()
+                    .record(raw_sql_query, self.clock.now() - started, event);
+                // streamed records are counted once all of them are consumed
+                if let QueryEvent::RecordsStreamed(_columns, records) = event {
+                    let statistics = self.statistics.clone();
+                    let raw_sql_query = raw_sql_query.to_owned();
+                    records.on_finish(move |rows| statistics.lock().unwrap().add_rows(&raw_sql_query, rows));
+                }
+            }
+            Err(_) => {
+                if let Some(transaction) = self.transaction.as_mut() {
+                    transaction.fail();
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// prepares statement of extended query protocol, the unnamed statement is
+    /// replaced by the next one
+    pub fn prepare(
+        &mut self,
+        statement_name: &str,
+        sql: &str,
+        param_types: Vec<Option<SqlType>>,
+    ) -> std::result::Result<(), QueryError> {
+        if !statement_name.is_empty() && self.statements.contains(statement_name) {
+            return Err(QueryError::prepared_statement_already_exists(statement_name.to_owned()));
+        }
+        let mut param_types = param_types;
+        let parameters = prepared::parameters(sql);
+        if param_types.len() < parameters {
+            param_types.resize(parameters, None);
+        }
+        let evicted = self.statements.insert(
+            statement_name.to_owned(),
+            PreparedStatement {
+                sql: sql.to_owned(),
+                param_types,
+            },
+            self.session.max_prepared_statements(),
+        );
+        for statement_name in evicted {
+            log::warn!(
+                "least recently used prepared statement {:?} is deallocated",
+                statement_name
+            );
+        }
+        Ok(())
+    }
+
+    /// creates portal from prepared statement and values of its parameters,
+    /// the unnamed portal is replaced by the next one
+    pub fn bind(
+        &mut self,
+        portal_name: &str,
+        statement_name: &str,
+        params: Vec<Option<String>>,
+    ) -> std::result::Result<(), QueryError> {
+        if !portal_name.is_empty() && self.portals.contains(portal_name) {
+            return Err(QueryError::portal_already_exists(portal_name.to_owned()));
+        }
+        let statement = match self.statements.get(statement_name) {
+            Some(statement) => statement,
+            None => return Err(QueryError::prepared_statement_does_not_exist(statement_name.to_owned())),
+        };
+        if params.len() != statement.param_types.len() {
+            return Err(QueryError::bind_parameters_mismatch(
+                statement_name.to_owned(),
+                params.len(),
+                statement.param_types.len(),
+            ));
+        }
+        let values = params
+            .iter()
+            .zip(statement.param_types.iter())
+            .map(|(value, sql_type)| prepared::literal(value.as_deref(), *sql_type))
+            .collect::<std::result::Result<Vec<String>, QueryError>>()?;
+        let sql = prepared::bind(&statement.sql, &values);
+        let evicted = self.portals.insert(
+            portal_name.to_owned(),
+            Portal::Bound(sql),
+            self.session.max_prepared_statements(),
+        );
+        for portal_name in evicted {
+            log::warn!("least recently used portal {:?} is closed", portal_name);
+        }
+        Ok(())
+    }
+
+    /// types of parameters and columns of rows that prepared statement
+    /// returns, `None` if it does not return rows
+    pub fn describe_statement(
+        &mut self,
+        statement_name: &str,
+    ) -> SystemResult<std::result::Result<StatementDescription, QueryError>> {
+        let (sql, param_types) = match self.statements.get(statement_name) {
+            Some(statement) => (statement.sql.clone(), statement.param_types.clone()),
+            None => {
+                return Ok(Err(QueryError::prepared_statement_does_not_exist(
+                    statement_name.to_owned(),
+                )))
+            }
+        };
+        if !prepared::returns_rows(&sql) {
+            return Ok(Ok((param_types, None)));
+        }
+        // columns are known once the statement is executed, parameters are
+        // not bound yet so NULL takes their place
+        let sql = prepared::bind(&sql, &vec!["NULL".to_owned(); param_types.len()]);
+        match self.execute_statement(&sql)? {
+            Ok(QueryEvent::RecordsSelected((columns, _))) | Ok(QueryEvent::RecordsStreamed(columns, _)) => {
+                Ok(Ok((param_types, Some(columns))))
+            }
+            Ok(_) => Ok(Ok((param_types, None))),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    /// columns of rows that portal returns, `None` if it does not return rows
+    pub fn describe_portal(
+        &mut self,
+        portal_name: &str,
+    ) -> SystemResult<std::result::Result<Option<Columns>, QueryError>> {
+        let portal = match self.portals.remove(portal_name) {
+            Some(portal) => portal,
+            None => return Ok(Err(QueryError::portal_does_not_exist(portal_name.to_owned()))),
+        };
+        // rows are produced when portal is described and returned when it is executed
+        let portal = match portal {
+            Portal::Bound(sql) if prepared::returns_rows(&sql) => match self.execute(&sql)? {
+                Ok(event) => Portal::Executed(event),
+                Err(error) => return Ok(Err(error)),
+            },
+            portal => portal,
+        };
+        let columns = match &portal {
+            Portal::Executed(QueryEvent::RecordsSelected((columns, _)))
+            | Portal::Executed(QueryEvent::RecordsStreamed(columns, _)) => Some(columns.clone()),
+            _ => None,
+        };
+        self.portals
+            .insert(portal_name.to_owned(), portal, self.session.max_prepared_statements());
+        Ok(Ok(columns))
+    }
+
+    /// executes portal, records that do not fit into `max_rows` are returned
+    /// by the next executions, `0` means that there is no limit
+    pub fn execute_portal(
+        &mut self,
+        portal_name: &str,
+        max_rows: usize,
+    ) -> SystemResult<std::result::Result<PortalEvent, QueryError>> {
+        let limit = self.session.max_prepared_statements();
+        let event = match self.portals.remove(portal_name) {
+            Some(Portal::Bound(sql)) => match self.execute(&sql)? {
+                Ok(event) => event,
+                Err(error) => return Ok(Err(error)),
+            },
+            Some(Portal::Executed(event)) => event,
+            Some(Portal::Completed) => QueryEvent::RecordsSelected((vec![], vec![])),
+            None => return Ok(Err(QueryError::portal_does_not_exist(portal_name.to_owned()))),
+        };
+        let (columns, mut records) = match event {
+            QueryEvent::RecordsSelected((columns, records)) => {
+                (columns, RecordStream::new(Box::new(records.into_iter().map(Ok))))
+            }
+            QueryEvent::RecordsStreamed(columns, records) => (columns, records),
+            event => {
+                self.portals.insert(portal_name.to_owned(), Portal::Completed, limit);
+                return Ok(Ok(PortalEvent::Completed(event)));
+            }
+        };
+        if max_rows == 0 {
+            self.portals.insert(portal_name.to_owned(), Portal::Completed, limit);
+            return Ok(Ok(PortalEvent::Completed(QueryEvent::RecordsStreamed(
+                columns, records,
+            ))));
+        }
+        let mut rows = vec![];
+        while rows.len() < max_rows {
+            match records.next() {
+                Some(row) => rows.push(row?),
+                None => {
+                    self.portals.insert(portal_name.to_owned(), Portal::Completed, limit);
+                    return Ok(Ok(PortalEvent::Completed(QueryEvent::RecordsSelected((columns, rows)))));
+                }
+            }
+        }
+        self.portals.insert(
+            portal_name.to_owned(),
+            Portal::Executed(QueryEvent::RecordsStreamed(columns, records)),
+            limit,
+        );
+        Ok(Ok(PortalEvent::Suspended(rows)))
+    }
+
+    /// closing of prepared statement or portal that does not exist is not an error
+    pub fn close_statement(&mut self, statement_name: &str) {
+        self.statements.remove(statement_name);
+    }
+
+    pub fn close_portal(&mut self, portal_name: &str) {
+        self.portals.remove(portal_name);
+    }
+
+    /// portals last until the end of transaction, that ends with Sync if it
+    /// is not started explicitly
+    pub fn sync(&mut self) {
+        if self.transaction.is_none() {
+            self.portals.clear();
+        }
+    }
+
+    fn execute_prepared(&mut self, command: PreparedCommand) -> SystemResult<QueryResult> {
+        match command {
+            PreparedCommand::Prepare {
+                name,
+                param_types,
+                statement,
+            } => Ok(self
+                .prepare(&name, &statement, param_types)
+                .map(|()| QueryEvent::StatementPrepared)),
+            PreparedCommand::Execute { name, args } => {
+                let statement = match self.statements.get(&name) {
+                    Some(statement) => statement,
+                    None => return Ok(Err(QueryError::prepared_statement_does_not_exist(name))),
+                };
+                if args.len() != statement.param_types.len() {
+                    return Ok(Err(QueryError::wrong_number_of_parameters(name)));
+                }
+                let args = args.iter().map(|arg| prepared::operand(arg)).collect::<Vec<String>>();
+                let sql = prepared::bind(&statement.sql, &args);
+                self.execute_statement(&sql)
+            }
+            PreparedCommand::Deallocate(Some(name)) => match self.statements.remove(&name) {
+                Some(_statement) => Ok(Ok(QueryEvent::StatementDeallocated)),
+                None => Ok(Err(QueryError::prepared_statement_does_not_exist(name))),
+            },
+            PreparedCommand::Deallocate(None) => {
+                self.statements.clear();
+                Ok(Ok(QueryEvent::AllStatementsDeallocated))
+            }
+        }
+    }
+
+    // changes of table rows are written to the undo log while session is in a
+    // transaction block, the block holds storage from its first change until
+    // it is ended
+    fn modify<T>(&mut self, operation: impl FnOnce(&mut FrontendStorage<P>) -> T) -> T {
+        let in_block = self.transaction.is_some();
+        let mut storage = self.storage_mut();
+        if in_block {
+            storage.start_journal();
+        }
+        let result = operation(&mut storage);
+        drop(storage);
+        if let Some(transaction) = self.transaction.as_mut() {
+            transaction.hold_storage();
+        }
+        result
+    }
+
+    // sessions read storage concurrently, while changes are made by one session at a time
+    fn storage(&self) -> RwLockReadGuard<'_, FrontendStorage<P>> {
+        self.wait_for_block(
+            || {
+                acquire(
+                    &self.activity,
+                    self.session.id(),
+                    || self.storage.try_read(),
+                    || self.storage.read(),
+                )
+            },
+            None,
+        )
+        .expect("storage is waited for without a deadline")
+    }
+
+    fn storage_mut(&self) -> RwLockWriteGuard<'_, FrontendStorage<P>> {
+        self.wait_for_block(
+            || {
+                acquire(
+                    &self.activity,
+                    self.session.id(),
+                    || self.storage.try_write(),
+                    || self.storage.write(),
+                )
+            },
+            None,
+        )
+        .expect("storage is waited for without a deadline")
+    }
+
+    /// whether a transaction block of another session holds storage, then
+    /// statements wait until the block is ended
+    pub fn waits_for_transaction(&self) -> bool {
+        let holds_storage = matches!(&self.transaction, Some(transaction) if transaction.holds_storage());
+        !holds_storage && self.storage.read().unwrap().is_journaled()
+    }
+
+    /// wait for a transaction block of another session to end before the next
+    /// command, `None` if there is nothing to wait for. The wait blocks, so it
+    /// is sent where it does not hold up other sessions
+    pub fn lock_wait(&mut self) -> Option<LockWait<P>> {
+        self.lock_timed_out = false;
+        if self.waits_for_transaction() {
+            Some(LockWait {
+                storage: self.storage.clone(),
+                timeout: self.session.lock_timeout(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// the block was not ended within lock_timeout, so the next statement is
+    /// canceled unless the block is ended by then
+    pub fn lock_timed_out(&mut self) {
+        self.lock_timed_out = true;
+    }
+
+    // statements wait for a transaction block of another session no longer
+    // than lock_timeout
+    fn wait_for_lock(&mut self) -> std::result::Result<(), QueryError> {
+        let deadline = if std::mem::take(&mut self.lock_timed_out) {
+            Some(Instant::now())
+        } else {
+            self.session.lock_timeout().map(|timeout| Instant::now() + timeout)
+        };
+        let storage = self.wait_for_block(
+            || {
+                acquire(
+                    &self.activity,
+                    self.session.id(),
+                    || self.storage.try_read(),
+                    || self.storage.read(),
+                )
+            },
+            deadline,
+        );
+        match storage {
+            Some(_storage) => Ok(()),
+            None => Err(QueryError::lock_timeout()),
+        }
+    }
+
+    fn wait_for_block<G: Deref<Target = FrontendStorage<P>>>(
+        &self,
+        lock: impl Fn() -> G,
+        deadline: Option<Instant>,
+    ) -> Option<G> {
+        if matches!(&self.transaction, Some(transaction) if transaction.holds_storage()) {
+            return Some(lock());
+        }
+        let mut waited = false;
+        let storage = block_ended(lock, deadline, || {
+            waited = true;
+            self.activity
+                .lock()
+                .unwrap()
+                .wait_started(self.session.id(), WaitEvent::TransactionLock);
+        });
+        if waited {
+            self.activity.lock().unwrap().wait_finished(self.session.id());
+        }
+        storage
+    }
+
+    fn end_transaction(&mut self, commit: bool) -> SystemResult<QueryResult> {
+        self.session.reset_local_settings();
+        self.portals.clear();
+        let committed = match self.transaction.as_ref() {
+            Some(transaction) => {
+                let committed = commit && !transaction.is_failed();
+                if transaction.holds_storage() {
+                    let mut storage = self.storage_mut();
+                    if committed {
+                        storage.clear_journal()?;
+                    } else {
+                        storage.revert()?;
+                    }
+                }
+                committed
+            }
+            None => {
+                log::warn!("there is no transaction in progress");
+                commit
+            }
+        };
+        self.transaction = None;
+        if committed {
+            Ok(Ok(QueryEvent::TransactionCommitted))
+        } else {
+            Ok(Ok(QueryEvent::TransactionRolledBack))
+        }
+    }
+
+    // statements except COMMIT and ROLLBACK are rejected until failed transaction block is ended
+    fn is_in_failed_transaction(&self) -> bool {
+        matches!(&self.transaction, Some(transaction) if transaction.is_failed())
+    }
+
+    #[allow(clippy::match_wild_err_arm)]
+    fn execute_statement(&mut self, raw_sql_query: &str) -> SystemResult<QueryResult> {
+        if let Err(error) = self.wait_for_lock() {
+            return Ok(Err(error));
+        }
+        let rewritten = rewrite::rewrite(raw_sql_query);
+        self.notices.extend(
+            rewritten
+                .ignored
+                .iter()
+                .map(|clause| QueryError::clause_ignored((*clause).to_owned())),
+        );
+        let raw_sql_query = rewritten.query.as_ref();
+        // sql parser does not know the command
+        if raw_sql_query
+            .trim()
+            .trim_end_matches(';')
+            .eq_ignore_ascii_case("checkpoint")
+        {
+            if self.is_in_failed_transaction() {
+                return Ok(Err(QueryError::in_failed_transaction()));
+            }
+            self.storage_mut().checkpoint()?;
+            return Ok(Ok(QueryEvent::Checkpoint));
+        }
+        if let Some(parsed) = sequence::parse(raw_sql_query) {
+            if self.is_in_failed_transaction() {
+                return Ok(Err(QueryError::in_failed_transaction()));
+            }
+            let statement = match parsed {
+                Ok(statement) => statement,
+                Err(error) => return Ok(Err(error)),
+            };
+            // changes of definitions can't be reverted yet
+            if self.transaction.is_some() {
+                return Ok(Err(QueryError::active_transaction(statement.command().to_owned())));
+            }
+            return self.execute_sequence_statement(statement);
+        }
+        if let Some(parsed) = alter_table::parse(raw_sql_query) {
+            if self.is_in_failed_transaction() {
+                return Ok(Err(QueryError::in_failed_transaction()));
+            }
+            let statement = match parsed {
+                Ok(statement) => statement,
+                Err(error) => return Ok(Err(error)),
+            };
+            // changes of definitions can't be reverted yet
+            if self.transaction.is_some() {
+                return Ok(Err(QueryError::active_transaction("ALTER TABLE".to_owned())));
+            }
+            return self.alter_table(statement, raw_sql_query);
+        }
+        if let Some(parsed) = truncate::parse(raw_sql_query) {
+            if self.is_in_failed_transaction() {
+                return Ok(Err(QueryError::in_failed_transaction()));
+            }
+            return match parsed {
+                Ok(names) => self.truncate(names),
+                Err(error) => Ok(Err(error)),
+            };
+        }
+        if let Some(setting) = custom_setting::parse(raw_sql_query) {
+            if self.is_in_failed_transaction() {
+                return Ok(Err(QueryError::in_failed_transaction()));
+            }
+            return Ok(self.set_variable(&setting.name, &setting.value, setting.local));
+        }
+        if let Some(parsed) = prepared::parse(raw_sql_query) {
+            if self.is_in_failed_transaction() {
+                return Ok(Err(QueryError::in_failed_transaction()));
+            }
+            return match parsed {
+                Ok(command) => self.execute_prepared(command),
+                Err(error) => Ok(Err(error)),
+            };
+        }
+        let parse_span = tracing::debug_span!("parse");
+        let parsing = parse_span.enter();
+        let statement = match Parser::parse_sql(&PostgreSqlDialect {}, raw_sql_query) {
+            Ok(mut statements) => match statements.pop() {
+                Some(statement) => statement,
+                None => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            },
+            Err(e) => {
+                log::error!("{:?} can't be parsed. Error: {:?}", raw_sql_query, e);
+                let message = match e {
+                    ParserError::TokenizerError(message) => message,
+                    ParserError::ParserError(message) => message,
+                };
+                return Ok(Err(QueryError::syntax_error(message)));
+            }
+        };
+        drop(parsing);
+        log::debug!("STATEMENT = {:?}", statement);
+        let execute_span = tracing::debug_span!("execute");
+        let _executing = execute_span.enter();
+        if let Some(transaction) = &self.transaction {
+            let definition = match &statement {
+                sqlparser::ast::Statement::Commit { .. } | sqlparser::ast::Statement::Rollback { .. } => None,
+                _ if transaction.is_failed() => return Ok(Err(QueryError::in_failed_transaction())),
+                sqlparser::ast::Statement::CreateTable { .. } => Some("CREATE TABLE"),
+                sqlparser::ast::Statement::CreateSchema { .. } => Some("CREATE SCHEMA"),
+                sqlparser::ast::Statement::CreateIndex { .. } => Some("CREATE INDEX"),
+                sqlparser::ast::Statement::Drop {
+                    object_type: sqlparser::ast::ObjectType::Table,
+                    ..
+                } => Some("DROP TABLE"),
+                sqlparser::ast::Statement::Drop {
+                    object_type: sqlparser::ast::ObjectType::Schema,
+                    ..
+                } => Some("DROP SCHEMA"),
+                sqlparser::ast::Statement::Drop {
+                    object_type: sqlparser::ast::ObjectType::Index,
+                    ..
+                } => Some("DROP INDEX"),
+                _ => None,
+            };
+            // changes of definitions can't be reverted yet
+            if let Some(command) = definition {
+                return Ok(Err(QueryError::active_transaction(command.to_owned())));
+            }
+        }
+        match statement {
+            sqlparser::ast::Statement::StartTransaction { .. } => {
+                if self.transaction.is_some() {
+                    log::warn!("there is already a transaction in progress");
+                } else {
+                    self.transaction = Some(Transaction::default());
+                }
+                Ok(Ok(QueryEvent::TransactionStarted))
+            }
+            sqlparser::ast::Statement::Commit { .. } => self.end_transaction(true),
+            sqlparser::ast::Statement::Rollback { .. } => self.end_transaction(false),
+            sqlparser::ast::Statement::SetVariable { local, variable, value } => {
+                let value = match value {
+                    sqlparser::ast::SetVariableValue::Ident(ident) => ident.value,
+                    sqlparser::ast::SetVariableValue::Literal(sqlparser::ast::Value::Number(number)) => number,
+                    sqlparser::ast::SetVariableValue::Literal(sqlparser::ast::Value::SingleQuotedString(value)) => {
+                        value
+                    }
+                    sqlparser::ast::SetVariableValue::Literal(sqlparser::ast::Value::Boolean(true)) => "on".to_owned(),
+                    sqlparser::ast::SetVariableValue::Literal(sqlparser::ast::Value::Boolean(false)) => {
+                        "off".to_owned()
+                    }
+                    sqlparser::ast::SetVariableValue::Literal(value) => value.to_string(),
+                };
+                Ok(self.set_variable(&variable.value, &value, local))
+            }
+            sqlparser::ast::Statement::CreateTable {
+                mut name,
+                columns,
+                constraints,
+                if_not_exists,
+                ..
+            } => {
+                let table_name = name.0.pop().unwrap().to_string();
+                let schema_name = self.session.schema_name(name.0.pop().unwrap().to_string());
+                let mut constraints = match table_constraints(
+                    &self.session,
+                    &schema_name,
+                    &table_name,
+                    &columns,
+                    constraints,
+                    raw_sql_query,
+                ) {
+                    Ok(constraints) => constraints,
+                    Err(error) => return Ok(Err(error)),
+                };
+                if schema_name == self.session.temp_schema() {
+                    // temporary schema is created along with the first temporary table
+                    let _exists = self.storage_mut().create_schema(&schema_name)?;
+                }
+                let owned_sequences = match self.create_owned_sequences(&mut constraints)? {
+                    Ok(sequences) => sequences,
+                    Err(error) => return Ok(Err(error)),
+                };
+                let created = self.storage_mut().create_table_with_constraints(
+                    &schema_name,
+                    &table_name,
+                    columns
+                        .into_iter()
+                        .map(|c| (c.name.to_string(), column_type(&c.data_type)))
+                        .collect(),
+                    constraints,
+                )?;
+                if created.is_err() {
+                    self.drop_sequences(owned_sequences)?;
+                }
+                match created {
+                    Ok(()) => Ok(Ok(QueryEvent::TableCreated)),
+                    Err(CreateTableError::SchemaDoesNotExist) => {
+                        Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                    }
+                    Err(CreateTableError::TableAlreadyExists) if if_not_exists => {
+                        self.notices.push(QueryError::table_already_exists_skipping(table_name));
+                        Ok(Ok(QueryEvent::TableCreated))
+                    }
+                    Err(CreateTableError::TableAlreadyExists) => Ok(Err(QueryError::table_already_exists(table_name))),
+                }
+            }
+            sqlparser::ast::Statement::CreateSchema { schema_name, .. } => {
+                let schema_name = schema_name.to_string();
+                match self.storage_mut().create_schema(&schema_name)? {
+                    Ok(()) => Ok(Ok(QueryEvent::SchemaCreated)),
+                    Err(SchemaAlreadyExists) => Ok(Err(QueryError::schema_already_exists(schema_name))),
+                }
+            }
+            sqlparser::ast::Statement::CreateIndex {
+                mut name,
+                table_name,
+                mut columns,
+                unique,
+                if_not_exists,
+            } => {
+                if unique {
+                    return Ok(Err(QueryError::feature_not_supported("unique index".to_owned())));
+                }
+                if columns.len() != 1 {
+                    return Ok(Err(QueryError::feature_not_supported(
+                        "index on multiple columns".to_owned(),
+                    )));
+                }
+                // index is created in the schema of its table
+                let (schema_name, table_name) = qualified_name(&self.session, &table_name.to_string());
+                let index_name = name.0.pop().unwrap().value;
+                let column_name = columns.pop().unwrap().value;
+                let created = self
+                    .storage_mut()
+                    .create_index(&schema_name, &table_name, &index_name, &column_name)?;
+                match created {
+                    Ok(()) => Ok(Ok(QueryEvent::IndexCreated)),
+                    Err(CreateIndexError::IndexAlreadyExists) if if_not_exists => {
+                        self.notices.push(QueryError::index_already_exists_skipping(index_name));
+                        Ok(Ok(QueryEvent::IndexCreated))
+                    }
+                    Err(CreateIndexError::IndexAlreadyExists) => Ok(Err(QueryError::index_already_exists(index_name))),
+                    Err(CreateIndexError::SchemaDoesNotExist) => {
+                        Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                    }
+                    Err(CreateIndexError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
+                        schema_name + "." + table_name.as_str(),
+                    ))),
+                    Err(CreateIndexError::ColumnDoesNotExist(column_name)) => {
+                        Ok(Err(QueryError::column_does_not_exist(vec![column_name])))
+                    }
+                }
+            }
+            sqlparser::ast::Statement::Drop {
+                object_type,
+                names,
+                if_exists,
+                ..
+            } => match object_type {
+                sqlparser::ast::ObjectType::Table => {
+                    let table_name = names[0].0[1].to_string();
+                    let schema_name = self.session.schema_name(names[0].0[0].to_string());
+                    let dropped = self.storage_mut().drop_table(&schema_name, &table_name)?;
+                    match dropped {
+                        Ok(()) => Ok(Ok(QueryEvent::TableDropped)),
+                        Err(DropTableError::TableDoesNotExist) if if_exists => {
+                            self.notices.push(QueryError::table_does_not_exist_skipping(
+                                schema_name + "." + table_name.as_str(),
+                            ));
+                            Ok(Ok(QueryEvent::TableDropped))
+                        }
+                        Err(DropTableError::SchemaDoesNotExist) if if_exists => {
+                            self.notices
+                                .push(QueryError::schema_does_not_exist_skipping(schema_name));
+                            Ok(Ok(QueryEvent::TableDropped))
+                        }
+                        Err(DropTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
+                            schema_name + "." + table_name.as_str(),
+                        ))),
+                        Err(DropTableError::SchemaDoesNotExist) => {
+                            Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                        }
+                    }
+                }
+                sqlparser::ast::ObjectType::Schema => {
+                    let schema_name = names[0].0[0].to_string();
+                    let dropped = self.storage_mut().drop_schema(&schema_name)?;
+                    match dropped {
+                        Ok(()) => Ok(Ok(QueryEvent::SchemaDropped)),
+                        Err(SchemaDoesNotExist) if if_exists => {
+                            self.notices
+                                .push(QueryError::schema_does_not_exist_skipping(schema_name));
+                            Ok(Ok(QueryEvent::SchemaDropped))
+                        }
+                        Err(SchemaDoesNotExist) => Ok(Err(QueryError::schema_does_not_exist(schema_name))),
+                    }
+                }
+                sqlparser::ast::ObjectType::Index => {
+                    for name in names {
+                        let (schema_name, index_name) = qualified_name(&self.session, &name.to_string());
+                        let dropped = self.storage_mut().drop_index(&schema_name, &index_name)?;
+                        match dropped {
+                            Ok(()) => {}
+                            Err(DropIndexError::IndexDoesNotExist) if if_exists => {
+                                self.notices.push(QueryError::index_does_not_exist_skipping(index_name));
+                            }
+                            Err(DropIndexError::SchemaDoesNotExist) if if_exists => {
+                                self.notices
+                                    .push(QueryError::schema_does_not_exist_skipping(schema_name));
+                            }
+                            Err(DropIndexError::IndexDoesNotExist) => {
+                                return Ok(Err(QueryError::index_does_not_exist(index_name)))
+                            }
+                            Err(DropIndexError::SchemaDoesNotExist) => {
+                                return Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                            }
+                        }
+                    }
+                    Ok(Ok(QueryEvent::IndexDropped))
+                }
+                _ => Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            },
+            sqlparser::ast::Statement::Insert {
+                mut table_name,
+                columns,
+                source,
+                ..
+            } => {
+                let name = table_name.0.pop().unwrap().to_string();
+                let schema_name = self.session.schema_name(table_name.0.pop().unwrap().to_string());
+                let sqlparser::ast::Query { body, .. } = &*source;
+                if let sqlparser::ast::SetExpr::Values(values) = &body {
+                    let values = &values.0;
+
+                    let mut columns: Vec<String> = columns
+                        .into_iter()
+                        .map(|id| {
+                            let sqlparser::ast::Ident { value, .. } = id;
+                            value
+                        })
+                        .collect();
+
+                    // `None` stands for `DEFAULT` keyword
+                    let rows: std::result::Result<Vec<Vec<Option<String>>>, QueryError> = values
+                        .iter()
+                        .map(|v| {
+                            v.iter()
+                                .map(|v| match v {
+                                    sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(v)) => {
+                                        Ok(Some(v.to_string()))
+                                    }
+                                    sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(v)) => {
+                                        Ok(Some(v.to_string()))
+                                    }
+                                    sqlparser::ast::Expr::UnaryOp { op, expr } => match (op, &**expr) {
+                                        (
+                                            sqlparser::ast::UnaryOperator::Minus,
+                                            sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(v)),
+                                        ) => Ok(Some("-".to_owned() + v.as_str())),
+                                        (op, expr) => unimplemented!("{:?} {:?} is not currently supported", op, expr),
+                                    },
+                                    // sql parser does not know `DEFAULT` keyword in values
+                                    sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident { value, .. })
+                                        if value.eq_ignore_ascii_case("default") =>
+                                    {
+                                        Ok(None)
+                                    }
+                                    sqlparser::ast::Expr::Function(function) => {
+                                        self.function_value(function, raw_sql_query).map(Some)
+                                    }
+                                    expr => unimplemented!("{:?} is not currently supported", expr),
+                                })
+                                .collect()
+                        })
+                        .collect();
+                    let mut rows = match rows {
+                        Ok(rows) => rows,
+                        Err(error) => return Ok(Err(error)),
+                    };
+
+                    // errors of tables that do not exist are reported by insert
+                    let defaults = self.storage().column_defaults(&schema_name, &name)?.unwrap_or_default();
+                    if !defaults.is_empty() || rows.iter().flatten().any(Option::is_none) {
+                        if columns.is_empty() {
+                            let given = rows.first().map(Vec::len).unwrap_or(0);
+                            if let Ok(all_columns) = self.storage().table_columns(&schema_name, &name)? {
+                                columns = all_columns.into_iter().take(given).map(|(name, _)| name).collect();
+                            }
+                        }
+                        let omitted: Vec<(String, DefaultValue)> = defaults
+                            .iter()
+                            .filter(|(column, _default)| !columns.contains(column))
+                            .cloned()
+                            .collect();
+                        for row in rows.iter_mut() {
+                            for (column, value) in columns.iter().zip(row.iter_mut()) {
+                                if value.is_some() {
+                                    continue;
+                                }
+                                let default = match defaults.iter().find(|(name, _default)| name == column) {
+                                    Some((_name, default)) => default,
+                                    // there are no NULL values yet
+                                    None => {
+                                        return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned())))
+                                    }
+                                };
+                                match self.default_value(default)? {
+                                    Ok(default) => *value = Some(default),
+                                    Err(error) => return Ok(Err(error)),
+                                }
+                            }
+                            for (_column, default) in omitted.iter() {
+                                match self.default_value(default)? {
+                                    Ok(default) => row.push(Some(default)),
+                                    Err(error) => return Ok(Err(error)),
+                                }
+                            }
+                        }
+                        columns.extend(omitted.into_iter().map(|(column, _default)| column));
+                    }
+                    let rows: Vec<Vec<String>> = rows
+                        .into_iter()
+                        .map(|row| row.into_iter().map(Option::unwrap_or_default).collect())
+                        .collect();
+                    let rows = match self.input_values(&schema_name, &name, &columns, rows)? {
+                        Ok(rows) => rows,
+                        Err(error) => return Ok(Err(error)),
+                    };
+
+                    let len = rows.len();
+                    match self.modify(|storage| storage.insert_into(&schema_name, &name, columns, rows))? {
+                        Ok(_) => Ok(Ok(QueryEvent::RecordsInserted(len))),
+                        Err(OperationOnTableError::SchemaDoesNotExist) => {
+                            Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                        }
+                        Err(OperationOnTableError::TableDoesNotExist) => {
+                            Ok(Err(QueryError::table_does_not_exist(schema_name + "." + name.as_str())))
+                        }
+                        Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)) => {
+                            Ok(Err(QueryError::column_does_not_exist(non_existing_columns)))
+                        }
+                        Err(OperationOnTableError::UniqueViolation(constraint_name)) => {
+                            Ok(Err(QueryError::unique_violation(constraint_name)))
+                        }
+                        Err(OperationOnTableError::NotNullViolation(column_name)) => {
+                            Ok(Err(QueryError::not_null_violation(column_name)))
+                        }
+                        Err(OperationOnTableError::CheckViolation(constraint_name)) => {
+                            Ok(Err(QueryError::check_violation(name, constraint_name)))
+                        }
+                        Err(e) => {
+                            eprintln!("{:?}", e);
+                            unimplemented!()
+                        }
+                    }
+                } else {
+                    Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned())))
+                }
+            }
+            sqlparser::ast::Statement::Query(query) => self.select(*query, raw_sql_query),
+            sqlparser::ast::Statement::Update {
+                table_name,
+                assignments,
+                selection,
+            } => self.update(table_name, assignments, selection, raw_sql_query),
+            sqlparser::ast::Statement::Delete { table_name, selection } => {
+                self.delete(table_name, selection, raw_sql_query)
+            }
+            _ => Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+        }
+    }
+
+    fn select(&mut self, query: sqlparser::ast::Query, raw_sql_query: &str) -> SystemResult<QueryResult> {
+        let sqlparser::ast::Query {
+            body,
+            order_by,
+            limit,
+            offset,
+            ..
+        } = query;
+        let limits = match self.row_limits(
+            order_by,
+            limit.as_ref(),
+            offset.as_ref().map(|offset| &offset.value),
+            raw_sql_query,
+        ) {
+            Ok(limits) => limits,
+            Err(error) => return Ok(Err(error)),
+        };
+        if let sqlparser::ast::SetExpr::Select(select) = body {
+            let select = if subquery::has_subqueries(&select) {
+                let mut run = |query| self.subquery(query, raw_sql_query);
+                match subquery::materialize(*select, &mut run)? {
+                    Ok(select) => select,
+                    Err(error) => return Ok(Err(error)),
+                }
+            } else {
+                *select
+            };
+            let sqlparser::ast::Select {
+                projection,
+                from,
+                selection,
+                group_by,
+                having,
+                ..
+            } = &select;
+            let aggregated = !group_by.is_empty()
+                || having.is_some()
+                || projection.iter().any(|item| match item {
+                    sqlparser::ast::SelectItem::UnnamedExpr(expr)
+                    | sqlparser::ast::SelectItem::ExprWithAlias { expr, .. } => aggregate::contains_aggregate(expr),
+                    _ => false,
+                });
+            // rows are put into groups after WHERE filters them
+            if selection.as_ref().map(aggregate::contains_aggregate) == Some(true) {
+                return Ok(Err(QueryError::aggregate_not_allowed("WHERE".to_owned())));
+            }
+            if from.is_empty() && aggregated {
+                // aggregates without FROM are computed over a single empty row
+                return self.select_groups(
+                    vec![],
+                    Box::new(std::iter::once(Ok(vec![]))),
+                    &select,
+                    &limits,
+                    raw_sql_query,
+                );
+            }
+            if from.is_empty() {
+                return self.select_functions(projection, &limits, raw_sql_query);
+            }
+            // there is no join operator yet, so only a single table without joins can be read
+            if from.len() != 1 || !from[0].joins.is_empty() {
+                return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned())));
+            }
+            let sqlparser::ast::TableWithJoins { relation, .. } = &from[0];
+            if let sqlparser::ast::TableFactor::Table { name, .. } = relation {
+                if let Some(relation) = SystemRelation::find(&name.0) {
+                    if aggregated {
+                        let (columns, rows) = relation.projection(&self.storage(), &self.session)?;
+                        let records = Box::new(rows.into_iter().map(Ok));
+                        return self.select_groups(columns, records, &select, &limits, raw_sql_query);
+                    }
+                    return self.select_system_relation(
+                        relation,
+                        projection,
+                        selection.as_ref(),
+                        &limits,
+                        raw_sql_query,
+                    );
+                }
+            }
+            let (schema_name, table_name) = match relation {
+                sqlparser::ast::TableFactor::Table { name, .. }
+                    if name.0.len() == 1 && name.0[0].value == PG_STAT_STATEMENTS =>
+                {
+                    let statistics = self.statistics.lock().unwrap().projection();
+                    return self.select_projection(statistics, &limits, raw_sql_query);
+                }
+                sqlparser::ast::TableFactor::Table { name, .. }
+                    if name.0.len() == 1 && name.0[0].value == PG_STAT_ACTIVITY =>
+                {
+                    let activity = self.activity.lock().unwrap().projection();
+                    return self.select_projection(activity, &limits, raw_sql_query);
+                }
+                sqlparser::ast::TableFactor::Table { name, args, .. }
+                    if name.0.len() == 1 && name.0[0].value.to_lowercase() == GENERATE_SERIES =>
+                {
+                    return self.select_series(projection, args, &limits, raw_sql_query);
+                }
+                sqlparser::ast::TableFactor::Table { name, .. } => {
+                    let table_name = name.0[1].to_string();
+                    let schema_name = self.session.schema_name(name.0[0].to_string());
+                    (schema_name, table_name)
+                }
+                _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            };
+            // select items of aggregated rows are evaluated after rows are grouped
+            let table_columns = if aggregated {
+                vec![]
+            } else {
+                let projection = projection.clone();
+                let mut columns: Vec<String> = vec![];
+                for item in projection {
+                    match item {
+                        sqlparser::ast::SelectItem::Wildcard => {
+                            match self.storage().table_columns(&schema_name, &table_name)? {
+                                Ok(all_columns) => columns.extend(
+                                    all_columns
+                                        .into_iter()
+                                        .map(|(name, _sql_type)| name)
+                                        .collect::<Vec<String>>(),
+                                ),
+                                Err(_e) => {
+                                    return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned())))
+                                }
+                            }
+                        }
+                        sqlparser::ast::SelectItem::UnnamedExpr(sqlparser::ast::Expr::Identifier(
+                            sqlparser::ast::Ident { value, .. },
+                        )) => columns.push(value.clone()),
+                        _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+                    }
+                }
+                columns
+            };
+            // all columns are read to evaluate the predicate and sort
+            // keys, rows are projected after they are filtered and sorted
+            let (read_columns, key_conditions) = match selection {
+                None if limits.order_by.is_empty() && !aggregated => (table_columns.clone(), vec![]),
+                Some(predicate) => {
+                    let all_columns = match self.storage().table_columns(&schema_name, &table_name)? {
+                        Ok(all_columns) => all_columns.into_iter().map(|(name, _sql_type)| name).collect(),
+                        Err(_error) => vec![],
+                    };
+                    let key_conditions = expression::column_conditions(predicate, &all_columns);
+                    (all_columns, key_conditions)
+                }
+                None => match self.storage().table_columns(&schema_name, &table_name)? {
+                    Ok(all_columns) => (all_columns.into_iter().map(|(name, _sql_type)| name).collect(), vec![]),
+                    Err(_error) => (vec![], vec![]),
+                },
+            };
+            match self
+                .storage()
+                .select_stream_where(&schema_name, &table_name, read_columns, key_conditions)?
+            {
+                Ok((columns, records)) if aggregated => {
+                    self.select_groups(columns, records, &select, &limits, raw_sql_query)
+                }
+                Ok((columns, records)) => {
+                    let (columns, records) = match self.select_rows(
+                        columns,
+                        records,
+                        selection.as_ref(),
+                        &limits,
+                        &table_columns,
+                        raw_sql_query,
+                    )? {
+                        Ok(selected) => selected,
+                        Err(error) => return Ok(Err(error)),
+                    };
+                    let records = self.output_values(&columns, records);
+                    if self.stream_results {
+                        Ok(Ok(QueryEvent::RecordsStreamed(columns, RecordStream::new(records))))
+                    } else {
+                        let records = records.collect::<SystemResult<_>>()?;
+                        Ok(Ok(QueryEvent::RecordsSelected((columns, records))))
+                    }
+                }
+                Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)) => {
+                    Ok(Err(QueryError::column_does_not_exist(non_existing_columns)))
+                }
+                Err(OperationOnTableError::SchemaDoesNotExist) => {
+                    Ok(Err(QueryError::schema_does_not_exist(schema_name.to_owned())))
+                }
+                Err(OperationOnTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
+                    schema_name.to_owned() + "." + table_name.as_str(),
+                ))),
+                _ => Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            }
+        } else {
+            Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned())))
+        }
+    }
+
+    // subqueries are executed as queries of their own and all of their rows
+    // are kept, so that they can be put into the query they are nested in
+    fn subquery(
+        &mut self,
+        query: sqlparser::ast::Query,
+        raw_sql_query: &str,
+    ) -> SystemResult<std::result::Result<Projection, QueryError>> {
+        match self.select(query, raw_sql_query)? {
+            Ok(QueryEvent::RecordsSelected(projection)) => Ok(Ok(projection)),
+            Ok(QueryEvent::RecordsStreamed(columns, records)) => {
+                Ok(Ok((columns, records.collect::<SystemResult<_>>()?)))
+            }
+            Ok(_) => Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    // rows are read with all of their columns, so that both the predicate and
+    // new values can refer to any of them, and written back in a single batch
+    fn update(
+        &mut self,
+        table_name: sqlparser::ast::ObjectName,
+        assignments: Vec<sqlparser::ast::Assignment>,
+        selection: Option<sqlparser::ast::Expr>,
+        raw_sql_query: &str,
+    ) -> SystemResult<QueryResult> {
+        let schema_name = self.session.schema_name(table_name.0[0].to_string());
+        let table_name = table_name.0[1].to_string();
+
+        let mut columns = vec![];
+        let mut values = vec![];
+        for sqlparser::ast::Assignment { id, value } in assignments {
+            // settings of the session are the same for all rows
+            let value = match value {
+                sqlparser::ast::Expr::Function(function)
+                    if function.name.to_string().eq_ignore_ascii_case("current_setting") =>
+                {
+                    match self.function_value(&function, raw_sql_query) {
+                        Ok(setting) => sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(setting)),
+                        Err(error) => return Ok(Err(error)),
+                    }
+                }
+                value => value,
+            };
+            columns.push(id.value);
+            values.push(value);
+        }
+
+        let key_conditions = match &selection {
+            Some(predicate) => self.column_conditions(&schema_name, &table_name, predicate)?,
+            None => vec![],
+        };
+        let (all_columns, records) =
+            match self
+                .storage()
+                .select_keyed_where(&schema_name, &table_name, key_conditions)?
+            {
+                Ok(selected) => selected,
+                Err(OperationOnTableError::SchemaDoesNotExist) => {
+                    return Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                }
+                Err(OperationOnTableError::TableDoesNotExist) => {
+                    return Ok(Err(QueryError::table_does_not_exist(
+                        schema_name + "." + table_name.as_str(),
+                    )))
+                }
+                Err(_) => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            };
+
+        let evaluator = Evaluator::new(self.session.date_style(), self.session.time_zone(), raw_sql_query)
+            .with_random(self.session.random())
+            .with_columns(&all_columns);
+        let checked = selection
+            .iter()
+            .chain(values.iter())
+            .try_for_each(|expr| evaluator.check(expr));
+        let mut rows = vec![];
+        let mut updated = vec![];
+        let evaluated = checked.and_then(|()| {
+            for (key, record) in records {
+                if let Some(predicate) = selection.as_ref() {
+                    if !evaluator.filter(predicate, &record)? {
+                        continue;
+                    }
+                }
+                let mut row = vec![];
+                for value in values.iter() {
+                    match evaluator.row_value(value, &record)? {
+                        // there are no NULL values yet
+                        Datum::Null => return Err(QueryError::not_supported_operation(raw_sql_query.to_owned())),
+                        datum => row.push(evaluator.output(datum)),
+                    }
+                }
+                updated.push(key);
+                rows.push(row);
+            }
+            Ok(())
+        });
+        if let Err(error) = evaluated {
+            return Ok(Err(error));
+        }
+        let rows = match self.input_values(&schema_name, &table_name, &columns, rows)? {
+            Ok(rows) => updated.into_iter().zip(rows).collect(),
+            Err(error) => return Ok(Err(error)),
+        };
+
+        match self.modify(|storage| storage.update_rows(&schema_name, &table_name, columns, rows))? {
+            Ok(records_number) => Ok(Ok(QueryEvent::RecordsUpdated(records_number))),
+            Err(OperationOnTableError::SchemaDoesNotExist) => Ok(Err(QueryError::schema_does_not_exist(schema_name))),
+            Err(OperationOnTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
+                schema_name + "." + table_name.as_str(),
+            ))),
+            Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)) => {
+                Ok(Err(QueryError::column_does_not_exist(non_existing_columns)))
+            }
+            Err(OperationOnTableError::UniqueViolation(constraint_name)) => {
+                Ok(Err(QueryError::unique_violation(constraint_name)))
+            }
+            Err(OperationOnTableError::CheckViolation(constraint_name)) => {
+                Ok(Err(QueryError::check_violation(table_name, constraint_name)))
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    // rows are read with all of their columns to evaluate the predicate, keys of
+    // the ones that satisfy it are deleted in a single batch
+    fn delete(
+        &mut self,
+        table_name: sqlparser::ast::ObjectName,
+        selection: Option<sqlparser::ast::Expr>,
+        raw_sql_query: &str,
+    ) -> SystemResult<QueryResult> {
+        let schema_name = self.session.schema_name(table_name.0[0].to_string());
+        let table_name = table_name.0[1].to_string();
+        let predicate = match selection {
+            Some(predicate) => predicate,
+            None => {
+                return match self.modify(|storage| storage.delete_all_from(&schema_name, &table_name))? {
+                    Ok(records_number) => Ok(Ok(QueryEvent::RecordsDeleted(records_number))),
+                    Err(OperationOnTableError::SchemaDoesNotExist) => {
+                        Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                    }
+                    Err(OperationOnTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
+                        schema_name + "." + table_name.as_str(),
+                    ))),
+                    Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)) => {
+                        Ok(Err(QueryError::column_does_not_exist(non_existing_columns)))
+                    }
+                    _ => unimplemented!(),
+                }
+            }
+        };
+
+        let key_conditions = self.column_conditions(&schema_name, &table_name, &predicate)?;
+        let (all_columns, records) =
+            match self
+                .storage()
+                .select_keyed_where(&schema_name, &table_name, key_conditions)?
+            {
+                Ok(selected) => selected,
+                Err(OperationOnTableError::SchemaDoesNotExist) => {
+                    return Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                }
+                Err(OperationOnTableError::TableDoesNotExist) => {
+                    return Ok(Err(QueryError::table_does_not_exist(
+                        schema_name + "." + table_name.as_str(),
+                    )))
+                }
+                Err(_) => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            };
+
+        let evaluator = Evaluator::new(self.session.date_style(), self.session.time_zone(), raw_sql_query)
+            .with_random(self.session.random())
+            .with_columns(&all_columns);
+        let mut keys = vec![];
+        let evaluated = evaluator.check(&predicate).and_then(|()| {
+            for (key, record) in records {
+                if evaluator.filter(&predicate, &record)? {
+                    keys.push(key);
+                }
+            }
+            Ok(())
+        });
+        if let Err(error) = evaluated {
+            return Ok(Err(error));
+        }
+
+        match self.modify(|storage| storage.delete_rows(&schema_name, &table_name, keys))? {
+            Ok(records_number) => Ok(Ok(QueryEvent::RecordsDeleted(records_number))),
+            Err(OperationOnTableError::SchemaDoesNotExist) => Ok(Err(QueryError::schema_does_not_exist(schema_name))),
+            Err(OperationOnTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
+                schema_name + "." + table_name.as_str(),
+            ))),
+            _ => unimplemented!(),
+        }
+    }
+
+    // all tables have to exist before any of them is truncated
+    fn truncate(&mut self, names: Vec<String>) -> SystemResult<QueryResult> {
+        let tables = names
+            .iter()
+            .map(|name| qualified_name(&self.session, name))
+            .collect::<Vec<(String, String)>>();
+        for (schema_name, table_name) in tables.iter() {
+            match self.storage().table_names(schema_name)? {
+                Ok(table_names) if table_names.contains(table_name) => {}
+                Ok(_) => {
+                    return Ok(Err(QueryError::table_does_not_exist(
+                        schema_name.to_owned() + "." + table_name.as_str(),
+                    )))
+                }
+                Err(SchemaDoesNotExist) => return Ok(Err(QueryError::schema_does_not_exist(schema_name.to_owned()))),
+            }
+        }
+        for (schema_name, table_name) in tables {
+            match self.modify(|storage| storage.truncate(&schema_name, &table_name))? {
+                Ok(()) => {}
+                Err(OperationOnTableError::SchemaDoesNotExist) => {
+                    return Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                }
+                Err(OperationOnTableError::TableDoesNotExist) => {
+                    return Ok(Err(QueryError::table_does_not_exist(
+                        schema_name + "." + table_name.as_str(),
+                    )))
+                }
+                _ => unimplemented!(),
+            }
+        }
+        Ok(Ok(QueryEvent::TableTruncated))
+    }
+
+    fn select_series(
+        &self,
+        projection: &[sqlparser::ast::SelectItem],
+        args: &[sqlparser::ast::Expr],
+        limits: &RowLimits,
+        raw_sql_query: &str,
+    ) -> SystemResult<QueryResult> {
+        let series = match args
+            .iter()
+            .map(integer_argument)
+            .collect::<Option<Vec<i64>>>()
+            .as_deref()
+        {
+            Some([_start, _stop, 0]) => return Ok(Err(QueryError::zero_step_size())),
+            Some([start, stop, step]) => Series::new(*start, *stop, *step),
+            Some([start, stop]) => Series::new(*start, *stop, 1),
+            _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+        };
+        for item in projection {
+            match item {
+                sqlparser::ast::SelectItem::Wildcard => {}
+                sqlparser::ast::SelectItem::UnnamedExpr(sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident {
+                    value,
+                    ..
+                })) if value.to_lowercase() == GENERATE_SERIES => {}
+                sqlparser::ast::SelectItem::UnnamedExpr(sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident {
+                    value,
+                    ..
+                })) => return Ok(Err(QueryError::column_does_not_exist(vec![value.clone()]))),
+                _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            }
+        }
+        // every selected column is the same single column of the series
+        let column_names = vec![GENERATE_SERIES.to_owned(); projection.len()];
+        let columns = vec![(GENERATE_SERIES.to_owned(), series.sql_type())];
+        let records = series.into_records(1);
+        let (columns, records) = match self.select_rows(columns, records, None, limits, &column_names, raw_sql_query)? {
+            Ok(selected) => selected,
+            Err(error) => return Ok(Err(error)),
+        };
+        if self.stream_results {
+            Ok(Ok(QueryEvent::RecordsStreamed(columns, RecordStream::new(records))))
+        } else {
+            let records = records.collect::<SystemResult<_>>()?;
+            Ok(Ok(QueryEvent::RecordsSelected((columns, records))))
+        }
+    }
+
+    // number of rows to skip and to return and keys to sort them by before;
+    // `LIMIT ALL` and `LIMIT NULL` do not limit rows
+    fn row_limits(
+        &self,
+        order_by: Vec<sqlparser::ast::OrderByExpr>,
+        limit: Option<&sqlparser::ast::Expr>,
+        offset: Option<&sqlparser::ast::Expr>,
+        raw_sql_query: &str,
+    ) -> std::result::Result<RowLimits, QueryError> {
+        let evaluator = Evaluator::new(self.session.date_style(), self.session.time_zone(), raw_sql_query)
+            .with_random(self.session.random());
+        let offset = match offset {
+            Some(expr) => evaluator.row_count(expr, "OFFSET")?.unwrap_or(0),
+            None => 0,
+        };
+        if offset < 0 {
+            return Err(QueryError::invalid_row_count_in_result_offset_clause());
+        }
+        let limit = match limit {
+            Some(expr) => evaluator.row_count(expr, "LIMIT")?,
+            None => None,
+        };
+        if limit.map(|limit| limit < 0).unwrap_or(false) {
+            return Err(QueryError::invalid_row_count_in_limit_clause());
+        }
+        Ok(RowLimits {
+            order_by,
+            offset: offset as usize,
+            limit: limit.map(|limit| limit as usize),
+        })
+    }
+
+    // rows are filtered and sorted before they are sent, so a failed evaluation
+    // does not leave a client with a part of the result. Rows are not read any
+    // further once the ones that LIMIT returns are found
+    fn select_rows(
+        &self,
+        columns: Vec<(String, SqlType)>,
+        records: RecordCursor,
+        selection: Option<&sqlparser::ast::Expr>,
+        limits: &RowLimits,
+        column_names: &[String],
+        raw_sql_query: &str,
+    ) -> SystemResult<std::result::Result<StreamedProjection, QueryError>> {
+        let mut indexes = vec![];
+        let mut non_existing_columns = vec![];
+        for column_name in column_names {
+            match columns.iter().position(|(name, _sql_type)| name == column_name) {
+                Some(index) => indexes.push(index),
+                None => non_existing_columns.push(column_name.clone()),
+            }
+        }
+        if !non_existing_columns.is_empty() {
+            return Ok(Err(QueryError::column_does_not_exist(non_existing_columns)));
+        }
+        let keys = match limits.positioned(column_names) {
+            Ok(RowLimits { order_by, .. }) => order_by,
+            Err(error) => return Ok(Err(error)),
+        };
+        let evaluator = Evaluator::new(self.session.date_style(), self.session.time_zone(), raw_sql_query)
+            .with_columns(&columns)
+            .with_random(self.session.random());
+        for expr in selection.into_iter().chain(keys.iter().map(|key| &key.expr)) {
+            if let Err(error) = evaluator.check(expr) {
+                return Ok(Err(error));
+            }
+        }
+        let needed = limits.limit.map(|limit| limits.offset + limit);
+        let records: RecordCursor = if !keys.is_empty() {
+            let mut sorter = Sorter::new(
+                self.session.work_mem(),
+                self.temp.clone(),
+                self.session.temp_file_limit(),
+            )
+            .with_bound(needed);
+            for record in records {
+                let record = record?;
+                if let Some(predicate) = selection {
+                    match evaluator.filter(predicate, &record) {
+                        Ok(true) => {}
+                        Ok(false) => continue,
+                        Err(error) => return Ok(Err(error)),
+                    }
+                }
+                let mut key = vec![];
+                for sqlparser::ast::OrderByExpr { expr, asc, nulls_first } in &keys {
+                    let descending = *asc == Some(false);
+                    match evaluator.row_value(expr, &record) {
+                        Ok(datum) => encode_key(&datum, descending, nulls_first.unwrap_or(descending), &mut key),
+                        Err(error) => return Ok(Err(error)),
+                    }
+                }
+                if let Err(TempFileLimitExceeded(limit)) = sorter.push(key, record)? {
+                    return Ok(Err(QueryError::temp_file_limit_exceeded(limit)));
+                }
+            }
+            sorter.finish()?
+        } else if let Some(predicate) = selection {
+            let mut rows = vec![];
+            for record in records {
+                if needed == Some(rows.len()) {
+                    break;
+                }
+                let record = record?;
+                match evaluator.filter(predicate, &record) {
+                    Ok(true) => rows.push(record),
+                    Ok(false) => {}
+                    Err(error) => return Ok(Err(error)),
+                }
+            }
+            Box::new(rows.into_iter().map(Ok))
+        } else {
+            records
+        };
+        let records = records.skip(limits.offset);
+        let records: RecordCursor = match limits.limit {
+            Some(limit) => Box::new(records.take(limit)),
+            None => Box::new(records),
+        };
+        let description = indexes.iter().map(|index| columns[*index].clone()).collect();
+        let records =
+            records.map(move |record| record.map(|row| indexes.iter().map(|index| row[*index].clone()).collect()));
+        Ok(Ok((description, Box::new(records))))
+    }
+
+    // rows are filtered and put into groups which HAVING filters, items of
+    // select are evaluated with values of a group and of its aggregates
+    fn select_groups(
+        &self,
+        columns: Vec<(String, SqlType)>,
+        records: RecordCursor,
+        select: &sqlparser::ast::Select,
+        limits: &RowLimits,
+        raw_sql_query: &str,
+    ) -> SystemResult<QueryResult> {
+        let sqlparser::ast::Select {
+            projection,
+            selection,
+            group_by,
+            having,
+            ..
+        } = select;
+        let mut items = vec![];
+        for item in projection {
+            match item {
+                sqlparser::ast::SelectItem::Wildcard => items.extend(columns.iter().map(|(name, _sql_type)| {
+                    (
+                        name.clone(),
+                        sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident::new(name.clone())),
+                    )
+                })),
+                sqlparser::ast::SelectItem::UnnamedExpr(expr) => match expr {
+                    sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident { value, .. }) => {
+                        items.push((value.clone(), expr.clone()))
+                    }
+                    expr => items.push((expression::column_name(expr), expr.clone())),
+                },
+                sqlparser::ast::SelectItem::ExprWithAlias { expr, alias } => {
+                    items.push((alias.value.clone(), expr.clone()))
+                }
+                _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            }
+        }
+        // positions refer to select items, names in ORDER BY may refer to their aliases
+        let item = |expr: &sqlparser::ast::Expr, clause: &str| match expr {
+            sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(position)) => position
+                .parse::<usize>()
+                .ok()
+                .and_then(|position| position.checked_sub(1))
+                .and_then(|index| items.get(index))
+                .map(|(_name, expr)| expr.clone())
+                .ok_or_else(|| QueryError::invalid_column_reference(clause.to_owned(), position.clone())),
+            sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident { value, .. }) if clause == "ORDER BY" => Ok(items
+                .iter()
+                .find(|(name, _expr)| name == value)
+                .map(|(_name, expr)| expr.clone())
+                .unwrap_or_else(|| expr.clone())),
+            expr => Ok(expr.clone()),
+        };
+        let group_by = match group_by
+            .iter()
+            .map(|expr| item(expr, "GROUP BY"))
+            .collect::<std::result::Result<Vec<_>, _>>()
+        {
+            Ok(group_by) => group_by,
+            Err(error) => return Ok(Err(error)),
+        };
+        let order_by = match limits
+            .order_by
+            .iter()
+            .map(|sqlparser::ast::OrderByExpr { expr, asc, nulls_first }| {
+                let descending = *asc == Some(false);
+                item(expr, "ORDER BY").map(|expr| (expr, descending, nulls_first.unwrap_or(descending)))
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+        {
+            Ok(order_by) => order_by,
+            Err(error) => return Ok(Err(error)),
+        };
+        let evaluator = Evaluator::new(self.session.date_style(), self.session.time_zone(), raw_sql_query)
+            .with_columns(&columns)
+            .with_random(self.session.random());
+        let exprs = items
+            .iter()
+            .map(|(_name, expr)| expr)
+            .chain(having.iter())
+            .chain(order_by.iter().map(|(expr, _descending, _nulls_first)| expr));
+        let mut calls = vec![];
+        for expr in selection.iter().chain(group_by.iter()).chain(exprs.clone()) {
+            if let Err(error) = evaluator.check(expr) {
+                return Ok(Err(error));
+            }
+        }
+        for expr in exprs {
+            if let Err(error) = aggregate::collect_calls(expr, &mut calls, raw_sql_query) {
+                return Ok(Err(error));
+            }
+        }
+        if group_by.iter().any(aggregate::contains_aggregate) {
+            return Ok(Err(QueryError::aggregate_not_allowed("GROUP BY".to_owned())));
+        }
+        let grouped = |expr: &sqlparser::ast::Expr| aggregate::grouped(expr, &group_by, &calls);
+        let (outputs, having, keys) = match (
+            items
+                .iter()
+                .map(|(_name, expr)| grouped(expr))
+                .collect::<std::result::Result<Vec<_>, _>>(),
+            having.as_ref().map(grouped).transpose(),
+            order_by
+                .iter()
+                .map(|(expr, descending, nulls_first)| grouped(expr).map(|expr| (expr, *descending, *nulls_first)))
+                .collect::<std::result::Result<Vec<_>, _>>(),
+        ) {
+            (Ok(outputs), Ok(having), Ok(keys)) => (outputs, having, keys),
+            (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => return Ok(Err(error)),
+        };
+        // types of grouped values are known from the columns they are computed of
+        let column_type = |expr: &sqlparser::ast::Expr| match expr {
+            sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident { value, .. }) => columns
+                .iter()
+                .find(|(name, _sql_type)| name == value)
+                .map(|(_name, sql_type)| *sql_type),
+            _ => None,
+        };
+        let mut grouped_columns = vec![];
+        for (index, expr) in group_by.iter().enumerate() {
+            grouped_columns.push((aggregate::group_column(index), column_type(expr)));
+        }
+        for (index, call) in calls.iter().enumerate() {
+            let sql_type = call.sql_type(call.arg().and_then(column_type));
+            grouped_columns.push((aggregate::aggregate_column(index), sql_type));
+        }
+
+        let mut aggregate = Aggregate::new(&group_by, &calls);
+        for record in records {
+            let record = record?;
+            if let Some(predicate) = selection {
+                match evaluator.filter(predicate, &record) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(error) => return Ok(Err(error)),
+                }
+            }
+            if let Err(error) = aggregate.add(&evaluator, &record) {
+                return Ok(Err(error));
+            }
+        }
+        let groups = match aggregate.finish() {
+            Ok(groups) => groups,
+            Err(error) => return Ok(Err(error)),
+        };
+
+        let mut types: Vec<Option<SqlType>> = outputs
+            .iter()
+            .map(|expr| match expr {
+                sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident { value, .. }) => grouped_columns
+                    .iter()
+                    .find(|(name, _sql_type)| name == value)
+                    .and_then(|(_name, sql_type)| *sql_type),
+                _ => None,
+            })
+            .collect();
+        let grouped_columns: Vec<(String, SqlType)> = grouped_columns
+            .into_iter()
+            .map(|(name, sql_type)| (name, sql_type.unwrap_or(SqlType::VarChar(255))))
+            .collect();
+        let evaluator = Evaluator::new(self.session.date_style(), self.session.time_zone(), raw_sql_query)
+            .with_columns(&grouped_columns)
+            .with_random(self.session.random());
+        let mut rows = vec![];
+        for group in groups {
+            if let Some(having) = &having {
+                match evaluator.filter(having, &group) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(error) => return Ok(Err(error)),
+                }
+            }
+            let mut key = vec![];
+            for (expr, descending, nulls_first) in &keys {
+                match evaluator.row_value(expr, &group) {
+                    Ok(datum) => encode_key(&datum, *descending, *nulls_first, &mut key),
+                    Err(error) => return Ok(Err(error)),
+                }
+            }
+            let mut values = vec![];
+            for (expr, sql_type) in outputs.iter().zip(types.iter_mut()) {
+                let datum = match evaluator.row_value(expr, &group) {
+                    Ok(datum) => datum,
+                    Err(error) => return Ok(Err(error)),
+                };
+                if sql_type.is_none() && datum != Datum::Null {
+                    *sql_type = Some(datum.sql_type());
+                }
+                values.push(evaluator.output(datum));
+            }
+            rows.push((key, values));
+        }
+        // groups are few enough to be sorted in memory
+        rows.sort_by(|(left, _), (right, _)| left.cmp(right));
+        let rows = rows
+            .into_iter()
+            .map(|(_key, values)| values)
+            .skip(limits.offset)
+            .take(limits.limit.unwrap_or(usize::MAX))
+            .collect();
+        let description = items
+            .into_iter()
+            .zip(types)
+            .map(|((name, _expr), sql_type)| (name, sql_type.unwrap_or(SqlType::VarChar(255))))
+            .collect();
+        Ok(Ok(QueryEvent::RecordsSelected((description, rows))))
+    }
+
+    // relations that are kept in memory are sorted and limited as a whole
+    fn select_projection(
+        &self,
+        projection: Projection,
+        limits: &RowLimits,
+        raw_sql_query: &str,
+    ) -> SystemResult<QueryResult> {
+        let (columns, rows) = projection;
+        let column_names = columns
+            .iter()
+            .map(|(name, _sql_type)| name.clone())
+            .collect::<Vec<String>>();
+        match self.select_rows(
+            columns,
+            Box::new(rows.into_iter().map(Ok)),
+            None,
+            limits,
+            &column_names,
+            raw_sql_query,
+        )? {
+            Ok((columns, records)) => Ok(Ok(QueryEvent::RecordsSelected((
+                columns,
+                records.collect::<SystemResult<_>>()?,
+            )))),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    // storage looks rows up by conditions on columns that it has indexes on
+    fn column_conditions(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        predicate: &sqlparser::ast::Expr,
+    ) -> SystemResult<Vec<(String, Comparison, String)>> {
+        let columns: Vec<String> = self
+            .storage()
+            .table_columns(schema_name, table_name)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, _sql_type)| name)
+            .collect();
+        Ok(expression::column_conditions(predicate, &columns))
+    }
+
+    fn select_system_relation(
+        &self,
+        relation: SystemRelation,
+        projection: &[sqlparser::ast::SelectItem],
+        selection: Option<&sqlparser::ast::Expr>,
+        limits: &RowLimits,
+        raw_sql_query: &str,
+    ) -> SystemResult<QueryResult> {
+        let mut column_names = vec![];
+        for item in projection {
+            match item {
+                sqlparser::ast::SelectItem::Wildcard => {
+                    column_names.extend(relation.columns().into_iter().map(|(name, _sql_type)| name))
+                }
+                sqlparser::ast::SelectItem::UnnamedExpr(sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident {
+                    value,
+                    ..
+                })) => column_names.push(value.clone()),
+                _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+            }
+        }
+        // positions in ORDER BY refer to selected columns, rows are projected
+        // after they are filtered and sorted by any of the columns
+        let selected = column_names
+            .iter()
+            .map(|name| name.to_lowercase())
+            .collect::<Vec<String>>();
+        let limits = match limits.positioned(&selected) {
+            Ok(limits) => limits,
+            Err(error) => return Ok(Err(error)),
+        };
+        let (columns, rows) = relation.projection(&self.storage(), &self.session)?;
+        let all_columns = columns
+            .iter()
+            .map(|(name, _sql_type)| name.clone())
+            .collect::<Vec<String>>();
+        let (columns, rows) = match self.select_rows(
+            columns,
+            Box::new(rows.into_iter().map(Ok)),
+            selection,
+            &limits,
+            &all_columns,
+            raw_sql_query,
+        )? {
+            Ok((columns, records)) => (columns, records.collect::<SystemResult<Vec<Vec<String>>>>()?),
+            Err(error) => return Ok(Err(error)),
+        };
+        match information_schema::select((columns, rows), &column_names) {
+            Ok(projection) => Ok(Ok(QueryEvent::RecordsSelected(projection))),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    fn select_functions(
+        &mut self,
+        projection: &[sqlparser::ast::SelectItem],
+        limits: &RowLimits,
+        raw_sql_query: &str,
+    ) -> SystemResult<QueryResult> {
+        let mut columns = vec![];
+        let mut values = vec![];
+        for item in projection {
+            let (name, args) =
+                match item {
+                    sqlparser::ast::SelectItem::UnnamedExpr(sqlparser::ast::Expr::Function(
+                        sqlparser::ast::Function { name, args, .. },
+                    )) => (name.to_string().to_lowercase(), args.as_slice()),
+                    // functions that are called without parentheses are parsed as identifiers
+                    sqlparser::ast::SelectItem::UnnamedExpr(sqlparser::ast::Expr::Identifier(
+                        sqlparser::ast::Ident { value, .. },
+                    )) => (value.to_lowercase(), &[][..]),
+                    sqlparser::ast::SelectItem::UnnamedExpr(expr) => match self.evaluate(expr, raw_sql_query) {
+                        Ok((column, value)) => {
+                            columns.push(column);
+                            values.push(value);
+                            continue;
+                        }
+                        Err(error) => return Ok(Err(error)),
+                    },
+                    _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+                };
+            let (sql_type, value) = match (name.as_str(), args) {
+                // there is a single database, so its name is not checked
+                ("pg_database_size", [])
+                | ("pg_database_size", [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(_))]) => {
+                    (SqlType::BigInt, self.storage().database_size()?.to_string())
+                }
+                (
+                    "pg_table_size",
+                    [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(full_table_name))],
+                )
+                | (
+                    "pg_indexes_size",
+                    [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(full_table_name))],
+                ) => {
+                    let (schema_name, table_name) = match full_table_name.split('.').collect::<Vec<&str>>().as_slice() {
+                        [schema_name, table_name] => (
+                            self.session.schema_name(schema_name.to_string()),
+                            table_name.to_string(),
+                        ),
+                        _ => return Ok(Err(QueryError::table_does_not_exist(full_table_name.to_owned()))),
+                    };
+                    let size = if name == "pg_indexes_size" {
+                        self.storage().indexes_size(&schema_name, &table_name)?
+                    } else {
+                        self.storage().table_size(&schema_name, &table_name)?
+                    };
+                    let size = match size {
+                        Ok(size) => size,
+                        Err(OperationOnTableError::SchemaDoesNotExist) => {
+                            return Ok(Err(QueryError::schema_does_not_exist(schema_name)))
+                        }
+                        Err(_) => return Ok(Err(QueryError::table_does_not_exist(full_table_name.to_owned()))),
+                    };
+                    (SqlType::BigInt, size.to_string())
+                }
+                ("version", []) => (
+                    SqlType::VarChar(255),
+                    format!(
+                        "PostgreSQL {} on database {}",
+                        SERVER_VERSION,
+                        env!("CARGO_PKG_VERSION")
+                    ),
+                ),
+                ("current_user", []) | ("session_user", []) => {
+                    (SqlType::VarChar(63), self.session.user_name().to_owned())
+                }
+                ("current_database", []) => (SqlType::VarChar(63), self.session.database_name().to_owned()),
+                ("current_schema", []) => (SqlType::VarChar(63), self.session.current_schema().to_owned()),
+                ("pg_backend_pid", []) => (SqlType::Integer, self.session.id().to_string()),
+                (
+                    "current_setting",
+                    [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(parameter))],
+                ) => match self.current_setting(parameter) {
+                    Ok(value) => (SqlType::VarChar(255), value),
+                    Err(error) => return Ok(Err(error)),
+                },
+                (
+                    "set_config",
+                    [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(parameter)), sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(value)), sqlparser::ast::Expr::Value(sqlparser::ast::Value::Boolean(is_local))],
+                ) => {
+                    // outside of a transaction block local value lasts only until the query ends
+                    let set = match (is_local, &self.transaction) {
+                        (false, _) => self.session.set(parameter, value),
+                        (true, Some(_transaction)) => self.session.set_local(parameter, value),
+                        (true, None) => Ok(()),
+                    };
+                    if let Err(error) = set {
+                        return Ok(Err(error));
+                    }
+                    (SqlType::VarChar(255), value.to_owned())
+                }
+                (
+                    "nextval",
+                    [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(sequence_name))],
+                ) => {
+                    let (schema_name, sequence_name) = qualified_name(&self.session, sequence_name);
+                    match self.next_value(schema_name, sequence_name)? {
+                        Ok(value) => (SqlType::BigInt, value.to_string()),
+                        Err(error) => return Ok(Err(error)),
+                    }
+                }
+                (
+                    "currval",
+                    [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(sequence_name))],
+                ) => {
+                    let (schema_name, sequence_name) = qualified_name(&self.session, sequence_name);
+                    let full_name = format!("{}.{}", schema_name, sequence_name);
+                    match self.session.sequences().current_value(&full_name) {
+                        Some(value) => (SqlType::BigInt, value.to_string()),
+                        None => return Ok(Err(QueryError::sequence_value_not_defined(Some(full_name)))),
+                    }
+                }
+                ("lastval", []) => match self.session.sequences().last_value() {
+                    Some(value) => (SqlType::BigInt, value.to_string()),
+                    None => return Ok(Err(QueryError::sequence_value_not_defined(None))),
+                },
+                (
+                    "setval",
+                    [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(sequence_name)), sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(value)), rest @ ..],
+                ) => {
+                    let is_called = match rest {
+                        [] => true,
+                        [sqlparser::ast::Expr::Value(sqlparser::ast::Value::Boolean(is_called))] => *is_called,
+                        _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+                    };
+                    let value = match value.parse::<i64>() {
+                        Ok(value) => value,
+                        Err(_) => return Ok(Err(QueryError::invalid_parameter_value(name.clone(), value.to_owned()))),
+                    };
+                    let (schema_name, sequence_name) = qualified_name(&self.session, sequence_name);
+                    let full_name = format!("{}.{}", schema_name, sequence_name);
+                    let set = self
+                        .storage_mut()
+                        .set_sequence_value(&schema_name, &sequence_name, value, is_called)?;
+                    if let Err(error) = set {
+                        return Ok(Err(sequence_error(error, schema_name, full_name)));
+                    }
+                    // values reserved before are not valid anymore
+                    self.session.sequences().reset(&full_name);
+                    if is_called {
+                        self.session.sequences().set_current(&full_name, value);
+                    }
+                    (SqlType::BigInt, value.to_string())
+                }
+                // functions of date and time values
+                _ => match item {
+                    sqlparser::ast::SelectItem::UnnamedExpr(expr) => match self.evaluate(expr, raw_sql_query) {
+                        Ok(((_name, sql_type), value)) => (sql_type, value),
+                        Err(error) => return Ok(Err(error)),
+                    },
+                    _ => return Ok(Err(QueryError::not_supported_operation(raw_sql_query.to_owned()))),
+                },
+            };
+            columns.push((name, sql_type));
+            values.push(value);
+        }
+        // the only row is the same whatever it is sorted by
+        let rows = vec![values]
+            .into_iter()
+            .skip(limits.offset)
+            .take(limits.limit.unwrap_or(usize::MAX))
+            .collect();
+        Ok(Ok(QueryEvent::RecordsSelected((columns, rows))))
+    }
+
+    // value of an expression along with the column it is shown in
+    fn evaluate(
+        &self,
+        expr: &sqlparser::ast::Expr,
+        raw_sql_query: &str,
+    ) -> std::result::Result<((String, SqlType), String), QueryError> {
+        let evaluator = Evaluator::new(self.session.date_style(), self.session.time_zone(), raw_sql_query)
+            .with_random(self.session.random());
+        let datum = evaluator.evaluate(expr)?;
+        let column = (expression::column_name(expr), datum.sql_type());
+        Ok((column, evaluator.output(datum)))
+    }
+
+    // local values are dropped when transaction block ends, they have no effect outside of it
+    fn set_variable(&mut self, name: &str, value: &str, local: bool) -> QueryResult {
+        let set = if !local {
+            self.session.set(name, value)
+        } else if self.transaction.is_some() {
+            self.session.set_local(name, value)
+        } else {
+            self.notices
+                .push(QueryError::no_active_transaction("SET LOCAL".to_owned()));
+            Ok(())
+        };
+        set.map(|()| QueryEvent::VariableSet)
+    }
+
+    fn current_setting(&self, parameter: &str) -> std::result::Result<String, QueryError> {
+        match self.session.setting(parameter) {
+            Some(value) => Ok(value.to_owned()),
+            None => Err(QueryError::unrecognized_parameter(parameter.to_owned())),
+        }
+    }
+
+    // functions that can give values of inserted or updated columns
+    fn function_value(
+        &self,
+        function: &sqlparser::ast::Function,
+        raw_sql_query: &str,
+    ) -> std::result::Result<String, QueryError> {
+        match (
+            function.name.to_string().to_lowercase().as_str(),
+            function.args.as_slice(),
+        ) {
+            (
+                "current_setting",
+                [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(parameter))],
+            ) => self.current_setting(parameter),
+            _ => Err(QueryError::not_supported_operation(raw_sql_query.to_owned())),
+        }
+    }
+
+    // values are taken from the block that session reserved before, a new block
+    // is reserved once it is used up
+    fn next_value(
+        &mut self,
+        schema_name: String,
+        sequence_name: String,
+    ) -> SystemResult<std::result::Result<i64, QueryError>> {
+        let full_name = format!("{}.{}", schema_name, sequence_name);
+        if let Some(value) = self.session.sequences().next_value(&full_name) {
+            return Ok(Ok(value));
+        }
+        let reserved = self
+            .storage_mut()
+            .reserve_sequence_values(&schema_name, &sequence_name)?;
+        match reserved {
+            Ok(block) => self.session.sequences().reserve(&full_name, block),
+            Err(error) => return Ok(Err(sequence_error(error, schema_name, full_name))),
+        }
+        match self.session.sequences().next_value(&full_name) {
+            Some(value) => Ok(Ok(value)),
+            None => Ok(Err(QueryError::sequence_limit_reached(full_name))),
+        }
+    }
+
+    fn default_value(&mut self, default: &DefaultValue) -> SystemResult<std::result::Result<String, QueryError>> {
+        match default {
+            DefaultValue::Constant(value) => Ok(Ok(value.clone())),
+            DefaultValue::NextValue {
+                schema_name,
+                sequence_name,
+                ..
+            } => Ok(self
+                .next_value(schema_name.clone(), sequence_name.clone())?
+                .map(|value| value.to_string())),
+        }
+    }
+
+    fn execute_sequence_statement(&mut self, statement: SequenceStatement) -> SystemResult<QueryResult> {
+        match statement {
+            SequenceStatement::Create {
+                name,
+                if_not_exists,
+                definition,
+            } => {
+                let (schema_name, sequence_name) = qualified_name(&self.session, &name);
+                if schema_name == self.session.temp_schema() {
+                    let _exists = self.storage_mut().create_schema(&schema_name)?;
+                }
+                let created = self
+                    .storage_mut()
+                    .create_sequence(&schema_name, &sequence_name, definition)?;
+                match created {
+                    Ok(()) => Ok(Ok(QueryEvent::SequenceCreated)),
+                    Err(SequenceError::SequenceAlreadyExists) if if_not_exists => {
+                        self.notices
+                            .push(QueryError::sequence_already_exists_skipping(sequence_name));
+                        Ok(Ok(QueryEvent::SequenceCreated))
+                    }
+                    Err(error) => Ok(Err(sequence_error(error, schema_name, sequence_name))),
+                }
+            }
+            SequenceStatement::Drop { name, if_exists } => {
+                let (schema_name, sequence_name) = qualified_name(&self.session, &name);
+                let full_name = format!("{}.{}", schema_name, sequence_name);
+                let dropped = self.storage_mut().drop_sequence(&schema_name, &sequence_name)?;
+                match dropped {
+                    Ok(()) => {
+                        // values reserved before can't be used by a sequence with the same name
+                        self.session.sequences().reset(&full_name);
+                        Ok(Ok(QueryEvent::SequenceDropped))
+                    }
+                    Err(SequenceError::SequenceDoesNotExist) if if_exists => {
+                        self.notices
+                            .push(QueryError::sequence_does_not_exist_skipping(full_name));
+                        Ok(Ok(QueryEvent::SequenceDropped))
+                    }
+                    Err(SequenceError::SchemaDoesNotExist) if if_exists => {
+                        self.notices
+                            .push(QueryError::schema_does_not_exist_skipping(schema_name));
+                        Ok(Ok(QueryEvent::SequenceDropped))
+                    }
+                    Err(error) => Ok(Err(sequence_error(error, schema_name, full_name))),
+                }
+            }
+            SequenceStatement::Alter {
+                name,
+                if_exists,
+                alterations,
+            } => {
+                let (schema_name, sequence_name) = qualified_name(&self.session, &name);
+                let full_name = format!("{}.{}", schema_name, sequence_name);
+                for alteration in alterations {
+                    let altered = self
+                        .storage_mut()
+                        .alter_sequence(&schema_name, &sequence_name, alteration)?;
+                    match altered {
+                        Ok(()) => {}
+                        Err(SequenceError::SequenceDoesNotExist) if if_exists => {
+                            self.notices
+                                .push(QueryError::sequence_does_not_exist_skipping(full_name));
+                            return Ok(Ok(QueryEvent::SequenceAltered));
+                        }
+                        Err(SequenceError::SchemaDoesNotExist) if if_exists => {
+                            self.notices
+                                .push(QueryError::schema_does_not_exist_skipping(schema_name));
+                            return Ok(Ok(QueryEvent::SequenceAltered));
+                        }
+                        Err(error) => return Ok(Err(sequence_error(error, schema_name, full_name))),
+                    }
+                }
+                // values reserved before do not follow the altered sequence
+                self.session.sequences().reset(&full_name);
+                Ok(Ok(QueryEvent::SequenceAltered))
+            }
+        }
+    }
+
+    fn alter_table(&mut self, statement: AlterTable, raw_sql_query: &str) -> SystemResult<QueryResult> {
+        let (schema_name, table_name) = qualified_name(&self.session, &statement.name);
+        let alteration = match statement.action {
+            AlterTableAction::AddColumn(definition) => {
+                match added_column(&self.session, &schema_name, &table_name, &definition, raw_sql_query) {
+                    Ok(alteration) => alteration,
+                    Err(error) => return Ok(Err(error)),
+                }
+            }
+            AlterTableAction::DropColumn(column) => TableAlteration::DropColumn(column),
+            AlterTableAction::RenameColumn { from, to } => TableAlteration::RenameColumn { from, to },
+            AlterTableAction::RenameTo(new_name) => TableAlteration::RenameTo(new_name),
+        };
+        let new_name = match &alteration {
+            TableAlteration::RenameTo(new_name) => new_name.clone(),
+            _ => table_name.clone(),
+        };
+        let altered = self.storage_mut().alter_table(&schema_name, &table_name, alteration)?;
+        match altered {
+            Ok(()) => Ok(Ok(QueryEvent::TableAltered)),
+            Err(AlterTableError::SchemaDoesNotExist) => Ok(Err(QueryError::schema_does_not_exist(schema_name))),
+            Err(AlterTableError::TableDoesNotExist) => Ok(Err(QueryError::table_does_not_exist(
+                schema_name + "." + table_name.as_str(),
+            ))),
+            Err(AlterTableError::TableAlreadyExists) => Ok(Err(QueryError::table_already_exists(new_name))),
+            Err(AlterTableError::ColumnAlreadyExists(column_name)) => {
+                Ok(Err(QueryError::column_already_exists(column_name, table_name)))
+            }
+            Err(AlterTableError::ColumnDoesNotExist(column_name)) => {
+                Ok(Err(QueryError::column_does_not_exist(vec![column_name])))
+            }
+            Err(AlterTableError::InvalidDefault(value)) => {
+                Ok(Err(QueryError::invalid_parameter_value("DEFAULT".to_owned(), value)))
+            }
+            Err(AlterTableError::NotNullViolation(column_name)) => Ok(Err(QueryError::not_null_violation(column_name))),
+            Err(AlterTableError::CheckViolation(constraint_name)) => {
+                Ok(Err(QueryError::check_violation(table_name, constraint_name)))
+            }
+        }
+    }
+
+    // sequences of serial columns are named after them, a number is appended
+    // to the name if a sequence with the same name already exists
+    fn create_owned_sequences(
+        &mut self,
+        constraints: &mut [TableConstraint],
+    ) -> SystemResult<std::result::Result<Vec<(String, String)>, QueryError>> {
+        let mut created = vec![];
+        for constraint in constraints.iter_mut() {
+            if let TableConstraint::Default(
+                _column,
+                DefaultValue::NextValue {
+                    schema_name,
+                    sequence_name,
+                    owned: true,
+                },
+            ) = constraint
+            {
+                let base_name = sequence_name.clone();
+                let mut suffix = 0;
+                loop {
+                    let result = self.storage_mut().create_sequence(
+                        schema_name,
+                        sequence_name,
+                        SequenceDefinition::default(),
+                    )?;
+                    match result {
+                        Ok(()) => break,
+                        Err(SequenceError::SequenceAlreadyExists) => {
+                            suffix += 1;
+                            *sequence_name = format!("{}{}", base_name, suffix);
+                        }
+                        Err(error) => {
+                            self.drop_sequences(created)?;
+                            let full_name = format!("{}.{}", schema_name, sequence_name);
+                            return Ok(Err(sequence_error(error, schema_name.clone(), full_name)));
+                        }
+                    }
+                }
+                created.push((schema_name.clone(), sequence_name.clone()));
+            }
+        }
+        Ok(Ok(created))
+    }
+
+    // timestamps are given in `DateStyle` format and in `TimeZone` of the session,
+    // storage keeps them in ISO format, values with time zone are kept in UTC
+    fn input_values(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        columns: &[String],
+        rows: Vec<Vec<String>>,
+    ) -> SystemResult<std::result::Result<Vec<Vec<String>>, QueryError>> {
+        // errors of tables that do not exist are reported by the statement
+        let all_columns = match self.storage().table_columns(schema_name, table_name)? {
+            Ok(all_columns) => all_columns,
+            Err(_) => return Ok(Ok(rows)),
+        };
+        if !datetime::has_datetimes(&all_columns) {
+            return Ok(Ok(rows));
+        }
+        let types: Vec<Option<SqlType>> = if columns.is_empty() {
+            all_columns.iter().map(|(_name, sql_type)| Some(*sql_type)).collect()
+        } else {
+            columns
+                .iter()
+                .map(|column| {
+                    all_columns
+                        .iter()
+                        .find(|(name, _sql_type)| name == column)
+                        .map(|(_name, sql_type)| *sql_type)
+                })
+                .collect()
+        };
+        let (style, zone) = (self.session.date_style(), self.session.time_zone());
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .zip(types.iter().chain(std::iter::repeat(&None)))
+                    .map(|(value, sql_type)| match sql_type {
+                        Some(sql_type) => datetime::input(value, *sql_type, style, zone),
+                        None => Ok(value),
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(rows)
+    }
+
+    // timestamps are sent in `DateStyle` format and in `TimeZone` of the session
+    fn output_values(&self, columns: &[(String, SqlType)], records: RecordCursor) -> RecordCursor {
+        if !datetime::has_datetimes(columns) {
+            return records;
+        }
+        let (style, zone) = (self.session.date_style(), self.session.time_zone());
+        let types: Vec<SqlType> = columns.iter().map(|(_name, sql_type)| *sql_type).collect();
+        Box::new(records.map(move |record| {
+            record.map(|values| {
+                values
+                    .into_iter()
+                    .zip(types.iter())
+                    .map(|(value, sql_type)| datetime::output(value, *sql_type, style, zone))
+                    .collect()
+            })
+        }))
+    }
+
+    fn drop_sequences(&mut self, sequences: Vec<(String, String)>) -> SystemResult<()> {
+        for (schema_name, sequence_name) in sequences {
+            let _dropped = self.storage_mut().drop_sequence(&schema_name, &sequence_name)?;
+        }
+        Ok(())
+    }
+}
+
+// objects without schema name are looked up in the current schema
+fn qualified_name(session: &Session, object_name: &str) -> (String, String) {
+    match object_name.split('.').collect::<Vec<&str>>().as_slice() {
+        [schema_name, object_name] => (session.schema_name(schema_name.to_string()), object_name.to_string()),
+        _ => (session.current_schema().to_owned(), object_name.to_owned()),
+    }
+}
+
+// sql parser knows column definitions only as a part of `CREATE TABLE`, existing
+// rows can't take distinct values, so unique and serial columns are not supported
+fn added_column(
+    session: &Session,
+    schema_name: &str,
+    table_name: &str,
+    definition: &str,
+    raw_sql_query: &str,
+) -> std::result::Result<TableAlteration, QueryError> {
+    let column = match Parser::parse_sql(
+        &PostgreSqlDialect {},
+        &format!("create table {} ({})", table_name, definition),
+    ) {
+        Ok(mut statements) => match statements.pop() {
+            Some(sqlparser::ast::Statement::CreateTable {
+                mut columns,
+                constraints,
+                ..
+            }) if columns.len() == 1 && constraints.is_empty() => columns.remove(0),
+            _ => return Err(QueryError::not_supported_operation(raw_sql_query.to_owned())),
+        },
+        Err(ParserError::TokenizerError(message)) | Err(ParserError::ParserError(message)) => {
+            return Err(QueryError::syntax_error(message))
+        }
+    };
+    let constraints = table_constraints(
+        session,
+        schema_name,
+        table_name,
+        std::slice::from_ref(&column),
+        vec![],
+        raw_sql_query,
+    )?;
+    if constraints.iter().any(|constraint| {
+        matches!(
+            constraint,
+            TableConstraint::Unique(_) | TableConstraint::Default(_, DefaultValue::NextValue { .. })
+        )
+    }) {
+        return Err(QueryError::not_supported_operation(raw_sql_query.to_owned()));
+    }
+    Ok(TableAlteration::AddColumn {
+        name: column.name.to_string(),
+        sql_type: column_type(&column.data_type),
+        constraints,
+    })
+}
+
+// columns of primary key can't be omitted
+fn table_constraints(
+    session: &Session,
+    schema_name: &str,
+    table_name: &str,
+    columns: &[sqlparser::ast::ColumnDef],
+    constraints: Vec<sqlparser::ast::TableConstraint>,
+    raw_sql_query: &str,
+) -> std::result::Result<Vec<TableConstraint>, QueryError> {
+    let mut table_constraints = vec![];
+    for column in columns {
+        let column_name = column.name.to_string();
+        if serial_type(&column.data_type).is_some() {
+            table_constraints.push(TableConstraint::NotNull(column_name.clone()));
+            table_constraints.push(TableConstraint::Default(
+                column_name.clone(),
+                DefaultValue::NextValue {
+                    schema_name: schema_name.to_owned(),
+                    sequence_name: format!("{}_{}_seq", table_name, column_name),
+                    owned: true,
+                },
+            ));
+        }
+        for option in column.options.iter() {
+            match &option.option {
+                sqlparser::ast::ColumnOption::Default(expr) => match default_value(session, expr) {
+                    Some(default) => table_constraints.push(TableConstraint::Default(column_name.clone(), default)),
+                    None => return Err(QueryError::not_supported_operation(raw_sql_query.to_owned())),
+                },
+                sqlparser::ast::ColumnOption::Unique { is_primary } => table_constraints.push(TableConstraint::Unique(
+                    unique_constraint(table_name, option.name.as_ref(), vec![column_name.clone()], *is_primary),
+                )),
+                sqlparser::ast::ColumnOption::NotNull => {
+                    table_constraints.push(TableConstraint::NotNull(column_name.clone()))
+                }
+                sqlparser::ast::ColumnOption::Check(expr) => {
+                    let name = match &option.name {
+                        Some(name) => name.to_string(),
+                        None => format!("{}_{}_check", table_name, column_name),
+                    };
+                    match check_constraints(&name, expr) {
+                        Some(checks) => table_constraints.extend(checks.into_iter().map(TableConstraint::Check)),
+                        None => return Err(QueryError::not_supported_operation(raw_sql_query.to_owned())),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    for constraint in constraints {
+        match constraint {
+            sqlparser::ast::TableConstraint::Unique {
+                name,
+                columns: constraint_columns,
+                is_primary,
+            } => table_constraints.push(TableConstraint::Unique(unique_constraint(
+                table_name,
+                name.as_ref(),
+                constraint_columns
+                    .into_iter()
+                    .map(|column| column.to_string())
+                    .collect(),
+                is_primary,
+            ))),
+            sqlparser::ast::TableConstraint::Check { name, expr } => {
+                let checks = match check_constraints("", &expr) {
+                    Some(checks) => checks,
+                    None => return Err(QueryError::not_supported_operation(raw_sql_query.to_owned())),
+                };
+                let name = match name {
+                    Some(name) => name.to_string(),
+                    None => format!("{}_{}_check", table_name, checks[0].column),
+                };
+                table_constraints.extend(checks.into_iter().map(|check| {
+                    TableConstraint::Check(CheckConstraint {
+                        name: name.clone(),
+                        ..check
+                    })
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    let mut primary_keys = 0;
+    let mut non_existing_columns = vec![];
+    let mut not_null_columns = vec![];
+    for constraint in table_constraints.iter() {
+        let constraint_columns = match constraint {
+            TableConstraint::Unique(unique) => {
+                if unique.is_primary {
+                    primary_keys += 1;
+                    not_null_columns.extend(unique.columns.iter().cloned());
+                }
+                unique.columns.clone()
+            }
+            TableConstraint::NotNull(column) => vec![column.clone()],
+            TableConstraint::Check(check) => vec![check.column.clone()],
+            TableConstraint::Default(column, _default) => vec![column.clone()],
+        };
+        for constraint_column in constraint_columns {
+            if columns
+                .iter()
+                .all(|column| column.name.to_string() != constraint_column)
+                && !non_existing_columns.contains(&constraint_column)
+            {
+                non_existing_columns.push(constraint_column);
+            }
+        }
+    }
+    if !non_existing_columns.is_empty() {
+        return Err(QueryError::column_does_not_exist(non_existing_columns));
+    }
+    if primary_keys > 1 {
+        return Err(QueryError::multiple_primary_keys(table_name.to_owned()));
+    }
+    table_constraints.extend(not_null_columns.into_iter().map(TableConstraint::NotNull));
+    Ok(table_constraints)
+}
+
+// only constants and next values of sequences are supported
+fn default_value(session: &Session, expr: &sqlparser::ast::Expr) -> Option<DefaultValue> {
+    match expr {
+        sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(value))
+        | sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(value)) => {
+            Some(DefaultValue::Constant(value.to_owned()))
+        }
+        sqlparser::ast::Expr::UnaryOp {
+            op: sqlparser::ast::UnaryOperator::Minus,
+            expr,
+        } => match &**expr {
+            sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(value)) => {
+                Some(DefaultValue::Constant("-".to_owned() + value.as_str()))
+            }
+            _ => None,
+        },
+        sqlparser::ast::Expr::Function(sqlparser::ast::Function { name, args, .. })
+            if name.to_string().eq_ignore_ascii_case("nextval") =>
+        {
+            match args.as_slice() {
+                [sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(name))] => {
+                    let (schema_name, sequence_name) = qualified_name(session, name);
+                    Some(DefaultValue::NextValue {
+                        schema_name,
+                        sequence_name,
+                        owned: false,
+                    })
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn column_type(data_type: &sqlparser::ast::DataType) -> SqlType {
+    match data_type {
+        sqlparser::ast::DataType::SmallInt => SqlType::SmallInt,
+        sqlparser::ast::DataType::Int => SqlType::Integer,
+        sqlparser::ast::DataType::BigInt => SqlType::BigInt,
+        sqlparser::ast::DataType::Char(len) => SqlType::Char(len.unwrap_or(255)),
+        sqlparser::ast::DataType::Varchar(len) => SqlType::VarChar(len.unwrap_or(255)),
+        sqlparser::ast::DataType::Timestamp => SqlType::Timestamp,
+        sqlparser::ast::DataType::Interval => SqlType::Interval,
+        // sql parser does not keep time zone of timestamp type, so it is rewritten before parsing
+        sqlparser::ast::DataType::Custom(name) if name.to_string().eq_ignore_ascii_case("timestamptz") => {
+            SqlType::TimestampWithTimeZone
+        }
+        data_type => match serial_type(data_type) {
+            Some(sql_type) => sql_type,
+            None => unimplemented!(),
+        },
+    }
+}
+
+// serial columns take next values of a sequence that is created along with the table
+fn serial_type(data_type: &sqlparser::ast::DataType) -> Option<SqlType> {
+    match data_type {
+        sqlparser::ast::DataType::Custom(name) => match name.to_string().to_lowercase().as_str() {
+            "smallserial" | "serial2" => Some(SqlType::SmallInt),
+            "serial" | "serial4" => Some(SqlType::Integer),
+            "bigserial" | "serial8" => Some(SqlType::BigInt),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// only comparisons of a column with a constant joined by AND are supported
+fn check_constraints(name: &str, expr: &sqlparser::ast::Expr) -> Option<Vec<CheckConstraint>> {
+    use sqlparser::ast::{BinaryOperator, Expr, UnaryOperator, Value};
+
+    fn constant(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Value(Value::Number(value)) | Expr::Value(Value::SingleQuotedString(value)) => Some(value.clone()),
+            Expr::UnaryOp {
+                op: UnaryOperator::Minus,
+                expr,
+            } => match &**expr {
+                Expr::Value(Value::Number(value)) => Some(format!("-{}", value)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    match expr {
+        Expr::Nested(expr) => check_constraints(name, expr),
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let mut checks = check_constraints(name, left)?;
+            checks.extend(check_constraints(name, right)?);
+            Some(checks)
+        }
+        Expr::BinaryOp { left, op, right } => {
+            let comparison = match op {
+                BinaryOperator::Eq => Comparison::Eq,
+                BinaryOperator::NotEq => Comparison::NotEq,
+                BinaryOperator::Lt => Comparison::Lt,
+                BinaryOperator::LtEq => Comparison::LtEq,
+                BinaryOperator::Gt => Comparison::Gt,
+                BinaryOperator::GtEq => Comparison::GtEq,
+                _ => return None,
+            };
+            let (column, comparison, value) = match (&**left, &**right) {
+                (Expr::Identifier(column), constant_expr) => (column, comparison, constant(constant_expr)?),
+                // constant is on the left side of the comparison
+                (constant_expr, Expr::Identifier(column)) => {
+                    let comparison = match comparison {
+                        Comparison::Lt => Comparison::Gt,
+                        Comparison::LtEq => Comparison::GtEq,
+                        Comparison::Gt => Comparison::Lt,
+                        Comparison::GtEq => Comparison::LtEq,
+                        comparison => comparison,
+                    };
+                    (column, comparison, constant(constant_expr)?)
+                }
+                _ => return None,
+            };
+            Some(vec![CheckConstraint {
+                name: name.to_owned(),
+                column: column.to_string(),
+                comparison,
+                value,
+            }])
+        }
+        _ => None,
+    }
+}
+
+// constraints without a name are named after the table and the columns like in PostgreSQL
+fn unique_constraint(
+    table_name: &str,
+    name: Option<&sqlparser::ast::Ident>,
+    columns: Vec<String>,
+    is_primary: bool,
+) -> UniqueConstraint {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None if is_primary => format!("{}_pkey", table_name),
+        None => format!("{}_{}_key", table_name, columns.join("_")),
+    };
+    UniqueConstraint {
+        name,
+        columns,
+        is_primary,
+    }
+}
+
+// session is reported as waiting for the lock while another session holds it
+// block that is not ended when session is gone is rolled back, otherwise it
+// would hold storage forever
+impl<P: BackendStorage> Drop for Handler<P> {
+    fn drop(&mut self) {
+        if matches!(&self.transaction, Some(transaction) if transaction.holds_storage()) {
+            if let Err(error) = self.storage_mut().revert() {
+                log::error!("transaction block can't be rolled back due to {:?}", error);
+            }
+        }
+    }
+}
+
+/// wait of a session for a transaction block of another session to end
+pub struct LockWait<P: BackendStorage> {
+    storage: Arc<RwLock<FrontendStorage<P>>>,
+    timeout: Option<Duration>,
+}
+
+impl<P: BackendStorage> LockWait<P> {
+    /// blocks until the transaction block is ended, `false` if lock_timeout
+    /// elapses first
+    pub fn wait(self) -> bool {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        block_ended(|| self.storage.read().unwrap(), deadline, || {}).is_some()
+    }
+}
+
+// storage that a transaction block holds is not accessed until the block is
+// ended. The block could wait for its client, so storage is released while
+// session waits. Storage is not returned if the block lasts past the deadline
+fn block_ended<P: BackendStorage, G: Deref<Target = FrontendStorage<P>>>(
+    lock: impl Fn() -> G,
+    deadline: Option<Instant>,
+    mut waiting: impl FnMut(),
+) -> Option<G> {
+    loop {
+        let storage = lock();
+        if !storage.is_journaled() {
+            return Some(storage);
+        }
+        let timeout = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) if timeout > Duration::default() => Some(timeout),
+                _ => return None,
+            },
+            None => None,
+        };
+        let journal_cleared = storage.journal_cleared();
+        let guard = journal_cleared.lock();
+        drop(storage);
+        waiting();
+        let _guard = journal_cleared.wait(guard, timeout);
+    }
+}
+
+fn acquire<G>(
+    activity: &Mutex<SessionActivity>,
+    session_id: u64,
+    try_lock: impl FnOnce() -> TryLockResult<G>,
+    lock: impl FnOnce() -> LockResult<G>,
+) -> G {
+    match try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::WouldBlock) => {
+            activity
+                .lock()
+                .unwrap()
+                .wait_started(session_id, WaitEvent::StorageLock);
+            let guard = lock().unwrap();
+            activity.lock().unwrap().wait_finished(session_id);
+            guard
+        }
+        Err(TryLockError::Poisoned(error)) => panic!("{}", error),
+    }
+}
+
+fn sequence_error(error: SequenceError, schema_name: String, sequence_name: String) -> QueryError {
+    match error {
+        SequenceError::SchemaDoesNotExist => QueryError::schema_does_not_exist(schema_name),
+        SequenceError::LimitReached => QueryError::sequence_limit_reached(sequence_name),
+        SequenceError::SequenceAlreadyExists => QueryError::sequence_already_exists(sequence_name),
+        SequenceError::SequenceDoesNotExist => QueryError::sequence_does_not_exist(sequence_name),
+    }
+}
+
+// ORDER BY, OFFSET and LIMIT of a query
+struct RowLimits {
+    order_by: Vec<sqlparser::ast::OrderByExpr>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl RowLimits {
+    // numbers in ORDER BY are positions of selected columns that rows are sorted by
+    fn positioned(&self, column_names: &[String]) -> std::result::Result<RowLimits, QueryError> {
+        let mut order_by = vec![];
+        for sqlparser::ast::OrderByExpr { expr, asc, nulls_first } in &self.order_by {
+            let expr = match expr {
+                sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(position)) => match position
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|position| (1..=column_names.len()).contains(position))
+                {
+                    Some(position) => {
+                        sqlparser::ast::Expr::Identifier(sqlparser::ast::Ident::new(column_names[position - 1].clone()))
+                    }
+                    None => {
+                        return Err(QueryError::invalid_column_reference(
+                            "ORDER BY".to_owned(),
+                            position.clone(),
+                        ))
+                    }
+                },
+                expr => expr.clone(),
+            };
+            order_by.push(sqlparser::ast::OrderByExpr {
+                expr,
+                asc: *asc,
+                nulls_first: *nulls_first,
+            });
+        }
+        Ok(RowLimits {
+            order_by,
+            offset: self.offset,
+            limit: self.limit,
+        })
+    }
+}
+
+// negative numbers are parsed as unary minus applied to a number
+fn integer_argument(expr: &sqlparser::ast::Expr) -> Option<i64> {
+    match expr {
+        sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(number)) => number.parse().ok(),
+        sqlparser::ast::Expr::UnaryOp {
+            op: sqlparser::ast::UnaryOperator::Minus,
+            expr,
+        } => integer_argument(expr).and_then(i64::checked_neg),
+        _ => None,
+    }
+}
+
+/// Records of a table that are read from storage as they are consumed
+pub struct RecordStream {
+    records: RecordCursor,
+    consumed: usize,
+    finished: Option<Box<dyn FnOnce(usize) + Send>>,
+}
+
+impl RecordStream {
+    pub fn new(records: RecordCursor) -> RecordStream {
+        RecordStream {
+            records,
+            consumed: 0,
+            finished: None,
+        }
+    }
+
+    /// callback receives number of records when all of them are consumed
+    pub fn on_finish<F: FnOnce(usize) + Send + 'static>(&mut self, finished: F) {
+        self.finished = Some(Box::new(finished));
+    }
+}
+
+impl Iterator for RecordStream {
+    type Item = SystemResult<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.records.next() {
+            Some(record) => {
+                self.consumed += 1;
+                Some(record)
+            }
+            None => {
+                if let Some(finished) = self.finished.take() {
+                    finished(self.consumed);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl Debug for RecordStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "RecordStream {{ consumed: {} }}", self.consumed)
+    }
+}
+
+// records can't be compared without consuming them, so a stream is equal only to itself
+impl PartialEq for RecordStream {
+    fn eq(&self, other: &RecordStream) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QueryEvent {
+    SchemaCreated,
+    SchemaDropped,
+    TableCreated,
+    TableDropped,
+    TableAltered,
+    TableTruncated,
+    SequenceCreated,
+    SequenceDropped,
+    SequenceAltered,
+    IndexCreated,
+    IndexDropped,
+    VariableSet,
+    TransactionStarted,
+    TransactionCommitted,
+    TransactionRolledBack,
+    Checkpoint,
+    RecordsInserted(usize),
+    RecordsSelected(Projection),
+    RecordsStreamed(Vec<(String, SqlType)>, RecordStream),
+    RecordsUpdated(usize),
+    RecordsDeleted(usize),
+    StatementPrepared,
+    StatementDeallocated,
+    AllStatementsDeallocated,
+    QueryEmpty,
+}
+
+/// Result of a portal execution
+#[derive(Debug, PartialEq)]
+pub enum PortalEvent {
+    /// statement of the portal is executed and records that are left are returned
+    Completed(QueryEvent),
+    /// records that fit into the row limit, the rest of them are kept in the portal
+    Suspended(Vec<Vec<String>>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sql_types::SqlType;
+    use std::thread;
+    use storage::{
+        backend::SledBackendStorage,
+        engine::{StorageConfig, StorageEngine, WAL_FILE},
+        frontend::FrontendStorage,
+        wal::WriteAheadLog,
+    };
+    use test_helpers::{clock::ManualClock, in_memory_backend_storage::InMemoryStorage};
+
+    type InMemorySqlEngine = Handler<InMemoryStorage>;
+
+    #[rstest::fixture]
+    fn sql_engine() -> InMemorySqlEngine {
+        Handler::new(in_memory_storage())
+    }
+
+    #[cfg(test)]
+    mod schema {
+        use super::*;
+
+        #[rstest::rstest]
+        fn create_schema_query(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("create schema schema_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SchemaCreated)
+            );
+        }
+
+        #[rstest::rstest]
+        fn create_schema_with_the_same_name(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+
+            assert_eq!(
+                sql_engine
+                    .execute("create schema schema_name;")
+                    .expect("no system errors"),
+                Err(QueryError::schema_already_exists("schema_name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_schema(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+
+            assert_eq!(
+                sql_engine
+                    .execute("drop schema schema_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SchemaDropped)
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("create schema schema_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SchemaCreated)
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_non_existent_schema(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("drop schema non_existent")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("non_existent".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_non_existent_schema_if_exists(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("drop schema if exists non_existent")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SchemaDropped)
+            );
+            assert_eq!(
+                sql_engine.take_notices(),
+                vec![QueryError::schema_does_not_exist_skipping("non_existent".to_owned())]
+            );
+        }
+
+        #[rstest::rstest]
+        fn select_from_nonexistent_schema(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select * from non_existent.some_table;")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("non_existent".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn select_named_columns_from_nonexistent_schema(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select column_1 from schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn insert_into_table_in_nonexistent_schema(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("insert into schema_name.table_name values (123);")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn update_records_in_table_from_non_existent_schema(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("update schema_name.table_name set column_test=789;")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn delete_from_table_in_nonexistent_schema(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("delete from schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod table {
+        use super::*;
+
+        #[cfg(test)]
+        mod schemaless {
+            use super::*;
+
+            #[rstest::rstest]
+            fn create_table_in_non_existent_schema(mut sql_engine: InMemorySqlEngine) {
+                assert_eq!(
+                    sql_engine
+                        .execute("create table schema_name.table_name (column_name smallint);")
+                        .expect("no system errors"),
+                    Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
+                );
+            }
+
+            #[rstest::rstest]
+            fn drop_table_from_non_existent_schema(mut sql_engine: InMemorySqlEngine) {
+                assert_eq!(
+                    sql_engine
+                        .execute("drop table schema_name.table_name;")
+                        .expect("no system errors"),
+                    Err(QueryError::schema_does_not_exist("schema_name".to_owned()))
+                );
+            }
+        }
+
+        #[rstest::fixture]
+        fn sql_engine_with_schema(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+
+            sql_engine
+        }
+
+        #[rstest::rstest]
+        fn insert_duplicate_primary_key(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create table schema_name.table_name (id smallint primary key, email varchar(20) unique);")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine_with_schema
+                .execute("insert into schema_name.table_name values (1, 'a@b.c');")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("insert into schema_name.table_name values (1, 'd@e.f');")
+                    .expect("no system errors"),
+                Err(QueryError::unique_violation("table_name_pkey".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("insert into schema_name.table_name values (2, 'a@b.c');")
+                    .expect("no system errors"),
+                Err(QueryError::unique_violation("table_name_email_key".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn update_to_duplicate_named_constraint(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create table schema_name.table_name (id smallint, constraint unique_id unique (id));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine_with_schema
+                .execute("insert into schema_name.table_name values (1), (2);")
+                .expect("no system errors")
+                .expect("rows inserted");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("update schema_name.table_name set id = 3;")
+                    .expect("no system errors"),
+                Err(QueryError::unique_violation("unique_id".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn insert_without_not_null_column(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create table schema_name.table_name (id smallint not null, other smallint);")
+                .expect("no system errors")
+                .expect("table created");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("insert into schema_name.table_name (other) values (1);")
+                    .expect("no system errors"),
+                Err(QueryError::not_null_violation("id".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn insert_and_update_that_violate_check(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create table schema_name.table_name (id smallint check (id > 0 and 10 >= id));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine_with_schema
+                .execute("insert into schema_name.table_name values (10);")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("insert into schema_name.table_name values (11);")
+                    .expect("no system errors"),
+                Err(QueryError::check_violation(
+                    "table_name".to_owned(),
+                    "table_name_id_check".to_owned()
+                ))
+            );
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("update schema_name.table_name set id = 0;")
+                    .expect("no system errors"),
+                Err(QueryError::check_violation(
+                    "table_name".to_owned(),
+                    "table_name_id_check".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn check_that_is_not_a_comparison_with_constant(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("create table schema_name.table_name (id smallint, other smallint, check (id > other));")
+                    .expect("no system errors"),
+                Err(QueryError::not_supported_operation(
+                    "create table schema_name.table_name (id smallint, other smallint, check (id > other));".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn multiple_primary_keys(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute(
+                        "create table schema_name.table_name (id smallint primary key, other smallint primary key);"
+                    )
+                    .expect("no system errors"),
+                Err(QueryError::multiple_primary_keys("table_name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn unique_constraint_on_non_existent_column(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("create table schema_name.table_name (id smallint, unique (other));")
+                    .expect("no system errors"),
+                Err(QueryError::column_does_not_exist(vec!["other".to_owned()]))
+            );
+        }
+
+        #[rstest::rstest]
+        fn insert_into_table_with_serial_column(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create table schema_name.table_name (id serial, name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine_with_schema
+                .execute("insert into schema_name.table_name (name) values ('a'), ('b');")
+                .expect("no system errors")
+                .expect("rows inserted");
+            sql_engine_with_schema
+                .execute("insert into schema_name.table_name values (default, 'c');")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("id".to_owned(), SqlType::Integer),
+                        ("name".to_owned(), SqlType::VarChar(10))
+                    ],
+                    vec![
+                        vec!["1".to_owned(), "a".to_owned()],
+                        vec!["2".to_owned(), "b".to_owned()],
+                        vec!["3".to_owned(), "c".to_owned()],
+                    ]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn insert_into_table_with_default_values(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute(
+                    "create table schema_name.table_name (id smallint, status varchar(10) default 'new', score smallint default -1);",
+                )
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine_with_schema
+                .execute("insert into schema_name.table_name (id) values (1);")
+                .expect("no system errors")
+                .expect("row inserted");
+            sql_engine_with_schema
+                .execute("insert into schema_name.table_name values (2, 'done');")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("id".to_owned(), SqlType::SmallInt),
+                        ("status".to_owned(), SqlType::VarChar(10)),
+                        ("score".to_owned(), SqlType::SmallInt)
+                    ],
+                    vec![
+                        vec!["1".to_owned(), "new".to_owned(), "-1".to_owned()],
+                        vec!["2".to_owned(), "done".to_owned(), "-1".to_owned()],
+                    ]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn column_default_from_sequence(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create sequence schema_name.ids start with 100;")
+                .expect("no system errors")
+                .expect("sequence created");
+            sql_engine_with_schema
+                .execute(
+                    "create table schema_name.table_name (id integer default nextval('schema_name.ids'), name varchar(10));",
+                )
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine_with_schema
+                .execute("insert into schema_name.table_name (name) values ('a');")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("select id from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("id".to_owned(), SqlType::Integer)],
+                    vec![vec!["100".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_table_with_serial_column(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create table schema_name.table_name (id bigserial);")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine_with_schema
+                .execute("drop table schema_name.table_name;")
+                .expect("no system errors")
+                .expect("table dropped");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("select nextval('schema_name.table_name_id_seq');")
+                    .expect("no system errors"),
+                Err(QueryError::sequence_does_not_exist(
+                    "schema_name.table_name_id_seq".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn create_table(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("create table schema_name.table_name (column_name smallint);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableCreated)
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_table(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create table schema_name.table_name (column_name smallint);")
+                .expect("no system errors")
+                .expect("table created");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("drop table schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableDropped)
+            );
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("create table schema_name.table_name (column_name smallint);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableCreated)
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_non_existent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("drop table schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_non_existent_table_if_exists(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("drop table if exists schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableDropped)
+            );
+            assert_eq!(
+                sql_engine_with_schema.take_notices(),
+                vec![QueryError::table_does_not_exist_skipping(
+                    "schema_name.table_name".to_owned()
+                )]
+            );
+        }
+
+        #[rstest::rstest]
+        fn create_existing_table_if_not_exists(mut sql_engine_with_schema: InMemorySqlEngine) {
+            sql_engine_with_schema
+                .execute("create table schema_name.table_name (column_name smallint);")
+                .expect("no system errors")
+                .expect("table created");
+
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("create table if not exists schema_name.table_name (column_name smallint);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableCreated)
+            );
+            assert_eq!(
+                sql_engine_with_schema.take_notices(),
+                vec![QueryError::table_already_exists_skipping("table_name".to_owned())]
+            );
+        }
+
+        #[rstest::rstest]
+        fn select_from_not_existed_table(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("select * from schema_name.non_existent;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.non_existent".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn select_named_columns_from_non_existent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("select column_1 from schema_name.non_existent;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.non_existent".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn insert_into_nonexistent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("insert into schema_name.table_name values (123);")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn update_records_in_non_existent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("update schema_name.table_name set column_test=789;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn delete_from_nonexistent_table(mut sql_engine_with_schema: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_schema
+                    .execute("delete from schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
+            );
+        }
+    }
+
+    #[rstest::rstest]
+    fn insert_and_select_single_row(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+
+        assert_eq!(
+            sql_engine
+                .execute("insert into schema_name.table_name values (123);")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsInserted(1))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![("column_test".to_owned(), SqlType::SmallInt)],
+                vec![vec!["123".to_owned()]]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn insert_and_select_multiple_rows(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (123);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![("column_test".to_owned(), SqlType::SmallInt)],
+                vec![vec!["123".to_owned()]]
+            )))
+        );
+
+        sql_engine
+            .execute("insert into schema_name.table_name values (456);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![("column_test".to_owned(), SqlType::SmallInt)],
+                vec![vec!["123".to_owned()], vec!["456".to_owned()]]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn insert_and_select_named_columns(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (col1 smallint, col2 smallint, col3 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name (col2, col3, col1) values (1, 2, 3), (4, 5, 6);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("col1".to_owned(), SqlType::SmallInt),
+                    ("col2".to_owned(), SqlType::SmallInt),
+                    ("col3".to_owned(), SqlType::SmallInt),
+                ],
+                vec![
+                    vec!["3".to_owned(), "1".to_owned(), "2".to_owned()],
+                    vec!["6".to_owned(), "4".to_owned(), "5".to_owned()],
+                ]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn update_all_records(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (123);")
+            .expect("no system errors")
+            .expect("row inserted");
+        sql_engine
+            .execute("insert into schema_name.table_name values (456);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![("column_test".to_owned(), SqlType::SmallInt)],
+                vec![vec!["123".to_owned()], vec!["456".to_owned()]]
+            )))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("update schema_name.table_name set column_test=789;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsUpdated(2))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![("column_test".to_owned(), SqlType::SmallInt)],
+                vec![vec!["789".to_owned()], vec!["789".to_owned()]]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn update_single_column_of_all_records(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (col1 smallint, col2 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (123, 789);")
+            .expect("no system errors")
+            .expect("row inserted");
+        sql_engine
+            .execute("insert into schema_name.table_name values (456, 789);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("col1".to_owned(), SqlType::SmallInt),
+                    ("col2".to_owned(), SqlType::SmallInt),
+                ],
+                vec![
+                    vec!["123".to_owned(), "789".to_owned()],
+                    vec!["456".to_owned(), "789".to_owned()],
+                ]
+            )))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("update schema_name.table_name set col2=357;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsUpdated(2))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("col1".to_owned(), SqlType::SmallInt),
+                    ("col2".to_owned(), SqlType::SmallInt),
+                ],
+                vec![
+                    vec!["123".to_owned(), "357".to_owned()],
+                    vec!["456".to_owned(), "357".to_owned()],
+                ]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn update_multiple_columns_of_all_records(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (col1 smallint, col2 smallint, col3 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (111, 222, 333);")
+            .expect("no system errors")
+            .expect("row inserted");
+        sql_engine
+            .execute("insert into schema_name.table_name values (444, 555, 666);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("col1".to_owned(), SqlType::SmallInt),
+                    ("col2".to_owned(), SqlType::SmallInt),
+                    ("col3".to_owned(), SqlType::SmallInt),
+                ],
+                vec![
+                    vec!["111".to_owned(), "222".to_owned(), "333".to_owned()],
+                    vec!["444".to_owned(), "555".to_owned(), "666".to_owned()],
+                ]
+            )))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("update schema_name.table_name set col3=777, col1=999;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsUpdated(2))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("col1".to_owned(), SqlType::SmallInt),
+                    ("col2".to_owned(), SqlType::SmallInt),
+                    ("col3".to_owned(), SqlType::SmallInt),
+                ],
+                vec![
+                    vec!["999".to_owned(), "222".to_owned(), "777".to_owned()],
+                    vec!["999".to_owned(), "555".to_owned(), "777".to_owned()],
+                ]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn update_all_records_in_multiple_columns(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_1 smallint, column_2 smallint, column_3 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (1, 2, 3), (4, 5, 6), (7, 8, 9);")
+            .expect("no system errors")
+            .expect("rows inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("column_1".to_owned(), SqlType::SmallInt),
+                    ("column_2".to_owned(), SqlType::SmallInt),
+                    ("column_3".to_owned(), SqlType::SmallInt)
+                ],
+                vec![
+                    vec!["1".to_owned(), "2".to_owned(), "3".to_owned()],
+                    vec!["4".to_owned(), "5".to_owned(), "6".to_owned()],
+                    vec!["7".to_owned(), "8".to_owned(), "9".to_owned()]
+                ]
+            )))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("update schema_name.table_name set column_1=10, column_2=-20, column_3=30;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsUpdated(3))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("column_1".to_owned(), SqlType::SmallInt),
+                    ("column_2".to_owned(), SqlType::SmallInt),
+                    ("column_3".to_owned(), SqlType::SmallInt)
+                ],
+                vec![
+                    vec!["10".to_owned(), "-20".to_owned(), "30".to_owned()],
+                    vec!["10".to_owned(), "-20".to_owned(), "30".to_owned()],
+                    vec!["10".to_owned(), "-20".to_owned(), "30".to_owned()]
+                ]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn update_records_in_nonexistent_table(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+
+        assert_eq!(
+            sql_engine
+                .execute("update schema_name.table_name set column_test=789;")
+                .expect("no system errors"),
+            Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
+        );
+    }
+
+    #[rstest::rstest]
+    fn update_non_existent_columns_of_records(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (123);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![("column_test".to_owned(), SqlType::SmallInt)],
+                vec![vec!["123".to_owned()]],
+            )))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("update schema_name.table_name set col1=456, col2=789;")
+                .expect("no system errors"),
+            Err(QueryError::column_does_not_exist(vec![
+                "col1".to_owned(),
+                "col2".to_owned()
+            ]))
+        );
+    }
+
+    #[rstest::rstest]
+    fn delete_all_records(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (123);")
+            .expect("no system errors")
+            .expect("row inserted");
+        sql_engine
+            .execute("insert into schema_name.table_name values (456);")
+            .expect("no system errors")
+            .expect("row inserted");
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![("column_test".to_owned(), SqlType::SmallInt)],
+                vec![vec!["123".to_owned()], vec!["456".to_owned()]]
+            )))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("delete from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsDeleted(2))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![("column_test".to_owned(), SqlType::SmallInt)],
+                vec![]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn select_all_from_table_with_multiple_columns(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_1 smallint, column_2 smallint, column_3 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (123, 456, 789);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("column_1".to_owned(), SqlType::SmallInt),
+                    ("column_2".to_owned(), SqlType::SmallInt),
+                    ("column_3".to_owned(), SqlType::SmallInt)
+                ],
+                vec![vec!["123".to_owned(), "456".to_owned(), "789".to_owned()]]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn insert_multiple_rows(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_1 smallint, column_2 smallint, column_3 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+
+        assert_eq!(
+            sql_engine
+                .execute("insert into schema_name.table_name values (1, 4, 7), (2, 5, 8), (3, 6, 9);")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsInserted(3))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("column_1".to_owned(), SqlType::SmallInt),
+                    ("column_2".to_owned(), SqlType::SmallInt),
+                    ("column_3".to_owned(), SqlType::SmallInt)
+                ],
+                vec![
+                    vec!["1".to_owned(), "4".to_owned(), "7".to_owned()],
+                    vec!["2".to_owned(), "5".to_owned(), "8".to_owned()],
+                    vec!["3".to_owned(), "6".to_owned(), "9".to_owned()],
+                ]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn select_not_all_columns(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_1 smallint, column_2 smallint, column_3 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (1, 4, 7), (2, 5, 8), (3, 6, 9);")
+            .expect("no system errors")
+            .expect("rows inserted");
+
+        assert_eq!(
+            sql_engine
+                .execute("select column_3, column_2 from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("column_3".to_owned(), SqlType::SmallInt),
+                    ("column_2".to_owned(), SqlType::SmallInt),
+                ],
+                vec![
+                    vec!["7".to_owned(), "4".to_owned()],
+                    vec!["8".to_owned(), "5".to_owned()],
+                    vec!["9".to_owned(), "6".to_owned()],
+                ]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn select_non_existing_columns_from_table(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_in_table smallint);")
+            .expect("no system errors")
+            .expect("table created");
+
+        assert_eq!(
+            sql_engine
+                .execute("select column_not_in_table1, column_not_in_table2 from schema_name.table_name;")
+                .expect("no system errors"),
+            Err(QueryError::column_does_not_exist(vec![
+                "column_not_in_table1".to_owned(),
+                "column_not_in_table2".to_owned()
+            ]))
+        );
+    }
+
+    #[rstest::rstest]
+    fn query_that_can_not_be_parsed(mut sql_engine: InMemorySqlEngine) {
+        assert_eq!(
+            sql_engine
+                .execute("create scheme schema_name;")
+                .expect("no system errors")
+                .map_err(|error| error.code()),
+            Err(Some("42601".to_owned()))
+        );
+    }
+
+    #[rstest::rstest]
+    fn select_from_multiple_tables(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_1 (column_1 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("create table schema_name.table_2 (column_2 smallint);")
+            .expect("no system errors")
+            .expect("table created");
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_1, schema_name.table_2;")
+                .expect("no system errors"),
+            Err(QueryError::not_supported_operation(
+                "select * from schema_name.table_1, schema_name.table_2;".to_owned()
+            ))
+        );
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_1 join schema_name.table_2 on column_1 = column_2;")
+                .expect("no system errors"),
+            Err(QueryError::not_supported_operation(
+                "select * from schema_name.table_1 join schema_name.table_2 on column_1 = column_2;".to_owned()
+            ))
+        );
+    }
+
+    #[rstest::rstest]
+    fn create_table_with_different_types(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+
+        assert_eq!(
+            sql_engine
+                .execute("create table schema_name.table_name (column_si smallint, column_i integer, column_bi bigint, column_c char(10), column_vc varchar(10));")
+                .expect("no system errors"),
+            Ok(QueryEvent::TableCreated)
+        )
+    }
+
+    #[rstest::rstest]
+    fn insert_and_select_different_integer_types(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+
+        sql_engine
+            .execute("create table schema_name.table_name (column_si smallint, column_i integer, column_bi bigint);")
+            .expect("no system errors")
+            .expect("table created");
+
+        assert_eq!(
+            sql_engine
+                .execute("insert into schema_name.table_name values(-32768, -2147483648, -9223372036854775808);")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsInserted(1))
+        );
+
+        assert_eq!(
+            sql_engine
+                .execute("insert into schema_name.table_name values(32767, 2147483647, 9223372036854775807);")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsInserted(1))
+        );
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("column_si".to_owned(), SqlType::SmallInt),
+                    ("column_i".to_owned(), SqlType::Integer),
+                    ("column_bi".to_owned(), SqlType::BigInt),
+                ],
+                vec![
+                    vec![
+                        "-32768".to_owned(),
+                        "-2147483648".to_owned(),
+                        "-9223372036854775808".to_owned()
+                    ],
+                    vec![
+                        "32767".to_owned(),
+                        "2147483647".to_owned(),
+                        "9223372036854775807".to_owned()
+                    ],
+                ]
+            )))
+        )
+    }
+
+    #[rstest::rstest]
+    fn insert_and_select_different_character_types(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+
+        sql_engine
+            .execute("create table schema_name.table_name (column_c char(10), column_vc varchar(10));")
+            .expect("no system errors")
+            .expect("table created");
+
+        assert_eq!(
+            sql_engine
+                .execute("insert into schema_name.table_name values('12345abcde', '12345abcde');")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsInserted(1))
+        );
+
+        assert_eq!(
+            sql_engine
+                .execute("insert into schema_name.table_name values('12345abcde', 'abcde');")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsInserted(1))
+        );
+
+        assert_eq!(
+            sql_engine
+                .execute("select * from schema_name.table_name;")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("column_c".to_owned(), SqlType::Char(10)),
+                    ("column_vc".to_owned(), SqlType::VarChar(10))
+                ],
+                vec![
+                    vec!["12345abcde".to_owned(), "12345abcde".to_owned()],
+                    vec!["12345abcde".to_owned(), "abcde".to_owned()],
+                ]
+            )))
+        )
+    }
+
+    #[test]
+    fn stream_selected_records() {
+        let statistics = Arc::new(Mutex::new(StatementStatistics::default()));
+        let mut sql_engine = Handler::with_statistics(in_memory_storage(), statistics.clone()).with_result_streaming();
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (123), (456);")
+            .expect("no system errors")
+            .expect("rows inserted");
+
+        match sql_engine
+            .execute("select * from schema_name.table_name;")
+            .expect("no system errors")
+        {
+            Ok(QueryEvent::RecordsStreamed(columns, records)) => {
+                assert_eq!(columns, vec![("column_test".to_owned(), SqlType::SmallInt)]);
+                assert_eq!(
+                    records
+                        .collect::<SystemResult<Vec<Vec<String>>>>()
+                        .expect("no system errors"),
+                    vec![vec!["123".to_owned()], vec!["456".to_owned()]]
+                );
+            }
+            otherwise => panic!("unexpected result {:?}", otherwise),
+        }
+
+        let (_columns, records) = statistics.lock().unwrap().projection();
+        let select = records
+            .into_iter()
+            .find(|record| record[0] == "select * from schema_name.table_name;")
+            .expect("select is recorded");
+        assert_eq!(select[4], "2");
+    }
+
+    #[test]
+    fn select_from_pg_stat_activity() {
+        let activity = Arc::new(Mutex::new(SessionActivity::default()));
+        activity.lock().unwrap().connect(1, "postgres", "psql");
+        let mut sql_engine = Handler::with_activity(in_memory_storage(), Arc::default(), activity);
+
+        match sql_engine
+            .execute("select * from pg_stat_activity;")
+            .expect("no system errors")
+        {
+            Ok(QueryEvent::RecordsSelected((columns, records))) => {
+                assert_eq!(
+                    columns
+                        .into_iter()
+                        .map(|(name, _sql_type)| name)
+                        .collect::<Vec<String>>(),
+                    vec![
+                        "pid",
+                        "usename",
+                        "application_name",
+                        "wait_event_type",
+                        "wait_event",
+                        "state",
+                        "query"
+                    ]
+                );
+                assert_eq!(
+                    records,
+                    vec![vec![
+                        "1".to_owned(),
+                        "postgres".to_owned(),
+                        "psql".to_owned(),
+                        "".to_owned(),
+                        "".to_owned(),
+                        "idle".to_owned(),
+                        "".to_owned()
+                    ]]
+                );
+            }
+            otherwise => panic!("unexpected result {:?}", otherwise),
+        }
+    }
+
+    #[cfg(test)]
+    mod alter_table {
+        use super::*;
+
+        #[rstest::fixture]
+        fn sql_engine_with_table() -> InMemorySqlEngine {
+            let storage = in_memory_storage();
+            {
+                let mut storage = storage.write().unwrap();
+                storage
+                    .create_schema("schema_name")
+                    .expect("no system errors")
+                    .expect("schema created");
+                storage
+                    .create_table(
+                        "schema_name",
+                        "table_name",
+                        vec![
+                            ("id".to_owned(), SqlType::SmallInt),
+                            ("name".to_owned(), SqlType::VarChar(20)),
+                        ],
+                    )
+                    .expect("no system errors")
+                    .expect("table created");
+                storage
+                    .insert_into(
+                        "schema_name",
+                        "table_name",
+                        vec![],
+                        vec![vec!["1".to_owned(), "a".to_owned()]],
+                    )
+                    .expect("no system errors")
+                    .expect("row inserted");
+            }
+            Handler::new(storage).with_session(Session::new(1, "user_name", "db_name"))
+        }
+
+        #[rstest::rstest]
+        fn add_column_with_default(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name add column age smallint default 18;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableAltered)
+            );
+            sql_engine_with_table
+                .execute("insert into schema_name.table_name (id, name) values (2, 'b');")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select id, age from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("id".to_owned(), SqlType::SmallInt),
+                        ("age".to_owned(), SqlType::SmallInt)
+                    ],
+                    vec![
+                        vec!["1".to_owned(), "18".to_owned()],
+                        vec!["2".to_owned(), "18".to_owned()]
+                    ]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn add_columns_without_default(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("alter table schema_name.table_name add column age integer;")
+                .expect("no system errors")
+                .expect("column added");
+            sql_engine_with_table
+                .execute("alter table schema_name.table_name add column nickname varchar(10);")
+                .expect("no system errors")
+                .expect("column added");
+
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("id".to_owned(), SqlType::SmallInt),
+                        ("name".to_owned(), SqlType::VarChar(20)),
+                        ("age".to_owned(), SqlType::Integer),
+                        ("nickname".to_owned(), SqlType::VarChar(10))
+                    ],
+                    vec![vec!["1".to_owned(), "a".to_owned(), "".to_owned(), "".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn add_existing_column(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name add name smallint;")
+                    .expect("no system errors"),
+                Err(QueryError::column_already_exists(
+                    "name".to_owned(),
+                    "table_name".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn add_not_null_column_to_table_with_rows(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name add column age smallint not null;")
+                    .expect("no system errors"),
+                Err(QueryError::not_null_violation("age".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn add_serial_column(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name add column number serial;")
+                    .expect("no system errors"),
+                Err(QueryError::not_supported_operation(
+                    "alter table schema_name.table_name add column number serial;".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_column(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name drop column id;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableAltered)
+            );
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("name".to_owned(), SqlType::VarChar(20))],
+                    vec![vec!["a".to_owned()]]
+                )))
+            );
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name drop column id;")
+                    .expect("no system errors"),
+                Err(QueryError::column_does_not_exist(vec!["id".to_owned()]))
+            );
+        }
+
+        #[rstest::rstest]
+        fn rename_column(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name rename column name to title;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableAltered)
+            );
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select title from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("title".to_owned(), SqlType::VarChar(20))],
+                    vec![vec!["a".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn rename_table(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name rename to new_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableAltered)
+            );
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select id from schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select id from schema_name.new_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("id".to_owned(), SqlType::SmallInt)],
+                    vec![vec!["1".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn alter_non_existent_table(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.other_table drop column id;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.other_table".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn alter_table_in_transaction(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("alter table schema_name.table_name drop column id;")
+                    .expect("no system errors"),
+                Err(QueryError::active_transaction("ALTER TABLE".to_owned()))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod temp_file_limit {
+        use super::*;
+
+        #[rstest::rstest]
+        fn no_limit_by_default(sql_engine: InMemorySqlEngine) {
+            assert_eq!(sql_engine.temp_file_limit(), None);
+        }
+
+        #[rstest::rstest]
+        fn set_in_kilobytes(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("set temp_file_limit = 1024;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::VariableSet)
+            );
+            assert_eq!(sql_engine.temp_file_limit(), Some(1024 * 1024));
+        }
+
+        #[rstest::rstest]
+        fn negative_value_removes_limit(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("set temp_file_limit = 1024;")
+                .expect("no system errors")
+                .expect("limit is set");
+
+            assert_eq!(
+                sql_engine
+                    .execute("set temp_file_limit = '-1';")
+                    .expect("no system errors"),
+                Ok(QueryEvent::VariableSet)
+            );
+            assert_eq!(sql_engine.temp_file_limit(), None);
+        }
+
+        #[rstest::rstest]
+        fn invalid_value(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("set temp_file_limit = 'unlimited';")
+                    .expect("no system errors"),
+                Err(QueryError::invalid_parameter_value(
+                    "temp_file_limit".to_owned(),
+                    "unlimited".to_owned()
+                ))
+            );
+        }
+    }
+
+    #[rstest::rstest]
+    fn select_from_pg_stat_statements(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (123);")
+            .expect("no system errors")
+            .expect("row inserted");
+        sql_engine
+            .execute("insert into schema_name.table_name values (456), (789);")
+            .expect("no system errors")
+            .expect("rows inserted");
+        sql_engine
+            .execute("insert into schema_name.table_name values (-1);")
+            .expect("no system errors")
+            .expect("row inserted");
+
+        match sql_engine
+            .execute("select * from pg_stat_statements;")
+            .expect("no system errors")
+        {
+            Ok(QueryEvent::RecordsSelected((columns, records))) => {
+                assert_eq!(
+                    columns
+                        .into_iter()
+                        .map(|(name, _sql_type)| name)
+                        .collect::<Vec<String>>(),
+                    vec!["query", "calls", "total_time", "mean_time", "rows"]
+                );
+                assert_eq!(
+                    records
+                        .into_iter()
+                        .map(|record| (record[0].clone(), record[1].clone(), record[4].clone()))
+                        .collect::<Vec<(String, String, String)>>(),
+                    vec![
+                        ("create schema schema_name;".to_owned(), "1".to_owned(), "0".to_owned()),
+                        (
+                            "create table schema_name.table_name (column_test smallint);".to_owned(),
+                            "1".to_owned(),
+                            "0".to_owned()
+                        ),
+                        (
+                            "insert into schema_name.table_name values ($1), ($2);".to_owned(),
+                            "1".to_owned(),
+                            "2".to_owned()
+                        ),
+                        (
+                            "insert into schema_name.table_name values ($1);".to_owned(),
+                            "2".to_owned(),
+                            "2".to_owned()
+                        ),
+                    ]
+                );
+            }
+            otherwise => panic!("unexpected result {:?}", otherwise),
+        }
+    }
+
+    #[rstest::rstest]
+    fn select_table_and_indexes_size(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+
+        assert_eq!(
+            sql_engine
+                .execute("select pg_table_size('schema_name.table_name'), pg_indexes_size('schema_name.table_name');")
+                .expect("no system errors"),
+            Ok(QueryEvent::RecordsSelected((
+                vec![
+                    ("pg_table_size".to_owned(), SqlType::BigInt),
+                    ("pg_indexes_size".to_owned(), SqlType::BigInt)
+                ],
+                vec![vec!["0".to_owned(), "0".to_owned()]]
+            )))
+        );
+    }
+
+    #[rstest::rstest]
+    fn select_size_of_not_existed_table(mut sql_engine: InMemorySqlEngine) {
+        sql_engine
+            .execute("create schema schema_name;")
             .expect("no system errors")
             .expect("schema created");
+
+        assert_eq!(
+            sql_engine
+                .execute("select pg_table_size('schema_name.table_name');")
+                .expect("no system errors"),
+            Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
+        );
+    }
+
+    #[rstest::rstest]
+    fn select_database_size(mut sql_engine: InMemorySqlEngine) {
         sql_engine
-            .execute("create table schema_name.table_name (col1 smallint, col2 smallint, col3 smallint);")
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+        sql_engine
+            .execute("create table schema_name.table_name (column_test smallint);")
             .expect("no system errors")
             .expect("table created");
+
+        match sql_engine
+            .execute("select pg_database_size();")
+            .expect("no system errors")
+        {
+            Ok(QueryEvent::RecordsSelected((columns, records))) => {
+                assert_eq!(columns, vec![("pg_database_size".to_owned(), SqlType::BigInt)]);
+                assert!(records[0][0].parse::<u64>().expect("size is a number") > 0);
+            }
+            otherwise => panic!("unexpected result {:?}", otherwise),
+        }
+    }
+
+    #[test]
+    fn select_database_size_by_name() {
+        let mut sql_engine = Handler::new(sled_storage());
         sql_engine
-            .execute("insert into schema_name.table_name values (111, 222, 333);")
+            .execute("create schema schema_name;")
             .expect("no system errors")
-            .expect("row inserted");
+            .expect("schema created");
         sql_engine
-            .execute("insert into schema_name.table_name values (444, 555, 666);")
+            .execute("create table schema_name.table_name (column_test smallint);")
+            .expect("no system errors")
+            .expect("table created");
+        sql_engine
+            .execute("insert into schema_name.table_name values (1);")
             .expect("no system errors")
             .expect("row inserted");
 
-        assert_eq!(
+        for query in &["select pg_database_size();", "select pg_database_size('postgres');"] {
+            match sql_engine.execute(query).expect("no system errors") {
+                Ok(QueryEvent::RecordsSelected((columns, records))) => {
+                    assert_eq!(columns, vec![("pg_database_size".to_owned(), SqlType::BigInt)]);
+                    assert!(records[0][0].parse::<u64>().expect("size is a number") > 0);
+                }
+                otherwise => panic!("unexpected result {:?}", otherwise),
+            }
+        }
+    }
+
+    #[test]
+    fn statement_time_is_measured_by_clock() {
+        let statistics = Arc::new(Mutex::new(StatementStatistics::default()));
+        let clock = Arc::new(ManualClock::default());
+        let mut sql_engine = Handler::with_statistics(in_memory_storage(), statistics.clone()).with_clock(clock);
+        sql_engine
+            .execute("create schema schema_name;")
+            .expect("no system errors")
+            .expect("schema created");
+
+        let (_columns, records) = statistics.lock().unwrap().projection();
+        assert_eq!(
+            records,
+            vec![vec![
+                "create schema schema_name;".to_owned(),
+                "1".to_owned(),
+                "0".to_owned(),
+                "0".to_owned(),
+                "0".to_owned()
+            ]]
+        );
+    }
+
+    #[test]
+    fn sessions_read_storage_concurrently() {
+        let storage = in_memory_storage();
+        let mut sql_engine = Handler::new(storage.clone());
+        let _other_session_reads = storage.read().unwrap();
+
+        match sql_engine
+            .execute("select pg_database_size();")
+            .expect("no system errors")
+        {
+            Ok(QueryEvent::RecordsSelected((columns, _))) => {
+                assert_eq!(columns, vec![("pg_database_size".to_owned(), SqlType::BigInt)]);
+            }
+            otherwise => panic!("unexpected result {:?}", otherwise),
+        }
+    }
+
+    #[cfg(test)]
+    mod system_functions {
+        use super::*;
+
+        #[test]
+        fn session_properties() {
+            let mut sql_engine =
+                Handler::new(in_memory_storage()).with_session(Session::new(7, "user_name", "db_name"));
+
+            assert_eq!(
+                sql_engine
+                    .execute("select current_user, current_database(), pg_backend_pid(), current_schema();")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("current_user".to_owned(), SqlType::VarChar(63)),
+                        ("current_database".to_owned(), SqlType::VarChar(63)),
+                        ("pg_backend_pid".to_owned(), SqlType::Integer),
+                        ("current_schema".to_owned(), SqlType::VarChar(63)),
+                    ],
+                    vec![vec![
+                        "user_name".to_owned(),
+                        "db_name".to_owned(),
+                        "7".to_owned(),
+                        "public".to_owned()
+                    ]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn version(mut sql_engine: InMemorySqlEngine) {
+            match sql_engine.execute("select version();").expect("no system errors") {
+                Ok(QueryEvent::RecordsSelected((_columns, records))) => {
+                    assert!(records[0][0].starts_with("PostgreSQL 12.4"));
+                }
+                otherwise => panic!("unexpected result {:?}", otherwise),
+            }
+        }
+
+        #[rstest::rstest]
+        fn set_config_and_current_setting(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select set_config('search_path', 'schema_name', false);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("set_config".to_owned(), SqlType::VarChar(255))],
+                    vec![vec!["schema_name".to_owned()]]
+                )))
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select current_setting('search_path');")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("current_setting".to_owned(), SqlType::VarChar(255))],
+                    vec![vec!["schema_name".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn current_setting_of_set_variable(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("set application_name = 'app';")
+                .expect("no system errors")
+                .expect("variable set");
+
+            assert_eq!(
+                sql_engine
+                    .execute("select current_setting('application_name');")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("current_setting".to_owned(), SqlType::VarChar(255))],
+                    vec![vec!["app".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn current_setting_of_unknown_parameter(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select current_setting('unknown');")
+                    .expect("no system errors"),
+                Err(QueryError::unrecognized_parameter("unknown".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn current_setting_of_custom_parameter(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("set my.app_tenant = '42';")
+                    .expect("no system errors"),
+                Ok(QueryEvent::VariableSet)
+            );
+
+            assert_eq!(
+                sql_engine
+                    .execute("select current_setting('my.app_tenant');")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("current_setting".to_owned(), SqlType::VarChar(255))],
+                    vec![vec!["42".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn set_local_outside_of_transaction(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("set local my.app_tenant = '42';")
+                    .expect("no system errors"),
+                Ok(QueryEvent::VariableSet)
+            );
+            assert_eq!(
+                sql_engine.take_notices(),
+                vec![QueryError::no_active_transaction("SET LOCAL".to_owned())]
+            );
+
+            assert_eq!(
+                sql_engine
+                    .execute("select current_setting('my.app_tenant');")
+                    .expect("no system errors"),
+                Err(QueryError::unrecognized_parameter("my.app_tenant".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn current_setting_as_column_value(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (tenant varchar(10), name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("set my.app_tenant = '42';")
+                .expect("no system errors")
+                .expect("variable set");
+            sql_engine
+                .execute("insert into schema_name.table_name values (current_setting('my.app_tenant'), 'a');")
+                .expect("no system errors")
+                .expect("row inserted");
+            sql_engine
+                .execute("set my.app_tenant = '43';")
+                .expect("no system errors")
+                .expect("variable set");
+            sql_engine
+                .execute("update schema_name.table_name set tenant = current_setting('my.app_tenant');")
+                .expect("no system errors")
+                .expect("row updated");
+
+            assert_eq!(
+                sql_engine
+                    .execute("select tenant, name from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("tenant".to_owned(), SqlType::VarChar(10)),
+                        ("name".to_owned(), SqlType::VarChar(10))
+                    ],
+                    vec![vec!["43".to_owned(), "a".to_owned()]]
+                )))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod sequences {
+        use super::*;
+        use storage::SequenceDefinition;
+
+        #[rstest::fixture]
+        fn sql_engine_with_sequence() -> InMemorySqlEngine {
+            let storage = in_memory_storage();
+            {
+                let mut storage = storage.write().unwrap();
+                storage
+                    .create_schema("schema_name")
+                    .expect("no system errors")
+                    .expect("schema created");
+                storage
+                    .create_sequence("schema_name", "sequence_name", SequenceDefinition::default())
+                    .expect("no system errors")
+                    .expect("sequence created");
+            }
+            Handler::new(storage).with_session(Session::new(1, "user_name", "db_name"))
+        }
+
+        fn selected(column: &str, value: &str) -> std::result::Result<QueryEvent, QueryError> {
+            Ok(QueryEvent::RecordsSelected((
+                vec![(column.to_owned(), SqlType::BigInt)],
+                vec![vec![value.to_owned()]],
+            )))
+        }
+
+        #[rstest::rstest]
+        fn nextval_returns_consecutive_values(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "1")
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "2")
+            );
+        }
+
+        #[rstest::rstest]
+        fn currval_and_lastval_after_nextval(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            sql_engine_with_sequence
+                .execute("select nextval('schema_name.sequence_name');")
+                .expect("no system errors")
+                .expect("value selected");
+
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select currval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("currval", "1")
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select lastval();")
+                    .expect("no system errors"),
+                selected("lastval", "1")
+            );
+        }
+
+        #[rstest::rstest]
+        fn currval_before_nextval(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select currval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                Err(QueryError::sequence_value_not_defined(Some(
+                    "schema_name.sequence_name".to_owned()
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn lastval_before_nextval(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select lastval();")
+                    .expect("no system errors"),
+                Err(QueryError::sequence_value_not_defined(None))
+            );
+        }
+
+        #[rstest::rstest]
+        fn setval_changes_next_value(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            sql_engine_with_sequence
+                .execute("select nextval('schema_name.sequence_name');")
+                .expect("no system errors")
+                .expect("value selected");
+
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select setval('schema_name.sequence_name', 10);")
+                    .expect("no system errors"),
+                selected("setval", "10")
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "11")
+            );
+        }
+
+        #[rstest::rstest]
+        fn setval_not_called(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            sql_engine_with_sequence
+                .execute("select setval('schema_name.sequence_name', 10, false);")
+                .expect("no system errors")
+                .expect("value set");
+
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "10")
+            );
+        }
+
+        #[rstest::rstest]
+        fn nextval_of_non_existent_sequence(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.non_existent');")
+                    .expect("no system errors"),
+                Err(QueryError::sequence_does_not_exist(
+                    "schema_name.non_existent".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn create_sequence(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("create sequence schema_name.new_sequence increment by 10 start with 5;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SequenceCreated)
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.new_sequence');")
+                    .expect("no system errors"),
+                selected("nextval", "5")
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.new_sequence');")
+                    .expect("no system errors"),
+                selected("nextval", "15")
+            );
+        }
+
+        #[rstest::rstest]
+        fn create_sequence_with_existing_name(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("create sequence schema_name.sequence_name;")
+                    .expect("no system errors"),
+                Err(QueryError::sequence_already_exists("sequence_name".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("create sequence if not exists schema_name.sequence_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SequenceCreated)
+            );
+            assert_eq!(
+                sql_engine_with_sequence.take_notices(),
+                vec![QueryError::sequence_already_exists_skipping("sequence_name".to_owned())]
+            );
+        }
+
+        #[rstest::rstest]
+        fn create_sequence_in_non_existent_schema(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("create sequence non_existent.sequence_name;")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("non_existent".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_sequence(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("drop sequence schema_name.sequence_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SequenceDropped)
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("drop sequence schema_name.sequence_name;")
+                    .expect("no system errors"),
+                Err(QueryError::sequence_does_not_exist(
+                    "schema_name.sequence_name".to_owned()
+                ))
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("drop sequence if exists schema_name.sequence_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SequenceDropped)
+            );
+            assert_eq!(
+                sql_engine_with_sequence.take_notices(),
+                vec![QueryError::sequence_does_not_exist_skipping(
+                    "schema_name.sequence_name".to_owned()
+                )]
+            );
+        }
+
+        #[rstest::rstest]
+        fn alter_sequence_restart(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            for _ in 0..3 {
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors")
+                    .expect("value selected");
+            }
+
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("alter sequence schema_name.sequence_name restart;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SequenceAltered)
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "1")
+            );
+
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("alter sequence schema_name.sequence_name restart with 5;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SequenceAltered)
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "5")
+            );
+        }
+
+        #[rstest::rstest]
+        fn alter_sequence_increment(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            sql_engine_with_sequence
+                .execute("select nextval('schema_name.sequence_name');")
+                .expect("no system errors")
+                .expect("value selected");
+
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("alter sequence schema_name.sequence_name increment by 2;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SequenceAltered)
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "2")
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('schema_name.sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "4")
+            );
+        }
+
+        #[rstest::rstest]
+        fn alter_non_existent_sequence(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("alter sequence schema_name.non_existent restart;")
+                    .expect("no system errors"),
+                Err(QueryError::sequence_does_not_exist(
+                    "schema_name.non_existent".to_owned()
+                ))
+            );
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("alter sequence if exists schema_name.non_existent restart;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::SequenceAltered)
+            );
+            assert_eq!(
+                sql_engine_with_sequence.take_notices(),
+                vec![QueryError::sequence_does_not_exist_skipping(
+                    "schema_name.non_existent".to_owned()
+                )]
+            );
+        }
+
+        #[rstest::rstest]
+        fn unqualified_sequence_is_looked_up_in_current_schema(mut sql_engine_with_sequence: InMemorySqlEngine) {
+            sql_engine_with_sequence
+                .execute("set search_path = schema_name;")
+                .expect("no system errors")
+                .expect("variable set");
+
+            assert_eq!(
+                sql_engine_with_sequence
+                    .execute("select nextval('sequence_name');")
+                    .expect("no system errors"),
+                selected("nextval", "1")
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod transactions {
+        use super::*;
+
+        #[rstest::fixture]
+        fn sql_engine_with_table() -> InMemorySqlEngine {
+            let mut sql_engine = Handler::new(in_memory_storage());
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (column_test smallint);")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.table_name values (123);")
+                .expect("no system errors")
+                .expect("row inserted");
+            sql_engine
+        }
+
+        fn select_all(sql_engine: &mut InMemorySqlEngine, values: Vec<&str>) {
+            assert_eq!(
+                sql_engine
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("column_test".to_owned(), SqlType::SmallInt)],
+                    values.into_iter().map(|value| vec![value.to_owned()]).collect()
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn commit_keeps_changes(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table.execute("begin;").expect("no system errors"),
+                Ok(QueryEvent::TransactionStarted)
+            );
+            assert_eq!(sql_engine_with_table.transaction_state(), TransactionState::InProgress);
+            sql_engine_with_table
+                .execute("insert into schema_name.table_name values (456);")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                sql_engine_with_table.execute("commit;").expect("no system errors"),
+                Ok(QueryEvent::TransactionCommitted)
+            );
+            assert_eq!(sql_engine_with_table.transaction_state(), TransactionState::Idle);
+            select_all(&mut sql_engine_with_table, vec!["123", "456"]);
+        }
+
+        #[rstest::rstest]
+        fn local_setting_lasts_until_transaction_ends(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            sql_engine_with_table
+                .execute("set local my.app_tenant = '42';")
+                .expect("no system errors")
+                .expect("variable set");
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select current_setting('my.app_tenant');")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("current_setting".to_owned(), SqlType::VarChar(255))],
+                    vec![vec!["42".to_owned()]]
+                )))
+            );
+            sql_engine_with_table
+                .execute("commit;")
+                .expect("no system errors")
+                .expect("transaction committed");
+
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select current_setting('my.app_tenant');")
+                    .expect("no system errors"),
+                Err(QueryError::unrecognized_parameter("my.app_tenant".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn rollback_reverts_changes(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            sql_engine_with_table
+                .execute("insert into schema_name.table_name values (456);")
+                .expect("no system errors")
+                .expect("row inserted");
+            sql_engine_with_table
+                .execute("update schema_name.table_name set column_test = 789;")
+                .expect("no system errors")
+                .expect("rows updated");
+            sql_engine_with_table
+                .execute("delete from schema_name.table_name;")
+                .expect("no system errors")
+                .expect("rows deleted");
+
+            assert_eq!(
+                sql_engine_with_table.execute("rollback;").expect("no system errors"),
+                Ok(QueryEvent::TransactionRolledBack)
+            );
+            assert_eq!(sql_engine_with_table.transaction_state(), TransactionState::Idle);
+            select_all(&mut sql_engine_with_table, vec!["123"]);
+        }
+
+        #[rstest::rstest]
+        fn failed_transaction_rejects_statements(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            sql_engine_with_table
+                .execute("insert into schema_name.table_name values (456);")
+                .expect("no system errors")
+                .expect("row inserted");
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select * from schema_name.non_existent;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.non_existent".to_owned()))
+            );
+            assert_eq!(sql_engine_with_table.transaction_state(), TransactionState::Failed);
+
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::in_failed_transaction())
+            );
+            assert_eq!(
+                sql_engine_with_table.execute("commit;").expect("no system errors"),
+                Ok(QueryEvent::TransactionRolledBack)
+            );
+            select_all(&mut sql_engine_with_table, vec!["123"]);
+        }
+
+        #[rstest::rstest]
+        fn empty_query_keeps_failed_transaction(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            assert_eq!(
+                sql_engine_with_table.execute(" ; ").expect("no system errors"),
+                Ok(QueryEvent::QueryEmpty)
+            );
+            assert_eq!(sql_engine_with_table.transaction_state(), TransactionState::InProgress);
+            sql_engine_with_table
+                .execute("select * from schema_name.non_existent;")
+                .expect("no system errors")
+                .expect_err("table does not exist");
+
+            assert_eq!(
+                sql_engine_with_table.execute("").expect("no system errors"),
+                Ok(QueryEvent::QueryEmpty)
+            );
+            assert_eq!(sql_engine_with_table.transaction_state(), TransactionState::Failed);
+            assert_eq!(
+                sql_engine_with_table.execute("rollback;").expect("no system errors"),
+                Ok(QueryEvent::TransactionRolledBack)
+            );
+            assert_eq!(sql_engine_with_table.transaction_state(), TransactionState::Idle);
+        }
+
+        #[rstest::rstest]
+        fn definitions_are_not_changed_in_transaction(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("drop table schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::active_transaction("DROP TABLE".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn uncommitted_rows_are_not_visible_to_other_sessions(mut sql_engine_with_table: InMemorySqlEngine) {
+            let mut other_session = Handler::new(sql_engine_with_table.storage.clone());
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            sql_engine_with_table
+                .execute("insert into schema_name.table_name values (456);")
+                .expect("no system errors")
+                .expect("row inserted");
+            assert!(other_session.waits_for_transaction());
+            assert!(!sql_engine_with_table.waits_for_transaction());
+
+            let reading = thread::spawn(move || {
+                select_all(&mut other_session, vec!["123", "456"]);
+                other_session
+            });
+            sql_engine_with_table
+                .execute("commit;")
+                .expect("no system errors")
+                .expect("transaction committed");
+
+            let other_session = reading.join().expect("rows are selected");
+            assert!(!other_session.waits_for_transaction());
+        }
+
+        #[rstest::rstest]
+        fn rollback_keeps_changes_of_other_sessions(mut sql_engine_with_table: InMemorySqlEngine) {
+            let mut other_session = Handler::new(sql_engine_with_table.storage.clone());
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            sql_engine_with_table
+                .execute("update schema_name.table_name set column_test = 456;")
+                .expect("no system errors")
+                .expect("rows updated");
+
+            let updating = thread::spawn(move || {
+                other_session
+                    .execute("update schema_name.table_name set column_test = 789;")
+                    .expect("no system errors")
+                    .expect("rows updated");
+            });
+            sql_engine_with_table
+                .execute("rollback;")
+                .expect("no system errors")
+                .expect("transaction rolled back");
+            updating.join().expect("rows are updated");
+
+            select_all(&mut sql_engine_with_table, vec!["789"]);
+        }
+
+        #[rstest::rstest]
+        fn transaction_of_closed_session_is_rolled_back(mut sql_engine_with_table: InMemorySqlEngine) {
+            let mut other_session = Handler::new(sql_engine_with_table.storage.clone());
+            other_session
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            other_session
+                .execute("insert into schema_name.table_name values (456);")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            drop(other_session);
+
+            assert!(!sql_engine_with_table.waits_for_transaction());
+            select_all(&mut sql_engine_with_table, vec!["123"]);
+        }
+
+        #[rstest::rstest]
+        fn statement_is_canceled_by_lock_timeout(mut sql_engine_with_table: InMemorySqlEngine) {
+            let mut other_session = Handler::new(sql_engine_with_table.storage.clone());
+            other_session
+                .execute("set lock_timeout = 10;")
+                .expect("no system errors")
+                .expect("timeout is set");
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            sql_engine_with_table
+                .execute("insert into schema_name.table_name values (456);")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                other_session
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::lock_timeout())
+            );
+
+            sql_engine_with_table
+                .execute("commit;")
+                .expect("no system errors")
+                .expect("transaction committed");
+            select_all(&mut other_session, vec!["123", "456"]);
+        }
+
+        #[rstest::rstest]
+        fn lock_wait_of_client_connection(mut sql_engine_with_table: InMemorySqlEngine) {
+            let mut other_session = Handler::new(sql_engine_with_table.storage.clone());
+            other_session
+                .execute("set lock_timeout = 10;")
+                .expect("no system errors")
+                .expect("timeout is set");
+            assert!(other_session.lock_wait().is_none());
+            sql_engine_with_table
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            sql_engine_with_table
+                .execute("insert into schema_name.table_name values (456);")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            let lock_wait = other_session.lock_wait().expect("session waits for transaction");
+            assert!(!thread::spawn(move || lock_wait.wait()).join().expect("wait is over"));
+            other_session.lock_timed_out();
+
+            other_session
+                .execute("set lock_timeout = 0;")
+                .expect("no system errors")
+                .expect_err("statement is canceled");
+            assert!(other_session.lock_wait().is_some());
+
+            let lock_wait = other_session.lock_wait().expect("session waits for transaction");
+            let waiting = thread::spawn(move || lock_wait.wait());
+            sql_engine_with_table
+                .execute("commit;")
+                .expect("no system errors")
+                .expect("transaction committed");
+            assert!(waiting.join().expect("wait is over"));
+            assert!(other_session.lock_wait().is_none());
+        }
+    }
+
+    #[cfg(test)]
+    mod generate_series {
+        use super::*;
+
+        #[rstest::rstest]
+        fn series_of_integers(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select * from generate_series(1, 3);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("generate_series".to_owned(), SqlType::Integer)],
+                    vec![vec!["1".to_owned()], vec!["2".to_owned()], vec!["3".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn series_with_negative_step(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select generate_series from generate_series(5, -5, -5);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("generate_series".to_owned(), SqlType::Integer)],
+                    vec![vec!["5".to_owned()], vec!["0".to_owned()], vec!["-5".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn zero_step(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select * from generate_series(1, 3, 0);")
+                    .expect("no system errors"),
+                Err(QueryError::zero_step_size())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod compatibility {
+        use super::*;
+
+        #[rstest::rstest]
+        fn begin_read_write_transaction(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine.execute("begin read write;").expect("no system errors"),
+                Ok(QueryEvent::TransactionStarted)
+            );
+            assert_eq!(
+                sql_engine.take_notices(),
+                vec![QueryError::clause_ignored("READ WRITE".to_owned())]
+            );
+        }
+
+        #[rstest::rstest]
+        fn select_for_read_only(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.table_name values ('a'::text);")
+                .expect("no system errors")
+                .expect("row inserted");
+
+            assert_eq!(
+                sql_engine
+                    .execute("select name from schema_name.table_name for read only;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("name".to_owned(), SqlType::VarChar(10))],
+                    vec![vec!["a".to_owned()]]
+                )))
+            );
+            assert_eq!(
+                sql_engine.take_notices(),
+                vec![QueryError::clause_ignored("FOR READ ONLY".to_owned())]
+            );
+        }
+
+        #[rstest::rstest]
+        fn qualified_system_function(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select pg_catalog.set_config('my.tenant', 'a', false);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("set_config".to_owned(), SqlType::VarChar(255))],
+                    vec![vec!["a".to_owned()]]
+                )))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod date_and_time_settings {
+        use super::*;
+
+        #[rstest::fixture]
+        fn sql_engine_with_table(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (local_time timestamp, moment timestamp with time zone);")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+        }
+
+        #[rstest::rstest]
+        fn timestamps_in_session_time_zone(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("set time zone 'Europe/Kiev';")
+                .expect("no system errors")
+                .expect("time zone set");
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("insert into schema_name.table_name values ('2020-07-01 03:00', '2020-07-01 03:00');")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsInserted(1))
+            );
+            sql_engine_with_table
+                .execute("set timezone = 'America/New_York';")
+                .expect("no system errors")
+                .expect("time zone set");
+
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("local_time".to_owned(), SqlType::Timestamp),
+                        ("moment".to_owned(), SqlType::TimestampWithTimeZone)
+                    ],
+                    vec![vec![
+                        "2020-07-01 03:00:00".to_owned(),
+                        "2020-06-30 20:00:00-04".to_owned()
+                    ]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn timestamps_in_date_style(mut sql_engine_with_table: InMemorySqlEngine) {
+            sql_engine_with_table
+                .execute("set datestyle = 'SQL, DMY';")
+                .expect("no system errors")
+                .expect("date style set");
+            sql_engine_with_table
+                .execute("insert into schema_name.table_name values ('01/02/2020 03:04:05', '01/02/2020 03:04:05');")
+                .expect("no system errors")
+                .expect("row inserted");
+            sql_engine_with_table
+                .execute("update schema_name.table_name set local_time = '02/02/2020 03:04:05';")
+                .expect("no system errors")
+                .expect("row updated");
+
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("local_time".to_owned(), SqlType::Timestamp),
+                        ("moment".to_owned(), SqlType::TimestampWithTimeZone)
+                    ],
+                    vec![vec![
+                        "02/02/2020 03:04:05".to_owned(),
+                        "01/02/2020 03:04:05 UTC".to_owned()
+                    ]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn invalid_timestamp(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("insert into schema_name.table_name values ('2020-02-30', '2020-02-01');")
+                    .expect("no system errors"),
+                Err(QueryError::invalid_datetime_format(
+                    "timestamp".to_owned(),
+                    "2020-02-30".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn invalid_time_zone(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("set time zone 'Mars/Olympus_Mons';")
+                    .expect("no system errors"),
+                Err(QueryError::invalid_parameter_value(
+                    "timezone".to_owned(),
+                    "Mars/Olympus_Mons".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn changes_are_reported_once(sql_engine: InMemorySqlEngine) {
+            let mut session = Session::default();
+            session.set("TimeZone", "Europe/Kiev").expect("time zone set");
+            let mut sql_engine = sql_engine.with_session(session);
+
+            assert_eq!(
+                sql_engine.take_parameter_changes(),
+                vec![("TimeZone".to_owned(), "Europe/Kiev".to_owned())]
+            );
+            assert_eq!(sql_engine.take_parameter_changes(), vec![]);
+        }
+    }
+
+    #[cfg(test)]
+    mod checkpoint {
+        use super::*;
+
+        #[rstest::rstest]
+        fn checkpoint(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine.execute("checkpoint;").expect("no system errors"),
+                Ok(QueryEvent::Checkpoint)
+            );
+            assert_eq!(
+                sql_engine.execute("CHECKPOINT").expect("no system errors"),
+                Ok(QueryEvent::Checkpoint)
+            );
+        }
+
+        #[test]
+        fn checkpoint_truncates_write_ahead_log() {
+            let data_directory = std::env::temp_dir().join(format!("database_checkpoint_{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&data_directory);
+            let log_file = data_directory.join(WAL_FILE);
+            {
+                let config = StorageConfig::default().with_data_directory(&data_directory);
+                let backend = StorageEngine::open("sled", &config)
+                    .expect("no system errors")
+                    .expect("engine is registered");
+                let mut sql_engine = Handler::new(Arc::new(RwLock::new(FrontendStorage::new(backend).unwrap())));
+                sql_engine
+                    .execute("create schema schema_name;")
+                    .expect("no system errors")
+                    .expect("schema created");
+                let (_log, operations) = WriteAheadLog::open(&log_file).expect("log is opened");
+                assert_ne!(operations, vec![]);
+
+                assert_eq!(
+                    sql_engine.execute("checkpoint;").expect("no system errors"),
+                    Ok(QueryEvent::Checkpoint)
+                );
+            }
+
+            let (_log, operations) = WriteAheadLog::open(&log_file).expect("log is opened");
+            assert_eq!(operations, vec![]);
+            std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+        }
+    }
+
+    #[cfg(test)]
+    mod temporary_objects {
+        use super::*;
+
+        #[test]
+        fn temporary_tables_are_visible_only_to_session() {
+            let storage = in_memory_storage();
+            let mut session = Handler::new(storage.clone()).with_session(Session::new(1, "user_name", "db_name"));
+            let mut another = Handler::new(storage).with_session(Session::new(2, "user_name", "db_name"));
+
+            assert_eq!(
+                session
+                    .execute("create table pg_temp.table_name (column_test smallint);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableCreated)
+            );
+            assert_eq!(
+                session
+                    .execute("insert into pg_temp.table_name values (1);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsInserted(1))
+            );
+            assert_eq!(
+                another
+                    .execute("insert into pg_temp.table_name values (1);")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("pg_temp_2".to_owned()))
+            );
+        }
+
+        #[test]
+        fn temporary_tables_are_dropped_when_session_ends() {
+            let storage = in_memory_storage();
+            let mut session = Handler::new(storage.clone()).with_session(Session::new(1, "user_name", "db_name"));
+            session
+                .execute("create table pg_temp.table_name (column_test smallint);")
+                .expect("no system errors")
+                .expect("table created");
+
+            assert_eq!(session.close(), Ok(()));
+
+            let mut reconnected = Handler::new(storage).with_session(Session::new(1, "user_name", "db_name"));
+            assert_eq!(
+                reconnected
+                    .execute("drop table pg_temp.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("pg_temp_1".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn closing_session_without_temporary_objects(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(sql_engine.close(), Ok(()));
+        }
+    }
+
+    #[cfg(test)]
+    mod interval_arithmetic {
+        use super::*;
+
+        fn selected(column: &str, sql_type: SqlType, value: &str) -> std::result::Result<QueryEvent, QueryError> {
+            Ok(QueryEvent::RecordsSelected((
+                vec![(column.to_owned(), sql_type)],
+                vec![vec![value.to_owned()]],
+            )))
+        }
+
+        #[rstest::rstest]
+        fn timestamps_and_intervals(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select timestamp '2020-01-31 10:00' + interval '1 month';")
+                    .expect("no system errors"),
+                selected("?column?", SqlType::Timestamp, "2020-02-29 10:00:00")
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select timestamp '2020-03-01' - timestamp '2020-01-01 12:00';")
+                    .expect("no system errors"),
+                selected("?column?", SqlType::Interval, "59 days 12:00:00")
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select interval '1 mon' * 1.5;")
+                    .expect("no system errors"),
+                selected("?column?", SqlType::Interval, "1 mon 15 days")
+            );
+        }
+
+        #[rstest::rstest]
+        fn days_are_added_in_session_time_zone(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("set timezone = 'America/New_York';")
+                .expect("no system errors")
+                .expect("time zone set");
+
+            assert_eq!(
+                sql_engine
+                    .execute("select '2020-03-07 12:00'::timestamp with time zone + interval '1 day';")
+                    .expect("no system errors"),
+                selected("?column?", SqlType::TimestampWithTimeZone, "2020-03-08 12:00:00-04")
+            );
+        }
+
+        #[rstest::rstest]
+        fn age(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select age(timestamp '2020-03-01', timestamp '2019-01-31');")
+                    .expect("no system errors"),
+                selected("age", SqlType::Interval, "1 year 1 mon 1 day")
+            );
+        }
+
+        #[rstest::rstest]
+        fn justify(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select justify_hours(interval '27 hours');")
+                    .expect("no system errors"),
+                selected("justify_hours", SqlType::Interval, "1 day 03:00:00")
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select justify_days(interval '35 days');")
+                    .expect("no system errors"),
+                selected("justify_days", SqlType::Interval, "1 mon 5 days")
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select justify_interval(interval '1 mon -1 hour');")
+                    .expect("no system errors"),
+                selected("justify_interval", SqlType::Interval, "29 days 23:00:00")
+            );
+        }
+
+        #[rstest::rstest]
+        fn undefined_operator(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select timestamp '2020-01-01' + timestamp '2020-01-01';")
+                    .expect("no system errors"),
+                Err(QueryError::undefined_operator(
+                    "timestamp without time zone + timestamp without time zone".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn interval_column(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (duration interval);")
+                .expect("no system errors")
+                .expect("table created");
+
+            assert_eq!(
+                sql_engine
+                    .execute("insert into schema_name.table_name values ('1.5 days');")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsInserted(1))
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("insert into schema_name.table_name values ('a while');")
+                    .expect("no system errors"),
+                Err(QueryError::invalid_datetime_format(
+                    "interval".to_owned(),
+                    "a while".to_owned()
+                ))
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("duration".to_owned(), SqlType::Interval)],
+                    vec![vec!["1 day 12:00:00".to_owned()]]
+                )))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod math_functions {
+        use super::*;
+
+        fn selected(column: &str, sql_type: SqlType, value: &str) -> std::result::Result<QueryEvent, QueryError> {
+            Ok(QueryEvent::RecordsSelected((
+                vec![(column.to_owned(), sql_type)],
+                vec![vec![value.to_owned()]],
+            )))
+        }
+
+        #[rstest::rstest]
+        fn double_precision_results(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select power(2, 10), sqrt(2.25);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("power".to_owned(), SqlType::DoublePrecision),
+                        ("sqrt".to_owned(), SqlType::DoublePrecision)
+                    ],
+                    vec![vec!["1024".to_owned(), "1.5".to_owned()]]
+                )))
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select degrees(atan2(1, 1));")
+                    .expect("no system errors"),
+                selected("degrees", SqlType::DoublePrecision, "45")
+            );
+        }
+
+        #[rstest::rstest]
+        fn numeric_results(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select sqrt(6.25::numeric);")
+                    .expect("no system errors"),
+                selected("sqrt", SqlType::Decimal, "2.5")
+            );
+            assert_eq!(
+                sql_engine.execute("select log(2, 64);").expect("no system errors"),
+                selected("log", SqlType::Decimal, "6")
+            );
+        }
+
+        #[rstest::rstest]
+        fn invalid_arguments(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine.execute("select ln(0);").expect("no system errors"),
+                Err(QueryError::invalid_argument_for_logarithm(
+                    "cannot take logarithm of zero".to_owned()
+                ))
+            );
+            assert_eq!(
+                sql_engine.execute("select sqrt(-1);").expect("no system errors"),
+                Err(QueryError::invalid_argument_for_power_function(
+                    "cannot take square root of a negative number".to_owned()
+                ))
+            );
+            assert_eq!(
+                sql_engine.execute("select exp(1000);").expect("no system errors"),
+                Err(QueryError::value_out_of_range(
+                    "value out of range: overflow".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn random_values_repeat_after_same_seed(mut sql_engine: InMemorySqlEngine) {
+            sql_engine
+                .execute("select setseed(0.25);")
+                .expect("no system errors")
+                .expect("seed set");
+            let first = sql_engine.execute("select random();").expect("no system errors");
+            let second = sql_engine.execute("select random();").expect("no system errors");
+            sql_engine
+                .execute("select setseed(0.25);")
+                .expect("no system errors")
+                .expect("seed set");
+
+            assert_ne!(first, second);
+            assert_eq!(sql_engine.execute("select random();").expect("no system errors"), first);
+            assert_eq!(
+                sql_engine.execute("select random();").expect("no system errors"),
+                second
+            );
+        }
+
+        #[rstest::rstest]
+        fn seed_out_of_range(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine.execute("select setseed(1.5);").expect("no system errors"),
+                Err(QueryError::value_out_of_range(
+                    "setseed parameter 1.5 is out of allowed range [-1,1]".to_owned()
+                ))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod system_relations {
+        use super::*;
+
+        #[rstest::fixture]
+        fn sql_engine_with_table(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (id serial primary key, name varchar(20));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+        }
+
+        #[rstest::rstest]
+        fn select_tables(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select table_schema, table_name from information_schema.tables;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("table_schema".to_owned(), SqlType::VarChar(63)),
+                        ("table_name".to_owned(), SqlType::VarChar(63))
+                    ],
+                    vec![vec!["schema_name".to_owned(), "table_name".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn select_columns(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute(
+                        "select column_name, column_default, is_nullable, data_type from information_schema.columns;"
+                    )
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("column_name".to_owned(), SqlType::VarChar(63)),
+                        ("column_default".to_owned(), SqlType::VarChar(255)),
+                        ("is_nullable".to_owned(), SqlType::VarChar(3)),
+                        ("data_type".to_owned(), SqlType::VarChar(63))
+                    ],
+                    vec![
+                        vec![
+                            "id".to_owned(),
+                            "nextval('schema_name.table_name_id_seq'::regclass)".to_owned(),
+                            "NO".to_owned(),
+                            "integer".to_owned()
+                        ],
+                        vec![
+                            "name".to_owned(),
+                            "".to_owned(),
+                            "YES".to_owned(),
+                            "character varying".to_owned()
+                        ]
+                    ]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn select_from_pg_catalog(mut sql_engine_with_table: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_table
+                    .execute("select * from pg_tables;")
+                    .expect("no system errors"),
+                sql_engine_with_table
+                    .execute("select * from pg_catalog.pg_tables;")
+                    .expect("no system errors")
+            );
+        }
+
+        #[rstest::rstest]
+        fn select_non_existent_column(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select relname from pg_catalog.pg_namespace;")
+                    .expect("no system errors"),
+                Err(QueryError::column_does_not_exist(vec!["relname".to_owned()]))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod where_clause {
+        use super::*;
+
+        #[rstest::fixture]
+        fn sql_engine_with_rows(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (id integer primary key, name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.table_name values (3, 'c'), (1, 'a'), (2, 'b');")
+                .expect("no system errors")
+                .expect("rows inserted");
+            sql_engine
+        }
+
+        fn names(values: Vec<&str>) -> std::result::Result<QueryEvent, QueryError> {
+            Ok(QueryEvent::RecordsSelected((
+                vec![("name".to_owned(), SqlType::VarChar(10))],
+                values.into_iter().map(|value| vec![value.to_owned()]).collect(),
+            )))
+        }
+
+        #[rstest::rstest]
+        fn range_of_primary_key(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where id >= 2;")
+                    .expect("no system errors"),
+                names(vec!["b", "c"])
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where 2 > id and name <> 'b';")
+                    .expect("no system errors"),
+                names(vec!["a"])
+            );
+        }
+
+        #[rstest::rstest]
+        fn predicate_on_other_columns(mut sql_engine_with_rows: InMemorySqlEngine) {
+            // rows that are not read through the index come in the order they were inserted
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where name in ('a', 'c') or id is null;")
+                    .expect("no system errors"),
+                names(vec!["c", "a"])
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where id between 5 and 10;")
+                    .expect("no system errors"),
+                names(vec![])
+            );
+        }
+
+        #[rstest::rstest]
+        fn non_existent_column(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where age > 1;")
+                    .expect("no system errors"),
+                Err(QueryError::column_does_not_exist(vec!["age".to_owned()]))
+            );
+        }
+
+        #[rstest::rstest]
+        fn predicate_that_is_not_boolean(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where id;")
+                    .expect("no system errors"),
+                Err(QueryError::datatype_mismatch(
+                    "WHERE".to_owned(),
+                    "boolean".to_owned(),
+                    "integer".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn division_by_zero_in_predicate(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where 1 / (id - 2) > 0;")
+                    .expect("no system errors"),
+                Err(QueryError::division_by_zero())
+            );
+        }
+
+        #[rstest::rstest]
+        fn system_relation(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select table_name from information_schema.tables where table_schema = 'public';")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("table_name".to_owned(), SqlType::VarChar(63))],
+                    vec![]
+                )))
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod order_and_limit {
+        use super::*;
+
+        #[rstest::fixture]
+        fn sql_engine_with_rows(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (id integer primary key, name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.table_name values (3, 'c'), (1, 'a'), (4, 'a'), (2, 'b');")
+                .expect("no system errors")
+                .expect("rows inserted");
+            sql_engine
+        }
+
+        fn rows(columns: Vec<(&str, SqlType)>, values: Vec<Vec<&str>>) -> std::result::Result<QueryEvent, QueryError> {
+            Ok(QueryEvent::RecordsSelected((
+                columns
+                    .into_iter()
+                    .map(|(name, sql_type)| (name.to_owned(), sql_type))
+                    .collect(),
+                values
+                    .into_iter()
+                    .map(|row| row.into_iter().map(str::to_owned).collect())
+                    .collect(),
+            )))
+        }
+
+        #[rstest::rstest]
+        fn sorted_by_columns(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select id from schema_name.table_name order by name desc, id;")
+                    .expect("no system errors"),
+                rows(
+                    vec![("id", SqlType::Integer)],
+                    vec![vec!["3"], vec!["2"], vec!["1"], vec!["4"]]
+                )
+            );
+        }
+
+        #[rstest::rstest]
+        fn sorted_by_position(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name, id from schema_name.table_name where id > 1 order by 2 desc;")
+                    .expect("no system errors"),
+                rows(
+                    vec![("name", SqlType::VarChar(10)), ("id", SqlType::Integer)],
+                    vec![vec!["a", "4"], vec!["c", "3"], vec!["b", "2"]]
+                )
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name order by 2;")
+                    .expect("no system errors"),
+                Err(QueryError::invalid_column_reference(
+                    "ORDER BY".to_owned(),
+                    "2".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn sorted_with_nulls(mut sql_engine_with_rows: InMemorySqlEngine) {
+            // `false or null` is null, so rows with id less than 3 have null keys
+            let sorted = |sql_engine: &mut InMemorySqlEngine, order: &str| {
+                sql_engine
+                    .execute(&format!(
+                        "select id from schema_name.table_name order by (id > 2 or null) {}, id;",
+                        order
+                    ))
+                    .expect("no system errors")
+            };
+            let nulls_last = rows(
+                vec![("id", SqlType::Integer)],
+                vec![vec!["3"], vec!["4"], vec!["1"], vec!["2"]],
+            );
+            let nulls_first = rows(
+                vec![("id", SqlType::Integer)],
+                vec![vec!["1"], vec!["2"], vec!["3"], vec!["4"]],
+            );
+
+            assert_eq!(sorted(&mut sql_engine_with_rows, "asc"), nulls_last);
+            assert_eq!(sorted(&mut sql_engine_with_rows, "asc nulls first"), nulls_first);
+            assert_eq!(sorted(&mut sql_engine_with_rows, "desc"), nulls_first);
+            assert_eq!(sorted(&mut sql_engine_with_rows, "desc nulls last"), nulls_last);
+        }
+
+        #[rstest::rstest]
+        fn limit_and_offset(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select id from schema_name.table_name order by id limit 2 offset 1;")
+                    .expect("no system errors"),
+                rows(vec![("id", SqlType::Integer)], vec![vec!["2"], vec!["3"]])
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select id from schema_name.table_name order by id desc offset 3;")
+                    .expect("no system errors"),
+                rows(vec![("id", SqlType::Integer)], vec![vec!["1"]])
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select id from schema_name.table_name where name = 'a' limit 0;")
+                    .expect("no system errors"),
+                rows(vec![("id", SqlType::Integer)], vec![])
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select id from schema_name.table_name order by id limit all;")
+                    .expect("no system errors"),
+                rows(
+                    vec![("id", SqlType::Integer)],
+                    vec![vec!["1"], vec!["2"], vec!["3"], vec!["4"]]
+                )
+            );
+        }
+
+        // sql parser accepts only numbers in LIMIT and OFFSET, so negative
+        // ones are given to the handler directly
+        #[rstest::rstest]
+        fn negative_row_counts(sql_engine: InMemorySqlEngine) {
+            let minus_one = sqlparser::ast::Expr::UnaryOp {
+                op: sqlparser::ast::UnaryOperator::Minus,
+                expr: Box::new(sqlparser::ast::Expr::Value(sqlparser::ast::Value::Number(
+                    "1".to_owned(),
+                ))),
+            };
+
+            assert_eq!(
+                sql_engine.row_limits(vec![], Some(&minus_one), None, "").err(),
+                Some(QueryError::invalid_row_count_in_limit_clause())
+            );
+            assert_eq!(
+                sql_engine.row_limits(vec![], None, Some(&minus_one), "").err(),
+                Some(QueryError::invalid_row_count_in_result_offset_clause())
+            );
+        }
+
+        #[rstest::rstest]
+        fn series(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("select * from generate_series(1, 5) order by 1 desc limit 2;")
+                    .expect("no system errors"),
+                rows(vec![("generate_series", SqlType::Integer)], vec![vec!["5"], vec!["4"]])
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod aggregates {
+        use super::*;
+
+        #[rstest::fixture]
+        fn sql_engine_with_rows(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute(
+                    "create table schema_name.table_name (id integer primary key, name varchar(10), amount bigint);",
+                )
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.table_name values (1, 'a', 10), (2, 'b', 20), (3, 'a', 30);")
+                .expect("no system errors")
+                .expect("rows inserted");
+            sql_engine
+        }
+
+        #[rstest::rstest]
+        fn aggregates_of_all_rows(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select count(*), sum(amount), min(name), max(id) from schema_name.table_name where id > 1;"
+                    )
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("count".to_owned(), SqlType::BigInt),
+                        ("sum".to_owned(), SqlType::BigInt),
+                        ("min".to_owned(), SqlType::VarChar(10)),
+                        ("max".to_owned(), SqlType::Integer),
+                    ],
+                    vec![vec!["2".to_owned(), "50".to_owned(), "a".to_owned(), "3".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn aggregates_of_no_rows(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select count(amount), avg(amount) from schema_name.table_name where id > 10;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("count".to_owned(), SqlType::BigInt),
+                        ("avg".to_owned(), SqlType::Decimal),
+                    ],
+                    vec![vec!["0".to_owned(), "".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn group_by_and_having(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select name, sum(amount) as total from schema_name.table_name \
+                        group by name having count(*) > 1 or min(amount) = 20 order by total desc;"
+                    )
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![
+                        ("name".to_owned(), SqlType::VarChar(10)),
+                        ("total".to_owned(), SqlType::BigInt),
+                    ],
+                    vec![
+                        vec!["a".to_owned(), "40".to_owned()],
+                        vec!["b".to_owned(), "20".to_owned()]
+                    ]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn groups_sorted_with_nulls(mut sql_engine_with_rows: InMemorySqlEngine) {
+            // group `a` has key `false or null` that is null
+            let sorted = |sql_engine: &mut InMemorySqlEngine, order: &str| {
+                sql_engine
+                    .execute(&format!(
+                        "select name from schema_name.table_name group by name order by (min(amount) > 10 or null) {};",
+                        order
+                    ))
+                    .expect("no system errors")
+            };
+            let names = |names: Vec<&str>| {
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("name".to_owned(), SqlType::VarChar(10))],
+                    names.into_iter().map(|name| vec![name.to_owned()]).collect(),
+                )))
+            };
+
+            assert_eq!(sorted(&mut sql_engine_with_rows, ""), names(vec!["b", "a"]));
+            assert_eq!(sorted(&mut sql_engine_with_rows, "nulls first"), names(vec!["a", "b"]));
+            assert_eq!(sorted(&mut sql_engine_with_rows, "desc"), names(vec!["a", "b"]));
+            assert_eq!(sorted(&mut sql_engine_with_rows, "desc nulls last"), names(vec!["b", "a"]));
+        }
+
+        #[rstest::rstest]
+        fn ungrouped_column(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name, count(*) from schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::ungrouped_column("name".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn aggregate_in_where(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where count(*) > 1;")
+                    .expect("no system errors"),
+                Err(QueryError::aggregate_not_allowed("WHERE".to_owned()))
+            );
+        }
+    }
+
+    fn in_memory_storage() -> Arc<RwLock<FrontendStorage<InMemoryStorage>>> {
+        Arc::new(RwLock::new(FrontendStorage::new(InMemoryStorage::default()).unwrap()))
+    }
+
+    // for behaviour that depends on the backend that the node runs with
+    fn sled_storage() -> Arc<RwLock<FrontendStorage<SledBackendStorage>>> {
+        Arc::new(RwLock::new(FrontendStorage::default().unwrap()))
+    }
+
+    #[cfg(test)]
+    mod prepared_statements {
+        use super::*;
+
+        #[rstest::fixture]
+        fn sql_engine_with_rows(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            sql_engine
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (id integer primary key, name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.table_name values (1, 'a'), (2, 'b'), (3, 'c');")
+                .expect("no system errors")
+                .expect("rows inserted");
+            sql_engine
+        }
+
+        #[rstest::rstest]
+        fn prepare_and_deallocate(mut sql_engine: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine
+                    .execute("prepare select_one as select 1;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::StatementPrepared)
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("prepare select_one as select 2;")
+                    .expect("no system errors"),
+                Err(QueryError::prepared_statement_already_exists("select_one".to_owned()))
+            );
+            assert_eq!(
+                sql_engine.execute("deallocate select_one;").expect("no system errors"),
+                Ok(QueryEvent::StatementDeallocated)
+            );
+            assert_eq!(
+                sql_engine.execute("deallocate select_one;").expect("no system errors"),
+                Err(QueryError::prepared_statement_does_not_exist("select_one".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn deallocate_all(mut sql_engine: InMemorySqlEngine) {
+            sql_engine.prepare("", "select 1;", vec![]).expect("statement prepared");
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("col1".to_owned(), SqlType::SmallInt),
-                    ("col2".to_owned(), SqlType::SmallInt),
-                    ("col3".to_owned(), SqlType::SmallInt),
-                ],
-                vec![
-                    vec!["111".to_owned(), "222".to_owned(), "333".to_owned()],
-                    vec!["444".to_owned(), "555".to_owned(), "666".to_owned()],
-                ]
-            )))
-        );
-        assert_eq!(
+                .execute("prepare select_two as select 2;")
+                .expect("no system errors")
+                .expect("statement prepared");
+
+            assert_eq!(
+                sql_engine.execute("deallocate all;").expect("no system errors"),
+                Ok(QueryEvent::AllStatementsDeallocated)
+            );
+            assert_eq!(
+                sql_engine.bind("", "", vec![]),
+                Err(QueryError::prepared_statement_does_not_exist("".to_owned()))
+            );
+            assert_eq!(
+                sql_engine.execute("execute select_two;").expect("no system errors"),
+                Err(QueryError::prepared_statement_does_not_exist("select_two".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn execute_with_wrong_number_of_parameters(mut sql_engine: InMemorySqlEngine) {
             sql_engine
-                .execute("update schema_name.table_name set col3=777, col1=999;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsUpdated(2))
-        );
-        assert_eq!(
+                .execute("prepare select_id (integer) as select $1;")
+                .expect("no system errors")
+                .expect("statement prepared");
+
+            assert_eq!(
+                sql_engine.execute("execute select_id;").expect("no system errors"),
+                Err(QueryError::wrong_number_of_parameters("select_id".to_owned()))
+            );
+            assert_eq!(
+                sql_engine.bind("", "select_id", vec![]),
+                Err(QueryError::bind_parameters_mismatch("select_id".to_owned(), 0, 1))
+            );
+            assert_eq!(
+                sql_engine.bind("", "select_id", vec![Some("one".to_owned())]),
+                Err(QueryError::invalid_text_representation(
+                    "integer".to_owned(),
+                    "one".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn execute_prepared_statement(mut sql_engine_with_rows: InMemorySqlEngine) {
+            sql_engine_with_rows
+                .execute("prepare select_name (integer) as select name from schema_name.table_name where id = $1;")
+                .expect("no system errors")
+                .expect("statement prepared");
+
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("execute select_name(2);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsSelected((
+                    vec![("name".to_owned(), SqlType::VarChar(10))],
+                    vec![vec!["b".to_owned()]]
+                )))
+            );
+        }
+
+        #[rstest::rstest]
+        fn describe_and_execute_portal_in_pages(mut sql_engine_with_rows: InMemorySqlEngine) {
+            sql_engine_with_rows
+                .prepare("", "select id from schema_name.table_name where id > $1;", vec![])
+                .expect("statement prepared");
+            sql_engine_with_rows
+                .bind("", "", vec![Some("1".to_owned())])
+                .expect("portal created");
+
+            assert_eq!(
+                sql_engine_with_rows.describe_portal("").expect("no system errors"),
+                Ok(Some(vec![("id".to_owned(), SqlType::Integer)]))
+            );
+            assert_eq!(
+                sql_engine_with_rows.execute_portal("", 1).expect("no system errors"),
+                Ok(PortalEvent::Suspended(vec![vec!["2".to_owned()]]))
+            );
+            assert_eq!(
+                sql_engine_with_rows.execute_portal("", 2).expect("no system errors"),
+                Ok(PortalEvent::Completed(QueryEvent::RecordsSelected((
+                    vec![("id".to_owned(), SqlType::Integer)],
+                    vec![vec!["3".to_owned()]]
+                ))))
+            );
+        }
+
+        #[rstest::rstest]
+        fn portals_are_closed_at_sync(mut sql_engine: InMemorySqlEngine) {
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("col1".to_owned(), SqlType::SmallInt),
-                    ("col2".to_owned(), SqlType::SmallInt),
-                    ("col3".to_owned(), SqlType::SmallInt),
-                ],
-                vec![
-                    vec!["999".to_owned(), "222".to_owned(), "777".to_owned()],
-                    vec!["999".to_owned(), "555".to_owned(), "777".to_owned()],
-                ]
-            )))
-        );
-    }
+                .prepare("checkpoint", "checkpoint;", vec![])
+                .expect("statement prepared");
+            sql_engine.bind("portal", "checkpoint", vec![]).expect("portal created");
 
-    #[rstest::rstest]
-    fn update_all_records_in_multiple_columns(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_1 smallint, column_2 smallint, column_3 smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name values (1, 2, 3), (4, 5, 6), (7, 8, 9);")
-            .expect("no system errors")
-            .expect("rows inserted");
+            assert_eq!(
+                sql_engine.bind("portal", "checkpoint", vec![]),
+                Err(QueryError::portal_already_exists("portal".to_owned()))
+            );
+            assert_eq!(
+                sql_engine.describe_portal("portal").expect("no system errors"),
+                Ok(None)
+            );
+            assert_eq!(
+                sql_engine.execute_portal("portal", 0).expect("no system errors"),
+                Ok(PortalEvent::Completed(QueryEvent::Checkpoint))
+            );
 
-        assert_eq!(
+            sql_engine.sync();
+
+            assert_eq!(
+                sql_engine.execute_portal("portal", 0).expect("no system errors"),
+                Err(QueryError::portal_does_not_exist("portal".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn least_recently_used_statement_is_deallocated() {
+            let mut session = Session::default();
+            session.set("max_prepared_statements", "2").expect("limit is set");
+            let mut sql_engine = Handler::new(in_memory_storage()).with_session(session);
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("column_1".to_owned(), SqlType::SmallInt),
-                    ("column_2".to_owned(), SqlType::SmallInt),
-                    ("column_3".to_owned(), SqlType::SmallInt)
-                ],
-                vec![
-                    vec!["1".to_owned(), "2".to_owned(), "3".to_owned()],
-                    vec!["4".to_owned(), "5".to_owned(), "6".to_owned()],
-                    vec!["7".to_owned(), "8".to_owned(), "9".to_owned()]
-                ]
-            )))
-        );
-        assert_eq!(
+                .prepare("first", "checkpoint;", vec![])
+                .expect("statement prepared");
             sql_engine
-                .execute("update schema_name.table_name set column_1=10, column_2=-20, column_3=30;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsUpdated(3))
-        );
-        assert_eq!(
+                .prepare("second", "checkpoint;", vec![])
+                .expect("statement prepared");
+            sql_engine.bind("", "first", vec![]).expect("portal created");
+
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("column_1".to_owned(), SqlType::SmallInt),
-                    ("column_2".to_owned(), SqlType::SmallInt),
-                    ("column_3".to_owned(), SqlType::SmallInt)
-                ],
-                vec![
-                    vec!["10".to_owned(), "-20".to_owned(), "30".to_owned()],
-                    vec!["10".to_owned(), "-20".to_owned(), "30".to_owned()],
-                    vec!["10".to_owned(), "-20".to_owned(), "30".to_owned()]
-                ]
-            )))
-        );
-    }
+                .prepare("third", "checkpoint;", vec![])
+                .expect("statement prepared");
 
-    #[rstest::rstest]
-    fn update_records_in_nonexistent_table(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
+            assert_eq!(sql_engine.bind("", "first", vec![]), Ok(()));
+            assert_eq!(
+                sql_engine.bind("", "second", vec![]),
+                Err(QueryError::prepared_statement_does_not_exist("second".to_owned()))
+            );
+            assert_eq!(sql_engine.bind("", "third", vec![]), Ok(()));
+        }
 
-        assert_eq!(
+        #[rstest::rstest]
+        fn close_statement(mut sql_engine: InMemorySqlEngine) {
             sql_engine
-                .execute("update schema_name.table_name set column_test=789;")
-                .expect("no system errors"),
-            Err(QueryError::table_does_not_exist("schema_name.table_name".to_owned()))
-        );
+                .prepare("statement", "checkpoint;", vec![])
+                .expect("statement prepared");
+
+            sql_engine.close_statement("statement");
+            sql_engine.close_statement("does_not_exist");
+
+            assert_eq!(
+                sql_engine.describe_statement("statement").expect("no system errors"),
+                Err(QueryError::prepared_statement_does_not_exist("statement".to_owned()))
+            );
+        }
     }
 
-    #[rstest::rstest]
-    fn update_non_existent_columns_of_records(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_test smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name values (123);")
-            .expect("no system errors")
-            .expect("row inserted");
+    mod subqueries {
+        use super::*;
 
-        assert_eq!(
+        #[rstest::fixture]
+        fn sql_engine_with_rows(mut sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![("column_test".to_owned(), SqlType::SmallInt)],
-                vec![vec!["123".to_owned()]],
-            )))
-        );
-        assert_eq!(
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
             sql_engine
-                .execute("update schema_name.table_name set col1=456, col2=789;")
-                .expect("no system errors"),
-            Err(QueryError::column_does_not_exist(vec![
-                "col1".to_owned(),
-                "col2".to_owned()
-            ]))
-        );
-    }
-
-    #[rstest::rstest]
-    fn delete_all_records(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_test smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name values (123);")
-            .expect("no system errors")
-            .expect("row inserted");
-        sql_engine
-            .execute("insert into schema_name.table_name values (456);")
-            .expect("no system errors")
-            .expect("row inserted");
-        assert_eq!(
+                .execute("create table schema_name.table_name (id integer primary key, name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![("column_test".to_owned(), SqlType::SmallInt)],
-                vec![vec!["123".to_owned()], vec!["456".to_owned()]]
-            )))
-        );
-        assert_eq!(
+                .execute("insert into schema_name.table_name values (1, 'a'), (2, 'b'), (3, 'c');")
+                .expect("no system errors")
+                .expect("rows inserted");
             sql_engine
-                .execute("delete from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsDeleted(2))
-        );
-        assert_eq!(
+                .execute("create table schema_name.orders (id integer primary key, customer integer);")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.orders values (10, 1), (20, 3), (30, 3);")
+                .expect("no system errors")
+                .expect("rows inserted");
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
+        }
+
+        fn names(values: Vec<&str>) -> std::result::Result<QueryEvent, QueryError> {
             Ok(QueryEvent::RecordsSelected((
-                vec![("column_test".to_owned(), SqlType::SmallInt)],
-                vec![]
+                vec![("name".to_owned(), SqlType::VarChar(10))],
+                values.into_iter().map(|value| vec![value.to_owned()]).collect(),
             )))
-        );
-    }
+        }
 
-    #[rstest::rstest]
-    fn select_all_from_table_with_multiple_columns(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_1 smallint, column_2 smallint, column_3 smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name values (123, 456, 789);")
-            .expect("no system errors")
-            .expect("row inserted");
+        #[rstest::rstest]
+        fn in_subquery(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where id in (select customer from schema_name.orders);")
+                    .expect("no system errors"),
+                names(vec!["a", "c"])
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select name from schema_name.table_name where id not in (select customer from schema_name.orders);"
+                    )
+                    .expect("no system errors"),
+                names(vec!["b"])
+            );
+        }
 
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("column_1".to_owned(), SqlType::SmallInt),
-                    ("column_2".to_owned(), SqlType::SmallInt),
-                    ("column_3".to_owned(), SqlType::SmallInt)
-                ],
-                vec![vec!["123".to_owned(), "456".to_owned(), "789".to_owned()]]
-            )))
-        );
+        #[rstest::rstest]
+        fn exists(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select name from schema_name.table_name \
+                        where exists (select id from schema_name.orders where customer = 2);"
+                    )
+                    .expect("no system errors"),
+                names(vec![])
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select name from schema_name.table_name \
+                        where not exists (select id from schema_name.orders where customer = 2);"
+                    )
+                    .expect("no system errors"),
+                names(vec!["a", "b", "c"])
+            );
+        }
+
+        #[rstest::rstest]
+        fn scalar_subquery(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select name from schema_name.table_name \
+                        where id = (select customer from schema_name.orders where id = 10);"
+                    )
+                    .expect("no system errors"),
+                names(vec!["a"])
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select name from schema_name.table_name \
+                        where id = (select customer from schema_name.orders where id = 40);"
+                    )
+                    .expect("no system errors"),
+                names(vec![])
+            );
+        }
+
+        #[rstest::rstest]
+        fn scalar_subquery_with_aggregate(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where id < (select max(customer) from schema_name.orders);")
+                    .expect("no system errors"),
+                names(vec!["a", "b"])
+            );
+        }
+
+        #[rstest::rstest]
+        fn scalar_subquery_returns_many_rows(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select name from schema_name.table_name where id = (select customer from schema_name.orders);"
+                    )
+                    .expect("no system errors"),
+                Err(QueryError::more_than_one_row_returned_by_subquery())
+            );
+        }
+
+        #[rstest::rstest]
+        fn in_subquery_with_many_columns(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select name from schema_name.table_name where id in (select * from schema_name.orders);")
+                    .expect("no system errors"),
+                Err(QueryError::subquery_has_too_many_columns())
+            );
+        }
+
+        #[rstest::rstest]
+        fn error_of_subquery(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute(
+                        "select name from schema_name.table_name where id in (select id from schema_name.missing);"
+                    )
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.missing".to_owned()))
+            );
+        }
     }
 
-    #[rstest::rstest]
-    fn insert_multiple_rows(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_1 smallint, column_2 smallint, column_3 smallint);")
-            .expect("no system errors")
-            .expect("table created");
+    mod update_where {
+        use super::*;
 
-        assert_eq!(
+        #[rstest::fixture]
+        fn sql_engine_with_rows(sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            with_rows(sql_engine)
+        }
+
+        fn with_rows<P: BackendStorage>(mut sql_engine: Handler<P>) -> Handler<P> {
             sql_engine
-                .execute("insert into schema_name.table_name values (1, 4, 7), (2, 5, 8), (3, 6, 9);")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsInserted(3))
-        );
-        assert_eq!(
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
+                .execute(
+                    "create table schema_name.table_name (id integer primary key, name varchar(10), amount bigint);",
+                )
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.table_name values (1, 'a', 10), (2, 'b', 20), (3, 'c', 30);")
+                .expect("no system errors")
+                .expect("rows inserted");
+            sql_engine
+        }
+
+        fn rows(values: Vec<(&str, &str, &str)>) -> std::result::Result<QueryEvent, QueryError> {
             Ok(QueryEvent::RecordsSelected((
                 vec![
-                    ("column_1".to_owned(), SqlType::SmallInt),
-                    ("column_2".to_owned(), SqlType::SmallInt),
-                    ("column_3".to_owned(), SqlType::SmallInt)
+                    ("id".to_owned(), SqlType::Integer),
+                    ("name".to_owned(), SqlType::VarChar(10)),
+                    ("amount".to_owned(), SqlType::BigInt),
                 ],
-                vec![
-                    vec!["1".to_owned(), "4".to_owned(), "7".to_owned()],
-                    vec!["2".to_owned(), "5".to_owned(), "8".to_owned()],
-                    vec!["3".to_owned(), "6".to_owned(), "9".to_owned()],
-                ]
+                values
+                    .into_iter()
+                    .map(|(id, name, amount)| vec![id.to_owned(), name.to_owned(), amount.to_owned()])
+                    .collect(),
             )))
-        );
+        }
+
+        #[rstest::rstest]
+        fn update_rows_that_satisfy_predicate(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("update schema_name.table_name set amount = amount * 2 + id, name = 'z' where id >= 2;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsUpdated(2))
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                rows(vec![("1", "a", "10"), ("2", "z", "42"), ("3", "z", "63")])
+            );
+        }
+
+        #[rstest::rstest]
+        fn update_primary_key(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("update schema_name.table_name set id = id + 10 where name <> 'b';")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsUpdated(2))
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select * from schema_name.table_name where id > 10;")
+                    .expect("no system errors"),
+                rows(vec![("11", "a", "10"), ("13", "c", "30")])
+            );
+        }
+
+        #[rstest::rstest]
+        fn update_with_duplicate_primary_key(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("update schema_name.table_name set id = 3 where id = 1;")
+                    .expect("no system errors"),
+                Err(QueryError::unique_violation("table_name_pkey".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn update_no_rows(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("update schema_name.table_name set amount = 0 where id > 5;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsUpdated(0))
+            );
+        }
+
+        #[test]
+        fn update_no_rows_in_sled() {
+            let mut sql_engine = with_rows(Handler::new(sled_storage()));
+
+            assert_eq!(
+                sql_engine
+                    .execute("update schema_name.table_name set amount = 0 where id > 5;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsUpdated(0))
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select * from schema_name.table_name where id = 1;")
+                    .expect("no system errors"),
+                rows(vec![("1", "a", "10")])
+            );
+        }
+
+        #[rstest::rstest]
+        fn predicate_with_non_existent_column(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("update schema_name.table_name set amount = 0 where missing = 1;")
+                    .expect("no system errors"),
+                Err(QueryError::column_does_not_exist(vec!["missing".to_owned()]))
+            );
+        }
     }
 
-    #[rstest::rstest]
-    fn select_not_all_columns(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_1 smallint, column_2 smallint, column_3 smallint);")
-            .expect("no system errors")
-            .expect("table created");
-        sql_engine
-            .execute("insert into schema_name.table_name values (1, 4, 7), (2, 5, 8), (3, 6, 9);")
-            .expect("no system errors")
-            .expect("rows inserted");
+    mod delete_where {
+        use super::*;
 
-        assert_eq!(
+        #[rstest::fixture]
+        fn sql_engine_with_rows(sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            with_rows(sql_engine)
+        }
+
+        fn with_rows<P: BackendStorage>(mut sql_engine: Handler<P>) -> Handler<P> {
             sql_engine
-                .execute("select column_3, column_2 from schema_name.table_name;")
-                .expect("no system errors"),
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
+            sql_engine
+                .execute("create table schema_name.table_name (id integer primary key, name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
+            sql_engine
+                .execute("insert into schema_name.table_name values (1, 'a'), (2, 'b'), (3, 'c');")
+                .expect("no system errors")
+                .expect("rows inserted");
+            sql_engine
+        }
+
+        fn rows(values: Vec<(&str, &str)>) -> std::result::Result<QueryEvent, QueryError> {
             Ok(QueryEvent::RecordsSelected((
                 vec![
-                    ("column_3".to_owned(), SqlType::SmallInt),
-                    ("column_2".to_owned(), SqlType::SmallInt),
+                    ("id".to_owned(), SqlType::Integer),
+                    ("name".to_owned(), SqlType::VarChar(10)),
                 ],
-                vec![
-                    vec!["7".to_owned(), "4".to_owned()],
-                    vec!["8".to_owned(), "5".to_owned()],
-                    vec!["9".to_owned(), "6".to_owned()],
-                ]
+                values
+                    .into_iter()
+                    .map(|(id, name)| vec![id.to_owned(), name.to_owned()])
+                    .collect(),
             )))
-        );
-    }
+        }
 
-    #[rstest::rstest]
-    fn select_non_existing_columns_from_table(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
-        sql_engine
-            .execute("create table schema_name.table_name (column_in_table smallint);")
-            .expect("no system errors")
-            .expect("table created");
+        #[rstest::rstest]
+        fn delete_rows_that_satisfy_predicate(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("delete from schema_name.table_name where id >= 2 and name <> 'c';")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsDeleted(1))
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                rows(vec![("1", "a"), ("3", "c")])
+            );
+        }
+
+        #[rstest::rstest]
+        fn deleted_primary_key_can_be_inserted_again(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("delete from schema_name.table_name where id = 2;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsDeleted(1))
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("insert into schema_name.table_name values (2, 'z');")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsInserted(1))
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select * from schema_name.table_name where id = 2;")
+                    .expect("no system errors"),
+                rows(vec![("2", "z")])
+            );
+        }
+
+        #[rstest::rstest]
+        fn delete_no_rows(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("delete from schema_name.table_name where id > 5;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsDeleted(0))
+            );
+        }
+
+        #[test]
+        fn delete_no_rows_in_sled() {
+            let mut sql_engine = with_rows(Handler::new(sled_storage()));
+
+            assert_eq!(
+                sql_engine
+                    .execute("delete from schema_name.table_name where id > 5;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsDeleted(0))
+            );
+            assert_eq!(
+                sql_engine
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                rows(vec![("1", "a"), ("2", "b"), ("3", "c")])
+            );
+        }
 
-        assert_eq!(
-            sql_engine
-                .execute("select column_not_in_table1, column_not_in_table2 from schema_name.table_name;")
-                .expect("no system errors"),
-            Err(QueryError::column_does_not_exist(vec![
-                "column_not_in_table1".to_owned(),
-                "column_not_in_table2".to_owned()
-            ]))
-        );
-    }
+        #[rstest::rstest]
+        fn predicate_with_non_existent_column(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("delete from schema_name.table_name where missing = 1;")
+                    .expect("no system errors"),
+                Err(QueryError::column_does_not_exist(vec!["missing".to_owned()]))
+            );
+        }
 
-    #[rstest::rstest]
-    fn create_table_with_different_types(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
+        #[rstest::rstest]
+        fn truncate_table(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("truncate table schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableTruncated)
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                rows(vec![])
+            );
+        }
 
-        assert_eq!(
-            sql_engine
-                .execute("create table schema_name.table_name (column_si smallint, column_i integer, column_bi bigint, column_c char(10), column_vc varchar(10));")
-                .expect("no system errors"),
-            Ok(QueryEvent::TableCreated)
-        )
+        #[rstest::rstest]
+        fn truncate_non_existent_table(mut sql_engine_with_rows: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("truncate schema_name.table_name, schema_name.non_existent;")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.non_existent".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select * from schema_name.table_name where id = 1;")
+                    .expect("no system errors"),
+                rows(vec![("1", "a")])
+            );
+        }
+
+        #[rstest::rstest]
+        fn rollback_truncate(mut sql_engine_with_rows: InMemorySqlEngine) {
+            sql_engine_with_rows
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("truncate schema_name.table_name;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::TableTruncated)
+            );
+            sql_engine_with_rows
+                .execute("rollback;")
+                .expect("no system errors")
+                .expect("transaction rolled back");
+            assert_eq!(
+                sql_engine_with_rows
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                rows(vec![("1", "a"), ("2", "b"), ("3", "c")])
+            );
+        }
     }
 
-    #[rstest::rstest]
-    fn insert_and_select_different_integer_types(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
+    mod indexes {
+        use super::*;
 
-        sql_engine
-            .execute("create table schema_name.table_name (column_si smallint, column_i integer, column_bi bigint);")
-            .expect("no system errors")
-            .expect("table created");
+        #[rstest::fixture]
+        fn sql_engine_with_index(sql_engine: InMemorySqlEngine) -> InMemorySqlEngine {
+            with_index(sql_engine)
+        }
 
-        assert_eq!(
+        fn with_index<P: BackendStorage>(mut sql_engine: Handler<P>) -> Handler<P> {
             sql_engine
-                .execute("insert into schema_name.table_name values(-32768, -2147483648, -9223372036854775808);")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsInserted(1))
-        );
-
-        assert_eq!(
+                .execute("create schema schema_name;")
+                .expect("no system errors")
+                .expect("schema created");
             sql_engine
-                .execute("insert into schema_name.table_name values(32767, 2147483647, 9223372036854775807);")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsInserted(1))
-        );
-
-        assert_eq!(
+                .execute("create table schema_name.table_name (id integer, name varchar(10));")
+                .expect("no system errors")
+                .expect("table created");
             sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
+                .execute("insert into schema_name.table_name values (1, 'a'), (2, 'b'), (3, 'a');")
+                .expect("no system errors")
+                .expect("rows inserted");
+            assert_eq!(
+                sql_engine
+                    .execute("create index name_idx on schema_name.table_name (name);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::IndexCreated)
+            );
+            sql_engine
+        }
+
+        fn rows(values: Vec<(&str, &str)>) -> std::result::Result<QueryEvent, QueryError> {
             Ok(QueryEvent::RecordsSelected((
                 vec![
-                    ("column_si".to_owned(), SqlType::SmallInt),
-                    ("column_i".to_owned(), SqlType::Integer),
-                    ("column_bi".to_owned(), SqlType::BigInt),
+                    ("id".to_owned(), SqlType::Integer),
+                    ("name".to_owned(), SqlType::VarChar(10)),
                 ],
-                vec![
-                    vec![
-                        "-32768".to_owned(),
-                        "-2147483648".to_owned(),
-                        "-9223372036854775808".to_owned()
-                    ],
-                    vec![
-                        "32767".to_owned(),
-                        "2147483647".to_owned(),
-                        "9223372036854775807".to_owned()
-                    ],
-                ]
+                values
+                    .into_iter()
+                    .map(|(id, name)| vec![id.to_owned(), name.to_owned()])
+                    .collect(),
             )))
-        )
-    }
+        }
 
-    #[rstest::rstest]
-    fn insert_and_select_different_character_types(mut sql_engine: InMemorySqlEngine) {
-        sql_engine
-            .execute("create schema schema_name;")
-            .expect("no system errors")
-            .expect("schema created");
+        #[rstest::rstest]
+        fn select_by_indexed_column(mut sql_engine_with_index: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("select * from schema_name.table_name where name = 'a';")
+                    .expect("no system errors"),
+                rows(vec![("1", "a"), ("3", "a")])
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("select * from schema_name.table_name where 'a' = name and id > 1;")
+                    .expect("no system errors"),
+                rows(vec![("3", "a")])
+            );
+        }
 
-        sql_engine
-            .execute("create table schema_name.table_name (column_c char(10), column_vc varchar(10));")
-            .expect("no system errors")
-            .expect("table created");
+        #[rstest::rstest]
+        fn update_and_delete_by_indexed_column(mut sql_engine_with_index: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("update schema_name.table_name set name = 'c' where name = 'a' and id = 1;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsUpdated(1))
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("delete from schema_name.table_name where name = 'b';")
+                    .expect("no system errors"),
+                Ok(QueryEvent::RecordsDeleted(1))
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("select * from schema_name.table_name where name = 'c';")
+                    .expect("no system errors"),
+                rows(vec![("1", "c")])
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("select * from schema_name.table_name;")
+                    .expect("no system errors"),
+                rows(vec![("1", "c"), ("3", "a")])
+            );
+        }
 
-        assert_eq!(
-            sql_engine
-                .execute("insert into schema_name.table_name values('12345abcde', '12345abcde');")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsInserted(1))
-        );
+        #[test]
+        fn select_by_indexed_column_in_sled() {
+            let mut sql_engine = with_index(Handler::new(sled_storage()));
 
-        assert_eq!(
-            sql_engine
-                .execute("insert into schema_name.table_name values('12345abcde', 'abcde');")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsInserted(1))
-        );
+            assert_eq!(
+                sql_engine
+                    .execute("select * from schema_name.table_name where name = 'b';")
+                    .expect("no system errors"),
+                rows(vec![("2", "b")])
+            );
+        }
 
-        assert_eq!(
-            sql_engine
-                .execute("select * from schema_name.table_name;")
-                .expect("no system errors"),
-            Ok(QueryEvent::RecordsSelected((
-                vec![
-                    ("column_c".to_owned(), SqlType::Char(10)),
-                    ("column_vc".to_owned(), SqlType::VarChar(10))
-                ],
-                vec![
-                    vec!["12345abcde".to_owned(), "12345abcde".to_owned()],
-                    vec!["12345abcde".to_owned(), "abcde".to_owned()],
-                ]
-            )))
-        )
-    }
+        #[rstest::rstest]
+        fn create_index_with_existing_name(mut sql_engine_with_index: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create index name_idx on schema_name.table_name (id);")
+                    .expect("no system errors"),
+                Err(QueryError::index_already_exists("name_idx".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create index table_name on schema_name.table_name (id);")
+                    .expect("no system errors"),
+                Err(QueryError::index_already_exists("table_name".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create index if not exists name_idx on schema_name.table_name (id);")
+                    .expect("no system errors"),
+                Ok(QueryEvent::IndexCreated)
+            );
+            assert_eq!(
+                sql_engine_with_index.take_notices(),
+                vec![QueryError::index_already_exists_skipping("name_idx".to_owned())]
+            );
+        }
+
+        #[rstest::rstest]
+        fn create_index_that_is_not_supported(mut sql_engine_with_index: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create unique index id_idx on schema_name.table_name (id);")
+                    .expect("no system errors"),
+                Err(QueryError::feature_not_supported("unique index".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create index id_name_idx on schema_name.table_name (id, name);")
+                    .expect("no system errors"),
+                Err(QueryError::feature_not_supported(
+                    "index on multiple columns".to_owned()
+                ))
+            );
+        }
+
+        #[rstest::rstest]
+        fn create_index_on_non_existent_objects(mut sql_engine_with_index: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create index id_idx on schema_name.table_name (non_existent);")
+                    .expect("no system errors"),
+                Err(QueryError::column_does_not_exist(vec!["non_existent".to_owned()]))
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create index id_idx on schema_name.non_existent (id);")
+                    .expect("no system errors"),
+                Err(QueryError::table_does_not_exist("schema_name.non_existent".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create index id_idx on non_existent.table_name (id);")
+                    .expect("no system errors"),
+                Err(QueryError::schema_does_not_exist("non_existent".to_owned()))
+            );
+        }
+
+        #[rstest::rstest]
+        fn drop_index(mut sql_engine_with_index: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("drop index schema_name.name_idx;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::IndexDropped)
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("drop index schema_name.name_idx;")
+                    .expect("no system errors"),
+                Err(QueryError::index_does_not_exist("name_idx".to_owned()))
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("drop index if exists schema_name.name_idx;")
+                    .expect("no system errors"),
+                Ok(QueryEvent::IndexDropped)
+            );
+            assert_eq!(
+                sql_engine_with_index.take_notices(),
+                vec![QueryError::index_does_not_exist_skipping("name_idx".to_owned())]
+            );
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("select * from schema_name.table_name where name = 'a';")
+                    .expect("no system errors"),
+                rows(vec![("1", "a"), ("3", "a")])
+            );
+        }
+
+        #[rstest::rstest]
+        fn table_is_not_dropped_as_index(mut sql_engine_with_index: InMemorySqlEngine) {
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("drop index schema_name.table_name;")
+                    .expect("no system errors"),
+                Err(QueryError::index_does_not_exist("table_name".to_owned()))
+            );
+        }
 
-    fn in_memory_storage() -> Arc<Mutex<FrontendStorage<InMemoryStorage>>> {
-        Arc::new(Mutex::new(FrontendStorage::new(InMemoryStorage::default()).unwrap()))
+        #[rstest::rstest]
+        fn indexes_are_not_changed_in_transaction(mut sql_engine_with_index: InMemorySqlEngine) {
+            sql_engine_with_index
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("create index id_idx on schema_name.table_name (id);")
+                    .expect("no system errors"),
+                Err(QueryError::active_transaction("CREATE INDEX".to_owned()))
+            );
+            sql_engine_with_index
+                .execute("rollback;")
+                .expect("no system errors")
+                .expect("transaction rolled back");
+            sql_engine_with_index
+                .execute("begin;")
+                .expect("no system errors")
+                .expect("transaction started");
+            assert_eq!(
+                sql_engine_with_index
+                    .execute("drop index schema_name.name_idx;")
+                    .expect("no system errors"),
+                Err(QueryError::active_transaction("DROP INDEX".to_owned()))
+            );
+        }
     }
 }
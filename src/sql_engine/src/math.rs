@@ -0,0 +1,250 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::QueryError;
+use std::{cell::Cell, f64::consts::PI};
+
+const MULTIPLIER: u64 = 0x5_DEEC_E66D;
+const INCREMENT: u64 = 0xB;
+const MODULUS: u64 = 1 << 48;
+
+/// Generator of `random()` values of a session. It is the 48-bit linear
+/// congruential generator of `drand48`, so values that follow `setseed` are
+/// the same in every session
+#[derive(Debug)]
+pub struct Random {
+    state: Cell<u64>,
+}
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Random {
+        Random {
+            state: Cell::new(seed % MODULUS),
+        }
+    }
+
+    /// seed is in the range from -1 to 1
+    pub(crate) fn set_seed(&self, seed: f64) {
+        let seed = (seed * f64::from(i32::MAX)) as i32 as u32;
+        self.state.set((u64::from(seed) << 16) | 0x330E);
+    }
+
+    /// value in the range from 0 to 1, 1 is not included
+    pub(crate) fn next(&self) -> f64 {
+        let state = self.state.get().wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT) % MODULUS;
+        self.state.set(state);
+        state as f64 / MODULUS as f64
+    }
+}
+
+/// whether there is a math function with the name that takes that many
+/// arguments, all of them are taken in double precision
+pub(crate) fn is_defined(name: &str, args: usize) -> bool {
+    matches!(
+        (name, args),
+        ("pi", 0)
+            | ("log", 2)
+            | ("power", 2)
+            | ("pow", 2)
+            | ("atan2", 2)
+            | ("sqrt", 1)
+            | ("cbrt", 1)
+            | ("exp", 1)
+            | ("ln", 1)
+            | ("log", 1)
+            | ("sin", 1)
+            | ("cos", 1)
+            | ("tan", 1)
+            | ("cot", 1)
+            | ("asin", 1)
+            | ("acos", 1)
+            | ("atan", 1)
+            | ("degrees", 1)
+            | ("radians", 1)
+    )
+}
+
+/// functions that give `numeric` values when they are given one
+pub(crate) fn has_numeric_variant(name: &str) -> bool {
+    matches!(name, "sqrt" | "exp" | "ln" | "log" | "power" | "pow")
+}
+
+pub(crate) fn apply(name: &str, args: &[f64]) -> Result<f64, QueryError> {
+    match (name, args) {
+        ("pi", []) => Ok(PI),
+        ("sqrt", [value]) if *value < 0.0 => Err(QueryError::invalid_argument_for_power_function(
+            "cannot take square root of a negative number".to_owned(),
+        )),
+        ("sqrt", [value]) => Ok(value.sqrt()),
+        ("cbrt", [value]) => Ok(value.cbrt()),
+        ("exp", [value]) => checked(value.exp(), &[*value], false),
+        ("ln", [value]) => logarithm(*value).map(f64::ln),
+        ("log", [value]) => logarithm(*value).map(f64::log10),
+        ("log", [base, value]) => {
+            let base = logarithm(*base)?;
+            let value = logarithm(*value)?;
+            if base == 1.0 {
+                return Err(QueryError::division_by_zero());
+            }
+            Ok(value.ln() / base.ln())
+        }
+        ("power", [base, exponent]) | ("pow", [base, exponent]) => power(*base, *exponent),
+        ("sin", [value]) => finite(*value).map(f64::sin),
+        ("cos", [value]) => finite(*value).map(f64::cos),
+        ("tan", [value]) => finite(*value).map(f64::tan),
+        ("cot", [value]) => finite(*value).map(|value| 1.0 / value.tan()),
+        ("asin", [value]) => unit(*value).map(f64::asin),
+        ("acos", [value]) => unit(*value).map(f64::acos),
+        ("atan", [value]) => Ok(value.atan()),
+        ("atan2", [y, x]) => Ok(y.atan2(*x)),
+        ("degrees", [value]) => checked(value.to_degrees(), &[*value], true),
+        ("radians", [value]) => checked(value.to_radians(), &[*value], true),
+        _ => Err(QueryError::undefined_function(format!(
+            "{}({})",
+            name,
+            vec!["double precision"; args.len()].join(", ")
+        ))),
+    }
+}
+
+fn power(base: f64, exponent: f64) -> Result<f64, QueryError> {
+    if base == 0.0 && exponent < 0.0 {
+        return Err(QueryError::invalid_argument_for_power_function(
+            "zero raised to a negative power is undefined".to_owned(),
+        ));
+    }
+    if base < 0.0 && exponent.floor() != exponent {
+        return Err(QueryError::invalid_argument_for_power_function(
+            "a negative number raised to a non-integer power yields a complex result".to_owned(),
+        ));
+    }
+    checked(base.powf(exponent), &[base, exponent], base == 0.0)
+}
+
+// result is out of range when it does not fit into double precision though
+// arguments do, or when it is rounded to zero though it is not
+fn checked(result: f64, args: &[f64], may_be_zero: bool) -> Result<f64, QueryError> {
+    if result.is_infinite() && args.iter().all(|arg| arg.is_finite()) {
+        Err(QueryError::value_out_of_range(
+            "value out of range: overflow".to_owned(),
+        ))
+    } else if result == 0.0 && !may_be_zero && args.iter().all(|arg| arg.is_finite()) {
+        Err(QueryError::value_out_of_range(
+            "value out of range: underflow".to_owned(),
+        ))
+    } else {
+        Ok(result)
+    }
+}
+
+fn logarithm(value: f64) -> Result<f64, QueryError> {
+    if value == 0.0 {
+        Err(QueryError::invalid_argument_for_logarithm(
+            "cannot take logarithm of zero".to_owned(),
+        ))
+    } else if value < 0.0 {
+        Err(QueryError::invalid_argument_for_logarithm(
+            "cannot take logarithm of a negative number".to_owned(),
+        ))
+    } else {
+        Ok(value)
+    }
+}
+
+fn finite(value: f64) -> Result<f64, QueryError> {
+    if value.is_infinite() {
+        Err(input_out_of_range())
+    } else {
+        Ok(value)
+    }
+}
+
+fn unit(value: f64) -> Result<f64, QueryError> {
+    if (-1.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(input_out_of_range())
+    }
+}
+
+fn input_out_of_range() -> QueryError {
+    QueryError::value_out_of_range("input is out of range".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_values() {
+        let random = Random::new(42);
+        random.set_seed(0.5);
+        let first = (random.next(), random.next());
+        random.set_seed(0.5);
+
+        assert_eq!((random.next(), random.next()), first);
+        assert!(first.0 >= 0.0 && first.0 < 1.0);
+    }
+
+    #[test]
+    fn logarithms() {
+        assert_eq!(apply("log", &[100.0]), Ok(2.0));
+        assert_eq!(apply("log", &[2.0, 8.0]), Ok(3.0));
+        assert_eq!(
+            apply("ln", &[0.0]),
+            Err(QueryError::invalid_argument_for_logarithm(
+                "cannot take logarithm of zero".to_owned()
+            ))
+        );
+        assert_eq!(apply("log", &[1.0, 8.0]), Err(QueryError::division_by_zero()));
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(apply("power", &[2.0, 10.0]), Ok(1024.0));
+        assert_eq!(apply("power", &[-8.0, 2.0]), Ok(64.0));
+        assert_eq!(
+            apply("power", &[-8.0, 0.5]),
+            Err(QueryError::invalid_argument_for_power_function(
+                "a negative number raised to a non-integer power yields a complex result".to_owned()
+            ))
+        );
+        assert_eq!(
+            apply("power", &[10.0, 400.0]),
+            Err(QueryError::value_out_of_range(
+                "value out of range: overflow".to_owned()
+            ))
+        );
+        assert_eq!(
+            apply("exp", &[-1000.0]),
+            Err(QueryError::value_out_of_range(
+                "value out of range: underflow".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn trigonometry() {
+        assert_eq!(apply("degrees", &[PI]), Ok(180.0));
+        assert_eq!(apply("atan2", &[1.0, 0.0]), Ok(PI / 2.0));
+        assert_eq!(
+            apply("asin", &[2.0]),
+            Err(QueryError::value_out_of_range("input is out of range".to_owned()))
+        );
+        assert_eq!(
+            apply("sin", &[f64::INFINITY]),
+            Err(QueryError::value_out_of_range("input is out of range".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,559 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{QueryError, QueryEvent};
+use sql_types::SqlType;
+use std::collections::HashMap;
+
+/// `PREPARE`, `EXECUTE` and `DEALLOCATE` statements, sql parser does not know them
+#[derive(Debug, PartialEq)]
+pub(crate) enum PreparedCommand {
+    Prepare {
+        name: String,
+        param_types: Vec<Option<SqlType>>,
+        statement: String,
+    },
+    // arguments are expressions that replace parameters of the statement
+    Execute {
+        name: String,
+        args: Vec<String>,
+    },
+    // `None` deallocates all prepared statements of the session
+    Deallocate(Option<String>),
+}
+
+/// Statement that is prepared by `PREPARE` or by Parse message, both share
+/// names of a session
+#[derive(Debug)]
+pub(crate) struct PreparedStatement {
+    pub(crate) sql: String,
+    // `None` when the type is not specified
+    pub(crate) param_types: Vec<Option<SqlType>>,
+}
+
+/// Prepared statement with values of its parameters
+#[derive(Debug)]
+pub(crate) enum Portal {
+    Bound(String),
+    // records of executed statement that are left to return
+    Executed(QueryEvent),
+    Completed,
+}
+
+/// Prepared statements or portals of a session, the least recently used one
+/// is dropped when the session has as many of them as it is allowed
+#[derive(Debug)]
+pub(crate) struct Registry<T> {
+    entries: HashMap<String, (u64, T)>,
+    uses: u64,
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Registry<T> {
+        Registry {
+            entries: HashMap::new(),
+            uses: 0,
+        }
+    }
+}
+
+impl<T> Registry<T> {
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub(crate) fn get(&mut self, name: &str) -> Option<&T> {
+        self.uses += 1;
+        let uses = self.uses;
+        self.entries.get_mut(name).map(|(used, value)| {
+            *used = uses;
+            &*value
+        })
+    }
+
+    /// returns names of the entries that are dropped to keep under the limit
+    pub(crate) fn insert(&mut self, name: String, value: T, limit: usize) -> Vec<String> {
+        let mut evicted = vec![];
+        if !self.entries.contains_key(&name) {
+            while !self.entries.is_empty() && self.entries.len() >= limit {
+                let least_recent = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_name, (used, _value))| *used)
+                    .map(|(name, _entry)| name.clone())
+                    .expect("there is an entry");
+                self.entries.remove(&least_recent);
+                evicted.push(least_recent);
+            }
+        }
+        self.uses += 1;
+        self.entries.insert(name, (self.uses, value));
+        evicted
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) -> Option<T> {
+        self.entries.remove(name).map(|(_used, value)| value)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// returns `None` if the query is not `PREPARE`, `EXECUTE` or `DEALLOCATE`
+pub(crate) fn parse(raw_sql_query: &str) -> Option<Result<PreparedCommand, QueryError>> {
+    let statement = raw_sql_query.trim().trim_end_matches(';').trim_end();
+    if let Some(rest) = keyword(statement, "prepare") {
+        Some(prepare(rest))
+    } else if let Some(rest) = keyword(statement, "execute") {
+        Some(execute(rest))
+    } else {
+        keyword(statement, "deallocate").map(|rest| deallocate(keyword(rest, "prepare").unwrap_or(rest)))
+    }
+}
+
+fn prepare(rest: &str) -> Result<PreparedCommand, QueryError> {
+    let (name, rest) = name(rest)?;
+    let (param_types, rest) = if rest.starts_with('(') {
+        let (types, rest) = list(rest)?;
+        let param_types = types
+            .iter()
+            .map(|type_name| match sql_type(type_name) {
+                Some(sql_type) => Ok(Some(sql_type)),
+                None => Err(QueryError::undefined_type(type_name.to_owned())),
+            })
+            .collect::<Result<Vec<Option<SqlType>>, QueryError>>()?;
+        (param_types, rest)
+    } else {
+        (vec![], rest)
+    };
+    let statement = match keyword(rest, "as") {
+        Some(statement) => statement,
+        None => return Err(unexpected("AS", word(rest))),
+    };
+    let command = word(statement);
+    if ["select", "values", "with", "insert", "update", "delete"]
+        .iter()
+        .any(|preparable| command.eq_ignore_ascii_case(preparable))
+    {
+        Ok(PreparedCommand::Prepare {
+            name,
+            param_types,
+            statement: statement.to_owned(),
+        })
+    } else {
+        Err(unexpected("SELECT, VALUES, INSERT, UPDATE or DELETE", command))
+    }
+}
+
+fn execute(rest: &str) -> Result<PreparedCommand, QueryError> {
+    let (name, rest) = name(rest)?;
+    let (args, rest) = if rest.starts_with('(') {
+        list(rest)?
+    } else {
+        (vec![], rest)
+    };
+    end(rest)?;
+    Ok(PreparedCommand::Execute { name, args })
+}
+
+fn deallocate(rest: &str) -> Result<PreparedCommand, QueryError> {
+    if let Some(rest) = keyword(rest, "all") {
+        end(rest)?;
+        return Ok(PreparedCommand::Deallocate(None));
+    }
+    let (name, rest) = name(rest)?;
+    end(rest)?;
+    Ok(PreparedCommand::Deallocate(Some(name)))
+}
+
+/// number of parameters that the statement refers to as `$n`
+pub(crate) fn parameters(sql: &str) -> usize {
+    placeholders(sql)
+        .into_iter()
+        .map(|(_start, _end, number)| number)
+        .max()
+        .unwrap_or_default()
+}
+
+/// replaces `$n` parameters with `n`th value
+pub(crate) fn bind(sql: &str, values: &[String]) -> String {
+    let mut bound = String::with_capacity(sql.len());
+    let mut copied = 0;
+    for (start, end, number) in placeholders(sql) {
+        if let Some(value) = values.get(number - 1) {
+            bound.push_str(&sql[copied..start]);
+            bound.push_str(value.as_str());
+            copied = end;
+        }
+    }
+    bound.push_str(&sql[copied..]);
+    bound
+}
+
+/// literal of parameter value that client sends as a text
+pub(crate) fn literal(value: Option<&str>, sql_type: Option<SqlType>) -> Result<String, QueryError> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok("NULL".to_owned()),
+    };
+    let number = match sql_type {
+        Some(SqlType::SmallInt) | Some(SqlType::Integer) | Some(SqlType::BigInt) => value.trim().parse::<i64>().is_ok(),
+        Some(SqlType::Real) | Some(SqlType::DoublePrecision) | Some(SqlType::Decimal) => {
+            let trimmed = value.trim();
+            trimmed.parse::<f64>().is_ok()
+                && trimmed
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e' || c == 'E')
+        }
+        Some(SqlType::Bool) => {
+            return match value.trim().to_lowercase().as_str() {
+                "t" | "true" | "y" | "yes" | "on" | "1" => Ok("true".to_owned()),
+                "f" | "false" | "n" | "no" | "off" | "0" => Ok("false".to_owned()),
+                _ => Err(QueryError::invalid_text_representation(
+                    "boolean".to_owned(),
+                    value.to_owned(),
+                )),
+            }
+        }
+        _ => return Ok(format!("'{}'", value.replace('\'', "''"))),
+    };
+    match sql_type {
+        Some(sql_type) if !number => Err(QueryError::invalid_text_representation(
+            type_name(sql_type).to_owned(),
+            value.to_owned(),
+        )),
+        _ => Ok(operand(value.trim())),
+    }
+}
+
+/// expression that is put in place of a parameter keeps its precedence
+pub(crate) fn operand(expr: &str) -> String {
+    let quoted = expr.len() >= 2 && expr.starts_with('\'') && expr.ends_with('\'');
+    if quoted || (!expr.is_empty() && expr.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')) {
+        expr.to_owned()
+    } else {
+        format!("({})", expr)
+    }
+}
+
+/// statements that are described by their rows
+pub(crate) fn returns_rows(sql: &str) -> bool {
+    let command = word(sql.trim_start().trim_start_matches('(').trim_start());
+    ["select", "values", "with", "show"]
+        .iter()
+        .any(|returning| command.eq_ignore_ascii_case(returning))
+}
+
+// start, end and number of every `$n` outside of literals, quoted identifiers
+// and comments
+fn placeholders(sql: &str) -> Vec<(usize, usize, usize)> {
+    let bytes = sql.as_bytes();
+    let identifier = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$';
+    let mut placeholders = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' => {
+                let escaped = i > 0 && (bytes[i - 1] == b'e' || bytes[i - 1] == b'E');
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'\'' {
+                    i += if escaped && bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            b'$' if i == 0 || !identifier(bytes[i - 1]) => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                if i > start + 1 {
+                    if let Ok(number) = sql[start + 1..i].parse::<usize>() {
+                        if number > 0 {
+                            placeholders.push((start, i, number));
+                        }
+                    }
+                    continue;
+                }
+                // dollar quoted string ends with the same tag it starts with
+                while i < bytes.len() && identifier(bytes[i]) && bytes[i] != b'$' {
+                    i += 1;
+                }
+                if bytes.get(i) == Some(&b'$') {
+                    let tag = &sql[start..=i];
+                    i = match sql[i + 1..].find(tag) {
+                        Some(end) => i + 1 + end + tag.len(),
+                        None => bytes.len(),
+                    };
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    placeholders
+}
+
+fn sql_type(type_name: &str) -> Option<SqlType> {
+    let type_name = type_name
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase();
+    match type_name.as_str() {
+        "smallint" | "int2" => Some(SqlType::SmallInt),
+        "integer" | "int" | "int4" => Some(SqlType::Integer),
+        "bigint" | "int8" => Some(SqlType::BigInt),
+        "real" | "float4" => Some(SqlType::Real),
+        "double precision" | "float8" => Some(SqlType::DoublePrecision),
+        "numeric" | "decimal" => Some(SqlType::Decimal),
+        "boolean" | "bool" => Some(SqlType::Bool),
+        "date" => Some(SqlType::Date),
+        "time" => Some(SqlType::Time),
+        "timestamp" => Some(SqlType::Timestamp),
+        "timestamptz" | "timestamp with time zone" => Some(SqlType::TimestampWithTimeZone),
+        "interval" => Some(SqlType::Interval),
+        // length of character types is checked when value is stored
+        "text" | "varchar" | "character varying" => Some(SqlType::VarChar(255)),
+        "char" | "character" | "bpchar" => Some(SqlType::Char(255)),
+        _ => None,
+    }
+}
+
+fn type_name(sql_type: SqlType) -> &'static str {
+    match sql_type {
+        SqlType::SmallInt => "smallint",
+        SqlType::Integer => "integer",
+        SqlType::BigInt => "bigint",
+        SqlType::Real => "real",
+        SqlType::DoublePrecision => "double precision",
+        _ => "numeric",
+    }
+}
+
+// name of a statement ends with a whitespace or with a list of parameters
+fn name(statement: &str) -> Result<(String, &str), QueryError> {
+    let end = statement
+        .find(|c: char| c.is_whitespace() || c == '(')
+        .unwrap_or(statement.len());
+    let (name, rest) = statement.split_at(end);
+    if name.is_empty() {
+        return Err(unexpected("statement name", name));
+    }
+    // quoted identifiers keep their case
+    let name = if name.len() >= 2 && name.starts_with('"') && name.ends_with('"') {
+        name[1..name.len() - 1].to_owned()
+    } else {
+        name.to_lowercase()
+    };
+    Ok((name, rest.trim_start()))
+}
+
+// comma separated items in parentheses, commas of nested parentheses and
+// literals do not split items
+fn list(statement: &str) -> Result<(Vec<String>, &str), QueryError> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut start = 1;
+    for (i, c) in statement.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            _ if quoted => {}
+            '(' => depth += 1,
+            ')' if depth == 1 => {
+                items.push(statement[start..i].trim().to_owned());
+                if items.iter().any(String::is_empty) {
+                    return Err(unexpected("expression", ")"));
+                }
+                return Ok((items, statement[i + 1..].trim_start()));
+            }
+            ')' => depth -= 1,
+            ',' if depth == 1 => {
+                items.push(statement[start..i].trim().to_owned());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    Err(unexpected(")", ""))
+}
+
+fn end(rest: &str) -> Result<(), QueryError> {
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(unexpected("end of statement", word(rest)))
+    }
+}
+
+// keyword has to be followed by a whitespace
+fn keyword<'q>(statement: &'q str, keyword: &str) -> Option<&'q str> {
+    let prefix = statement.get(..keyword.len())?;
+    let rest = &statement[keyword.len()..];
+    if prefix.eq_ignore_ascii_case(keyword) && (rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+fn word(statement: &str) -> &str {
+    let end = statement
+        .find(|c: char| c.is_whitespace() || c == '(')
+        .unwrap_or(statement.len());
+    &statement[..end]
+}
+
+fn unexpected(expected: &str, found: &str) -> QueryError {
+    let found = if found.is_empty() { "EOF" } else { found };
+    QueryError::syntax_error(format!("Expected {}, found: {}", expected, found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepare_statement() {
+        assert_eq!(
+            parse("prepare Insert_Row (int, double precision) as insert into t values ($1, $2);"),
+            Some(Ok(PreparedCommand::Prepare {
+                name: "insert_row".to_owned(),
+                param_types: vec![Some(SqlType::Integer), Some(SqlType::DoublePrecision)],
+                statement: "insert into t values ($1, $2)".to_owned(),
+            }))
+        );
+        assert_eq!(
+            parse("PREPARE select_all AS select * from t"),
+            Some(Ok(PreparedCommand::Prepare {
+                name: "select_all".to_owned(),
+                param_types: vec![],
+                statement: "select * from t".to_owned(),
+            }))
+        );
+    }
+
+    #[test]
+    fn prepare_not_preparable_statement() {
+        assert_eq!(
+            parse("prepare drop_it as drop table t;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected SELECT, VALUES, INSERT, UPDATE or DELETE, found: drop".to_owned()
+            )))
+        );
+        assert_eq!(
+            parse("prepare s (unknown) as select $1;"),
+            Some(Err(QueryError::undefined_type("unknown".to_owned())))
+        );
+    }
+
+    #[test]
+    fn execute_statement() {
+        assert_eq!(
+            parse("execute insert_row(1, 'a, b', f(2, 3));"),
+            Some(Ok(PreparedCommand::Execute {
+                name: "insert_row".to_owned(),
+                args: vec!["1".to_owned(), "'a, b'".to_owned(), "f(2, 3)".to_owned()],
+            }))
+        );
+        assert_eq!(
+            parse("execute select_all"),
+            Some(Ok(PreparedCommand::Execute {
+                name: "select_all".to_owned(),
+                args: vec![],
+            }))
+        );
+        assert_eq!(
+            parse("execute s(1"),
+            Some(Err(QueryError::syntax_error("Expected ), found: EOF".to_owned())))
+        );
+    }
+
+    #[test]
+    fn deallocate_statement() {
+        assert_eq!(
+            parse("deallocate insert_row;"),
+            Some(Ok(PreparedCommand::Deallocate(Some("insert_row".to_owned()))))
+        );
+        assert_eq!(
+            parse("DEALLOCATE PREPARE insert_row"),
+            Some(Ok(PreparedCommand::Deallocate(Some("insert_row".to_owned()))))
+        );
+        assert_eq!(parse("deallocate all;"), Some(Ok(PreparedCommand::Deallocate(None))));
+        assert_eq!(parse("select * from deallocate;"), None);
+    }
+
+    #[test]
+    fn bind_parameters() {
+        let sql = "select $1, '$1', \"$2\", $$ $1 $$, a$1 -- $1\n from t where b = $2 /* $1 */ and c = $10";
+        assert_eq!(parameters(sql), 10);
+        let values = (1..=10).map(|value| format!("v{}", value)).collect::<Vec<String>>();
+        assert_eq!(
+            bind(sql, &values),
+            "select v1, '$1', \"$2\", $$ $1 $$, a$1 -- $1\n from t where b = v2 /* $1 */ and c = v10"
+        );
+    }
+
+    #[test]
+    fn literals_of_parameters() {
+        assert_eq!(literal(None, Some(SqlType::Integer)), Ok("NULL".to_owned()));
+        assert_eq!(literal(Some("-5"), Some(SqlType::Integer)), Ok("(-5)".to_owned()));
+        assert_eq!(literal(Some("1.5e3"), Some(SqlType::Decimal)), Ok("1.5e3".to_owned()));
+        assert_eq!(literal(Some("on"), Some(SqlType::Bool)), Ok("true".to_owned()));
+        assert_eq!(literal(Some("it's"), None), Ok("'it''s'".to_owned()));
+        assert_eq!(
+            literal(Some("1 or 1 = 1"), Some(SqlType::BigInt)),
+            Err(QueryError::invalid_text_representation(
+                "bigint".to_owned(),
+                "1 or 1 = 1".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let mut registry = Registry::default();
+        assert_eq!(registry.insert("a".to_owned(), 1, 2), Vec::<String>::new());
+        assert_eq!(registry.insert("b".to_owned(), 2, 2), Vec::<String>::new());
+        assert_eq!(registry.get("a"), Some(&1));
+        assert_eq!(registry.insert("c".to_owned(), 3, 2), vec!["b".to_owned()]);
+        assert_eq!(registry.insert("c".to_owned(), 4, 2), Vec::<String>::new());
+        assert!(registry.contains("a"));
+        assert!(!registry.contains("b"));
+        assert_eq!(
+            registry.insert("d".to_owned(), 5, 1),
+            vec!["a".to_owned(), "c".to_owned()]
+        );
+    }
+}
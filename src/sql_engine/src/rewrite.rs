@@ -0,0 +1,516 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+
+/// Query as it is executed, constructs that clients send but that can't be
+/// executed yet are translated or dropped if they do not change the result
+#[derive(Debug, PartialEq)]
+pub(crate) struct Rewritten<'q> {
+    pub(crate) query: Cow<'q, str>,
+    // clauses that are dropped from the query, clients are notified about them
+    pub(crate) ignored: Vec<&'static str>,
+}
+
+pub(crate) fn rewrite(raw_sql_query: &str) -> Rewritten<'_> {
+    let mut ignored = vec![];
+    let statement = raw_sql_query.trim().trim_end_matches(';').trim_end();
+    let mut query = match read_only_cursor(statement).or_else(|| read_write_transaction(statement)) {
+        Some((rewritten, clause)) => {
+            ignored.push(clause);
+            Cow::Owned(rewritten.to_owned())
+        }
+        None => Cow::Borrowed(raw_sql_query),
+    };
+    // rules are applied one after another to the result of the previous one
+    let rules: [fn(&str) -> Option<String>; 4] = [set_time_zone, timestamp_types, system_names, nulls_order];
+    for rule in rules.iter() {
+        if let Some(rewritten) = rule(&query) {
+            query = Cow::Owned(rewritten);
+        }
+    }
+    Rewritten { query, ignored }
+}
+
+// all cursors are read only
+fn read_only_cursor(statement: &str) -> Option<(&str, &'static str)> {
+    if !first_word(statement).eq_ignore_ascii_case("select") {
+        return None;
+    }
+    let rest = last_word(statement, "only")?;
+    let rest = last_word(rest, "read")?;
+    Some((last_word(rest, "for")?, "FOR READ ONLY"))
+}
+
+// transactions are read write by default
+fn read_write_transaction(statement: &str) -> Option<(&str, &'static str)> {
+    let words: Vec<String> = statement.split_whitespace().map(str::to_lowercase).collect();
+    let start = match words.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["begin", "read", "write"]
+        | ["begin", "transaction", "read", "write"]
+        | ["begin", "work", "read", "write"]
+        | ["start", "transaction", "read", "write"] => last_word(last_word(statement, "write")?, "read")?,
+        _ => return None,
+    };
+    Some((start, "READ WRITE"))
+}
+
+// `SET TIME ZONE` is another form of setting `TimeZone` parameter
+fn set_time_zone(query: &str) -> Option<String> {
+    let statement = query.trim().trim_end_matches(';').trim_end();
+    let rest = leading_word(statement, "set")?;
+    let (scope, rest) = match ["session", "local"]
+        .iter()
+        .find_map(|scope| leading_word(rest, scope).map(|rest| (*scope, rest)))
+    {
+        Some((scope, rest)) => (format!("{} ", scope), rest),
+        None => (String::new(), rest),
+    };
+    let value = leading_word(leading_word(rest, "time")?, "zone")?;
+    Some(format!("set {}timezone to {}", scope, value))
+}
+
+const TIMESTAMP_TYPES: [(&[&str], &str); 2] = [
+    (&["timestamp", "with", "time", "zone"], "timestamptz"),
+    (&["timestamp", "without", "time", "zone"], "timestamp"),
+];
+
+// sql parser drops time zone of timestamp type, so its short name is used
+fn timestamp_types(query: &str) -> Option<String> {
+    let mut rewritten = String::with_capacity(query.len());
+    let mut changed = false;
+    for (part, quoted) in split_quoted(query) {
+        let mut rest = part;
+        if !quoted {
+            while let Some((start, end, name)) = TIMESTAMP_TYPES
+                .iter()
+                .filter_map(|(words, name)| find_words(rest, words).map(|(start, end)| (start, end, *name)))
+                .min_by_key(|(start, _end, _name)| *start)
+            {
+                rewritten.push_str(&rest[..start]);
+                rewritten.push_str(name);
+                rest = &rest[end..];
+                changed = true;
+            }
+        }
+        rewritten.push_str(rest);
+    }
+    if changed {
+        Some(rewritten)
+    } else {
+        None
+    }
+}
+
+// system functions and types are known without `pg_catalog` qualifier and
+// literals do not have to be cast to text types
+fn system_names(query: &str) -> Option<String> {
+    let mut rewritten = String::with_capacity(query.len());
+    let mut changed = false;
+    let mut after_literal = false;
+    for (part, quoted) in split_quoted(query) {
+        if quoted {
+            rewritten.push_str(part);
+            after_literal = part.starts_with('\'');
+            continue;
+        }
+        let unqualified = unqualified(part);
+        let mut part = unqualified.as_deref().unwrap_or(part);
+        changed |= unqualified.is_some();
+        if after_literal {
+            if let Some(rest) = text_cast(part) {
+                part = rest;
+                changed = true;
+            }
+        }
+        rewritten.push_str(part);
+        after_literal = false;
+    }
+    if changed {
+        Some(rewritten)
+    } else {
+        None
+    }
+}
+
+const SYSTEM_SCHEMA: &str = "pg_catalog.";
+
+const TEXT_TYPES: &[&str] = &["text", "varchar", "bpchar", "name"];
+
+// qualifier is dropped only from names of functions and types of casts,
+// system tables are not known without it
+fn unqualified(part: &str) -> Option<String> {
+    let lowercase = part.to_ascii_lowercase();
+    let mut rewritten = String::with_capacity(part.len());
+    let mut position = 0;
+    let mut changed = false;
+    while let Some(found) = lowercase[position..].find(SYSTEM_SCHEMA) {
+        let start = position + found;
+        let end = start + SYSTEM_SCHEMA.len();
+        let name_end = end
+            + part[end..]
+                .find(|c: char| !is_identifier(c))
+                .unwrap_or(part.len() - end);
+        let standalone = !part[..start].ends_with(|c: char| is_identifier(c) || c == '.');
+        let is_cast = part[..start].trim_end().ends_with("::");
+        let is_call = part[name_end..].trim_start().starts_with('(');
+        rewritten.push_str(&part[position..start]);
+        if standalone && (is_cast || is_call) {
+            changed = true;
+        } else {
+            rewritten.push_str(&part[start..end]);
+        }
+        position = end;
+    }
+    if changed {
+        rewritten.push_str(&part[position..]);
+        Some(rewritten)
+    } else {
+        None
+    }
+}
+
+// returns the rest of the query after the cast
+fn text_cast(part: &str) -> Option<&str> {
+    let rest = part.trim_start();
+    if !rest.starts_with("::") {
+        return None;
+    }
+    let rest = rest[2..].trim_start();
+    let name_end = rest.find(|c: char| !is_identifier(c)).unwrap_or(rest.len());
+    let (name, rest) = rest.split_at(name_end);
+    if TEXT_TYPES.iter().any(|text_type| name.eq_ignore_ascii_case(text_type)) && !rest.starts_with('(') {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+// sql parser does not know `NULLS FIRST` and `NULLS LAST`, nulls are sorted
+// after other values, so an ORDER BY item is preceded by a key that tells
+// whether its value is null, e.g. `a desc nulls last` is `a is null, a desc`
+fn nulls_order(query: &str) -> Option<String> {
+    // quoted parts are masked to not find clauses in literals
+    let masked: String = split_quoted(query)
+        .into_iter()
+        .map(|(part, quoted)| {
+            if quoted {
+                "?".repeat(part.len())
+            } else {
+                part.to_ascii_lowercase()
+            }
+        })
+        .collect();
+    let mut rewritten = String::with_capacity(query.len());
+    let mut changed = false;
+    let mut from = 0;
+    while let Some((_start, end)) = find_words(&masked[from..], &["order", "by"]) {
+        let start = from + end;
+        rewritten.push_str(&query[from..start]);
+        from = order_by_end(&masked, start);
+        let mut items = vec![];
+        for (item_start, item_end) in order_by_items(&masked, start, from) {
+            let item = &query[item_start..item_end];
+            let masked_item = masked[item_start..item_end].trim_end();
+            let key = ["first", "last"].iter().find_map(|position| {
+                find_words(masked_item, &["nulls", position])
+                    .filter(|(_start, end)| *end == masked_item.len())
+                    .map(|(start, _end)| (item[..start].trim_end(), *position))
+            });
+            match key {
+                Some((ordered, position)) => {
+                    let expr = last_word(ordered, "asc")
+                        .or_else(|| last_word(ordered, "desc"))
+                        .unwrap_or(ordered)
+                        .trim();
+                    let check = if position == "first" { "is not null" } else { "is null" };
+                    let leading = &item[..item.len() - item.trim_start().len()];
+                    let trailing = &item[item.trim_end().len()..];
+                    items.push(format!(
+                        "{}{} {}, {}{}",
+                        leading,
+                        expr,
+                        check,
+                        ordered.trim_start(),
+                        trailing
+                    ));
+                    changed = true;
+                }
+                None => items.push(item.to_owned()),
+            }
+        }
+        rewritten.push_str(&items.join(","));
+    }
+    rewritten.push_str(&query[from..]);
+    if changed {
+        Some(rewritten)
+    } else {
+        None
+    }
+}
+
+// ORDER BY ends with the clause that follows it or with the end of a subquery
+fn order_by_end(masked: &str, start: usize) -> usize {
+    let mut depth = 0;
+    for (index, c) in masked[start..].char_indices() {
+        let index = start + index;
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return index,
+            ')' => depth -= 1,
+            ';' if depth == 0 => return index,
+            _ if depth == 0 && !masked[..index].ends_with(is_identifier) => {
+                let rest = &masked[index..];
+                let is_clause = ["limit", "offset", "fetch", "for"]
+                    .iter()
+                    .any(|clause| rest.starts_with(clause) && !rest[clause.len()..].starts_with(is_identifier));
+                if is_clause {
+                    return index;
+                }
+            }
+            _ => {}
+        }
+    }
+    masked.len()
+}
+
+// items are separated by commas that are not inside of parentheses
+fn order_by_items(masked: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut item_start = start;
+    for (index, c) in masked[start..end].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push((item_start, start + index));
+                item_start = start + index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push((item_start, end));
+    items
+}
+
+// parts of the query in order with a flag whether a part is a quoted literal or identifier
+fn split_quoted(query: &str) -> Vec<(&str, bool)> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut quote = None;
+    let mut chars = query.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match quote {
+            None if c == '\'' || c == '"' => {
+                if start < index {
+                    parts.push((&query[start..index], false));
+                }
+                start = index;
+                quote = Some(c);
+            }
+            Some(open) if c == open => {
+                // quotes are escaped by doubling them
+                if chars.peek().map(|(_index, next)| *next == open).unwrap_or(false) {
+                    chars.next();
+                } else {
+                    parts.push((&query[start..=index], true));
+                    start = index + 1;
+                    quote = None;
+                }
+            }
+            _ => {}
+        }
+    }
+    if start < query.len() {
+        parts.push((&query[start..], quote.is_some()));
+    }
+    parts
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// position of words that are separated by whitespaces
+fn find_words(part: &str, words: &[&str]) -> Option<(usize, usize)> {
+    let lowercase = part.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lowercase[from..].find(words[0]) {
+        let start = from + found;
+        from = start + words[0].len();
+        if part[..start].ends_with(is_identifier) {
+            continue;
+        }
+        let mut end = start;
+        let matched = words.iter().enumerate().all(|(index, word)| {
+            let spaces = lowercase[end..].len() - lowercase[end..].trim_start().len();
+            if index > 0 && spaces == 0 {
+                return false;
+            }
+            end += if index > 0 { spaces } else { 0 };
+            let matched =
+                lowercase[end..].starts_with(word) && !lowercase[end + word.len()..].starts_with(is_identifier);
+            end += word.len();
+            matched
+        });
+        if matched {
+            return Some((start, end));
+        }
+    }
+    None
+}
+
+fn first_word(statement: &str) -> &str {
+    statement.split_whitespace().next().unwrap_or("")
+}
+
+// returns the rest of the statement after the first word if it is the given one
+fn leading_word<'q>(statement: &'q str, word: &str) -> Option<&'q str> {
+    let prefix = statement.get(..word.len())?;
+    let rest = &statement[word.len()..];
+    if prefix.eq_ignore_ascii_case(word) && rest.starts_with(char::is_whitespace) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+// returns the statement without the last word if it is the given one
+fn last_word<'q>(statement: &'q str, word: &str) -> Option<&'q str> {
+    let statement = statement.trim_end();
+    let start = statement.rfind(char::is_whitespace)? + 1;
+    if statement[start..].eq_ignore_ascii_case(word) {
+        Some(statement[..start].trim_end())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewritten(query: &str, ignored: Vec<&'static str>) -> Rewritten<'static> {
+        Rewritten {
+            query: Cow::Owned(query.to_owned()),
+            ignored,
+        }
+    }
+
+    #[test]
+    fn supported_query_is_not_changed() {
+        let query = "select * from schema_name.table_name where name = 'pg_catalog.text'::varchar(5);";
+        assert_eq!(
+            rewrite(query),
+            Rewritten {
+                query: Cow::Borrowed(query),
+                ignored: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn select_for_read_only() {
+        assert_eq!(
+            rewrite("select * from schema_name.table_name FOR READ  ONLY;"),
+            rewritten("select * from schema_name.table_name", vec!["FOR READ ONLY"])
+        );
+        assert_eq!(
+            rewrite("select 'for read only'").query,
+            Cow::Borrowed("select 'for read only'")
+        );
+    }
+
+    #[test]
+    fn read_write_transaction() {
+        assert_eq!(rewrite("BEGIN READ WRITE;"), rewritten("BEGIN", vec!["READ WRITE"]));
+        assert_eq!(
+            rewrite("start transaction read write"),
+            rewritten("start transaction", vec!["READ WRITE"])
+        );
+        assert_eq!(rewrite("begin read only;").ignored, Vec::<&str>::new());
+    }
+
+    #[test]
+    fn system_function_qualifier() {
+        assert_eq!(
+            rewrite("select pg_catalog.current_setting('my.tenant');"),
+            rewritten("select current_setting('my.tenant');", vec![])
+        );
+        assert_eq!(
+            rewrite("select * from pg_catalog.pg_type;").query,
+            Cow::Borrowed("select * from pg_catalog.pg_type;")
+        );
+    }
+
+    #[test]
+    fn set_time_zone() {
+        assert_eq!(
+            rewrite("SET TIME ZONE 'Europe/Kiev';"),
+            rewritten("set timezone to 'Europe/Kiev'", vec![])
+        );
+        assert_eq!(
+            rewrite("set local time  zone LOCAL").query,
+            Cow::Borrowed("set local timezone to LOCAL")
+        );
+        assert_eq!(
+            rewrite("set timezone = 'UTC'").query,
+            Cow::Borrowed("set timezone = 'UTC'")
+        );
+    }
+
+    #[test]
+    fn timestamp_types() {
+        assert_eq!(
+            rewrite("create table t (a TIMESTAMP WITH TIME ZONE, b timestamp without\ttime zone, c timestamp);"),
+            rewritten("create table t (a timestamptz, b timestamp, c timestamp);", vec![])
+        );
+        assert_eq!(
+            rewrite("insert into t values ('timestamp with time zone');").query,
+            Cow::Borrowed("insert into t values ('timestamp with time zone');")
+        );
+    }
+
+    #[test]
+    fn nulls_order() {
+        assert_eq!(
+            rewrite("select * from t order by a nulls first, b DESC NULLS LAST limit 2;"),
+            rewritten(
+                "select * from t order by a is not null, a, b is null, b DESC limit 2;",
+                vec![]
+            )
+        );
+        assert_eq!(
+            rewrite("select * from t order by lower(a, b) asc nulls last").query,
+            Cow::Borrowed("select * from t order by lower(a, b) is null, lower(a, b) asc")
+        );
+        assert_eq!(
+            rewrite("select * from t where a = 'order by b nulls first' order by a").query,
+            Cow::Borrowed("select * from t where a = 'order by b nulls first' order by a")
+        );
+    }
+
+    #[test]
+    fn text_casts_of_literals() {
+        assert_eq!(
+            rewrite("insert into schema_name.table_name values ('it''s'::pg_catalog.text, 'b' :: varchar);"),
+            rewritten("insert into schema_name.table_name values ('it''s', 'b');", vec![])
+        );
+        assert_eq!(
+            rewrite("select '1'::integer;").query,
+            Cow::Borrowed("select '1'::integer;")
+        );
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::QueryError;
+use storage::{SequenceAlteration, SequenceDefinition};
+
+/// Sequence statements that sql parser does not know
+#[derive(Debug, PartialEq)]
+pub(crate) enum SequenceStatement {
+    Create {
+        name: String,
+        if_not_exists: bool,
+        definition: SequenceDefinition,
+    },
+    Drop {
+        name: String,
+        if_exists: bool,
+    },
+    Alter {
+        name: String,
+        if_exists: bool,
+        alterations: Vec<SequenceAlteration>,
+    },
+}
+
+impl SequenceStatement {
+    pub(crate) fn command(&self) -> &'static str {
+        match self {
+            SequenceStatement::Create { .. } => "CREATE SEQUENCE",
+            SequenceStatement::Drop { .. } => "DROP SEQUENCE",
+            SequenceStatement::Alter { .. } => "ALTER SEQUENCE",
+        }
+    }
+}
+
+/// returns `None` if the query is not a sequence statement
+pub(crate) fn parse(raw_sql_query: &str) -> Option<Result<SequenceStatement, QueryError>> {
+    let mut tokens = Tokens::new(raw_sql_query);
+    if tokens.keywords(&["create", "sequence"]) {
+        Some(create(tokens))
+    } else if tokens.keywords(&["drop", "sequence"]) {
+        Some(drop(tokens))
+    } else if tokens.keywords(&["alter", "sequence"]) {
+        Some(alter(tokens))
+    } else {
+        None
+    }
+}
+
+// only START, INCREMENT and CACHE options are supported
+fn create(mut tokens: Tokens) -> Result<SequenceStatement, QueryError> {
+    let if_not_exists = tokens.keywords(&["if", "not", "exists"]);
+    let name = tokens.name()?;
+    let mut start = None;
+    let mut definition = SequenceDefinition::default();
+    while let Some(option) = tokens.next() {
+        match option.to_lowercase().as_str() {
+            "start" => {
+                tokens.keywords(&["with"]);
+                start = Some(tokens.number()?);
+            }
+            "increment" => definition.increment = increment(&mut tokens)?,
+            "cache" => {
+                let cache = tokens.number()?;
+                if cache < 1 {
+                    return Err(QueryError::invalid_parameter_value(
+                        "CACHE".to_owned(),
+                        cache.to_string(),
+                    ));
+                }
+                definition.cache = cache as u64;
+            }
+            _ => return Err(unexpected("end of statement", Some(option))),
+        }
+    }
+    // descending sequences start from their largest value
+    definition.start = start.unwrap_or(if definition.increment > 0 { 1 } else { -1 });
+    Ok(SequenceStatement::Create {
+        name,
+        if_not_exists,
+        definition,
+    })
+}
+
+fn drop(mut tokens: Tokens) -> Result<SequenceStatement, QueryError> {
+    let if_exists = tokens.keywords(&["if", "exists"]);
+    let name = tokens.name()?;
+    match tokens.next() {
+        None => Ok(SequenceStatement::Drop { name, if_exists }),
+        token => Err(unexpected("end of statement", token)),
+    }
+}
+
+// only RESTART and INCREMENT options are supported
+fn alter(mut tokens: Tokens) -> Result<SequenceStatement, QueryError> {
+    let if_exists = tokens.keywords(&["if", "exists"]);
+    let name = tokens.name()?;
+    let mut alterations = vec![];
+    while let Some(option) = tokens.next() {
+        match option.to_lowercase().as_str() {
+            "restart" => {
+                let value = if tokens.keywords(&["with"]) {
+                    Some(tokens.number()?)
+                } else {
+                    tokens.optional_number()
+                };
+                alterations.push(SequenceAlteration::Restart(value));
+            }
+            "increment" => alterations.push(SequenceAlteration::IncrementBy(increment(&mut tokens)?)),
+            _ => return Err(unexpected("RESTART or INCREMENT", Some(option))),
+        }
+    }
+    if alterations.is_empty() {
+        return Err(unexpected("RESTART or INCREMENT", None));
+    }
+    Ok(SequenceStatement::Alter {
+        name,
+        if_exists,
+        alterations,
+    })
+}
+
+fn increment(tokens: &mut Tokens) -> Result<i64, QueryError> {
+    tokens.keywords(&["by"]);
+    let increment = tokens.number()?;
+    if increment == 0 {
+        return Err(QueryError::invalid_parameter_value(
+            "INCREMENT".to_owned(),
+            "0".to_owned(),
+        ));
+    }
+    Ok(increment)
+}
+
+fn unexpected(expected: &str, found: Option<&str>) -> QueryError {
+    QueryError::syntax_error(format!("Expected {}, found: {}", expected, found.unwrap_or("EOF")))
+}
+
+struct Tokens<'q> {
+    tokens: Vec<&'q str>,
+    position: usize,
+}
+
+impl<'q> Tokens<'q> {
+    fn new(raw_sql_query: &'q str) -> Self {
+        Self {
+            tokens: raw_sql_query.trim().trim_end_matches(';').split_whitespace().collect(),
+            position: 0,
+        }
+    }
+
+    fn next(&mut self) -> Option<&'q str> {
+        let token = self.tokens.get(self.position).copied();
+        self.position += 1;
+        token
+    }
+
+    // tokens are consumed only if all keywords match
+    fn keywords(&mut self, keywords: &[&str]) -> bool {
+        let matches = keywords.iter().enumerate().all(|(index, keyword)| {
+            self.tokens
+                .get(self.position + index)
+                .map(|token| token.eq_ignore_ascii_case(keyword))
+                .unwrap_or(false)
+        });
+        if matches {
+            self.position += keywords.len();
+        }
+        matches
+    }
+
+    fn name(&mut self) -> Result<String, QueryError> {
+        match self.next() {
+            Some(name) => Ok(name.to_owned()),
+            None => Err(unexpected("sequence name", None)),
+        }
+    }
+
+    fn number(&mut self) -> Result<i64, QueryError> {
+        match self.next() {
+            Some(token) => token.parse().map_err(|_| unexpected("a number", Some(token))),
+            None => Err(unexpected("a number", None)),
+        }
+    }
+
+    // token is consumed only if it is a number
+    fn optional_number(&mut self) -> Option<i64> {
+        let number = self.tokens.get(self.position).and_then(|token| token.parse().ok());
+        if number.is_some() {
+            self.position += 1;
+        }
+        number
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_a_sequence_statement() {
+        assert_eq!(parse("create table schema_name.table_name (id integer);"), None);
+        assert_eq!(parse("drop schema schema_name;"), None);
+    }
+
+    #[test]
+    fn create_sequence() {
+        assert_eq!(
+            parse("create sequence schema_name.sequence_name;"),
+            Some(Ok(SequenceStatement::Create {
+                name: "schema_name.sequence_name".to_owned(),
+                if_not_exists: false,
+                definition: SequenceDefinition::default(),
+            }))
+        );
+    }
+
+    #[test]
+    fn create_sequence_with_options() {
+        assert_eq!(
+            parse("CREATE SEQUENCE IF NOT EXISTS sequence_name INCREMENT BY 5 START WITH 100 CACHE 10"),
+            Some(Ok(SequenceStatement::Create {
+                name: "sequence_name".to_owned(),
+                if_not_exists: true,
+                definition: SequenceDefinition {
+                    start: 100,
+                    increment: 5,
+                    cache: 10,
+                },
+            }))
+        );
+    }
+
+    #[test]
+    fn descending_sequence() {
+        assert_eq!(
+            parse("create sequence sequence_name increment -1;"),
+            Some(Ok(SequenceStatement::Create {
+                name: "sequence_name".to_owned(),
+                if_not_exists: false,
+                definition: SequenceDefinition {
+                    start: -1,
+                    increment: -1,
+                    cache: 1,
+                },
+            }))
+        );
+    }
+
+    #[test]
+    fn zero_increment() {
+        assert_eq!(
+            parse("create sequence sequence_name increment by 0;"),
+            Some(Err(QueryError::invalid_parameter_value(
+                "INCREMENT".to_owned(),
+                "0".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn unsupported_option() {
+        assert_eq!(
+            parse("create sequence sequence_name maxvalue 10;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected end of statement, found: maxvalue".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn drop_sequence() {
+        assert_eq!(
+            parse("drop sequence if exists schema_name.sequence_name;"),
+            Some(Ok(SequenceStatement::Drop {
+                name: "schema_name.sequence_name".to_owned(),
+                if_exists: true,
+            }))
+        );
+        assert_eq!(
+            parse("drop sequence;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected sequence name, found: EOF".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn alter_sequence() {
+        assert_eq!(
+            parse("alter sequence schema_name.sequence_name restart;"),
+            Some(Ok(SequenceStatement::Alter {
+                name: "schema_name.sequence_name".to_owned(),
+                if_exists: false,
+                alterations: vec![SequenceAlteration::Restart(None)],
+            }))
+        );
+        assert_eq!(
+            parse("ALTER SEQUENCE IF EXISTS sequence_name RESTART WITH 5 INCREMENT BY 2"),
+            Some(Ok(SequenceStatement::Alter {
+                name: "sequence_name".to_owned(),
+                if_exists: true,
+                alterations: vec![SequenceAlteration::Restart(Some(5)), SequenceAlteration::IncrementBy(2)],
+            }))
+        );
+        assert_eq!(
+            parse("alter sequence sequence_name restart 7;"),
+            Some(Ok(SequenceStatement::Alter {
+                name: "sequence_name".to_owned(),
+                if_exists: false,
+                alterations: vec![SequenceAlteration::Restart(Some(7))],
+            }))
+        );
+    }
+
+    #[test]
+    fn alter_sequence_without_options() {
+        assert_eq!(
+            parse("alter sequence sequence_name;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected RESTART or INCREMENT, found: EOF".to_owned()
+            )))
+        );
+        assert_eq!(
+            parse("alter sequence sequence_name cache 10;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected RESTART or INCREMENT, found: cache".to_owned()
+            )))
+        );
+    }
+}
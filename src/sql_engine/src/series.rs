@@ -0,0 +1,102 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use sql_types::SqlType;
+use storage::RecordCursor;
+
+/// name of the set returning function that generates a series of integers
+pub const GENERATE_SERIES: &str = "generate_series";
+
+/// Integers from `start` to `stop` that are `step` apart. They are produced as
+/// records are consumed, so a long series is never kept in memory
+#[derive(Debug)]
+pub(crate) struct Series {
+    next: Option<i64>,
+    stop: i64,
+    step: i64,
+}
+
+impl Series {
+    // step can't be zero, the series would never end
+    pub(crate) fn new(start: i64, stop: i64, step: i64) -> Self {
+        Self {
+            next: Some(start),
+            stop,
+            step,
+        }
+    }
+
+    /// integers are reported as `bigint` only when they do not fit into `integer`
+    pub(crate) fn sql_type(&self) -> SqlType {
+        let fits = |value: i64| value >= i64::from(i32::MIN) && value <= i64::from(i32::MAX);
+        match self.next {
+            Some(start) if !fits(start) || !fits(self.stop) || !fits(self.step) => SqlType::BigInt,
+            _ => SqlType::Integer,
+        }
+    }
+
+    /// every record repeats the value for each of `columns`
+    pub(crate) fn into_records(self, columns: usize) -> RecordCursor {
+        Box::new(self.map(move |value| Ok(vec![value.to_string(); columns])))
+    }
+}
+
+impl Iterator for Series {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        if (self.step > 0 && current > self.stop) || (self.step < 0 && current < self.stop) {
+            self.next = None;
+            return None;
+        }
+        // series ends when the next value does not fit
+        self.next = current.checked_add(self.step);
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascending() {
+        assert_eq!(Series::new(1, 3, 1).collect::<Vec<i64>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn descending_with_step() {
+        assert_eq!(Series::new(10, 1, -4).collect::<Vec<i64>>(), vec![10, 6, 2]);
+    }
+
+    #[test]
+    fn empty_when_start_is_past_stop() {
+        assert_eq!(Series::new(3, 1, 1).collect::<Vec<i64>>(), vec![]);
+    }
+
+    #[test]
+    fn ends_before_overflow() {
+        assert_eq!(
+            Series::new(i64::MAX - 1, i64::MAX, 1).collect::<Vec<i64>>(),
+            vec![i64::MAX - 1, i64::MAX]
+        );
+    }
+
+    #[test]
+    fn bigint_when_values_do_not_fit_integer() {
+        assert_eq!(Series::new(1, 10, 1).sql_type(), SqlType::Integer);
+        assert_eq!(Series::new(1, i64::from(i32::MAX) + 1, 1).sql_type(), SqlType::BigInt);
+    }
+}
@@ -0,0 +1,519 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    datetime::{DateStyle, TimeZone},
+    math::Random,
+    QueryError, KILOBYTE,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use storage::SequenceBlock;
+
+/// version of PostgreSQL that server pretends to be to clients
+pub const SERVER_VERSION: &str = "12.4";
+const TEMP_FILE_LIMIT: &str = "temp_file_limit";
+const WORK_MEM: &str = "work_mem";
+// the least memory in kilobytes that a sort is given
+const MIN_WORK_MEM: u64 = 64;
+// prepared statements and portals that a session keeps, the least recently
+// used one is dropped to make room for a new one
+const MAX_PREPARED_STATEMENTS: &str = "max_prepared_statements";
+// milliseconds that a statement waits for a transaction block of another
+// session to end, `0` means that it waits as long as the block lasts
+const LOCK_TIMEOUT: &str = "lock_timeout";
+const DATE_STYLE: &str = "datestyle";
+const TIME_ZONE: &str = "timezone";
+/// parameters which values are reported to client whenever they change
+const REPORTED: [(&str, &str); 2] = [("DateStyle", DATE_STYLE), ("TimeZone", TIME_ZONE)];
+/// alias of the schema where session keeps its temporary objects
+pub const TEMP_SCHEMA: &str = "pg_temp";
+
+/// Client session properties and run-time parameters that the client has set
+#[derive(Debug)]
+pub struct Session {
+    id: u64,
+    user_name: String,
+    database_name: String,
+    settings: BTreeMap<String, String>,
+    // settings that last until the end of the current transaction block
+    local_settings: BTreeMap<String, String>,
+    sequences: SessionSequences,
+    random: Random,
+}
+
+impl Default for Session {
+    fn default() -> Session {
+        Session::new(0, "postgres", "postgres")
+    }
+}
+
+impl Session {
+    pub fn new(id: u64, user_name: &str, database_name: &str) -> Session {
+        let settings = vec![
+            ("application_name", ""),
+            ("client_encoding", "UTF8"),
+            (DATE_STYLE, "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            (LOCK_TIMEOUT, "0"),
+            (MAX_PREPARED_STATEMENTS, "1000"),
+            ("search_path", "public"),
+            ("server_encoding", "UTF8"),
+            ("server_version", SERVER_VERSION),
+            (TEMP_FILE_LIMIT, "-1"),
+            (TIME_ZONE, "UTC"),
+            (WORK_MEM, "4096"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect();
+        Session {
+            id,
+            user_name: user_name.to_owned(),
+            database_name: database_name.to_owned(),
+            settings,
+            local_settings: BTreeMap::new(),
+            sequences: SessionSequences::default(),
+            // sessions that are not seeded get different values
+            random: Random::new(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_nanos() as u64)
+                    .unwrap_or_default()
+                    ^ id,
+            ),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn user_name(&self) -> &str {
+        self.user_name.as_str()
+    }
+
+    pub fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    /// the first schema in search path
+    pub fn current_schema(&self) -> &str {
+        self.settings
+            .get("search_path")
+            .and_then(|search_path| search_path.split(',').next())
+            .map(str::trim)
+            .unwrap_or_default()
+    }
+
+    /// schema of temporary objects that is dropped when session ends
+    pub fn temp_schema(&self) -> String {
+        format!("{}_{}", TEMP_SCHEMA, self.id)
+    }
+
+    /// `pg_temp` refers to the temporary schema of the session
+    pub fn schema_name(&self, schema_name: String) -> String {
+        if schema_name.to_lowercase() == TEMP_SCHEMA {
+            self.temp_schema()
+        } else {
+            schema_name
+        }
+    }
+
+    /// parameter names are case insensitive, custom parameters have qualified
+    /// names like `app.tenant`
+    pub fn setting(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.local_settings
+            .get(&name)
+            .or_else(|| self.settings.get(&name))
+            .map(String::as_str)
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), QueryError> {
+        let (name, value) = self.validate(name, value)?;
+        self.local_settings.remove(&name);
+        self.settings.insert(name, value);
+        Ok(())
+    }
+
+    /// the value is used instead of the session one until the transaction block ends
+    pub fn set_local(&mut self, name: &str, value: &str) -> Result<(), QueryError> {
+        let (name, value) = self.validate(name, value)?;
+        self.local_settings.insert(name, value);
+        Ok(())
+    }
+
+    // values of date and time parameters are kept in their canonical form
+    fn validate(&self, name: &str, value: &str) -> Result<(String, String), QueryError> {
+        let name = name.to_lowercase();
+        let canonical = match name.as_str() {
+            TEMP_FILE_LIMIT => value.parse::<i64>().ok().map(|_limit| value.to_owned()),
+            WORK_MEM => value
+                .parse::<u64>()
+                .ok()
+                .filter(|kilobytes| *kilobytes >= MIN_WORK_MEM)
+                .map(|_kilobytes| value.to_owned()),
+            LOCK_TIMEOUT => value.parse::<u64>().ok().map(|_milliseconds| value.to_owned()),
+            MAX_PREPARED_STATEMENTS => value
+                .parse::<usize>()
+                .ok()
+                .filter(|limit| *limit > 0)
+                .map(|_limit| value.to_owned()),
+            DATE_STYLE => DateStyle::parse(value, self.date_style()).map(|style| style.to_string()),
+            TIME_ZONE => TimeZone::parse(value).map(|zone| zone.name()),
+            _ => Some(value.to_owned()),
+        };
+        match canonical {
+            Some(value) => Ok((name, value)),
+            None => Err(QueryError::invalid_parameter_value(name, value.to_owned())),
+        }
+    }
+
+    /// format of date and time values that client sends and receives
+    pub(crate) fn date_style(&self) -> DateStyle {
+        self.setting(DATE_STYLE)
+            .and_then(|value| DateStyle::parse(value, DateStyle::default()))
+            .unwrap_or_default()
+    }
+
+    /// time zone of timestamps that client sends and receives
+    pub(crate) fn time_zone(&self) -> TimeZone {
+        self.setting(TIME_ZONE).and_then(TimeZone::parse).unwrap_or_default()
+    }
+
+    /// current values of parameters that client is notified about
+    pub fn reported_settings(&self) -> Vec<(String, String)> {
+        REPORTED
+            .iter()
+            .map(|(reported, name)| {
+                (
+                    (*reported).to_owned(),
+                    self.setting(name).unwrap_or_default().to_owned(),
+                )
+            })
+            .collect()
+    }
+
+    pub fn reset_local_settings(&mut self) {
+        self.local_settings.clear();
+    }
+
+    pub fn sequences(&mut self) -> &mut SessionSequences {
+        &mut self.sequences
+    }
+
+    /// memory in bytes that a sort keeps rows in before it spills them to temp
+    /// files, it is set in kilobytes
+    pub fn work_mem(&self) -> u64 {
+        self.setting(WORK_MEM)
+            .and_then(|kilobytes| kilobytes.parse::<u64>().ok())
+            .unwrap_or(MIN_WORK_MEM)
+            * KILOBYTE
+    }
+
+    /// how long a statement waits for a transaction block of another session
+    /// to end, `None` if it waits as long as the block lasts
+    pub fn lock_timeout(&self) -> Option<Duration> {
+        self.setting(LOCK_TIMEOUT)
+            .and_then(|milliseconds| milliseconds.parse::<u64>().ok())
+            .filter(|milliseconds| *milliseconds > 0)
+            .map(Duration::from_millis)
+    }
+
+    /// number of prepared statements, as well as of portals, that session keeps
+    pub fn max_prepared_statements(&self) -> usize {
+        self.setting(MAX_PREPARED_STATEMENTS)
+            .and_then(|limit| limit.parse::<usize>().ok())
+            .unwrap_or(1)
+    }
+
+    /// generator of `random()` values that `setseed()` seeds
+    pub(crate) fn random(&self) -> &Random {
+        &self.random
+    }
+
+    /// total size in bytes of temp files that a query is allowed to spill, it
+    /// is set in kilobytes and negative value means that there is no limit
+    pub fn temp_file_limit(&self) -> Option<u64> {
+        self.setting(TEMP_FILE_LIMIT)
+            .and_then(|limit| limit.parse::<i64>().ok())
+            .filter(|kilobytes| *kilobytes >= 0)
+            .map(|kilobytes| kilobytes as u64 * KILOBYTE)
+    }
+}
+
+/// Sequence values that session has reserved and the ones it has used,
+/// sequences are identified by their full names
+#[derive(Debug, Default)]
+pub struct SessionSequences {
+    reserved: HashMap<String, SequenceBlock>,
+    current: HashMap<String, i64>,
+    last: Option<i64>,
+}
+
+impl SessionSequences {
+    /// takes the next reserved value of the sequence
+    pub fn next_value(&mut self, sequence: &str) -> Option<i64> {
+        let value = self.reserved.get_mut(sequence)?.next_value()?;
+        self.set_current(sequence, value);
+        self.last = Some(value);
+        Some(value)
+    }
+
+    pub fn reserve(&mut self, sequence: &str, block: SequenceBlock) {
+        self.reserved.insert(sequence.to_owned(), block);
+    }
+
+    /// drops reserved values, so the next value is read from storage
+    pub fn reset(&mut self, sequence: &str) {
+        self.reserved.remove(sequence);
+    }
+
+    pub fn set_current(&mut self, sequence: &str, value: i64) {
+        self.current.insert(sequence.to_owned(), value);
+    }
+
+    /// value that `nextval` has returned in the session the last time for the sequence
+    pub fn current_value(&self, sequence: &str) -> Option<i64> {
+        self.current.get(sequence).copied()
+    }
+
+    /// value that `nextval` has returned in the session the last time for any sequence
+    pub fn last_value(&self) -> Option<i64> {
+        self.last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setting_names_are_case_insensitive() {
+        let mut session = Session::default();
+
+        assert_eq!(session.set("DateStyle", "ISO, DMY"), Ok(()));
+        assert_eq!(session.setting("datestyle"), Some("ISO, DMY"));
+    }
+
+    #[test]
+    fn custom_settings() {
+        let mut session = Session::default();
+
+        assert_eq!(session.setting("my.app_tenant"), None);
+
+        session.set("my.app_tenant", "42").expect("custom setting is set");
+
+        assert_eq!(session.setting("My.App_Tenant"), Some("42"));
+    }
+
+    #[test]
+    fn local_settings_override_session_ones() {
+        let mut session = Session::default();
+        session.set("my.app_tenant", "42").expect("setting is set");
+
+        session.set_local("my.app_tenant", "43").expect("local setting is set");
+        session.set_local("my.app_user", "alice").expect("local setting is set");
+
+        assert_eq!(session.setting("my.app_tenant"), Some("43"));
+        assert_eq!(session.setting("my.app_user"), Some("alice"));
+
+        session.reset_local_settings();
+
+        assert_eq!(session.setting("my.app_tenant"), Some("42"));
+        assert_eq!(session.setting("my.app_user"), None);
+    }
+
+    #[test]
+    fn session_setting_replaces_local_one() {
+        let mut session = Session::default();
+        session.set_local("my.app_tenant", "43").expect("local setting is set");
+
+        session.set("my.app_tenant", "42").expect("setting is set");
+        session.reset_local_settings();
+
+        assert_eq!(session.setting("my.app_tenant"), Some("42"));
+    }
+
+    #[test]
+    fn current_schema_is_first_in_search_path() {
+        let mut session = Session::default();
+
+        assert_eq!(session.current_schema(), "public");
+
+        session
+            .set("search_path", "schema_name, public")
+            .expect("search path is set");
+
+        assert_eq!(session.current_schema(), "schema_name");
+    }
+
+    #[test]
+    fn pg_temp_is_temporary_schema_of_session() {
+        let session = Session::new(42, "postgres", "postgres");
+
+        assert_eq!(session.temp_schema(), "pg_temp_42");
+        assert_eq!(session.schema_name("pg_temp".to_owned()), "pg_temp_42");
+        assert_eq!(session.schema_name("PG_TEMP".to_owned()), "pg_temp_42");
+        assert_eq!(session.schema_name("public".to_owned()), "public");
+    }
+
+    #[test]
+    fn work_mem() {
+        let mut session = Session::default();
+
+        assert_eq!(session.work_mem(), 4096 * 1024);
+        assert_eq!(
+            session.set("work_mem", "16"),
+            Err(QueryError::invalid_parameter_value(
+                "work_mem".to_owned(),
+                "16".to_owned()
+            ))
+        );
+
+        session.set("work_mem", "64").expect("work_mem is set");
+
+        assert_eq!(session.work_mem(), 64 * 1024);
+    }
+
+    #[test]
+    fn max_prepared_statements() {
+        let mut session = Session::default();
+
+        assert_eq!(session.max_prepared_statements(), 1000);
+        assert_eq!(
+            session.set("max_prepared_statements", "0"),
+            Err(QueryError::invalid_parameter_value(
+                "max_prepared_statements".to_owned(),
+                "0".to_owned()
+            ))
+        );
+
+        session.set("max_prepared_statements", "10").expect("limit is set");
+
+        assert_eq!(session.max_prepared_statements(), 10);
+    }
+
+    #[test]
+    fn lock_timeout() {
+        let mut session = Session::default();
+
+        assert_eq!(session.lock_timeout(), None);
+        assert_eq!(
+            session.set("lock_timeout", "-1"),
+            Err(QueryError::invalid_parameter_value(
+                "lock_timeout".to_owned(),
+                "-1".to_owned()
+            ))
+        );
+
+        session.set("lock_timeout", "100").expect("timeout is set");
+
+        assert_eq!(session.lock_timeout(), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn temp_file_limit() {
+        let mut session = Session::default();
+
+        assert_eq!(session.temp_file_limit(), None);
+
+        session.set("temp_file_limit", "2").expect("limit is set");
+
+        assert_eq!(session.temp_file_limit(), Some(2048));
+    }
+
+    #[test]
+    fn invalid_temp_file_limit() {
+        let mut session = Session::default();
+
+        assert_eq!(
+            session.set("temp_file_limit", "unlimited"),
+            Err(QueryError::invalid_parameter_value(
+                "temp_file_limit".to_owned(),
+                "unlimited".to_owned()
+            ))
+        );
+        assert_eq!(session.setting("temp_file_limit"), Some("-1"));
+    }
+
+    #[test]
+    fn date_style_is_kept_in_canonical_form() {
+        let mut session = Session::default();
+
+        session.set("DateStyle", "sql, dmy").expect("date style is set");
+        assert_eq!(session.setting("datestyle"), Some("SQL, DMY"));
+
+        session.set("DateStyle", "ymd").expect("date style is set");
+        assert_eq!(session.setting("datestyle"), Some("SQL, YMD"));
+    }
+
+    #[test]
+    fn invalid_date_style() {
+        let mut session = Session::default();
+
+        assert_eq!(
+            session.set("DateStyle", "ISO, SQL"),
+            Err(QueryError::invalid_parameter_value(
+                "datestyle".to_owned(),
+                "ISO, SQL".to_owned()
+            ))
+        );
+        assert_eq!(session.setting("datestyle"), Some("ISO, MDY"));
+    }
+
+    #[test]
+    fn time_zone() {
+        let mut session = Session::default();
+
+        assert_eq!(session.setting("TimeZone"), Some("UTC"));
+
+        session.set("TimeZone", "europe/kiev").expect("time zone is set");
+        assert_eq!(session.setting("TimeZone"), Some("Europe/Kiev"));
+
+        assert_eq!(
+            session.set("TimeZone", "Mars/Olympus_Mons"),
+            Err(QueryError::invalid_parameter_value(
+                "timezone".to_owned(),
+                "Mars/Olympus_Mons".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn reported_settings() {
+        let mut session = Session::default();
+        session.set_local("TimeZone", "-8").expect("time zone is set");
+
+        assert_eq!(
+            session.reported_settings(),
+            vec![
+                ("DateStyle".to_owned(), "ISO, MDY".to_owned()),
+                ("TimeZone".to_owned(), "-08".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn no_sequence_values_without_reservation() {
+        let mut session = Session::default();
+
+        assert_eq!(session.sequences().next_value("public.sequence_name"), None);
+        assert_eq!(session.sequences().current_value("public.sequence_name"), None);
+        assert_eq!(session.sequences().last_value(), None);
+    }
+}
@@ -0,0 +1,446 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::expression::{self, Datum};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use kernel::{SystemError, SystemResult};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    io::{BufReader, ErrorKind, Read},
+    sync::Arc,
+};
+use storage::{
+    temp::{QueryTempFiles, TempFile, TempFileLimitExceeded, TempStorage},
+    RecordCursor,
+};
+
+// nulls are put before or after other values whichever way values are ordered
+const NULLS_FIRST: u8 = 0;
+const NOT_NULL: u8 = 1;
+const NULLS_LAST: u8 = 2;
+
+/// Appends a value of ORDER BY key to the bytes that rows are sorted by.
+/// Bytes of values compare in the same order as the values do, sign bits of
+/// numbers are flipped so negative ones come first. Bytes of descending keys
+/// are inverted, except the byte that tells nulls from other values
+pub(crate) fn encode_key(datum: &Datum, descending: bool, nulls_first: bool, key: &mut Vec<u8>) {
+    let start = key.len() + 1;
+    match datum {
+        Datum::Null if nulls_first => key.push(NULLS_FIRST),
+        Datum::Null => key.push(NULLS_LAST),
+        Datum::Boolean(value) => key.extend_from_slice(&[NOT_NULL, *value as u8]),
+        Datum::Integer(value) => {
+            key.push(NOT_NULL);
+            key.extend_from_slice(&(*value as u64 ^ 1 << 63).to_be_bytes());
+        }
+        Datum::Float(value) | Datum::Numeric(value) => {
+            key.push(NOT_NULL);
+            // negative values are ordered backwards by their bits, NaN is
+            // greater than any other number
+            let bits = value.to_bits();
+            let bits = if *value < 0.0 { !bits } else { bits | (1 << 63) };
+            key.extend_from_slice(&bits.to_be_bytes());
+        }
+        Datum::Text(value) | Datum::Unknown(value) => {
+            key.push(NOT_NULL);
+            // zero bytes are escaped, so a shorter text that is a prefix of a
+            // longer one comes first
+            for byte in value.bytes() {
+                key.push(byte);
+                if byte == 0 {
+                    key.push(0xFF);
+                }
+            }
+            key.extend_from_slice(&[0, 0]);
+        }
+        Datum::Date(date) => {
+            key.push(NOT_NULL);
+            key.extend_from_slice(&(date.num_days_from_ce() as u32 ^ 1 << 31).to_be_bytes());
+        }
+        Datum::Timestamp(timestamp) | Datum::TimestampWithTimeZone(timestamp) => {
+            key.push(NOT_NULL);
+            key.extend_from_slice(&instant(timestamp));
+        }
+        Datum::Interval(interval) => {
+            key.push(NOT_NULL);
+            key.extend_from_slice(&(expression::span(interval) as u128 ^ 1 << 127).to_be_bytes());
+        }
+    }
+    if descending {
+        for byte in &mut key[start..] {
+            *byte = !*byte;
+        }
+    }
+}
+
+fn instant(timestamp: &NaiveDateTime) -> [u8; 12] {
+    let mut bytes = [0; 12];
+    bytes[..4].copy_from_slice(&(timestamp.date().num_days_from_ce() as u32 ^ 1 << 31).to_be_bytes());
+    bytes[4..8].copy_from_slice(&timestamp.time().num_seconds_from_midnight().to_be_bytes());
+    bytes[8..].copy_from_slice(&timestamp.time().nanosecond().to_be_bytes());
+    bytes
+}
+
+/// Sorts rows by their keys. Rows are kept in memory until they take more
+/// than `work_mem`, then they are sorted and spilled to a temp file as a run.
+/// Runs are merged as sorted rows are consumed. When only first rows are
+/// needed the buffer is cut down to them instead
+pub(crate) struct Sorter {
+    buffer: Vec<(Vec<u8>, Vec<String>)>,
+    buffered: u64,
+    work_mem: u64,
+    // number of first rows that are needed
+    bound: Option<usize>,
+    temp: Option<Arc<TempStorage>>,
+    temp_file_limit: Option<u64>,
+    files: Option<QueryTempFiles>,
+    runs: Vec<TempFile>,
+}
+
+impl Sorter {
+    /// rows are not spilled when there is no temp storage
+    pub(crate) fn new(work_mem: u64, temp: Option<Arc<TempStorage>>, temp_file_limit: Option<u64>) -> Sorter {
+        Sorter {
+            buffer: vec![],
+            buffered: 0,
+            work_mem,
+            bound: None,
+            temp,
+            temp_file_limit,
+            files: None,
+            runs: vec![],
+        }
+    }
+
+    pub(crate) fn with_bound(mut self, bound: Option<usize>) -> Sorter {
+        self.bound = bound;
+        self
+    }
+
+    pub(crate) fn push(&mut self, key: Vec<u8>, row: Vec<String>) -> SystemResult<Result<(), TempFileLimitExceeded>> {
+        self.buffered += size(&key, &row);
+        self.buffer.push((key, row));
+        if let Some(bound) = self.bound {
+            if self.buffer.len() > bound.max(1) * 2 {
+                self.sort_buffer();
+                self.buffer.truncate(bound);
+                self.buffered = self.buffer.iter().map(|(key, row)| size(key, row)).sum();
+            }
+        }
+        if self.buffered > self.work_mem && self.temp.is_some() {
+            self.spill()
+        } else {
+            Ok(Ok(()))
+        }
+    }
+
+    /// rows in order of their keys, equal ones are kept in order they are pushed
+    pub(crate) fn finish(mut self) -> SystemResult<RecordCursor> {
+        self.sort_buffer();
+        if let Some(bound) = self.bound {
+            self.buffer.truncate(bound);
+        }
+        if self.runs.is_empty() {
+            return Ok(Box::new(self.buffer.into_iter().map(|(_key, row)| Ok(row))));
+        }
+        let mut sources: Vec<Source> = vec![];
+        for mut run in self.runs {
+            run.rewind()?;
+            sources.push(Box::new(RunReader {
+                reader: BufReader::new(run),
+            }));
+        }
+        sources.push(Box::new(self.buffer.into_iter().map(Ok)));
+        Merge::new(sources, self.files).map(|merge| Box::new(merge) as RecordCursor)
+    }
+
+    fn sort_buffer(&mut self) {
+        self.buffer.sort_by(|(left, _), (right, _)| left.cmp(right));
+    }
+
+    fn spill(&mut self) -> SystemResult<Result<(), TempFileLimitExceeded>> {
+        self.sort_buffer();
+        if let Some(bound) = self.bound {
+            self.buffer.truncate(bound);
+        }
+        if self.files.is_none() {
+            if let Some(temp) = &self.temp {
+                self.files = Some(temp.new_query_files(self.temp_file_limit)?);
+            }
+        }
+        let files = match &mut self.files {
+            Some(files) => files,
+            None => return Ok(Ok(())),
+        };
+        let mut run = files.create()?;
+        let mut bytes = vec![];
+        for (key, row) in self.buffer.drain(..) {
+            write_bytes(&mut bytes, &key);
+            bytes.extend_from_slice(&(row.len() as u32).to_be_bytes());
+            for value in &row {
+                write_bytes(&mut bytes, value.as_bytes());
+            }
+        }
+        self.buffered = 0;
+        if let Err(exceeded) = run.append(&bytes)? {
+            return Ok(Err(exceeded));
+        }
+        self.runs.push(run);
+        Ok(Ok(()))
+    }
+}
+
+fn size(key: &[u8], row: &[String]) -> u64 {
+    (key.len() + row.iter().map(String::len).sum::<usize>()) as u64
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(value);
+}
+
+// rows of a run are read back in the format they are spilled in
+struct RunReader {
+    reader: BufReader<TempFile>,
+}
+
+impl RunReader {
+    fn read_u32(&mut self) -> SystemResult<Option<u32>> {
+        let mut bytes = [0; 4];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Ok(Some(u32::from_be_bytes(bytes))),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(error) => Err(SystemError::io(error)),
+        }
+    }
+
+    fn read_bytes(&mut self) -> SystemResult<Vec<u8>> {
+        let len = self.read_u32()?.unwrap_or_default();
+        let mut bytes = vec![0; len as usize];
+        self.reader.read_exact(&mut bytes).map_err(SystemError::io)?;
+        Ok(bytes)
+    }
+
+    fn read_row(&mut self) -> SystemResult<Option<(Vec<u8>, Vec<String>)>> {
+        let key_len = match self.read_u32()? {
+            Some(key_len) => key_len,
+            None => return Ok(None),
+        };
+        let mut key = vec![0; key_len as usize];
+        self.reader.read_exact(&mut key).map_err(SystemError::io)?;
+        let columns = self.read_u32()?.unwrap_or_default();
+        let mut row = Vec::with_capacity(columns as usize);
+        for _ in 0..columns {
+            let value = self.read_bytes()?;
+            row.push(String::from_utf8(value).map_err(|error| SystemError::unrecoverable(error.to_string()))?);
+        }
+        Ok(Some((key, row)))
+    }
+}
+
+impl Iterator for RunReader {
+    type Item = SystemResult<(Vec<u8>, Vec<String>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_row().transpose()
+    }
+}
+
+type Source = Box<dyn Iterator<Item = SystemResult<(Vec<u8>, Vec<String>)>> + Send>;
+
+// rows of earlier runs come first when keys are equal
+struct Merge {
+    sources: Vec<Source>,
+    heap: BinaryHeap<Reverse<(Vec<u8>, usize, RowOrder)>>,
+    // files are removed when rows are merged
+    _files: Option<QueryTempFiles>,
+}
+
+impl Merge {
+    fn new(mut sources: Vec<Source>, files: Option<QueryTempFiles>) -> SystemResult<Merge> {
+        let mut heap = BinaryHeap::new();
+        for (index, source) in sources.iter_mut().enumerate() {
+            if let Some(head) = source.next() {
+                let (key, row) = head?;
+                heap.push(Reverse((key, index, RowOrder(row))));
+            }
+        }
+        Ok(Merge {
+            sources,
+            heap,
+            _files: files,
+        })
+    }
+}
+
+impl Iterator for Merge {
+    type Item = SystemResult<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_key, index, RowOrder(row))) = self.heap.pop()?;
+        match self.sources[index].next() {
+            Some(Ok((key, next))) => self.heap.push(Reverse((key, index, RowOrder(next)))),
+            Some(Err(error)) => return Some(Err(error)),
+            None => {}
+        }
+        Some(Ok(row))
+    }
+}
+
+// rows are not compared, keys and indexes of runs decide their order
+struct RowOrder(Vec<String>);
+
+impl PartialEq for RowOrder {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for RowOrder {}
+
+impl PartialOrd for RowOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RowOrder {
+    fn cmp(&self, _other: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // nulls are last in ascending and first in descending order by default
+    fn key(datum: Datum, descending: bool) -> Vec<u8> {
+        ordered_key(datum, descending, descending)
+    }
+
+    fn ordered_key(datum: Datum, descending: bool, nulls_first: bool) -> Vec<u8> {
+        let mut key = vec![];
+        encode_key(&datum, descending, nulls_first, &mut key);
+        key
+    }
+
+    fn root(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("database_sort_{}_{}", name, std::process::id()))
+    }
+
+    fn row(value: &str) -> Vec<String> {
+        vec![value.to_owned()]
+    }
+
+    fn sorted(sorter: Sorter) -> Vec<String> {
+        sorter
+            .finish()
+            .expect("no system errors")
+            .map(|row| row.expect("no system errors").remove(0))
+            .collect()
+    }
+
+    #[test]
+    fn numbers_are_ordered_by_value() {
+        assert!(key(Datum::Integer(-2), false) < key(Datum::Integer(-1), false));
+        assert!(key(Datum::Integer(-1), false) < key(Datum::Integer(0), false));
+        assert!(key(Datum::Float(-1.5), false) < key(Datum::Float(-0.5), false));
+        assert!(key(Datum::Float(-0.5), false) < key(Datum::Float(2.0), false));
+        assert!(key(Datum::Integer(i64::MAX), false) < key(Datum::Null, false));
+    }
+
+    #[test]
+    fn texts_are_ordered_by_bytes() {
+        assert!(key(Datum::Text("ab".to_owned()), false) < key(Datum::Text("abc".to_owned()), false));
+        assert!(key(Datum::Text("a\0b".to_owned()), false) < key(Datum::Text("ab".to_owned()), false));
+        assert!(key(Datum::Text("a".to_owned()), false) < key(Datum::Text("a\0".to_owned()), false));
+    }
+
+    #[test]
+    fn descending_keys() {
+        assert!(key(Datum::Integer(2), true) < key(Datum::Integer(1), true));
+        assert!(key(Datum::Text("b".to_owned()), true) < key(Datum::Text("ab".to_owned()), true));
+        assert!(key(Datum::Null, true) < key(Datum::Integer(1), true));
+    }
+
+    #[test]
+    fn nulls_first_and_last() {
+        assert!(ordered_key(Datum::Null, false, true) < ordered_key(Datum::Integer(i64::MIN), false, true));
+        assert!(ordered_key(Datum::Integer(i64::MAX), true, false) < ordered_key(Datum::Null, true, false));
+        assert!(ordered_key(Datum::Null, true, true) < ordered_key(Datum::Integer(i64::MAX), true, true));
+        assert!(ordered_key(Datum::Text("".to_owned()), false, false) < ordered_key(Datum::Null, false, false));
+    }
+
+    #[test]
+    fn equal_keys_keep_order_of_rows() {
+        let mut sorter = Sorter::new(1024, None, None);
+        for (value, name) in &[(2, "a"), (1, "b"), (2, "c"), (1, "d")] {
+            sorter
+                .push(key(Datum::Integer(*value), false), row(name))
+                .expect("no system errors")
+                .expect("row is sorted");
+        }
+
+        assert_eq!(sorted(sorter), vec!["b", "d", "a", "c"]);
+    }
+
+    #[test]
+    fn spilled_runs_are_merged() {
+        let temp = Arc::new(TempStorage::open(root("merged")).expect("temp storage is opened"));
+        let mut sorter = Sorter::new(32, Some(temp), None);
+        for value in (0..20).rev() {
+            sorter
+                .push(key(Datum::Integer(value % 10), false), row(&value.to_string()))
+                .expect("no system errors")
+                .expect("row is sorted");
+        }
+        let expected: Vec<String> = (0..10)
+            .flat_map(|value| vec![(value + 10).to_string(), value.to_string()])
+            .collect();
+
+        assert_eq!(sorted(sorter), expected);
+    }
+
+    #[test]
+    fn only_first_rows_are_kept() {
+        let mut sorter = Sorter::new(1024, None, None).with_bound(Some(3));
+        for value in 0..100 {
+            sorter
+                .push(key(Datum::Integer(value), true), row(&value.to_string()))
+                .expect("no system errors")
+                .expect("row is sorted");
+        }
+
+        assert!(sorter.buffer.len() <= 6);
+        assert_eq!(sorted(sorter), vec!["99", "98", "97"]);
+    }
+
+    #[test]
+    fn temp_file_limit_is_exceeded() {
+        let temp = Arc::new(TempStorage::open(root("limited")).expect("temp storage is opened"));
+        let mut sorter = Sorter::new(8, Some(temp), Some(16));
+        let pushed = (0..10)
+            .map(|value| {
+                sorter
+                    .push(key(Datum::Integer(value), false), row("value"))
+                    .expect("no system errors")
+            })
+            .find(Result::is_err);
+
+        assert_eq!(pushed, Some(Err(TempFileLimitExceeded(16))));
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::QueryEvent;
+use sql_types::SqlType;
+use sqlparser::{
+    dialect::{keywords::Keyword, PostgreSqlDialect},
+    tokenizer::{Token, Tokenizer},
+};
+use std::{collections::BTreeMap, time::Duration};
+use storage::Projection;
+
+/// name of the virtual table that exposes collected statement statistics
+pub const PG_STAT_STATEMENTS: &str = "pg_stat_statements";
+/// the same as default of `pg_stat_statements.max`
+const MAX_ENTRIES: usize = 5000;
+
+#[derive(Debug, Default)]
+struct StatementEntry {
+    calls: u64,
+    total_time: Duration,
+    rows: usize,
+}
+
+/// Aggregates executed statements by their normalized text, the least called
+/// statement is dropped when there are as many of them as it is allowed
+#[derive(Debug)]
+pub struct StatementStatistics {
+    entries: BTreeMap<String, StatementEntry>,
+    max_entries: usize,
+}
+
+impl Default for StatementStatistics {
+    fn default() -> StatementStatistics {
+        StatementStatistics {
+            entries: BTreeMap::new(),
+            max_entries: MAX_ENTRIES,
+        }
+    }
+}
+
+impl StatementStatistics {
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn record(&mut self, raw_sql_query: &str, elapsed: Duration, event: &QueryEvent) {
+        let fingerprint = fingerprint(raw_sql_query);
+        if !self.entries.contains_key(&fingerprint) {
+            while !self.entries.is_empty() && self.entries.len() >= self.max_entries {
+                let least_called = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_query, entry)| entry.calls)
+                    .map(|(query, _entry)| query.clone())
+                    .expect("there is an entry");
+                self.entries.remove(&least_called);
+            }
+        }
+        let entry = self.entries.entry(fingerprint).or_default();
+        entry.calls += 1;
+        entry.total_time += elapsed;
+        entry.rows += match event {
+            QueryEvent::RecordsInserted(records)
+            | QueryEvent::RecordsUpdated(records)
+            | QueryEvent::RecordsDeleted(records) => *records,
+            QueryEvent::RecordsSelected((_columns, records)) => records.len(),
+            _ => 0,
+        };
+    }
+
+    /// rows of a statement that is evicted since it was recorded are dropped
+    pub fn add_rows(&mut self, raw_sql_query: &str, rows: usize) {
+        if let Some(entry) = self.entries.get_mut(&fingerprint(raw_sql_query)) {
+            entry.rows += rows;
+        }
+    }
+
+    pub fn projection(&self) -> Projection {
+        let columns = vec![
+            ("query".to_owned(), SqlType::VarChar(255)),
+            ("calls".to_owned(), SqlType::BigInt),
+            ("total_time".to_owned(), SqlType::DoublePrecision),
+            ("mean_time".to_owned(), SqlType::DoublePrecision),
+            ("rows".to_owned(), SqlType::BigInt),
+        ];
+        let records = self
+            .entries
+            .iter()
+            .map(|(query, entry)| {
+                let total_time = entry.total_time.as_secs_f64() * 1000.0;
+                vec![
+                    query.clone(),
+                    entry.calls.to_string(),
+                    total_time.to_string(),
+                    (total_time / entry.calls as f64).to_string(),
+                    entry.rows.to_string(),
+                ]
+            })
+            .collect();
+        (columns, records)
+    }
+}
+
+/// Replaces constants, along with minus signs of negative numbers, with positional
+/// parameters and collapses whitespaces, so statements that differ only in their
+/// values share the same fingerprint
+pub fn fingerprint(raw_sql_query: &str) -> String {
+    let tokens = match Tokenizer::new(&PostgreSqlDialect {}, raw_sql_query).tokenize() {
+        Ok(tokens) => tokens,
+        Err(_) => return raw_sql_query.trim().to_owned(),
+    };
+    let mut parameters = 0;
+    let mut normalized = String::with_capacity(raw_sql_query.len());
+    // the last token that is not a whitespace
+    let mut previous = None;
+    // whitespaces between a sign and its number are dropped along with the sign
+    let mut signed = false;
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Minus if is_unary(previous) && starts_number(&tokens[index + 1..]) => {
+                signed = true;
+                continue;
+            }
+            Token::Number(_)
+            | Token::SingleQuotedString(_)
+            | Token::NationalStringLiteral(_)
+            | Token::HexStringLiteral(_) => {
+                parameters += 1;
+                normalized.push_str(format!("${}", parameters).as_str());
+            }
+            Token::Whitespace(_) => {
+                if !signed && !normalized.is_empty() && !normalized.ends_with(' ') {
+                    normalized.push(' ');
+                }
+                continue;
+            }
+            token => normalized.push_str(token.to_string().as_str()),
+        }
+        previous = Some(token);
+        signed = false;
+    }
+    normalized.trim_end().to_owned()
+}
+
+/// minus is a sign of a number that follows it unless there is an operand before it
+fn is_unary(previous: Option<&Token>) -> bool {
+    match previous {
+        None => true,
+        // keywords that could be names of columns are operands
+        Some(Token::Word(word)) => {
+            word.quote_style.is_none()
+                && matches!(
+                    word.keyword,
+                    Keyword::SELECT
+                        | Keyword::WHERE
+                        | Keyword::HAVING
+                        | Keyword::AND
+                        | Keyword::OR
+                        | Keyword::NOT
+                        | Keyword::BETWEEN
+                        | Keyword::WHEN
+                        | Keyword::THEN
+                        | Keyword::ELSE
+                        | Keyword::LIMIT
+                        | Keyword::OFFSET
+                )
+        }
+        Some(token) => !matches!(
+            token,
+            Token::Number(_)
+                | Token::SingleQuotedString(_)
+                | Token::NationalStringLiteral(_)
+                | Token::HexStringLiteral(_)
+                | Token::RParen
+                | Token::RBracket
+        ),
+    }
+}
+
+fn starts_number(tokens: &[Token]) -> bool {
+    matches!(
+        tokens.iter().find(|token| !matches!(token, Token::Whitespace(_))),
+        Some(Token::Number(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants_are_replaced_with_parameters() {
+        assert_eq!(
+            fingerprint("insert into schema_name.table_name values (1, 'a');"),
+            "insert into schema_name.table_name values ($1, $2);"
+        );
+    }
+
+    #[test]
+    fn statements_with_different_constants_have_the_same_fingerprint() {
+        assert_eq!(
+            fingerprint("update schema_name.table_name set column_1 = 123;"),
+            fingerprint("update  schema_name.table_name\nset column_1 = 456;")
+        );
+    }
+
+    #[test]
+    fn negative_numbers_are_replaced_with_parameters() {
+        assert_eq!(
+            fingerprint("select * from schema_name.table_name where column_1 = -5 and column_2 in (- 1, -2);"),
+            fingerprint("select * from schema_name.table_name where column_1 = 5 and column_2 in (1, 2);")
+        );
+        assert_eq!(
+            fingerprint("select -5, column_1 - 5, value - 5, -column_2 from schema_name.table_name;"),
+            "select $1, column_1 - $2, value - $3, -column_2 from schema_name.table_name;"
+        );
+    }
+
+    #[test]
+    fn least_called_statement_is_evicted() {
+        let mut statistics = StatementStatistics::default().with_max_entries(2);
+        let elapsed = Duration::from_millis(1);
+        statistics.record("insert into t values (1);", elapsed, &QueryEvent::RecordsInserted(1));
+        statistics.record("insert into t values (2);", elapsed, &QueryEvent::RecordsInserted(1));
+        statistics.record("delete from t;", elapsed, &QueryEvent::RecordsDeleted(2));
+        statistics.record("update t set a = 1;", elapsed, &QueryEvent::RecordsUpdated(3));
+        statistics.add_rows("delete from t;", 5);
+
+        let (_columns, records) = statistics.projection();
+        assert_eq!(
+            records
+                .into_iter()
+                .map(|record| (record[0].clone(), record[1].clone(), record[4].clone()))
+                .collect::<Vec<(String, String, String)>>(),
+            vec![
+                ("insert into t values ($1);".to_owned(), "2".to_owned(), "2".to_owned()),
+                ("update t set a = $1;".to_owned(), "1".to_owned(), "3".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn calls_and_rows_are_aggregated_per_fingerprint() {
+        let mut statistics = StatementStatistics::default();
+        statistics.record(
+            "insert into schema_name.table_name values (1);",
+            Duration::from_millis(2),
+            &QueryEvent::RecordsInserted(1),
+        );
+        statistics.record(
+            "insert into schema_name.table_name values (2);",
+            Duration::from_millis(4),
+            &QueryEvent::RecordsInserted(1),
+        );
+
+        let (_columns, records) = statistics.projection();
+        assert_eq!(
+            records
+                .into_iter()
+                .map(|record| (record[0].clone(), record[1].clone(), record[4].clone()))
+                .collect::<Vec<(String, String, String)>>(),
+            vec![(
+                "insert into schema_name.table_name values ($1);".to_owned(),
+                "2".to_owned(),
+                "2".to_owned()
+            )]
+        );
+    }
+}
@@ -0,0 +1,434 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::QueryError;
+use kernel::{SystemError, SystemResult};
+use sql_types::SqlType;
+use sqlparser::ast::{Expr, Function, Query, Select, SelectItem, Value};
+use storage::Projection;
+
+// a query is not evaluated any further when either of them happens
+enum Failure {
+    System(SystemError),
+    Query(QueryError),
+}
+
+type Materialized<T> = std::result::Result<T, Failure>;
+
+/// Subqueries are not correlated with the query they are nested in, so each
+/// of them is executed once before the outer query and its result is put
+/// into the outer query as literals: rows of `IN (SELECT ...)` become a list
+/// of values, `EXISTS (SELECT ...)` becomes a boolean and a scalar subquery
+/// becomes the single value it returns or null when there are no rows
+pub(crate) fn materialize<F>(select: Select, run: &mut F) -> SystemResult<Result<Select, QueryError>>
+where
+    F: FnMut(Query) -> SystemResult<Result<Projection, QueryError>>,
+{
+    match materialize_select(select, run) {
+        Ok(select) => Ok(Ok(select)),
+        Err(Failure::Query(error)) => Ok(Err(error)),
+        Err(Failure::System(error)) => Err(error),
+    }
+}
+
+pub(crate) fn has_subqueries(select: &Select) -> bool {
+    select.projection.iter().any(|item| match item {
+        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => contains_subquery(expr),
+        _ => false,
+    }) || select.selection.iter().any(contains_subquery)
+        || select.group_by.iter().any(contains_subquery)
+        || select.having.iter().any(contains_subquery)
+}
+
+fn contains_subquery(expr: &Expr) -> bool {
+    match expr {
+        Expr::Subquery(_) | Expr::InSubquery { .. } | Expr::Exists(_) => true,
+        Expr::IsNull(expr)
+        | Expr::IsNotNull(expr)
+        | Expr::Nested(expr)
+        | Expr::Cast { expr, .. }
+        | Expr::Extract { expr, .. }
+        | Expr::Collate { expr, .. }
+        | Expr::UnaryOp { expr, .. } => contains_subquery(expr),
+        Expr::BinaryOp { left, right, .. } => contains_subquery(left) || contains_subquery(right),
+        Expr::Between { expr, low, high, .. } => {
+            contains_subquery(expr) || contains_subquery(low) || contains_subquery(high)
+        }
+        Expr::InList { expr, list, .. } => contains_subquery(expr) || list.iter().any(contains_subquery),
+        Expr::Function(function) => function.args.iter().any(contains_subquery),
+        Expr::Case {
+            operand,
+            conditions,
+            results,
+            else_result,
+        } => {
+            operand.iter().any(|operand| contains_subquery(operand))
+                || conditions.iter().any(contains_subquery)
+                || results.iter().any(contains_subquery)
+                || else_result.iter().any(|result| contains_subquery(result))
+        }
+        _ => false,
+    }
+}
+
+fn materialize_select<F>(select: Select, run: &mut F) -> Materialized<Select>
+where
+    F: FnMut(Query) -> SystemResult<Result<Projection, QueryError>>,
+{
+    // other parts of the select, e.g. `TOP`, are kept as they are
+    let Select {
+        projection,
+        selection,
+        group_by,
+        having,
+        ..
+    } = select;
+    let mut items = vec![];
+    for item in projection {
+        items.push(match item {
+            SelectItem::UnnamedExpr(expr) => SelectItem::UnnamedExpr(materialize_expr(expr, run)?),
+            SelectItem::ExprWithAlias { expr, alias } => SelectItem::ExprWithAlias {
+                expr: materialize_expr(expr, run)?,
+                alias,
+            },
+            item => item,
+        });
+    }
+    Ok(Select {
+        projection: items,
+        selection: materialize_optional(selection, run)?,
+        group_by: materialize_all(group_by, run)?,
+        having: materialize_optional(having, run)?,
+        ..select
+    })
+}
+
+fn materialize_expr<F>(expr: Expr, run: &mut F) -> Materialized<Expr>
+where
+    F: FnMut(Query) -> SystemResult<Result<Projection, QueryError>>,
+{
+    if !contains_subquery(&expr) {
+        return Ok(expr);
+    }
+    let expr = match expr {
+        Expr::Subquery(query) => scalar(execute(*query, run)?)?,
+        Expr::Exists(query) => Expr::Value(Value::Boolean(!execute(*query, run)?.1.is_empty())),
+        Expr::InSubquery {
+            expr,
+            subquery,
+            negated,
+        } => Expr::InList {
+            expr: materialize_boxed(*expr, run)?,
+            list: list(execute(*subquery, run)?)?,
+            negated,
+        },
+        Expr::IsNull(expr) => Expr::IsNull(materialize_boxed(*expr, run)?),
+        Expr::IsNotNull(expr) => Expr::IsNotNull(materialize_boxed(*expr, run)?),
+        Expr::Nested(expr) => Expr::Nested(materialize_boxed(*expr, run)?),
+        Expr::Cast { expr, data_type } => Expr::Cast {
+            expr: materialize_boxed(*expr, run)?,
+            data_type,
+        },
+        Expr::Extract { field, expr } => Expr::Extract {
+            field,
+            expr: materialize_boxed(*expr, run)?,
+        },
+        Expr::Collate { expr, collation } => Expr::Collate {
+            expr: materialize_boxed(*expr, run)?,
+            collation,
+        },
+        Expr::UnaryOp { op, expr } => Expr::UnaryOp {
+            op,
+            expr: materialize_boxed(*expr, run)?,
+        },
+        Expr::BinaryOp { left, op, right } => Expr::BinaryOp {
+            left: materialize_boxed(*left, run)?,
+            op,
+            right: materialize_boxed(*right, run)?,
+        },
+        Expr::Between {
+            expr,
+            negated,
+            low,
+            high,
+        } => Expr::Between {
+            expr: materialize_boxed(*expr, run)?,
+            negated,
+            low: materialize_boxed(*low, run)?,
+            high: materialize_boxed(*high, run)?,
+        },
+        Expr::InList { expr, list, negated } => Expr::InList {
+            expr: materialize_boxed(*expr, run)?,
+            list: materialize_all(list, run)?,
+            negated,
+        },
+        Expr::Function(Function {
+            name,
+            args,
+            over,
+            distinct,
+        }) => Expr::Function(Function {
+            name,
+            args: materialize_all(args, run)?,
+            over,
+            distinct,
+        }),
+        Expr::Case {
+            operand,
+            conditions,
+            results,
+            else_result,
+        } => Expr::Case {
+            operand: match operand {
+                Some(operand) => Some(materialize_boxed(*operand, run)?),
+                None => None,
+            },
+            conditions: materialize_all(conditions, run)?,
+            results: materialize_all(results, run)?,
+            else_result: match else_result {
+                Some(result) => Some(materialize_boxed(*result, run)?),
+                None => None,
+            },
+        },
+        expr => expr,
+    };
+    Ok(expr)
+}
+
+fn materialize_boxed<F>(expr: Expr, run: &mut F) -> Materialized<Box<Expr>>
+where
+    F: FnMut(Query) -> SystemResult<Result<Projection, QueryError>>,
+{
+    materialize_expr(expr, run).map(Box::new)
+}
+
+fn materialize_optional<F>(expr: Option<Expr>, run: &mut F) -> Materialized<Option<Expr>>
+where
+    F: FnMut(Query) -> SystemResult<Result<Projection, QueryError>>,
+{
+    match expr {
+        Some(expr) => materialize_expr(expr, run).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn materialize_all<F>(exprs: Vec<Expr>, run: &mut F) -> Materialized<Vec<Expr>>
+where
+    F: FnMut(Query) -> SystemResult<Result<Projection, QueryError>>,
+{
+    exprs.into_iter().map(|expr| materialize_expr(expr, run)).collect()
+}
+
+fn execute<F>(query: Query, run: &mut F) -> Materialized<Projection>
+where
+    F: FnMut(Query) -> SystemResult<Result<Projection, QueryError>>,
+{
+    match run(query) {
+        Ok(Ok(projection)) => Ok(projection),
+        Ok(Err(error)) => Err(Failure::Query(error)),
+        Err(error) => Err(Failure::System(error)),
+    }
+}
+
+fn scalar((columns, mut rows): Projection) -> Materialized<Expr> {
+    if columns.len() != 1 {
+        return Err(Failure::Query(QueryError::subquery_must_return_one_column()));
+    }
+    if rows.len() > 1 {
+        return Err(Failure::Query(QueryError::more_than_one_row_returned_by_subquery()));
+    }
+    Ok(match rows.pop().and_then(|mut row| row.pop()) {
+        Some(value) => literal(value, columns[0].1),
+        None => Expr::Value(Value::Null),
+    })
+}
+
+fn list((columns, rows): Projection) -> Materialized<Vec<Expr>> {
+    if columns.len() != 1 {
+        return Err(Failure::Query(QueryError::subquery_has_too_many_columns()));
+    }
+    let sql_type = columns[0].1;
+    Ok(rows
+        .into_iter()
+        .filter_map(|mut row| row.pop())
+        .map(|value| literal(value, sql_type))
+        .collect())
+}
+
+// values of other types are given as untyped strings the same way a client
+// would write them in a query, so they are read in the type of the context
+fn literal(value: String, sql_type: SqlType) -> Expr {
+    match sql_type {
+        SqlType::SmallInt
+        | SqlType::Integer
+        | SqlType::BigInt
+        | SqlType::Real
+        | SqlType::DoublePrecision
+        | SqlType::Decimal => match value.parse::<f64>() {
+            Ok(_) => Expr::Value(Value::Number(value)),
+            Err(_) => Expr::Value(Value::Null),
+        },
+        SqlType::Bool => match value.as_str() {
+            "t" | "true" => Expr::Value(Value::Boolean(true)),
+            "f" | "false" => Expr::Value(Value::Boolean(false)),
+            _ => Expr::Value(Value::Null),
+        },
+        _ => Expr::Value(Value::SingleQuotedString(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::ast::{BinaryOperator, Ident, SetExpr};
+
+    fn query() -> Query {
+        Query {
+            ctes: vec![],
+            body: SetExpr::Select(Box::new(select(None))),
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            fetch: None,
+        }
+    }
+
+    fn select(selection: Option<Expr>) -> Select {
+        Select {
+            distinct: false,
+            top: None,
+            projection: vec![SelectItem::Wildcard],
+            from: vec![],
+            selection,
+            group_by: vec![],
+            having: None,
+        }
+    }
+
+    fn column(name: &str) -> Expr {
+        Expr::Identifier(Ident::new(name))
+    }
+
+    fn number(value: &str) -> Expr {
+        Expr::Value(Value::Number(value.to_owned()))
+    }
+
+    fn rows(sql_type: SqlType, values: Vec<&str>) -> Projection {
+        (
+            vec![("x".to_owned(), sql_type)],
+            values.into_iter().map(|value| vec![value.to_owned()]).collect(),
+        )
+    }
+
+    fn run_with(projection: Projection) -> impl FnMut(Query) -> SystemResult<Result<Projection, QueryError>> {
+        move |_query| Ok(Ok(projection.clone()))
+    }
+
+    #[test]
+    fn select_without_subqueries() {
+        let predicate = Expr::BinaryOp {
+            left: Box::new(column("id")),
+            op: BinaryOperator::Eq,
+            right: Box::new(number("1")),
+        };
+        assert!(!has_subqueries(&select(Some(predicate))));
+    }
+
+    #[test]
+    fn in_subquery_becomes_list_of_values() {
+        let predicate = Expr::InSubquery {
+            expr: Box::new(column("id")),
+            subquery: Box::new(query()),
+            negated: false,
+        };
+        assert!(has_subqueries(&select(Some(predicate.clone()))));
+        assert_eq!(
+            materialize(
+                select(Some(predicate)),
+                &mut run_with(rows(SqlType::Integer, vec!["1", "2"]))
+            )
+            .expect("no system errors"),
+            Ok(select(Some(Expr::InList {
+                expr: Box::new(column("id")),
+                list: vec![number("1"), number("2")],
+                negated: false,
+            })))
+        );
+    }
+
+    #[test]
+    fn exists_becomes_boolean() {
+        let predicate = Expr::UnaryOp {
+            op: sqlparser::ast::UnaryOperator::Not,
+            expr: Box::new(Expr::Exists(Box::new(query()))),
+        };
+        assert_eq!(
+            materialize(select(Some(predicate)), &mut run_with(rows(SqlType::Integer, vec![])))
+                .expect("no system errors"),
+            Ok(select(Some(Expr::UnaryOp {
+                op: sqlparser::ast::UnaryOperator::Not,
+                expr: Box::new(Expr::Value(Value::Boolean(false))),
+            })))
+        );
+    }
+
+    #[test]
+    fn scalar_subquery_becomes_literal() {
+        let subquery = Some(Expr::Subquery(Box::new(query())));
+        assert_eq!(
+            materialize(
+                select(subquery.clone()),
+                &mut run_with(rows(SqlType::VarChar(10), vec!["a"]))
+            )
+            .expect("no system errors"),
+            Ok(select(Some(Expr::Value(Value::SingleQuotedString("a".to_owned())))))
+        );
+        assert_eq!(
+            materialize(select(subquery.clone()), &mut run_with(rows(SqlType::Bool, vec!["t"])))
+                .expect("no system errors"),
+            Ok(select(Some(Expr::Value(Value::Boolean(true)))))
+        );
+        assert_eq!(
+            materialize(select(subquery), &mut run_with(rows(SqlType::Integer, vec![]))).expect("no system errors"),
+            Ok(select(Some(Expr::Value(Value::Null))))
+        );
+    }
+
+    #[test]
+    fn scalar_subquery_with_many_rows() {
+        let subquery = Some(Expr::Subquery(Box::new(query())));
+        assert_eq!(
+            materialize(select(subquery), &mut run_with(rows(SqlType::Integer, vec!["1", "2"])))
+                .expect("no system errors"),
+            Err(QueryError::more_than_one_row_returned_by_subquery())
+        );
+    }
+
+    #[test]
+    fn in_subquery_with_many_columns() {
+        let predicate = Expr::InSubquery {
+            expr: Box::new(column("id")),
+            subquery: Box::new(query()),
+            negated: true,
+        };
+        let projection = (
+            vec![("a".to_owned(), SqlType::Integer), ("b".to_owned(), SqlType::Integer)],
+            vec![],
+        );
+        assert_eq!(
+            materialize(select(Some(predicate)), &mut run_with(projection)).expect("no system errors"),
+            Err(QueryError::subquery_has_too_many_columns())
+        );
+    }
+}
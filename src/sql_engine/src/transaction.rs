@@ -0,0 +1,50 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// State of a transaction block of a session
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionState {
+    Idle,
+    InProgress,
+    // statements are rejected until the block is ended
+    Failed,
+}
+
+/// Changes of a transaction block are written to storage as statements are
+/// executed and reverted from the undo log if the block is rolled back. Block
+/// that changed storage holds it until the block is ended, so other sessions
+/// neither see its uncommitted rows nor change them
+#[derive(Debug, Default)]
+pub(crate) struct Transaction {
+    holds_storage: bool,
+    failed: bool,
+}
+
+impl Transaction {
+    pub(crate) fn hold_storage(&mut self) {
+        self.holds_storage = true;
+    }
+
+    pub(crate) fn holds_storage(&self) -> bool {
+        self.holds_storage
+    }
+
+    pub(crate) fn fail(&mut self) {
+        self.failed = true;
+    }
+
+    pub(crate) fn is_failed(&self) -> bool {
+        self.failed
+    }
+}
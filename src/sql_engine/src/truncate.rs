@@ -0,0 +1,110 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::QueryError;
+
+/// names of tables in `TRUNCATE [TABLE] name [, ...]`, returns `None` if the
+/// query is not `TRUNCATE`. sql parser does not know the command
+pub(crate) fn parse(raw_sql_query: &str) -> Option<Result<Vec<String>, QueryError>> {
+    let statement = raw_sql_query.trim().trim_end_matches(';').trim_end();
+    let rest = keyword(statement, "truncate")?;
+    let rest = keyword(rest, "table").unwrap_or(rest);
+    Some(rest.split(',').map(table_name).collect())
+}
+
+fn table_name(name: &str) -> Result<String, QueryError> {
+    let name = name.trim();
+    let end = name.find(char::is_whitespace).unwrap_or(name.len());
+    let (name, rest) = name.split_at(end);
+    if name.is_empty() {
+        return Err(unexpected("table name", rest.trim()));
+    }
+    let rest = rest.trim_start();
+    if !rest.is_empty() {
+        return Err(unexpected(
+            "end of statement",
+            rest.split_whitespace().next().unwrap_or(rest),
+        ));
+    }
+    // quoted identifiers keep their case
+    Ok(name
+        .split('.')
+        .map(|part| part.trim_matches('"'))
+        .collect::<Vec<&str>>()
+        .join("."))
+}
+
+// keyword has to be followed by a whitespace
+fn keyword<'q>(statement: &'q str, keyword: &str) -> Option<&'q str> {
+    let prefix = statement.get(..keyword.len())?;
+    let rest = &statement[keyword.len()..];
+    if prefix.eq_ignore_ascii_case(keyword) && (rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+fn unexpected(expected: &str, found: &str) -> QueryError {
+    let found = if found.is_empty() { "EOF" } else { found };
+    QueryError::syntax_error(format!("Expected {}, found: {}", expected, found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_truncate() {
+        assert_eq!(parse("delete from schema_name.table_name;"), None);
+        assert_eq!(parse("truncated"), None);
+    }
+
+    #[test]
+    fn truncate_table() {
+        assert_eq!(
+            parse("truncate table schema_name.table_name;"),
+            Some(Ok(vec!["schema_name.table_name".to_owned()]))
+        );
+        assert_eq!(parse("TRUNCATE table_name"), Some(Ok(vec!["table_name".to_owned()])));
+    }
+
+    #[test]
+    fn truncate_multiple_tables() {
+        assert_eq!(
+            parse("truncate table_1, \"Table_2\""),
+            Some(Ok(vec!["table_1".to_owned(), "Table_2".to_owned()]))
+        );
+    }
+
+    #[test]
+    fn without_table_name() {
+        assert_eq!(
+            parse("truncate table;"),
+            Some(Err(QueryError::syntax_error(
+                "Expected table name, found: EOF".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn unsupported_options() {
+        assert_eq!(
+            parse("truncate table_name restart identity"),
+            Some(Err(QueryError::syntax_error(
+                "Expected end of statement, found: restart".to_owned()
+            )))
+        );
+    }
+}
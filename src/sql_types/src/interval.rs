@@ -0,0 +1,507 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{Constraint, ConstraintError, Serializer};
+use std::{
+    convert::TryInto,
+    fmt::{self, Display, Formatter},
+};
+
+const MICROSECONDS_PER_SECOND: i64 = 1_000_000;
+const MICROSECONDS_PER_DAY: i64 = 86_400 * MICROSECONDS_PER_SECOND;
+const DAYS_PER_MONTH: i64 = 30;
+const MONTHS_PER_YEAR: i64 = 12;
+
+/// Span of time that is kept the same way PostgreSQL keeps it: months and days
+/// have varying length, so they are not converted into smaller units until
+/// they are added to a timestamp or explicitly justified
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub microseconds: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, microseconds: i64) -> Interval {
+        Interval {
+            months,
+            days,
+            microseconds,
+        }
+    }
+
+    /// accepts verbose format with units, e.g. `1 year 2 mons -3 days 04:05:06`
+    /// or `@ 1 hour 30 minutes ago`, number without unit is a number of seconds
+    pub fn parse(value: &str) -> Option<Interval> {
+        let mut words = value.split_whitespace().map(str::to_lowercase).peekable();
+        if words.peek().map(String::as_str) == Some("@") {
+            words.next();
+        }
+        let mut words = words.collect::<Vec<_>>();
+        let ago = words.last().map(String::as_str) == Some("ago");
+        if ago {
+            words.pop();
+        }
+        if words.is_empty() {
+            return None;
+        }
+        // fractions of months and days are kept in smaller units
+        let (mut months, mut days, mut microseconds) = (0f64, 0f64, 0f64);
+        let mut index = 0;
+        while index < words.len() {
+            let word = &words[index];
+            index += 1;
+            if word.contains(':') {
+                microseconds += time(word)?;
+                continue;
+            }
+            let (number, unit) = match word.find(|c: char| c.is_ascii_alphabetic()) {
+                Some(position) => (&word[..position], word[position..].to_owned()),
+                None if index < words.len() && words[index].starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    index += 1;
+                    (word.as_str(), words[index - 1].clone())
+                }
+                None => (word.as_str(), "seconds".to_owned()),
+            };
+            let number = number.parse::<f64>().ok().filter(|number| number.is_finite())?;
+            match unit.as_str() {
+                "millennium" | "millennia" | "millenniums" | "mil" | "mils" => months += number * 12_000.0,
+                "century" | "centuries" | "c" => months += number * 1_200.0,
+                "decade" | "decades" | "dec" | "decs" => months += number * 120.0,
+                "year" | "years" | "y" | "yr" | "yrs" => months += number * 12.0,
+                "month" | "months" | "mon" | "mons" => months += number,
+                "week" | "weeks" | "w" => days += number * 7.0,
+                "day" | "days" | "d" => days += number,
+                "hour" | "hours" | "h" | "hr" | "hrs" => microseconds += number * 3_600_000_000.0,
+                "minute" | "minutes" | "m" | "min" | "mins" => microseconds += number * 60_000_000.0,
+                "second" | "seconds" | "s" | "sec" | "secs" => microseconds += number * 1_000_000.0,
+                "millisecond" | "milliseconds" | "ms" | "msec" | "msecs" => microseconds += number * 1_000.0,
+                "microsecond" | "microseconds" | "us" | "usec" | "usecs" => microseconds += number,
+                _ => return None,
+            }
+        }
+        let interval = Interval::from_fractions(months, days, microseconds)?;
+        if ago {
+            interval.checked_neg()
+        } else {
+            Some(interval)
+        }
+    }
+
+    /// moves whole days out of time part, e.g. `27 hours` becomes `1 day 03:00:00`
+    pub fn justify_hours(self) -> Interval {
+        let mut days = self.days as i64 + self.microseconds / MICROSECONDS_PER_DAY;
+        let mut microseconds = self.microseconds % MICROSECONDS_PER_DAY;
+        if days > 0 && microseconds < 0 {
+            microseconds += MICROSECONDS_PER_DAY;
+            days -= 1;
+        } else if days < 0 && microseconds > 0 {
+            microseconds -= MICROSECONDS_PER_DAY;
+            days += 1;
+        }
+        Interval::new(self.months, days as i32, microseconds)
+    }
+
+    /// moves 30 day periods out of days, e.g. `35 days` becomes `1 mon 5 days`
+    pub fn justify_days(self) -> Interval {
+        let mut months = self.months as i64 + self.days as i64 / DAYS_PER_MONTH;
+        let mut days = self.days as i64 % DAYS_PER_MONTH;
+        if months > 0 && days < 0 {
+            days += DAYS_PER_MONTH;
+            months -= 1;
+        } else if months < 0 && days > 0 {
+            days -= DAYS_PER_MONTH;
+            months += 1;
+        }
+        Interval::new(months as i32, days as i32, self.microseconds)
+    }
+
+    /// justifies both days and hours so that all parts have the same sign
+    pub fn justify_interval(self) -> Interval {
+        let days = self.days as i64 + self.microseconds / MICROSECONDS_PER_DAY;
+        let mut microseconds = self.microseconds % MICROSECONDS_PER_DAY;
+        let mut months = self.months as i64 + days / DAYS_PER_MONTH;
+        let mut days = days % DAYS_PER_MONTH;
+        if months > 0 && (days < 0 || (days == 0 && microseconds < 0)) {
+            days += DAYS_PER_MONTH;
+            months -= 1;
+        } else if months < 0 && (days > 0 || (days == 0 && microseconds > 0)) {
+            days -= DAYS_PER_MONTH;
+            months += 1;
+        }
+        if days > 0 && microseconds < 0 {
+            microseconds += MICROSECONDS_PER_DAY;
+            days -= 1;
+        } else if days < 0 && microseconds > 0 {
+            microseconds -= MICROSECONDS_PER_DAY;
+            days += 1;
+        }
+        Interval::new(months as i32, days as i32, microseconds)
+    }
+
+    pub fn checked_add(self, other: Interval) -> Option<Interval> {
+        Some(Interval::new(
+            self.months.checked_add(other.months)?,
+            self.days.checked_add(other.days)?,
+            self.microseconds.checked_add(other.microseconds)?,
+        ))
+    }
+
+    pub fn checked_sub(self, other: Interval) -> Option<Interval> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    pub fn checked_neg(self) -> Option<Interval> {
+        Some(Interval::new(
+            self.months.checked_neg()?,
+            self.days.checked_neg()?,
+            self.microseconds.checked_neg()?,
+        ))
+    }
+
+    /// fractions of months and days that the result has are moved into
+    /// smaller units, e.g. `1 mon` multiplied by `1.5` is `1 mon 15 days`
+    pub fn checked_mul(self, factor: f64) -> Option<Interval> {
+        Interval::from_fractions(
+            self.months as f64 * factor,
+            self.days as f64 * factor,
+            self.microseconds as f64 * factor,
+        )
+    }
+
+    pub fn checked_div(self, divisor: f64) -> Option<Interval> {
+        if divisor == 0.0 {
+            return None;
+        }
+        Interval::from_fractions(
+            self.months as f64 / divisor,
+            self.days as f64 / divisor,
+            self.microseconds as f64 / divisor,
+        )
+    }
+
+    fn from_fractions(months: f64, days: f64, microseconds: f64) -> Option<Interval> {
+        let whole_months = months.trunc();
+        let days = days + round_microseconds((months - whole_months) * DAYS_PER_MONTH as f64);
+        let whole_days = days.trunc();
+        let microseconds = microseconds + ((days - whole_days) * MICROSECONDS_PER_DAY as f64).round();
+        let in_range = |value: f64, limit: f64| value.is_finite() && value.abs() <= limit;
+        if !in_range(whole_months, i32::MAX as f64)
+            || !in_range(whole_days, i32::MAX as f64)
+            || !in_range(microseconds, i64::MAX as f64)
+        {
+            return None;
+        }
+        Some(Interval::new(
+            whole_months as i32,
+            whole_days as i32,
+            microseconds.round() as i64,
+        ))
+    }
+}
+
+// fractions of days are kept with microseconds precision
+fn round_microseconds(days: f64) -> f64 {
+    (days * MICROSECONDS_PER_DAY as f64).round() / MICROSECONDS_PER_DAY as f64
+}
+
+// `[-]hours:minutes[:seconds]`, hours are not limited to a day
+fn time(word: &str) -> Option<f64> {
+    let (negative, word) = match word.chars().next() {
+        Some('-') => (true, &word[1..]),
+        Some('+') => (false, &word[1..]),
+        _ => (false, word),
+    };
+    let parts = word.split(':').collect::<Vec<_>>();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [hours, minutes] => (*hours, *minutes, "0"),
+        [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+        _ => return None,
+    };
+    if !hours.chars().all(|c| c.is_ascii_digit()) || !minutes.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours = hours.parse::<f64>().ok()?;
+    let minutes = minutes.parse::<f64>().ok().filter(|minutes| *minutes < 60.0)?;
+    let seconds = seconds
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| *seconds >= 0.0 && *seconds < 60.0)?;
+    let microseconds = (hours * 3_600.0 + minutes * 60.0 + seconds) * MICROSECONDS_PER_SECOND as f64;
+    Some(if negative { -microseconds } else { microseconds })
+}
+
+/// Formats intervals in `postgres` style, e.g. `1 year 2 mons -3 days +04:05:06`,
+/// parts that are zero are omitted
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let months = self.months as i64;
+        let mut parts = vec![];
+        // sign of a part is shown when it differs from the sign of the part before
+        let mut is_before_negative = false;
+        for (value, unit, plural) in &[
+            (months / MONTHS_PER_YEAR, "year", "years"),
+            (months % MONTHS_PER_YEAR, "mon", "mons"),
+            (self.days as i64, "day", "days"),
+        ] {
+            if *value != 0 {
+                let sign = if is_before_negative && *value > 0 { "+" } else { "" };
+                let unit = if *value == 1 { unit } else { plural };
+                parts.push(format!("{}{} {}", sign, value, unit));
+                is_before_negative = *value < 0;
+            }
+        }
+        if self.microseconds != 0 || parts.is_empty() {
+            let sign = if self.microseconds < 0 {
+                "-"
+            } else if is_before_negative {
+                "+"
+            } else {
+                ""
+            };
+            let total = (self.microseconds as i128).abs();
+            let seconds = total / MICROSECONDS_PER_SECOND as i128;
+            let fraction = total % MICROSECONDS_PER_SECOND as i128;
+            let mut time = format!(
+                "{}{:02}:{:02}:{:02}",
+                sign,
+                seconds / 3_600,
+                seconds / 60 % 60,
+                seconds % 60
+            );
+            if fraction != 0 {
+                time.push_str(format!(".{:06}", fraction).trim_end_matches('0'));
+            }
+            parts.push(time);
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+pub(crate) struct IntervalTypeConstraint;
+
+impl Constraint for IntervalTypeConstraint {
+    fn validate(&self, in_value: &str) -> Result<(), ConstraintError> {
+        match Interval::parse(in_value) {
+            Some(_) => Ok(()),
+            None => Err(ConstraintError::NotAnInterval),
+        }
+    }
+}
+
+/// intervals are kept in 16 bytes, months and days take four bytes each and
+/// time part takes the rest
+pub(crate) struct IntervalTypeSerializer;
+
+impl Serializer for IntervalTypeSerializer {
+    fn ser(&self, in_value: &str) -> Vec<u8> {
+        match Interval::parse(in_value) {
+            Some(interval) => {
+                let mut bytes = interval.months.to_be_bytes().to_vec();
+                bytes.extend_from_slice(&interval.days.to_be_bytes());
+                bytes.extend_from_slice(&interval.microseconds.to_be_bytes());
+                bytes
+            }
+            None => unimplemented!(),
+        }
+    }
+
+    fn des(&self, out_value: &[u8]) -> String {
+        Interval::new(
+            i32::from_be_bytes(out_value[0..4].try_into().unwrap()),
+            i32::from_be_bytes(out_value[4..8].try_into().unwrap()),
+            i64::from_be_bytes(out_value[8..16].try_into().unwrap()),
+        )
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(test)]
+    mod parsing {
+        use super::*;
+
+        #[test]
+        fn units() {
+            assert_eq!(
+                Interval::parse("1 year 2 mons 3 days 04:05:06.5"),
+                Some(Interval::new(14, 3, 14_706_500_000))
+            );
+            assert_eq!(
+                Interval::parse("2 weeks 3 hours 30 minutes"),
+                Some(Interval::new(0, 14, 12_600_000_000))
+            );
+            assert_eq!(Interval::parse("1decade 1d"), Some(Interval::new(120, 1, 0)));
+            assert_eq!(Interval::parse("90"), Some(Interval::new(0, 0, 90_000_000)));
+        }
+
+        #[test]
+        fn fractions_are_moved_into_smaller_units() {
+            assert_eq!(Interval::parse("1.5 years"), Some(Interval::new(18, 0, 0)));
+            assert_eq!(Interval::parse("1.5 months"), Some(Interval::new(1, 15, 0)));
+            assert_eq!(
+                Interval::parse("1.5 days"),
+                Some(Interval::new(0, 1, 12 * 3_600_000_000))
+            );
+        }
+
+        #[test]
+        fn negative() {
+            assert_eq!(
+                Interval::parse("-1 days +02:00:00"),
+                Some(Interval::new(0, -1, 7_200_000_000))
+            );
+            assert_eq!(
+                Interval::parse("@ 1 day 1 hour ago"),
+                Some(Interval::new(0, -1, -3_600_000_000))
+            );
+        }
+
+        #[test]
+        fn invalid() {
+            assert_eq!(Interval::parse(""), None);
+            assert_eq!(Interval::parse("1 fortnight"), None);
+            assert_eq!(Interval::parse("01:61"), None);
+            assert_eq!(Interval::parse("one day"), None);
+        }
+    }
+
+    #[cfg(test)]
+    mod formatting {
+        use super::*;
+
+        #[test]
+        fn all_parts() {
+            assert_eq!(
+                Interval::new(14, 3, 14_706_500_000).to_string(),
+                "1 year 2 mons 3 days 04:05:06.5"
+            );
+        }
+
+        #[test]
+        fn zero() {
+            assert_eq!(Interval::default().to_string(), "00:00:00");
+        }
+
+        #[test]
+        fn hours_are_not_limited_to_a_day() {
+            assert_eq!(Interval::new(0, 0, 36 * 3_600_000_000).to_string(), "36:00:00");
+        }
+
+        #[test]
+        fn signs_of_parts() {
+            assert_eq!(Interval::new(0, -1, 7_200_000_000).to_string(), "-1 days +02:00:00");
+            assert_eq!(
+                Interval::new(-13, 1, -1).to_string(),
+                "-1 years -1 mons +1 day -00:00:00.000001"
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod justification {
+        use super::*;
+
+        #[test]
+        fn hours() {
+            assert_eq!(
+                Interval::new(0, 0, 27 * 3_600_000_000).justify_hours(),
+                Interval::new(0, 1, 3 * 3_600_000_000)
+            );
+            assert_eq!(
+                Interval::new(0, 1, -3_600_000_000).justify_hours(),
+                Interval::new(0, 0, 23 * 3_600_000_000)
+            );
+        }
+
+        #[test]
+        fn days() {
+            assert_eq!(Interval::new(0, 35, 0).justify_days(), Interval::new(1, 5, 0));
+            assert_eq!(Interval::new(1, -5, 0).justify_days(), Interval::new(0, 25, 0));
+        }
+
+        #[test]
+        fn interval() {
+            assert_eq!(
+                Interval::new(1, 0, -3_600_000_000).justify_interval(),
+                Interval::new(0, 29, 23 * 3_600_000_000)
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod arithmetic {
+        use super::*;
+
+        #[test]
+        fn add_and_subtract() {
+            let interval = Interval::new(1, 2, 3);
+
+            assert_eq!(interval.checked_add(interval), Some(Interval::new(2, 4, 6)));
+            assert_eq!(interval.checked_sub(interval), Some(Interval::default()));
+        }
+
+        #[test]
+        fn multiply() {
+            assert_eq!(Interval::new(1, 0, 0).checked_mul(1.5), Some(Interval::new(1, 15, 0)));
+            assert_eq!(
+                Interval::new(0, 1, 0).checked_mul(0.5),
+                Some(Interval::new(0, 0, 12 * 3_600_000_000))
+            );
+        }
+
+        #[test]
+        fn divide() {
+            assert_eq!(Interval::new(1, 0, 0).checked_div(2.0), Some(Interval::new(0, 15, 0)));
+            assert_eq!(Interval::new(1, 0, 0).checked_div(0.0), None);
+        }
+
+        #[test]
+        fn out_of_range() {
+            assert_eq!(Interval::new(i32::MAX, 0, 0).checked_mul(2.0), None);
+            assert_eq!(Interval::new(i32::MIN, 0, 0).checked_neg(), None);
+        }
+    }
+
+    #[cfg(test)]
+    mod serialization {
+        use super::*;
+
+        #[test]
+        fn kept_in_sixteen_bytes() {
+            let serializer = IntervalTypeSerializer;
+
+            assert_eq!(
+                serializer.ser("1 mon 2 days 00:00:01"),
+                vec![0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 15, 66, 64]
+            );
+            assert_eq!(
+                serializer.des(&serializer.ser("-1 days +02:00:00")),
+                "-1 days +02:00:00".to_owned()
+            );
+        }
+
+        #[test]
+        fn not_an_interval() {
+            assert_eq!(
+                IntervalTypeConstraint.validate("tomorrow"),
+                Err(ConstraintError::NotAnInterval)
+            );
+        }
+    }
+}
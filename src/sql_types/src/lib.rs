@@ -12,9 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::interval::{IntervalTypeConstraint, IntervalTypeSerializer};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Timelike};
 use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
 
+pub use crate::interval::Interval;
+
+mod interval;
+
 #[derive(PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum SqlType {
     Bool,
@@ -42,6 +48,9 @@ impl SqlType {
             SqlType::SmallInt => Box::new(SmallIntTypeConstraint),
             SqlType::Integer => Box::new(IntegerSqlTypeConstraint),
             SqlType::BigInt => Box::new(BigIntTypeConstraint),
+            SqlType::Timestamp => Box::new(TimestampTypeConstraint),
+            SqlType::TimestampWithTimeZone => Box::new(TimestampWithTimeZoneTypeConstraint),
+            SqlType::Interval => Box::new(IntervalTypeConstraint),
             sql_type => unimplemented!("Type constraint for {:?} is not currently implemented", sql_type),
         }
     }
@@ -53,6 +62,9 @@ impl SqlType {
             SqlType::SmallInt => Box::new(SmallIntTypeSerializer),
             SqlType::Integer => Box::new(IntegerSqlTypeSerializer),
             SqlType::BigInt => Box::new(BigIntTypeSerializer),
+            SqlType::Timestamp => Box::new(TimestampTypeSerializer),
+            SqlType::TimestampWithTimeZone => Box::new(TimestampWithTimeZoneTypeSerializer),
+            SqlType::Interval => Box::new(IntervalTypeSerializer),
             sql_type => unimplemented!("Type Serializer for {:?} is not currently implemented", sql_type),
         }
     }
@@ -67,6 +79,8 @@ pub enum ConstraintError {
     OutOfRange,
     NotAnInt,
     ValueTooLong,
+    NotATimestamp,
+    NotAnInterval,
 }
 
 pub trait Serializer {
@@ -213,6 +227,113 @@ impl Serializer for VarCharSqlTypeSerializer {
     }
 }
 
+/// Timestamps are exchanged with storage in ISO 8601 format with
+/// microseconds precision, e.g. `2020-01-02 03:04:05.678`, values with time
+/// zone have an offset from UTC at the end, e.g. `2020-01-02 03:04:05+00`
+pub fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").ok()
+}
+
+/// returns UTC time of a timestamp that has an offset from UTC in hours,
+/// e.g. `+03`, `-05:30` or `+0530`
+pub fn parse_timestamp_with_time_zone(value: &str) -> Option<NaiveDateTime> {
+    // dashes of date part are not signs of an offset
+    let sign = 10 + value.get(10..)?.rfind(&['+', '-'][..])?;
+    let (timestamp, offset) = value.split_at(sign);
+    let digits = offset[1..].replace(':', "");
+    if digits.len() % 2 != 0 || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let seconds = digits
+        .as_bytes()
+        .chunks(2)
+        .zip(&[3600, 60, 1])
+        .map(|(part, unit)| ((part[0] - b'0') as i64 * 10 + (part[1] - b'0') as i64) * unit)
+        .sum::<i64>();
+    let seconds = if offset.starts_with('-') { -seconds } else { seconds };
+    parse_timestamp(timestamp.trim_end())?.checked_sub_signed(Duration::seconds(seconds))
+}
+
+/// fractional part of seconds is omitted when it is zero
+pub fn format_timestamp(timestamp: &NaiveDateTime) -> String {
+    let formatted = timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
+    match timestamp.nanosecond() / 1_000 {
+        0 => formatted,
+        microseconds => format!("{}.{}", formatted, format!("{:06}", microseconds).trim_end_matches('0')),
+    }
+}
+
+// timestamps are kept as number of microseconds since Unix epoch
+fn epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1970, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+fn microseconds(timestamp: &NaiveDateTime) -> Option<i64> {
+    timestamp.signed_duration_since(epoch()).num_microseconds()
+}
+
+fn timestamp(microseconds: i64) -> NaiveDateTime {
+    epoch() + Duration::microseconds(microseconds)
+}
+
+struct TimestampTypeConstraint;
+
+impl Constraint for TimestampTypeConstraint {
+    fn validate(&self, in_value: &str) -> Result<(), ConstraintError> {
+        match parse_timestamp(in_value) {
+            Some(timestamp) if microseconds(&timestamp).is_some() => Ok(()),
+            Some(_) => Err(ConstraintError::OutOfRange),
+            None => Err(ConstraintError::NotATimestamp),
+        }
+    }
+}
+
+struct TimestampTypeSerializer;
+
+impl Serializer for TimestampTypeSerializer {
+    fn ser(&self, in_value: &str) -> Vec<u8> {
+        match parse_timestamp(in_value).as_ref().and_then(microseconds) {
+            Some(microseconds) => microseconds.to_be_bytes().to_vec(),
+            None => unimplemented!(),
+        }
+    }
+
+    fn des(&self, out_value: &[u8]) -> String {
+        format_timestamp(&timestamp(i64::from_be_bytes(out_value[0..8].try_into().unwrap())))
+    }
+}
+
+struct TimestampWithTimeZoneTypeConstraint;
+
+impl Constraint for TimestampWithTimeZoneTypeConstraint {
+    fn validate(&self, in_value: &str) -> Result<(), ConstraintError> {
+        match parse_timestamp_with_time_zone(in_value) {
+            Some(timestamp) if microseconds(&timestamp).is_some() => Ok(()),
+            Some(_) => Err(ConstraintError::OutOfRange),
+            None => Err(ConstraintError::NotATimestamp),
+        }
+    }
+}
+
+struct TimestampWithTimeZoneTypeSerializer;
+
+impl Serializer for TimestampWithTimeZoneTypeSerializer {
+    fn ser(&self, in_value: &str) -> Vec<u8> {
+        match parse_timestamp_with_time_zone(in_value).as_ref().and_then(microseconds) {
+            Some(microseconds) => microseconds.to_be_bytes().to_vec(),
+            None => unimplemented!(),
+        }
+    }
+
+    fn des(&self, out_value: &[u8]) -> String {
+        let timestamp = timestamp(i64::from_be_bytes(out_value[0..8].try_into().unwrap()));
+        format!("{}+00", format_timestamp(&timestamp))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -514,4 +635,98 @@ mod tests {
             }
         }
     }
+
+    #[cfg(test)]
+    mod timestamps {
+        use super::*;
+
+        #[cfg(test)]
+        mod without_time_zone {
+            use super::*;
+
+            #[rstest::fixture]
+            fn serializer() -> Box<dyn Serializer> {
+                SqlType::Timestamp.serializer()
+            }
+
+            #[rstest::fixture]
+            fn constraint() -> Box<dyn Constraint> {
+                SqlType::Timestamp.constraint()
+            }
+
+            #[rstest::rstest]
+            fn serialize(serializer: Box<dyn Serializer>) {
+                assert_eq!(
+                    serializer.ser("1970-01-01 00:00:01.5"),
+                    vec![0, 0, 0, 0, 0, 22, 227, 96]
+                )
+            }
+
+            #[rstest::rstest]
+            fn deserialize(serializer: Box<dyn Serializer>) {
+                assert_eq!(
+                    serializer.des(&[0, 0, 0, 0, 0, 22, 227, 96]),
+                    "1970-01-01 00:00:01.5".to_owned()
+                )
+            }
+
+            #[rstest::rstest]
+            fn before_epoch(serializer: Box<dyn Serializer>) {
+                assert_eq!(
+                    serializer.des(&serializer.ser("1969-12-31 23:59:59")),
+                    "1969-12-31 23:59:59".to_owned()
+                )
+            }
+
+            #[rstest::rstest]
+            fn valid(constraint: Box<dyn Constraint>) {
+                assert_eq!(constraint.validate("2020-01-02 03:04:05.678901"), Ok(()))
+            }
+
+            #[rstest::rstest]
+            fn not_a_timestamp(constraint: Box<dyn Constraint>) {
+                assert_eq!(constraint.validate("01/02/2020"), Err(ConstraintError::NotATimestamp))
+            }
+        }
+
+        #[cfg(test)]
+        mod with_time_zone {
+            use super::*;
+
+            #[rstest::fixture]
+            fn serializer() -> Box<dyn Serializer> {
+                SqlType::TimestampWithTimeZone.serializer()
+            }
+
+            #[rstest::fixture]
+            fn constraint() -> Box<dyn Constraint> {
+                SqlType::TimestampWithTimeZone.constraint()
+            }
+
+            #[rstest::rstest]
+            fn kept_in_utc(serializer: Box<dyn Serializer>) {
+                assert_eq!(
+                    serializer.des(&serializer.ser("2020-01-02 03:04:05+03")),
+                    "2020-01-02 00:04:05+00".to_owned()
+                );
+                assert_eq!(
+                    serializer.des(&serializer.ser("2020-01-02 03:04:05-05:30")),
+                    "2020-01-02 08:34:05+00".to_owned()
+                );
+            }
+
+            #[rstest::rstest]
+            fn valid(constraint: Box<dyn Constraint>) {
+                assert_eq!(constraint.validate("2020-01-02 03:04:05.6+0530"), Ok(()))
+            }
+
+            #[rstest::rstest]
+            fn without_offset(constraint: Box<dyn Constraint>) {
+                assert_eq!(
+                    constraint.validate("2020-01-02 03:04:05"),
+                    Err(ConstraintError::NotATimestamp)
+                )
+            }
+        }
+    }
 }
@@ -13,63 +13,285 @@
 // limitations under the License.
 
 use kernel::{SystemError, SystemResult};
-use std::{collections::HashMap, fmt::Debug};
+use serde::{Deserialize, Serialize};
+use sled::{transaction::TransactionError, Transactional};
+use std::{collections::HashSet, fmt::Debug, ops::Bound, path::Path};
 
 pub type Result<T, E> = std::result::Result<T, E>;
 pub type Row = (Key, Values);
 pub type Key = Vec<u8>;
 pub type Values = Vec<u8>;
-pub type ReadCursor = Box<dyn Iterator<Item = Result<Row, SystemError>>>;
+/// cursors own the rows they iterate over, so they can be consumed on another thread
+pub type ReadCursor = Box<dyn Iterator<Item = Result<Row, SystemError>> + Send>;
+pub type KeyCursor = Box<dyn Iterator<Item = Result<Key, SystemError>> + Send>;
+pub type KeyRange = (Bound<Key>, Bound<Key>);
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// Reasons why a backend storage operation fails
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum StorageError {
+    /// storage can't proceed due to IO failures, data corruption or bugs
+    #[error(transparent)]
+    System(#[from] SystemError),
+    /// operation refers to namespaces or objects in a way that contradicts what exists
+    #[error(transparent)]
+    Definition(#[from] DefinitionError),
+}
 
-#[derive(Debug, PartialEq)]
-pub struct NamespaceAlreadyExists;
-#[derive(Debug, PartialEq)]
-pub struct NamespaceDoesNotExist;
+impl StorageError {
+    /// SQLSTATE code that is reported to a client
+    pub fn sql_state(&self) -> &'static str {
+        match self {
+            StorageError::System(_) => "XX000",
+            StorageError::Definition(DefinitionError::NamespaceAlreadyExists) => "42P06",
+            StorageError::Definition(DefinitionError::NamespaceDoesNotExist) => "3F000",
+            StorageError::Definition(DefinitionError::ObjectAlreadyExists) => "42P07",
+            StorageError::Definition(DefinitionError::ObjectDoesNotExist) => "42P01",
+        }
+    }
+}
 
-#[derive(Debug, PartialEq)]
-pub enum CreateObjectError {
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum DefinitionError {
+    #[error("namespace already exists")]
+    NamespaceAlreadyExists,
+    #[error("namespace does not exist")]
     NamespaceDoesNotExist,
+    #[error("object already exists")]
     ObjectAlreadyExists,
+    #[error("object does not exist")]
+    ObjectDoesNotExist,
 }
 
-#[derive(Debug, PartialEq)]
-pub enum DropObjectError {
-    NamespaceDoesNotExist,
-    ObjectDoesNotExist,
+/// Modifications of objects and their definitions that are applied atomically.
+/// Operations are checked in order, definitions that a batch drops can't be
+/// created by the same batch again
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteBatch {
+    operations: Vec<BatchOperation>,
 }
 
-#[derive(Debug, PartialEq)]
-pub enum OperationOnObjectError {
-    NamespaceDoesNotExist,
-    ObjectDoesNotExist,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BatchOperation {
+    Write(String, String, Row),
+    Delete(String, String, Key),
+    CreateNamespace(String),
+    DropNamespace(String),
+    CreateObject(String, String),
+    DropObject(String, String),
+    PutObjectMetadata(String, String, Values),
+}
+
+impl WriteBatch {
+    pub fn write(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) {
+        self.operations.extend(
+            rows.into_iter()
+                .map(|row| BatchOperation::Write(namespace.to_owned(), object_name.to_owned(), row)),
+        );
+    }
+
+    pub fn delete(&mut self, namespace: &str, object_name: &str, keys: Vec<Key>) {
+        self.operations.extend(
+            keys.into_iter()
+                .map(|key| BatchOperation::Delete(namespace.to_owned(), object_name.to_owned(), key)),
+        );
+    }
+
+    pub fn create_namespace(&mut self, namespace: &str) {
+        self.operations
+            .push(BatchOperation::CreateNamespace(namespace.to_owned()));
+    }
+
+    pub fn drop_namespace(&mut self, namespace: &str) {
+        self.operations
+            .push(BatchOperation::DropNamespace(namespace.to_owned()));
+    }
+
+    pub fn create_object(&mut self, namespace: &str, object_name: &str) {
+        self.operations.push(BatchOperation::CreateObject(
+            namespace.to_owned(),
+            object_name.to_owned(),
+        ));
+    }
+
+    pub fn drop_object(&mut self, namespace: &str, object_name: &str) {
+        self.operations
+            .push(BatchOperation::DropObject(namespace.to_owned(), object_name.to_owned()));
+    }
+
+    pub fn put_object_metadata(&mut self, namespace: &str, object_name: &str, metadata: Values) {
+        self.operations.push(BatchOperation::PutObjectMetadata(
+            namespace.to_owned(),
+            object_name.to_owned(),
+            metadata,
+        ));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn operations(&self) -> &[BatchOperation] {
+        self.operations.as_slice()
+    }
+
+    /// namespaces and names of objects whose rows are modified in order of their
+    /// first appearance in the batch
+    pub fn object_names(&self) -> Vec<(&str, &str)> {
+        let mut object_names = vec![];
+        for operation in self.operations.iter() {
+            let object_name = match operation {
+                BatchOperation::Write(namespace, object_name, _row) => (namespace.as_str(), object_name.as_str()),
+                BatchOperation::Delete(namespace, object_name, _key) => (namespace.as_str(), object_name.as_str()),
+                _definition => continue,
+            };
+            if !object_names.contains(&object_name) {
+                object_names.push(object_name);
+            }
+        }
+        object_names
+    }
+
+    /// checks that every operation refers to definitions that exist before the
+    /// batch or are created by it, so backend can apply it without failures
+    pub fn validate(
+        &self,
+        namespace_exists: impl Fn(&str) -> bool,
+        object_exists: impl Fn(&str, &str) -> bool,
+    ) -> StorageResult<()> {
+        let mut created_namespaces = HashSet::new();
+        let mut dropped_namespaces = HashSet::new();
+        let mut created_objects = HashSet::new();
+        let mut dropped_objects = HashSet::new();
+        for operation in self.operations.iter() {
+            let (namespace, object_name) = operation.names();
+            let namespace_is_known = namespace_exists(namespace) || created_namespaces.contains(namespace);
+            if let BatchOperation::CreateNamespace(_namespace) = operation {
+                if namespace_is_known {
+                    return Err(DefinitionError::NamespaceAlreadyExists.into());
+                }
+                created_namespaces.insert(namespace);
+                continue;
+            }
+            if !namespace_is_known || dropped_namespaces.contains(namespace) {
+                return Err(DefinitionError::NamespaceDoesNotExist.into());
+            }
+            let object_name = match object_name {
+                Some(object_name) => (namespace, object_name),
+                None => {
+                    dropped_namespaces.insert(namespace);
+                    continue;
+                }
+            };
+            let object_is_known = object_exists(namespace, object_name.1) || created_objects.contains(&object_name);
+            if let BatchOperation::CreateObject(..) = operation {
+                if object_is_known {
+                    return Err(DefinitionError::ObjectAlreadyExists.into());
+                }
+                created_objects.insert(object_name);
+            } else if !object_is_known || dropped_objects.contains(&object_name) {
+                return Err(DefinitionError::ObjectDoesNotExist.into());
+            } else if let BatchOperation::DropObject(..) = operation {
+                dropped_objects.insert(object_name);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BatchOperation {
+    // operations on namespaces do not have an object name
+    fn names(&self) -> (&str, Option<&str>) {
+        match self {
+            BatchOperation::CreateNamespace(namespace) | BatchOperation::DropNamespace(namespace) => {
+                (namespace.as_str(), None)
+            }
+            BatchOperation::Write(namespace, object_name, _)
+            | BatchOperation::Delete(namespace, object_name, _)
+            | BatchOperation::CreateObject(namespace, object_name)
+            | BatchOperation::DropObject(namespace, object_name)
+            | BatchOperation::PutObjectMetadata(namespace, object_name, _) => {
+                (namespace.as_str(), Some(object_name.as_str()))
+            }
+        }
+    }
 }
 
 pub trait BackendStorage {
-    type ErrorMapper: StorageErrorMapper;
+    fn create_namespace(&mut self, namespace: &str) -> StorageResult<()>;
 
-    fn create_namespace(&mut self, namespace: &str) -> SystemResult<Result<(), NamespaceAlreadyExists>>;
+    fn drop_namespace(&mut self, namespace: &str) -> StorageResult<()>;
 
-    fn drop_namespace(&mut self, namespace: &str) -> SystemResult<Result<(), NamespaceDoesNotExist>>;
+    fn create_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()>;
 
-    fn create_object(&mut self, namespace: &str, object_name: &str) -> SystemResult<Result<(), CreateObjectError>>;
+    fn drop_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()>;
 
-    fn drop_object(&mut self, namespace: &str, object_name: &str) -> SystemResult<Result<(), DropObjectError>>;
+    /// names of namespaces in ascending order
+    fn namespaces(&self) -> StorageResult<Vec<String>>;
 
-    fn write(
-        &mut self,
-        namespace: &str,
-        object_name: &str,
-        values: Vec<Row>,
-    ) -> SystemResult<Result<usize, OperationOnObjectError>>;
+    /// names of objects of the namespace in ascending order
+    fn object_names(&self, namespace: &str) -> StorageResult<Vec<String>>;
 
-    fn read(&self, namespace: &str, object_name: &str) -> SystemResult<Result<ReadCursor, OperationOnObjectError>>;
+    /// writes all rows or none of them
+    fn write(&mut self, namespace: &str, object_name: &str, values: Vec<Row>) -> StorageResult<usize>;
 
-    fn delete(
+    /// overwrites values of rows whose keys exist, returns how many rows were overwritten
+    fn update(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize>;
+
+    /// replaces values of the key with `new` only if they are `expected` at the
+    /// moment, `None` stands for an absent key. Values that the key has are
+    /// returned when they are not the expected ones
+    fn cas(
         &mut self,
         namespace: &str,
         object_name: &str,
-        keys: Vec<Key>,
-    ) -> SystemResult<Result<usize, OperationOnObjectError>>;
+        key: Key,
+        expected: Option<Values>,
+        new: Option<Values>,
+    ) -> StorageResult<Result<(), Option<Values>>>;
+
+    fn read(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor>;
+
+    /// reads rows of the object with keys within the range starting from the least key
+    fn read_range(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor>;
+
+    /// reads rows of the object which keys start with the prefix
+    fn read_prefix(&self, namespace: &str, object_name: &str, prefix: &[u8]) -> StorageResult<ReadCursor>;
+
+    /// reads rows of the object starting from the greatest key
+    fn read_reverse(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor>;
+
+    /// reads rows of the object with keys within the range starting from the greatest key
+    fn read_range_reverse(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor>;
+
+    /// reads rows of the object with the given keys, keys without rows are skipped
+    fn lookup(&self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<ReadCursor>;
+
+    /// reads keys of the object skipping its values
+    fn read_keys(&self, namespace: &str, object_name: &str) -> StorageResult<KeyCursor>;
+
+    /// number of rows of the object with keys within the range
+    fn count(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<usize>;
+
+    /// deletes all keys or none of them
+    fn delete(&mut self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<usize>;
+
+    /// applies all writes and deletes of the batch or none of them
+    fn apply_batch(&mut self, batch: WriteBatch) -> StorageResult<()>;
+
+    /// stores a blob that describes the object, it is dropped together with the object
+    fn put_object_metadata(&mut self, namespace: &str, object_name: &str, metadata: Values) -> StorageResult<()>;
+
+    fn get_object_metadata(&self, namespace: &str, object_name: &str) -> StorageResult<Option<Values>>;
+
+    /// number of bytes occupied by keys and values of the object
+    fn object_size(&self, namespace: &str, object_name: &str) -> StorageResult<u64>;
+
+    /// number of bytes occupied by all namespaces
+    fn size(&self) -> StorageResult<u64>;
+
+    /// makes all modifications done so far survive a crash
+    fn flush(&mut self) -> StorageResult<()>;
 }
 
 pub trait StorageErrorMapper {
@@ -92,11 +314,11 @@ impl StorageErrorMapper for SledErrorMapper {
             sled::Error::Unsupported(operation) => {
                 SystemError::unrecoverable(format!("Unsupported operation [{}] was used on Sled", operation))
             }
-            sled::Error::Corruption { at, bt: cause } => {
+            sled::Error::Corruption { at, .. } => {
                 if let Some(at) = at {
-                    SystemError::unrecoverable_with_cause(format!("Sled encountered corruption at {}", at), cause)
+                    SystemError::unrecoverable(format!("Sled encountered corruption at {}", at))
                 } else {
-                    SystemError::unrecoverable_with_cause("Sled encountered corruption".to_owned(), cause)
+                    SystemError::unrecoverable("Sled encountered corruption".to_owned())
                 }
             }
             sled::Error::ReportableBug(description) => {
@@ -107,169 +329,398 @@ impl StorageErrorMapper for SledErrorMapper {
     }
 }
 
-#[derive(Default)]
+/// Keeps every namespace in a single sled database, so objects of different
+/// namespaces can be modified in one transaction. Metadata of objects is kept
+/// in the default tree under names of their trees, names of namespaces are
+/// kept there too and never contain the zero byte that tree names have
 pub struct SledBackendStorage {
-    namespaces: HashMap<String, sled::Db>,
+    database: sled::Db,
+    namespaces: HashSet<String>,
 }
 
-impl BackendStorage for SledBackendStorage {
-    type ErrorMapper = SledErrorMapper;
+impl Default for SledBackendStorage {
+    fn default() -> Self {
+        Self {
+            database: sled::Config::default()
+                .temporary(true)
+                .open()
+                .expect("temporary sled database is opened"),
+            namespaces: HashSet::new(),
+        }
+    }
+}
 
-    fn create_namespace(&mut self, namespace: &str) -> SystemResult<Result<(), NamespaceAlreadyExists>> {
-        if self.namespaces.contains_key(namespace) {
-            Ok(Err(NamespaceAlreadyExists))
-        } else {
-            match sled::Config::default().temporary(true).open() {
-                Ok(database) => {
-                    self.namespaces.insert(namespace.to_owned(), database);
-                    Ok(Ok(()))
-                }
-                Err(error) => Err(Self::ErrorMapper::map(error)),
+impl SledBackendStorage {
+    /// opens storage that keeps its data in the directory, namespaces and objects
+    /// that were created there before are available again
+    pub fn persistent<D: AsRef<Path>>(data_directory: D) -> SystemResult<Self> {
+        let database = sled::Config::default()
+            .path(data_directory)
+            .open()
+            .map_err(SledErrorMapper::map)?;
+        let mut namespaces = HashSet::new();
+        for item in database.iter().keys() {
+            let key = item.map_err(SledErrorMapper::map)?;
+            if !key.contains(&0) {
+                namespaces.insert(String::from_utf8_lossy(&key).into_owned());
             }
         }
+        Ok(Self { database, namespaces })
     }
 
-    fn drop_namespace(&mut self, namespace: &str) -> SystemResult<Result<(), NamespaceDoesNotExist>> {
-        match self.namespaces.remove(namespace) {
-            Some(namespace) => {
-                drop(namespace);
-                Ok(Ok(()))
-            }
-            None => Ok(Err(NamespaceDoesNotExist)),
+    // identifiers can't contain zero byte, so it separates namespace from object name
+    fn tree_name(namespace: &str, object_name: &str) -> Vec<u8> {
+        let mut tree_name = Self::tree_prefix(namespace);
+        tree_name.extend_from_slice(object_name.as_bytes());
+        tree_name
+    }
+
+    fn tree_prefix(namespace: &str) -> Vec<u8> {
+        let mut prefix = namespace.as_bytes().to_vec();
+        prefix.push(0);
+        prefix
+    }
+
+    fn object_exists(&self, namespace: &str, object_name: &str) -> bool {
+        self.database
+            .tree_names()
+            .contains(&Self::tree_name(namespace, object_name).into())
+    }
+
+    fn open_object(&self, namespace: &str, object_name: &str) -> StorageResult<sled::Tree> {
+        if !self.namespaces.contains(namespace) {
+            Err(DefinitionError::NamespaceDoesNotExist.into())
+        } else if !self.object_exists(namespace, object_name) {
+            Err(DefinitionError::ObjectDoesNotExist.into())
+        } else {
+            self.database
+                .open_tree(Self::tree_name(namespace, object_name))
+                .map_err(|error| SledErrorMapper::map(error).into())
         }
     }
+}
 
-    fn create_object(&mut self, namespace: &str, object_name: &str) -> SystemResult<Result<(), CreateObjectError>> {
-        match self.namespaces.get(namespace) {
-            Some(namespace) => {
-                if namespace.tree_names().contains(&(object_name.into())) {
-                    Ok(Err(CreateObjectError::ObjectAlreadyExists))
-                } else {
-                    match namespace.open_tree(object_name) {
-                        Ok(_object) => Ok(Ok(())),
-                        Err(error) => Err(Self::ErrorMapper::map(error)),
-                    }
-                }
+fn sled_error(error: sled::Error) -> StorageError {
+    StorageError::System(SledErrorMapper::map(error))
+}
+
+impl BackendStorage for SledBackendStorage {
+    fn create_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        if self.namespaces.contains(namespace) {
+            return Err(DefinitionError::NamespaceAlreadyExists.into());
+        }
+        self.database.insert(namespace, vec![]).map_err(sled_error)?;
+        self.namespaces.insert(namespace.to_owned());
+        Ok(())
+    }
+
+    fn drop_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        if !self.namespaces.remove(namespace) {
+            return Err(DefinitionError::NamespaceDoesNotExist.into());
+        }
+        self.database.remove(namespace).map_err(sled_error)?;
+        let prefix = Self::tree_prefix(namespace);
+        for tree_name in self.database.tree_names() {
+            if tree_name.starts_with(prefix.as_slice()) {
+                self.database.drop_tree(tree_name).map_err(sled_error)?;
             }
-            None => Ok(Err(CreateObjectError::NamespaceDoesNotExist)),
         }
+        for item in self.database.scan_prefix(prefix).keys() {
+            item.and_then(|tree_name| self.database.remove(tree_name))
+                .map_err(sled_error)?;
+        }
+        Ok(())
     }
 
-    fn drop_object(&mut self, namespace: &str, object_name: &str) -> SystemResult<Result<(), DropObjectError>> {
-        match self.namespaces.get(namespace) {
-            Some(namespace) => match namespace.drop_tree(object_name.as_bytes()) {
-                Ok(true) => Ok(Ok(())),
-                Ok(false) => Ok(Err(DropObjectError::ObjectDoesNotExist)),
-                Err(error) => Err(Self::ErrorMapper::map(error)),
-            },
-            None => Ok(Err(DropObjectError::NamespaceDoesNotExist)),
+    fn create_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        if !self.namespaces.contains(namespace) {
+            Err(DefinitionError::NamespaceDoesNotExist.into())
+        } else if self.object_exists(namespace, object_name) {
+            Err(DefinitionError::ObjectAlreadyExists.into())
+        } else {
+            self.database
+                .open_tree(Self::tree_name(namespace, object_name))
+                .map(|_object| ())
+                .map_err(sled_error)
+        }
+    }
+
+    fn drop_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        if !self.namespaces.contains(namespace) {
+            return Err(DefinitionError::NamespaceDoesNotExist.into());
+        }
+        let tree_name = Self::tree_name(namespace, object_name);
+        if self.database.drop_tree(tree_name.as_slice()).map_err(sled_error)? {
+            self.database.remove(tree_name).map(|_metadata| ()).map_err(sled_error)
+        } else {
+            Err(DefinitionError::ObjectDoesNotExist.into())
+        }
+    }
+
+    fn namespaces(&self) -> StorageResult<Vec<String>> {
+        let mut namespaces = self.namespaces.iter().cloned().collect::<Vec<String>>();
+        namespaces.sort();
+        Ok(namespaces)
+    }
+
+    fn object_names(&self, namespace: &str) -> StorageResult<Vec<String>> {
+        if !self.namespaces.contains(namespace) {
+            return Err(DefinitionError::NamespaceDoesNotExist.into());
+        }
+        let prefix = Self::tree_prefix(namespace);
+        let mut object_names = self
+            .database
+            .tree_names()
+            .into_iter()
+            .filter(|tree_name| tree_name.starts_with(prefix.as_slice()))
+            .map(|tree_name| String::from_utf8_lossy(&tree_name[prefix.len()..]).into_owned())
+            .collect::<Vec<String>>();
+        object_names.sort();
+        Ok(object_names)
+    }
+
+    fn write(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize> {
+        let object = self.open_object(namespace, object_name)?;
+        let written_rows = rows.len();
+        let mut batch = sled::Batch::default();
+        for (key, values) in rows {
+            batch.insert(key, values);
+        }
+        object.apply_batch(batch).map_err(sled_error)?;
+        Ok(written_rows)
+    }
+
+    fn update(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize> {
+        let object = self.open_object(namespace, object_name)?;
+        let updated = object.transaction(|object| {
+            let mut updated = 0;
+            for (key, values) in rows.iter() {
+                if object.get(key.as_slice())?.is_some() {
+                    object.insert(key.as_slice(), values.as_slice())?;
+                    updated += 1;
+                }
+            }
+            Ok(updated)
+        });
+        match updated {
+            Ok(updated) => Ok(updated),
+            Err(TransactionError::Storage(error)) => Err(sled_error(error)),
+            Err(TransactionError::Abort(())) => unreachable!("update transaction is never aborted"),
         }
     }
 
-    fn write(
+    fn cas(
         &mut self,
         namespace: &str,
         object_name: &str,
-        rows: Vec<Row>,
-    ) -> SystemResult<Result<usize, OperationOnObjectError>> {
-        match self.namespaces.get(namespace) {
-            Some(namespace) => {
-                if namespace.tree_names().contains(&(object_name.into())) {
-                    match namespace.open_tree(object_name) {
-                        Ok(object) => {
-                            let mut written_rows = 0;
-                            for (key, values) in rows {
-                                // let to_insert = values
-                                //     .iter()
-                                //     .map(|v| v.as_slice())
-                                //     .collect::<Vec<&[u8]>>()
-                                //     .join(&b'|')
-                                //     .to_vec();
-                                match object.insert::<sled::IVec, sled::IVec>(key.into(), values.into()) {
-                                    Ok(_) => written_rows += 1,
-                                    Err(error) => return Err(Self::ErrorMapper::map(error)),
-                                }
-                            }
-                            Ok(Ok(written_rows))
-                        }
-                        Err(error) => Err(Self::ErrorMapper::map(error)),
-                    }
-                } else {
-                    Ok(Err(OperationOnObjectError::ObjectDoesNotExist))
-                }
+        key: Key,
+        expected: Option<Values>,
+        new: Option<Values>,
+    ) -> StorageResult<Result<(), Option<Values>>> {
+        let object = self.open_object(namespace, object_name)?;
+        match object.compare_and_swap(key, expected, new).map_err(sled_error)? {
+            Ok(()) => Ok(Ok(())),
+            Err(error) => Ok(Err(error.current.map(|current| current.to_vec()))),
+        }
+    }
+
+    fn read(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        let object = self.open_object(namespace, object_name)?;
+        Ok(Box::new(object.iter().map(|item| match item {
+            Ok((key, values)) => Ok((key.to_vec(), values.to_vec())),
+            Err(error) => Err(SledErrorMapper::map(error)),
+        })))
+    }
+
+    fn read_range(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        let object = self.open_object(namespace, object_name)?;
+        Ok(Box::new(object.range::<Key, KeyRange>(range).map(|item| match item {
+            Ok((key, values)) => Ok((key.to_vec(), values.to_vec())),
+            Err(error) => Err(SledErrorMapper::map(error)),
+        })))
+    }
+
+    fn read_prefix(&self, namespace: &str, object_name: &str, prefix: &[u8]) -> StorageResult<ReadCursor> {
+        let object = self.open_object(namespace, object_name)?;
+        Ok(Box::new(object.scan_prefix(prefix).map(|item| match item {
+            Ok((key, values)) => Ok((key.to_vec(), values.to_vec())),
+            Err(error) => Err(SledErrorMapper::map(error)),
+        })))
+    }
+
+    fn read_reverse(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        self.read_range_reverse(namespace, object_name, (Bound::Unbounded, Bound::Unbounded))
+    }
+
+    fn read_range_reverse(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        let object = self.open_object(namespace, object_name)?;
+        Ok(Box::new(object.range::<Key, KeyRange>(range).rev().map(
+            |item| match item {
+                Ok((key, values)) => Ok((key.to_vec(), values.to_vec())),
+                Err(error) => Err(SledErrorMapper::map(error)),
+            },
+        )))
+    }
+
+    fn lookup(&self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<ReadCursor> {
+        let object = self.open_object(namespace, object_name)?;
+        let mut rows = vec![];
+        for key in keys {
+            if let Some(values) = object.get(&key).map_err(sled_error)? {
+                rows.push(Ok((key, values.to_vec())));
+            }
+        }
+        Ok(Box::new(rows.into_iter()))
+    }
+
+    fn read_keys(&self, namespace: &str, object_name: &str) -> StorageResult<KeyCursor> {
+        let object = self.open_object(namespace, object_name)?;
+        Ok(Box::new(object.iter().keys().map(|item| match item {
+            Ok(key) => Ok(key.to_vec()),
+            Err(error) => Err(SledErrorMapper::map(error)),
+        })))
+    }
+
+    fn count(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<usize> {
+        let object = self.open_object(namespace, object_name)?;
+        let mut count = 0;
+        for item in object.range::<Key, KeyRange>(range).keys() {
+            item.map_err(sled_error)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn delete(&mut self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<usize> {
+        let object = self.open_object(namespace, object_name)?;
+        let deleted = keys.len();
+        let mut batch = sled::Batch::default();
+        for key in keys {
+            batch.remove(key);
+        }
+        object.apply_batch(batch).map_err(sled_error)?;
+        Ok(deleted)
+    }
+
+    // sled can't create or drop trees in a transaction, so definitions are created
+    // before rows and metadata are changed and dropped after it. Objects that are
+    // left by a crash in between are not recorded anywhere
+    fn apply_batch(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        batch.validate(
+            |namespace| self.namespaces.contains(namespace),
+            |namespace, object_name| self.object_exists(namespace, object_name),
+        )?;
+        for operation in batch.operations() {
+            match operation {
+                BatchOperation::CreateNamespace(namespace) => self.create_namespace(namespace)?,
+                BatchOperation::CreateObject(namespace, object_name) => self.create_object(namespace, object_name)?,
+                _ => {}
             }
-            None => Ok(Err(OperationOnObjectError::NamespaceDoesNotExist)),
-        }
-    }
-
-    fn read(&self, namespace: &str, object_name: &str) -> SystemResult<Result<ReadCursor, OperationOnObjectError>> {
-        match self.namespaces.get(namespace) {
-            Some(namespace) => {
-                if namespace.tree_names().contains(&(object_name.into())) {
-                    match namespace.open_tree(object_name) {
-                        Ok(object) => Ok(Ok(Box::new(object.iter().map(|item| {
-                            match item {
-                                Ok((key, values)) => Ok((
-                                    key.to_vec(),
-                                    values.to_vec(),
-                                    // .split(|b| *b == b'|')
-                                    // .map(|v| v.to_vec())
-                                    // .collect::<Vec<Vec<u8>>>(),
-                                )),
-                                Err(error) => Err(Self::ErrorMapper::map(error)),
-                            }
-                        })))),
-                        Err(error) => Err(Self::ErrorMapper::map(error)),
+        }
+        let object_names = batch.object_names();
+        let mut objects = Vec::with_capacity(object_names.len() + 1);
+        for (namespace, object_name) in object_names.iter() {
+            objects.push(self.open_object(namespace, object_name)?);
+        }
+        // metadata of objects is kept in the default tree
+        let metadata = objects.len();
+        objects.push((*self.database).clone());
+        let position = |namespace: &str, object_name: &str| {
+            object_names
+                .iter()
+                .position(|name| *name == (namespace, object_name))
+                .unwrap()
+        };
+        let applied = objects.as_slice().transaction(|objects| {
+            for operation in batch.operations() {
+                match operation {
+                    BatchOperation::Write(namespace, object_name, (key, values)) => {
+                        objects[position(namespace, object_name)].insert(key.as_slice(), values.as_slice())?;
                     }
-                } else {
-                    Ok(Err(OperationOnObjectError::ObjectDoesNotExist))
+                    BatchOperation::Delete(namespace, object_name, key) => {
+                        objects[position(namespace, object_name)].remove(key.as_slice())?;
+                    }
+                    BatchOperation::PutObjectMetadata(namespace, object_name, values) => {
+                        objects[metadata].insert(Self::tree_name(namespace, object_name), values.as_slice())?;
+                    }
+                    _ => {}
                 }
             }
-            None => Ok(Err(OperationOnObjectError::NamespaceDoesNotExist)),
+            Ok(())
+        });
+        match applied {
+            Ok(()) => {}
+            Err(TransactionError::Storage(error)) => return Err(sled_error(error)),
+            Err(TransactionError::Abort(())) => unreachable!("batch transaction is never aborted"),
+        }
+        for operation in batch.operations() {
+            match operation {
+                BatchOperation::DropObject(namespace, object_name) => self.drop_object(namespace, object_name)?,
+                BatchOperation::DropNamespace(namespace) => self.drop_namespace(namespace)?,
+                _ => {}
+            }
         }
+        Ok(())
     }
 
-    fn delete(
-        &mut self,
-        namespace: &str,
-        object_name: &str,
-        keys: Vec<Key>,
-    ) -> SystemResult<Result<usize, OperationOnObjectError>> {
-        match self.namespaces.get(namespace) {
-            Some(namespace) => {
-                if namespace.tree_names().contains(&(object_name.into())) {
-                    let mut deleted = 0;
-                    match namespace.open_tree(object_name) {
-                        Ok(object) => {
-                            for key in keys {
-                                match object.remove(key) {
-                                    Ok(_) => deleted += 1,
-                                    Err(error) => return Err(Self::ErrorMapper::map(error)),
-                                }
-                            }
-                        }
-                        Err(error) => return Err(Self::ErrorMapper::map(error)),
-                    }
-                    Ok(Ok(deleted))
-                } else {
-                    Ok(Err(OperationOnObjectError::ObjectDoesNotExist))
+    fn put_object_metadata(&mut self, namespace: &str, object_name: &str, metadata: Values) -> StorageResult<()> {
+        self.open_object(namespace, object_name)?;
+        self.database
+            .insert(Self::tree_name(namespace, object_name), metadata)
+            .map(|_previous| ())
+            .map_err(sled_error)
+    }
+
+    fn get_object_metadata(&self, namespace: &str, object_name: &str) -> StorageResult<Option<Values>> {
+        self.open_object(namespace, object_name)?;
+        self.database
+            .get(Self::tree_name(namespace, object_name))
+            .map(|metadata| metadata.map(|metadata| metadata.to_vec()))
+            .map_err(sled_error)
+    }
+
+    fn object_size(&self, namespace: &str, object_name: &str) -> StorageResult<u64> {
+        let object = self.open_object(namespace, object_name)?;
+        let mut size = 0;
+        for item in object.iter() {
+            let (key, values) = item.map_err(sled_error)?;
+            size += (key.len() + values.len()) as u64;
+        }
+        Ok(size)
+    }
+
+    // sled writes data to disk when it is flushed, so it is counted in trees
+    fn size(&self) -> StorageResult<u64> {
+        let mut size = 0;
+        for tree_name in self.database.tree_names() {
+            if tree_name.contains(&0) {
+                let object = self.database.open_tree(tree_name).map_err(sled_error)?;
+                for item in object.iter() {
+                    let (key, values) = item.map_err(sled_error)?;
+                    size += (key.len() + values.len()) as u64;
                 }
             }
-            None => Ok(Err(OperationOnObjectError::NamespaceDoesNotExist)),
         }
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> StorageResult<()> {
+        self.database.flush().map(|_flushed_bytes| ()).map_err(sled_error)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use backtrace::Backtrace;
 
     #[cfg(test)]
     mod sled_error_mapper {
         use super::*;
         use sled::DiskPtr;
-        use std::io::{Error, ErrorKind};
+        use std::io::Error;
 
         #[test]
         fn collection_not_found() {
@@ -289,26 +740,18 @@ mod tests {
 
         #[test]
         fn corruption_with_position() {
-            let cause = Backtrace::new();
             let at = DiskPtr::Inline(900);
             assert_eq!(
-                SledErrorMapper::map(sled::Error::Corruption {
-                    at: Some(at),
-                    bt: cause.clone()
-                }),
-                SystemError::unrecoverable_with_cause(format!("Sled encountered corruption at {}", at), cause,)
+                SledErrorMapper::map(sled::Error::Corruption { at: Some(at), bt: () }),
+                SystemError::unrecoverable(format!("Sled encountered corruption at {}", at))
             )
         }
 
         #[test]
         fn corruption_without_position() {
-            let cause = Backtrace::new();
             assert_eq!(
-                SledErrorMapper::map(sled::Error::Corruption {
-                    at: None,
-                    bt: cause.clone()
-                }),
-                SystemError::unrecoverable_with_cause("Sled encountered corruption".to_owned(), cause,)
+                SledErrorMapper::map(sled::Error::Corruption { at: None, bt: () }),
+                SystemError::unrecoverable("Sled encountered corruption".to_owned())
             )
         }
 
@@ -324,42 +767,74 @@ mod tests {
         #[test]
         fn io() {
             assert_eq!(
-                SledErrorMapper::map(sled::Error::Io(Error::new(ErrorKind::Other, "oh no!"))),
-                SystemError::io(Error::new(ErrorKind::Other, "oh no!"))
+                SledErrorMapper::map(sled::Error::Io(Error::other("oh no!"))),
+                SystemError::io(Error::other("oh no!"))
             )
         }
     }
 
     #[cfg(test)]
-    mod namespace {
+    mod storage_error {
         use super::*;
 
         #[test]
-        fn create_namespaces_with_different_names() {
-            let mut storage = SledBackendStorage::default();
+        fn system_errors_are_internal_errors() {
+            assert_eq!(
+                StorageError::from(SystemError::unrecoverable("failure".to_owned())).sql_state(),
+                "XX000"
+            );
+        }
 
+        #[test]
+        fn definition_errors() {
+            assert_eq!(
+                StorageError::from(DefinitionError::NamespaceAlreadyExists).sql_state(),
+                "42P06"
+            );
+            assert_eq!(
+                StorageError::from(DefinitionError::NamespaceDoesNotExist).sql_state(),
+                "3F000"
+            );
             assert_eq!(
-                storage.create_namespace("namespace_1").expect("namespace created"),
-                Ok(())
+                StorageError::from(DefinitionError::ObjectAlreadyExists).sql_state(),
+                "42P07"
             );
             assert_eq!(
-                storage.create_namespace("namespace_2").expect("namespace created"),
-                Ok(())
+                StorageError::from(DefinitionError::ObjectDoesNotExist).sql_state(),
+                "42P01"
             );
         }
 
+        #[test]
+        fn message_of_system_error() {
+            assert_eq!(
+                StorageError::from(SystemError::unrecoverable("failure".to_owned())).to_string(),
+                "failure"
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod namespace {
+        use super::*;
+
+        #[test]
+        fn create_namespaces_with_different_names() {
+            let mut storage = SledBackendStorage::default();
+
+            assert_eq!(storage.create_namespace("namespace_1"), Ok(()));
+            assert_eq!(storage.create_namespace("namespace_2"), Ok(()));
+        }
+
         #[test]
         fn create_namespace_with_existing_name() {
             let mut storage = SledBackendStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
 
             assert_eq!(
-                storage.create_namespace("namespace").expect("no system errors"),
-                Err(NamespaceAlreadyExists)
+                storage.create_namespace("namespace"),
+                Err(StorageError::Definition(DefinitionError::NamespaceAlreadyExists))
             );
         }
 
@@ -367,13 +842,10 @@ mod tests {
         fn drop_namespace() {
             let mut storage = SledBackendStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
 
-            assert_eq!(storage.drop_namespace("namespace").expect("no system errors"), Ok(()));
-            assert_eq!(storage.create_namespace("namespace").expect("no system errors"), Ok(()));
+            assert_eq!(storage.drop_namespace("namespace"), Ok(()));
+            assert_eq!(storage.create_namespace("namespace"), Ok(()));
         }
 
         #[test]
@@ -381,8 +853,8 @@ mod tests {
             let mut storage = SledBackendStorage::default();
 
             assert_eq!(
-                storage.drop_namespace("does_not_exists").expect("no system errors"),
-                Err(NamespaceDoesNotExist)
+                storage.drop_namespace("does_not_exists"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
 
@@ -390,35 +862,45 @@ mod tests {
         fn dropping_namespace_drops_objects_in_it() {
             let mut storage = SledBackendStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
             storage
                 .create_object("namespace", "object_name_1")
-                .expect("no system errors")
                 .expect("object created");
             storage
                 .create_object("namespace", "object_name_2")
-                .expect("no system errors")
                 .expect("object created");
 
-            assert_eq!(storage.drop_namespace("namespace").expect("no system errors"), Ok(()));
+            assert_eq!(storage.drop_namespace("namespace"), Ok(()));
+            assert_eq!(storage.create_namespace("namespace"), Ok(()));
+            assert_eq!(storage.create_object("namespace", "object_name_1"), Ok(()));
+            assert_eq!(storage.create_object("namespace", "object_name_2"), Ok(()));
+        }
+
+        #[test]
+        fn namespaces_and_their_objects() {
+            let mut storage = SledBackendStorage::default();
+
+            storage.create_namespace("namespace_2").expect("namespace created");
+            storage.create_namespace("namespace_1").expect("namespace created");
+            storage
+                .create_object("namespace_1", "object_name_2")
+                .expect("object created");
+            storage
+                .create_object("namespace_1", "object_name_1")
+                .expect("object created");
+
             assert_eq!(
-                storage.create_namespace("namespace").expect("namespace created"),
-                Ok(())
+                storage.namespaces(),
+                Ok(vec!["namespace_1".to_owned(), "namespace_2".to_owned()])
             );
             assert_eq!(
-                storage
-                    .create_object("namespace", "object_name_1")
-                    .expect("no system errors"),
-                Ok(())
+                storage.object_names("namespace_1"),
+                Ok(vec!["object_name_1".to_owned(), "object_name_2".to_owned()])
             );
+            assert_eq!(storage.object_names("namespace_2"), Ok(vec![]));
             assert_eq!(
-                storage
-                    .create_object("namespace", "object_name_2")
-                    .expect("no system errors"),
-                Ok(())
+                storage.object_names("not_existent"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
     }
@@ -431,23 +913,10 @@ mod tests {
         fn create_objects_with_different_names() {
             let mut storage = SledBackendStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
 
-            assert_eq!(
-                storage
-                    .create_object("namespace", "object_name_1")
-                    .expect("no system errors"),
-                Ok(())
-            );
-            assert_eq!(
-                storage
-                    .create_object("namespace", "object_name_2")
-                    .expect("no system errors"),
-                Ok(())
-            );
+            assert_eq!(storage.create_object("namespace", "object_name_1"), Ok(()));
+            assert_eq!(storage.create_object("namespace", "object_name_2"), Ok(()));
         }
 
         #[test]
@@ -457,10 +926,8 @@ mod tests {
             create_object(&mut storage, "namespace", "object_name");
 
             assert_eq!(
-                storage
-                    .create_object("namespace", "object_name")
-                    .expect("no system errors"),
-                Err(CreateObjectError::ObjectAlreadyExists)
+                storage.create_object("namespace", "object_name"),
+                Err(StorageError::Definition(DefinitionError::ObjectAlreadyExists))
             );
         }
 
@@ -468,26 +935,10 @@ mod tests {
         fn create_object_with_the_same_name_in_different_namespaces() {
             let mut storage = SledBackendStorage::default();
 
-            storage
-                .create_namespace("namespace_1")
-                .expect("no system errors")
-                .expect("namespace created");
-            storage
-                .create_namespace("namespace_2")
-                .expect("no system errors")
-                .expect("namespace created");
-            assert_eq!(
-                storage
-                    .create_object("namespace_1", "object_name")
-                    .expect("no system errors"),
-                Ok(())
-            );
-            assert_eq!(
-                storage
-                    .create_object("namespace_2", "object_name")
-                    .expect("no system errors"),
-                Ok(())
-            );
+            storage.create_namespace("namespace_1").expect("namespace created");
+            storage.create_namespace("namespace_2").expect("namespace created");
+            assert_eq!(storage.create_object("namespace_1", "object_name"), Ok(()));
+            assert_eq!(storage.create_object("namespace_2", "object_name"), Ok(()));
         }
 
         #[test]
@@ -495,10 +946,8 @@ mod tests {
             let mut storage = SledBackendStorage::default();
 
             assert_eq!(
-                storage
-                    .create_object("not_existent", "object_name")
-                    .expect("no system errors"),
-                Err(CreateObjectError::NamespaceDoesNotExist)
+                storage.create_object("not_existent", "object_name"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
     }
@@ -512,33 +961,18 @@ mod tests {
             let mut storage = SledBackendStorage::default();
 
             create_object(&mut storage, "namespace", "object_name");
-            assert_eq!(
-                storage
-                    .drop_object("namespace", "object_name")
-                    .expect("no system errors"),
-                Ok(())
-            );
-            assert_eq!(
-                storage
-                    .create_object("namespace", "object_name")
-                    .expect("no system errors"),
-                Ok(())
-            );
+            assert_eq!(storage.drop_object("namespace", "object_name"), Ok(()));
+            assert_eq!(storage.create_object("namespace", "object_name"), Ok(()));
         }
 
         #[test]
         fn drop_not_created_object() {
             let mut storage = SledBackendStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
             assert_eq!(
-                storage
-                    .drop_object("namespace", "not_existed_object")
-                    .expect("no system errors"),
-                Err(DropObjectError::ObjectDoesNotExist)
+                storage.drop_object("namespace", "not_existed_object"),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
             );
         }
 
@@ -547,55 +981,121 @@ mod tests {
             let mut storage = SledBackendStorage::default();
 
             assert_eq!(
-                storage.drop_object("not_existent", "object").expect("no system errors"),
-                Err(DropObjectError::NamespaceDoesNotExist)
+                storage.drop_object("not_existent", "object"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
     }
 
     #[cfg(test)]
-    mod operations_on_object {
+    mod persistent {
         use super::*;
+        use std::path::PathBuf;
+
+        fn data_directory(name: &str) -> PathBuf {
+            let directory = std::env::temp_dir().join(format!("database_sled_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&directory);
+            directory
+        }
 
         #[test]
-        fn insert_row_into_object() {
-            let mut storage = SledBackendStorage::default();
+        fn namespaces_and_objects_are_available_after_reopening() {
+            let directory = data_directory("reopen");
+            {
+                let mut storage = SledBackendStorage::persistent(&directory).expect("storage is opened");
+                create_object(&mut storage, "namespace", "object_name");
+                storage
+                    .write("namespace", "object_name", as_rows(vec![(1u8, vec!["1"])]))
+                    .expect("write occurred");
+                storage
+                    .put_object_metadata("namespace", "object_name", vec![1, 2, 3])
+                    .expect("metadata is stored");
+            }
+
+            let mut storage =
+                crate::reopen(|| SledBackendStorage::persistent(&directory)).expect("storage is reopened");
 
-            create_object(&mut storage, "namespace", "object_name");
             assert_eq!(
-                storage
-                    .write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]))
-                    .expect("no system errors"),
-                Ok(1)
+                storage.create_namespace("namespace"),
+                Err(StorageError::Definition(DefinitionError::NamespaceAlreadyExists))
             );
-
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
-                Ok(as_read_cursor(vec![(1u8, vec!["123"])]).collect())
+                Ok(as_read_cursor(vec![(1u8, vec!["1"])]).collect())
+            );
+            assert_eq!(
+                storage.get_object_metadata("namespace", "object_name"),
+                Ok(Some(vec![1, 2, 3]))
             );
+
+            drop(storage);
+            std::fs::remove_dir_all(directory).expect("data directory is removed");
         }
 
         #[test]
-        fn insert_many_rows_into_object() {
-            let mut storage = SledBackendStorage::default();
+        fn dropped_namespace_is_not_available_after_reopening() {
+            let directory = data_directory("dropped");
+            {
+                let mut storage = SledBackendStorage::persistent(&directory).expect("storage is opened");
+                create_object(&mut storage, "namespace", "object_name");
+                storage.drop_namespace("namespace").expect("namespace is dropped");
+            }
 
-            create_object(&mut storage, "namespace", "object_name");
-            storage
-                .write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]))
-                .expect("no system errors")
-                .expect("values are written");
+            let mut storage =
+                crate::reopen(|| SledBackendStorage::persistent(&directory)).expect("storage is reopened");
+
+            assert_eq!(storage.create_namespace("namespace"), Ok(()));
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+
+            drop(storage);
+            std::fs::remove_dir_all(directory).expect("data directory is removed");
+        }
+    }
+
+    #[cfg(test)]
+    mod operations_on_object {
+        use super::*;
+
+        #[test]
+        fn insert_row_into_object() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            assert_eq!(
+                storage.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])])),
+                Ok(1)
+            );
+
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(1u8, vec!["123"])]).collect())
+            );
+        }
+
+        #[test]
+        fn insert_many_rows_into_object() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]))
+                .expect("values are written");
             storage
                 .write("namespace", "object_name", as_rows(vec![(2u8, vec!["456"])]))
-                .expect("no system errors")
                 .expect("values are written");
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![(1u8, vec!["123"]), (2u8, vec!["456"])]).collect())
             );
@@ -605,44 +1105,117 @@ mod tests {
         fn insert_into_non_existent_object() {
             let mut storage = SledBackendStorage::default();
 
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage.write("namespace", "not_existed", as_rows(vec![(1u8, vec!["123"])],)),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn insert_into_object_in_non_existent_namespace() {
+            let mut storage = SledBackendStorage::default();
+
+            assert_eq!(
+                storage.write("not_existed", "object", as_rows(vec![(1u8, vec!["123"])],)),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn update_rows_that_exist() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
             storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["123"]), (2u8, vec!["456"])]),
+                )
+                .expect("values are written");
+
+            assert_eq!(
+                storage.update(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(2u8, vec!["789"]), (3u8, vec!["789"])])
+                ),
+                Ok(1)
+            );
             assert_eq!(
                 storage
-                    .write("namespace", "not_existed", as_rows(vec![(1u8, vec!["123"])],))
-                    .expect("no system errors"),
-                Err(OperationOnObjectError::ObjectDoesNotExist)
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(1u8, vec!["123"]), (2u8, vec!["789"])]).collect())
             );
         }
 
         #[test]
-        fn insert_into_object_in_non_existent_namespace() {
+        fn update_non_existent_object() {
+            let mut storage = SledBackendStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage.update("namespace", "not_existed", as_rows(vec![(1u8, vec!["123"])])),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn cas_swaps_expected_values() {
             let mut storage = SledBackendStorage::default();
 
+            create_object(&mut storage, "namespace", "object_name");
+            assert_eq!(
+                storage.cas("namespace", "object_name", vec![1], None, Some(vec![1])),
+                Ok(Ok(()))
+            );
+            assert_eq!(
+                storage.cas("namespace", "object_name", vec![1], Some(vec![1]), Some(vec![2])),
+                Ok(Ok(()))
+            );
+            assert_eq!(
+                storage.cas("namespace", "object_name", vec![1], Some(vec![2]), None),
+                Ok(Ok(()))
+            );
             assert_eq!(
                 storage
-                    .write("not_existed", "object", as_rows(vec![(1u8, vec!["123"])],))
-                    .expect("no system errors"),
-                Err(OperationOnObjectError::NamespaceDoesNotExist)
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![])
             );
         }
 
         #[test]
-        fn select_from_object_that_does_not_exist() {
+        fn cas_returns_current_values() {
             let mut storage = SledBackendStorage::default();
 
+            create_object(&mut storage, "namespace", "object_name");
             storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+                .write("namespace", "object_name", vec![(vec![1], vec![1])])
+                .expect("values are written");
+
+            assert_eq!(
+                storage.cas("namespace", "object_name", vec![1], None, Some(vec![2])),
+                Ok(Err(Some(vec![1])))
+            );
+            assert_eq!(
+                storage.cas("namespace", "object_name", vec![2], Some(vec![2]), Some(vec![2])),
+                Ok(Err(None))
+            );
+        }
+
+        #[test]
+        fn select_from_object_that_does_not_exist() {
+            let mut storage = SledBackendStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
             assert_eq!(
                 storage
                     .read("namespace", "not_existed")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
-                Err(OperationOnObjectError::ObjectDoesNotExist)
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
             );
         }
 
@@ -653,9 +1226,8 @@ mod tests {
             assert_eq!(
                 storage
                     .read("not_existed", "object")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
-                Err(OperationOnObjectError::NamespaceDoesNotExist)
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
 
@@ -670,20 +1242,13 @@ mod tests {
                     "object_name",
                     as_rows(vec![(1u8, vec!["123"]), (2u8, vec!["456"]), (3u8, vec!["789"])]),
                 )
-                .expect("no system errors")
                 .expect("write occurred");
 
-            assert_eq!(
-                storage
-                    .delete("namespace", "object_name", as_keys(vec![2u8]))
-                    .expect("no system errors"),
-                Ok(1)
-            );
+            assert_eq!(storage.delete("namespace", "object_name", as_keys(vec![2u8])), Ok(1));
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![(1u8, vec!["123"]), (3u8, vec!["789"])]).collect())
             );
@@ -693,16 +1258,11 @@ mod tests {
         fn delete_from_not_existed_object() {
             let mut storage = SledBackendStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
 
             assert_eq!(
-                storage
-                    .delete("namespace", "not_existent", vec![])
-                    .expect("no system errors"),
-                Err(OperationOnObjectError::ObjectDoesNotExist)
+                storage.delete("namespace", "not_existent", vec![]),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
             );
         }
 
@@ -711,10 +1271,8 @@ mod tests {
             let mut storage = SledBackendStorage::default();
 
             assert_eq!(
-                storage
-                    .delete("not existent", "object", vec![])
-                    .expect("no system errors"),
-                Err(OperationOnObjectError::NamespaceDoesNotExist)
+                storage.delete("not existent", "object", vec![]),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
 
@@ -725,13 +1283,11 @@ mod tests {
             create_object(&mut storage, "namespace", "object_name");
             storage
                 .write("namespace", "object_name", as_rows(vec![(1u8, vec!["1", "2", "3"])]))
-                .expect("no system errors")
                 .expect("write occurred");
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![(1u8, vec!["1", "2", "3"])]).collect())
             );
@@ -752,13 +1308,11 @@ mod tests {
                         (3u8, vec!["7", "8", "9"]),
                     ]),
                 )
-                .expect("no system errors")
                 .expect("write occurred");
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![
                     (1u8, vec!["1", "2", "3"]),
@@ -768,17 +1322,595 @@ mod tests {
                 .collect()),
             );
         }
+
+        #[test]
+        fn read_rows_on_another_thread() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]))
+                .expect("write occurred");
+            let cursor = storage.read("namespace", "object_name").expect("object exists");
+
+            assert_eq!(
+                std::thread::spawn(move || cursor.collect::<Vec<Result<Row, SystemError>>>())
+                    .join()
+                    .expect("thread is finished"),
+                as_read_cursor(vec![(1u8, vec!["123"])]).collect::<Vec<Result<Row, SystemError>>>()
+            );
+        }
+
+        #[test]
+        fn read_rows_in_reverse_order() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![
+                        (2u8, vec!["4", "5", "6"]),
+                        (1u8, vec!["1", "2", "3"]),
+                        (3u8, vec!["7", "8", "9"]),
+                    ]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_reverse("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![
+                    (3u8, vec!["7", "8", "9"]),
+                    (2u8, vec!["4", "5", "6"]),
+                    (1u8, vec!["1", "2", "3"])
+                ])
+                .collect()),
+            );
+        }
+
+        #[test]
+        fn read_range_of_rows() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![
+                        (4u8, vec!["4"]),
+                        (1u8, vec!["1"]),
+                        (3u8, vec!["3"]),
+                        (2u8, vec!["2"]),
+                    ]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_range(
+                        "namespace",
+                        "object_name",
+                        (Bound::Included(vec![2u8]), Bound::Unbounded)
+                    )
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(2u8, vec!["2"]), (3u8, vec!["3"]), (4u8, vec!["4"])]).collect()),
+            );
+        }
+
+        #[test]
+        fn read_rows_with_key_prefix() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    vec![
+                        (vec![1u8, 1u8], vec![1u8]),
+                        (vec![2u8, 1u8], vec![2u8]),
+                        (vec![1u8, 2u8], vec![3u8]),
+                    ],
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_prefix("namespace", "object_name", &[1u8])
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![Ok((vec![1u8, 1u8], vec![1u8])), Ok((vec![1u8, 2u8], vec![3u8]))]),
+            );
+        }
+
+        #[test]
+        fn read_prefix_from_object_that_does_not_exist() {
+            let mut storage = SledBackendStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage
+                    .read_prefix("namespace", "not_existed", &[1u8])
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn read_range_of_rows_in_reverse_order() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![
+                        (1u8, vec!["1"]),
+                        (2u8, vec!["2"]),
+                        (3u8, vec!["3"]),
+                        (4u8, vec!["4"]),
+                    ]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_range_reverse(
+                        "namespace",
+                        "object_name",
+                        (Bound::Excluded(vec![1u8]), Bound::Included(vec![3u8]))
+                    )
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(3u8, vec!["3"]), (2u8, vec!["2"])]).collect()),
+            );
+        }
+
+        #[test]
+        fn read_reverse_from_object_that_does_not_exist() {
+            let mut storage = SledBackendStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage
+                    .read_reverse("namespace", "not_existed")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn lookup_rows_by_keys() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["1"]), (2u8, vec!["2"]), (3u8, vec!["3"])]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .lookup("namespace", "object_name", as_keys(vec![3u8, 4u8, 1u8]))
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(3u8, vec!["3"]), (1u8, vec!["1"])]).collect()),
+            );
+        }
+
+        #[test]
+        fn lookup_in_object_that_does_not_exist() {
+            let mut storage = SledBackendStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage
+                    .lookup("namespace", "not_existed", as_keys(vec![1u8]))
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn read_keys_of_object() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["1", "2", "3"]), (2u8, vec!["4", "5", "6"])]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_keys("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Key, SystemError>>>()),
+                Ok(vec![Ok(vec![1u8]), Ok(vec![2u8])])
+            );
+        }
+
+        #[test]
+        fn count_rows_within_range() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["1"]), (2u8, vec!["2"]), (3u8, vec!["3"])]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage.count("namespace", "object_name", (Bound::Unbounded, Bound::Unbounded)),
+                Ok(3)
+            );
+            assert_eq!(
+                storage.count(
+                    "namespace",
+                    "object_name",
+                    (Bound::Included(vec![2u8]), Bound::Unbounded)
+                ),
+                Ok(2)
+            );
+        }
+
+        #[test]
+        fn count_rows_of_object_in_not_existent_namespace() {
+            let storage = SledBackendStorage::default();
+
+            assert_eq!(
+                storage.count("not_existed", "object_name", (Bound::Unbounded, Bound::Unbounded)),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn object_without_metadata() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+
+            assert_eq!(storage.get_object_metadata("namespace", "object_name"), Ok(None));
+        }
+
+        #[test]
+        fn put_and_get_object_metadata() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .put_object_metadata("namespace", "object_name", vec![1, 2, 3])
+                .expect("metadata is put");
+
+            assert_eq!(
+                storage.get_object_metadata("namespace", "object_name"),
+                Ok(Some(vec![1, 2, 3]))
+            );
+        }
+
+        #[test]
+        fn metadata_is_dropped_with_object() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .put_object_metadata("namespace", "object_name", vec![1, 2, 3])
+                .expect("metadata is put");
+            storage.drop_object("namespace", "object_name").expect("object dropped");
+            storage
+                .create_object("namespace", "object_name")
+                .expect("object created");
+
+            assert_eq!(storage.get_object_metadata("namespace", "object_name"), Ok(None));
+        }
+
+        #[test]
+        fn metadata_is_dropped_with_namespace() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .put_object_metadata("namespace", "object_name", vec![1, 2, 3])
+                .expect("metadata is put");
+            storage.drop_namespace("namespace").expect("namespace dropped");
+            create_object(&mut storage, "namespace", "object_name");
+
+            assert_eq!(storage.get_object_metadata("namespace", "object_name"), Ok(None));
+        }
+
+        #[test]
+        fn put_metadata_of_object_that_does_not_exist() {
+            let mut storage = SledBackendStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage.put_object_metadata("namespace", "not_existed", vec![1, 2, 3]),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn size_of_object() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["1", "2", "3"]), (2u8, vec!["4", "5", "6"])]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(storage.object_size("namespace", "object_name"), Ok(12));
+        }
+
+        #[test]
+        fn size_of_objects_that_are_not_flushed() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name_1");
+            storage
+                .create_object("namespace", "object_name_2")
+                .expect("object created");
+            storage
+                .write("namespace", "object_name_1", as_rows(vec![(1u8, vec!["1", "2", "3"])]))
+                .expect("write occurred");
+            storage
+                .write("namespace", "object_name_2", as_rows(vec![(2u8, vec!["4", "5", "6"])]))
+                .expect("write occurred");
+
+            assert_eq!(storage.size(), Ok(12));
+        }
+
+        #[test]
+        fn size_of_object_that_does_not_exist() {
+            let mut storage = SledBackendStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage.object_size("namespace", "not_existed"),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn size_of_object_in_not_existent_namespace() {
+            let storage = SledBackendStorage::default();
+
+            assert_eq!(
+                storage.object_size("not_existed", "object"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn batch_of_writes_and_deletes_in_different_objects() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name_1");
+            storage
+                .create_object("namespace", "object_name_2")
+                .expect("object created");
+            storage
+                .write("namespace", "object_name_1", as_rows(vec![(1u8, vec!["123"])]))
+                .expect("values are written");
+
+            let mut batch = WriteBatch::default();
+            batch.delete("namespace", "object_name_1", as_keys(vec![1u8]));
+            batch.write("namespace", "object_name_2", as_rows(vec![(2u8, vec!["456"])]));
+            assert_eq!(storage.apply_batch(batch), Ok(()));
+
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name_1")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![])
+            );
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name_2")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(2u8, vec!["456"])]).collect())
+            );
+        }
+
+        #[test]
+        fn batch_of_writes_in_different_namespaces() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace_1", "object_name");
+            create_object(&mut storage, "namespace_2", "object_name");
+
+            let mut batch = WriteBatch::default();
+            batch.write("namespace_1", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            batch.write("namespace_2", "object_name", as_rows(vec![(2u8, vec!["456"])]));
+            assert_eq!(storage.apply_batch(batch), Ok(()));
+
+            assert_eq!(
+                storage
+                    .read("namespace_1", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(1u8, vec!["123"])]).collect())
+            );
+            assert_eq!(
+                storage
+                    .read("namespace_2", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(2u8, vec!["456"])]).collect())
+            );
+        }
+
+        #[test]
+        fn batch_with_not_existed_namespace_is_not_applied() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+
+            let mut batch = WriteBatch::default();
+            batch.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            batch.write("not_existed", "object_name", as_rows(vec![(2u8, vec!["456"])]));
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![])
+            );
+        }
+
+        #[test]
+        fn batch_with_not_existed_object_is_not_applied() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+
+            let mut batch = WriteBatch::default();
+            batch.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            batch.write("namespace", "not_existed", as_rows(vec![(2u8, vec!["456"])]));
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![])
+            );
+        }
+
+        #[test]
+        fn empty_batch() {
+            let mut storage = SledBackendStorage::default();
+
+            assert_eq!(storage.apply_batch(WriteBatch::default()), Ok(()));
+        }
+
+        #[test]
+        fn batch_creates_objects_before_writes() {
+            let mut storage = SledBackendStorage::default();
+
+            let mut batch = WriteBatch::default();
+            batch.create_namespace("namespace");
+            batch.create_object("namespace", "object_name");
+            batch.put_object_metadata("namespace", "object_name", vec![1, 2, 3]);
+            batch.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            assert_eq!(storage.apply_batch(batch), Ok(()));
+
+            assert_eq!(
+                storage.get_object_metadata("namespace", "object_name"),
+                Ok(Some(vec![1, 2, 3]))
+            );
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(1u8, vec!["123"])]).collect())
+            );
+        }
+
+        #[test]
+        fn batch_drops_objects_after_writes() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name_1");
+            storage
+                .create_object("namespace", "object_name_2")
+                .expect("object created");
+
+            let mut batch = WriteBatch::default();
+            batch.write("namespace", "object_name_1", as_rows(vec![(1u8, vec!["123"])]));
+            batch.drop_object("namespace", "object_name_2");
+            assert_eq!(storage.apply_batch(batch), Ok(()));
+
+            assert_eq!(storage.object_names("namespace"), Ok(vec!["object_name_1".to_owned()]));
+
+            let mut batch = WriteBatch::default();
+            batch.drop_namespace("namespace");
+            assert_eq!(storage.apply_batch(batch), Ok(()));
+
+            assert_eq!(storage.namespaces(), Ok(vec![]));
+        }
+
+        #[test]
+        fn batch_with_existing_object_is_not_applied() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+
+            let mut batch = WriteBatch::default();
+            batch.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            batch.create_object("namespace", "other_object");
+            batch.create_object("namespace", "object_name");
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::ObjectAlreadyExists))
+            );
+
+            assert_eq!(storage.object_names("namespace"), Ok(vec!["object_name".to_owned()]));
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![])
+            );
+        }
+
+        #[test]
+        fn batch_does_not_recreate_dropped_object() {
+            let mut storage = SledBackendStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+
+            let mut batch = WriteBatch::default();
+            batch.drop_object("namespace", "object_name");
+            batch.create_object("namespace", "object_name");
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::ObjectAlreadyExists))
+            );
+
+            let mut batch = WriteBatch::default();
+            batch.drop_object("namespace", "object_name");
+            batch.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn batch_in_not_existent_namespace() {
+            let mut storage = SledBackendStorage::default();
+
+            let mut batch = WriteBatch::default();
+            batch.write("not_existed", "object", as_rows(vec![(1u8, vec!["123"])]));
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+        }
     }
 
     fn create_object(storage: &mut SledBackendStorage, namespace: &str, object_name: &str) {
-        storage
-            .create_namespace(namespace)
-            .expect("no system errors")
-            .expect("namespace created");
-        storage
-            .create_object(namespace, object_name)
-            .expect("no system errors")
-            .expect("object created");
+        storage.create_namespace(namespace).expect("namespace created");
+        storage.create_object(namespace, object_name).expect("object created");
     }
 
     fn as_rows(items: Vec<(u8, Vec<&'static str>)>) -> Vec<Row> {
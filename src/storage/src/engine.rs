@@ -0,0 +1,346 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    backend::{
+        BackendStorage, Key, KeyCursor, KeyRange, ReadCursor, Row, SledBackendStorage, StorageResult, Values,
+        WriteBatch,
+    },
+    memory::MemoryBackendStorage,
+    wal::{WalBackendStorage, DEFAULT_CHECKPOINT_TIMEOUT, DEFAULT_MAX_WAL_SIZE},
+};
+use kernel::SystemResult;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Write ahead log of storage that keeps its data in a directory
+pub const WAL_FILE: &str = "wal";
+
+/// Backend storage that is chosen when the database starts
+pub type BoxedBackendStorage = Box<dyn BackendStorage + Send + Sync>;
+
+type Factory = Box<dyn Fn(&StorageConfig) -> SystemResult<BoxedBackendStorage> + Send + Sync>;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StorageConfig {
+    data_directory: Option<PathBuf>,
+    checkpoint_timeout: Option<Duration>,
+    max_wal_size: Option<u64>,
+}
+
+impl StorageConfig {
+    /// storage that keeps its data in a directory, otherwise data is temporary
+    pub fn with_data_directory<D: Into<PathBuf>>(mut self, data_directory: D) -> Self {
+        self.data_directory = Some(data_directory.into());
+        self
+    }
+
+    pub fn data_directory(&self) -> Option<&Path> {
+        self.data_directory.as_deref()
+    }
+
+    /// time between checkpoints of the write ahead log
+    pub fn with_checkpoint_timeout(mut self, checkpoint_timeout: Duration) -> Self {
+        self.checkpoint_timeout = Some(checkpoint_timeout);
+        self
+    }
+
+    pub fn checkpoint_timeout(&self) -> Duration {
+        self.checkpoint_timeout.unwrap_or(DEFAULT_CHECKPOINT_TIMEOUT)
+    }
+
+    /// size of the write ahead log that triggers a checkpoint
+    pub fn with_max_wal_size(mut self, max_wal_size: u64) -> Self {
+        self.max_wal_size = Some(max_wal_size);
+        self
+    }
+
+    pub fn max_wal_size(&self) -> u64 {
+        self.max_wal_size.unwrap_or(DEFAULT_MAX_WAL_SIZE)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownStorageEngine(pub String);
+
+/// Registry of backend storages that can be opened by name. `sled` and
+/// `memory` are registered by default, other engines, e.g. `rocksdb`, have to
+/// be registered by whoever links them in.
+pub struct StorageEngine {
+    factories: HashMap<String, Factory>,
+}
+
+impl Default for StorageEngine {
+    fn default() -> Self {
+        let mut engine = Self {
+            factories: HashMap::new(),
+        };
+        engine.register("sled", |config| match config.data_directory() {
+            Some(data_directory) => {
+                let sled = SledBackendStorage::persistent(data_directory)?;
+                Ok(Box::new(
+                    WalBackendStorage::open(sled, data_directory.join(WAL_FILE))?
+                        .with_checkpoint_timeout(config.checkpoint_timeout())
+                        .with_max_wal_size(config.max_wal_size()),
+                ))
+            }
+            None => Ok(Box::new(SledBackendStorage::default())),
+        });
+        engine.register("memory", |_config| Ok(Box::new(MemoryBackendStorage::default())));
+        engine
+    }
+}
+
+impl StorageEngine {
+    /// opens one of the engines that are registered by default
+    pub fn open(name: &str, config: &StorageConfig) -> SystemResult<Result<BoxedBackendStorage, UnknownStorageEngine>> {
+        Self::default().create(name, config)
+    }
+
+    /// engine that was registered with the same name is replaced
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&StorageConfig) -> SystemResult<BoxedBackendStorage> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_lowercase(), Box::new(factory));
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self.factories.keys().map(String::as_str).collect::<Vec<&str>>();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn create(
+        &self,
+        name: &str,
+        config: &StorageConfig,
+    ) -> SystemResult<Result<BoxedBackendStorage, UnknownStorageEngine>> {
+        match self.factories.get(&name.to_lowercase()) {
+            Some(factory) => factory(config).map(Ok),
+            None => Ok(Err(UnknownStorageEngine(name.to_owned()))),
+        }
+    }
+}
+
+impl BackendStorage for BoxedBackendStorage {
+    fn create_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        (**self).create_namespace(namespace)
+    }
+
+    fn drop_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        (**self).drop_namespace(namespace)
+    }
+
+    fn create_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        (**self).create_object(namespace, object_name)
+    }
+
+    fn drop_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        (**self).drop_object(namespace, object_name)
+    }
+
+    fn namespaces(&self) -> StorageResult<Vec<String>> {
+        (**self).namespaces()
+    }
+
+    fn object_names(&self, namespace: &str) -> StorageResult<Vec<String>> {
+        (**self).object_names(namespace)
+    }
+
+    fn write(&mut self, namespace: &str, object_name: &str, values: Vec<Row>) -> StorageResult<usize> {
+        (**self).write(namespace, object_name, values)
+    }
+
+    fn update(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize> {
+        (**self).update(namespace, object_name, rows)
+    }
+
+    fn cas(
+        &mut self,
+        namespace: &str,
+        object_name: &str,
+        key: Key,
+        expected: Option<Values>,
+        new: Option<Values>,
+    ) -> StorageResult<Result<(), Option<Values>>> {
+        (**self).cas(namespace, object_name, key, expected, new)
+    }
+
+    fn read(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        (**self).read(namespace, object_name)
+    }
+
+    fn read_range(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        (**self).read_range(namespace, object_name, range)
+    }
+
+    fn read_prefix(&self, namespace: &str, object_name: &str, prefix: &[u8]) -> StorageResult<ReadCursor> {
+        (**self).read_prefix(namespace, object_name, prefix)
+    }
+
+    fn read_reverse(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        (**self).read_reverse(namespace, object_name)
+    }
+
+    fn read_range_reverse(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        (**self).read_range_reverse(namespace, object_name, range)
+    }
+
+    fn lookup(&self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<ReadCursor> {
+        (**self).lookup(namespace, object_name, keys)
+    }
+
+    fn read_keys(&self, namespace: &str, object_name: &str) -> StorageResult<KeyCursor> {
+        (**self).read_keys(namespace, object_name)
+    }
+
+    fn count(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<usize> {
+        (**self).count(namespace, object_name, range)
+    }
+
+    fn delete(&mut self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<usize> {
+        (**self).delete(namespace, object_name, keys)
+    }
+
+    fn apply_batch(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        (**self).apply_batch(batch)
+    }
+
+    fn put_object_metadata(&mut self, namespace: &str, object_name: &str, metadata: Values) -> StorageResult<()> {
+        (**self).put_object_metadata(namespace, object_name, metadata)
+    }
+
+    fn get_object_metadata(&self, namespace: &str, object_name: &str) -> StorageResult<Option<Values>> {
+        (**self).get_object_metadata(namespace, object_name)
+    }
+
+    fn object_size(&self, namespace: &str, object_name: &str) -> StorageResult<u64> {
+        (**self).object_size(namespace, object_name)
+    }
+
+    fn size(&self) -> StorageResult<u64> {
+        (**self).size()
+    }
+
+    fn flush(&mut self) -> StorageResult<()> {
+        (**self).flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frontend::FrontendStorage,
+        wal::{Operation, WriteAheadLog},
+    };
+
+    #[test]
+    fn built_in_engines() {
+        assert_eq!(StorageEngine::default().names(), vec!["memory", "sled"]);
+    }
+
+    #[test]
+    fn open_memory_engine() {
+        let mut storage = StorageEngine::open("memory", &StorageConfig::default())
+            .expect("no system errors")
+            .expect("engine is registered");
+
+        storage.create_namespace("namespace").expect("namespace is created");
+        storage
+            .create_object("namespace", "object_name")
+            .expect("object is created");
+        storage
+            .write("namespace", "object_name", vec![(vec![1], vec![1])])
+            .expect("values are written");
+
+        assert_eq!(
+            storage
+                .read("namespace", "object_name")
+                .map(|rows| rows.map(Result::unwrap).collect::<Vec<Row>>()),
+            Ok(vec![(vec![1], vec![1])])
+        );
+    }
+
+    #[test]
+    fn unknown_engine() {
+        assert_eq!(
+            StorageEngine::open("rocksdb", &StorageConfig::default())
+                .expect("no system errors")
+                .err(),
+            Some(UnknownStorageEngine("rocksdb".to_owned()))
+        );
+    }
+
+    #[test]
+    fn registered_engine() {
+        let mut engine = StorageEngine::default();
+        engine.register("rocksdb", |_config| Ok(Box::new(MemoryBackendStorage::default())));
+
+        assert!(matches!(engine.create("RocksDB", &StorageConfig::default()), Ok(Ok(_))));
+    }
+
+    #[test]
+    fn frontend_over_boxed_backend() {
+        let backend = StorageEngine::open("sled", &StorageConfig::default())
+            .expect("no system errors")
+            .expect("engine is registered");
+        let mut storage = FrontendStorage::new(backend).expect("no system errors");
+
+        assert_eq!(storage.create_schema("schema_name").expect("no system errors"), Ok(()));
+    }
+
+    #[test]
+    fn sled_in_data_directory_records_operations_in_log() {
+        let data_directory = std::env::temp_dir().join(format!("database_engine_wal_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_directory);
+        {
+            let mut storage =
+                StorageEngine::open("sled", &StorageConfig::default().with_data_directory(&data_directory))
+                    .expect("no system errors")
+                    .expect("engine is registered");
+            storage.create_namespace("namespace").expect("namespace is created");
+        }
+
+        let (_log, operations) = WriteAheadLog::open(data_directory.join(WAL_FILE)).expect("log is opened");
+        assert_eq!(operations, vec![Operation::CreateNamespace("namespace".to_owned())]);
+        std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+    }
+
+    #[test]
+    fn log_over_configured_size_is_truncated() {
+        let data_directory = std::env::temp_dir().join(format!("database_engine_wal_size_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_directory);
+        {
+            let config = StorageConfig::default()
+                .with_data_directory(&data_directory)
+                .with_max_wal_size(16);
+            let mut storage = StorageEngine::open("sled", &config)
+                .expect("no system errors")
+                .expect("engine is registered");
+            storage.create_namespace("namespace").expect("namespace is created");
+            storage
+                .create_object("namespace", "object_name")
+                .expect("object is created");
+        }
+
+        let (_log, operations) = WriteAheadLog::open(data_directory.join(WAL_FILE)).expect("log is opened");
+        assert_eq!(operations, vec![]);
+        std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+    }
+}
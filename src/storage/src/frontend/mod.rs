@@ -14,62 +14,215 @@
 
 use crate::{
     backend::{
-        self, BackendStorage, CreateObjectError, DropObjectError, NamespaceAlreadyExists, NamespaceDoesNotExist,
-        OperationOnObjectError, Row, SledBackendStorage,
+        self, BackendStorage, DefinitionError, Key, KeyRange, ReadCursor, Row, SledBackendStorage, StorageError,
+        StorageResult, Values, WriteBatch,
     },
-    CreateTableError, DropTableError, OperationOnTableError, Projection, SchemaAlreadyExists, SchemaDoesNotExist,
+    AlterTableError, Comparison, CreateIndexError, CreateTableError, DefaultValue, DropIndexError, DropTableError,
+    KeyedProjection, OperationOnTableError, Projection, RowChange, SchemaAlreadyExists, SchemaDoesNotExist,
+    SequenceAlteration, SequenceBlock, SequenceDefinition, SequenceError, StreamedProjection, TableAlteration,
+    TableConstraint,
 };
 use kernel::{SystemError, SystemResult};
 use serde::{Deserialize, Serialize};
-use sql_types::{ConstraintError, SqlType};
-use std::collections::HashMap;
+use sql_types::SqlType;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    mem::size_of,
+    ops::Bound,
+    path::Path,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// identifier of a schema, table or sequence in the catalog
+pub type Oid = u64;
+
+type Columns = Vec<(String, SqlType)>;
+
+// ids below are reserved for system objects like in PostgreSQL
+const FIRST_OBJECT_ID: Oid = 16384;
+
+// value of a column that is null. Serializers never produce it, numbers have
+// at least two bytes and text is valid UTF-8 that can't have 0xFF
+const NULL: [u8; 1] = [0xFF];
 
 pub struct FrontendStorage<P: BackendStorage> {
-    key_id_generator: usize,
     persistent: P,
+    // changes of table rows are written to the undo log only while it is
+    // set, it is the key of the next change
+    journal: Option<u64>,
+    journal_cleared: Arc<JournalCleared>,
+}
+
+/// sessions wait on it for a transaction block of another session to clear
+/// the undo log instead of checking storage over and over
+#[derive(Debug, Default)]
+pub struct JournalCleared {
+    lock: Mutex<()>,
+    condvar: Condvar,
+}
+
+impl JournalCleared {
+    /// the guard is taken while storage is still locked, so the undo log
+    /// can't be cleared before the session starts waiting
+    pub fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap()
+    }
+
+    /// waits until the undo log is cleared or the timeout elapses, wakeups
+    /// could be spurious, so storage has to be checked again
+    pub fn wait<'g>(&self, guard: MutexGuard<'g, ()>, timeout: Option<Duration>) -> MutexGuard<'g, ()> {
+        match timeout {
+            None => self.condvar.wait(guard).unwrap(),
+            Some(timeout) => self.condvar.wait_timeout(guard, timeout).unwrap().0,
+        }
+    }
+
+    fn notify(&self) {
+        let _guard = self.lock();
+        self.condvar.notify_all();
+    }
 }
 
 impl FrontendStorage<SledBackendStorage> {
+    // opening storage can fail, so it can't be Default
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> SystemResult<Self> {
         Self::new(SledBackendStorage::default())
     }
+
+    /// opens storage that keeps its data in the directory
+    pub fn persistent<D: AsRef<Path>>(data_directory: D) -> SystemResult<Self> {
+        Self::new(SledBackendStorage::persistent(data_directory)?)
+    }
 }
 
 impl<P: BackendStorage> FrontendStorage<P> {
     pub fn new(mut persistent: P) -> SystemResult<Self> {
-        match persistent.create_namespace("system")? {
-            Ok(()) => match persistent.create_object("system", "columns")? {
-                Ok(()) => Ok(Self {
-                    key_id_generator: 0,
+        match split(persistent.create_namespace("system"))? {
+            Ok(()) => {
+                for catalog in &["schemas", "objects", "columns", "undo"] {
+                    if let Err(error) = split(persistent.create_object("system", catalog))? {
+                        // something that can't be possible
+                        return Err(SystemError::unrecoverable(format!(
+                            "system table '{}' can't be created due to {:?}",
+                            catalog, error
+                        )));
+                    }
+                }
+                Ok(Self {
                     persistent,
-                }),
-                Err(CreateObjectError::NamespaceDoesNotExist) => {
-                    // something that can't be possible
-                    Err(SystemError::unrecoverable("system namespace does not exist".to_owned()))
-                }
-                Err(CreateObjectError::ObjectAlreadyExists) => Err(SystemError::unrecoverable(
-                    // something that can't be possible
-                    "system table 'columns' already exists".to_owned(),
-                )),
-            },
-            Err(NamespaceAlreadyExists) => {
-                Err(SystemError::unrecoverable("system namespace already exists".to_owned()))
+                    journal: None,
+                    journal_cleared: Arc::default(),
+                })
+            }
+            // storage was opened from a directory where it was created before
+            Err(DefinitionError::NamespaceAlreadyExists) => {
+                // directories created before the undo log was introduced do not have it
+                match split(persistent.create_object("system", "undo"))? {
+                    Ok(()) | Err(DefinitionError::ObjectAlreadyExists) => {}
+                    Err(error) => return Err(catalog_error("undo", error)),
+                }
+                let mut storage = Self {
+                    persistent,
+                    journal: None,
+                    journal_cleared: Arc::default(),
+                };
+                // changes of a transaction block that was not ended before shutdown
+                storage.revert()?;
+                storage.reconcile()?;
+                Ok(storage)
             }
+            Err(error) => Err(SystemError::unrecoverable(format!(
+                "system namespace can't be created due to {:?}",
+                error
+            ))),
         }
     }
 
     pub fn create_schema(&mut self, schema_name: &str) -> SystemResult<Result<(), SchemaAlreadyExists>> {
-        match self.persistent.create_namespace(schema_name)? {
-            Ok(()) => Ok(Ok(())),
-            Err(NamespaceAlreadyExists) => Ok(Err(SchemaAlreadyExists)),
+        let span = tracing::debug_span!("create_schema", schema_name);
+        let _enter = span.enter();
+        if self.schema_id(schema_name)?.is_some() {
+            return Ok(Err(SchemaAlreadyExists));
         }
+        let schema_id = self.next_id()?;
+        // schema is visible once it is recorded in the catalog
+        let mut batch = WriteBatch::default();
+        batch.create_namespace(&schema_id.to_string());
+        batch.write(
+            "system",
+            "schemas",
+            vec![(schema_name.as_bytes().to_vec(), schema_id.to_be_bytes().to_vec())],
+        );
+        split(self.persistent.apply_batch(batch))?.map_err(|error| {
+            SystemError::unrecoverable(format!("{} schema can't be created due to {:?}", schema_name, error))
+        })?;
+        Ok(Ok(()))
     }
 
     pub fn drop_schema(&mut self, schema_name: &str) -> SystemResult<Result<(), SchemaDoesNotExist>> {
-        match self.persistent.drop_namespace(schema_name)? {
-            Ok(()) => Ok(Ok(())),
-            Err(NamespaceDoesNotExist) => Ok(Err(SchemaDoesNotExist)),
-        }
+        let span = tracing::debug_span!("drop_schema", schema_name);
+        let _enter = span.enter();
+        let schema_id = match self.schema_id(schema_name)? {
+            Some(schema_id) => schema_id,
+            None => return Ok(Err(SchemaDoesNotExist)),
+        };
+        let objects: Vec<Row> = split(
+            self.persistent
+                .read_prefix("system", "objects", &schema_id.to_be_bytes()),
+        )?
+        .map_err(|error| catalog_error("objects", error))?
+        .collect::<SystemResult<_>>()?;
+        let mut batch = WriteBatch::default();
+        batch.delete("system", "schemas", vec![schema_name.as_bytes().to_vec()]);
+        batch.delete(
+            "system",
+            "objects",
+            objects.iter().map(|(key, _id)| key.clone()).collect(),
+        );
+        batch.delete("system", "columns", objects.into_iter().map(|(_key, id)| id).collect());
+        batch.drop_namespace(&schema_id.to_string());
+        split(self.persistent.apply_batch(batch))?.map_err(|error| {
+            SystemError::unrecoverable(format!("{} schema can't be dropped due to {:?}", schema_name, error))
+        })?;
+        Ok(Ok(()))
+    }
+
+    /// names of schemas that are recorded in the catalog
+    pub fn schema_names(&self) -> SystemResult<Vec<String>> {
+        let span = tracing::debug_span!("schema_names");
+        let _enter = span.enter();
+        split(self.persistent.read("system", "schemas"))?
+            .map_err(|error| catalog_error("schemas", error))?
+            .map(|row| row.map(|(name, _id)| String::from_utf8_lossy(&name).into_owned()))
+            .collect()
+    }
+
+    /// names of tables of the schema, sequences are recorded in the catalog
+    /// along with tables but they do not have columns
+    pub fn table_names(&self, schema_name: &str) -> SystemResult<Result<Vec<String>, SchemaDoesNotExist>> {
+        let span = tracing::debug_span!("table_names", schema_name);
+        let _enter = span.enter();
+        let schema_id = match self.schema_id(schema_name)? {
+            Some(schema_id) => schema_id,
+            None => return Ok(Err(SchemaDoesNotExist)),
+        };
+        let prefix = schema_id.to_be_bytes();
+        let objects: Vec<Row> = split(self.persistent.read_prefix("system", "objects", &prefix))?
+            .map_err(|error| catalog_error("objects", error))?
+            .collect::<SystemResult<_>>()?;
+        let ids = objects.iter().map(|(_key, id)| id.clone()).collect();
+        let tables: HashSet<Key> = split(self.persistent.lookup("system", "columns", ids))?
+            .map_err(|error| catalog_error("columns", error))?
+            .map(|row| row.map(|(id, _metadata)| id))
+            .collect::<SystemResult<_>>()?;
+        Ok(Ok(objects
+            .into_iter()
+            .filter(|(_key, id)| tables.contains(id))
+            .map(|(key, _id)| String::from_utf8_lossy(&key[prefix.len()..]).into_owned())
+            .collect()))
     }
 
     pub fn create_table(
@@ -78,76 +231,465 @@ impl<P: BackendStorage> FrontendStorage<P> {
         table_name: &str,
         column_names: Vec<(String, SqlType)>,
     ) -> SystemResult<Result<(), CreateTableError>> {
-        match self.persistent.create_object(schema_name, table_name)? {
-            Ok(()) => {
-                match self.persistent.write(
+        self.create_table_with_constraints(schema_name, table_name, column_names, vec![])
+    }
+
+    /// values of unique constraint columns are checked on insert and update
+    pub fn create_table_with_constraints(
+        &mut self,
+        schema_name: &str,
+        table_name: &str,
+        column_names: Vec<(String, SqlType)>,
+        constraints: Vec<TableConstraint>,
+    ) -> SystemResult<Result<(), CreateTableError>> {
+        let span = tracing::debug_span!("create_table", schema_name, table_name);
+        let _enter = span.enter();
+        let id = match self.new_object_id(schema_name, table_name)? {
+            Ok(id) => id,
+            Err(DefinitionError::NamespaceDoesNotExist) => return Ok(Err(CreateTableError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(CreateTableError::TableAlreadyExists)),
+        };
+        // table is visible once it is recorded in the catalog along with its objects
+        let mut batch = WriteBatch::default();
+        batch.create_object(&id.namespace(), &id.name());
+        if indexed_key(&column_names, &constraints).is_some() {
+            batch.create_object(&id.namespace(), &id.index_name());
+        }
+        let metadata = TableMetadata {
+            columns: column_names
+                .into_iter()
+                .map(|(name, sql_type)| ColumnMetadata {
+                    name,
+                    sql_type,
+                    missing: None,
+                })
+                .collect(),
+            constraints,
+            indexes: vec![],
+        };
+        batch.write("system", "columns", vec![(id.key(), encode_metadata(&metadata)?)]);
+        batch.write(
+            "system",
+            "objects",
+            vec![(catalog_key(id.schema, table_name), id.object.to_be_bytes().to_vec())],
+        );
+        split(self.persistent.apply_batch(batch))?.map_err(|error| {
+            SystemError::unrecoverable(format!(
+                "{}.{} table can't be created due to {:?}",
+                schema_name, table_name, error
+            ))
+        })?;
+        Ok(Ok(()))
+    }
+
+    pub fn table_columns(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> SystemResult<Result<Vec<(String, SqlType)>, OperationOnTableError>> {
+        let span = tracing::debug_span!("table_columns", schema_name, table_name);
+        let _enter = span.enter();
+        match self.table(schema_name, table_name)? {
+            Ok(table) => Ok(Ok(table.columns)),
+            // there are no columns recorded for tables that do not exist
+            Err(_error) => Ok(Ok(vec![])),
+        }
+    }
+
+    /// rules that rows of the table follow, they are described to clients
+    pub fn table_constraints(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> SystemResult<Result<Vec<TableConstraint>, OperationOnTableError>> {
+        match self.table(schema_name, table_name)? {
+            Ok(table) => Ok(Ok(table.constraints)),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    /// values of columns that are not given on insert
+    pub fn column_defaults(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> SystemResult<Result<Vec<(String, DefaultValue)>, OperationOnTableError>> {
+        match self.table(schema_name, table_name)? {
+            Ok(table) => Ok(Ok(table
+                .constraints
+                .into_iter()
+                .filter_map(|constraint| match constraint {
+                    TableConstraint::Default(column, value) => Some((column, value)),
+                    _ => None,
+                })
+                .collect())),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    pub fn drop_table(&mut self, schema_name: &str, table_name: &str) -> SystemResult<Result<(), DropTableError>> {
+        let span = tracing::debug_span!("drop_table", schema_name, table_name);
+        let _enter = span.enter();
+        let id = match self.object_id(schema_name, table_name)? {
+            Ok(id) => id,
+            Err(DefinitionError::NamespaceDoesNotExist) => return Ok(Err(DropTableError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(DropTableError::TableDoesNotExist)),
+        };
+        let table = match self.table(schema_name, table_name)? {
+            Ok(table) => table,
+            Err(_error) => return Ok(Err(DropTableError::TableDoesNotExist)),
+        };
+        let mut batch = WriteBatch::default();
+        batch.delete("system", "objects", vec![catalog_key(id.schema, table_name)]);
+        batch.delete("system", "columns", vec![id.key()]);
+        batch.drop_object(&id.namespace(), &id.name());
+        if indexed_key(&table.columns, &table.constraints).is_some() {
+            batch.drop_object(&id.namespace(), &id.index_name());
+        }
+        // indexes are dropped along with their table
+        batch.delete(
+            "system",
+            "objects",
+            table
+                .indexes
+                .iter()
+                .map(|index| catalog_key(id.schema, &index.name))
+                .collect(),
+        );
+        for index in table.indexes.iter() {
+            batch.drop_object(&id.namespace(), &index.object_name());
+        }
+        split(self.persistent.apply_batch(batch))?.map_err(|error| {
+            SystemError::unrecoverable(format!(
+                "{}.{} table can't be dropped due to {:?}",
+                schema_name, table_name, error
+            ))
+        })?;
+        let owned_sequences = table.constraints.into_iter().filter_map(|constraint| match constraint {
+            TableConstraint::Default(
+                _column,
+                DefaultValue::NextValue {
+                    schema_name,
+                    sequence_name,
+                    owned: true,
+                },
+            ) => Some((schema_name, sequence_name)),
+            _ => None,
+        });
+        for (schema_name, sequence_name) in owned_sequences {
+            if let Err(error) = self.drop_sequence(&schema_name, &sequence_name)? {
+                log::warn!(
+                    "{}.{} sequence of {} table is not dropped due to {:?}",
+                    schema_name,
+                    sequence_name,
+                    table_name,
+                    error
+                );
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    // tables without primary key do not have an index
+    fn drop_primary_key_index(&mut self, id: ObjectId) {
+        match self.persistent.drop_object(&id.namespace(), &id.index_name()) {
+            Ok(()) | Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist)) => {}
+            Err(error) => log::error!("failed to drop index of {} table due to {:?}", id.name(), error),
+        }
+    }
+
+    pub fn alter_table(
+        &mut self,
+        schema_name: &str,
+        table_name: &str,
+        alteration: TableAlteration,
+    ) -> SystemResult<Result<(), AlterTableError>> {
+        let span = tracing::debug_span!("alter_table", schema_name, table_name);
+        let _enter = span.enter();
+        let id = match self.table(schema_name, table_name)? {
+            Ok(table) => table.id,
+            Err(OperationOnTableError::SchemaDoesNotExist) => return Ok(Err(AlterTableError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(AlterTableError::TableDoesNotExist)),
+        };
+        let mut metadata = self.table_metadata(id)?;
+        let position = |metadata: &TableMetadata, column_name: &str| {
+            metadata.columns.iter().position(|column| column.name == column_name)
+        };
+        match alteration {
+            TableAlteration::AddColumn {
+                name,
+                sql_type,
+                constraints,
+            } => {
+                if position(&metadata, &name).is_some() {
+                    return Ok(Err(AlterTableError::ColumnAlreadyExists(name)));
+                }
+                let default = constraints.iter().find_map(|constraint| match constraint {
+                    TableConstraint::Default(_column, DefaultValue::Constant(value)) => Some(value.clone()),
+                    _ => None,
+                });
+                let missing = match &default {
+                    Some(value) => match sql_type.constraint().validate(value) {
+                        Ok(()) => Some(sql_type.serializer().ser(value)),
+                        Err(_error) => return Ok(Err(AlterTableError::InvalidDefault(value.clone()))),
+                    },
+                    None => None,
+                };
+                // rows are not rewritten, existing ones take the default value
+                // that has to satisfy constraints of the column
+                let has_rows = match split(self.persistent.read(&id.namespace(), &id.name()))? {
+                    Ok(mut rows) => rows.next().is_some(),
+                    Err(error) => return Ok(Err(alter_error(error))),
+                };
+                if has_rows {
+                    let given: Vec<(&String, SqlType, &str)> =
+                        default.iter().map(|value| (&name, sql_type, value.as_str())).collect();
+                    if let Some(column) = not_null_violation(&constraints, &given) {
+                        return Ok(Err(AlterTableError::NotNullViolation(column)));
+                    }
+                    if let Some(constraint) = check_violation(&constraints, &given) {
+                        return Ok(Err(AlterTableError::CheckViolation(constraint)));
+                    }
+                }
+                metadata.columns.push(ColumnMetadata {
+                    name,
+                    sql_type,
+                    missing,
+                });
+                metadata.constraints.extend(constraints);
+                self.write_table_metadata(id, &metadata)?;
+            }
+            TableAlteration::DropColumn(column) => {
+                let index = match position(&metadata, &column) {
+                    Some(index) => index,
+                    None => return Ok(Err(AlterTableError::ColumnDoesNotExist(column))),
+                };
+                let rows: Vec<Row> = match split(self.persistent.read(&id.namespace(), &id.name()))? {
+                    Ok(reads) => reads.collect::<SystemResult<_>>()?,
+                    Err(error) => return Ok(Err(alter_error(error))),
+                };
+                // rows written before the column was added do not have its value
+                let rewritten = rows
+                    .into_iter()
+                    .filter_map(|(key, values)| {
+                        let mut values: Vec<Vec<u8>> = bincode::deserialize(&values).unwrap();
+                        if index < values.len() {
+                            values.remove(index);
+                            Some((key, bincode::serialize(&values).unwrap()))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                // index of primary key is dropped along with its column
+                let drops_index = indexed_key(&metadata.column_types(), &metadata.constraints)
+                    .map(|(key_index, _sql_type)| key_index)
+                    == Some(index);
+                metadata.columns.remove(index);
+                // indexes on the column are dropped along with it
+                let (dropped_indexes, indexes) = metadata
+                    .indexes
+                    .drain(..)
+                    .partition(|index: &IndexMetadata| index.column == column);
+                metadata.indexes = indexes;
+                let dropped_checks: Vec<String> = metadata
+                    .constraints
+                    .iter()
+                    .filter_map(|constraint| match constraint {
+                        TableConstraint::Check(check) if check.column == column => Some(check.name.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                let mut owned_sequences = vec![];
+                metadata.constraints.retain(|constraint| match constraint {
+                    TableConstraint::Unique(unique) => !unique.columns.contains(&column),
+                    TableConstraint::NotNull(name) => *name != column,
+                    TableConstraint::Check(check) => !dropped_checks.contains(&check.name),
+                    TableConstraint::Default(
+                        name,
+                        DefaultValue::NextValue {
+                            schema_name,
+                            sequence_name,
+                            owned: true,
+                        },
+                    ) if *name == column => {
+                        owned_sequences.push((schema_name.clone(), sequence_name.clone()));
+                        false
+                    }
+                    TableConstraint::Default(name, _default) => *name != column,
+                });
+                // rows and the catalog are changed at once, so values always match columns
+                let mut batch = WriteBatch::default();
+                batch.write(&id.namespace(), &id.name(), rewritten);
+                batch.write("system", "columns", vec![(id.key(), encode_metadata(&metadata)?)]);
+                if drops_index {
+                    batch.drop_object(&id.namespace(), &id.index_name());
+                }
+                batch.delete(
                     "system",
-                    "columns",
-                    vec![(
-                        (schema_name.to_owned() + table_name).as_bytes().to_vec(),
-                        column_names
-                            .into_iter()
-                            .map(|(name, sql_type)| bincode::serialize(&ColumnMetadata { name, sql_type }).unwrap())
-                            .collect::<Vec<Vec<u8>>>()
-                            .join(&b'|')
-                            .to_vec(),
-                    )],
-                )? {
-                    Ok(_) => {
-                        log::info!("column data is recorded");
+                    "objects",
+                    dropped_indexes
+                        .iter()
+                        .map(|index: &IndexMetadata| catalog_key(id.schema, &index.name))
+                        .collect(),
+                );
+                for index in dropped_indexes {
+                    batch.drop_object(&id.namespace(), &index.object_name());
+                }
+                split(self.persistent.apply_batch(batch))?.map_err(|error| catalog_error("columns", error))?;
+                for (schema_name, sequence_name) in owned_sequences {
+                    if let Err(error) = self.drop_sequence(&schema_name, &sequence_name)? {
+                        log::warn!(
+                            "{}.{} sequence of {} column is not dropped due to {:?}",
+                            schema_name,
+                            sequence_name,
+                            column,
+                            error
+                        );
                     }
-                    Err(e) => {
-                        let message = format!("something went wrong {:?}", e);
-                        log::error!("{}", message);
-                        return Err(SystemError::unrecoverable(message));
+                }
+            }
+            TableAlteration::RenameColumn { from, to } => {
+                let index = match position(&metadata, &from) {
+                    Some(index) => index,
+                    None => return Ok(Err(AlterTableError::ColumnDoesNotExist(from))),
+                };
+                if position(&metadata, &to).is_some() {
+                    return Ok(Err(AlterTableError::ColumnAlreadyExists(to)));
+                }
+                metadata.columns[index].name = to.clone();
+                for index in metadata.indexes.iter_mut().filter(|index| index.column == from) {
+                    index.column = to.clone();
+                }
+                for constraint in metadata.constraints.iter_mut() {
+                    match constraint {
+                        TableConstraint::Unique(unique) => {
+                            for column in unique.columns.iter_mut().filter(|column| **column == from) {
+                                *column = to.clone();
+                            }
+                        }
+                        TableConstraint::NotNull(column) | TableConstraint::Default(column, _) if *column == from => {
+                            *column = to.clone()
+                        }
+                        TableConstraint::Check(check) if check.column == from => check.column = to.clone(),
+                        _ => {}
                     }
                 }
-                Ok(Ok(()))
+                self.write_table_metadata(id, &metadata)?;
+            }
+            TableAlteration::RenameTo(new_name) => {
+                if self
+                    .find_in_catalog("objects", catalog_key(id.schema, &new_name))?
+                    .is_some()
+                {
+                    return Ok(Err(AlterTableError::TableAlreadyExists));
+                }
+                // backend object is named by the id, so only the catalog is changed
+                let mut batch = WriteBatch::default();
+                batch.delete("system", "objects", vec![catalog_key(id.schema, table_name)]);
+                batch.write(
+                    "system",
+                    "objects",
+                    vec![(catalog_key(id.schema, &new_name), id.object.to_be_bytes().to_vec())],
+                );
+                split(self.persistent.apply_batch(batch))?.map_err(|error| catalog_error("objects", error))?;
             }
-            Err(CreateObjectError::ObjectAlreadyExists) => Ok(Err(CreateTableError::TableAlreadyExists)),
-            Err(CreateObjectError::NamespaceDoesNotExist) => Ok(Err(CreateTableError::SchemaDoesNotExist)),
         }
+        Ok(Ok(()))
     }
 
-    pub fn table_columns(
+    /// index maps values of the column to rows, so rows with the value are
+    /// looked up instead of reading the whole table. It is filled with
+    /// entries of existing rows in the same batch it is recorded in the catalog
+    pub fn create_index(
         &mut self,
         schema_name: &str,
         table_name: &str,
-    ) -> SystemResult<Result<Vec<(String, SqlType)>, OperationOnTableError>> {
-        self.persistent
-            .read("system", "columns")?
-            .map(|reads| {
-                Ok(reads
-                    .map(backend::Result::unwrap)
-                    .filter(|(table, _columns)| *table == (schema_name.to_owned() + table_name).as_bytes().to_vec())
-                    .map(|(_id, columns)| {
-                        columns
-                            .split(|b| *b == b'|')
-                            .filter(|v| !v.is_empty())
-                            .map(|c| {
-                                let ColumnMetadata { name, sql_type } = bincode::deserialize(c).unwrap();
-                                (name, sql_type)
-                            })
-                            .collect::<Vec<(String, SqlType)>>()
-                    })
-                    .next()
-                    .unwrap_or_default())
-            })
-            // is not covered yet
-            .map_err(|err| {
-                SystemError::unrecoverable(format!(
-                    "failed to read table's columns from system.columns due to {:?}",
-                    err
-                ))
-            })
+        index_name: &str,
+        column_name: &str,
+    ) -> SystemResult<Result<(), CreateIndexError>> {
+        let span = tracing::debug_span!("create_index", schema_name, table_name, index_name);
+        let _enter = span.enter();
+        let table = match self.table(schema_name, table_name)? {
+            Ok(table) => table,
+            Err(OperationOnTableError::SchemaDoesNotExist) => return Ok(Err(CreateIndexError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(CreateIndexError::TableDoesNotExist)),
+        };
+        if table.columns.iter().all(|(name, _sql_type)| name != column_name) {
+            return Ok(Err(CreateIndexError::ColumnDoesNotExist(column_name.to_owned())));
+        }
+        let id = match self.new_object_id(schema_name, index_name)? {
+            Ok(id) => id,
+            Err(DefinitionError::NamespaceDoesNotExist) => return Ok(Err(CreateIndexError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(CreateIndexError::IndexAlreadyExists)),
+        };
+        let index = IndexMetadata {
+            name: index_name.to_owned(),
+            column: column_name.to_owned(),
+            object: id.object,
+        };
+        let rows: Vec<Row> = match split(self.persistent.read(&table.id.namespace(), &table.id.name()))? {
+            Ok(reads) => reads.collect::<SystemResult<_>>()?,
+            Err(DefinitionError::NamespaceDoesNotExist) => return Ok(Err(CreateIndexError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(CreateIndexError::TableDoesNotExist)),
+        };
+        let entries = index_entries(&table.columns, &table.missing, &index, &rows);
+        let mut metadata = self.table_metadata(table.id)?;
+        metadata.indexes.push(index);
+        let mut batch = WriteBatch::default();
+        batch.create_object(&id.namespace(), &id.name());
+        batch.write(&id.namespace(), &id.name(), entries);
+        batch.write("system", "columns", vec![(table.id.key(), encode_metadata(&metadata)?)]);
+        batch.write(
+            "system",
+            "objects",
+            vec![(catalog_key(id.schema, index_name), id.object.to_be_bytes().to_vec())],
+        );
+        split(self.persistent.apply_batch(batch))?.map_err(|error| {
+            SystemError::unrecoverable(format!(
+                "{}.{} index can't be created due to {:?}",
+                schema_name, index_name, error
+            ))
+        })?;
+        Ok(Ok(()))
     }
 
-    pub fn drop_table(&mut self, schema_name: &str, table_name: &str) -> SystemResult<Result<(), DropTableError>> {
-        match self.persistent.drop_object(schema_name, table_name)? {
-            Ok(()) => Ok(Ok(())),
-            Err(DropObjectError::ObjectDoesNotExist) => Ok(Err(DropTableError::TableDoesNotExist)),
-            Err(DropObjectError::NamespaceDoesNotExist) => Ok(Err(DropTableError::SchemaDoesNotExist)),
+    pub fn drop_index(&mut self, schema_name: &str, index_name: &str) -> SystemResult<Result<(), DropIndexError>> {
+        let span = tracing::debug_span!("drop_index", schema_name, index_name);
+        let _enter = span.enter();
+        let id = match self.object_id(schema_name, index_name)? {
+            Ok(id) => id,
+            Err(DefinitionError::NamespaceDoesNotExist) => return Ok(Err(DropIndexError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(DropIndexError::IndexDoesNotExist)),
+        };
+        // index is recorded only in metadata of its table
+        let mut owner = None;
+        for table_name in self.table_names(schema_name)?.unwrap_or_default() {
+            if let Ok(table) = self.table(schema_name, &table_name)? {
+                if table.indexes.iter().any(|index| index.object == id.object) {
+                    owner = Some(table.id);
+                    break;
+                }
+            }
         }
+        let table = match owner {
+            Some(table) => table,
+            // names of tables and sequences are not names of indexes
+            None => return Ok(Err(DropIndexError::IndexDoesNotExist)),
+        };
+        let mut metadata = self.table_metadata(table)?;
+        metadata.indexes.retain(|index| index.object != id.object);
+        let mut batch = WriteBatch::default();
+        batch.delete("system", "objects", vec![catalog_key(id.schema, index_name)]);
+        batch.write("system", "columns", vec![(table.key(), encode_metadata(&metadata)?)]);
+        batch.drop_object(&id.namespace(), &id.name());
+        split(self.persistent.apply_batch(batch))?.map_err(|error| {
+            SystemError::unrecoverable(format!(
+                "{}.{} index can't be dropped due to {:?}",
+                schema_name, index_name, error
+            ))
+        })?;
+        Ok(Ok(()))
     }
 
     pub fn insert_into(
@@ -157,8 +699,16 @@ impl<P: BackendStorage> FrontendStorage<P> {
         columns: Vec<String>,
         rows: Vec<Vec<String>>,
     ) -> SystemResult<Result<(), OperationOnTableError>> {
-        match self.table_columns(schema_name, table_name)? {
-            Ok(all_columns) => {
+        let span = tracing::debug_span!("insert_into", schema_name, table_name);
+        let _enter = span.enter();
+        match self.table(schema_name, table_name)? {
+            Ok(TableDefinition {
+                id,
+                columns: all_columns,
+                constraints,
+                missing,
+                indexes,
+            }) => {
                 let index_columns = if columns.is_empty() {
                     let mut index_cols = vec![];
                     for (index, (name, sql_type)) in all_columns.iter().enumerate() {
@@ -193,64 +743,98 @@ impl<P: BackendStorage> FrontendStorage<P> {
                     index_cols
                 };
 
+                let first_key_id = match self.next_key_id(id)? {
+                    Ok(key_id) => key_id,
+                    Err(error) => return Ok(Err(table_error(error))),
+                };
+                let primary_key = indexed_key(&all_columns, &constraints);
                 let mut to_write: Vec<Row> = vec![];
+                let mut key_entries: Vec<Row> = vec![];
                 let mut errors = HashMap::new();
-                for row in rows {
-                    let key = self.key_id_generator.to_be_bytes().to_vec();
-
-                    // TODO: The default value or NULL should be initialized for SQL types of all columns.
-                    let mut record = vec![vec![0, 0]; all_columns.len()];
-                    let mut out_of_range = vec![];
-                    let mut not_an_int = vec![];
-                    let mut value_too_long = vec![];
+                let mut violation = None;
+                for (key_id, row) in (first_key_id..).zip(rows) {
+                    let key = key_id.to_be_bytes().to_vec();
+                    if violation.is_none() {
+                        let given: Vec<(&String, SqlType, &str)> = row
+                            .iter()
+                            .zip(index_columns.iter())
+                            .map(|(item, (_index, name, sql_type))| (name, *sql_type, item.as_str()))
+                            .collect();
+                        violation = not_null_violation(&constraints, &given)
+                            .map(OperationOnTableError::NotNullViolation)
+                            .or_else(|| {
+                                check_violation(&constraints, &given).map(OperationOnTableError::CheckViolation)
+                            });
+                    }
+
+                    // columns that are not given are null
+                    let mut record = vec![NULL.to_vec(); all_columns.len()];
+                    let mut violations = HashMap::new();
                     for (item, (index, name, sql_type)) in row.iter().zip(index_columns.iter()) {
                         match sql_type.constraint().validate(item.as_str()) {
                             Ok(()) => {
                                 record[*index] = sql_type.serializer().ser(item.as_str());
                             }
-                            Err(ConstraintError::OutOfRange) => {
-                                out_of_range.push((name.clone(), *sql_type));
-                            }
-                            Err(ConstraintError::NotAnInt) => {
-                                not_an_int.push((name.clone(), *sql_type));
-                            }
-                            Err(ConstraintError::ValueTooLong) => {
-                                value_too_long.push((name.clone(), *sql_type));
-                            }
+                            Err(error) => violations
+                                .entry(error)
+                                .or_insert_with(Vec::new)
+                                .push((name.clone(), *sql_type)),
                         }
                     }
-                    if !out_of_range.is_empty() {
-                        errors
-                            .entry(ConstraintError::OutOfRange)
-                            .or_insert_with(Vec::new)
-                            .push(out_of_range);
+                    for (error, columns) in violations {
+                        errors.entry(error).or_insert_with(Vec::new).push(columns);
                     }
-                    if !not_an_int.is_empty() {
-                        errors
-                            .entry(ConstraintError::NotAnInt)
-                            .or_insert_with(Vec::new)
-                            .push(not_an_int);
+                    if let Some((index, sql_type)) = primary_key {
+                        key_entries.push((index_key(sql_type, &record[index]), key.clone()));
                     }
-                    if !value_too_long.is_empty() {
-                        errors
-                            .entry(ConstraintError::ValueTooLong)
-                            .or_insert_with(Vec::new)
-                            .push(value_too_long);
-                    }
-                    to_write.push((key, record.join(&b'|')));
-                    self.key_id_generator += 1;
+                    to_write.push((key, bincode::serialize(&record).unwrap()));
                 }
                 if !errors.is_empty() {
                     return Ok(Err(OperationOnTableError::ConstraintViolation(errors)));
                 }
-                match self.persistent.write(schema_name, table_name, to_write)? {
-                    Ok(_size) => Ok(Ok(())),
-                    Err(OperationOnObjectError::ObjectDoesNotExist) => {
-                        Ok(Err(OperationOnTableError::TableDoesNotExist))
+                if let Some(violation) = violation {
+                    return Ok(Err(violation));
+                }
+                if constraints
+                    .iter()
+                    .any(|constraint| matches!(constraint, TableConstraint::Unique(_)))
+                {
+                    let existing = match split(self.persistent.read(&id.namespace(), &id.name()))? {
+                        Ok(reads) => reads.collect::<SystemResult<Vec<Row>>>()?,
+                        Err(error) => return Ok(Err(table_error(error))),
+                    };
+                    if let Some(constraint) =
+                        unique_violation(&all_columns, &missing, &constraints, existing, &to_write)
+                    {
+                        return Ok(Err(OperationOnTableError::UniqueViolation(constraint)));
                     }
-                    Err(OperationOnObjectError::NamespaceDoesNotExist) => {
-                        Ok(Err(OperationOnTableError::SchemaDoesNotExist))
+                }
+                if self.journal.is_some() {
+                    let keys = to_write.iter().map(|(key, _values)| key.clone()).collect();
+                    self.journal(RowChange::Inserted {
+                        namespace: id.namespace(),
+                        object_name: id.name(),
+                        keys,
+                    })?;
+                }
+                let written = if primary_key.is_some() || !indexes.is_empty() {
+                    // rows and their index entries are written at once
+                    let mut batch = WriteBatch::default();
+                    if primary_key.is_some() {
+                        self.change_entries(&mut batch, id, &id.index_name(), vec![], key_entries)?;
                     }
+                    for index in indexes.iter() {
+                        let entries = index_entries(&all_columns, &missing, index, &to_write);
+                        self.change_entries(&mut batch, id, &index.object_name(), vec![], entries)?;
+                    }
+                    batch.write(&id.namespace(), &id.name(), to_write);
+                    split(self.persistent.apply_batch(batch))?
+                } else {
+                    split(self.persistent.write(&id.namespace(), &id.name(), to_write))?.map(|_size| ())
+                };
+                match written {
+                    Ok(()) => Ok(Ok(())),
+                    Err(error) => Ok(Err(table_error(error))),
                 }
             }
             Err(e) => Ok(Err(e)),
@@ -258,61 +842,86 @@ impl<P: BackendStorage> FrontendStorage<P> {
     }
 
     pub fn select_all_from(
-        &mut self,
+        &self,
         schema_name: &str,
         table_name: &str,
         columns: Vec<String>,
     ) -> SystemResult<Result<Projection, OperationOnTableError>> {
-        match self.table_columns(schema_name, table_name)? {
-            Ok(all_columns) => {
+        match self.select_stream(schema_name, table_name, columns)? {
+            Ok((description, records)) => Ok(Ok((description, records.collect::<SystemResult<_>>()?))),
+            Err(e) => Ok(Err(e)),
+        }
+    }
+
+    /// selected records are read from the table as the cursor is advanced
+    pub fn select_stream(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        columns: Vec<String>,
+    ) -> SystemResult<Result<StreamedProjection, OperationOnTableError>> {
+        self.select_stream_where(schema_name, table_name, columns, vec![])
+    }
+
+    /// only rows which primary key values can satisfy all comparisons of its
+    /// column are read, the others are skipped using the primary key index.
+    /// Without them rows with the value that an indexed column is compared as
+    /// equal to are looked up in its index. Conditions narrow nothing when
+    /// columns are not indexed or a value is not of the column type, so rows
+    /// have to be filtered by the caller anyway
+    pub fn select_stream_where(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        columns: Vec<String>,
+        key_conditions: Vec<(String, Comparison, String)>,
+    ) -> SystemResult<Result<StreamedProjection, OperationOnTableError>> {
+        let span = tracing::debug_span!("select_stream", schema_name, table_name);
+        let _enter = span.enter();
+        match self.table(schema_name, table_name)? {
+            Ok(table) => {
+                let all_columns = &table.columns;
                 let mut description = vec![];
                 let mut column_indexes = vec![];
                 let mut non_existing_columns = vec![];
-                for (i, column) in columns.iter().enumerate() {
+                for column in columns.iter() {
                     let mut found = None;
                     for (index, (name, sql_type)) in all_columns.iter().enumerate() {
                         if name == column {
-                            found = Some(((index, i), (name.clone(), *sql_type)));
+                            found = Some((index, (name.clone(), *sql_type)));
                             break;
                         }
                     }
 
-                    if let Some((index_pair, name_type_pair)) = found {
-                        column_indexes.push(index_pair);
+                    if let Some((index, name_type_pair)) = found {
+                        column_indexes.push(index);
                         description.push(name_type_pair);
                     } else {
                         non_existing_columns.push(column.clone());
                     }
                 }
 
-                let data = match self.persistent.read(schema_name, table_name)? {
+                match self.read_rows(&table, &key_conditions)? {
                     Ok(read) => {
+                        let missing = table.missing;
                         if !non_existing_columns.is_empty() {
                             return Ok(Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)));
                         }
-                        read.map(backend::Result::unwrap)
-                            .map(|(_key, values)| values)
-                            .map(|bytes| {
-                                let mut values = vec![];
-                                for (i, (origin, ord)) in column_indexes.iter().enumerate() {
-                                    for (index, value) in bytes.split(|b| *b == b'|').enumerate() {
-                                        if index == *origin {
-                                            values.push((ord, description[i].1.serializer().des(value)))
-                                        }
-                                    }
-                                }
-                                values.into_iter().map(|(_, value)| value).collect()
-                            })
-                            .collect()
+                        let sql_types: Vec<SqlType> = description.iter().map(|(_name, sql_type)| *sql_type).collect();
+                        let records = read.map(move |row| {
+                            let (_key, bytes) = row?;
+                            let mut record: Vec<Vec<u8>> = bincode::deserialize(&bytes).unwrap();
+                            complete(&mut record, &missing);
+                            Ok(column_indexes
+                                .iter()
+                                .zip(sql_types.iter())
+                                .map(|(origin, sql_type)| decode(*sql_type, &record[*origin]))
+                                .collect())
+                        });
+                        Ok(Ok((description, Box::new(records))))
                     }
-                    Err(OperationOnObjectError::ObjectDoesNotExist) => {
-                        return Ok(Err(OperationOnTableError::TableDoesNotExist))
-                    }
-                    Err(OperationOnObjectError::NamespaceDoesNotExist) => {
-                        return Ok(Err(OperationOnTableError::SchemaDoesNotExist))
-                    }
-                };
-                Ok(Ok((description, data)))
+                    Err(error) => Ok(Err(table_error(error))),
+                }
             }
             Err(e) => Ok(Err(e)),
         }
@@ -324,12 +933,29 @@ impl<P: BackendStorage> FrontendStorage<P> {
         table_name: &str,
         rows: Vec<(String, String)>,
     ) -> SystemResult<Result<usize, OperationOnTableError>> {
-        match self.table_columns(schema_name, table_name)? {
-            Ok(all_columns) => {
+        let span = tracing::debug_span!("update_all", schema_name, table_name);
+        let _enter = span.enter();
+        match self.table(schema_name, table_name)? {
+            Ok(TableDefinition {
+                id,
+                columns: all_columns,
+                constraints,
+                missing,
+                indexes,
+            }) => {
+                // columns that are not updated keep values that satisfy constraints
+                let given: Vec<(&String, SqlType, &str)> = rows
+                    .iter()
+                    .filter_map(|(column_name, value)| {
+                        all_columns
+                            .iter()
+                            .find(|(name, _sql_type)| name == column_name)
+                            .map(|(name, sql_type)| (name, *sql_type, value.as_str()))
+                    })
+                    .collect();
+                let violation = check_violation(&constraints, &given);
                 let mut errors = HashMap::new();
-                let mut out_of_range = vec![];
-                let mut not_an_int = vec![];
-                let mut value_too_long = vec![];
+                let mut violations = HashMap::new();
                 let mut index_value_pairs = vec![];
                 let mut non_existing_columns = vec![];
                 for (column_name, value) in rows {
@@ -340,46 +966,26 @@ impl<P: BackendStorage> FrontendStorage<P> {
                                 Ok(()) => {
                                     found = Some((index, sql_type.serializer().ser(value.as_str())));
                                 }
-                                Err(ConstraintError::OutOfRange) => {
-                                    out_of_range.push((name.clone(), *sql_type));
-                                }
-                                Err(ConstraintError::NotAnInt) => {
-                                    not_an_int.push((name.clone(), *sql_type));
-                                }
-                                Err(ConstraintError::ValueTooLong) => {
-                                    value_too_long.push((name.clone(), *sql_type));
-                                }
+                                Err(error) => violations
+                                    .entry(error)
+                                    .or_insert_with(Vec::new)
+                                    .push((name.clone(), *sql_type)),
                             }
                             break;
                         }
                     }
                     if let Some(pair) = found {
                         index_value_pairs.push(pair);
-                    } else if out_of_range.is_empty() && not_an_int.is_empty() && value_too_long.is_empty() {
+                    } else if violations.is_empty() {
                         non_existing_columns.push(column_name.clone());
                     }
                 }
 
-                if !out_of_range.is_empty() {
-                    errors
-                        .entry(ConstraintError::OutOfRange)
-                        .or_insert_with(Vec::new)
-                        .push(out_of_range);
-                }
-                if !not_an_int.is_empty() {
-                    errors
-                        .entry(ConstraintError::NotAnInt)
-                        .or_insert_with(Vec::new)
-                        .push(not_an_int);
-                }
-                if !value_too_long.is_empty() {
-                    errors
-                        .entry(ConstraintError::ValueTooLong)
-                        .or_insert_with(Vec::new)
-                        .push(value_too_long);
+                for (error, columns) in violations {
+                    errors.entry(error).or_insert_with(Vec::new).push(columns);
                 }
 
-                match self.persistent.read(schema_name, table_name)? {
+                match split(self.persistent.read(&id.namespace(), &id.name()))? {
                     Ok(reads) => {
                         if !non_existing_columns.is_empty() {
                             return Ok(Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)));
@@ -387,69 +993,1463 @@ impl<P: BackendStorage> FrontendStorage<P> {
                         if !errors.is_empty() {
                             return Ok(Err(OperationOnTableError::ConstraintViolation(errors)));
                         }
+                        if let Some(constraint) = violation {
+                            return Ok(Err(OperationOnTableError::CheckViolation(constraint)));
+                        }
+                        let reads: Vec<Row> = reads.map(backend::Result::unwrap).collect();
+                        if self.journal.is_some() {
+                            self.journal(RowChange::Overwritten {
+                                namespace: id.namespace(),
+                                object_name: id.name(),
+                                rows: reads.clone(),
+                            })?;
+                        }
                         let to_update: Vec<Row> = reads
-                            .map(backend::Result::unwrap)
+                            .into_iter()
                             .map(|(key, values)| {
-                                let mut values: Vec<&[u8]> = values.split(|b| *b == b'|').collect();
+                                let mut values: Vec<Vec<u8>> = bincode::deserialize(&values).unwrap();
+                                complete(&mut values, &missing);
                                 for (index, updated_value) in &index_value_pairs {
-                                    values[*index] = updated_value;
+                                    values[*index] = updated_value.clone();
                                 }
 
-                                (key, values.join(&b'|'))
+                                (key, bincode::serialize(&values).unwrap())
                             })
                             .collect();
-
-                        let len = to_update.len();
-                        match self.persistent.write(schema_name, table_name, to_update)? {
-                            Ok(_size) => Ok(Ok(len)),
-                            Err(OperationOnObjectError::ObjectDoesNotExist) => {
-                                Ok(Err(OperationOnTableError::TableDoesNotExist))
+                        // all rows are updated, so only new values can violate constraints
+                        if let Some(constraint) =
+                            unique_violation(&all_columns, &missing, &constraints, vec![], &to_update)
+                        {
+                            return Ok(Err(OperationOnTableError::UniqueViolation(constraint)));
+                        }
+                        match indexed_key(&all_columns, &constraints) {
+                            Some((key_index, sql_type))
+                                if index_value_pairs.iter().any(|(index, _value)| *index == key_index) =>
+                            {
+                                let entries = to_update
+                                    .iter()
+                                    .map(|(key, values)| {
+                                        let values: Vec<Vec<u8>> = bincode::deserialize(values).unwrap();
+                                        (index_key(sql_type, &values[key_index]), key.clone())
+                                    })
+                                    .collect();
+                                if let Err(error) = self.reindex(id, &id.index_name(), entries)? {
+                                    return Ok(Err(table_error(error)));
+                                }
                             }
-                            Err(OperationOnObjectError::NamespaceDoesNotExist) => {
-                                Ok(Err(OperationOnTableError::SchemaDoesNotExist))
+                            _ => {}
+                        }
+                        for index in indexes.iter().filter(|index| {
+                            index_value_pairs
+                                .iter()
+                                .any(|(position, _value)| all_columns[*position].0 == index.column)
+                        }) {
+                            let entries = index_entries(&all_columns, &missing, index, &to_update);
+                            if let Err(error) = self.reindex(id, &index.object_name(), entries)? {
+                                return Ok(Err(table_error(error)));
                             }
                         }
+
+                        match split(self.persistent.update(&id.namespace(), &id.name(), to_update))? {
+                            Ok(updated) => Ok(Ok(updated)),
+                            Err(error) => Ok(Err(table_error(error))),
+                        }
                     }
-                    Err(OperationOnObjectError::ObjectDoesNotExist) => {
-                        Ok(Err(OperationOnTableError::TableDoesNotExist))
-                    }
-                    Err(OperationOnObjectError::NamespaceDoesNotExist) => {
-                        Ok(Err(OperationOnTableError::SchemaDoesNotExist))
-                    }
+                    Err(error) => Ok(Err(table_error(error))),
                 }
             }
             Err(e) => Ok(Err(e)),
         }
     }
 
-    pub fn delete_all_from(
+    /// all columns of rows that can satisfy key conditions along with keys of the
+    /// rows, so that the ones that the caller selects can be written back
+    pub fn select_keyed_where(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        key_conditions: Vec<(String, Comparison, String)>,
+    ) -> SystemResult<Result<KeyedProjection, OperationOnTableError>> {
+        let span = tracing::debug_span!("select_keyed_where", schema_name, table_name);
+        let _enter = span.enter();
+        let table = match self.table(schema_name, table_name)? {
+            Ok(definition) => definition,
+            Err(error) => return Ok(Err(error)),
+        };
+        let read = match self.read_rows(&table, &key_conditions)? {
+            Ok(read) => read,
+            Err(error) => return Ok(Err(table_error(error))),
+        };
+        let TableDefinition { columns, missing, .. } = table;
+        let mut records = vec![];
+        for row in read {
+            let (key, bytes) = row?;
+            let mut record: Vec<Vec<u8>> = bincode::deserialize(&bytes).unwrap();
+            complete(&mut record, &missing);
+            let values = columns
+                .iter()
+                .zip(record.iter())
+                .map(|((_name, sql_type), value)| decode(*sql_type, value))
+                .collect();
+            records.push((key, values));
+        }
+        Ok(Ok((columns, records)))
+    }
+
+    /// values of the columns are replaced in rows with the keys, each row is given
+    /// its own values. Rows and entries of their indexes are written at once
+    pub fn update_rows(
         &mut self,
         schema_name: &str,
         table_name: &str,
+        columns: Vec<String>,
+        rows: Vec<(Key, Vec<String>)>,
     ) -> SystemResult<Result<usize, OperationOnTableError>> {
-        let reads = self.persistent.read(schema_name, table_name)?;
+        let span = tracing::debug_span!("update_rows", schema_name, table_name);
+        let _enter = span.enter();
+        let TableDefinition {
+            id,
+            columns: all_columns,
+            constraints,
+            missing,
+            indexes: column_indexes,
+        } = match self.table(schema_name, table_name)? {
+            Ok(definition) => definition,
+            Err(error) => return Ok(Err(error)),
+        };
+        let mut indexes = vec![];
+        let mut non_existing_columns = vec![];
+        for column in columns {
+            match all_columns.iter().position(|(name, _sql_type)| *name == column) {
+                Some(index) => indexes.push(index),
+                None => non_existing_columns.push(column),
+            }
+        }
+        if !non_existing_columns.is_empty() {
+            return Ok(Err(OperationOnTableError::ColumnDoesNotExist(non_existing_columns)));
+        }
 
-        let to_delete: Vec<Vec<u8>> = match reads {
-            Ok(reads) => reads.map(backend::Result::unwrap).map(|(key, _)| key).collect(),
-            Err(OperationOnObjectError::ObjectDoesNotExist) => {
-                return Ok(Err(OperationOnTableError::TableDoesNotExist))
+        let mut errors = HashMap::new();
+        let mut updates = HashMap::new();
+        for (key, values) in rows {
+            let given: Vec<(&String, SqlType, &str)> = indexes
+                .iter()
+                .zip(values.iter())
+                .map(|(index, value)| (&all_columns[*index].0, all_columns[*index].1, value.as_str()))
+                .collect();
+            let mut violations = HashMap::new();
+            let mut encoded = vec![];
+            for (name, sql_type, value) in given {
+                match sql_type.constraint().validate(value) {
+                    Ok(()) => encoded.push(sql_type.serializer().ser(value)),
+                    Err(error) => violations
+                        .entry(error)
+                        .or_insert_with(Vec::new)
+                        .push((name.clone(), sql_type)),
+                }
             }
-            Err(OperationOnObjectError::NamespaceDoesNotExist) => {
-                return Ok(Err(OperationOnTableError::SchemaDoesNotExist))
+            for (error, columns) in violations {
+                errors.entry(error).or_insert_with(Vec::new).push(columns);
             }
+            updates.insert(key, encoded);
+        }
+        if !errors.is_empty() {
+            return Ok(Err(OperationOnTableError::ConstraintViolation(errors)));
+        }
+
+        let keys = updates.keys().cloned().collect();
+        let existing: Vec<Row> = match split(self.persistent.lookup(&id.namespace(), &id.name(), keys))? {
+            Ok(reads) => reads.collect::<SystemResult<_>>()?,
+            Err(error) => return Ok(Err(table_error(error))),
         };
+        if existing.is_empty() {
+            return Ok(Ok(0));
+        }
+        let to_update: Vec<Row> = existing
+            .iter()
+            .map(|(key, values)| {
+                let mut values: Vec<Vec<u8>> = bincode::deserialize(values).unwrap();
+                complete(&mut values, &missing);
+                for (index, value) in indexes.iter().zip(updates[key].iter()) {
+                    values[*index] = value.clone();
+                }
+                (key.clone(), bincode::serialize(&values).unwrap())
+            })
+            .collect();
+        // CHECK constraints are checked against whole rows, columns that are
+        // not assigned keep their stored values
+        for (_key, values) in to_update.iter() {
+            let values: Vec<Vec<u8>> = bincode::deserialize(values).unwrap();
+            let row: Vec<(&String, SqlType, String)> = all_columns
+                .iter()
+                .zip(values.iter())
+                .filter(|((name, _sql_type), value)| is_checked(&constraints, name) && value.as_slice() != NULL)
+                .map(|((name, sql_type), value)| (name, *sql_type, sql_type.serializer().des(value)))
+                .collect();
+            let given: Vec<(&String, SqlType, &str)> = row
+                .iter()
+                .map(|(name, sql_type, value)| (*name, *sql_type, value.as_str()))
+                .collect();
+            if let Some(constraint) = check_violation(&constraints, &given) {
+                return Ok(Err(OperationOnTableError::CheckViolation(constraint)));
+            }
+        }
+        if constraints
+            .iter()
+            .any(|constraint| matches!(constraint, TableConstraint::Unique(_)))
+        {
+            // values of updated rows are compared with the ones of the rows left as they are
+            let others = match split(self.persistent.read(&id.namespace(), &id.name()))? {
+                Ok(reads) => reads
+                    .filter(|row| match row {
+                        Ok((key, _values)) => !updates.contains_key(key),
+                        Err(_) => true,
+                    })
+                    .collect::<SystemResult<Vec<Row>>>()?,
+                Err(error) => return Ok(Err(table_error(error))),
+            };
+            if let Some(constraint) = unique_violation(&all_columns, &missing, &constraints, others, &to_update) {
+                return Ok(Err(OperationOnTableError::UniqueViolation(constraint)));
+            }
+        }
+
+        let mut batch = WriteBatch::default();
+        let primary_key =
+            indexed_key(&all_columns, &constraints).filter(|(key_index, _sql_type)| indexes.contains(key_index));
+        if let Some((key_index, sql_type)) = primary_key {
+            let entry = |(key, values): &Row| {
+                let values: Vec<Vec<u8>> = bincode::deserialize(values).unwrap();
+                (index_key(sql_type, &values[key_index]), key.clone())
+            };
+            let removed: Vec<Row> = existing.iter().map(entry).collect();
+            let added: Vec<Row> = to_update.iter().map(entry).collect();
+            self.change_entries(&mut batch, id, &id.index_name(), removed, added)?;
+        }
+        for index in column_indexes
+            .iter()
+            .filter(|index| indexes.iter().any(|position| all_columns[*position].0 == index.column))
+        {
+            let removed = index_entries(&all_columns, &missing, index, &existing);
+            let added = index_entries(&all_columns, &missing, index, &to_update);
+            self.change_entries(&mut batch, id, &index.object_name(), removed, added)?;
+        }
+        if self.journal.is_some() {
+            self.journal(RowChange::Overwritten {
+                namespace: id.namespace(),
+                object_name: id.name(),
+                rows: existing,
+            })?;
+        }
+        let updated = to_update.len();
+        batch.write(&id.namespace(), &id.name(), to_update);
+        match split(self.persistent.apply_batch(batch))? {
+            Ok(()) => Ok(Ok(updated)),
+            Err(error) => Ok(Err(table_error(error))),
+        }
+    }
 
-        match self.persistent.delete(schema_name, table_name, to_delete)? {
-            Ok(len) => Ok(Ok(len)),
+    pub fn delete_all_from(
+        &mut self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> SystemResult<Result<usize, OperationOnTableError>> {
+        let span = tracing::debug_span!("delete_all_from", schema_name, table_name);
+        let _enter = span.enter();
+        let (id, indexes) = match self.table(schema_name, table_name)? {
+            Ok(table) => (table.id, table.indexes),
+            Err(error) => return Ok(Err(error)),
+        };
+        let to_delete: Vec<Key> = if self.journal.is_some() {
+            // deleted rows have to be kept to write them back
+            let rows: Vec<Row> = match split(self.persistent.read(&id.namespace(), &id.name()))? {
+                Ok(reads) => reads.map(backend::Result::unwrap).collect(),
+                Err(error) => return Ok(Err(table_error(error))),
+            };
+            let keys = rows.iter().map(|(key, _values)| key.clone()).collect();
+            self.journal(RowChange::Overwritten {
+                namespace: id.namespace(),
+                object_name: id.name(),
+                rows,
+            })?;
+            keys
+        } else {
+            match split(self.persistent.read_keys(&id.namespace(), &id.name()))? {
+                Ok(reads) => reads.map(backend::Result::unwrap).collect(),
+                Err(error) => return Ok(Err(table_error(error))),
+            }
+        };
+
+        match split(self.persistent.delete(&id.namespace(), &id.name(), to_delete))? {
+            Ok(len) => {
+                match self.reindex(id, &id.index_name(), vec![])? {
+                    Ok(()) | Err(DefinitionError::ObjectDoesNotExist) => {}
+                    Err(error) => return Ok(Err(table_error(error))),
+                }
+                for index in indexes {
+                    if let Err(error) = self.reindex(id, &index.object_name(), vec![])? {
+                        return Ok(Err(table_error(error)));
+                    }
+                }
+                Ok(Ok(len))
+            }
             _ => unimplemented!(),
         }
     }
+
+    /// rows with the keys are deleted along with their index entries in a
+    /// single batch
+    pub fn delete_rows(
+        &mut self,
+        schema_name: &str,
+        table_name: &str,
+        keys: Vec<Key>,
+    ) -> SystemResult<Result<usize, OperationOnTableError>> {
+        let span = tracing::debug_span!("delete_rows", schema_name, table_name);
+        let _enter = span.enter();
+        let TableDefinition {
+            id,
+            columns,
+            constraints,
+            missing,
+            indexes,
+        } = match self.table(schema_name, table_name)? {
+            Ok(definition) => definition,
+            Err(error) => return Ok(Err(error)),
+        };
+        let existing: Vec<Row> = match split(self.persistent.lookup(&id.namespace(), &id.name(), keys))? {
+            Ok(reads) => reads.collect::<SystemResult<_>>()?,
+            Err(error) => return Ok(Err(table_error(error))),
+        };
+        if existing.is_empty() {
+            return Ok(Ok(0));
+        }
+
+        let mut batch = WriteBatch::default();
+        if let Some((key_index, sql_type)) = indexed_key(&columns, &constraints) {
+            let entries: Vec<Row> = existing
+                .iter()
+                .map(|(key, values)| {
+                    let values: Vec<Vec<u8>> = bincode::deserialize(values).unwrap();
+                    (index_key(sql_type, &values[key_index]), key.clone())
+                })
+                .collect();
+            self.change_entries(&mut batch, id, &id.index_name(), entries, vec![])?;
+        }
+        for index in indexes.iter() {
+            let entries = index_entries(&columns, &missing, index, &existing);
+            self.change_entries(&mut batch, id, &index.object_name(), entries, vec![])?;
+        }
+        let deleted = existing.len();
+        batch.delete(
+            &id.namespace(),
+            &id.name(),
+            existing.iter().map(|(key, _values)| key.clone()).collect(),
+        );
+        if self.journal.is_some() {
+            self.journal(RowChange::Overwritten {
+                namespace: id.namespace(),
+                object_name: id.name(),
+                rows: existing,
+            })?;
+        }
+        match split(self.persistent.apply_batch(batch))? {
+            Ok(()) => Ok(Ok(deleted)),
+            Err(error) => Ok(Err(table_error(error))),
+        }
+    }
+
+    /// objects of the table are dropped and created empty instead of deleting
+    /// rows one by one. Rows are deleted as `delete_all_from` does while changes
+    /// are recorded, otherwise they could not be written back
+    pub fn truncate(&mut self, schema_name: &str, table_name: &str) -> SystemResult<Result<(), OperationOnTableError>> {
+        let span = tracing::debug_span!("truncate", schema_name, table_name);
+        let _enter = span.enter();
+        if self.journal.is_some() {
+            return Ok(self.delete_all_from(schema_name, table_name)?.map(|_deleted| ()));
+        }
+        let TableDefinition {
+            id,
+            columns,
+            constraints,
+            indexes,
+            ..
+        } = match self.table(schema_name, table_name)? {
+            Ok(definition) => definition,
+            Err(error) => return Ok(Err(error)),
+        };
+        if let Err(error) = split(self.persistent.drop_object(&id.namespace(), &id.name()))? {
+            return Ok(Err(table_error(error)));
+        }
+        self.drop_primary_key_index(id);
+        for index in indexes.iter() {
+            if let Err(error) = split(self.persistent.drop_object(&id.namespace(), &index.object_name()))? {
+                log::error!("failed to drop {} index due to {:?}", index.name, error);
+            }
+        }
+        split(self.persistent.create_object(&id.namespace(), &id.name()))?.map_err(|error| {
+            SystemError::unrecoverable(format!(
+                "object of {}.{} table can't be created due to {:?}",
+                schema_name, table_name, error
+            ))
+        })?;
+        if indexed_key(&columns, &constraints).is_some() {
+            split(self.persistent.create_object(&id.namespace(), &id.index_name()))?.map_err(|error| {
+                SystemError::unrecoverable(format!(
+                    "primary key index of {}.{} table can't be created due to {:?}",
+                    schema_name, table_name, error
+                ))
+            })?;
+        }
+        for index in indexes {
+            split(self.persistent.create_object(&id.namespace(), &index.object_name()))?.map_err(|error| {
+                SystemError::unrecoverable(format!(
+                    "{}.{} index can't be created due to {:?}",
+                    schema_name, index.name, error
+                ))
+            })?;
+        }
+        Ok(Ok(()))
+    }
+
+    pub fn table_size(&self, schema_name: &str, table_name: &str) -> SystemResult<Result<u64, OperationOnTableError>> {
+        let span = tracing::debug_span!("table_size", schema_name, table_name);
+        let _enter = span.enter();
+        let id = match self.object_id(schema_name, table_name)? {
+            Ok(id) => id,
+            Err(error) => return Ok(Err(table_error(error))),
+        };
+        match split(self.persistent.object_size(&id.namespace(), &id.name()))? {
+            Ok(size) => Ok(Ok(size)),
+            Err(error) => Ok(Err(table_error(error))),
+        }
+    }
+
+    /// size of the primary key index along with indexes on other columns,
+    /// tables without them take no space for indexes
+    pub fn indexes_size(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> SystemResult<Result<u64, OperationOnTableError>> {
+        let span = tracing::debug_span!("indexes_size", schema_name, table_name);
+        let _enter = span.enter();
+        let id = match self.object_id(schema_name, table_name)? {
+            Ok(id) => id,
+            Err(error) => return Ok(Err(table_error(error))),
+        };
+        let mut size = match split(self.persistent.object_size(&id.namespace(), &id.index_name()))? {
+            Ok(size) => size,
+            Err(DefinitionError::ObjectDoesNotExist) => 0,
+            Err(error) => return Ok(Err(table_error(error))),
+        };
+        if let Ok(table) = self.table(schema_name, table_name)? {
+            for index in table.indexes {
+                match split(self.persistent.object_size(&id.namespace(), &index.object_name()))? {
+                    Ok(index_size) => size += index_size,
+                    Err(error) => return Ok(Err(table_error(error))),
+                }
+            }
+        }
+        Ok(Ok(size))
+    }
+
+    pub fn create_sequence(
+        &mut self,
+        schema_name: &str,
+        sequence_name: &str,
+        definition: SequenceDefinition,
+    ) -> SystemResult<Result<(), SequenceError>> {
+        let span = tracing::debug_span!("create_sequence", schema_name, sequence_name);
+        let _enter = span.enter();
+        let id = match self.new_object_id(schema_name, sequence_name)? {
+            Ok(id) => id,
+            Err(DefinitionError::NamespaceDoesNotExist) => return Ok(Err(SequenceError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(SequenceError::SequenceAlreadyExists)),
+        };
+        let sequence = SequenceState {
+            start: definition.start,
+            increment: definition.increment,
+            cache: definition.cache.max(1),
+            next: definition.start,
+        };
+        // sequence is visible once it is recorded in the catalog along with its state
+        let mut batch = WriteBatch::default();
+        batch.create_object(&id.namespace(), &id.name());
+        batch.put_object_metadata(&id.namespace(), &id.name(), encode_sequence(&sequence)?);
+        batch.write(
+            "system",
+            "objects",
+            vec![(catalog_key(id.schema, sequence_name), id.object.to_be_bytes().to_vec())],
+        );
+        split(self.persistent.apply_batch(batch))?.map_err(|error| {
+            SystemError::unrecoverable(format!(
+                "{}.{} sequence can't be created due to {:?}",
+                schema_name, sequence_name, error
+            ))
+        })?;
+        Ok(Ok(()))
+    }
+
+    pub fn drop_sequence(&mut self, schema_name: &str, sequence_name: &str) -> SystemResult<Result<(), SequenceError>> {
+        let span = tracing::debug_span!("drop_sequence", schema_name, sequence_name);
+        let _enter = span.enter();
+        let id = match self.read_sequence(schema_name, sequence_name)? {
+            Ok((id, _sequence)) => id,
+            Err(error) => return Ok(Err(error)),
+        };
+        let mut batch = WriteBatch::default();
+        batch.delete("system", "objects", vec![catalog_key(id.schema, sequence_name)]);
+        batch.drop_object(&id.namespace(), &id.name());
+        split(self.persistent.apply_batch(batch))?.map_err(|error| {
+            SystemError::unrecoverable(format!(
+                "failed to drop {}.{} sequence due to {:?}",
+                schema_name, sequence_name, error
+            ))
+        })?;
+        Ok(Ok(()))
+    }
+
+    /// reserves block of values that a session can use without accessing storage,
+    /// the reservation is persisted before values are handed out, so values are
+    /// never repeated after a crash
+    pub fn reserve_sequence_values(
+        &mut self,
+        schema_name: &str,
+        sequence_name: &str,
+    ) -> SystemResult<Result<SequenceBlock, SequenceError>> {
+        let span = tracing::debug_span!("reserve_sequence_values", schema_name, sequence_name);
+        let _enter = span.enter();
+        let (id, mut sequence) = match self.read_sequence(schema_name, sequence_name)? {
+            Ok(sequence) => sequence,
+            Err(error) => return Ok(Err(error)),
+        };
+        let block = SequenceBlock {
+            next: sequence.next,
+            increment: sequence.increment,
+            remaining: sequence.cache,
+        };
+        sequence.next = match (sequence.cache as i64)
+            .checked_mul(sequence.increment)
+            .and_then(|reserved| sequence.next.checked_add(reserved))
+        {
+            Some(next) => next,
+            None => return Ok(Err(SequenceError::LimitReached)),
+        };
+        self.write_sequence(id, schema_name, sequence_name, &sequence)?;
+        Ok(Ok(block))
+    }
+
+    /// next reserved value is `value` or the one after it if `is_called`
+    pub fn set_sequence_value(
+        &mut self,
+        schema_name: &str,
+        sequence_name: &str,
+        value: i64,
+        is_called: bool,
+    ) -> SystemResult<Result<(), SequenceError>> {
+        let span = tracing::debug_span!("set_sequence_value", schema_name, sequence_name);
+        let _enter = span.enter();
+        let (id, mut sequence) = match self.read_sequence(schema_name, sequence_name)? {
+            Ok(sequence) => sequence,
+            Err(error) => return Ok(Err(error)),
+        };
+        sequence.next = if is_called {
+            match value.checked_add(sequence.increment) {
+                Some(next) => next,
+                None => return Ok(Err(SequenceError::LimitReached)),
+            }
+        } else {
+            value
+        };
+        self.write_sequence(id, schema_name, sequence_name, &sequence)?;
+        Ok(Ok(()))
+    }
+
+    pub fn alter_sequence(
+        &mut self,
+        schema_name: &str,
+        sequence_name: &str,
+        alteration: SequenceAlteration,
+    ) -> SystemResult<Result<(), SequenceError>> {
+        let span = tracing::debug_span!("alter_sequence", schema_name, sequence_name);
+        let _enter = span.enter();
+        let (id, mut sequence) = match self.read_sequence(schema_name, sequence_name)? {
+            Ok(sequence) => sequence,
+            Err(error) => return Ok(Err(error)),
+        };
+        match alteration {
+            SequenceAlteration::Restart(value) => sequence.next = value.unwrap_or(sequence.start),
+            SequenceAlteration::IncrementBy(increment) => sequence.increment = increment,
+        }
+        self.write_sequence(id, schema_name, sequence_name, &sequence)?;
+        Ok(Ok(()))
+    }
+
+    fn read_sequence(
+        &mut self,
+        schema_name: &str,
+        sequence_name: &str,
+    ) -> SystemResult<Result<(ObjectId, SequenceState), SequenceError>> {
+        let id = match self.object_id(schema_name, sequence_name)? {
+            Ok(id) => id,
+            Err(DefinitionError::NamespaceDoesNotExist) => return Ok(Err(SequenceError::SchemaDoesNotExist)),
+            Err(_error) => return Ok(Err(SequenceError::SequenceDoesNotExist)),
+        };
+        match split(self.persistent.get_object_metadata(&id.namespace(), &id.name()))? {
+            Ok(Some(state)) => bincode::deserialize(&state)
+                .map(|state| Ok((id, state)))
+                .map_err(|error| {
+                    SystemError::unrecoverable(format!(
+                        "state of {}.{} sequence can't be read due to {:?}",
+                        schema_name, sequence_name, error
+                    ))
+                }),
+            // tables do not have metadata
+            Ok(None) => Ok(Err(SequenceError::SequenceDoesNotExist)),
+            Err(DefinitionError::NamespaceDoesNotExist) => Ok(Err(SequenceError::SchemaDoesNotExist)),
+            Err(_error) => Ok(Err(SequenceError::SequenceDoesNotExist)),
+        }
+    }
+
+    fn write_sequence(
+        &mut self,
+        id: ObjectId,
+        schema_name: &str,
+        sequence_name: &str,
+        sequence: &SequenceState,
+    ) -> SystemResult<()> {
+        let state = encode_sequence(sequence)?;
+        split(self.persistent.put_object_metadata(&id.namespace(), &id.name(), state))?.map_err(|error| {
+            SystemError::unrecoverable(format!(
+                "state of {}.{} sequence can't be written due to {:?}",
+                schema_name, sequence_name, error
+            ))
+        })
+    }
+
+    /// changes of table rows are written to the undo log before they are made
+    /// until they are either kept or reverted
+    pub fn start_journal(&mut self) {
+        self.journal.get_or_insert(0);
+    }
+
+    pub fn is_journaled(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// sessions that wait for the undo log to be cleared are notified through it
+    pub fn journal_cleared(&self) -> Arc<JournalCleared> {
+        self.journal_cleared.clone()
+    }
+
+    /// changes are kept and not written to the undo log anymore
+    pub fn clear_journal(&mut self) -> SystemResult<()> {
+        let span = tracing::debug_span!("clear_journal");
+        let _enter = span.enter();
+        self.journal = None;
+        self.journal_cleared.notify();
+        let keys = split(self.persistent.read_keys("system", "undo"))?
+            .map_err(|error| catalog_error("undo", error))?
+            .collect::<SystemResult<Vec<Key>>>()?;
+        split(self.persistent.delete("system", "undo", keys))?.map_err(|error| catalog_error("undo", error))?;
+        Ok(())
+    }
+
+    /// reverts changes from the undo log in the reverse order they were made
+    pub fn revert(&mut self) -> SystemResult<()> {
+        let span = tracing::debug_span!("revert");
+        let _enter = span.enter();
+        let changes = split(self.persistent.read_reverse("system", "undo"))?
+            .map_err(|error| catalog_error("undo", error))?
+            .collect::<SystemResult<Vec<Row>>>()?;
+        for (_key, change) in changes {
+            let change: RowChange = bincode::deserialize(&change).map_err(|error| {
+                SystemError::unrecoverable(format!("change can't be read from the undo log due to {:?}", error))
+            })?;
+            let reverted = match change {
+                RowChange::Inserted {
+                    namespace,
+                    object_name,
+                    keys,
+                } => split(self.persistent.delete(&namespace, &object_name, keys))?.map(|_len| ()),
+                RowChange::Overwritten {
+                    namespace,
+                    object_name,
+                    rows,
+                } => split(self.persistent.write(&namespace, &object_name, rows))?.map(|_size| ()),
+            };
+            // table could be dropped after the change, then there is nothing to revert
+            if let Err(error) = reverted {
+                log::warn!("change can't be reverted due to {:?}", error);
+            }
+        }
+        self.clear_journal()
+    }
+
+    pub fn database_size(&self) -> SystemResult<u64> {
+        let span = tracing::debug_span!("database_size");
+        let _enter = span.enter();
+        split(self.persistent.size())?
+            .map_err(|error| SystemError::unrecoverable(format!("failed to compute database size due to {:?}", error)))
+    }
+
+    /// makes all changes durable, so they are not replayed from a log on start up
+    pub fn checkpoint(&mut self) -> SystemResult<()> {
+        let span = tracing::debug_span!("checkpoint");
+        let _enter = span.enter();
+        split(self.persistent.flush())?
+            .map_err(|error| SystemError::unrecoverable(format!("failed to checkpoint due to {:?}", error)))
+    }
+}
+
+// failures of the backend are propagated, while definition errors are reported to a client
+impl<P: BackendStorage> FrontendStorage<P> {
+    // change is durable before it is made, so it is reverted even if storage
+    // is not shut down gracefully
+    fn journal(&mut self, change: RowChange) -> SystemResult<()> {
+        if let Some(next) = self.journal.as_mut() {
+            let key = next.to_be_bytes().to_vec();
+            *next += 1;
+            let change = bincode::serialize(&change).expect("change is serialized");
+            split(self.persistent.write("system", "undo", vec![(key, change)]))?
+                .map_err(|error| catalog_error("undo", error))?;
+        }
+        Ok(())
+    }
+
+    // keys continue after the greatest key of the table, so they stay unique
+    // when storage is reopened
+    fn next_key_id(&self, id: ObjectId) -> SystemResult<Result<usize, DefinitionError>> {
+        match split(self.persistent.read_reverse(&id.namespace(), &id.name()))? {
+            Ok(mut rows) => match rows.next() {
+                None => Ok(Ok(0)),
+                Some(Ok((key, _values))) => {
+                    let mut bytes = [0u8; size_of::<usize>()];
+                    if key.len() != bytes.len() {
+                        return Err(SystemError::unrecoverable(format!(
+                            "key {:?} of object {} is not a row id",
+                            key,
+                            id.name()
+                        )));
+                    }
+                    bytes.copy_from_slice(&key);
+                    Ok(Ok(usize::from_be_bytes(bytes) + 1))
+                }
+                Some(Err(error)) => Err(error),
+            },
+            Err(error) => Ok(Err(error)),
+        }
+    }
+
+    // entries of an index are replaced all at once
+    fn reindex(
+        &mut self,
+        id: ObjectId,
+        object_name: &str,
+        entries: Vec<Row>,
+    ) -> SystemResult<Result<(), DefinitionError>> {
+        let existing: Vec<Row> = match split(self.persistent.read(&id.namespace(), object_name))? {
+            Ok(reads) => reads.collect::<SystemResult<_>>()?,
+            Err(error) => return Ok(Err(error)),
+        };
+        let mut batch = WriteBatch::default();
+        self.change_entries(&mut batch, id, object_name, existing, entries)?;
+        split(self.persistent.apply_batch(batch))
+    }
+
+    // entries of an index are changed in the same batch as rows they map to
+    fn change_entries(
+        &mut self,
+        batch: &mut WriteBatch,
+        id: ObjectId,
+        object_name: &str,
+        removed: Vec<Row>,
+        added: Vec<Row>,
+    ) -> SystemResult<()> {
+        if self.journal.is_some() {
+            if !removed.is_empty() {
+                self.journal(RowChange::Overwritten {
+                    namespace: id.namespace(),
+                    object_name: object_name.to_owned(),
+                    rows: removed.clone(),
+                })?;
+            }
+            if !added.is_empty() {
+                self.journal(RowChange::Inserted {
+                    namespace: id.namespace(),
+                    object_name: object_name.to_owned(),
+                    keys: added.iter().map(|(key, _row_key)| key.clone()).collect(),
+                })?;
+            }
+        }
+        batch.delete(
+            &id.namespace(),
+            object_name,
+            removed.into_iter().map(|(key, _row_key)| key).collect(),
+        );
+        batch.write(&id.namespace(), object_name, added);
+        Ok(())
+    }
+
+    // rows with keys that the primary key index maps values within the range
+    // of key conditions to, otherwise the ones that an index on a column with
+    // an equality condition maps its value to
+    fn read_rows(
+        &self,
+        table: &TableDefinition,
+        key_conditions: &[(String, Comparison, String)],
+    ) -> SystemResult<Result<ReadCursor, DefinitionError>> {
+        let id = table.id;
+        if let Some((key_index, sql_type)) = indexed_key(&table.columns, &table.constraints) {
+            let conditions: Vec<(Comparison, String)> = key_conditions
+                .iter()
+                .filter(|(column, _comparison, _value)| *column == table.columns[key_index].0)
+                .map(|(_column, comparison, value)| (*comparison, value.clone()))
+                .collect();
+            match key_range(sql_type, &conditions).filter(|_range| !conditions.is_empty()) {
+                None => {}
+                Some(range) if is_empty(&range) => return Ok(Ok(Box::new(std::iter::empty()))),
+                Some(range) => {
+                    return match split(self.persistent.read_range(&id.namespace(), &id.index_name(), range))? {
+                        Ok(entries) => {
+                            let keys = entries
+                                .map(|entry| entry.map(|(_key, row_key)| row_key))
+                                .collect::<SystemResult<Vec<Key>>>()?;
+                            split(self.persistent.lookup(&id.namespace(), &id.name(), keys))
+                        }
+                        Err(error) => Ok(Err(error)),
+                    }
+                }
+            }
+        }
+        for index in table.indexes.iter() {
+            let sql_type = match table.columns.iter().find(|(name, _sql_type)| *name == index.column) {
+                Some((_name, sql_type)) if keeps_order(*sql_type) => *sql_type,
+                _ => continue,
+            };
+            let value = key_conditions
+                .iter()
+                .find_map(|(column, comparison, value)| match comparison {
+                    Comparison::Eq if *column == index.column => key_value(sql_type, value),
+                    _ => None,
+                });
+            if let Some(value) = value {
+                let prefix = index_key(sql_type, &value);
+                let entries = match split(
+                    self.persistent
+                        .read_prefix(&id.namespace(), &index.object_name(), &prefix),
+                )? {
+                    Ok(entries) => entries,
+                    Err(error) => return Ok(Err(error)),
+                };
+                // entries of longer values that start with the same bytes are skipped
+                let mut keys = vec![];
+                for entry in entries {
+                    let (key, row_key) = entry?;
+                    if key.len() == prefix.len() + row_key.len() {
+                        keys.push(row_key);
+                    }
+                }
+                return split(self.persistent.lookup(&id.namespace(), &id.name(), keys));
+            }
+        }
+        split(self.persistent.read(&id.namespace(), &id.name()))
+    }
+
+    fn table(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> SystemResult<Result<TableDefinition, OperationOnTableError>> {
+        let id = match self.object_id(schema_name, table_name)? {
+            Ok(id) => id,
+            Err(error) => return Ok(Err(table_error(error))),
+        };
+        let mut columns = split(self.persistent.lookup("system", "columns", vec![id.key()]))?
+            // is not covered yet
+            .map_err(|err| {
+                SystemError::unrecoverable(format!(
+                    "failed to read table's columns from system.columns due to {:?}",
+                    err
+                ))
+            })?;
+        match columns.next() {
+            Some(Ok((_id, metadata))) => {
+                let TableMetadata {
+                    columns,
+                    constraints,
+                    indexes,
+                } = decode_metadata(id, &metadata)?;
+                let missing = columns
+                    .iter()
+                    .map(|column| column.missing.clone().unwrap_or_else(|| NULL.to_vec()))
+                    .collect();
+                Ok(Ok(TableDefinition {
+                    id,
+                    columns: columns
+                        .into_iter()
+                        .map(|ColumnMetadata { name, sql_type, .. }| (name, sql_type))
+                        .collect(),
+                    constraints,
+                    missing,
+                    indexes,
+                }))
+            }
+            Some(Err(error)) => Err(error),
+            // sequences and indexes do not have columns
+            None => Ok(Err(OperationOnTableError::TableDoesNotExist)),
+        }
+    }
+
+    fn table_metadata(&self, id: ObjectId) -> SystemResult<TableMetadata> {
+        match split(self.persistent.lookup("system", "columns", vec![id.key()]))?
+            .map_err(|error| catalog_error("columns", error))?
+            .next()
+        {
+            Some(Ok((_id, metadata))) => decode_metadata(id, &metadata),
+            Some(Err(error)) => Err(error),
+            None => Err(SystemError::unrecoverable(format!(
+                "columns of object {} are not recorded in system.columns",
+                id.name()
+            ))),
+        }
+    }
+
+    fn write_table_metadata(&mut self, id: ObjectId, metadata: &TableMetadata) -> SystemResult<()> {
+        split(
+            self.persistent
+                .write("system", "columns", vec![(id.key(), encode_metadata(metadata)?)]),
+        )?
+        .map(|_size| ())
+        .map_err(|error| catalog_error("columns", error))
+    }
+
+    fn schema_id(&self, schema_name: &str) -> SystemResult<Option<Oid>> {
+        self.find_in_catalog("schemas", schema_name.as_bytes().to_vec())
+    }
+
+    // user given names are mapped to ids, that name backend namespaces and objects,
+    // so they never collide with internal objects
+    fn object_id(&self, schema_name: &str, object_name: &str) -> SystemResult<Result<ObjectId, DefinitionError>> {
+        let schema = match self.schema_id(schema_name)? {
+            Some(schema) => schema,
+            None => return Ok(Err(DefinitionError::NamespaceDoesNotExist)),
+        };
+        match self.find_in_catalog("objects", catalog_key(schema, object_name))? {
+            Some(object) => Ok(Ok(ObjectId { schema, object })),
+            None => Ok(Err(DefinitionError::ObjectDoesNotExist)),
+        }
+    }
+
+    // object is visible once it is recorded in the catalog with the id
+    fn new_object_id(
+        &mut self,
+        schema_name: &str,
+        object_name: &str,
+    ) -> SystemResult<Result<ObjectId, DefinitionError>> {
+        let schema = match self.schema_id(schema_name)? {
+            Some(schema) => schema,
+            None => return Ok(Err(DefinitionError::NamespaceDoesNotExist)),
+        };
+        if self
+            .find_in_catalog("objects", catalog_key(schema, object_name))?
+            .is_some()
+        {
+            return Ok(Err(DefinitionError::ObjectAlreadyExists));
+        }
+        Ok(Ok(ObjectId {
+            schema,
+            object: self.next_id()?,
+        }))
+    }
+
+    // ids are not reused, so rows of a dropped object never show up in a new one
+    fn next_id(&mut self) -> SystemResult<Oid> {
+        let next = match split(self.persistent.get_object_metadata("system", "objects"))? {
+            Ok(Some(next)) => decode_id(&next)?,
+            Ok(None) => FIRST_OBJECT_ID,
+            Err(error) => return Err(catalog_error("objects", error)),
+        };
+        split(
+            self.persistent
+                .put_object_metadata("system", "objects", (next + 1).to_be_bytes().to_vec()),
+        )?
+        .map_err(|error| catalog_error("objects", error))?;
+        Ok(next)
+    }
+
+    fn find_in_catalog(&self, catalog: &str, key: Key) -> SystemResult<Option<Oid>> {
+        match split(self.persistent.lookup("system", catalog, vec![key]))?
+            .map_err(|error| catalog_error(catalog, error))?
+            .next()
+        {
+            Some(Ok((_key, id))) => decode_id(&id).map(Some),
+            Some(Err(error)) => Err(error),
+            None => Ok(None),
+        }
+    }
+
+    // backend may not create and drop objects atomically with the catalog
+    // changes, and truncate recreates objects of a table, so a crash leaves
+    // objects that are not recorded in the catalog or tables without their
+    // objects. Clients see what is recorded in the catalog, so objects are
+    // brought in line with it
+    fn reconcile(&mut self) -> SystemResult<()> {
+        let schemas = split(self.persistent.read("system", "schemas"))?
+            .map_err(|error| catalog_error("schemas", error))?
+            .map(|row| row.and_then(|(_name, id)| decode_id(&id)))
+            .collect::<SystemResult<HashSet<Oid>>>()?;
+        let mut owners = HashMap::new();
+        for row in split(self.persistent.read("system", "objects"))?.map_err(|error| catalog_error("objects", error))? {
+            let (key, id) = row?;
+            // key starts with the id of the schema that the object belongs to
+            let schema = key.get(..size_of::<Oid>()).unwrap_or(&key);
+            owners.insert(decode_id(&id)?, decode_id(schema)?);
+        }
+
+        let mut object_names = HashMap::new();
+        for namespace in split(self.persistent.namespaces())?.map_err(|error| catalog_error("schemas", error))? {
+            if namespace == "system" {
+                continue;
+            }
+            let schema = match namespace.parse::<Oid>() {
+                Ok(schema) if schemas.contains(&schema) => schema,
+                _ => {
+                    log::warn!("namespace {} is not recorded in the catalog, it is dropped", namespace);
+                    split(self.persistent.drop_namespace(&namespace))?
+                        .map_err(|error| reconcile_error(&namespace, error))?;
+                    continue;
+                }
+            };
+            let mut names = HashSet::new();
+            for object_name in
+                split(self.persistent.object_names(&namespace))?.map_err(|error| reconcile_error(&namespace, error))?
+            {
+                match object_name.trim_end_matches("_pkey").parse::<Oid>() {
+                    Ok(object) if owners.get(&object) == Some(&schema) => {
+                        names.insert(object_name);
+                    }
+                    _ => {
+                        log::warn!(
+                            "object {}.{} is not recorded in the catalog, it is dropped",
+                            namespace,
+                            object_name
+                        );
+                        split(self.persistent.drop_object(&namespace, &object_name))?
+                            .map_err(|error| reconcile_error(&namespace, error))?;
+                    }
+                }
+            }
+            object_names.insert(schema, names);
+        }
+
+        let mut dangling = vec![];
+        for row in split(self.persistent.read("system", "columns"))?.map_err(|error| catalog_error("columns", error))? {
+            let (key, metadata) = row?;
+            let object = decode_id(&key)?;
+            let id = match owners.get(&object) {
+                Some(schema) => ObjectId {
+                    schema: *schema,
+                    object,
+                },
+                None => {
+                    log::warn!(
+                        "columns of {} table are not recorded in the catalog, they are dropped",
+                        object
+                    );
+                    dangling.push(key);
+                    continue;
+                }
+            };
+            let metadata = decode_metadata(id, &metadata)?;
+            let mut names = vec![id.name()];
+            if indexed_key(&metadata.column_types(), &metadata.constraints).is_some() {
+                names.push(id.index_name());
+            }
+            names.extend(metadata.indexes.iter().map(IndexMetadata::object_name));
+            let existing = object_names.get(&id.schema);
+            for name in names {
+                // objects of a table are dropped and created empty on truncate
+                if existing.map(|existing| !existing.contains(&name)).unwrap_or(false) {
+                    log::warn!("object {}.{} of a table does not exist, it is created", id.schema, name);
+                    split(self.persistent.create_object(&id.namespace(), &name))?
+                        .map_err(|error| reconcile_error(&id.namespace(), error))?;
+                }
+            }
+        }
+        if !dangling.is_empty() {
+            split(self.persistent.delete("system", "columns", dangling))?
+                .map_err(|error| catalog_error("columns", error))?;
+        }
+        Ok(())
+    }
+}
+
+// ids of the schema and the object that name its backend namespace and object
+#[derive(Debug, Clone, Copy)]
+struct ObjectId {
+    schema: Oid,
+    object: Oid,
+}
+
+impl ObjectId {
+    fn namespace(&self) -> String {
+        self.schema.to_string()
+    }
+
+    fn name(&self) -> String {
+        self.object.to_string()
+    }
+
+    fn key(&self) -> Key {
+        self.object.to_be_bytes().to_vec()
+    }
+
+    // values of primary key are mapped to keys of rows in the object of the index
+    fn index_name(&self) -> String {
+        format!("{}_pkey", self.object)
+    }
+}
+
+// objects are looked up by name within their schema
+fn catalog_key(schema: Oid, object_name: &str) -> Key {
+    let mut key = schema.to_be_bytes().to_vec();
+    key.extend_from_slice(object_name.as_bytes());
+    key
+}
+
+fn decode_id(bytes: &[u8]) -> SystemResult<Oid> {
+    let mut id = [0u8; size_of::<Oid>()];
+    if bytes.len() != id.len() {
+        return Err(SystemError::unrecoverable(format!("{:?} is not an object id", bytes)));
+    }
+    id.copy_from_slice(bytes);
+    Ok(Oid::from_be_bytes(id))
+}
+
+fn encode_metadata(metadata: &TableMetadata) -> SystemResult<Values> {
+    bincode::serialize(metadata)
+        .map_err(|error| SystemError::unrecoverable(format!("columns can't be serialized due to {:?}", error)))
+}
+
+fn decode_metadata(id: ObjectId, bytes: &[u8]) -> SystemResult<TableMetadata> {
+    bincode::deserialize(bytes).map_err(|error| {
+        SystemError::unrecoverable(format!(
+            "columns of {} table can't be read due to {:?}",
+            id.name(),
+            error
+        ))
+    })
+}
+
+fn encode_sequence(sequence: &SequenceState) -> SystemResult<Values> {
+    bincode::serialize(sequence)
+        .map_err(|error| SystemError::unrecoverable(format!("sequence state can't be serialized due to {:?}", error)))
+}
+
+fn reconcile_error(namespace: &str, error: DefinitionError) -> SystemError {
+    SystemError::unrecoverable(format!(
+        "objects of {} namespace can't be reconciled with the catalog due to {:?}",
+        namespace, error
+    ))
+}
+
+fn catalog_error(catalog: &str, error: DefinitionError) -> SystemError {
+    SystemError::unrecoverable(format!("system.{} can't be accessed due to {:?}", catalog, error))
+}
+
+fn split<T>(result: StorageResult<T>) -> SystemResult<Result<T, DefinitionError>> {
+    match result {
+        Ok(value) => Ok(Ok(value)),
+        Err(StorageError::System(error)) => Err(error),
+        Err(StorageError::Definition(error)) => Ok(Err(error)),
+    }
+}
+
+fn alter_error(error: DefinitionError) -> AlterTableError {
+    match error {
+        DefinitionError::NamespaceDoesNotExist => AlterTableError::SchemaDoesNotExist,
+        _ => AlterTableError::TableDoesNotExist,
+    }
+}
+
+fn table_error(error: DefinitionError) -> OperationOnTableError {
+    match error {
+        DefinitionError::NamespaceDoesNotExist => OperationOnTableError::SchemaDoesNotExist,
+        _ => OperationOnTableError::TableDoesNotExist,
+    }
+}
+
+// values of columns that a unique constraint is violated by
+fn unique_violation(
+    all_columns: &[(String, SqlType)],
+    missing: &[Vec<u8>],
+    constraints: &[TableConstraint],
+    existing: Vec<Row>,
+    rows: &[Row],
+) -> Option<String> {
+    for constraint in constraints.iter().filter_map(|constraint| match constraint {
+        TableConstraint::Unique(unique) => Some(unique),
+        _ => None,
+    }) {
+        let indexes: Vec<usize> = constraint
+            .columns
+            .iter()
+            .filter_map(|column| all_columns.iter().position(|(name, _sql_type)| name == column))
+            .collect();
+        let key = |values: &[u8]| {
+            let mut values: Vec<Vec<u8>> = bincode::deserialize(values).unwrap();
+            complete(&mut values, missing);
+            indexes
+                .iter()
+                .map(|index| values[*index].clone())
+                .collect::<Vec<Vec<u8>>>()
+        };
+        // nulls are not equal to each other, so they never violate a unique constraint
+        let mut keys: HashSet<Vec<Vec<u8>>> = existing
+            .iter()
+            .map(|(_key, values)| key(values))
+            .filter(|key| !key.iter().any(|value| value.as_slice() == NULL))
+            .collect();
+        for (_key, values) in rows {
+            let key = key(values);
+            if !key.iter().any(|value| value.as_slice() == NULL) && !keys.insert(key) {
+                return Some(constraint.name.clone());
+            }
+        }
+    }
+    None
+}
+
+// single column primary key is indexed when encoded values of its type keep
+// their order
+fn indexed_key(columns: &[(String, SqlType)], constraints: &[TableConstraint]) -> Option<(usize, SqlType)> {
+    constraints
+        .iter()
+        .find_map(|constraint| match constraint {
+            TableConstraint::Unique(unique) if unique.is_primary && unique.columns.len() == 1 => columns
+                .iter()
+                .position(|(name, _sql_type)| *name == unique.columns[0])
+                .map(|index| (index, columns[index].1)),
+            _ => None,
+        })
+        .filter(|(_index, sql_type)| keeps_order(*sql_type))
+}
+
+// values are equal when their encoded bytes are, and the ones of integers and
+// timestamps are ordered as the values once `index_key` flips their sign bit
+fn keeps_order(sql_type: SqlType) -> bool {
+    matches!(
+        sql_type,
+        SqlType::SmallInt
+            | SqlType::Integer
+            | SqlType::BigInt
+            | SqlType::Char(_)
+            | SqlType::VarChar(_)
+            | SqlType::Timestamp
+            | SqlType::TimestampWithTimeZone
+    )
+}
+
+// integers and timestamps are kept in two's complement, flipping the sign bit
+// orders their bytes the same way as the values
+fn index_key(sql_type: SqlType, value: &[u8]) -> Key {
+    let mut key = value.to_vec();
+    match (sql_type, key.first_mut()) {
+        (SqlType::Char(_), _) | (SqlType::VarChar(_), _) | (_, None) => {}
+        (_, Some(byte)) => *byte ^= 0x80,
+    }
+    key
+}
+
+// keys of values that satisfy all comparisons, `None` if any value is not of
+// the key type
+fn key_range(sql_type: SqlType, key_conditions: &[(Comparison, String)]) -> Option<KeyRange> {
+    let mut start = Bound::Unbounded;
+    let mut end = Bound::Unbounded;
+    for (comparison, value) in key_conditions {
+        let key = index_key(sql_type, &key_value(sql_type, value)?);
+        match comparison {
+            Comparison::Eq => {
+                start = narrower(start, Bound::Included(key.clone()), Ordering::Greater);
+                end = narrower(end, Bound::Included(key), Ordering::Less);
+            }
+            Comparison::Gt => start = narrower(start, Bound::Excluded(key), Ordering::Greater),
+            Comparison::GtEq => start = narrower(start, Bound::Included(key), Ordering::Greater),
+            Comparison::Lt => end = narrower(end, Bound::Excluded(key), Ordering::Less),
+            Comparison::LtEq => end = narrower(end, Bound::Included(key), Ordering::Less),
+            Comparison::NotEq => {}
+        }
+    }
+    Some((start, end))
+}
+
+// encoded value that keys of an index are compared with, `None` if it is not
+// of the type
+fn key_value(sql_type: SqlType, value: &str) -> Option<Vec<u8>> {
+    if sql_type.constraint().validate(value).is_err() {
+        return None;
+    }
+    // values that are stored differently than they are written, like
+    // "007" or "a  ", would be compared as other keys
+    let serialized = sql_type.serializer().ser(value);
+    if sql_type.serializer().des(&serialized) != value {
+        return None;
+    }
+    Some(serialized)
+}
+
+// entries that the rows have in the index on a column other than the one of
+// primary key, rows keep their keys, so an entry ends with the key of its row
+fn index_entries(columns: &[(String, SqlType)], missing: &[Vec<u8>], index: &IndexMetadata, rows: &[Row]) -> Vec<Row> {
+    let (position, sql_type) = match columns.iter().position(|(name, _sql_type)| *name == index.column) {
+        Some(position) => (position, columns[position].1),
+        None => return vec![],
+    };
+    rows.iter()
+        .map(|(key, values)| {
+            let mut values: Vec<Vec<u8>> = bincode::deserialize(values).unwrap();
+            complete(&mut values, missing);
+            let mut entry = index_key(sql_type, &values[position]);
+            entry.extend_from_slice(key);
+            (entry, key.clone())
+        })
+        .collect()
+}
+
+// of two starts the greater one lets fewer keys in, of two ends the lesser one
+fn narrower(current: Bound<Key>, other: Bound<Key>, order: Ordering) -> Bound<Key> {
+    match (&current, &other) {
+        (Bound::Unbounded, _) => other,
+        (_, Bound::Unbounded) => current,
+        (Bound::Included(left), Bound::Included(right))
+        | (Bound::Included(left), Bound::Excluded(right))
+        | (Bound::Excluded(left), Bound::Included(right))
+        | (Bound::Excluded(left), Bound::Excluded(right)) => match left.cmp(right) {
+            Ordering::Equal if matches!(current, Bound::Excluded(_)) => current,
+            Ordering::Equal => other,
+            ordering if ordering == order => current,
+            _ => other,
+        },
+    }
+}
+
+// backends do not accept ranges that end before they start
+fn is_empty((start, end): &KeyRange) -> bool {
+    match (start, end) {
+        (Bound::Included(start), Bound::Included(end)) => start > end,
+        (Bound::Included(start), Bound::Excluded(end))
+        | (Bound::Excluded(start), Bound::Included(end))
+        | (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+        _ => false,
+    }
+}
+
+// values that are not given are nulls
+fn not_null_violation(constraints: &[TableConstraint], given: &[(&String, SqlType, &str)]) -> Option<String> {
+    constraints.iter().find_map(|constraint| match constraint {
+        TableConstraint::NotNull(column) if given.iter().all(|(name, _sql_type, _value)| *name != column) => {
+            Some(column.clone())
+        }
+        _ => None,
+    })
+}
+
+// nulls satisfy check constraints, so only given values are checked
+fn check_violation(constraints: &[TableConstraint], given: &[(&String, SqlType, &str)]) -> Option<String> {
+    constraints.iter().find_map(|constraint| match constraint {
+        TableConstraint::Check(check)
+            if given
+                .iter()
+                .any(|(name, sql_type, value)| **name == check.column && !check.accepts(*sql_type, value)) =>
+        {
+            Some(check.name.clone())
+        }
+        _ => None,
+    })
+}
+
+fn is_checked(constraints: &[TableConstraint], column: &str) -> bool {
+    constraints
+        .iter()
+        .any(|constraint| matches!(constraint, TableConstraint::Check(check) if check.column == column))
+}
+
+// nulls are read as empty strings like the engine outputs them
+fn decode(sql_type: SqlType, value: &[u8]) -> String {
+    if value == NULL {
+        String::new()
+    } else {
+        sql_type.serializer().des(value)
+    }
+}
+
+// rows written before columns were added are shorter than the table definition,
+// values of the columns they do not have are taken from the catalog
+fn complete(record: &mut Vec<Vec<u8>>, missing: &[Vec<u8>]) {
+    if record.len() < missing.len() {
+        record.extend_from_slice(&missing[record.len()..]);
+    }
+}
+
+// columns of a table as they are kept in the catalog along with its constraints
+struct TableDefinition {
+    id: ObjectId,
+    columns: Columns,
+    constraints: Vec<TableConstraint>,
+    // values of columns for rows that were written before the columns were added
+    missing: Vec<Vec<u8>>,
+    indexes: Vec<IndexMetadata>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TableMetadata {
+    columns: Vec<ColumnMetadata>,
+    constraints: Vec<TableConstraint>,
+    // indexes that are created on columns besides the one of primary key
+    indexes: Vec<IndexMetadata>,
+}
+
+impl TableMetadata {
+    fn column_types(&self) -> Columns {
+        self.columns
+            .iter()
+            .map(|column| (column.name.clone(), column.sql_type))
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 struct ColumnMetadata {
     name: String,
     sql_type: SqlType,
+    // value that rows written before the column was added have, null if it is not set
+    missing: Option<Vec<u8>>,
+}
+
+// index name is recorded in the catalog along with tables and sequences, its
+// entries map values of the column along with keys of rows to the keys, so
+// rows with the same value have entries of their own
+#[derive(Clone, Serialize, Deserialize)]
+struct IndexMetadata {
+    name: String,
+    column: String,
+    object: Oid,
+}
+
+impl IndexMetadata {
+    fn object_name(&self) -> String {
+        self.object.to_string()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SequenceState {
+    start: i64,
+    increment: i64,
+    cache: u64,
+    // the first value that is not reserved by any session
+    next: i64,
 }
 
 #[cfg(test)]
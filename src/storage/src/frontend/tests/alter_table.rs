@@ -0,0 +1,375 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::{CheckConstraint, Comparison, DefaultValue, TableAlteration, UniqueConstraint};
+use sql_types::SqlType;
+
+#[rstest::fixture]
+fn with_table(mut storage: PersistentStorage) -> PersistentStorage {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("id", SqlType::SmallInt), ("name", SqlType::VarChar(20))],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["1", "a"]);
+    storage
+}
+
+fn alter_table(storage: &mut PersistentStorage, alteration: TableAlteration) -> Result<(), AlterTableError> {
+    storage
+        .alter_table("schema_name", "table_name", alteration)
+        .expect("no system errors")
+}
+
+fn select(storage: &mut PersistentStorage, table_name: &str, columns: Vec<&str>) -> Vec<Vec<String>> {
+    storage
+        .select_all_from(
+            "schema_name",
+            table_name,
+            columns.into_iter().map(ToOwned::to_owned).collect(),
+        )
+        .expect("no system errors")
+        .expect("values are selected")
+        .1
+}
+
+fn add_column(name: &str, sql_type: SqlType, constraints: Vec<TableConstraint>) -> TableAlteration {
+    TableAlteration::AddColumn {
+        name: name.to_owned(),
+        sql_type,
+        constraints,
+    }
+}
+
+#[rstest::rstest]
+fn existing_rows_take_default_of_added_column(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(
+            &mut with_table,
+            add_column(
+                "age",
+                SqlType::Integer,
+                vec![TableConstraint::Default(
+                    "age".to_owned(),
+                    DefaultValue::Constant("18".to_owned())
+                )]
+            )
+        ),
+        Ok(())
+    );
+    insert_into(
+        &mut with_table,
+        "schema_name",
+        "table_name",
+        vec![],
+        vec!["2", "b", "30"],
+    );
+
+    assert_eq!(
+        select(&mut with_table, "table_name", vec!["id", "age"]),
+        vec![
+            vec!["1".to_owned(), "18".to_owned()],
+            vec!["2".to_owned(), "30".to_owned()]
+        ]
+    );
+    assert_eq!(
+        with_table
+            .column_defaults("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(vec![("age".to_owned(), DefaultValue::Constant("18".to_owned()))])
+    );
+}
+
+#[rstest::rstest]
+fn update_rows_written_before_column_is_added(mut with_table: PersistentStorage) {
+    alter_table(&mut with_table, add_column("age", SqlType::SmallInt, vec![])).expect("column is added");
+
+    assert_eq!(
+        with_table
+            .update_all("schema_name", "table_name", vec![("age".to_owned(), "5".to_owned())])
+            .expect("no system errors"),
+        Ok(1)
+    );
+    assert_eq!(
+        select(&mut with_table, "table_name", vec!["id", "name", "age"]),
+        vec![vec!["1".to_owned(), "a".to_owned(), "5".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn existing_rows_have_null_in_added_column_without_default(mut with_table: PersistentStorage) {
+    alter_table(&mut with_table, add_column("age", SqlType::Integer, vec![])).expect("column is added");
+    alter_table(&mut with_table, add_column("nickname", SqlType::VarChar(10), vec![])).expect("column is added");
+    insert_into(
+        &mut with_table,
+        "schema_name",
+        "table_name",
+        vec!["id", "name", "age"],
+        vec!["2", "b", "30"],
+    );
+
+    assert_eq!(
+        select(&mut with_table, "table_name", vec!["id", "name", "age", "nickname"]),
+        vec![
+            vec!["1".to_owned(), "a".to_owned(), "".to_owned(), "".to_owned()],
+            vec!["2".to_owned(), "b".to_owned(), "30".to_owned(), "".to_owned()]
+        ]
+    );
+}
+
+#[rstest::rstest]
+fn add_existing_column(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(&mut with_table, add_column("name", SqlType::SmallInt, vec![])),
+        Err(AlterTableError::ColumnAlreadyExists("name".to_owned()))
+    );
+}
+
+#[rstest::rstest]
+fn add_column_with_invalid_default(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(
+            &mut with_table,
+            add_column(
+                "age",
+                SqlType::SmallInt,
+                vec![TableConstraint::Default(
+                    "age".to_owned(),
+                    DefaultValue::Constant("100000".to_owned())
+                )]
+            )
+        ),
+        Err(AlterTableError::InvalidDefault("100000".to_owned()))
+    );
+}
+
+#[rstest::rstest]
+fn add_not_null_column_without_default(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(
+            &mut with_table,
+            add_column(
+                "age",
+                SqlType::SmallInt,
+                vec![TableConstraint::NotNull("age".to_owned())]
+            )
+        ),
+        Err(AlterTableError::NotNullViolation("age".to_owned()))
+    );
+    assert_eq!(
+        with_table
+            .table_columns("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(vec![
+            ("id".to_owned(), SqlType::SmallInt),
+            ("name".to_owned(), SqlType::VarChar(20))
+        ])
+    );
+}
+
+#[rstest::rstest]
+fn add_column_with_default_that_violates_check(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(
+            &mut with_table,
+            add_column(
+                "age",
+                SqlType::SmallInt,
+                vec![
+                    TableConstraint::Default("age".to_owned(), DefaultValue::Constant("0".to_owned())),
+                    TableConstraint::Check(CheckConstraint {
+                        name: "table_name_age_check".to_owned(),
+                        column: "age".to_owned(),
+                        comparison: Comparison::Gt,
+                        value: "0".to_owned(),
+                    })
+                ]
+            )
+        ),
+        Err(AlterTableError::CheckViolation("table_name_age_check".to_owned()))
+    );
+}
+
+#[rstest::rstest]
+fn drop_column(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(&mut with_table, TableAlteration::DropColumn("id".to_owned())),
+        Ok(())
+    );
+    insert_into(&mut with_table, "schema_name", "table_name", vec![], vec!["b"]);
+
+    assert_eq!(
+        with_table
+            .table_columns("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(vec![("name".to_owned(), SqlType::VarChar(20))])
+    );
+    assert_eq!(
+        select(&mut with_table, "table_name", vec!["name"]),
+        vec![vec!["a".to_owned()], vec!["b".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn drop_column_drops_its_constraints(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    storage
+        .create_table_with_constraints(
+            "schema_name",
+            "table_name",
+            vec![
+                ("id".to_owned(), SqlType::SmallInt),
+                ("name".to_owned(), SqlType::VarChar(20)),
+            ],
+            vec![
+                TableConstraint::Unique(UniqueConstraint {
+                    name: "table_name_id_name_key".to_owned(),
+                    columns: vec!["id".to_owned(), "name".to_owned()],
+                    is_primary: false,
+                }),
+                TableConstraint::NotNull("name".to_owned()),
+            ],
+        )
+        .expect("no system errors")
+        .expect("table is created");
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["1", "a"]);
+
+    alter_table(&mut storage, TableAlteration::DropColumn("name".to_owned())).expect("column is dropped");
+
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["1"]);
+    assert_eq!(
+        select(&mut storage, "table_name", vec!["id"]),
+        vec![vec!["1".to_owned()], vec!["1".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn drop_column_of_rows_written_before_it_is_added(mut with_table: PersistentStorage) {
+    alter_table(&mut with_table, add_column("age", SqlType::SmallInt, vec![])).expect("column is added");
+    alter_table(&mut with_table, TableAlteration::DropColumn("age".to_owned())).expect("column is dropped");
+    alter_table(&mut with_table, TableAlteration::DropColumn("id".to_owned())).expect("column is dropped");
+
+    assert_eq!(
+        select(&mut with_table, "table_name", vec!["name"]),
+        vec![vec!["a".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn drop_non_existent_column(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(&mut with_table, TableAlteration::DropColumn("age".to_owned())),
+        Err(AlterTableError::ColumnDoesNotExist("age".to_owned()))
+    );
+}
+
+#[rstest::rstest]
+fn rename_column(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(
+            &mut with_table,
+            TableAlteration::RenameColumn {
+                from: "name".to_owned(),
+                to: "title".to_owned()
+            }
+        ),
+        Ok(())
+    );
+
+    assert_eq!(
+        select(&mut with_table, "table_name", vec!["id", "title"]),
+        vec![vec!["1".to_owned(), "a".to_owned()]]
+    );
+    assert_eq!(
+        alter_table(
+            &mut with_table,
+            TableAlteration::RenameColumn {
+                from: "name".to_owned(),
+                to: "other".to_owned()
+            }
+        ),
+        Err(AlterTableError::ColumnDoesNotExist("name".to_owned()))
+    );
+    assert_eq!(
+        alter_table(
+            &mut with_table,
+            TableAlteration::RenameColumn {
+                from: "id".to_owned(),
+                to: "title".to_owned()
+            }
+        ),
+        Err(AlterTableError::ColumnAlreadyExists("title".to_owned()))
+    );
+}
+
+#[rstest::rstest]
+fn rename_table(mut with_table: PersistentStorage) {
+    assert_eq!(
+        alter_table(&mut with_table, TableAlteration::RenameTo("new_name".to_owned())),
+        Ok(())
+    );
+
+    assert_eq!(
+        select(&mut with_table, "new_name", vec!["id", "name"]),
+        vec![vec!["1".to_owned(), "a".to_owned()]]
+    );
+    assert_eq!(
+        with_table
+            .select_all_from("schema_name", "table_name", vec!["id".to_owned()])
+            .expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn rename_table_to_existing_name(mut with_table: PersistentStorage) {
+    create_table(
+        &mut with_table,
+        "schema_name",
+        "other_table",
+        vec![("id", SqlType::SmallInt)],
+    );
+
+    assert_eq!(
+        alter_table(&mut with_table, TableAlteration::RenameTo("other_table".to_owned())),
+        Err(AlterTableError::TableAlreadyExists)
+    );
+}
+
+#[rstest::rstest]
+fn alter_non_existent_table(mut storage: PersistentStorage) {
+    assert_eq!(
+        storage
+            .alter_table(
+                "schema_name",
+                "table_name",
+                TableAlteration::DropColumn("id".to_owned())
+            )
+            .expect("no system errors"),
+        Err(AlterTableError::SchemaDoesNotExist)
+    );
+    create_schema(&mut storage, "schema_name");
+    assert_eq!(
+        storage
+            .alter_table(
+                "schema_name",
+                "table_name",
+                TableAlteration::DropColumn("id".to_owned())
+            )
+            .expect("no system errors"),
+        Err(AlterTableError::TableDoesNotExist)
+    );
+}
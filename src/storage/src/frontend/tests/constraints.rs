@@ -0,0 +1,258 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::{CheckConstraint, Comparison, DefaultValue, UniqueConstraint};
+use sql_types::SqlType;
+
+#[rstest::fixture]
+fn with_constraints(mut storage: PersistentStorage) -> PersistentStorage {
+    create_schema(&mut storage, "schema_name");
+    storage
+        .create_table_with_constraints(
+            "schema_name",
+            "table_name",
+            vec![
+                ("id".to_owned(), SqlType::SmallInt),
+                ("email".to_owned(), SqlType::VarChar(20)),
+            ],
+            vec![
+                TableConstraint::Unique(UniqueConstraint {
+                    name: "table_name_pkey".to_owned(),
+                    columns: vec!["id".to_owned()],
+                    is_primary: true,
+                }),
+                TableConstraint::Unique(UniqueConstraint {
+                    name: "table_name_email_key".to_owned(),
+                    columns: vec!["email".to_owned()],
+                    is_primary: false,
+                }),
+                TableConstraint::NotNull("id".to_owned()),
+                TableConstraint::Check(CheckConstraint {
+                    name: "table_name_id_check".to_owned(),
+                    column: "id".to_owned(),
+                    comparison: Comparison::Gt,
+                    value: "0".to_owned(),
+                }),
+            ],
+        )
+        .expect("no system errors")
+        .expect("table is created");
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["1", "a@b.c"]);
+    storage
+}
+
+fn select_all(storage: &mut PersistentStorage) -> Vec<Vec<String>> {
+    storage
+        .select_all_from("schema_name", "table_name", vec!["id".to_owned(), "email".to_owned()])
+        .expect("no system errors")
+        .expect("values are selected")
+        .1
+}
+
+#[rstest::rstest]
+fn table_constraints(with_constraints: PersistentStorage) {
+    let constraints = with_constraints
+        .table_constraints("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("table exists");
+
+    assert_eq!(constraints.len(), 4);
+    assert!(constraints.contains(&TableConstraint::NotNull("id".to_owned())));
+    assert_eq!(
+        with_constraints
+            .table_constraints("schema_name", "non_existent")
+            .expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn insert_row_with_duplicate_primary_key(mut with_constraints: PersistentStorage) {
+    assert_eq!(
+        with_constraints
+            .insert_into(
+                "schema_name",
+                "table_name",
+                vec![],
+                vec![vec!["1".to_owned(), "d@e.f".to_owned()]]
+            )
+            .expect("no system errors"),
+        Err(OperationOnTableError::UniqueViolation("table_name_pkey".to_owned()))
+    );
+    assert_eq!(
+        select_all(&mut with_constraints),
+        vec![vec!["1".to_owned(), "a@b.c".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn insert_rows_that_duplicate_each_other(mut with_constraints: PersistentStorage) {
+    assert_eq!(
+        with_constraints
+            .insert_into(
+                "schema_name",
+                "table_name",
+                vec![],
+                vec![
+                    vec!["2".to_owned(), "d@e.f".to_owned()],
+                    vec!["3".to_owned(), "d@e.f".to_owned()]
+                ]
+            )
+            .expect("no system errors"),
+        Err(OperationOnTableError::UniqueViolation(
+            "table_name_email_key".to_owned()
+        ))
+    );
+    assert_eq!(
+        select_all(&mut with_constraints),
+        vec![vec!["1".to_owned(), "a@b.c".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn insert_rows_with_unique_values(mut with_constraints: PersistentStorage) {
+    insert_into(
+        &mut with_constraints,
+        "schema_name",
+        "table_name",
+        vec![],
+        vec!["2", "d@e.f"],
+    );
+
+    assert_eq!(
+        select_all(&mut with_constraints),
+        vec![
+            vec!["1".to_owned(), "a@b.c".to_owned()],
+            vec!["2".to_owned(), "d@e.f".to_owned()]
+        ]
+    );
+}
+
+#[rstest::rstest]
+fn update_rows_to_the_same_unique_value(mut with_constraints: PersistentStorage) {
+    insert_into(
+        &mut with_constraints,
+        "schema_name",
+        "table_name",
+        vec![],
+        vec!["2", "d@e.f"],
+    );
+
+    assert_eq!(
+        with_constraints
+            .update_all(
+                "schema_name",
+                "table_name",
+                vec![("email".to_owned(), "g@h.i".to_owned())]
+            )
+            .expect("no system errors"),
+        Err(OperationOnTableError::UniqueViolation(
+            "table_name_email_key".to_owned()
+        ))
+    );
+}
+
+#[rstest::rstest]
+fn update_single_row_of_unique_column(mut with_constraints: PersistentStorage) {
+    assert_eq!(
+        with_constraints
+            .update_all("schema_name", "table_name", vec![("id".to_owned(), "2".to_owned())])
+            .expect("no system errors"),
+        Ok(1)
+    );
+}
+
+#[rstest::rstest]
+fn insert_row_without_not_null_column(mut with_constraints: PersistentStorage) {
+    assert_eq!(
+        with_constraints
+            .insert_into(
+                "schema_name",
+                "table_name",
+                vec!["email".to_owned()],
+                vec![vec!["d@e.f".to_owned()]]
+            )
+            .expect("no system errors"),
+        Err(OperationOnTableError::NotNullViolation("id".to_owned()))
+    );
+}
+
+#[rstest::rstest]
+fn insert_row_that_violates_check(mut with_constraints: PersistentStorage) {
+    assert_eq!(
+        with_constraints
+            .insert_into(
+                "schema_name",
+                "table_name",
+                vec![],
+                vec![vec!["-1".to_owned(), "d@e.f".to_owned()]]
+            )
+            .expect("no system errors"),
+        Err(OperationOnTableError::CheckViolation("table_name_id_check".to_owned()))
+    );
+}
+
+#[rstest::rstest]
+fn update_rows_that_violate_check(mut with_constraints: PersistentStorage) {
+    assert_eq!(
+        with_constraints
+            .update_all("schema_name", "table_name", vec![("id".to_owned(), "0".to_owned())])
+            .expect("no system errors"),
+        Err(OperationOnTableError::CheckViolation("table_name_id_check".to_owned()))
+    );
+    assert_eq!(
+        select_all(&mut with_constraints),
+        vec![vec!["1".to_owned(), "a@b.c".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn column_defaults(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    storage
+        .create_table_with_constraints(
+            "schema_name",
+            "table_name",
+            vec![
+                ("id".to_owned(), SqlType::Integer),
+                ("status".to_owned(), SqlType::VarChar(10)),
+            ],
+            vec![
+                TableConstraint::NotNull("id".to_owned()),
+                TableConstraint::Default("status".to_owned(), DefaultValue::Constant("new".to_owned())),
+            ],
+        )
+        .expect("no system errors")
+        .expect("table is created");
+
+    assert_eq!(
+        storage
+            .column_defaults("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(vec![("status".to_owned(), DefaultValue::Constant("new".to_owned()))])
+    );
+}
+
+#[rstest::rstest]
+fn column_defaults_of_non_existent_table(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+
+    assert_eq!(
+        storage
+            .column_defaults("schema_name", "table_name")
+            .expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+}
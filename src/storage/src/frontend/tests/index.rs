@@ -0,0 +1,610 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::{Comparison, CreateIndexError, DropIndexError, TableAlteration, UniqueConstraint};
+use sql_types::SqlType;
+
+#[rstest::fixture]
+fn with_primary_key(mut storage: PersistentStorage) -> PersistentStorage {
+    create_schema(&mut storage, "schema_name");
+    storage
+        .create_table_with_constraints(
+            "schema_name",
+            "table_name",
+            vec![
+                ("id".to_owned(), SqlType::Integer),
+                ("name".to_owned(), SqlType::VarChar(20)),
+            ],
+            vec![TableConstraint::Unique(UniqueConstraint {
+                name: "table_name_pkey".to_owned(),
+                columns: vec!["id".to_owned()],
+                is_primary: true,
+            })],
+        )
+        .expect("no system errors")
+        .expect("table is created");
+    for (id, name) in &[("-2", "a"), ("3", "b"), ("1", "c"), ("300", "d")] {
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec![id, name]);
+    }
+    storage
+}
+
+fn select_where(storage: &PersistentStorage, key_conditions: Vec<(Comparison, &str)>) -> Vec<Vec<String>> {
+    let (_columns, records) = storage
+        .select_stream_where(
+            "schema_name",
+            "table_name",
+            vec!["id".to_owned(), "name".to_owned()],
+            key_conditions
+                .into_iter()
+                .map(|(comparison, value)| ("id".to_owned(), comparison, value.to_owned()))
+                .collect(),
+        )
+        .expect("no system errors")
+        .expect("values are selected");
+    records.collect::<SystemResult<_>>().expect("no system errors")
+}
+
+fn row(id: &str, name: &str) -> Vec<String> {
+    vec![id.to_owned(), name.to_owned()]
+}
+
+#[rstest::rstest]
+fn rows_are_read_in_order_of_primary_key(with_primary_key: PersistentStorage) {
+    assert_eq!(
+        select_where(
+            &with_primary_key,
+            vec![(Comparison::GtEq, "-2"), (Comparison::Lt, "300")]
+        ),
+        vec![row("-2", "a"), row("1", "c"), row("3", "b")]
+    );
+}
+
+#[rstest::rstest]
+fn narrowest_bounds_are_taken(with_primary_key: PersistentStorage) {
+    assert_eq!(
+        select_where(
+            &with_primary_key,
+            vec![
+                (Comparison::Gt, "-5"),
+                (Comparison::Gt, "1"),
+                (Comparison::LtEq, "300"),
+                (Comparison::NotEq, "3")
+            ]
+        ),
+        vec![row("3", "b"), row("300", "d")]
+    );
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Eq, "1"), (Comparison::GtEq, "1")]),
+        vec![row("1", "c")]
+    );
+}
+
+#[rstest::rstest]
+fn conditions_that_no_key_satisfies(with_primary_key: PersistentStorage) {
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Gt, "3"), (Comparison::Lt, "3")]),
+        Vec::<Vec<String>>::new()
+    );
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Eq, "1"), (Comparison::Eq, "3")]),
+        Vec::<Vec<String>>::new()
+    );
+}
+
+#[rstest::rstest]
+fn values_of_other_type_do_not_narrow_read(with_primary_key: PersistentStorage) {
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Lt, "3000000000")]).len(),
+        4
+    );
+    assert_eq!(select_where(&with_primary_key, vec![(Comparison::Gt, "0300")]).len(), 4);
+}
+
+#[rstest::rstest]
+fn index_follows_updated_and_deleted_rows(mut with_primary_key: PersistentStorage) {
+    with_primary_key
+        .delete_all_from("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("rows are deleted");
+    insert_into(
+        &mut with_primary_key,
+        "schema_name",
+        "table_name",
+        vec![],
+        vec!["5", "e"],
+    );
+    assert_eq!(
+        with_primary_key
+            .update_all("schema_name", "table_name", vec![("id".to_owned(), "7".to_owned())])
+            .expect("no system errors"),
+        Ok(1)
+    );
+
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Eq, "5")]),
+        Vec::<Vec<String>>::new()
+    );
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Gt, "-3")]),
+        vec![row("7", "e")]
+    );
+}
+
+#[rstest::rstest]
+fn reverted_changes_of_index(mut with_primary_key: PersistentStorage) {
+    with_primary_key.start_journal();
+    insert_into(
+        &mut with_primary_key,
+        "schema_name",
+        "table_name",
+        vec![],
+        vec!["2", "e"],
+    );
+    with_primary_key
+        .update_all("schema_name", "table_name", vec![("name".to_owned(), "f".to_owned())])
+        .expect("no system errors")
+        .expect("rows are updated");
+    with_primary_key.revert().expect("no system errors");
+
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Gt, "0"), (Comparison::Lt, "5")]),
+        vec![row("1", "c"), row("3", "b")]
+    );
+}
+
+#[rstest::rstest]
+fn no_rows_deleted_by_keys(mut with_primary_key: PersistentStorage) {
+    assert_eq!(
+        with_primary_key
+            .delete_rows("schema_name", "table_name", vec![])
+            .expect("no system errors"),
+        Ok(0)
+    );
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Eq, "3")]),
+        vec![row("3", "b")]
+    );
+}
+
+#[rstest::rstest]
+fn index_of_dropped_primary_key_column(mut with_primary_key: PersistentStorage) {
+    with_primary_key
+        .alter_table(
+            "schema_name",
+            "table_name",
+            TableAlteration::DropColumn("id".to_owned()),
+        )
+        .expect("no system errors")
+        .expect("column is dropped");
+
+    assert_eq!(
+        with_primary_key
+            .indexes_size("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(0)
+    );
+}
+
+#[rstest::rstest]
+fn size_of_index(mut with_primary_key: PersistentStorage) {
+    assert!(
+        with_primary_key
+            .indexes_size("schema_name", "table_name")
+            .expect("no system errors")
+            .expect("table exists")
+            > 0
+    );
+
+    with_primary_key
+        .drop_table("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("table is dropped");
+    assert_eq!(
+        with_primary_key
+            .indexes_size("schema_name", "table_name")
+            .expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn index_follows_rows_updated_by_keys(mut with_primary_key: PersistentStorage) {
+    let (_columns, rows) = with_primary_key
+        .select_keyed_where(
+            "schema_name",
+            "table_name",
+            vec![("id".to_owned(), Comparison::Eq, "3".to_owned())],
+        )
+        .expect("no system errors")
+        .expect("rows are selected");
+    let updated = rows
+        .into_iter()
+        .map(|(key, _values)| (key, vec!["-1".to_owned()]))
+        .collect();
+    assert_eq!(
+        with_primary_key
+            .update_rows("schema_name", "table_name", vec!["id".to_owned()], updated)
+            .expect("no system errors"),
+        Ok(1)
+    );
+
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Eq, "3")]),
+        Vec::<Vec<String>>::new()
+    );
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Lt, "1")]),
+        vec![row("-2", "a"), row("-1", "b")]
+    );
+}
+
+#[rstest::rstest]
+fn rows_updated_by_keys_violate_primary_key(mut with_primary_key: PersistentStorage) {
+    let (_columns, rows) = with_primary_key
+        .select_keyed_where(
+            "schema_name",
+            "table_name",
+            vec![("id".to_owned(), Comparison::Gt, "1".to_owned())],
+        )
+        .expect("no system errors")
+        .expect("rows are selected");
+    let updated = rows
+        .into_iter()
+        .map(|(key, _values)| (key, vec!["1".to_owned()]))
+        .collect();
+
+    assert_eq!(
+        with_primary_key
+            .update_rows("schema_name", "table_name", vec!["id".to_owned()], updated)
+            .expect("no system errors"),
+        Err(OperationOnTableError::UniqueViolation("table_name_pkey".to_owned()))
+    );
+}
+
+#[rstest::rstest]
+fn no_rows_updated_by_keys(mut with_primary_key: PersistentStorage) {
+    assert_eq!(
+        with_primary_key
+            .update_rows("schema_name", "table_name", vec!["id".to_owned()], vec![])
+            .expect("no system errors"),
+        Ok(0)
+    );
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Eq, "3")]),
+        vec![row("3", "b")]
+    );
+}
+
+#[rstest::rstest]
+fn index_follows_rows_deleted_by_keys(mut with_primary_key: PersistentStorage) {
+    let (_columns, rows) = with_primary_key
+        .select_keyed_where(
+            "schema_name",
+            "table_name",
+            vec![("id".to_owned(), Comparison::Lt, "1".to_owned())],
+        )
+        .expect("no system errors")
+        .expect("rows are selected");
+    let deleted = rows.into_iter().map(|(key, _values)| key).collect();
+    assert_eq!(
+        with_primary_key
+            .delete_rows("schema_name", "table_name", deleted)
+            .expect("no system errors"),
+        Ok(1)
+    );
+
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Lt, "1")]),
+        Vec::<Vec<String>>::new()
+    );
+}
+
+#[rstest::rstest]
+fn index_of_truncated_table(mut with_primary_key: PersistentStorage) {
+    assert_eq!(
+        with_primary_key
+            .truncate("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(())
+    );
+    insert_into(
+        &mut with_primary_key,
+        "schema_name",
+        "table_name",
+        vec![],
+        vec!["3", "c"],
+    );
+
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Eq, "3")]),
+        vec![row("3", "c")]
+    );
+    assert_eq!(
+        select_where(&with_primary_key, vec![(Comparison::Lt, "3")]),
+        Vec::<Vec<String>>::new()
+    );
+}
+
+#[rstest::fixture]
+fn with_index(mut storage: PersistentStorage) -> PersistentStorage {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("id", SqlType::Integer), ("name", SqlType::VarChar(20))],
+    );
+    for (id, name) in &[("1", "a"), ("2", "b"), ("3", "a"), ("4", "ab")] {
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec![id, name]);
+    }
+    storage
+        .create_index("schema_name", "table_name", "name_idx", "name")
+        .expect("no system errors")
+        .expect("index is created");
+    storage
+}
+
+fn select_by_name(storage: &PersistentStorage, name: &str) -> Vec<Vec<String>> {
+    let (_columns, records) = storage
+        .select_stream_where(
+            "schema_name",
+            "table_name",
+            vec!["id".to_owned(), "name".to_owned()],
+            vec![("name".to_owned(), Comparison::Eq, name.to_owned())],
+        )
+        .expect("no system errors")
+        .expect("values are selected");
+    records.collect::<SystemResult<_>>().expect("no system errors")
+}
+
+fn keys_by_name(storage: &PersistentStorage, name: &str) -> Vec<Key> {
+    let (_columns, rows) = storage
+        .select_keyed_where(
+            "schema_name",
+            "table_name",
+            vec![("name".to_owned(), Comparison::Eq, name.to_owned())],
+        )
+        .expect("no system errors")
+        .expect("rows are selected");
+    rows.into_iter().map(|(key, _values)| key).collect()
+}
+
+#[rstest::rstest]
+fn rows_are_looked_up_by_indexed_values(with_index: PersistentStorage) {
+    assert_eq!(select_by_name(&with_index, "a"), vec![row("1", "a"), row("3", "a")]);
+    assert_eq!(select_by_name(&with_index, "ab"), vec![row("4", "ab")]);
+    assert_eq!(select_by_name(&with_index, "c"), Vec::<Vec<String>>::new());
+}
+
+#[rstest::rstest]
+fn conditions_on_columns_without_index_do_not_narrow_read(with_index: PersistentStorage) {
+    let (_columns, records) = with_index
+        .select_stream_where(
+            "schema_name",
+            "table_name",
+            vec!["id".to_owned()],
+            vec![
+                ("id".to_owned(), Comparison::Eq, "1".to_owned()),
+                ("name".to_owned(), Comparison::Gt, "a".to_owned()),
+            ],
+        )
+        .expect("no system errors")
+        .expect("values are selected");
+    assert_eq!(records.count(), 4);
+}
+
+#[rstest::rstest]
+fn index_follows_changed_rows(mut with_index: PersistentStorage) {
+    insert_into(&mut with_index, "schema_name", "table_name", vec![], vec!["5", "a"]);
+    let updated = keys_by_name(&with_index, "a")
+        .into_iter()
+        .take(1)
+        .map(|key| (key, vec!["c".to_owned()]))
+        .collect();
+    assert_eq!(
+        with_index
+            .update_rows("schema_name", "table_name", vec!["name".to_owned()], updated)
+            .expect("no system errors"),
+        Ok(1)
+    );
+    let deleted = keys_by_name(&with_index, "a").into_iter().take(1).collect();
+    assert_eq!(
+        with_index
+            .delete_rows("schema_name", "table_name", deleted)
+            .expect("no system errors"),
+        Ok(1)
+    );
+
+    assert_eq!(select_by_name(&with_index, "a"), vec![row("5", "a")]);
+    assert_eq!(select_by_name(&with_index, "c"), vec![row("1", "c")]);
+
+    assert_eq!(
+        with_index
+            .update_all("schema_name", "table_name", vec![("name".to_owned(), "d".to_owned())])
+            .expect("no system errors"),
+        Ok(4)
+    );
+    assert_eq!(select_by_name(&with_index, "a"), Vec::<Vec<String>>::new());
+    assert_eq!(select_by_name(&with_index, "d").len(), 4);
+
+    assert_eq!(
+        with_index
+            .delete_all_from("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(4)
+    );
+    assert_eq!(select_by_name(&with_index, "d"), Vec::<Vec<String>>::new());
+}
+
+#[rstest::rstest]
+fn reverted_changes_of_indexed_rows(mut with_index: PersistentStorage) {
+    with_index.start_journal();
+    insert_into(&mut with_index, "schema_name", "table_name", vec![], vec!["5", "a"]);
+    let deleted = keys_by_name(&with_index, "b");
+    with_index
+        .delete_rows("schema_name", "table_name", deleted)
+        .expect("no system errors")
+        .expect("rows are deleted");
+    with_index.revert().expect("no system errors");
+
+    assert_eq!(select_by_name(&with_index, "a"), vec![row("1", "a"), row("3", "a")]);
+    assert_eq!(select_by_name(&with_index, "b"), vec![row("2", "b")]);
+}
+
+#[rstest::rstest]
+fn index_of_truncated_table_is_empty(mut with_index: PersistentStorage) {
+    assert_eq!(
+        with_index
+            .truncate("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(())
+    );
+    insert_into(&mut with_index, "schema_name", "table_name", vec![], vec!["5", "a"]);
+
+    assert_eq!(select_by_name(&with_index, "a"), vec![row("5", "a")]);
+}
+
+#[rstest::rstest]
+fn index_that_can_not_be_created(mut with_index: PersistentStorage) {
+    assert_eq!(
+        with_index
+            .create_index("schema_name", "table_name", "name_idx", "id")
+            .expect("no system errors"),
+        Err(CreateIndexError::IndexAlreadyExists)
+    );
+    assert_eq!(
+        with_index
+            .create_index("schema_name", "table_name", "table_name", "id")
+            .expect("no system errors"),
+        Err(CreateIndexError::IndexAlreadyExists)
+    );
+    assert_eq!(
+        with_index
+            .create_index("schema_name", "table_name", "id_idx", "non_existent")
+            .expect("no system errors"),
+        Err(CreateIndexError::ColumnDoesNotExist("non_existent".to_owned()))
+    );
+    assert_eq!(
+        with_index
+            .create_index("schema_name", "non_existent", "id_idx", "id")
+            .expect("no system errors"),
+        Err(CreateIndexError::TableDoesNotExist)
+    );
+    assert_eq!(
+        with_index
+            .create_index("non_existent", "table_name", "id_idx", "id")
+            .expect("no system errors"),
+        Err(CreateIndexError::SchemaDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn dropped_index(mut with_index: PersistentStorage) {
+    assert_eq!(
+        with_index
+            .drop_index("schema_name", "table_name")
+            .expect("no system errors"),
+        Err(DropIndexError::IndexDoesNotExist)
+    );
+    assert_eq!(
+        with_index
+            .drop_index("schema_name", "name_idx")
+            .expect("no system errors"),
+        Ok(())
+    );
+    assert_eq!(
+        with_index
+            .drop_index("schema_name", "name_idx")
+            .expect("no system errors"),
+        Err(DropIndexError::IndexDoesNotExist)
+    );
+    assert_eq!(
+        with_index
+            .drop_index("non_existent", "name_idx")
+            .expect("no system errors"),
+        Err(DropIndexError::SchemaDoesNotExist)
+    );
+
+    // rows are not looked up without the index
+    assert_eq!(select_by_name(&with_index, "a").len(), 4);
+    assert_eq!(
+        with_index
+            .indexes_size("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(0)
+    );
+}
+
+#[rstest::rstest]
+fn indexes_are_dropped_along_with_their_columns_and_tables(mut with_index: PersistentStorage) {
+    with_index
+        .create_index("schema_name", "table_name", "id_idx", "id")
+        .expect("no system errors")
+        .expect("index is created");
+    with_index
+        .alter_table(
+            "schema_name",
+            "table_name",
+            TableAlteration::DropColumn("name".to_owned()),
+        )
+        .expect("no system errors")
+        .expect("column is dropped");
+    assert_eq!(
+        with_index
+            .drop_index("schema_name", "name_idx")
+            .expect("no system errors"),
+        Err(DropIndexError::IndexDoesNotExist)
+    );
+
+    with_index
+        .drop_table("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("table is dropped");
+    assert_eq!(
+        with_index
+            .drop_index("schema_name", "id_idx")
+            .expect("no system errors"),
+        Err(DropIndexError::IndexDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn index_of_renamed_column(mut with_index: PersistentStorage) {
+    with_index
+        .alter_table(
+            "schema_name",
+            "table_name",
+            TableAlteration::RenameColumn {
+                from: "name".to_owned(),
+                to: "title".to_owned(),
+            },
+        )
+        .expect("no system errors")
+        .expect("column is renamed");
+
+    let (_columns, records) = with_index
+        .select_stream_where(
+            "schema_name",
+            "table_name",
+            vec!["id".to_owned()],
+            vec![("title".to_owned(), Comparison::Eq, "b".to_owned())],
+        )
+        .expect("no system errors")
+        .expect("values are selected");
+    assert_eq!(
+        records.collect::<SystemResult<Vec<Vec<String>>>>(),
+        Ok(vec![vec!["2".to_owned()]])
+    );
+}
@@ -0,0 +1,180 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+fn select_all(storage: &mut PersistentStorage) -> Vec<Vec<String>> {
+    storage
+        .select_all_from("schema_name", "table_name", vec!["column_test".to_owned()])
+        .expect("no system errors")
+        .expect("values are selected")
+        .1
+}
+
+#[rstest::fixture]
+fn with_table(mut storage: PersistentStorage) -> PersistentStorage {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["123"]);
+    storage
+}
+
+#[rstest::rstest]
+fn changes_are_not_recorded_by_default(mut with_table: PersistentStorage) {
+    insert_into(&mut with_table, "schema_name", "table_name", vec![], vec!["456"]);
+
+    with_table.revert().expect("no system errors");
+
+    assert_eq!(
+        select_all(&mut with_table),
+        vec![vec!["123".to_owned()], vec!["456".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn revert_insert(mut with_table: PersistentStorage) {
+    with_table.start_journal();
+    insert_into(&mut with_table, "schema_name", "table_name", vec![], vec!["456"]);
+
+    assert_eq!(
+        select_all(&mut with_table),
+        vec![vec!["123".to_owned()], vec!["456".to_owned()]]
+    );
+
+    with_table.revert().expect("no system errors");
+
+    assert_eq!(select_all(&mut with_table), vec![vec!["123".to_owned()]]);
+}
+
+#[rstest::rstest]
+fn revert_update(mut with_table: PersistentStorage) {
+    with_table.start_journal();
+    with_table
+        .update_all(
+            "schema_name",
+            "table_name",
+            vec![("column_test".to_owned(), "456".to_owned())],
+        )
+        .expect("no system errors")
+        .expect("values are updated");
+
+    assert_eq!(select_all(&mut with_table), vec![vec!["456".to_owned()]]);
+
+    with_table.revert().expect("no system errors");
+
+    assert_eq!(select_all(&mut with_table), vec![vec!["123".to_owned()]]);
+}
+
+#[rstest::rstest]
+fn revert_delete(mut with_table: PersistentStorage) {
+    with_table.start_journal();
+    with_table
+        .delete_all_from("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("values are deleted");
+
+    assert_eq!(select_all(&mut with_table), Vec::<Vec<String>>::new());
+
+    with_table.revert().expect("no system errors");
+
+    assert_eq!(select_all(&mut with_table), vec![vec!["123".to_owned()]]);
+}
+
+#[rstest::rstest]
+fn revert_truncate(mut with_table: PersistentStorage) {
+    with_table.start_journal();
+    with_table
+        .truncate("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("table is truncated");
+
+    assert_eq!(select_all(&mut with_table), Vec::<Vec<String>>::new());
+
+    with_table.revert().expect("no system errors");
+
+    assert_eq!(select_all(&mut with_table), vec![vec!["123".to_owned()]]);
+}
+
+#[rstest::rstest]
+fn revert_changes_in_reverse_order(mut with_table: PersistentStorage) {
+    with_table.start_journal();
+    insert_into(&mut with_table, "schema_name", "table_name", vec![], vec!["456"]);
+    with_table
+        .update_all(
+            "schema_name",
+            "table_name",
+            vec![("column_test".to_owned(), "789".to_owned())],
+        )
+        .expect("no system errors")
+        .expect("values are updated");
+    with_table
+        .delete_all_from("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("values are deleted");
+
+    with_table.revert().expect("no system errors");
+
+    assert_eq!(select_all(&mut with_table), vec![vec!["123".to_owned()]]);
+}
+
+#[rstest::rstest]
+fn revert_changes_of_dropped_table(mut with_table: PersistentStorage) {
+    with_table.start_journal();
+    insert_into(&mut with_table, "schema_name", "table_name", vec![], vec!["456"]);
+    with_table
+        .drop_table("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("table is dropped");
+
+    with_table.revert().expect("no system errors");
+}
+
+#[rstest::rstest]
+fn kept_changes_are_not_reverted(mut with_table: PersistentStorage) {
+    with_table.start_journal();
+    insert_into(&mut with_table, "schema_name", "table_name", vec![], vec!["456"]);
+    with_table.clear_journal().expect("no system errors");
+
+    with_table.revert().expect("no system errors");
+
+    assert_eq!(
+        select_all(&mut with_table),
+        vec![vec!["123".to_owned()], vec!["456".to_owned()]]
+    );
+}
+
+#[rstest::rstest]
+fn waiting_sessions_are_notified_when_changes_are_reverted(mut with_table: PersistentStorage) {
+    with_table.start_journal();
+    let journal_cleared = with_table.journal_cleared();
+    let guard = journal_cleared.lock();
+    let reverting = thread::spawn(move || {
+        with_table.revert().expect("no system errors");
+        with_table
+    });
+
+    let started = Instant::now();
+    let _guard = journal_cleared.wait(guard, Some(Duration::from_secs(10)));
+
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(!reverting.join().expect("changes are reverted").is_journaled());
+}
@@ -13,12 +13,25 @@
 // limitations under the License.
 
 use super::*;
+use sql_types::ConstraintError;
 
+#[cfg(test)]
+mod alter_table;
+#[cfg(test)]
+mod constraints;
+#[cfg(test)]
+mod index;
+#[cfg(test)]
+mod journal;
+#[cfg(test)]
+mod persistent;
 #[cfg(test)]
 mod queries;
 #[cfg(test)]
 mod schema;
 #[cfg(test)]
+mod sequence;
+#[cfg(test)]
 mod table;
 
 type PersistentStorage = FrontendStorage<SledBackendStorage>;
@@ -0,0 +1,283 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::{Comparison, TableAlteration, UniqueConstraint};
+
+#[test]
+fn inserted_rows_are_kept_after_reopening() {
+    let data_directory = std::env::temp_dir().join(format!("database_frontend_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_directory);
+    {
+        let mut storage = FrontendStorage::persistent(&data_directory).expect("no system errors");
+        create_schema_with_table(
+            &mut storage,
+            "schema_name",
+            "table_name",
+            vec![("column_test", SqlType::SmallInt)],
+        );
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["123"]);
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["456"]);
+    }
+
+    let mut storage = crate::reopen(|| FrontendStorage::persistent(&data_directory)).expect("no system errors");
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["789"]);
+
+    assert_eq!(
+        storage
+            .select_all_from("schema_name", "table_name", vec!["column_test".to_owned()])
+            .expect("no system errors"),
+        Ok((
+            vec![("column_test".to_owned(), SqlType::SmallInt)],
+            vec![vec!["123".to_owned()], vec!["456".to_owned()], vec!["789".to_owned()]]
+        ))
+    );
+
+    drop(storage);
+    std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+}
+
+#[test]
+fn catalog_is_kept_after_reopening() {
+    let data_directory = std::env::temp_dir().join(format!("database_catalog_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_directory);
+    {
+        let mut storage = FrontendStorage::persistent(&data_directory).expect("no system errors");
+        create_schema(&mut storage, "schema_name");
+        storage
+            .create_table_with_constraints(
+                "schema_name",
+                "table_name",
+                vec![("id".to_owned(), SqlType::SmallInt)],
+                vec![TableConstraint::Unique(UniqueConstraint {
+                    name: "table_name_pkey".to_owned(),
+                    columns: vec!["id".to_owned()],
+                    is_primary: true,
+                })],
+            )
+            .expect("no system errors")
+            .expect("table is created");
+        storage
+            .alter_table(
+                "schema_name",
+                "table_name",
+                TableAlteration::AddColumn {
+                    name: "name".to_owned(),
+                    sql_type: SqlType::VarChar(10),
+                    constraints: vec![],
+                },
+            )
+            .expect("no system errors")
+            .expect("column is added");
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["1", "a"]);
+    }
+
+    let mut storage = crate::reopen(|| FrontendStorage::persistent(&data_directory)).expect("no system errors");
+
+    assert_eq!(
+        storage.schema_names().expect("no system errors"),
+        vec!["schema_name".to_owned()]
+    );
+    assert_eq!(
+        storage.table_names("schema_name").expect("no system errors"),
+        Ok(vec!["table_name".to_owned()])
+    );
+    assert_eq!(
+        storage
+            .table_columns("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(vec![
+            ("id".to_owned(), SqlType::SmallInt),
+            ("name".to_owned(), SqlType::VarChar(10))
+        ])
+    );
+    assert_eq!(
+        storage
+            .insert_into(
+                "schema_name",
+                "table_name",
+                vec![],
+                vec![vec!["1".to_owned(), "b".to_owned()]]
+            )
+            .expect("no system errors"),
+        Err(OperationOnTableError::UniqueViolation("table_name_pkey".to_owned()))
+    );
+
+    drop(storage);
+    std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+}
+
+#[test]
+fn objects_are_reconciled_with_catalog_after_reopening() {
+    let data_directory = std::env::temp_dir().join(format!("database_reconcile_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_directory);
+    {
+        let mut storage = FrontendStorage::persistent(&data_directory).expect("no system errors");
+        create_schema_with_table(
+            &mut storage,
+            "schema_name",
+            "table_name",
+            vec![("column_test", SqlType::SmallInt)],
+        );
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["123"]);
+    }
+    let schema = FIRST_OBJECT_ID.to_string();
+    let table = FIRST_OBJECT_ID + 1;
+    {
+        let mut backend = crate::reopen(|| SledBackendStorage::persistent(&data_directory)).expect("no system errors");
+        // crash while a schema is created
+        backend.create_namespace("99999").expect("namespace is created");
+        backend.create_object("99999", "100000").expect("object is created");
+        // crash while a table is created
+        backend.create_object(&schema, "99998").expect("object is created");
+        backend
+            .write("system", "columns", vec![(99998u64.to_be_bytes().to_vec(), vec![])])
+            .expect("columns are written");
+        // crash while the table is truncated
+        backend
+            .drop_object(&schema, &table.to_string())
+            .expect("object is dropped");
+    }
+
+    let storage = crate::reopen(|| FrontendStorage::persistent(&data_directory)).expect("no system errors");
+
+    assert_eq!(
+        storage
+            .select_all_from("schema_name", "table_name", vec!["column_test".to_owned()])
+            .expect("no system errors"),
+        Ok((vec![("column_test".to_owned(), SqlType::SmallInt)], vec![]))
+    );
+    drop(storage);
+    let backend = crate::reopen(|| SledBackendStorage::persistent(&data_directory)).expect("no system errors");
+    assert_eq!(backend.namespaces(), Ok(vec![schema.clone(), "system".to_owned()]));
+    assert_eq!(backend.object_names(&schema), Ok(vec![table.to_string()]));
+    assert_eq!(
+        backend
+            .read_keys("system", "columns")
+            .expect("columns are read")
+            .collect::<SystemResult<Vec<Key>>>()
+            .expect("no system errors"),
+        vec![table.to_be_bytes().to_vec()]
+    );
+
+    drop(backend);
+    std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+}
+
+#[test]
+fn journaled_changes_are_reverted_after_reopening() {
+    let data_directory = std::env::temp_dir().join(format!("database_journal_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_directory);
+    {
+        let mut storage = FrontendStorage::persistent(&data_directory).expect("no system errors");
+        create_schema_with_table(
+            &mut storage,
+            "schema_name",
+            "table_name",
+            vec![("column_test", SqlType::SmallInt)],
+        );
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["123"]);
+        storage.start_journal();
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["456"]);
+        storage
+            .update_all(
+                "schema_name",
+                "table_name",
+                vec![("column_test".to_owned(), "789".to_owned())],
+            )
+            .expect("no system errors")
+            .expect("values are updated");
+    }
+
+    let storage = crate::reopen(|| FrontendStorage::persistent(&data_directory)).expect("no system errors");
+
+    assert_eq!(
+        storage
+            .select_all_from("schema_name", "table_name", vec!["column_test".to_owned()])
+            .expect("no system errors"),
+        Ok((
+            vec![("column_test".to_owned(), SqlType::SmallInt)],
+            vec![vec!["123".to_owned()]]
+        ))
+    );
+
+    drop(storage);
+    std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+}
+
+#[test]
+fn indexes_are_kept_after_reopening() {
+    let data_directory = std::env::temp_dir().join(format!("database_index_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_directory);
+    {
+        let mut storage = FrontendStorage::persistent(&data_directory).expect("no system errors");
+        create_schema_with_table(
+            &mut storage,
+            "schema_name",
+            "table_name",
+            vec![("column_test", SqlType::SmallInt)],
+        );
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["123"]);
+        insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["456"]);
+        storage
+            .create_index("schema_name", "table_name", "column_test_idx", "column_test")
+            .expect("no system errors")
+            .expect("index is created");
+    }
+
+    let storage = crate::reopen(|| FrontendStorage::persistent(&data_directory)).expect("no system errors");
+
+    let (_columns, records) = storage
+        .select_stream_where(
+            "schema_name",
+            "table_name",
+            vec!["column_test".to_owned()],
+            vec![("column_test".to_owned(), Comparison::Eq, "456".to_owned())],
+        )
+        .expect("no system errors")
+        .expect("values are selected");
+    assert_eq!(
+        records.collect::<SystemResult<Vec<Vec<String>>>>(),
+        Ok(vec![vec!["456".to_owned()]])
+    );
+
+    drop(storage);
+    std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+}
+
+#[test]
+fn corrupted_columns_are_reported_on_reopening() {
+    let data_directory = std::env::temp_dir().join(format!("database_corrupted_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_directory);
+    {
+        let mut storage = FrontendStorage::persistent(&data_directory).expect("no system errors");
+        create_schema_with_table(
+            &mut storage,
+            "schema_name",
+            "table_name",
+            vec![("column_test", SqlType::SmallInt)],
+        );
+    }
+    {
+        let table = FIRST_OBJECT_ID + 1;
+        let mut backend = crate::reopen(|| SledBackendStorage::persistent(&data_directory)).expect("no system errors");
+        backend
+            .write("system", "columns", vec![(table.to_be_bytes().to_vec(), vec![1])])
+            .expect("columns are written");
+    }
+
+    assert!(crate::reopen(|| FrontendStorage::persistent(&data_directory)).is_err());
+
+    std::fs::remove_dir_all(data_directory).expect("data directory is removed");
+}
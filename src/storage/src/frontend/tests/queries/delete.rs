@@ -71,3 +71,95 @@ fn delete_all_from_table(mut storage: PersistentStorage) {
         Ok((vec![("column_test".to_owned(), SqlType::SmallInt)], vec![]))
     );
 }
+
+#[rstest::rstest]
+fn delete_rows_by_keys(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("id", SqlType::SmallInt), ("amount", SqlType::Integer)],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["1", "10"]);
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["2", "20"]);
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["3", "30"]);
+
+    let (_columns, rows) = storage
+        .select_keyed_where("schema_name", "table_name", vec![])
+        .expect("no system errors")
+        .expect("rows are selected");
+    let deleted = rows
+        .into_iter()
+        .filter(|(_key, values)| values[0] != "2")
+        .map(|(key, _values)| key)
+        .collect();
+
+    assert_eq!(
+        storage
+            .delete_rows("schema_name", "table_name", deleted)
+            .expect("no system errors"),
+        Ok(2)
+    );
+    assert_eq!(
+        storage
+            .select_all_from("schema_name", "table_name", vec!["id".to_owned(), "amount".to_owned()])
+            .expect("no system errors"),
+        Ok((
+            vec![
+                ("id".to_owned(), SqlType::SmallInt),
+                ("amount".to_owned(), SqlType::Integer)
+            ],
+            vec![vec!["2".to_owned(), "20".to_owned()]]
+        ))
+    );
+}
+
+#[rstest::rstest]
+fn delete_rows_of_non_existent_table(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+
+    assert_eq!(
+        storage
+            .delete_rows("schema_name", "table_name", vec![])
+            .expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn truncate_table(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["123"]);
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["456"]);
+
+    assert_eq!(
+        storage.truncate("schema_name", "table_name").expect("no system errors"),
+        Ok(())
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["789"]);
+
+    assert_eq!(
+        storage
+            .select_all_from("schema_name", "table_name", vec!["column_test".to_owned()])
+            .expect("no system errors"),
+        Ok((
+            vec![("column_test".to_owned(), SqlType::SmallInt)],
+            vec![vec!["789".to_owned()]]
+        ))
+    );
+}
+
+#[rstest::rstest]
+fn truncate_non_existent_table(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+
+    assert_eq!(
+        storage.truncate("schema_name", "table_name").expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+}
@@ -31,7 +31,7 @@ fn with_small_ints_table(mut storage: PersistentStorage) -> PersistentStorage {
 }
 
 #[rstest::rstest]
-fn select_from_table_from_non_existent_schema(mut storage: PersistentStorage) {
+fn select_from_table_from_non_existent_schema(storage: PersistentStorage) {
     assert_eq!(
         storage
             .select_all_from("non_existent", "table_name", vec![])
@@ -368,3 +368,75 @@ fn select_different_character_strings_types(mut storage: PersistentStorage) {
         ))
     );
 }
+
+#[rstest::rstest]
+fn select_values_that_contain_separator_byte(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_si", SqlType::SmallInt), ("column_c", SqlType::Char(124))],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["124", "|"]);
+
+    assert_eq!(
+        storage
+            .select_all_from(
+                "schema_name",
+                "table_name",
+                vec!["column_si".to_owned(), "column_c".to_owned()]
+            )
+            .expect("no system errors"),
+        Ok((
+            vec![
+                ("column_si".to_owned(), SqlType::SmallInt),
+                ("column_c".to_owned(), SqlType::Char(124))
+            ],
+            vec![vec!["124".to_owned(), "|".to_owned()]]
+        ))
+    );
+}
+
+#[rstest::rstest]
+fn select_stream_reads_records_lazily(mut with_small_ints_table: PersistentStorage) {
+    insert_into(
+        &mut with_small_ints_table,
+        "schema_name",
+        "table_name",
+        vec![],
+        vec!["1", "2", "3"],
+    );
+    insert_into(
+        &mut with_small_ints_table,
+        "schema_name",
+        "table_name",
+        vec![],
+        vec!["4", "5", "6"],
+    );
+
+    let (description, mut records) = with_small_ints_table
+        .select_stream(
+            "schema_name",
+            "table_name",
+            vec!["column_3".to_owned(), "column_1".to_owned()],
+        )
+        .expect("no system errors")
+        .expect("records are selected");
+
+    assert_eq!(
+        description,
+        vec![
+            ("column_3".to_owned(), SqlType::SmallInt),
+            ("column_1".to_owned(), SqlType::SmallInt)
+        ]
+    );
+    assert_eq!(
+        records.next().map(|record| record.expect("no system errors")),
+        Some(vec!["3".to_owned(), "1".to_owned()])
+    );
+    assert_eq!(
+        records.next().map(|record| record.expect("no system errors")),
+        Some(vec!["6".to_owned(), "4".to_owned()])
+    );
+    assert!(records.next().is_none());
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use super::*;
+use crate::{CheckConstraint, Comparison};
 use sql_types::SqlType;
 
 #[rstest::rstest]
@@ -58,6 +59,57 @@ fn update_all_records(mut storage: PersistentStorage) {
     );
 }
 
+#[rstest::rstest]
+fn update_values_that_contain_separator_byte(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_si", SqlType::SmallInt), ("column_c", SqlType::Char(124))],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["1", "a"]);
+
+    assert_eq!(
+        storage
+            .update_all(
+                "schema_name",
+                "table_name",
+                vec![
+                    ("column_si".to_owned(), "124".to_owned()),
+                    ("column_c".to_owned(), "|".to_owned())
+                ]
+            )
+            .expect("no system errors"),
+        Ok(1)
+    );
+
+    assert_eq!(
+        storage
+            .table_columns("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(vec![
+            ("column_si".to_owned(), SqlType::SmallInt),
+            ("column_c".to_owned(), SqlType::Char(124))
+        ])
+    );
+    assert_eq!(
+        storage
+            .select_all_from(
+                "schema_name",
+                "table_name",
+                vec!["column_si".to_owned(), "column_c".to_owned()]
+            )
+            .expect("no system errors"),
+        Ok((
+            vec![
+                ("column_si".to_owned(), SqlType::SmallInt),
+                ("column_c".to_owned(), SqlType::Char(124))
+            ],
+            vec![vec!["124".to_owned(), "|".to_owned()]]
+        ))
+    );
+}
+
 #[rstest::rstest]
 fn update_not_existed_table(mut storage: PersistentStorage) {
     create_schema(&mut storage, "schema_name");
@@ -80,6 +132,153 @@ fn update_non_existent_schema(mut storage: PersistentStorage) {
     );
 }
 
+#[rstest::rstest]
+fn update_rows_by_keys(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("id", SqlType::SmallInt), ("amount", SqlType::Integer)],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["1", "10"]);
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["2", "20"]);
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["3", "30"]);
+
+    let (columns, rows) = storage
+        .select_keyed_where("schema_name", "table_name", vec![])
+        .expect("no system errors")
+        .expect("rows are selected");
+    assert_eq!(
+        columns,
+        vec![
+            ("id".to_owned(), SqlType::SmallInt),
+            ("amount".to_owned(), SqlType::Integer)
+        ]
+    );
+    let updated = rows
+        .into_iter()
+        .filter(|(_key, values)| values[0] != "2")
+        .map(|(key, values)| (key, vec![format!("{}1", values[1])]))
+        .collect();
+
+    assert_eq!(
+        storage
+            .update_rows("schema_name", "table_name", vec!["amount".to_owned()], updated)
+            .expect("no system errors"),
+        Ok(2)
+    );
+    assert_eq!(
+        storage
+            .select_all_from("schema_name", "table_name", vec!["id".to_owned(), "amount".to_owned()])
+            .expect("no system errors"),
+        Ok((
+            vec![
+                ("id".to_owned(), SqlType::SmallInt),
+                ("amount".to_owned(), SqlType::Integer)
+            ],
+            vec![
+                vec!["1".to_owned(), "101".to_owned()],
+                vec!["2".to_owned(), "20".to_owned()],
+                vec!["3".to_owned(), "301".to_owned()]
+            ]
+        ))
+    );
+}
+
+#[rstest::rstest]
+fn update_rows_checks_whole_rows(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    // check (low > 0 and high < 100) is kept as a constraint per column
+    let check = |column: &str, comparison, value: &str| {
+        TableConstraint::Check(CheckConstraint {
+            name: "table_name_check".to_owned(),
+            column: column.to_owned(),
+            comparison,
+            value: value.to_owned(),
+        })
+    };
+    storage
+        .create_table_with_constraints(
+            "schema_name",
+            "table_name",
+            vec![
+                ("low".to_owned(), SqlType::SmallInt),
+                ("high".to_owned(), SqlType::SmallInt),
+            ],
+            vec![check("low", Comparison::Gt, "0"), check("high", Comparison::Lt, "100")],
+        )
+        .expect("no system errors")
+        .expect("table is created");
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["5", "50"]);
+    let (_columns, rows) = storage
+        .select_keyed_where("schema_name", "table_name", vec![])
+        .expect("no system errors")
+        .expect("rows are selected");
+    let key = rows[0].0.clone();
+    let mut update = |column: &str, value: &str| {
+        storage
+            .update_rows(
+                "schema_name",
+                "table_name",
+                vec![column.to_owned()],
+                vec![(key.clone(), vec![value.to_owned()])],
+            )
+            .expect("no system errors")
+    };
+
+    assert_eq!(
+        update("high", "100"),
+        Err(OperationOnTableError::CheckViolation("table_name_check".to_owned()))
+    );
+    assert_eq!(
+        update("low", "0"),
+        Err(OperationOnTableError::CheckViolation("table_name_check".to_owned()))
+    );
+    assert_eq!(update("low", "6"), Ok(1));
+    assert_eq!(
+        storage
+            .select_all_from("schema_name", "table_name", vec!["low".to_owned(), "high".to_owned()])
+            .expect("no system errors")
+            .map(|(_columns, values)| values),
+        Ok(vec![vec!["6".to_owned(), "50".to_owned()]])
+    );
+}
+
+#[rstest::rstest]
+fn update_rows_of_non_existent_columns(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+
+    assert_eq!(
+        storage
+            .update_rows("schema_name", "table_name", vec!["col1".to_owned()], vec![])
+            .expect("no system errors"),
+        Err(OperationOnTableError::ColumnDoesNotExist(vec!["col1".to_owned()]))
+    );
+}
+
+#[rstest::rstest]
+fn update_rows_of_non_existent_table(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+
+    assert_eq!(
+        storage
+            .select_keyed_where("schema_name", "not_existed", vec![])
+            .expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+    assert_eq!(
+        storage
+            .update_rows("schema_name", "not_existed", vec![], vec![])
+            .expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+}
+
 #[cfg(test)]
 mod constraints {
     use super::*;
@@ -63,6 +63,31 @@ fn same_table_names_with_different_columns_in_different_schemas(mut storage: Per
     );
 }
 
+#[rstest::rstest]
+fn schema_named_as_system_one(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "system",
+        "columns",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+
+    assert_eq!(storage.drop_schema("system").expect("no system errors"), Ok(()));
+
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+    assert_eq!(
+        storage
+            .table_columns("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(vec![("column_test".to_owned(), SqlType::SmallInt)])
+    );
+}
+
 #[rstest::rstest]
 fn drop_schema(mut storage: PersistentStorage) {
     create_schema(&mut storage, "schema_name");
@@ -82,9 +107,6 @@ fn drop_schema_that_was_not_created() {
 }
 
 #[rstest::rstest]
-#[ignore]
-// TODO store tables and columns into "system" schema
-//      but simple select by predicate has to be implemented
 fn drop_schema_drops_tables_in_it(mut storage: PersistentStorage) {
     create_schema(&mut storage, "schema_name");
     create_table(
@@ -123,3 +145,19 @@ fn drop_schema_drops_tables_in_it(mut storage: PersistentStorage) {
         Ok(())
     );
 }
+
+#[rstest::rstest]
+fn schema_names(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_2");
+    create_schema(&mut storage, "schema_1");
+    create_schema(&mut storage, "schema_3");
+    storage
+        .drop_schema("schema_3")
+        .expect("no system errors")
+        .expect("schema is dropped");
+
+    assert_eq!(
+        storage.schema_names().expect("no system errors"),
+        vec!["schema_1".to_owned(), "schema_2".to_owned()]
+    );
+}
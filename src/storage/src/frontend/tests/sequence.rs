@@ -0,0 +1,266 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+fn create_sequence<P: backend::BackendStorage>(
+    storage: &mut FrontendStorage<P>,
+    schema_name: &str,
+    sequence_name: &str,
+    definition: SequenceDefinition,
+) {
+    storage
+        .create_sequence(schema_name, sequence_name, definition)
+        .expect("no system errors")
+        .expect("sequence is created");
+}
+
+fn reserve<P: backend::BackendStorage>(storage: &mut FrontendStorage<P>, sequence_name: &str) -> Vec<i64> {
+    let mut block = storage
+        .reserve_sequence_values("schema_name", sequence_name)
+        .expect("no system errors")
+        .expect("values are reserved");
+    let mut values = vec![];
+    while let Some(value) = block.next_value() {
+        values.push(value);
+    }
+    values
+}
+
+#[rstest::rstest]
+fn create_sequence_in_non_existent_schema(mut storage: PersistentStorage) {
+    assert_eq!(
+        storage
+            .create_sequence("schema_name", "sequence_name", SequenceDefinition::default())
+            .expect("no system errors"),
+        Err(SequenceError::SchemaDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn create_sequence_with_existing_name(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    create_sequence(
+        &mut storage,
+        "schema_name",
+        "sequence_name",
+        SequenceDefinition::default(),
+    );
+
+    assert_eq!(
+        storage
+            .create_sequence("schema_name", "sequence_name", SequenceDefinition::default())
+            .expect("no system errors"),
+        Err(SequenceError::SequenceAlreadyExists)
+    );
+}
+
+#[rstest::rstest]
+fn reserved_blocks_do_not_overlap(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    create_sequence(
+        &mut storage,
+        "schema_name",
+        "sequence_name",
+        SequenceDefinition {
+            start: 10,
+            increment: 5,
+            cache: 3,
+        },
+    );
+
+    assert_eq!(reserve(&mut storage, "sequence_name"), vec![10, 15, 20]);
+    assert_eq!(reserve(&mut storage, "sequence_name"), vec![25, 30, 35]);
+}
+
+#[rstest::rstest]
+fn table_is_not_a_sequence(mut storage: PersistentStorage) {
+    create_schema_with_table(&mut storage, "schema_name", "table_name", vec![]);
+
+    assert_eq!(
+        storage
+            .reserve_sequence_values("schema_name", "table_name")
+            .expect("no system errors"),
+        Err(SequenceError::SequenceDoesNotExist)
+    );
+    assert_eq!(
+        storage
+            .drop_sequence("schema_name", "table_name")
+            .expect("no system errors"),
+        Err(SequenceError::SequenceDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn set_value(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    create_sequence(
+        &mut storage,
+        "schema_name",
+        "sequence_name",
+        SequenceDefinition::default(),
+    );
+
+    assert_eq!(
+        storage
+            .set_sequence_value("schema_name", "sequence_name", 100, true)
+            .expect("no system errors"),
+        Ok(())
+    );
+    assert_eq!(reserve(&mut storage, "sequence_name"), vec![101]);
+
+    assert_eq!(
+        storage
+            .set_sequence_value("schema_name", "sequence_name", 100, false)
+            .expect("no system errors"),
+        Ok(())
+    );
+    assert_eq!(reserve(&mut storage, "sequence_name"), vec![100]);
+}
+
+#[rstest::rstest]
+fn restart_and_increment_by(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    create_sequence(
+        &mut storage,
+        "schema_name",
+        "sequence_name",
+        SequenceDefinition::default(),
+    );
+    reserve(&mut storage, "sequence_name");
+    reserve(&mut storage, "sequence_name");
+
+    assert_eq!(
+        storage
+            .alter_sequence("schema_name", "sequence_name", SequenceAlteration::Restart(None))
+            .expect("no system errors"),
+        Ok(())
+    );
+    assert_eq!(
+        storage
+            .alter_sequence("schema_name", "sequence_name", SequenceAlteration::IncrementBy(-2))
+            .expect("no system errors"),
+        Ok(())
+    );
+    assert_eq!(reserve(&mut storage, "sequence_name"), vec![1]);
+    assert_eq!(reserve(&mut storage, "sequence_name"), vec![-1]);
+
+    storage
+        .alter_sequence("schema_name", "sequence_name", SequenceAlteration::Restart(Some(50)))
+        .expect("no system errors")
+        .expect("sequence is restarted");
+
+    assert_eq!(reserve(&mut storage, "sequence_name"), vec![50]);
+}
+
+#[rstest::rstest]
+fn sequence_that_reached_its_limit(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    create_sequence(
+        &mut storage,
+        "schema_name",
+        "sequence_name",
+        SequenceDefinition {
+            start: i64::MAX,
+            increment: 1,
+            cache: 1,
+        },
+    );
+
+    assert_eq!(
+        storage
+            .reserve_sequence_values("schema_name", "sequence_name")
+            .expect("no system errors"),
+        Err(SequenceError::LimitReached)
+    );
+}
+
+#[rstest::rstest]
+fn drop_sequence(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    create_sequence(
+        &mut storage,
+        "schema_name",
+        "sequence_name",
+        SequenceDefinition::default(),
+    );
+
+    assert_eq!(
+        storage
+            .drop_sequence("schema_name", "sequence_name")
+            .expect("no system errors"),
+        Ok(())
+    );
+    assert_eq!(
+        storage
+            .reserve_sequence_values("schema_name", "sequence_name")
+            .expect("no system errors"),
+        Err(SequenceError::SequenceDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn drop_table_drops_owned_sequences(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+    create_sequence(
+        &mut storage,
+        "schema_name",
+        "table_name_id_seq",
+        SequenceDefinition::default(),
+    );
+    create_sequence(&mut storage, "schema_name", "shared_seq", SequenceDefinition::default());
+    storage
+        .create_table_with_constraints(
+            "schema_name",
+            "table_name",
+            vec![
+                ("id".to_owned(), SqlType::Integer),
+                ("code".to_owned(), SqlType::Integer),
+            ],
+            vec![
+                TableConstraint::Default(
+                    "id".to_owned(),
+                    DefaultValue::NextValue {
+                        schema_name: "schema_name".to_owned(),
+                        sequence_name: "table_name_id_seq".to_owned(),
+                        owned: true,
+                    },
+                ),
+                TableConstraint::Default(
+                    "code".to_owned(),
+                    DefaultValue::NextValue {
+                        schema_name: "schema_name".to_owned(),
+                        sequence_name: "shared_seq".to_owned(),
+                        owned: false,
+                    },
+                ),
+            ],
+        )
+        .expect("no system errors")
+        .expect("table is created");
+
+    assert_eq!(
+        storage
+            .drop_table("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(())
+    );
+    assert_eq!(
+        storage
+            .reserve_sequence_values("schema_name", "table_name_id_seq")
+            .expect("no system errors"),
+        Err(SequenceError::SequenceDoesNotExist)
+    );
+    assert_eq!(reserve(&mut storage, "shared_seq"), vec![1]);
+}
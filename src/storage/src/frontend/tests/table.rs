@@ -114,6 +114,70 @@ fn drop_table(mut storage: PersistentStorage) {
     );
 }
 
+#[rstest::rstest]
+fn table_names_that_concatenate_to_the_same_string(mut storage: PersistentStorage) {
+    create_schema_with_table(&mut storage, "schema_a", "b", vec![("column_1", SqlType::SmallInt)]);
+    create_schema_with_table(&mut storage, "schema_", "ab", vec![("column_2", SqlType::BigInt)]);
+
+    assert_eq!(
+        storage.table_columns("schema_a", "b").expect("no system errors"),
+        Ok(vec![("column_1".to_owned(), SqlType::SmallInt)])
+    );
+    assert_eq!(
+        storage.table_columns("schema_", "ab").expect("no system errors"),
+        Ok(vec![("column_2".to_owned(), SqlType::BigInt)])
+    );
+}
+
+#[rstest::rstest]
+fn recreated_table_is_empty(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["123"]);
+    storage
+        .drop_table("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("table dropped");
+    create_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+
+    assert_eq!(
+        storage
+            .select_all_from("schema_name", "table_name", vec!["column_test".to_owned()])
+            .expect("no system errors"),
+        Ok((vec![("column_test".to_owned(), SqlType::SmallInt)], vec![]))
+    );
+}
+
+#[rstest::rstest]
+fn drop_table_removes_its_columns(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+    storage
+        .drop_table("schema_name", "table_name")
+        .expect("no system errors")
+        .expect("table dropped");
+
+    assert_eq!(
+        storage
+            .table_columns("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(vec![])
+    );
+}
+
 #[rstest::rstest]
 fn table_columns_on_empty_table(mut storage: PersistentStorage) {
     create_schema_with_table(&mut storage, "schema_name", "table_name", vec![]);
@@ -136,3 +200,71 @@ fn drop_not_created_table(mut storage: PersistentStorage) {
         Err(DropTableError::TableDoesNotExist)
     );
 }
+
+#[rstest::rstest]
+fn size_of_empty_table(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+
+    assert_eq!(
+        storage
+            .table_size("schema_name", "table_name")
+            .expect("no system errors"),
+        Ok(0)
+    );
+}
+
+#[rstest::rstest]
+fn size_of_table_grows_with_inserted_records(mut storage: PersistentStorage) {
+    create_schema_with_table(
+        &mut storage,
+        "schema_name",
+        "table_name",
+        vec![("column_test", SqlType::SmallInt)],
+    );
+    insert_into(&mut storage, "schema_name", "table_name", vec![], vec!["123"]);
+
+    assert!(
+        storage
+            .table_size("schema_name", "table_name")
+            .expect("no system errors")
+            .expect("table exists")
+            > 0
+    );
+}
+
+#[rstest::rstest]
+fn size_of_not_created_table(mut storage: PersistentStorage) {
+    create_schema(&mut storage, "schema_name");
+
+    assert_eq!(
+        storage
+            .table_size("schema_name", "not_existed_table")
+            .expect("no system errors"),
+        Err(OperationOnTableError::TableDoesNotExist)
+    );
+}
+
+#[rstest::rstest]
+fn table_names(mut storage: PersistentStorage) {
+    create_schema_with_table(&mut storage, "schema_name", "table_2", vec![("id", SqlType::SmallInt)]);
+    create_table(&mut storage, "schema_name", "table_1", vec![("id", SqlType::SmallInt)]);
+    create_schema_with_table(&mut storage, "other_schema", "table_3", vec![("id", SqlType::SmallInt)]);
+    storage
+        .create_sequence("schema_name", "sequence_name", SequenceDefinition::default())
+        .expect("no system errors")
+        .expect("sequence is created");
+
+    assert_eq!(
+        storage.table_names("schema_name").expect("no system errors"),
+        Ok(vec!["table_1".to_owned(), "table_2".to_owned()])
+    );
+    assert_eq!(
+        storage.table_names("non_existent").expect("no system errors"),
+        Err(SchemaDoesNotExist)
+    );
+}
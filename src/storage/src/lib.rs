@@ -16,13 +16,24 @@ extern crate kernel;
 extern crate log;
 extern crate sql_types;
 
+use kernel::SystemResult;
+use serde::{Deserialize, Serialize};
 use sql_types::{ConstraintError, SqlType};
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::HashMap};
 
 pub mod backend;
+pub mod engine;
 pub mod frontend;
+pub mod memory;
+pub mod temp;
+pub mod wal;
 
 pub type Projection = (Vec<(String, sql_types::SqlType)>, Vec<Vec<String>>);
+pub type RecordCursor = Box<dyn Iterator<Item = SystemResult<Vec<String>>> + Send>;
+// records of projection are read as the cursor is advanced
+pub type StreamedProjection = (Vec<(String, sql_types::SqlType)>, RecordCursor);
+// records along with keys they are kept under, so they can be updated or deleted by the keys
+pub type KeyedProjection = (Vec<(String, sql_types::SqlType)>, Vec<(backend::Key, Vec<String>)>);
 
 #[derive(Debug, PartialEq)]
 pub struct SchemaAlreadyExists;
@@ -41,6 +52,37 @@ pub enum DropTableError {
     TableDoesNotExist,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum AlterTableError {
+    SchemaDoesNotExist,
+    TableDoesNotExist,
+    // new name of the table is taken by another table or sequence
+    TableAlreadyExists,
+    ColumnAlreadyExists(String),
+    ColumnDoesNotExist(String),
+    // default value of added column that is not valid for its type
+    InvalidDefault(String),
+    // name of added column that existing rows do not have a value for
+    NotNullViolation(String),
+    // name of the check constraint that default value of added column violates
+    CheckViolation(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CreateIndexError {
+    SchemaDoesNotExist,
+    TableDoesNotExist,
+    // name of the index is taken by another index, table or sequence
+    IndexAlreadyExists,
+    ColumnDoesNotExist(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DropIndexError {
+    SchemaDoesNotExist,
+    IndexDoesNotExist,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum OperationOnTableError {
     SchemaDoesNotExist,
@@ -48,4 +90,201 @@ pub enum OperationOnTableError {
     // Returns non existing columns.
     ColumnDoesNotExist(Vec<String>),
     ConstraintViolation(HashMap<ConstraintError, Vec<Vec<(String, SqlType)>>>),
+    // name of the unique constraint that values of a row violate
+    UniqueViolation(String),
+    // name of the column that a value is not given for
+    NotNullViolation(String),
+    // name of the check constraint that values of a row violate
+    CheckViolation(String),
+}
+
+/// Rule that values of table rows have to follow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TableConstraint {
+    Unique(UniqueConstraint),
+    // name of the column that has to be given a value
+    NotNull(String),
+    Check(CheckConstraint),
+    // name of the column and the value it takes when it is not given
+    Default(String, DefaultValue),
+}
+
+/// Value of a column that is not given on insert
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DefaultValue {
+    Constant(String),
+    /// next value of the sequence, owned sequences are dropped along with their table
+    NextValue {
+        schema_name: String,
+        sequence_name: String,
+        owned: bool,
+    },
+}
+
+/// Columns of a table that can't have the same values in two rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UniqueConstraint {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub is_primary: bool,
+}
+
+/// Comparison of column values with a constant, constraints with the same
+/// name have to be satisfied all together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckConstraint {
+    pub name: String,
+    pub column: String,
+    pub comparison: Comparison,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Comparison {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl CheckConstraint {
+    /// integers are compared by their values and strings lexicographically
+    pub fn accepts(&self, sql_type: SqlType, value: &str) -> bool {
+        let ordering = match sql_type {
+            SqlType::SmallInt | SqlType::Integer | SqlType::BigInt => {
+                match (value.parse::<i64>(), self.value.parse::<i64>()) {
+                    (Ok(value), Ok(constant)) => value.cmp(&constant),
+                    _ => return false,
+                }
+            }
+            _ => value.cmp(self.value.as_str()),
+        };
+        match self.comparison {
+            Comparison::Eq => ordering == Ordering::Equal,
+            Comparison::NotEq => ordering != Ordering::Equal,
+            Comparison::Lt => ordering == Ordering::Less,
+            Comparison::LtEq => ordering != Ordering::Greater,
+            Comparison::Gt => ordering == Ordering::Greater,
+            Comparison::GtEq => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SequenceError {
+    SchemaDoesNotExist,
+    SequenceAlreadyExists,
+    SequenceDoesNotExist,
+    // sequence can't produce next values without overflow
+    LimitReached,
+}
+
+/// Modification of table rows that is written to the undo log to be reverted later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RowChange {
+    Inserted {
+        namespace: String,
+        object_name: String,
+        keys: Vec<backend::Key>,
+    },
+    // rows as they were before they were updated or deleted
+    Overwritten {
+        namespace: String,
+        object_name: String,
+        rows: Vec<backend::Row>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SequenceDefinition {
+    pub start: i64,
+    pub increment: i64,
+    // how many values a session reserves at once
+    pub cache: u64,
+}
+
+impl Default for SequenceDefinition {
+    fn default() -> SequenceDefinition {
+        SequenceDefinition {
+            start: 1,
+            increment: 1,
+            cache: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequenceAlteration {
+    // restarts from the start value if no value is given
+    Restart(Option<i64>),
+    IncrementBy(i64),
+}
+
+/// Change of a table definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableAlteration {
+    /// rows that are written before the column is added take its default value
+    AddColumn {
+        name: String,
+        sql_type: SqlType,
+        constraints: Vec<TableConstraint>,
+    },
+    /// constraints of the column are dropped along with it
+    DropColumn(String),
+    RenameColumn {
+        from: String,
+        to: String,
+    },
+    RenameTo(String),
+}
+
+/// Values of a sequence that are reserved for a session, values that session
+/// does not use are lost
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceBlock {
+    next: i64,
+    increment: i64,
+    remaining: u64,
+}
+
+impl SequenceBlock {
+    pub fn next_value(&mut self) -> Option<i64> {
+        if self.remaining == 0 {
+            None
+        } else {
+            let value = self.next;
+            self.next += self.increment;
+            self.remaining -= 1;
+            Some(value)
+        }
+    }
+}
+
+// sled releases the lock of a data directory once its background threads
+// stop, which happens a bit later than the database is dropped. Other errors
+// are returned right away
+#[cfg(test)]
+fn reopen<T>(open: impl Fn() -> kernel::SystemResult<T>) -> kernel::SystemResult<T> {
+    let mut attempts = 100;
+    loop {
+        match open() {
+            Err(error) if attempts > 0 && lock_is_held(&error) => {
+                attempts -= 1;
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            opened => return opened,
+        }
+    }
+}
+
+// sled reports that another instance holds the lock only with a message of
+// its io error
+#[cfg(test)]
+fn lock_is_held(error: &kernel::SystemError) -> bool {
+    use std::error::Error;
+    error
+        .source()
+        .is_some_and(|cause| cause.to_string().starts_with("could not acquire lock on"))
 }
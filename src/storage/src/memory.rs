@@ -0,0 +1,404 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::backend::{
+    BackendStorage, BatchOperation, DefinitionError, Key, KeyCursor, KeyRange, ReadCursor, Row, StorageResult, Values,
+    WriteBatch,
+};
+use std::{collections::BTreeMap, ops::Bound};
+
+#[derive(Debug, Default)]
+struct Object {
+    rows: BTreeMap<Key, Values>,
+    metadata: Option<Values>,
+}
+
+impl Object {
+    fn size(&self) -> u64 {
+        self.rows
+            .iter()
+            .map(|(key, values)| (key.len() + values.len()) as u64)
+            .sum()
+    }
+}
+
+/// Keeps objects in memory ordered by keys, data is lost when it is dropped
+#[derive(Debug, Default)]
+pub struct MemoryBackendStorage {
+    namespaces: BTreeMap<String, BTreeMap<String, Object>>,
+}
+
+impl MemoryBackendStorage {
+    fn object(&self, namespace: &str, object_name: &str) -> StorageResult<&Object> {
+        match self.namespaces.get(namespace) {
+            Some(objects) => match objects.get(object_name) {
+                Some(object) => Ok(object),
+                None => Err(DefinitionError::ObjectDoesNotExist.into()),
+            },
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
+        }
+    }
+
+    fn object_mut(&mut self, namespace: &str, object_name: &str) -> StorageResult<&mut Object> {
+        match self.namespaces.get_mut(namespace) {
+            Some(objects) => match objects.get_mut(object_name) {
+                Some(object) => Ok(object),
+                None => Err(DefinitionError::ObjectDoesNotExist.into()),
+            },
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
+        }
+    }
+}
+
+// cursors own rows they iterate over, so rows are copied out of the object
+fn cursor<'r>(rows: impl Iterator<Item = (&'r Key, &'r Values)>) -> ReadCursor {
+    Box::new(
+        rows.map(|(key, values)| Ok((key.clone(), values.clone())))
+            .collect::<Vec<_>>()
+            .into_iter(),
+    )
+}
+
+impl BackendStorage for MemoryBackendStorage {
+    fn create_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        if self.namespaces.contains_key(namespace) {
+            return Err(DefinitionError::NamespaceAlreadyExists.into());
+        }
+        self.namespaces.insert(namespace.to_owned(), BTreeMap::new());
+        Ok(())
+    }
+
+    fn drop_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        match self.namespaces.remove(namespace) {
+            Some(_objects) => Ok(()),
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
+        }
+    }
+
+    fn create_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        match self.namespaces.get_mut(namespace) {
+            Some(objects) if objects.contains_key(object_name) => Err(DefinitionError::ObjectAlreadyExists.into()),
+            Some(objects) => {
+                objects.insert(object_name.to_owned(), Object::default());
+                Ok(())
+            }
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
+        }
+    }
+
+    fn drop_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        match self.namespaces.get_mut(namespace) {
+            Some(objects) => match objects.remove(object_name) {
+                Some(_object) => Ok(()),
+                None => Err(DefinitionError::ObjectDoesNotExist.into()),
+            },
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
+        }
+    }
+
+    fn namespaces(&self) -> StorageResult<Vec<String>> {
+        Ok(self.namespaces.keys().cloned().collect())
+    }
+
+    fn object_names(&self, namespace: &str) -> StorageResult<Vec<String>> {
+        match self.namespaces.get(namespace) {
+            Some(objects) => Ok(objects.keys().cloned().collect()),
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
+        }
+    }
+
+    fn write(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize> {
+        let object = self.object_mut(namespace, object_name)?;
+        let written_rows = rows.len();
+        object.rows.extend(rows);
+        Ok(written_rows)
+    }
+
+    fn update(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize> {
+        let object = self.object_mut(namespace, object_name)?;
+        let mut updated = 0;
+        for (key, values) in rows {
+            if let Some(existing) = object.rows.get_mut(&key) {
+                *existing = values;
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    }
+
+    fn cas(
+        &mut self,
+        namespace: &str,
+        object_name: &str,
+        key: Key,
+        expected: Option<Values>,
+        new: Option<Values>,
+    ) -> StorageResult<Result<(), Option<Values>>> {
+        let object = self.object_mut(namespace, object_name)?;
+        let current = object.rows.get(&key).cloned();
+        if current != expected {
+            return Ok(Err(current));
+        }
+        match new {
+            Some(values) => object.rows.insert(key, values),
+            None => object.rows.remove(&key),
+        };
+        Ok(Ok(()))
+    }
+
+    fn read(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        Ok(cursor(self.object(namespace, object_name)?.rows.iter()))
+    }
+
+    fn read_range(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        Ok(cursor(self.object(namespace, object_name)?.rows.range(range)))
+    }
+
+    fn read_prefix(&self, namespace: &str, object_name: &str, prefix: &[u8]) -> StorageResult<ReadCursor> {
+        let object = self.object(namespace, object_name)?;
+        Ok(cursor(
+            object
+                .rows
+                .range::<Key, KeyRange>((Bound::Included(prefix.to_vec()), Bound::Unbounded))
+                .take_while(|(key, _values)| key.starts_with(prefix)),
+        ))
+    }
+
+    fn read_reverse(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        Ok(cursor(self.object(namespace, object_name)?.rows.iter().rev()))
+    }
+
+    fn read_range_reverse(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        Ok(cursor(self.object(namespace, object_name)?.rows.range(range).rev()))
+    }
+
+    fn lookup(&self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<ReadCursor> {
+        let object = self.object(namespace, object_name)?;
+        Ok(cursor(
+            keys.iter().filter_map(|key| object.rows.get_key_value(key.as_slice())),
+        ))
+    }
+
+    fn read_keys(&self, namespace: &str, object_name: &str) -> StorageResult<KeyCursor> {
+        let object = self.object(namespace, object_name)?;
+        Ok(Box::new(
+            object.rows.keys().cloned().map(Ok).collect::<Vec<_>>().into_iter(),
+        ))
+    }
+
+    fn count(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<usize> {
+        Ok(self.object(namespace, object_name)?.rows.range(range).count())
+    }
+
+    fn delete(&mut self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<usize> {
+        let object = self.object_mut(namespace, object_name)?;
+        let deleted = keys.len();
+        for key in keys {
+            object.rows.remove(&key);
+        }
+        Ok(deleted)
+    }
+
+    fn apply_batch(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        // nothing can fail once the batch is validated, so it is applied entirely
+        batch.validate(
+            |namespace| self.namespaces.contains_key(namespace),
+            |namespace, object_name| self.object(namespace, object_name).is_ok(),
+        )?;
+        for operation in batch.operations() {
+            match operation {
+                BatchOperation::Write(namespace, object_name, (key, values)) => {
+                    self.object_mut(namespace, object_name)?
+                        .rows
+                        .insert(key.clone(), values.clone());
+                }
+                BatchOperation::Delete(namespace, object_name, key) => {
+                    self.object_mut(namespace, object_name)?.rows.remove(key);
+                }
+                BatchOperation::CreateNamespace(namespace) => self.create_namespace(namespace)?,
+                BatchOperation::DropNamespace(namespace) => self.drop_namespace(namespace)?,
+                BatchOperation::CreateObject(namespace, object_name) => self.create_object(namespace, object_name)?,
+                BatchOperation::DropObject(namespace, object_name) => self.drop_object(namespace, object_name)?,
+                BatchOperation::PutObjectMetadata(namespace, object_name, metadata) => {
+                    self.put_object_metadata(namespace, object_name, metadata.clone())?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn put_object_metadata(&mut self, namespace: &str, object_name: &str, metadata: Values) -> StorageResult<()> {
+        self.object_mut(namespace, object_name)?.metadata = Some(metadata);
+        Ok(())
+    }
+
+    fn get_object_metadata(&self, namespace: &str, object_name: &str) -> StorageResult<Option<Values>> {
+        Ok(self.object(namespace, object_name)?.metadata.clone())
+    }
+
+    fn object_size(&self, namespace: &str, object_name: &str) -> StorageResult<u64> {
+        Ok(self.object(namespace, object_name)?.size())
+    }
+
+    fn size(&self) -> StorageResult<u64> {
+        Ok(self
+            .namespaces
+            .values()
+            .flat_map(|objects| objects.values())
+            .map(Object::size)
+            .sum())
+    }
+
+    fn flush(&mut self) -> StorageResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::StorageError;
+
+    #[rstest::fixture]
+    fn storage() -> MemoryBackendStorage {
+        let mut storage = MemoryBackendStorage::default();
+        storage.create_namespace("namespace").expect("namespace is created");
+        storage
+            .create_object("namespace", "object_name")
+            .expect("object is created");
+        storage
+    }
+
+    fn rows(cursor: StorageResult<ReadCursor>) -> Vec<Row> {
+        cursor.expect("rows are read").map(Result::unwrap).collect()
+    }
+
+    #[rstest::rstest]
+    fn create_object_twice(mut storage: MemoryBackendStorage) {
+        assert_eq!(
+            storage.create_object("namespace", "object_name"),
+            Err(StorageError::Definition(DefinitionError::ObjectAlreadyExists))
+        );
+    }
+
+    #[rstest::rstest]
+    fn rows_are_ordered_by_keys(mut storage: MemoryBackendStorage) {
+        storage
+            .write(
+                "namespace",
+                "object_name",
+                vec![(vec![3], vec![3]), (vec![1], vec![1]), (vec![2], vec![2])],
+            )
+            .expect("values are written");
+
+        assert_eq!(
+            rows(storage.read("namespace", "object_name")),
+            vec![(vec![1], vec![1]), (vec![2], vec![2]), (vec![3], vec![3])]
+        );
+        assert_eq!(
+            rows(storage.read_reverse("namespace", "object_name")),
+            vec![(vec![3], vec![3]), (vec![2], vec![2]), (vec![1], vec![1])]
+        );
+    }
+
+    #[rstest::rstest]
+    fn update_rows_that_exist(mut storage: MemoryBackendStorage) {
+        storage
+            .write("namespace", "object_name", vec![(vec![1], vec![1])])
+            .expect("values are written");
+
+        assert_eq!(
+            storage.update("namespace", "object_name", vec![(vec![1], vec![2]), (vec![2], vec![2])]),
+            Ok(1)
+        );
+        assert_eq!(rows(storage.read("namespace", "object_name")), vec![(vec![1], vec![2])]);
+    }
+
+    #[rstest::rstest]
+    fn cas(mut storage: MemoryBackendStorage) {
+        assert_eq!(
+            storage.cas("namespace", "object_name", vec![1], None, Some(vec![1])),
+            Ok(Ok(()))
+        );
+        assert_eq!(
+            storage.cas("namespace", "object_name", vec![1], None, Some(vec![2])),
+            Ok(Err(Some(vec![1])))
+        );
+        assert_eq!(
+            storage.cas("namespace", "object_name", vec![1], Some(vec![1]), None),
+            Ok(Ok(()))
+        );
+        assert_eq!(rows(storage.read("namespace", "object_name")), vec![]);
+    }
+
+    #[rstest::rstest]
+    fn read_prefix(mut storage: MemoryBackendStorage) {
+        storage
+            .write(
+                "namespace",
+                "object_name",
+                vec![(vec![1, 1], vec![1]), (vec![1, 2], vec![2]), (vec![2, 1], vec![3])],
+            )
+            .expect("values are written");
+
+        assert_eq!(
+            rows(storage.read_prefix("namespace", "object_name", &[1])),
+            vec![(vec![1, 1], vec![1]), (vec![1, 2], vec![2])]
+        );
+    }
+
+    #[rstest::rstest]
+    fn batch_with_non_existent_object_is_not_applied(mut storage: MemoryBackendStorage) {
+        let mut batch = WriteBatch::default();
+        batch.write("namespace", "object_name", vec![(vec![1], vec![1])]);
+        batch.write("namespace", "non_existent", vec![(vec![1], vec![1])]);
+
+        assert_eq!(
+            storage.apply_batch(batch),
+            Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+        );
+        assert_eq!(rows(storage.read("namespace", "object_name")), vec![]);
+    }
+
+    #[rstest::rstest]
+    fn batch_with_definitions(mut storage: MemoryBackendStorage) {
+        let mut batch = WriteBatch::default();
+        batch.create_object("namespace", "other_object");
+        batch.put_object_metadata("namespace", "other_object", vec![1]);
+        batch.write("namespace", "other_object", vec![(vec![1], vec![1])]);
+        batch.drop_object("namespace", "object_name");
+
+        assert_eq!(storage.apply_batch(batch), Ok(()));
+        assert_eq!(storage.object_names("namespace"), Ok(vec!["other_object".to_owned()]));
+        assert_eq!(
+            storage.get_object_metadata("namespace", "other_object"),
+            Ok(Some(vec![1]))
+        );
+        assert_eq!(
+            rows(storage.read("namespace", "other_object")),
+            vec![(vec![1], vec![1])]
+        );
+    }
+
+    #[rstest::rstest]
+    fn dropping_namespace_drops_objects_in_it(mut storage: MemoryBackendStorage) {
+        storage.drop_namespace("namespace").expect("namespace is dropped");
+        storage.create_namespace("namespace").expect("namespace is created");
+
+        assert_eq!(
+            storage.read("namespace", "object_name").map(|_rows| ()),
+            Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+        );
+    }
+}
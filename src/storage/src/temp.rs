@@ -0,0 +1,208 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use kernel::{SystemError, SystemResult};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+#[derive(Debug, PartialEq)]
+pub struct TempFileLimitExceeded(pub u64);
+
+/// Area on disk where operators spill intermediate results that do not fit
+/// into memory. Every query gets its own directory that is removed when the
+/// query is done.
+pub struct TempStorage {
+    root: PathBuf,
+    next_query_id: AtomicU64,
+}
+
+impl TempStorage {
+    /// removes files that queries have left in `root` if the node crashed
+    pub fn open<P: Into<PathBuf>>(root: P) -> SystemResult<TempStorage> {
+        let root = root.into();
+        if root.exists() {
+            fs::remove_dir_all(&root).map_err(SystemError::io)?;
+        }
+        fs::create_dir_all(&root).map_err(SystemError::io)?;
+        Ok(TempStorage {
+            root,
+            next_query_id: AtomicU64::new(0),
+        })
+    }
+
+    /// files of a query that is given the next unused id
+    pub fn new_query_files(&self, limit: Option<u64>) -> SystemResult<QueryTempFiles> {
+        self.query_files(self.next_query_id.fetch_add(1, Ordering::SeqCst), limit)
+    }
+
+    /// `limit` is the total size of query temp files in bytes
+    pub fn query_files(&self, query_id: u64, limit: Option<u64>) -> SystemResult<QueryTempFiles> {
+        let dir = self.root.join(query_id.to_string());
+        fs::create_dir(&dir).map_err(SystemError::io)?;
+        Ok(QueryTempFiles {
+            dir,
+            limit,
+            used: Arc::new(AtomicU64::new(0)),
+            created: 0,
+        })
+    }
+}
+
+pub struct QueryTempFiles {
+    dir: PathBuf,
+    limit: Option<u64>,
+    used: Arc<AtomicU64>,
+    created: usize,
+}
+
+impl QueryTempFiles {
+    pub fn create(&mut self) -> SystemResult<TempFile> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create_new(true)
+            .open(self.dir.join(self.created.to_string()))
+            .map_err(SystemError::io)?;
+        self.created += 1;
+        Ok(TempFile {
+            file,
+            limit: self.limit,
+            used: self.used.clone(),
+        })
+    }
+
+    /// total size of query temp files in bytes
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for QueryTempFiles {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_dir_all(&self.dir) {
+            log::warn!("temp files in {:?} are not removed due to {:?}", self.dir, error);
+        }
+    }
+}
+
+pub struct TempFile {
+    file: File,
+    limit: Option<u64>,
+    used: Arc<AtomicU64>,
+}
+
+impl TempFile {
+    /// writes to the end of the file unless query temp files would exceed the limit
+    pub fn append(&mut self, bytes: &[u8]) -> SystemResult<Result<(), TempFileLimitExceeded>> {
+        let len = bytes.len() as u64;
+        let used = self.used.fetch_add(len, Ordering::SeqCst) + len;
+        if let Some(limit) = self.limit {
+            if used > limit {
+                self.used.fetch_sub(len, Ordering::SeqCst);
+                return Ok(Err(TempFileLimitExceeded(limit)));
+            }
+        }
+        match self.file.write_all(bytes) {
+            Ok(()) => Ok(Ok(())),
+            Err(error) => Err(SystemError::io(error)),
+        }
+    }
+
+    /// moves to the beginning of the file to read it from the start
+    pub fn rewind(&mut self) -> SystemResult<()> {
+        self.file.seek(SeekFrom::Start(0)).map(|_| ()).map_err(SystemError::io)
+    }
+}
+
+impl Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("database_temp_{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn written_bytes_are_read_back() {
+        let storage = TempStorage::open(root("read_back")).expect("temp storage is opened");
+        let mut files = storage.query_files(1, None).expect("query temp files");
+        let mut file = files.create().expect("temp file is created");
+
+        assert_eq!(file.append(b"spilled"), Ok(Ok(())));
+        assert_eq!(file.append(b" rows"), Ok(Ok(())));
+        file.rewind().expect("rewound");
+
+        let mut content = String::new();
+        file.read_to_string(&mut content).expect("read temp file");
+        assert_eq!(content, "spilled rows");
+        assert_eq!(files.used(), 12);
+    }
+
+    #[test]
+    fn files_are_removed_when_query_is_done() {
+        let root = root("query_done");
+        let storage = TempStorage::open(root.clone()).expect("temp storage is opened");
+        let mut files = storage.query_files(1, None).expect("query temp files");
+        files
+            .create()
+            .expect("temp file is created")
+            .append(b"spilled")
+            .expect("no system errors")
+            .expect("limit is not exceeded");
+
+        drop(files);
+
+        assert!(!root.join("1").exists());
+    }
+
+    #[test]
+    fn leftovers_are_removed_on_open() {
+        let root = root("leftovers");
+        let storage = TempStorage::open(root.clone()).expect("temp storage is opened");
+        let mut files = storage.query_files(1, None).expect("query temp files");
+        files.create().expect("temp file is created");
+        // simulates crash of the node during the query
+        std::mem::forget(files);
+
+        TempStorage::open(root.clone()).expect("temp storage is reopened");
+
+        assert!(!root.join("1").exists());
+    }
+
+    #[test]
+    fn limit_is_shared_by_query_files() {
+        let storage = TempStorage::open(root("limit")).expect("temp storage is opened");
+        let mut files = storage.query_files(1, Some(10)).expect("query temp files");
+        let mut first = files.create().expect("temp file is created");
+        let mut second = files.create().expect("temp file is created");
+
+        assert_eq!(first.append(b"123456"), Ok(Ok(())));
+        assert_eq!(second.append(b"123456"), Ok(Err(TempFileLimitExceeded(10))));
+        assert_eq!(second.append(b"1234"), Ok(Ok(())));
+        assert_eq!(files.used(), 10);
+    }
+}
@@ -0,0 +1,652 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::backend::{
+    BackendStorage, Key, KeyCursor, KeyRange, ReadCursor, Row, StorageError, StorageResult, Values, WriteBatch,
+};
+use kernel::{
+    clock::{Clock, SystemClock},
+    SystemError, SystemResult,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+// log sequence number of the first record in the file
+const LOG_HEADER_SIZE: usize = 8;
+// length and checksum of a record payload
+const RECORD_HEADER_SIZE: usize = 8;
+
+pub const DEFAULT_CHECKPOINT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+pub const DEFAULT_MAX_WAL_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Position of a record in the log. It is the number of bytes that were
+/// appended before the record since the log was created, so it keeps growing
+/// when the log is truncated.
+pub type Lsn = u64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Operation {
+    CreateNamespace(String),
+    DropNamespace(String),
+    CreateObject(String, String),
+    DropObject(String, String),
+    Write(String, String, Vec<Row>),
+    Delete(String, String, Vec<Key>),
+    Batch(WriteBatch),
+    PutObjectMetadata(String, String, Values),
+    Update(String, String, Vec<Row>),
+    CompareAndSwap(String, String, Key, Option<Values>, Option<Values>),
+}
+
+/// Append only file of operations that backend storage may not have made
+/// durable yet. Records have their length and checksum in front of them, so a
+/// record that was torn by a crash ends the log.
+pub struct WriteAheadLog {
+    file: File,
+    start: Lsn,
+    size: u64,
+}
+
+impl WriteAheadLog {
+    /// opens the log and reads operations that were recorded since it was truncated
+    pub fn open<P: AsRef<Path>>(path: P) -> SystemResult<(WriteAheadLog, Vec<Operation>)> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(SystemError::io)?;
+        let mut content = vec![];
+        file.read_to_end(&mut content).map_err(SystemError::io)?;
+        if content.len() < LOG_HEADER_SIZE {
+            let mut log = WriteAheadLog {
+                file,
+                start: 0,
+                size: 0,
+            };
+            log.reset(0)?;
+            return Ok((log, vec![]));
+        }
+        let mut start = [0u8; LOG_HEADER_SIZE];
+        start.copy_from_slice(&content[0..LOG_HEADER_SIZE]);
+        let mut operations = vec![];
+        let mut position = LOG_HEADER_SIZE;
+        while let Some((operation, size)) = read_record(&content[position..]) {
+            operations.push(operation);
+            position += size;
+        }
+        // new records have to follow the last complete one
+        file.set_len(position as u64).map_err(SystemError::io)?;
+        Ok((
+            WriteAheadLog {
+                file,
+                start: u64::from_be_bytes(start),
+                size: (position - LOG_HEADER_SIZE) as u64,
+            },
+            operations,
+        ))
+    }
+
+    /// operation is durable when it is appended
+    pub fn append(&mut self, operation: &Operation) -> SystemResult<()> {
+        let payload = bincode::serialize(operation)
+            .map_err(|error| SystemError::unrecoverable(format!("operation can't be serialized due to {:?}", error)))?;
+        let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + payload.len());
+        record.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        record.extend_from_slice(&checksum(&payload).to_be_bytes());
+        record.extend_from_slice(&payload);
+        self.file.write_all(&record).map_err(SystemError::io)?;
+        self.file.sync_data().map_err(SystemError::io)?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+
+    /// removes recorded operations, recovery starts from the end of the log
+    pub fn truncate(&mut self) -> SystemResult<()> {
+        self.reset(self.end())
+    }
+
+    /// sequence number where recovery starts
+    pub fn start(&self) -> Lsn {
+        self.start
+    }
+
+    /// sequence number of the next record
+    pub fn end(&self) -> Lsn {
+        self.start + self.size
+    }
+
+    /// bytes that records take since the log was truncated
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn reset(&mut self, start: Lsn) -> SystemResult<()> {
+        self.file.set_len(0).map_err(SystemError::io)?;
+        self.file.write_all(&start.to_be_bytes()).map_err(SystemError::io)?;
+        self.file.sync_data().map_err(SystemError::io)?;
+        self.start = start;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn read_record(bytes: &[u8]) -> Option<(Operation, usize)> {
+    if bytes.len() < RECORD_HEADER_SIZE {
+        return None;
+    }
+    let mut length = [0u8; 4];
+    length.copy_from_slice(&bytes[0..4]);
+    let length = u32::from_be_bytes(length) as usize;
+    let mut expected = [0u8; 4];
+    expected.copy_from_slice(&bytes[4..8]);
+    let payload = bytes.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + length)?;
+    if checksum(payload) != u32::from_be_bytes(expected) {
+        return None;
+    }
+    bincode::deserialize(payload)
+        .ok()
+        .map(|operation| (operation, RECORD_HEADER_SIZE + length))
+}
+
+// FNV-1a
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Records modifications that the wrapped backend applied in the write ahead
+/// log. Backend may keep them in memory until it is flushed, so operations
+/// that were recorded after the last checkpoint are applied again when storage
+/// is opened. Operations that failed are not recorded and are not replayed.
+///
+/// Checkpoint is done when `checkpoint_timeout` has passed since the previous
+/// one or when the log grows over `max_wal_size` bytes.
+pub struct WalBackendStorage<B: BackendStorage> {
+    inner: B,
+    log: WriteAheadLog,
+    clock: Arc<dyn Clock>,
+    checkpoint_timeout: Duration,
+    max_wal_size: u64,
+    last_checkpoint: Instant,
+}
+
+impl<B: BackendStorage> WalBackendStorage<B> {
+    pub fn open<P: AsRef<Path>>(mut inner: B, log_path: P) -> SystemResult<Self> {
+        let (log, operations) = WriteAheadLog::open(log_path)?;
+        for operation in operations {
+            replay(&mut inner, operation)?;
+        }
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let mut storage = Self {
+            inner,
+            log,
+            last_checkpoint: clock.now(),
+            clock,
+            checkpoint_timeout: DEFAULT_CHECKPOINT_TIMEOUT,
+            max_wal_size: DEFAULT_MAX_WAL_SIZE,
+        };
+        storage.checkpoint()?;
+        Ok(storage)
+    }
+
+    pub fn with_checkpoint_timeout(mut self, checkpoint_timeout: Duration) -> Self {
+        self.checkpoint_timeout = checkpoint_timeout;
+        self
+    }
+
+    pub fn with_max_wal_size(mut self, max_wal_size: u64) -> Self {
+        self.max_wal_size = max_wal_size;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.last_checkpoint = clock.now();
+        self.clock = clock;
+        self
+    }
+
+    /// makes modifications of the wrapped backend durable and truncates the log
+    pub fn checkpoint(&mut self) -> SystemResult<()> {
+        match self.inner.flush() {
+            Ok(()) => {
+                self.log.truncate()?;
+                self.last_checkpoint = self.clock.now();
+                log::debug!("checkpoint is done, recovery starts from {}", self.log.start());
+                Ok(())
+            }
+            Err(StorageError::System(error)) => Err(error),
+            Err(error) => Err(SystemError::unrecoverable(format!(
+                "backend can't be flushed due to {:?}",
+                error
+            ))),
+        }
+    }
+
+    /// sequence number of the log where recovery starts
+    pub fn redo_lsn(&self) -> Lsn {
+        self.log.start()
+    }
+
+    // operation is recorded after it is applied, so the log has only the
+    // operations that succeeded
+    fn apply<T>(&mut self, operation: Operation, apply: impl FnOnce(&mut B) -> StorageResult<T>) -> StorageResult<T> {
+        let result = apply(&mut self.inner)?;
+        self.log.append(&operation).map_err(StorageError::System)?;
+        if self.log.size() > self.max_wal_size || self.clock.now() - self.last_checkpoint >= self.checkpoint_timeout {
+            self.checkpoint().map_err(StorageError::System)?;
+        }
+        Ok(result)
+    }
+}
+
+// operations are replayed on top of the state that has some of them applied
+// already, objects that exist or are gone already are skipped
+fn replay<B: BackendStorage>(inner: &mut B, operation: Operation) -> SystemResult<()> {
+    let replayed = match operation {
+        Operation::CreateNamespace(namespace) => inner.create_namespace(&namespace),
+        Operation::DropNamespace(namespace) => inner.drop_namespace(&namespace),
+        Operation::CreateObject(namespace, object_name) => inner.create_object(&namespace, &object_name),
+        Operation::DropObject(namespace, object_name) => inner.drop_object(&namespace, &object_name),
+        Operation::Write(namespace, object_name, rows) => inner.write(&namespace, &object_name, rows).map(|_| ()),
+        Operation::Delete(namespace, object_name, keys) => inner.delete(&namespace, &object_name, keys).map(|_| ()),
+        Operation::Batch(batch) => inner.apply_batch(batch),
+        Operation::PutObjectMetadata(namespace, object_name, metadata) => {
+            inner.put_object_metadata(&namespace, &object_name, metadata)
+        }
+        Operation::Update(namespace, object_name, rows) => inner.update(&namespace, &object_name, rows).map(|_| ()),
+        // swap that was applied before the crash does not match anymore and is skipped
+        Operation::CompareAndSwap(namespace, object_name, key, expected, new) => inner
+            .cas(&namespace, &object_name, key, expected, new)
+            .map(|_swapped| ()),
+    };
+    match replayed {
+        Ok(()) | Err(StorageError::Definition(_)) => Ok(()),
+        Err(StorageError::System(error)) => Err(error),
+    }
+}
+
+impl<B: BackendStorage> BackendStorage for WalBackendStorage<B> {
+    fn create_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        self.apply(Operation::CreateNamespace(namespace.to_owned()), |inner| {
+            inner.create_namespace(namespace)
+        })
+    }
+
+    fn drop_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        self.apply(Operation::DropNamespace(namespace.to_owned()), |inner| {
+            inner.drop_namespace(namespace)
+        })
+    }
+
+    fn create_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        self.apply(
+            Operation::CreateObject(namespace.to_owned(), object_name.to_owned()),
+            |inner| inner.create_object(namespace, object_name),
+        )
+    }
+
+    fn drop_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        self.apply(
+            Operation::DropObject(namespace.to_owned(), object_name.to_owned()),
+            |inner| inner.drop_object(namespace, object_name),
+        )
+    }
+
+    fn namespaces(&self) -> StorageResult<Vec<String>> {
+        self.inner.namespaces()
+    }
+
+    fn object_names(&self, namespace: &str) -> StorageResult<Vec<String>> {
+        self.inner.object_names(namespace)
+    }
+
+    fn write(&mut self, namespace: &str, object_name: &str, values: Vec<Row>) -> StorageResult<usize> {
+        self.apply(
+            Operation::Write(namespace.to_owned(), object_name.to_owned(), values.clone()),
+            |inner| inner.write(namespace, object_name, values),
+        )
+    }
+
+    fn update(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize> {
+        self.apply(
+            Operation::Update(namespace.to_owned(), object_name.to_owned(), rows.clone()),
+            |inner| inner.update(namespace, object_name, rows),
+        )
+    }
+
+    fn cas(
+        &mut self,
+        namespace: &str,
+        object_name: &str,
+        key: Key,
+        expected: Option<Values>,
+        new: Option<Values>,
+    ) -> StorageResult<Result<(), Option<Values>>> {
+        self.apply(
+            Operation::CompareAndSwap(
+                namespace.to_owned(),
+                object_name.to_owned(),
+                key.clone(),
+                expected.clone(),
+                new.clone(),
+            ),
+            |inner| inner.cas(namespace, object_name, key, expected, new),
+        )
+    }
+
+    fn read(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        self.inner.read(namespace, object_name)
+    }
+
+    fn read_range(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        self.inner.read_range(namespace, object_name, range)
+    }
+
+    fn read_prefix(&self, namespace: &str, object_name: &str, prefix: &[u8]) -> StorageResult<ReadCursor> {
+        self.inner.read_prefix(namespace, object_name, prefix)
+    }
+
+    fn read_reverse(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        self.inner.read_reverse(namespace, object_name)
+    }
+
+    fn read_range_reverse(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        self.inner.read_range_reverse(namespace, object_name, range)
+    }
+
+    fn lookup(&self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<ReadCursor> {
+        self.inner.lookup(namespace, object_name, keys)
+    }
+
+    fn read_keys(&self, namespace: &str, object_name: &str) -> StorageResult<KeyCursor> {
+        self.inner.read_keys(namespace, object_name)
+    }
+
+    fn count(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<usize> {
+        self.inner.count(namespace, object_name, range)
+    }
+
+    fn delete(&mut self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<usize> {
+        self.apply(
+            Operation::Delete(namespace.to_owned(), object_name.to_owned(), keys.clone()),
+            |inner| inner.delete(namespace, object_name, keys),
+        )
+    }
+
+    fn apply_batch(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        self.apply(Operation::Batch(batch.clone()), |inner| inner.apply_batch(batch))
+    }
+
+    fn put_object_metadata(&mut self, namespace: &str, object_name: &str, metadata: Values) -> StorageResult<()> {
+        self.apply(
+            Operation::PutObjectMetadata(namespace.to_owned(), object_name.to_owned(), metadata.clone()),
+            |inner| inner.put_object_metadata(namespace, object_name, metadata),
+        )
+    }
+
+    fn get_object_metadata(&self, namespace: &str, object_name: &str) -> StorageResult<Option<Values>> {
+        self.inner.get_object_metadata(namespace, object_name)
+    }
+
+    fn object_size(&self, namespace: &str, object_name: &str) -> StorageResult<u64> {
+        self.inner.object_size(namespace, object_name)
+    }
+
+    fn size(&self) -> StorageResult<u64> {
+        self.inner.size()
+    }
+
+    fn flush(&mut self) -> StorageResult<()> {
+        self.checkpoint().map_err(StorageError::System)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::SledBackendStorage;
+    use std::{path::PathBuf, sync::Mutex};
+
+    #[derive(Default)]
+    struct StoppedClock(Mutex<Option<Instant>>);
+
+    impl StoppedClock {
+        fn advance(&self, duration: Duration) {
+            let mut now = self.0.lock().unwrap();
+            *now = Some(now.unwrap_or_else(Instant::now) + duration);
+        }
+    }
+
+    impl Clock for StoppedClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap().get_or_insert_with(Instant::now)
+        }
+    }
+
+    fn log_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("database_wal_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn read_all<B: BackendStorage>(storage: &B) -> StorageResult<Vec<Row>> {
+        storage
+            .read("namespace", "object_name")
+            .map(|rows| rows.map(Result::unwrap).collect())
+    }
+
+    #[test]
+    fn recorded_operations_are_read_back() {
+        let path = log_path("read_back");
+        {
+            let (mut log, operations) = WriteAheadLog::open(&path).expect("log is opened");
+            assert_eq!(operations, vec![]);
+            log.append(&Operation::CreateNamespace("namespace".to_owned()))
+                .expect("operation is recorded");
+            log.append(&Operation::Write(
+                "namespace".to_owned(),
+                "object_name".to_owned(),
+                vec![(vec![1], vec![1])],
+            ))
+            .expect("operation is recorded");
+        }
+
+        let (_log, operations) = WriteAheadLog::open(&path).expect("log is opened");
+
+        assert_eq!(
+            operations,
+            vec![
+                Operation::CreateNamespace("namespace".to_owned()),
+                Operation::Write(
+                    "namespace".to_owned(),
+                    "object_name".to_owned(),
+                    vec![(vec![1], vec![1])]
+                )
+            ]
+        );
+        std::fs::remove_file(path).expect("log is removed");
+    }
+
+    #[test]
+    fn torn_record_ends_log() {
+        let path = log_path("torn");
+        {
+            let (mut log, _operations) = WriteAheadLog::open(&path).expect("log is opened");
+            log.append(&Operation::CreateNamespace("namespace".to_owned()))
+                .expect("operation is recorded");
+        }
+        let complete = std::fs::metadata(&path).expect("log metadata").len();
+        {
+            let mut file = OpenOptions::new().append(true).open(&path).expect("log is opened");
+            file.write_all(&[0, 0, 0, 100, 1, 2, 3])
+                .expect("torn record is written");
+        }
+
+        let (mut log, operations) = WriteAheadLog::open(&path).expect("log is opened");
+        assert_eq!(operations, vec![Operation::CreateNamespace("namespace".to_owned())]);
+        assert_eq!(std::fs::metadata(&path).expect("log metadata").len(), complete);
+
+        log.append(&Operation::DropNamespace("namespace".to_owned()))
+            .expect("operation is recorded");
+        drop(log);
+        let (_log, operations) = WriteAheadLog::open(&path).expect("log is opened");
+        assert_eq!(
+            operations,
+            vec![
+                Operation::CreateNamespace("namespace".to_owned()),
+                Operation::DropNamespace("namespace".to_owned())
+            ]
+        );
+        std::fs::remove_file(path).expect("log is removed");
+    }
+
+    #[test]
+    fn operations_that_were_not_applied_are_replayed() {
+        let path = log_path("replay");
+        {
+            let (mut log, _operations) = WriteAheadLog::open(&path).expect("log is opened");
+            log.append(&Operation::CreateNamespace("namespace".to_owned()))
+                .expect("operation is recorded");
+            log.append(&Operation::CreateObject(
+                "namespace".to_owned(),
+                "object_name".to_owned(),
+            ))
+            .expect("operation is recorded");
+            log.append(&Operation::Write(
+                "namespace".to_owned(),
+                "object_name".to_owned(),
+                vec![(vec![1], vec![1]), (vec![2], vec![2])],
+            ))
+            .expect("operation is recorded");
+        }
+        // the crash happened after the namespace was created
+        let mut backend = SledBackendStorage::default();
+        backend.create_namespace("namespace").expect("namespace is created");
+
+        let storage = WalBackendStorage::open(backend, &path).expect("storage is opened");
+
+        assert_eq!(read_all(&storage), Ok(vec![(vec![1], vec![1]), (vec![2], vec![2])]));
+        std::fs::remove_file(path).expect("log is removed");
+    }
+
+    #[test]
+    fn failed_operations_are_not_recorded() {
+        let path = log_path("failed");
+        let mut storage = WalBackendStorage::open(SledBackendStorage::default(), &path).expect("storage is opened");
+        storage.create_namespace("namespace").expect("namespace is created");
+
+        assert!(storage.create_namespace("namespace").is_err());
+        assert!(storage.drop_object("namespace", "object_name").is_err());
+        drop(storage);
+
+        let (_log, operations) = WriteAheadLog::open(&path).expect("log is opened");
+        assert_eq!(operations, vec![Operation::CreateNamespace("namespace".to_owned())]);
+        std::fs::remove_file(path).expect("log is removed");
+    }
+
+    #[test]
+    fn checkpoint_truncates_log() {
+        let path = log_path("checkpoint");
+        let mut storage = WalBackendStorage::open(SledBackendStorage::default(), &path).expect("storage is opened");
+        storage.create_namespace("namespace").expect("namespace is created");
+        storage
+            .create_object("namespace", "object_name")
+            .expect("object is created");
+
+        storage.checkpoint().expect("checkpoint is done");
+        drop(storage);
+
+        let (_log, operations) = WriteAheadLog::open(&path).expect("log is opened");
+        assert_eq!(operations, vec![]);
+        std::fs::remove_file(path).expect("log is removed");
+    }
+
+    #[test]
+    fn sequence_numbers_continue_after_truncation() {
+        let path = log_path("lsn");
+        {
+            let (mut log, _operations) = WriteAheadLog::open(&path).expect("log is opened");
+            log.append(&Operation::CreateNamespace("namespace".to_owned()))
+                .expect("operation is recorded");
+            let end = log.end();
+            assert!(end > 0);
+
+            log.truncate().expect("log is truncated");
+            assert_eq!(log.start(), end);
+            assert_eq!(log.size(), 0);
+        }
+
+        let (log, operations) = WriteAheadLog::open(&path).expect("log is opened");
+        assert_eq!(operations, vec![]);
+        assert!(log.start() > 0);
+        std::fs::remove_file(path).expect("log is removed");
+    }
+
+    #[test]
+    fn checkpoint_when_log_is_over_max_size() {
+        let path = log_path("max_size");
+        let mut storage = WalBackendStorage::open(SledBackendStorage::default(), &path)
+            .expect("storage is opened")
+            .with_max_wal_size(64);
+        storage.create_namespace("namespace").expect("namespace is created");
+        storage
+            .create_object("namespace", "object_name")
+            .expect("object is created");
+        let redo_lsn = storage.redo_lsn();
+
+        storage
+            .write("namespace", "object_name", vec![(vec![1], vec![1; 64])])
+            .expect("values are written");
+
+        assert!(storage.redo_lsn() > redo_lsn);
+        assert_eq!(read_all(&storage), Ok(vec![(vec![1], vec![1; 64])]));
+        drop(storage);
+        let (_log, operations) = WriteAheadLog::open(&path).expect("log is opened");
+        assert_eq!(operations, vec![]);
+        std::fs::remove_file(path).expect("log is removed");
+    }
+
+    #[test]
+    fn checkpoint_when_timeout_has_passed() {
+        let path = log_path("timeout");
+        let clock = Arc::new(StoppedClock::default());
+        let mut storage = WalBackendStorage::open(SledBackendStorage::default(), &path)
+            .expect("storage is opened")
+            .with_clock(clock.clone())
+            .with_checkpoint_timeout(Duration::from_secs(60));
+        storage.create_namespace("namespace").expect("namespace is created");
+        let redo_lsn = storage.redo_lsn();
+
+        clock.advance(Duration::from_secs(59));
+        storage
+            .create_object("namespace", "object_name")
+            .expect("object is created");
+        assert_eq!(storage.redo_lsn(), redo_lsn);
+
+        clock.advance(Duration::from_secs(1));
+        storage
+            .write("namespace", "object_name", vec![(vec![1], vec![1])])
+            .expect("values are written");
+        assert!(storage.redo_lsn() > redo_lsn);
+        std::fs::remove_file(path).expect("log is removed");
+    }
+}
@@ -60,6 +60,8 @@ impl TestCase {
         }
     }
 
+    // connection is read by a single test, so the lock is never contended
+    #[allow(clippy::await_holding_lock)]
     pub async fn read_result(&self) -> BytesMut {
         use async_std::prelude::*;
 
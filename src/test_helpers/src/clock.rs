@@ -0,0 +1,69 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use kernel::clock::Clock;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Clock that moves only when it is advanced
+#[derive(Debug)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl Default for ManualClock {
+    fn default() -> ManualClock {
+        ManualClock {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::default()),
+        }
+    }
+}
+
+impl ManualClock {
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_stands_still() {
+        let clock = ManualClock::default();
+
+        assert_eq!(clock.now(), clock.now());
+    }
+
+    #[test]
+    fn time_moves_when_advanced() {
+        let clock = ManualClock::default();
+        let before = clock.now();
+
+        clock.advance(Duration::from_millis(5));
+
+        assert_eq!(clock.now() - before, Duration::from_millis(5));
+    }
+}
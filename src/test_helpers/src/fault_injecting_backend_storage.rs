@@ -0,0 +1,454 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use kernel::{SystemError, SystemResult};
+use std::cell::Cell;
+use storage::backend::{BackendStorage, Key, KeyCursor, KeyRange, ReadCursor, Row, StorageResult, Values, WriteBatch};
+
+#[derive(Debug, Clone)]
+enum Operation {
+    CreateNamespace(String),
+    DropNamespace(String),
+    CreateObject(String, String),
+    DropObject(String, String),
+    Write(String, String, Vec<Row>),
+    Delete(String, String, Vec<Key>),
+    Batch(WriteBatch),
+    PutObjectMetadata(String, String, Values),
+    Update(String, String, Vec<Row>),
+}
+
+/// Wraps a backend to fail scripted operations and to simulate crashes
+///
+/// Operations are counted from the moment a failure is scripted, reads
+/// included. Modifications are journaled and a simulated crash rebuilds the
+/// wrapped backend from the modifications that were flushed before it.
+#[derive(Default)]
+pub struct FaultInjectingBackendStorage<B: BackendStorage> {
+    inner: B,
+    operations: Cell<usize>,
+    fail_at: Cell<Option<usize>>,
+    flushed: Vec<Operation>,
+    unflushed: Vec<Operation>,
+}
+
+impl<B: BackendStorage> FaultInjectingBackendStorage<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            operations: Cell::new(0),
+            fail_at: Cell::new(None),
+            flushed: vec![],
+            unflushed: vec![],
+        }
+    }
+
+    /// the `nth` operation starting from the next one fails with a system error
+    pub fn fail_operation(&mut self, nth: usize) {
+        self.operations.set(0);
+        self.fail_at.set(Some(nth));
+    }
+
+    fn next_operation(&self) -> SystemResult<()> {
+        let operation = self.operations.get() + 1;
+        self.operations.set(operation);
+        if self.fail_at.get() == Some(operation) {
+            self.fail_at.set(None);
+            Err(SystemError::unrecoverable(format!(
+                "injected fault on operation {}",
+                operation
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn journal(&mut self, operation: Operation) {
+        self.unflushed.push(operation);
+    }
+}
+
+impl<B: BackendStorage + Default> FaultInjectingBackendStorage<B> {
+    /// loses every modification that was not flushed
+    pub fn crash(&mut self) {
+        self.unflushed.clear();
+        self.fail_at.set(None);
+        let mut inner = B::default();
+        for operation in self.flushed.iter().cloned() {
+            let replayed = match operation {
+                Operation::CreateNamespace(namespace) => inner.create_namespace(&namespace).map(|_| ()),
+                Operation::DropNamespace(namespace) => inner.drop_namespace(&namespace).map(|_| ()),
+                Operation::CreateObject(namespace, object_name) => {
+                    inner.create_object(&namespace, &object_name).map(|_| ())
+                }
+                Operation::DropObject(namespace, object_name) => {
+                    inner.drop_object(&namespace, &object_name).map(|_| ())
+                }
+                Operation::Write(namespace, object_name, rows) => {
+                    inner.write(&namespace, &object_name, rows).map(|_| ())
+                }
+                Operation::Delete(namespace, object_name, keys) => {
+                    inner.delete(&namespace, &object_name, keys).map(|_| ())
+                }
+                Operation::Batch(batch) => inner.apply_batch(batch).map(|_| ()),
+                Operation::PutObjectMetadata(namespace, object_name, metadata) => inner
+                    .put_object_metadata(&namespace, &object_name, metadata)
+                    .map(|_| ()),
+                Operation::Update(namespace, object_name, rows) => {
+                    inner.update(&namespace, &object_name, rows).map(|_| ())
+                }
+            };
+            replayed.expect("flushed operation is replayed");
+        }
+        self.inner = inner;
+    }
+}
+
+impl<B: BackendStorage> BackendStorage for FaultInjectingBackendStorage<B> {
+    fn create_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        self.next_operation()?;
+        self.inner.create_namespace(namespace)?;
+        self.journal(Operation::CreateNamespace(namespace.to_owned()));
+        Ok(())
+    }
+
+    fn drop_namespace(&mut self, namespace: &str) -> StorageResult<()> {
+        self.next_operation()?;
+        self.inner.drop_namespace(namespace)?;
+        self.journal(Operation::DropNamespace(namespace.to_owned()));
+        Ok(())
+    }
+
+    fn create_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        self.next_operation()?;
+        self.inner.create_object(namespace, object_name)?;
+        self.journal(Operation::CreateObject(namespace.to_owned(), object_name.to_owned()));
+        Ok(())
+    }
+
+    fn drop_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
+        self.next_operation()?;
+        self.inner.drop_object(namespace, object_name)?;
+        self.journal(Operation::DropObject(namespace.to_owned(), object_name.to_owned()));
+        Ok(())
+    }
+
+    fn namespaces(&self) -> StorageResult<Vec<String>> {
+        self.next_operation()?;
+        self.inner.namespaces()
+    }
+
+    fn object_names(&self, namespace: &str) -> StorageResult<Vec<String>> {
+        self.next_operation()?;
+        self.inner.object_names(namespace)
+    }
+
+    fn write(&mut self, namespace: &str, object_name: &str, values: Vec<Row>) -> StorageResult<usize> {
+        self.next_operation()?;
+        let written = self.inner.write(namespace, object_name, values.clone())?;
+        self.journal(Operation::Write(namespace.to_owned(), object_name.to_owned(), values));
+        Ok(written)
+    }
+
+    fn update(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize> {
+        self.next_operation()?;
+        let updated = self.inner.update(namespace, object_name, rows.clone())?;
+        self.journal(Operation::Update(namespace.to_owned(), object_name.to_owned(), rows));
+        Ok(updated)
+    }
+
+    fn cas(
+        &mut self,
+        namespace: &str,
+        object_name: &str,
+        key: Key,
+        expected: Option<Values>,
+        new: Option<Values>,
+    ) -> StorageResult<Result<(), Option<Values>>> {
+        self.next_operation()?;
+        let swapped = self
+            .inner
+            .cas(namespace, object_name, key.clone(), expected, new.clone())?;
+        // swap is replayed as the modification that it made
+        if swapped.is_ok() {
+            self.journal(match new {
+                Some(values) => Operation::Write(namespace.to_owned(), object_name.to_owned(), vec![(key, values)]),
+                None => Operation::Delete(namespace.to_owned(), object_name.to_owned(), vec![key]),
+            });
+        }
+        Ok(swapped)
+    }
+
+    fn read(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        self.next_operation()?;
+        self.inner.read(namespace, object_name)
+    }
+
+    fn read_range(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        self.next_operation()?;
+        self.inner.read_range(namespace, object_name, range)
+    }
+
+    fn read_prefix(&self, namespace: &str, object_name: &str, prefix: &[u8]) -> StorageResult<ReadCursor> {
+        self.next_operation()?;
+        self.inner.read_prefix(namespace, object_name, prefix)
+    }
+
+    fn read_reverse(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        self.next_operation()?;
+        self.inner.read_reverse(namespace, object_name)
+    }
+
+    fn read_range_reverse(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        self.next_operation()?;
+        self.inner.read_range_reverse(namespace, object_name, range)
+    }
+
+    fn lookup(&self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<ReadCursor> {
+        self.next_operation()?;
+        self.inner.lookup(namespace, object_name, keys)
+    }
+
+    fn read_keys(&self, namespace: &str, object_name: &str) -> StorageResult<KeyCursor> {
+        self.next_operation()?;
+        self.inner.read_keys(namespace, object_name)
+    }
+
+    fn count(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<usize> {
+        self.next_operation()?;
+        self.inner.count(namespace, object_name, range)
+    }
+
+    fn delete(&mut self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<usize> {
+        self.next_operation()?;
+        let deleted = self.inner.delete(namespace, object_name, keys.clone())?;
+        self.journal(Operation::Delete(namespace.to_owned(), object_name.to_owned(), keys));
+        Ok(deleted)
+    }
+
+    fn apply_batch(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        self.next_operation()?;
+        self.inner.apply_batch(batch.clone())?;
+        self.journal(Operation::Batch(batch));
+        Ok(())
+    }
+
+    fn put_object_metadata(&mut self, namespace: &str, object_name: &str, metadata: Values) -> StorageResult<()> {
+        self.next_operation()?;
+        self.inner
+            .put_object_metadata(namespace, object_name, metadata.clone())?;
+        self.journal(Operation::PutObjectMetadata(
+            namespace.to_owned(),
+            object_name.to_owned(),
+            metadata,
+        ));
+        Ok(())
+    }
+
+    fn get_object_metadata(&self, namespace: &str, object_name: &str) -> StorageResult<Option<Values>> {
+        self.next_operation()?;
+        self.inner.get_object_metadata(namespace, object_name)
+    }
+
+    fn object_size(&self, namespace: &str, object_name: &str) -> StorageResult<u64> {
+        self.next_operation()?;
+        self.inner.object_size(namespace, object_name)
+    }
+
+    fn size(&self) -> StorageResult<u64> {
+        self.next_operation()?;
+        self.inner.size()
+    }
+
+    fn flush(&mut self) -> StorageResult<()> {
+        self.next_operation()?;
+        self.inner.flush()?;
+        self.flushed.append(&mut self.unflushed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::in_memory_backend_storage::InMemoryStorage;
+    use sql_types::SqlType;
+    use storage::{
+        backend::{DefinitionError, StorageError},
+        frontend::FrontendStorage,
+    };
+
+    type FaultInjectingStorage = FaultInjectingBackendStorage<InMemoryStorage>;
+
+    #[rstest::fixture]
+    fn backend() -> FaultInjectingStorage {
+        let mut backend = FaultInjectingStorage::default();
+        backend.create_namespace("namespace").expect("namespace created");
+        backend
+            .create_object("namespace", "object_name")
+            .expect("object created");
+        backend
+    }
+
+    fn read_all(backend: &FaultInjectingStorage) -> StorageResult<Vec<Row>> {
+        backend
+            .read("namespace", "object_name")
+            .map(|cursor| cursor.map(|row| row.expect("no system errors")).collect())
+    }
+
+    #[cfg(test)]
+    mod faults {
+        use super::*;
+
+        #[rstest::rstest]
+        fn nth_operation_fails(mut backend: FaultInjectingStorage) {
+            backend.fail_operation(2);
+
+            assert_eq!(
+                backend.write("namespace", "object_name", vec![(vec![1], vec![1])]),
+                Ok(1)
+            );
+            assert_eq!(
+                backend.write("namespace", "object_name", vec![(vec![2], vec![2])]),
+                Err(StorageError::System(SystemError::unrecoverable(
+                    "injected fault on operation 2".to_owned()
+                )))
+            );
+            assert_eq!(
+                backend.write("namespace", "object_name", vec![(vec![3], vec![3])]),
+                Ok(1)
+            );
+            assert_eq!(read_all(&backend), Ok(vec![(vec![1], vec![1]), (vec![3], vec![3])]));
+        }
+
+        #[rstest::rstest]
+        fn reads_are_counted(mut backend: FaultInjectingStorage) {
+            backend.fail_operation(1);
+
+            assert_eq!(
+                backend.read("namespace", "object_name").map(|rows| rows.count()),
+                Err(StorageError::System(SystemError::unrecoverable(
+                    "injected fault on operation 1".to_owned()
+                )))
+            );
+        }
+
+        #[test]
+        fn table_is_not_left_without_columns() {
+            let mut backend = FaultInjectingStorage::default();
+            // opening frontend storage creates system namespace and four catalog objects,
+            // creating a schema takes four operations, then creating a table looks up its
+            // schema and name, takes an id and applies a batch with its objects and columns
+            backend.fail_operation(14);
+            let mut storage = FrontendStorage::new(backend).expect("no system errors");
+            storage
+                .create_schema("schema_name")
+                .expect("no system errors")
+                .expect("schema created");
+
+            assert!(storage
+                .create_table(
+                    "schema_name",
+                    "table_name",
+                    vec![("column_test".to_owned(), SqlType::SmallInt)]
+                )
+                .is_err());
+
+            assert_eq!(
+                storage
+                    .create_table(
+                        "schema_name",
+                        "table_name",
+                        vec![("column_test".to_owned(), SqlType::SmallInt)]
+                    )
+                    .expect("no system errors"),
+                Ok(())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod sequence {
+        use super::*;
+        use storage::SequenceDefinition;
+
+        #[test]
+        fn values_are_not_handed_out_when_reservation_is_not_persisted() {
+            let mut backend = FaultInjectingStorage::default();
+            // opening frontend storage takes five operations and creating a schema four, creating
+            // a sequence takes five, then reservation looks up the sequence, reads its state and
+            // persists the reserved block
+            backend.fail_operation(18);
+            let mut storage = FrontendStorage::new(backend).expect("no system errors");
+            storage
+                .create_schema("schema_name")
+                .expect("no system errors")
+                .expect("schema created");
+            storage
+                .create_sequence("schema_name", "sequence_name", SequenceDefinition::default())
+                .expect("no system errors")
+                .expect("sequence created");
+
+            assert!(storage.reserve_sequence_values("schema_name", "sequence_name").is_err());
+
+            let mut block = storage
+                .reserve_sequence_values("schema_name", "sequence_name")
+                .expect("no system errors")
+                .expect("values are reserved");
+            assert_eq!(block.next_value(), Some(1));
+        }
+    }
+
+    #[cfg(test)]
+    mod crash {
+        use super::*;
+
+        #[rstest::rstest]
+        fn unflushed_writes_are_lost(mut backend: FaultInjectingStorage) {
+            backend.flush().expect("modifications are flushed");
+            backend
+                .write("namespace", "object_name", vec![(vec![1], vec![1])])
+                .expect("values are written");
+
+            backend.crash();
+
+            assert_eq!(read_all(&backend), Ok(vec![]));
+        }
+
+        #[rstest::rstest]
+        fn flushed_writes_survive(mut backend: FaultInjectingStorage) {
+            backend
+                .write("namespace", "object_name", vec![(vec![1], vec![1])])
+                .expect("values are written");
+            backend.flush().expect("modifications are flushed");
+            backend
+                .delete("namespace", "object_name", vec![vec![1]])
+                .expect("values are deleted");
+
+            backend.crash();
+
+            assert_eq!(read_all(&backend), Ok(vec![(vec![1], vec![1])]));
+        }
+
+        #[rstest::rstest]
+        fn unflushed_objects_are_lost(mut backend: FaultInjectingStorage) {
+            backend.crash();
+
+            assert_eq!(
+                read_all(&backend),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2020 Alex Dukhno
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Deterministic source of arbitrary bytes to feed message decoders with
+pub struct ByteSource {
+    state: u64,
+}
+
+impl ByteSource {
+    pub fn new(seed: u64) -> ByteSource {
+        // xorshift gets stuck on zero state
+        ByteSource { state: seed | 1 }
+    }
+
+    /// random bytes of random length that is less than `max_len`
+    pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.next_below(max_len);
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+
+    /// copy of a valid message with a few bytes changed and possibly truncated
+    pub fn mutate(&mut self, message: &[u8]) -> Vec<u8> {
+        let mut mutated = message.to_vec();
+        if mutated.is_empty() {
+            return mutated;
+        }
+        for _ in 0..=self.next_below(4) {
+            let position = self.next_below(mutated.len());
+            mutated[position] = self.next() as u8;
+        }
+        if self.next().is_multiple_of(2) {
+            let len = self.next_below(mutated.len());
+            mutated.truncate(len);
+        }
+        mutated
+    }
+
+    fn next_below(&mut self, bound: usize) -> usize {
+        if bound == 0 {
+            0
+        } else {
+            (self.next() % bound as u64) as usize
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_bytes() {
+        assert_eq!(ByteSource::new(42).bytes(64), ByteSource::new(42).bytes(64));
+    }
+
+    #[test]
+    fn mutated_message_is_not_longer_than_original() {
+        let mut source = ByteSource::new(42);
+
+        for _ in 0..100 {
+            assert!(source.mutate(&[1, 2, 3, 4, 5]).len() <= 5);
+        }
+    }
+}
@@ -12,16 +12,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use kernel::{SystemError, SystemResult};
-use std::collections::HashMap;
+use kernel::SystemError;
+use std::{
+    collections::HashMap,
+    ops::{Bound, RangeBounds},
+};
 use storage::backend::{
-    BackendStorage, CreateObjectError, DropObjectError, Key, NamespaceAlreadyExists, NamespaceDoesNotExist,
-    OperationOnObjectError, ReadCursor, Result, Row, Values,
+    BackendStorage, BatchOperation, DefinitionError, Key, KeyCursor, KeyRange, ReadCursor, Result, Row, StorageResult,
+    Values, WriteBatch,
 };
 
 #[derive(Default, Debug)]
 struct StorageObject {
     records: Vec<(Key, Values)>,
+    metadata: Option<Values>,
+}
+
+impl StorageObject {
+    fn size(&self) -> u64 {
+        self.records
+            .iter()
+            .map(|(key, values)| (key.len() + values.len()) as u64)
+            .sum()
+    }
 }
 
 #[derive(Default, Debug)]
@@ -34,121 +47,288 @@ pub struct InMemoryStorage {
     namespaces: HashMap<String, Namespace>,
 }
 
-impl BackendStorage for InMemoryStorage {
-    type ErrorMapper = storage::backend::SledErrorMapper;
+impl InMemoryStorage {
+    fn object(&self, namespace: &str, object_name: &str) -> StorageResult<&StorageObject> {
+        match self.namespaces.get(namespace) {
+            Some(namespace) => match namespace.objects.get(object_name) {
+                Some(object) => Ok(object),
+                None => Err(DefinitionError::ObjectDoesNotExist.into()),
+            },
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
+        }
+    }
 
-    fn create_namespace(&mut self, namespace: &str) -> SystemResult<Result<(), NamespaceAlreadyExists>> {
+    fn object_mut(&mut self, namespace: &str, object_name: &str) -> StorageResult<&mut StorageObject> {
+        match self.namespaces.get_mut(namespace) {
+            Some(namespace) => match namespace.objects.get_mut(object_name) {
+                Some(object) => Ok(object),
+                None => Err(DefinitionError::ObjectDoesNotExist.into()),
+            },
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
+        }
+    }
+}
+
+impl BackendStorage for InMemoryStorage {
+    fn create_namespace(&mut self, namespace: &str) -> StorageResult<()> {
         if self.namespaces.contains_key(namespace) {
-            Ok(Err(NamespaceAlreadyExists))
+            Err(DefinitionError::NamespaceAlreadyExists.into())
         } else {
             self.namespaces.insert(namespace.to_owned(), Namespace::default());
-            Ok(Ok(()))
+            Ok(())
         }
     }
 
-    fn drop_namespace(&mut self, namespace: &str) -> SystemResult<Result<(), NamespaceDoesNotExist>> {
+    fn drop_namespace(&mut self, namespace: &str) -> StorageResult<()> {
         match self.namespaces.remove(namespace) {
-            Some(_namespace) => Ok(Ok(())),
-            None => Ok(Err(NamespaceDoesNotExist)),
+            Some(_namespace) => Ok(()),
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
         }
     }
 
-    fn create_object(&mut self, namespace: &str, object_name: &str) -> SystemResult<Result<(), CreateObjectError>> {
+    fn create_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
         match self.namespaces.get_mut(namespace) {
             Some(namespace) => {
                 if namespace.objects.contains_key(object_name) {
-                    Ok(Err(CreateObjectError::ObjectAlreadyExists))
+                    Err(DefinitionError::ObjectAlreadyExists.into())
                 } else {
                     namespace
                         .objects
                         .insert(object_name.to_owned(), StorageObject::default());
-                    Ok(Ok(()))
+                    Ok(())
                 }
             }
-            None => Ok(Err(CreateObjectError::NamespaceDoesNotExist)),
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
         }
     }
 
-    fn drop_object(&mut self, namespace: &str, object_name: &str) -> SystemResult<Result<(), DropObjectError>> {
+    fn drop_object(&mut self, namespace: &str, object_name: &str) -> StorageResult<()> {
         match self.namespaces.get_mut(namespace) {
             Some(namespace) => match namespace.objects.remove(object_name) {
-                Some(_) => Ok(Ok(())),
-                None => Ok(Err(DropObjectError::ObjectDoesNotExist)),
+                Some(_) => Ok(()),
+                None => Err(DefinitionError::ObjectDoesNotExist.into()),
             },
-            None => Ok(Err(DropObjectError::NamespaceDoesNotExist)),
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
         }
     }
 
-    fn write(
-        &mut self,
-        namespace: &str,
-        object_name: &str,
-        values: Vec<(Key, Values)>,
-    ) -> SystemResult<Result<usize, OperationOnObjectError>> {
-        match self.namespaces.get_mut(namespace) {
-            Some(namespace) => match namespace.objects.get_mut(object_name) {
-                Some(object) => {
-                    object.records = object
-                        .records
-                        .iter()
-                        .filter(|(key, _value)| values.iter().find(|(k, _v)| k == key).is_none())
-                        .cloned()
-                        .collect();
-                    let len = values.len();
-                    object.records.extend_from_slice(values.as_slice());
-                    Ok(Ok(len))
-                }
-                None => Ok(Err(OperationOnObjectError::ObjectDoesNotExist)),
-            },
-            None => Ok(Err(OperationOnObjectError::NamespaceDoesNotExist)),
-        }
+    fn namespaces(&self) -> StorageResult<Vec<String>> {
+        let mut namespaces = self.namespaces.keys().cloned().collect::<Vec<String>>();
+        namespaces.sort();
+        Ok(namespaces)
     }
 
-    fn read(&self, namespace: &str, object_name: &str) -> SystemResult<Result<ReadCursor, OperationOnObjectError>> {
+    fn object_names(&self, namespace: &str) -> StorageResult<Vec<String>> {
         match self.namespaces.get(namespace) {
-            Some(namespace) => match namespace.objects.get(object_name) {
-                Some(object) => Ok(Ok(Box::new(
-                    object
-                        .records
-                        .iter()
-                        .cloned()
-                        .map(Ok)
-                        .collect::<Vec<Result<Row, SystemError>>>()
-                        .into_iter(),
-                ))),
-                None => Ok(Err(OperationOnObjectError::ObjectDoesNotExist)),
-            },
-            None => Ok(Err(OperationOnObjectError::NamespaceDoesNotExist)),
+            Some(namespace) => {
+                let mut object_names = namespace.objects.keys().cloned().collect::<Vec<String>>();
+                object_names.sort();
+                Ok(object_names)
+            }
+            None => Err(DefinitionError::NamespaceDoesNotExist.into()),
         }
     }
 
-    fn delete(
+    fn write(&mut self, namespace: &str, object_name: &str, values: Vec<(Key, Values)>) -> StorageResult<usize> {
+        let object = self.object_mut(namespace, object_name)?;
+        object
+            .records
+            .retain(|(key, _value)| !values.iter().any(|(k, _v)| k == key));
+        let len = values.len();
+        object.records.extend_from_slice(values.as_slice());
+        Ok(len)
+    }
+
+    fn update(&mut self, namespace: &str, object_name: &str, rows: Vec<Row>) -> StorageResult<usize> {
+        let object = self.object_mut(namespace, object_name)?;
+        let mut updated = 0;
+        for (key, values) in rows {
+            if let Some(record) = object.records.iter_mut().find(|(k, _v)| *k == key) {
+                record.1 = values;
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    }
+
+    fn cas(
         &mut self,
         namespace: &str,
         object_name: &str,
-        keys: Vec<Key>,
-    ) -> SystemResult<Result<usize, OperationOnObjectError>> {
-        match self.namespaces.get_mut(namespace) {
-            Some(namespace) => match namespace.objects.get_mut(object_name) {
-                Some(object) => {
-                    object.records = object
+        key: Key,
+        expected: Option<Values>,
+        new: Option<Values>,
+    ) -> StorageResult<Result<(), Option<Values>>> {
+        let object = self.object_mut(namespace, object_name)?;
+        let position = object.records.iter().position(|(k, _v)| *k == key);
+        let current = position.map(|position| object.records[position].1.clone());
+        if current != expected {
+            return Ok(Err(current));
+        }
+        match (position, new) {
+            (Some(position), Some(values)) => object.records[position].1 = values,
+            (Some(position), None) => {
+                object.records.remove(position);
+            }
+            (None, Some(values)) => object.records.push((key, values)),
+            (None, None) => {}
+        }
+        Ok(Ok(()))
+    }
+
+    fn read(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        let object = self.object(namespace, object_name)?;
+        Ok(Box::new(
+            object
+                .records
+                .iter()
+                .cloned()
+                .map(Ok)
+                .collect::<Vec<Result<Row, SystemError>>>()
+                .into_iter(),
+        ))
+    }
+
+    fn read_range(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        let object = self.object(namespace, object_name)?;
+        let mut records = object
+            .records
+            .iter()
+            .filter(|(key, _values)| range.contains(key))
+            .cloned()
+            .collect::<Vec<Row>>();
+        records.sort_by(|(left, _), (right, _)| left.cmp(right));
+        Ok(Box::new(records.into_iter().map(Ok)))
+    }
+
+    fn read_prefix(&self, namespace: &str, object_name: &str, prefix: &[u8]) -> StorageResult<ReadCursor> {
+        let object = self.object(namespace, object_name)?;
+        let mut records = object
+            .records
+            .iter()
+            .filter(|(key, _values)| key.starts_with(prefix))
+            .cloned()
+            .collect::<Vec<Row>>();
+        records.sort_by(|(left, _), (right, _)| left.cmp(right));
+        Ok(Box::new(records.into_iter().map(Ok)))
+    }
+
+    fn read_reverse(&self, namespace: &str, object_name: &str) -> StorageResult<ReadCursor> {
+        self.read_range_reverse(namespace, object_name, (Bound::Unbounded, Bound::Unbounded))
+    }
+
+    fn read_range_reverse(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<ReadCursor> {
+        let object = self.object(namespace, object_name)?;
+        let mut records = object
+            .records
+            .iter()
+            .filter(|(key, _values)| range.contains(key))
+            .cloned()
+            .collect::<Vec<Row>>();
+        records.sort_by(|(left, _), (right, _)| right.cmp(left));
+        Ok(Box::new(records.into_iter().map(Ok)))
+    }
+
+    fn lookup(&self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<ReadCursor> {
+        let object = self.object(namespace, object_name)?;
+        Ok(Box::new(
+            keys.into_iter()
+                .filter_map(|key| object.records.iter().find(|(k, _values)| *k == key).cloned())
+                .map(Ok)
+                .collect::<Vec<Result<Row, SystemError>>>()
+                .into_iter(),
+        ))
+    }
+
+    fn read_keys(&self, namespace: &str, object_name: &str) -> StorageResult<KeyCursor> {
+        let object = self.object(namespace, object_name)?;
+        Ok(Box::new(
+            object
+                .records
+                .iter()
+                .map(|(key, _values)| Ok(key.clone()))
+                .collect::<Vec<Result<Key, SystemError>>>()
+                .into_iter(),
+        ))
+    }
+
+    fn count(&self, namespace: &str, object_name: &str, range: KeyRange) -> StorageResult<usize> {
+        let object = self.object(namespace, object_name)?;
+        Ok(object
+            .records
+            .iter()
+            .filter(|(key, _values)| range.contains(key))
+            .count())
+    }
+
+    fn delete(&mut self, namespace: &str, object_name: &str, keys: Vec<Key>) -> StorageResult<usize> {
+        let object = self.object_mut(namespace, object_name)?;
+        object.records.retain(|(key, _values)| !keys.contains(key));
+        Ok(keys.len())
+    }
+
+    fn apply_batch(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        batch.validate(
+            |namespace| self.namespaces.contains_key(namespace),
+            |namespace, object_name| self.object(namespace, object_name).is_ok(),
+        )?;
+        for operation in batch.operations() {
+            match operation {
+                BatchOperation::Write(namespace, object_name, (key, values)) => {
+                    let object = self.object_mut(namespace, object_name)?;
+                    match object.records.iter_mut().find(|(k, _v)| k == key) {
+                        Some(record) => record.1 = values.clone(),
+                        None => object.records.push((key.clone(), values.clone())),
+                    }
+                }
+                BatchOperation::Delete(namespace, object_name, key) => {
+                    self.object_mut(namespace, object_name)?
                         .records
-                        .iter()
-                        .filter(|(key, _values)| !keys.contains(key))
-                        .cloned()
-                        .collect();
-                    Ok(Ok(keys.len()))
+                        .retain(|(k, _v)| k != key);
                 }
-                None => Ok(Err(OperationOnObjectError::ObjectDoesNotExist)),
-            },
-            None => Ok(Err(OperationOnObjectError::NamespaceDoesNotExist)),
+                BatchOperation::CreateNamespace(namespace) => self.create_namespace(namespace)?,
+                BatchOperation::DropNamespace(namespace) => self.drop_namespace(namespace)?,
+                BatchOperation::CreateObject(namespace, object_name) => self.create_object(namespace, object_name)?,
+                BatchOperation::DropObject(namespace, object_name) => self.drop_object(namespace, object_name)?,
+                BatchOperation::PutObjectMetadata(namespace, object_name, metadata) => {
+                    self.put_object_metadata(namespace, object_name, metadata.clone())?
+                }
+            }
         }
+        Ok(())
+    }
+
+    fn put_object_metadata(&mut self, namespace: &str, object_name: &str, metadata: Values) -> StorageResult<()> {
+        self.object_mut(namespace, object_name)?.metadata = Some(metadata);
+        Ok(())
+    }
+
+    fn get_object_metadata(&self, namespace: &str, object_name: &str) -> StorageResult<Option<Values>> {
+        Ok(self.object(namespace, object_name)?.metadata.clone())
+    }
+
+    fn object_size(&self, namespace: &str, object_name: &str) -> StorageResult<u64> {
+        Ok(self.object(namespace, object_name)?.size())
+    }
+
+    fn size(&self) -> StorageResult<u64> {
+        Ok(self
+            .namespaces
+            .values()
+            .flat_map(|namespace| namespace.objects.values())
+            .map(StorageObject::size)
+            .sum())
+    }
+
+    fn flush(&mut self) -> StorageResult<()> {
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use storage::backend::StorageError;
 
     #[cfg(test)]
     mod namespace {
@@ -158,28 +338,19 @@ mod tests {
         fn create_namespaces_with_different_names() {
             let mut storage = InMemoryStorage::default();
 
-            assert_eq!(
-                storage.create_namespace("namespace_1").expect("namespace created"),
-                Ok(())
-            );
-            assert_eq!(
-                storage.create_namespace("namespace_2").expect("namespace created"),
-                Ok(())
-            );
+            assert_eq!(storage.create_namespace("namespace_1"), Ok(()));
+            assert_eq!(storage.create_namespace("namespace_2"), Ok(()));
         }
 
         #[test]
         fn create_namespace_with_existing_name() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
 
             assert_eq!(
-                storage.create_namespace("namespace").expect("no system errors"),
-                Err(NamespaceAlreadyExists)
+                storage.create_namespace("namespace"),
+                Err(StorageError::Definition(DefinitionError::NamespaceAlreadyExists))
             );
         }
 
@@ -187,16 +358,10 @@ mod tests {
         fn drop_namespace() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
 
-            assert_eq!(storage.drop_namespace("namespace").expect("namespace dropped"), Ok(()));
-            assert_eq!(
-                storage.create_namespace("namespace").expect("namespace created"),
-                Ok(())
-            );
+            assert_eq!(storage.drop_namespace("namespace"), Ok(()));
+            assert_eq!(storage.create_namespace("namespace"), Ok(()));
         }
 
         #[test]
@@ -204,8 +369,8 @@ mod tests {
             let mut storage = InMemoryStorage::default();
 
             assert_eq!(
-                storage.drop_namespace("does_not_exists").expect("no system errors"),
-                Err(NamespaceDoesNotExist)
+                storage.drop_namespace("does_not_exists"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
 
@@ -213,36 +378,18 @@ mod tests {
         fn dropping_namespace_drops_objects_in_it() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
             storage
                 .create_object("namespace", "object_name_1")
-                .expect("no system errors")
                 .expect("object created");
             storage
                 .create_object("namespace", "object_name_2")
-                .expect("no system errors")
                 .expect("object created");
 
-            assert_eq!(storage.drop_namespace("namespace").expect("no system errors"), Ok(()));
-            assert_eq!(
-                storage.create_namespace("namespace").expect("namespace created"),
-                Ok(())
-            );
-            assert_eq!(
-                storage
-                    .create_object("namespace", "object_name_1")
-                    .expect("no system errors"),
-                Ok(())
-            );
-            assert_eq!(
-                storage
-                    .create_object("namespace", "object_name_2")
-                    .expect("no system errors"),
-                Ok(())
-            );
+            assert_eq!(storage.drop_namespace("namespace"), Ok(()));
+            assert_eq!(storage.create_namespace("namespace"), Ok(()));
+            assert_eq!(storage.create_object("namespace", "object_name_1"), Ok(()));
+            assert_eq!(storage.create_object("namespace", "object_name_2"), Ok(()));
         }
     }
 
@@ -254,23 +401,10 @@ mod tests {
         fn create_objects_with_different_names() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
 
-            assert_eq!(
-                storage
-                    .create_object("namespace", "object_name_1")
-                    .expect("no system errors"),
-                Ok(())
-            );
-            assert_eq!(
-                storage
-                    .create_object("namespace", "object_name_2")
-                    .expect("no system errors"),
-                Ok(())
-            );
+            assert_eq!(storage.create_object("namespace", "object_name_1"), Ok(()));
+            assert_eq!(storage.create_object("namespace", "object_name_2"), Ok(()));
         }
 
         #[test]
@@ -280,10 +414,8 @@ mod tests {
             create_object(&mut storage, "namespace", "object_name");
 
             assert_eq!(
-                storage
-                    .create_object("namespace", "object_name")
-                    .expect("no system errors"),
-                Err(CreateObjectError::ObjectAlreadyExists)
+                storage.create_object("namespace", "object_name"),
+                Err(StorageError::Definition(DefinitionError::ObjectAlreadyExists))
             );
         }
 
@@ -291,26 +423,10 @@ mod tests {
         fn create_object_with_the_same_name_in_different_namespaces() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace_1")
-                .expect("no system errors")
-                .expect("namespace created");
-            storage
-                .create_namespace("namespace_2")
-                .expect("no system errors")
-                .expect("namespace created");
-            assert_eq!(
-                storage
-                    .create_object("namespace_1", "object_name")
-                    .expect("no system errors"),
-                Ok(())
-            );
-            assert_eq!(
-                storage
-                    .create_object("namespace_2", "object_name")
-                    .expect("no system errors"),
-                Ok(())
-            );
+            storage.create_namespace("namespace_1").expect("namespace created");
+            storage.create_namespace("namespace_2").expect("namespace created");
+            assert_eq!(storage.create_object("namespace_1", "object_name"), Ok(()));
+            assert_eq!(storage.create_object("namespace_2", "object_name"), Ok(()));
         }
 
         #[test]
@@ -318,10 +434,8 @@ mod tests {
             let mut storage = InMemoryStorage::default();
 
             assert_eq!(
-                storage
-                    .create_object("not_existent", "object_name")
-                    .expect("no system errors"),
-                Err(CreateObjectError::NamespaceDoesNotExist)
+                storage.create_object("not_existent", "object_name"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
     }
@@ -335,33 +449,18 @@ mod tests {
             let mut storage = InMemoryStorage::default();
 
             create_object(&mut storage, "namespace", "object_name");
-            assert_eq!(
-                storage
-                    .drop_object("namespace", "object_name")
-                    .expect("no system errors"),
-                Ok(())
-            );
-            assert_eq!(
-                storage
-                    .create_object("namespace", "object_name")
-                    .expect("no system errors"),
-                Ok(())
-            );
+            assert_eq!(storage.drop_object("namespace", "object_name"), Ok(()));
+            assert_eq!(storage.create_object("namespace", "object_name"), Ok(()));
         }
 
         #[test]
         fn drop_not_created_object() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
             assert_eq!(
-                storage
-                    .drop_object("namespace", "not_existed_object")
-                    .expect("no system errors"),
-                Err(DropObjectError::ObjectDoesNotExist)
+                storage.drop_object("namespace", "not_existed_object"),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
             );
         }
 
@@ -370,8 +469,8 @@ mod tests {
             let mut storage = InMemoryStorage::default();
 
             assert_eq!(
-                storage.drop_object("not_existent", "object").expect("no system errors"),
-                Err(DropObjectError::NamespaceDoesNotExist)
+                storage.drop_object("not_existent", "object"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
     }
@@ -386,16 +485,13 @@ mod tests {
 
             create_object(&mut storage, "namespace", "object_name");
             assert_eq!(
-                storage
-                    .write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])],))
-                    .expect("no system errors"),
+                storage.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])],)),
                 Ok(1)
             );
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![(1u8, vec!["123"])]).collect())
             );
@@ -408,17 +504,14 @@ mod tests {
             create_object(&mut storage, "namespace", "object_name");
             storage
                 .write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]))
-                .expect("no system errors")
                 .expect("values are written");
             storage
                 .write("namespace", "object_name", as_rows(vec![(2u8, vec!["456"])]))
-                .expect("no system errors")
                 .expect("values are written");
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![(1u8, vec!["123"]), (2u8, vec!["456"])]).collect())
             );
@@ -428,15 +521,10 @@ mod tests {
         fn insert_into_non_existent_object() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
             assert_eq!(
-                storage
-                    .write("namespace", "not_existed", as_rows(vec![(1u8, vec!["123"])],))
-                    .expect("no system errors"),
-                Err(OperationOnObjectError::ObjectDoesNotExist)
+                storage.write("namespace", "not_existed", as_rows(vec![(1u8, vec!["123"])],)),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
             );
         }
 
@@ -445,10 +533,8 @@ mod tests {
             let mut storage = InMemoryStorage::default();
 
             assert_eq!(
-                storage
-                    .write("not_existed", "object", as_rows(vec![(1u8, vec!["123"])],))
-                    .expect("no system errors"),
-                Err(OperationOnObjectError::NamespaceDoesNotExist)
+                storage.write("not_existed", "object", as_rows(vec![(1u8, vec!["123"])],)),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
 
@@ -456,16 +542,12 @@ mod tests {
         fn select_from_object_that_does_not_exist() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
             assert_eq!(
                 storage
                     .read("namespace", "not_existed")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
-                Err(OperationOnObjectError::ObjectDoesNotExist)
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
             );
         }
 
@@ -476,9 +558,8 @@ mod tests {
             assert_eq!(
                 storage
                     .read("not_existed", "object")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
-                Err(OperationOnObjectError::NamespaceDoesNotExist)
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
 
@@ -493,20 +574,13 @@ mod tests {
                     "object_name",
                     as_rows(vec![(1u8, vec!["123"]), (2u8, vec!["456"]), (3u8, vec!["789"])]),
                 )
-                .expect("no system errors")
                 .expect("write occurred");
 
-            assert_eq!(
-                storage
-                    .delete("namespace", "object_name", as_keys(vec![2u8]))
-                    .expect("no system errors"),
-                Ok(1)
-            );
+            assert_eq!(storage.delete("namespace", "object_name", as_keys(vec![2u8])), Ok(1));
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![(1u8, vec!["123"]), (3u8, vec!["789"])]).collect())
             );
@@ -516,16 +590,11 @@ mod tests {
         fn delete_from_not_existed_object() {
             let mut storage = InMemoryStorage::default();
 
-            storage
-                .create_namespace("namespace")
-                .expect("no system errors")
-                .expect("namespace created");
+            storage.create_namespace("namespace").expect("namespace created");
 
             assert_eq!(
-                storage
-                    .delete("namespace", "not_existent", vec![])
-                    .expect("no system errors"),
-                Err(OperationOnObjectError::ObjectDoesNotExist)
+                storage.delete("namespace", "not_existent", vec![]),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
             );
         }
 
@@ -534,10 +603,8 @@ mod tests {
             let mut storage = InMemoryStorage::default();
 
             assert_eq!(
-                storage
-                    .delete("not existent", "object", vec![])
-                    .expect("no system errors"),
-                Err(OperationOnObjectError::NamespaceDoesNotExist)
+                storage.delete("not existent", "object", vec![]),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
             );
         }
 
@@ -548,13 +615,11 @@ mod tests {
             create_object(&mut storage, "namespace", "object_name");
             storage
                 .write("namespace", "object_name", as_rows(vec![(1u8, vec!["1", "2", "3"])]))
-                .expect("no system errors")
                 .expect("write occurred");
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![(1u8, vec!["1", "2", "3"])]).collect())
             );
@@ -575,13 +640,11 @@ mod tests {
                         (3u8, vec!["7", "8", "9"]),
                     ]),
                 )
-                .expect("no system errors")
                 .expect("write occurred");
 
             assert_eq!(
                 storage
                     .read("namespace", "object_name")
-                    .expect("no system errors")
                     .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
                 Ok(as_read_cursor(vec![
                     (1u8, vec!["1", "2", "3"]),
@@ -591,17 +654,433 @@ mod tests {
                 .collect()),
             );
         }
+
+        #[test]
+        fn read_rows_in_reverse_order() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![
+                        (2u8, vec!["4", "5", "6"]),
+                        (1u8, vec!["1", "2", "3"]),
+                        (3u8, vec!["7", "8", "9"]),
+                    ]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_reverse("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![
+                    (3u8, vec!["7", "8", "9"]),
+                    (2u8, vec!["4", "5", "6"]),
+                    (1u8, vec!["1", "2", "3"])
+                ])
+                .collect()),
+            );
+        }
+
+        #[test]
+        fn lookup_rows_by_keys() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["1"]), (2u8, vec!["2"]), (3u8, vec!["3"])]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .lookup("namespace", "object_name", vec![vec![3u8], vec![4u8], vec![1u8]])
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(3u8, vec!["3"]), (1u8, vec!["1"])]).collect()),
+            );
+        }
+
+        #[test]
+        fn read_range_of_rows() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![
+                        (4u8, vec!["4"]),
+                        (1u8, vec!["1"]),
+                        (3u8, vec!["3"]),
+                        (2u8, vec!["2"]),
+                    ]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_range(
+                        "namespace",
+                        "object_name",
+                        (Bound::Included(vec![2u8]), Bound::Unbounded)
+                    )
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(2u8, vec!["2"]), (3u8, vec!["3"]), (4u8, vec!["4"])]).collect()),
+            );
+        }
+
+        #[test]
+        fn read_rows_with_key_prefix() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    vec![
+                        (vec![1u8, 2u8], vec![3u8]),
+                        (vec![2u8, 1u8], vec![2u8]),
+                        (vec![1u8, 1u8], vec![1u8]),
+                    ],
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_prefix("namespace", "object_name", &[1u8])
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![Ok((vec![1u8, 1u8], vec![1u8])), Ok((vec![1u8, 2u8], vec![3u8]))]),
+            );
+        }
+
+        #[test]
+        fn read_range_of_rows_in_reverse_order() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![
+                        (1u8, vec!["1"]),
+                        (2u8, vec!["2"]),
+                        (3u8, vec!["3"]),
+                        (4u8, vec!["4"]),
+                    ]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_range_reverse(
+                        "namespace",
+                        "object_name",
+                        (Bound::Excluded(vec![1u8]), Bound::Included(vec![3u8]))
+                    )
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(3u8, vec!["3"]), (2u8, vec!["2"])]).collect()),
+            );
+        }
+
+        #[test]
+        fn read_reverse_from_object_that_does_not_exist() {
+            let mut storage = InMemoryStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage
+                    .read_reverse("namespace", "not_existed")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn read_keys_of_object() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["1", "2", "3"]), (2u8, vec!["4", "5", "6"])]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage
+                    .read_keys("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Key, SystemError>>>()),
+                Ok(vec![Ok(vec![1u8]), Ok(vec![2u8])])
+            );
+        }
+
+        #[test]
+        fn count_rows_within_range() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["1"]), (2u8, vec!["2"]), (3u8, vec!["3"])]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(
+                storage.count("namespace", "object_name", (Bound::Unbounded, Bound::Unbounded)),
+                Ok(3)
+            );
+            assert_eq!(
+                storage.count(
+                    "namespace",
+                    "object_name",
+                    (Bound::Included(vec![2u8]), Bound::Unbounded)
+                ),
+                Ok(2)
+            );
+        }
+
+        #[test]
+        fn count_rows_of_object_in_not_existent_namespace() {
+            let storage = InMemoryStorage::default();
+
+            assert_eq!(
+                storage.count("not_existed", "object_name", (Bound::Unbounded, Bound::Unbounded)),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn object_without_metadata() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+
+            assert_eq!(storage.get_object_metadata("namespace", "object_name"), Ok(None));
+        }
+
+        #[test]
+        fn put_and_get_object_metadata() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .put_object_metadata("namespace", "object_name", vec![1, 2, 3])
+                .expect("metadata is put");
+
+            assert_eq!(
+                storage.get_object_metadata("namespace", "object_name"),
+                Ok(Some(vec![1, 2, 3]))
+            );
+        }
+
+        #[test]
+        fn metadata_is_dropped_with_object() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .put_object_metadata("namespace", "object_name", vec![1, 2, 3])
+                .expect("metadata is put");
+            storage.drop_object("namespace", "object_name").expect("object dropped");
+            storage
+                .create_object("namespace", "object_name")
+                .expect("object created");
+
+            assert_eq!(storage.get_object_metadata("namespace", "object_name"), Ok(None));
+        }
+
+        #[test]
+        fn metadata_is_dropped_with_namespace() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .put_object_metadata("namespace", "object_name", vec![1, 2, 3])
+                .expect("metadata is put");
+            storage.drop_namespace("namespace").expect("namespace dropped");
+            create_object(&mut storage, "namespace", "object_name");
+
+            assert_eq!(storage.get_object_metadata("namespace", "object_name"), Ok(None));
+        }
+
+        #[test]
+        fn put_metadata_of_object_that_does_not_exist() {
+            let mut storage = InMemoryStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage.put_object_metadata("namespace", "not_existed", vec![1, 2, 3]),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn size_of_object() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+            storage
+                .write(
+                    "namespace",
+                    "object_name",
+                    as_rows(vec![(1u8, vec!["1", "2", "3"]), (2u8, vec!["4", "5", "6"])]),
+                )
+                .expect("write occurred");
+
+            assert_eq!(storage.object_size("namespace", "object_name"), Ok(12));
+        }
+
+        #[test]
+        fn size_of_object_that_does_not_exist() {
+            let mut storage = InMemoryStorage::default();
+
+            storage.create_namespace("namespace").expect("namespace created");
+            assert_eq!(
+                storage.object_size("namespace", "not_existed"),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn size_of_object_in_not_existent_namespace() {
+            let storage = InMemoryStorage::default();
+
+            assert_eq!(
+                storage.object_size("not_existed", "object"),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+        }
+
+        #[test]
+        fn batch_of_writes_and_deletes_in_different_objects() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name_1");
+            storage
+                .create_object("namespace", "object_name_2")
+                .expect("object created");
+            storage
+                .write("namespace", "object_name_1", as_rows(vec![(1u8, vec!["123"])]))
+                .expect("values are written");
+
+            let mut batch = WriteBatch::default();
+            batch.delete("namespace", "object_name_1", as_keys(vec![1u8]));
+            batch.write("namespace", "object_name_2", as_rows(vec![(2u8, vec!["456"])]));
+            assert_eq!(storage.apply_batch(batch), Ok(()));
+
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name_1")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![])
+            );
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name_2")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(2u8, vec!["456"])]).collect())
+            );
+        }
+
+        #[test]
+        fn batch_of_writes_in_different_namespaces() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace_1", "object_name");
+            create_object(&mut storage, "namespace_2", "object_name");
+
+            let mut batch = WriteBatch::default();
+            batch.write("namespace_1", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            batch.write("namespace_2", "object_name", as_rows(vec![(2u8, vec!["456"])]));
+            assert_eq!(storage.apply_batch(batch), Ok(()));
+
+            assert_eq!(
+                storage
+                    .read("namespace_1", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(1u8, vec!["123"])]).collect())
+            );
+            assert_eq!(
+                storage
+                    .read("namespace_2", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(as_read_cursor(vec![(2u8, vec!["456"])]).collect())
+            );
+        }
+
+        #[test]
+        fn batch_with_not_existed_namespace_is_not_applied() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+
+            let mut batch = WriteBatch::default();
+            batch.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            batch.write("not_existed", "object_name", as_rows(vec![(2u8, vec!["456"])]));
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![])
+            );
+        }
+
+        #[test]
+        fn batch_with_not_existed_object_is_not_applied() {
+            let mut storage = InMemoryStorage::default();
+
+            create_object(&mut storage, "namespace", "object_name");
+
+            let mut batch = WriteBatch::default();
+            batch.write("namespace", "object_name", as_rows(vec![(1u8, vec!["123"])]));
+            batch.write("namespace", "not_existed", as_rows(vec![(2u8, vec!["456"])]));
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::ObjectDoesNotExist))
+            );
+
+            assert_eq!(
+                storage
+                    .read("namespace", "object_name")
+                    .map(|iter| iter.collect::<Vec<Result<Row, SystemError>>>()),
+                Ok(vec![])
+            );
+        }
+
+        #[test]
+        fn batch_in_not_existent_namespace() {
+            let mut storage = InMemoryStorage::default();
+
+            let mut batch = WriteBatch::default();
+            batch.write("not_existed", "object", as_rows(vec![(1u8, vec!["123"])]));
+            assert_eq!(
+                storage.apply_batch(batch),
+                Err(StorageError::Definition(DefinitionError::NamespaceDoesNotExist))
+            );
+        }
     }
 
     fn create_object(storage: &mut InMemoryStorage, namespace: &str, object_name: &str) {
-        storage
-            .create_namespace(namespace)
-            .expect("no system errors")
-            .expect("namespace created");
-        storage
-            .create_object(namespace, object_name)
-            .expect("no system errors")
-            .expect("object created");
+        storage.create_namespace(namespace).expect("namespace created");
+        storage.create_object(namespace, object_name).expect("object created");
     }
 
     fn as_rows(items: Vec<(u8, Vec<&'static str>)>) -> Vec<Row> {
@@ -17,5 +17,8 @@ extern crate bytes;
 extern crate tempfile;
 
 pub mod async_io;
+pub mod clock;
+pub mod fault_injecting_backend_storage;
+pub mod fuzz;
 pub mod in_memory_backend_storage;
 pub mod pg_frontend;
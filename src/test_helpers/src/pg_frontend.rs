@@ -16,6 +16,12 @@ use bytes::{BufMut, BytesMut};
 
 const QUERY: u8 = b'Q';
 const TERMINATE: u8 = b'X';
+const PARSE: u8 = b'P';
+const BIND: u8 = b'B';
+const DESCRIBE: u8 = b'D';
+const EXECUTE: u8 = b'E';
+const CLOSE: u8 = b'C';
+const SYNC: u8 = b'S';
 
 pub enum Message {
     Query(&'static str),
@@ -24,6 +30,17 @@ pub enum Message {
     SslDisabled,
     SslRequired,
     Password(&'static str),
+    // statement name, sql and parameter type ids
+    Parse(&'static str, &'static str, Vec<i32>),
+    // portal name, statement name and parameters in text format
+    Bind(&'static str, &'static str, Vec<Option<&'static str>>),
+    // `S` for a statement or `P` for a portal and its name
+    Describe(u8, &'static str),
+    // portal name and maximum number of rows
+    Execute(&'static str, i32),
+    // `S` for a statement or `P` for a portal and its name
+    Close(u8, &'static str),
+    Sync,
 }
 
 impl Message {
@@ -73,10 +90,72 @@ impl Message {
                 with_len.extend_from_slice(&buff);
                 with_len.to_vec()
             }
+            Message::Parse(name, sql, param_types) => {
+                let mut buff = BytesMut::with_capacity(256);
+                buff.extend_from_slice(name.as_bytes());
+                buff.put_u8(0);
+                buff.extend_from_slice(sql.as_bytes());
+                buff.put_u8(0);
+                buff.put_i16(param_types.len() as i16);
+                for param_type in param_types {
+                    buff.put_i32(*param_type);
+                }
+                with_tag(PARSE, &buff)
+            }
+            Message::Bind(portal, statement, params) => {
+                let mut buff = BytesMut::with_capacity(256);
+                buff.extend_from_slice(portal.as_bytes());
+                buff.put_u8(0);
+                buff.extend_from_slice(statement.as_bytes());
+                buff.put_u8(0);
+                buff.put_i16(0);
+                buff.put_i16(params.len() as i16);
+                for param in params {
+                    match param {
+                        Some(value) => {
+                            buff.put_i32(value.len() as i32);
+                            buff.extend_from_slice(value.as_bytes());
+                        }
+                        None => buff.put_i32(-1),
+                    }
+                }
+                buff.put_i16(0);
+                with_tag(BIND, &buff)
+            }
+            Message::Describe(kind, name) => {
+                let mut buff = BytesMut::with_capacity(256);
+                buff.put_u8(*kind);
+                buff.extend_from_slice(name.as_bytes());
+                buff.put_u8(0);
+                with_tag(DESCRIBE, &buff)
+            }
+            Message::Execute(portal, max_rows) => {
+                let mut buff = BytesMut::with_capacity(256);
+                buff.extend_from_slice(portal.as_bytes());
+                buff.put_u8(0);
+                buff.put_i32(*max_rows);
+                with_tag(EXECUTE, &buff)
+            }
+            Message::Close(kind, name) => {
+                let mut buff = BytesMut::with_capacity(256);
+                buff.put_u8(*kind);
+                buff.extend_from_slice(name.as_bytes());
+                buff.put_u8(0);
+                with_tag(CLOSE, &buff)
+            }
+            Message::Sync => vec![SYNC, 0, 0, 0, 4],
         }
     }
 }
 
+fn with_tag(tag: u8, buff: &[u8]) -> Vec<u8> {
+    let mut with_len = BytesMut::with_capacity(256);
+    with_len.put_u8(tag);
+    with_len.put_u32(buff.len() as u32 + 4);
+    with_len.extend_from_slice(buff);
+    with_len.to_vec()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,6 +194,14 @@ mod tests {
         assert_eq!(Message::SslRequired.as_vec(), vec![0, 0, 0, 8, 4, 210, 22, 47])
     }
 
+    #[test]
+    fn execute() {
+        assert_eq!(
+            Message::Execute("p", 10).as_vec(),
+            vec![EXECUTE, 0, 0, 0, 10, 112, 0, 0, 0, 0, 10]
+        )
+    }
+
     #[test]
     fn password() {
         assert_eq!(Message::Password("123").as_vec(), vec![112, 0, 0, 0, 8, 49, 50, 51, 0])